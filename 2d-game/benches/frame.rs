@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::hint::black_box;
+use std::path::Path;
+use bumpalo::Bump;
+use criterion::{criterion_group, criterion_main, Criterion};
+use learnopengl_shared::util;
+use learnopengl_2d_game::affine::Affine2;
+use learnopengl_2d_game::controller::{PaddleController, PredictiveController, Skill, View};
+use learnopengl_2d_game::credits::{Credits, Section};
+use learnopengl_2d_game::drops::DropTable;
+use learnopengl_2d_game::game_object::GameObject;
+use learnopengl_2d_game::particle_generator::ParticleGenerator;
+use learnopengl_2d_game::shader::Shader;
+use learnopengl_2d_game::simulation::Simulation;
+use learnopengl_2d_game::sprite_batch::build_instances;
+use learnopengl_2d_game::sprite_renderer::SpriteRenderer;
+use learnopengl_2d_game::text_renderer::utf8::FTHelper;
+use learnopengl_2d_game::texture::Texture2D;
+
+// Micro benchmarks of code which runs every frame; run them with
+//
+//     cargo bench -p learnopengl-2d-game
+//
+// Each benchmark builds its input without a window (GL uploads are left out), so only the
+// CPU side of the renderer is measured.
+
+// a screen full of bricks (8x6 each, over a handful of textures)
+fn bricks() -> Vec<GameObject> {
+    let mut textures = [(); 8].map(|_| Texture2D::empty());
+    for (i, texture) in textures.iter_mut().enumerate() {
+        texture.id = i as u32 + 1;
+    }
+    (0..10_000)
+        .map(|i| GameObject::new_ex0(glm::vec2((i % 100) as f32 * 8.0, (i / 100) as f32 * 6.0), glm::vec2(8.0, 6.0), textures[i % 8].clone()))
+        .collect()
+}
+
+fn sprite_batch(c: &mut Criterion) {
+    let sprites = bricks();
+    let refs: Vec<&GameObject> = sprites.iter().collect();
+    c.bench_function("sprite_batch/build 10k", |b| b.iter(|| build_instances(black_box(&refs))));
+}
+
+fn transform(c: &mut Criterion) {
+    let sprites: Vec<(glm::TVec2<f32>, f32)> = (0..10_000).map(|i| (glm::vec2((i % 100) as f32 * 8.0, (i / 100) as f32 * 6.0), (i % 4) as f32 * 15.0)).collect();
+    let size = glm::vec2(8.0, 6.0);
+    let mut group = c.benchmark_group("transform");
+    group.bench_function("model_matrix 10k", |b| b.iter(|| sprites.iter()
+        .map(|(position, rotate)| SpriteRenderer::model_matrix(*position, size, *rotate))
+        .collect::<Vec<_>>()));
+    group.bench_function("affine 10k", |b| b.iter(|| sprites.iter()
+        .map(|(position, rotate)| Affine2::sprite(*position, size, *rotate))
+        .collect::<Vec<_>>()));
+    group.finish();
+}
+
+fn particles(c: &mut Criterion) {
+    let mut particles = ParticleGenerator::new(Shader::new(), Texture2D::empty(), 10_000);
+    let object = GameObject::new_ex1(glm::vec2(400.0, 300.0), glm::vec2(25.0, 25.0), Texture2D::empty(), util::glm::scale_vec3(1.0), glm::vec2(100.0, -350.0));
+    // fill the pool first, so every step updates 10k living particles
+    particles.life = 1000.0;
+    particles.emit(&object, 10_000, glm::vec2(0.0, 0.0));
+    c.bench_function("particles/update 10k", |b| b.iter(|| particles.update_ex(1.0 / 120.0, &object, 2, glm::vec2(6.0, 6.0))));
+}
+
+fn text_layout(c: &mut Criterion) {
+    let line = "Breakout 打砖块 — 得分 1234, lives ×3. 暂停时按 Esc 返回菜单。";
+    let text = line.repeat(50);
+    let mut helper = FTHelper::with_metrics(line);
+    let mut arena = Bump::new();
+    c.bench_function("text/layout utf8 (2.5k glyphs)", |b| b.iter(|| {
+        arena.reset();
+        helper.layout(&arena, text.as_str(), 0.0, 0.0, 1.0).len()
+    }));
+}
+
+fn credits(c: &mut Criterion) {
+    let line = "Breakout 打砖块 — a rather long line of credits to lay out".to_string();
+    let sections: Vec<Section> = (0..1000).map(|i| Section { title: format!("Section {}", i), lines: vec![line.clone(); 20] }).collect();
+    let mut credits = Credits::new(&sections);
+    credits.scroll = credits.height / 2.0;
+    c.bench_function("credits/visible (21k lines)", |b| b.iter(|| credits.visible(600.0).count()));
+}
+
+// ten seconds of level one with the predictive AI steering
+fn controller(c: &mut Criterion) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/levels/one.lvl");
+    let mut group = c.benchmark_group("controller");
+    group.sample_size(10);
+    group.bench_function("play level one (10s)", |b| b.iter(|| {
+        let mut simulation = Simulation::new(path.to_string_lossy().as_ref(), 1, DropTable::default(), 7, 800, 600).unwrap();
+        let mut controller = PredictiveController::new(Skill::Normal, 3);
+        let dt = 1.0 / 120.0;
+        for _ in 0..10 * 120 {
+            let view = View::of(&simulation.player, &simulation.ball, simulation.width as f32, simulation.level.bounds());
+            let input = controller.control(&view, dt);
+            simulation.step(dt, input);
+        }
+        simulation.steps
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, sprite_batch, transform, particles, text_layout, credits, controller);
+criterion_main!(benches);
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use crate::sprite_renderer::SpriteTransform;
+
+// The sprite batch used to build a 4x4 model matrix per sprite, chaining translations, a
+// rotation and a scale through nalgebra. Sprites only ever move in 2D, so the batch uses the
+// closed form of that chain instead: the two rows of a 2x3 affine matrix, computed with a
+// handful of multiply-adds on plain arrays (which the compiler is free to vectorize).
+
+/// A 2D affine transform, as the first two rows of a 3x3 matrix: x' = rows[0] . (x, y, 1), y' = rows[1] . (x, y, 1).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Affine2 {
+    pub rows: [[f32; 3]; 2]
+}
+
+impl Affine2 {
+    pub const IDENTITY: Affine2 = Affine2 { rows: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] };
+
+    // the transform of a unit quad drawn as a sprite, the same as SpriteRenderer::model_matrix
+    pub fn sprite(position: glm::TVec2<f32>, size: glm::TVec2<f32>, rotate: f32) -> Self {
+        if rotate == 0.0 {
+            // most sprites (all bricks) aren't rotated, which leaves a scale and a translation
+            return Self { rows: [[size.x, 0.0, position.x], [0.0, size.y, position.y]] };
+        }
+        Self::sprite_ex(position, size, rotate, &SpriteTransform::default())
+    }
+
+    // the same as SpriteRenderer::model_matrix_ex: scaled, sheared and rotated around the
+    // pivot, then moved to the position
+    pub fn sprite_ex(position: glm::TVec2<f32>, size: glm::TVec2<f32>, rotate: f32, transform: &SpriteTransform) -> Self {
+        let (sin, cos) = rotate.to_radians().sin_cos();
+        let (shear_x, shear_y) = (transform.shear.x, transform.shear.y);
+        // rotation times shear
+        let m = [[cos - sin * shear_y, cos * shear_x - sin], [sin + cos * shear_y, sin * shear_x + cos]];
+        let pivot = [transform.pivot.x * size.x, transform.pivot.y * size.y];
+        let translation = [
+            position.x + pivot[0] - (m[0][0] * pivot[0] + m[0][1] * pivot[1]),
+            position.y + pivot[1] - (m[1][0] * pivot[0] + m[1][1] * pivot[1])
+        ];
+        Self {
+            rows: [
+                [m[0][0] * size.x, m[0][1] * size.y, translation[0]],
+                [m[1][0] * size.x, m[1][1] * size.y, translation[1]]
+            ]
+        }
+    }
+
+    pub fn apply(&self, point: glm::TVec2<f32>) -> glm::TVec2<f32> {
+        let [x, y] = self.rows;
+        glm::vec2(x[0] * point.x + x[1] * point.y + x[2], y[0] * point.x + y[1] * point.y + y[2])
+    }
+
+    pub fn to_mat4(&self) -> glm::TMat4<f32> {
+        let [x, y] = self.rows;
+        glm::mat4(
+            x[0], x[1], 0.0, x[2],
+            y[0], y[1], 0.0, y[2],
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite_renderer::SpriteRenderer;
+
+    #[test]
+    fn affine_test_matches_model_matrix() {
+        let (position, size) = (glm::vec2(10.0, 20.0), glm::vec2(40.0, 20.0));
+        let transforms = [
+            SpriteTransform::default(),
+            SpriteTransform { pivot: glm::vec2(0.0, 1.0), shear: glm::vec2(0.5, -0.25), ..SpriteTransform::default() }
+        ];
+        for rotate in [0.0, 30.0, 90.0, -135.0] {
+            let expected = SpriteRenderer::model_matrix(position, size, rotate);
+            assert!((Affine2::sprite(position, size, rotate).to_mat4() - expected).abs().max() < 1e-4);
+            for transform in transforms.iter() {
+                let expected = SpriteRenderer::model_matrix_ex(position, size, rotate, transform);
+                assert!((Affine2::sprite_ex(position, size, rotate, transform).to_mat4() - expected).abs().max() < 1e-4);
+            }
+        }
+        let corner = Affine2::sprite(position, size, 0.0).apply(glm::vec2(1.0, 1.0));
+        assert_eq!(corner, glm::vec2(50.0, 40.0));
+        assert_eq!(Affine2::IDENTITY.to_mat4(), glm::Mat4::identity());
+    }
+}
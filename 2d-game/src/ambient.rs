@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use learnopengl_shared::util;
+use crate::game_object::GameObject;
+use crate::particle_generator::ParticleGenerator;
+use crate::shader::Shader;
+use crate::texture::Texture2D;
+
+// particles spawned per second are limited to this
+pub const MAX_RATE: f32 = 100.0;
+// pixels particles spawn beyond the edge they come in from
+const SPAWN_MARGIN: f32 = 10.0;
+// steps the screen is filled with before the level is shown
+const PREWARM_STEP: f32 = 0.25;
+
+fn default_rate() -> f32 {
+    20.0
+}
+
+/// What drifts through a level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AmbientKind {
+    // falls in from the top
+    Snow,
+    // shows up anywhere and drifts slowly
+    Dust,
+    // rises from the bottom and burns out on the way up
+    Embers
+}
+
+impl AmbientKind {
+    // range of the speed of the particles, in pixels per second
+    fn speed(self) -> (f32, f32) {
+        match self {
+            AmbientKind::Snow => (35.0, 70.0),
+            AmbientKind::Dust => (4.0, 14.0),
+            AmbientKind::Embers => (40.0, 80.0)
+        }
+    }
+
+    // seconds a particle lives on a screen of the given height
+    fn life(self, height: f32) -> f32 {
+        match self {
+            // long enough for the slowest flakes to reach the bottom
+            AmbientKind::Snow => (height + 2.0 * SPAWN_MARGIN) / self.speed().0,
+            AmbientKind::Dust => 8.0,
+            AmbientKind::Embers => height * 0.6 / self.speed().0
+        }
+    }
+
+    fn tint(self) -> glm::TVec3<f32> {
+        match self {
+            AmbientKind::Snow => glm::vec3(0.9, 0.95, 1.0),
+            AmbientKind::Dust => glm::vec3(0.6, 0.55, 0.45),
+            AmbientKind::Embers => glm::vec3(1.0, 0.45, 0.1)
+        }
+    }
+
+    // position and velocity of a new particle
+    fn spawn<R: Rng + ?Sized>(self, rng: &mut R, width: f32, height: f32) -> (glm::TVec2<f32>, glm::TVec2<f32>) {
+        let (min, max) = self.speed();
+        let speed = rng.random_range(min..max);
+        let x = rng.random_range(0.0..width);
+        match self {
+            AmbientKind::Snow => (glm::vec2(x, -SPAWN_MARGIN), glm::vec2(rng.random_range(-0.3..0.3) * speed, speed)),
+            AmbientKind::Dust => {
+                let angle = rng.random_range(0.0..std::f32::consts::TAU);
+                (glm::vec2(x, rng.random_range(0.0..height)), glm::vec2(angle.cos(), angle.sin()) * speed)
+            }
+            AmbientKind::Embers => (glm::vec2(x, height + SPAWN_MARGIN), glm::vec2(rng.random_range(-0.2..0.2) * speed, -speed))
+        }
+    }
+}
+
+/// The ambient particles of a level (the `ambient` table of a structured level).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Ambient {
+    pub kind: AmbientKind,
+    // particles spawned per second
+    #[serde(default = "default_rate")]
+    pub rate: f32,
+    // color the particles are tinted with instead of the one of their kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[f32; 3]>
+}
+
+impl Ambient {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.rate > 0.0 && self.rate <= MAX_RATE) {
+            return Err(format!("rate {} is not above 0 and at most {}", self.rate, MAX_RATE));
+        }
+        if self.color.is_some_and(|color| color.iter().any(|c| !(0.0..=1.0).contains(c))) {
+            return Err("color channels have to be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Long-lived particles spawned all over the screen for the whole level, drawn behind the bricks.
+pub struct AmbientParticles {
+    pub config: Ambient,
+    particles: ParticleGenerator,
+    width: f32,
+    height: f32,
+    // share of a particle left over from the previous steps
+    carry: f32
+}
+
+impl AmbientParticles {
+    pub fn new(config: Ambient, shader: Shader, texture: Texture2D, width: u32, height: u32) -> Self {
+        let life = config.kind.life(height as f32);
+        let mut particles = ParticleGenerator::new(shader, texture, (config.rate * life).ceil() as u32 + 1);
+        particles.life = life;
+        particles.spread = 0.0;
+        particles.velocity_factor = 1.0;
+        particles.fade_rate = 0.0;
+        particles.tint = config.color.map(|it| glm::vec3(it[0], it[1], it[2])).unwrap_or(config.kind.tint());
+        let mut result = Self { config, particles, width: width as f32, height: height as f32, carry: 0.0 };
+        // the level starts out with the screen full of particles
+        for _ in 0..(life / PREWARM_STEP).ceil() as u32 {
+            result.update(PREWARM_STEP);
+        }
+        result
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.carry += self.config.rate * dt;
+        let mut rng = rand::rng();
+        while self.carry >= 1.0 {
+            self.carry -= 1.0;
+            let (position, velocity) = self.config.kind.spawn(&mut rng, self.width, self.height);
+            // the generator moves particles against the velocity of their emitter
+            let emitter = GameObject::new_ex1(position, util::glm::empty_vec2(), Texture2D::empty(), util::glm::scale_vec3(1.0), -velocity);
+            self.particles.emit(&emitter, 1, util::glm::empty_vec2());
+        }
+        self.particles.advance(dt);
+    }
+
+    pub fn draw(&self) {
+        self.particles.draw();
+    }
+
+    // number of particles alive
+    pub fn count(&self) -> usize {
+        self.particles.alive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ambient(kind: AmbientKind) -> Ambient {
+        Ambient { kind, rate: 10.0, color: None }
+    }
+
+    #[test]
+    fn ambient_test_validate() {
+        assert!(ambient(AmbientKind::Snow).validate().is_ok());
+        assert!(Ambient { rate: 0.0, ..ambient(AmbientKind::Snow) }.validate().is_err());
+        assert!(Ambient { rate: MAX_RATE * 2.0, ..ambient(AmbientKind::Snow) }.validate().is_err());
+        assert!(Ambient { color: Some([1.0, 2.0, 0.0]), ..ambient(AmbientKind::Dust) }.validate().is_err());
+        let parsed: Ambient = toml::from_str("kind = \"embers\"").unwrap();
+        assert_eq!(parsed, Ambient { kind: AmbientKind::Embers, rate: default_rate(), color: None });
+    }
+
+    #[test]
+    fn ambient_test_spawn() {
+        let mut rng = rand::rng();
+        for _ in 0..100 {
+            let (position, velocity) = AmbientKind::Snow.spawn(&mut rng, 800.0, 600.0);
+            assert!(position.y < 0.0 && velocity.y > 0.0 && (0.0..800.0).contains(&position.x));
+            let (position, velocity) = AmbientKind::Embers.spawn(&mut rng, 800.0, 600.0);
+            assert!(position.y > 600.0 && velocity.y < 0.0);
+        }
+        // the screen is full from the start, and stays about as full
+        let mut particles = AmbientParticles::new(ambient(AmbientKind::Snow), Shader::new(), Texture2D::empty(), 800, 600);
+        let full = particles.count();
+        assert!(full as f32 >= 10.0 * (AmbientKind::Snow.life(600.0) - PREWARM_STEP));
+        for _ in 0..600 {
+            particles.update(1.0 / 120.0);
+        }
+        assert!(particles.count().abs_diff(full) <= 10);
+    }
+}
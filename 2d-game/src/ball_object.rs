@@ -18,20 +18,25 @@ extern crate nalgebra_glm as glm;
 
 use learnopengl_shared::util;
 use crate::game_object::GameObject;
+use crate::inspect::{Inspect, Value};
 use crate::sprite_renderer::SpriteRenderer;
 use crate::texture::Texture2D;
+use crate::walls::{WallEvent, Walls};
 
 // BallObject holds the state of the Ball object inheriting
 // relevant state data from GameObject. Contains some extra
 // functionality specific to Breakout's ball object that
 // were too specific for within GameObject alone.
+#[derive(Clone)]
 pub struct BallObject {
     pub game_obj: GameObject,
     // ball state
     pub radius: f32,
     pub stuck: bool,
     pub sticky: bool,
-    pub pass_through: bool
+    pub pass_through: bool,
+    // seconds left until a caught ball is thrown (0 unless caught)
+    pub catch_time: f32
 }
 
 impl BallObject {
@@ -42,7 +47,8 @@ impl BallObject {
             radius: 12.5,
             stuck: true,
             sticky: false,
-            pass_through: false
+            pass_through: false,
+            catch_time: 0.0
         }
     }
 
@@ -62,30 +68,22 @@ impl BallObject {
             radius,
             stuck: true,
             sticky: false,
-            pass_through: false
+            pass_through: false,
+            catch_time: 0.0
         }
     }
 
-    // moves the ball, keeping it constrained within the window bounds (except bottom edge); returns new position
-    pub fn move_ball(&mut self, dt: f32, window_width: u32) -> glm::TVec2<f32> {
+    // moves the ball, keeping it constrained by the walls of the playfield (the bottom edge is always open);
+    // returns what happened at the edges
+    pub fn move_ball(&mut self, dt: f32, window_width: u32, walls: &Walls) -> Option<WallEvent> {
         // if not stuck to player board
-        if !self.stuck {
-            // move the ball
-            self.game_obj.position += self.game_obj.velocity * dt;
-            // then check if outside window bounds and if so, reverse velocity and restore at correct position
-            if self.game_obj.position.x <= 0.0 {
-                self.game_obj.velocity.x = -self.game_obj.velocity.x;
-                self.game_obj.position.x = 0.0;
-            } else if self.game_obj.position.x + self.game_obj.size.x >= window_width as f32 {
-                self.game_obj.velocity.x = -self.game_obj.velocity.x;
-                self.game_obj.position.x = window_width as f32 - self.game_obj.size.x;
-            }
-            if self.game_obj.position.y <= 0.0 {
-                self.game_obj.velocity.y = -self.game_obj.velocity.y;
-                self.game_obj.position.y = 0.0;
-            }
+        if self.stuck {
+            return None;
         }
-        self.game_obj.position
+        // move the ball
+        self.game_obj.position += self.game_obj.velocity * dt;
+        // then check if outside window bounds and if so, bounce, wrap or let it go
+        walls.constrain(&mut self.game_obj, window_width as f32)
     }
 
     // resets the ball to original state with given position and velocity
@@ -99,9 +97,45 @@ impl BallObject {
         self.stuck = true;
         self.sticky = false;
         self.pass_through = false;
+        self.catch_time = 0.0;
+        self.game_obj.status.clear();
     }
 
     pub fn draw(&self, renderer: &SpriteRenderer) {
         self.game_obj.draw(renderer);
     }
-}
\ No newline at end of file
+}
+// the ball additionally exposes its own state; everything else is the GameObject's
+impl Inspect for BallObject {
+    fn property_names(&self) -> Vec<&'static str> {
+        let mut names = self.game_obj.property_names();
+        names.extend(["radius", "stuck", "sticky", "pass_through", "catch_time"]);
+        names
+    }
+
+    fn property(&self, name: &str) -> Option<Value> {
+        match name {
+            "radius" => Some(Value::Float(self.radius)),
+            "stuck" => Some(Value::Bool(self.stuck)),
+            "sticky" => Some(Value::Bool(self.sticky)),
+            "pass_through" => Some(Value::Bool(self.pass_through)),
+            "catch_time" => Some(Value::Float(self.catch_time)),
+            _ => self.game_obj.property(name)
+        }
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        match (name, value) {
+            ("radius", Value::Float(v)) => {
+                // keep the sprite matching the collision circle
+                self.radius = v;
+                self.game_obj.size = glm::vec2(v * 2.0, v * 2.0);
+            }
+            ("stuck", Value::Bool(v)) => self.stuck = v,
+            ("sticky", Value::Bool(v)) => self.sticky = v,
+            ("pass_through", Value::Bool(v)) => self.pass_through = v,
+            ("catch_time", Value::Float(v)) => self.catch_time = v,
+            _ => self.game_obj.set_property(name, value)
+        }
+    }
+}
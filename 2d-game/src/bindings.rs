@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use glfw::Key;
+use serde::{Deserialize, Serialize};
+
+/// Gameplay actions which can be bound to a key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Launch,
+    Pause,
+    Dash,
+    TiltLeft,
+    TiltRight
+}
+
+// all actions, in the order they are listed in the options screen
+pub const ACTIONS: [Action; 7] = [
+    Action::MoveLeft, Action::MoveRight, Action::Launch, Action::Pause, Action::Dash, Action::TiltLeft, Action::TiltRight
+];
+
+// keys which keep their fixed meaning and can't be bound
+pub const RESERVED_KEYS: [Key; 3] = [Key::Escape, Key::F3, Key::F5];
+
+// keys which can be written to (and read from) the bindings file
+const KEY_NAMES: [(Key, &str); 56] = [
+    (Key::A, "A"), (Key::B, "B"), (Key::C, "C"), (Key::D, "D"), (Key::E, "E"), (Key::F, "F"),
+    (Key::G, "G"), (Key::H, "H"), (Key::I, "I"), (Key::J, "J"), (Key::K, "K"), (Key::L, "L"),
+    (Key::M, "M"), (Key::N, "N"), (Key::O, "O"), (Key::P, "P"), (Key::Q, "Q"), (Key::R, "R"),
+    (Key::S, "S"), (Key::T, "T"), (Key::U, "U"), (Key::V, "V"), (Key::W, "W"), (Key::X, "X"),
+    (Key::Y, "Y"), (Key::Z, "Z"),
+    (Key::Num0, "0"), (Key::Num1, "1"), (Key::Num2, "2"), (Key::Num3, "3"), (Key::Num4, "4"),
+    (Key::Num5, "5"), (Key::Num6, "6"), (Key::Num7, "7"), (Key::Num8, "8"), (Key::Num9, "9"),
+    (Key::Space, "Space"), (Key::Enter, "Enter"), (Key::Tab, "Tab"), (Key::Backspace, "Backspace"),
+    (Key::Left, "Left"), (Key::Right, "Right"), (Key::Up, "Up"), (Key::Down, "Down"),
+    (Key::LeftShift, "LeftShift"), (Key::RightShift, "RightShift"),
+    (Key::LeftControl, "LeftControl"), (Key::RightControl, "RightControl"),
+    (Key::LeftAlt, "LeftAlt"), (Key::RightAlt, "RightAlt"),
+    (Key::Comma, "Comma"), (Key::Period, "Period"), (Key::Slash, "Slash"), (Key::Semicolon, "Semicolon"),
+    (Key::Minus, "Minus"), (Key::Equal, "Equal")
+];
+
+impl Action {
+    // identifier used in the bindings file and for the translated action names
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Launch => "launch",
+            Action::Pause => "pause",
+            Action::Dash => "dash",
+            Action::TiltLeft => "tilt_left",
+            Action::TiltRight => "tilt_right"
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        ACTIONS.iter().copied().find(|action| action.id() == id)
+    }
+}
+
+pub fn key_name(key: Key) -> &'static str {
+    KEY_NAMES.iter().find(|(k, _)| *k == key).map(|(_, name)| *name).unwrap_or("?")
+}
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES.iter().find(|(_, n)| n.eq_ignore_ascii_case(name)).map(|(key, _)| *key)
+}
+
+/// Why a key couldn't be bound to an action.
+#[derive(Debug, PartialEq)]
+pub enum BindError {
+    // the key has a fixed meaning
+    Reserved(Key),
+    // the key isn't supported by the bindings file
+    Unsupported(Key),
+    // the key is already bound to another action
+    Conflict(Action)
+}
+
+impl Display for BindError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindError::Reserved(key) => write!(f, "{:?} is reserved", key),
+            BindError::Unsupported(key) => write!(f, "{:?} can't be bound", key),
+            BindError::Conflict(action) => write!(f, "key is already bound to {}", action.id())
+        }
+    }
+}
+
+// layout of the bindings file
+#[derive(Serialize, Deserialize)]
+struct BindingsFile {
+    // action id -> key name
+    bindings: BTreeMap<String, String>
+}
+
+/// Maps gameplay actions to keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+    keys: BTreeMap<Action, Key>
+}
+
+impl Bindings {
+    pub fn key(&self, action: Action) -> Key {
+        self.keys[&action]
+    }
+
+    // the action the key is bound to, if any
+    pub fn action_for(&self, key: Key) -> Option<Action> {
+        self.keys.iter().find(|(_, k)| **k == key).map(|(action, _)| *action)
+    }
+
+    // binds the key to the action, unless it's reserved or already used by another action
+    pub fn bind(&mut self, action: Action, key: Key) -> Result<(), BindError> {
+        if RESERVED_KEYS.contains(&key) {
+            return Err(BindError::Reserved(key));
+        }
+        if key_name(key) == "?" {
+            return Err(BindError::Unsupported(key));
+        }
+        match self.action_for(key) {
+            Some(other) if other != action => Err(BindError::Conflict(other)),
+            _ => {
+                self.keys.insert(action, key);
+                Ok(())
+            }
+        }
+    }
+
+    // parses a bindings file; unknown or conflicting entries keep their default key. The
+    // entries are taken as a whole (so keys can be swapped), the actions the file leaves out
+    // get their default key, or the first free one if the file gave that to another action
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let file: BindingsFile = toml::from_str(source).map_err(|e| e.to_string())?;
+        let mut bindings = Self { keys: BTreeMap::new() };
+        for (id, name) in file.bindings.iter() {
+            let (Some(action), Some(key)) = (Action::from_id(id), key_from_name(name)) else {
+                log::warn!("BINDINGS: Ignoring unknown binding {} = \"{}\"", id, name);
+                continue;
+            };
+            if let Err(e) = bindings.bind(action, key) {
+                log::warn!("BINDINGS: Ignoring binding {} = \"{}\": {}", id, name, e);
+            }
+        }
+        let defaults = Self::default();
+        for action in ACTIONS {
+            if bindings.keys.contains_key(&action) {
+                continue;
+            }
+            let free = std::iter::once(defaults.key(action))
+                .chain(KEY_NAMES.iter().map(|(key, _)| *key))
+                .find(|key| bindings.action_for(*key).is_none())
+                .ok_or_else(|| format!("no key is left for {}", action.id()))?;
+            if free != defaults.key(action) {
+                log::warn!("BINDINGS: {} is bound to {} instead of its default key, which is in use", action.id(), key_name(free));
+            }
+            bindings.keys.insert(action, free);
+        }
+        Ok(bindings)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let file = BindingsFile {
+            bindings: self.keys.iter()
+                .map(|(action, key)| (action.id().to_string(), key_name(*key).to_string()))
+                .collect()
+        };
+        toml::to_string(&file).unwrap()
+    }
+
+    // loads the bindings from a file, falling back to the defaults if it doesn't exist or is invalid
+    pub fn load(path: &str) -> Self {
+        let Ok(source) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        Self::parse(source.as_str()).unwrap_or_else(|e| {
+            log::error!("BINDINGS: Failed to parse {}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keys: BTreeMap::from([
+                (Action::MoveLeft, Key::A),
+                (Action::MoveRight, Key::D),
+                (Action::Launch, Key::Space),
+                (Action::Pause, Key::P),
+                (Action::Dash, Key::LeftShift),
+                (Action::TiltLeft, Key::Q),
+                (Action::TiltRight, Key::E)
+            ])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_test_bind() {
+        let mut bindings = Bindings::default();
+        assert_eq!(bindings.bind(Action::MoveLeft, Key::Left), Ok(()));
+        assert_eq!(bindings.key(Action::MoveLeft), Key::Left);
+        // rebinding an action to its own key is fine
+        assert_eq!(bindings.bind(Action::MoveLeft, Key::Left), Ok(()));
+        assert_eq!(bindings.bind(Action::MoveRight, Key::Left), Err(BindError::Conflict(Action::MoveLeft)));
+        assert_eq!(bindings.key(Action::MoveRight), Key::D);
+        assert_eq!(bindings.bind(Action::Pause, Key::Escape), Err(BindError::Reserved(Key::Escape)));
+        assert_eq!(bindings.bind(Action::Pause, Key::F12), Err(BindError::Unsupported(Key::F12)));
+    }
+
+    #[test]
+    fn bindings_test_round_trip() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Action::Launch, Key::Up).unwrap();
+        bindings.bind(Action::Pause, Key::Num9).unwrap();
+        let parsed = Bindings::parse(bindings.to_toml().as_str()).unwrap();
+        assert_eq!(parsed, bindings);
+    }
+
+    #[test]
+    fn bindings_test_swap_round_trip() {
+        let mut bindings = Bindings::default();
+        // swaps the keys of moving left and right in the options screen's way
+        bindings.bind(Action::MoveLeft, Key::Left).unwrap();
+        bindings.bind(Action::MoveRight, Key::A).unwrap();
+        bindings.bind(Action::MoveLeft, Key::D).unwrap();
+        let parsed = Bindings::parse(bindings.to_toml().as_str()).unwrap();
+        assert_eq!(parsed, bindings);
+        assert_eq!(parsed.key(Action::MoveLeft), Key::D);
+        // a binding moved onto the default key of an action the file leaves out
+        let bindings = Bindings::parse("[bindings]\nlaunch = \"P\"\n").unwrap();
+        assert_eq!(bindings.key(Action::Launch), Key::P);
+        assert_ne!(bindings.key(Action::Pause), Key::P);
+        assert!(!RESERVED_KEYS.contains(&bindings.key(Action::Pause)));
+    }
+
+    #[test]
+    fn bindings_test_parse_invalid_entries() {
+        let source = "[bindings]\nmove_left = \"left\"\njump = \"J\"\nmove_right = \"Left\"\nlaunch = \"F12\"\n";
+        let bindings = Bindings::parse(source).unwrap();
+        assert_eq!(bindings.key(Action::MoveLeft), Key::Left);
+        // conflicting and unknown entries keep the defaults
+        assert_eq!(bindings.key(Action::MoveRight), Key::D);
+        assert_eq!(bindings.key(Action::Launch), Key::Space);
+    }
+}
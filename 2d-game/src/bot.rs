@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::controller::{PaddleController, PredictiveController, Skill, View};
+use crate::drops::DropTable;
+use crate::game::LEVEL_FILES;
+use crate::replay;
+use crate::simulation::Simulation;
+
+// The bot harness plays levels with the AI paddle controller in the headless Simulation
+// (run with --bot [level files...]), to find out whether they can be cleared at all, e.g.
+// after editing one. A level counts as solvable once any of its attempts clears it.
+
+// simulation steps per second
+pub const RATE: u32 = 120;
+// attempts at each level, each with another seed
+pub const ATTEMPTS: u64 = 3;
+// simulated seconds after which an attempt is given up (the last bricks can take a while)
+pub const TIMEOUT: f32 = 1800.0;
+pub const SKILL: Skill = Skill::Hard;
+
+/// How an attempt at a level went.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attempt {
+    pub cleared: bool,
+    // simulated seconds played
+    pub time: f32,
+    pub lives: u32,
+    pub bricks_left: usize
+}
+
+/// The result of playing a level.
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub level: PathBuf,
+    pub attempts: Vec<Attempt>,
+    // real time taken by all attempts
+    pub elapsed: Duration
+}
+
+impl Report {
+    pub fn is_solvable(&self) -> bool {
+        self.attempts.iter().any(|it| it.cleared)
+    }
+
+    // the attempt which cleared the level, or the last one
+    pub fn best(&self) -> &Attempt {
+        self.attempts.iter().find(|it| it.cleared).unwrap_or_else(|| self.attempts.last().unwrap())
+    }
+}
+
+// picks the brick the AI goes for: another one left (at random, so the ball doesn't get stuck
+// in a loop that misses the last bricks) each time the ball starts falling
+pub struct Targeting {
+    rng: StdRng,
+    brick: Option<usize>,
+    falling: bool
+}
+
+impl Targeting {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed), brick: None, falling: false }
+    }
+
+    pub fn update(&mut self, simulation: &Simulation) -> Option<glm::TVec2<f32>> {
+        let bricks = &simulation.level.bricks;
+        let falling = simulation.ball.game_obj.velocity.y > 0.0;
+        let destroyed = self.brick.is_none_or(|i| bricks[i].destroyed);
+        if destroyed || (falling && !self.falling) {
+            let left: Vec<usize> = (0..bricks.len()).filter(|i| !bricks[*i].is_solid && !bricks[*i].destroyed).collect();
+            self.brick = if left.is_empty() { None } else { Some(left[self.rng.random_range(0..left.len())]) };
+        }
+        self.falling = falling;
+        self.brick.map(|i| bricks[i].position + bricks[i].size / 2.0)
+    }
+}
+
+// plays the level until it's cleared, all lives are lost or the timeout is reached
+pub fn attempt(simulation: &mut Simulation, controller: &mut dyn PaddleController, targeting: &mut Targeting, timeout: f32) -> Attempt {
+    let dt = 1.0 / RATE as f32;
+    let max_steps = (timeout * RATE as f32) as u32;
+    while simulation.lives > 0 && !simulation.level.is_completed() && simulation.steps < max_steps {
+        let target = targeting.update(simulation);
+        let view = View::of(&simulation.player, &simulation.ball, simulation.width as f32, simulation.level.bounds()).aiming_at(target);
+        let input = controller.control(&view, dt);
+        simulation.step(dt, input);
+    }
+    Attempt {
+        cleared: simulation.lives > 0 && simulation.level.is_completed(),
+        time: simulation.steps as f32 * dt,
+        lives: simulation.lives,
+        bricks_left: simulation.level.bricks_left()
+    }
+}
+
+// plays a level file (counted from 1 for the drop table) until an attempt clears it
+pub fn play(level: &Path, level_number: usize, drops: &DropTable, timeout: f32) -> Result<Report, String> {
+    let start = Instant::now();
+    let mut attempts = Vec::new();
+    for seed in 0..ATTEMPTS {
+        let mut simulation = Simulation::new(level.to_string_lossy().as_ref(), level_number, drops.clone(), seed, 800, 600)
+            .map_err(|e| format!("{}: {}", level.display(), e))?;
+        let attempt = attempt(&mut simulation, &mut PredictiveController::new(SKILL, seed), &mut Targeting::new(seed), timeout);
+        let cleared = attempt.cleared;
+        attempts.push(attempt);
+        if cleared {
+            break;
+        }
+    }
+    Ok(Report { level: level.to_path_buf(), attempts, elapsed: start.elapsed() })
+}
+
+// plays the given level files (all the levels of the game if there are none) and logs how each
+// went; returns whether every level is solvable
+pub fn run(levels: &[PathBuf], root: &Path) -> Result<bool, String> {
+    let drops = replay::load_rules(root)?;
+    let levels: Vec<PathBuf> = if levels.is_empty() {
+        LEVEL_FILES.iter().map(|it| root.join(it)).collect()
+    } else {
+        levels.to_vec()
+    };
+    let mut solvable = true;
+    for (i, level) in levels.iter().enumerate() {
+        let report = play(level, i + 1, &drops, TIMEOUT)?;
+        let best = report.best();
+        if report.is_solvable() {
+            log::info!("BOT: {} cleared in {:.1}s with {} lives left (attempt {}/{}, took {:.2?})",
+                level.display(), best.time, best.lives, report.attempts.len(), ATTEMPTS, report.elapsed);
+        } else {
+            let reason = if best.lives == 0 { "all lives lost".to_string() } else { format!("timed out after {:.0}s", best.time) };
+            log::warn!("BOT: {} not cleared in {} attempts ({}, {} bricks left, took {:.2?})",
+                level.display(), ATTEMPTS, reason, best.bricks_left, report.elapsed);
+        }
+        solvable &= report.is_solvable();
+    }
+    Ok(solvable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+    }
+
+    #[test]
+    fn bot_test_timeout() {
+        let drops = replay::load_rules(root()).unwrap();
+        let report = play(&root().join(LEVEL_FILES[0]), 1, &drops, 5.0).unwrap();
+        assert!(!report.is_solvable());
+        assert_eq!(report.attempts.len() as u64, ATTEMPTS);
+        let best = report.best();
+        assert!(best.lives > 0 && best.bricks_left > 0);
+        assert!((best.time - 5.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn bot_test_levels_are_solvable() {
+        assert_eq!(run(&[], root()), Ok(true));
+    }
+}
@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::RwLock;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use crate::drops::DropRule;
+use crate::score::BRICK_POINTS;
+
+/// What happens to a brick the ball hits.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnHit {
+    // loses a hit point, and is destroyed once it has none left
+    #[default]
+    Damage,
+    Indestructible
+}
+
+/// A type of brick, referenced by its tile code from the level files.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct BrickType {
+    pub name: String,
+    pub texture: String,
+    pub color: [f32; 3],
+    // tint with the colorblind-safe palette (the usual color if None)
+    pub colorblind_color: Option<[f32; 3]>,
+    // hits needed to destroy a brick of the type
+    pub hp: u32,
+    // points for destroying a brick of the type
+    pub points: u32,
+    pub on_hit: OnHit,
+    // power-up drops, which the rules of the drop table apply on top of
+    pub drops: Option<DropRule>
+}
+
+impl Default for BrickType {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            texture: "block".to_string(),
+            color: [1.0, 1.0, 1.0],
+            colorblind_color: None,
+            hp: 1,
+            points: BRICK_POINTS,
+            on_hit: OnHit::Damage,
+            drops: None
+        }
+    }
+}
+
+impl BrickType {
+    pub fn is_solid(&self) -> bool {
+        self.on_hit == OnHit::Indestructible
+    }
+}
+
+// the brick types shipped with the game, also used until (or unless) the table is loaded from disk
+const SHIPPED_TYPES: &str = include_str!("../../resources/levels/bricks.toml");
+
+lazy_static! {
+    static ref BRICK_TYPES: RwLock<BTreeMap<u32, BrickType>> = RwLock::new(
+        parse_types(SHIPPED_TYPES, &[]).expect("The shipped brick types should be valid.")
+    );
+}
+
+// parses a table of brick types keyed by tile code; power-ups dropped by them have to be one of `known`
+// (which isn't checked if it is empty)
+pub fn parse_types(source: &str, known: &[&str]) -> Result<BTreeMap<u32, BrickType>, String> {
+    let table: BTreeMap<String, BrickType> = toml::from_str(source).map_err(|e| e.to_string())?;
+    let mut types = BTreeMap::new();
+    for (key, brick_type) in table {
+        let code = match key.parse::<u32>() {
+            Ok(code) if code > 0 => code,
+            _ => return Err(format!("\"{}\" is not a tile code (expected a number above 0)", key))
+        };
+        if brick_type.hp == 0 {
+            return Err(format!("brick type {} needs at least 1 hp", code));
+        }
+        let weights = brick_type.drops.iter().flat_map(|it| it.weights.keys());
+        if let Some(unknown) = weights.filter(|_| !known.is_empty()).find(|it| !known.contains(&it.as_str())) {
+            return Err(format!("unknown power-up \"{}\" in the drops of brick type {}", unknown, code));
+        }
+        types.insert(code, brick_type);
+    }
+    Ok(types)
+}
+
+// registers a brick type, replacing the one with the same tile code
+pub fn register(code: u32, brick_type: BrickType) {
+    BRICK_TYPES.write().unwrap().insert(code, brick_type);
+}
+
+// registers the brick types of a table on disk; an invalid table is left out as a whole
+pub fn load_types(path: &str, known: &[&str]) {
+    let types = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|source| parse_types(source.as_str(), known));
+    match types {
+        Ok(types) => {
+            for (code, brick_type) in types {
+                register(code, brick_type);
+            }
+        }
+        Err(e) => log::error!("BRICK_TYPE: Failed to load {}: {}", path, e)
+    }
+}
+
+// the brick type with the given tile code, if there is one
+pub fn get(code: u32) -> Option<BrickType> {
+    BRICK_TYPES.read().unwrap().get(&code).cloned()
+}
+
+// whether the tile code stands for a brick type (or for empty space)
+pub fn is_known(code: u32) -> bool {
+    code == 0 || BRICK_TYPES.read().unwrap().contains_key(&code)
+}
+
+// whether the tile code stands for a brick which can be destroyed
+pub fn is_destructible(code: u32) -> bool {
+    get(code).is_some_and(|it| !it.is_solid())
+}
+
+// tile codes of all brick types, in ascending order
+pub fn codes() -> Vec<u32> {
+    BRICK_TYPES.read().unwrap().keys().copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brick_type_test_shipped_types() {
+        let types = parse_types(SHIPPED_TYPES, &[]).unwrap();
+        assert_eq!(types.keys().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert!(types[&1].is_solid());
+        assert_eq!(types[&1].texture, "block_solid");
+        assert!(types.values().skip(1).all(|it| !it.is_solid() && it.texture == "block" && it.points == BRICK_POINTS));
+    }
+
+    #[test]
+    fn brick_type_test_parse() {
+        let source = "[7]\nname = \"gold\"\ncolor = [1.0, 0.8, 0.1]\nhp = 3\npoints = 50\ndrops = { chance = 1.0, weights = { speed = 1 } }";
+        let types = parse_types(source, &["speed"]).unwrap();
+        let gold = &types[&7];
+        assert_eq!((gold.hp, gold.points, gold.on_hit), (3, 50, OnHit::Damage));
+        assert_eq!(gold.texture, "block");
+        assert_eq!(gold.drops.as_ref().unwrap().chance, Some(1.0));
+        assert!(parse_types(source, &["sticky"]).is_err());
+        assert!(parse_types("[0]\nhp = 1", &[]).is_err());
+        assert!(parse_types("[solid]\nhp = 1", &[]).is_err());
+        assert!(parse_types("[6]\nhp = 0", &[]).is_err());
+        assert!(parse_types("[6]\non-hit = \"explode\"", &[]).is_err());
+    }
+
+    #[test]
+    fn brick_type_test_register() {
+        // a code no other test uses, as the registry is shared
+        register(42, BrickType { name: "test".to_string(), on_hit: OnHit::Indestructible, ..BrickType::default() });
+        assert!(is_known(42) && is_known(0) && !is_known(43));
+        assert!(!is_destructible(42) && is_destructible(2));
+        assert!(codes().contains(&42));
+    }
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+// Assets compiled into the executable, so the renderers can always be created: the default font,
+// a white texture and the shaders of the sprite, particle and text renderers. They stand in for
+// the files when these are missing, and let tests construct renderers without the resources
+// directory.
+
+// the font used when a font file can't be loaded
+pub static FONT: &[u8] = include_bytes!("../../resources/fonts/OCRAEXT.TTF");
+
+// RGBA pixels of a plain white texture, for untextured quads
+pub const WHITE_TEXTURE_SIZE: u32 = 2;
+pub static WHITE_TEXTURE: [u8; (WHITE_TEXTURE_SIZE * WHITE_TEXTURE_SIZE * 4) as usize] = [255; 16];
+
+// shader sources by file name, including what they #include
+static SHADERS: [(&str, &str); 7] = [
+    ("common.glsl", include_str!("../../resources/shaders/common.glsl")),
+    ("sprite.vs", include_str!("../../resources/shaders/sprite.vs")),
+    ("sprite.fs", include_str!("../../resources/shaders/sprite.fs")),
+    ("particle.vs", include_str!("../../resources/shaders/particle.vs")),
+    ("particle.fs", include_str!("../../resources/shaders/particle.fs")),
+    ("text_2d.vs", include_str!("../../resources/shaders/text_2d.vs")),
+    ("text_2d.fs", include_str!("../../resources/shaders/text_2d.fs"))
+];
+
+// the built-in source of a shader file (looked up by its file name, wherever it was expected)
+pub fn shader_source(file: &str) -> Option<&'static str> {
+    let name = Path::new(file).file_name()?.to_str()?;
+    SHADERS.iter().find(|(it, _)| *it == name).map(|(_, source)| *source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader;
+
+    #[test]
+    fn builtin_test_shader_source() {
+        assert!(shader_source("/missing/resources/shaders/sprite.vs").unwrap().contains("void main()"));
+        assert!(shader_source("post_processing.fs").is_none());
+        // the built-in shaders only include built-in files
+        let resolve = |name: &str| shader_source(name).map(str::to_string).ok_or(format!("{} is not built in", name));
+        for (name, source) in SHADERS.iter() {
+            if let Err(e) = shader::preprocess(source, &[("PARTICLE_SCALE", "10.0")], &resolve) {
+                panic!("{} must preprocess: {}", name, e);
+            }
+        }
+    }
+
+    #[test]
+    fn builtin_test_font() {
+        // a TrueType font starts with version 1.0 of the format
+        assert_eq!(&FONT[0..4], &[0, 1, 0, 0]);
+        assert!(WHITE_TEXTURE.iter().all(|it| *it == 255));
+    }
+}
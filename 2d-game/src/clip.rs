@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::gl_api::{GlApi, NativeGl};
+use crate::ui::Rect;
+use crate::viewport::Viewport;
+
+// Clip rects keep what is drawn inside a region of the UI, e.g. a list scrolling past the
+// edges of its panel. Each rect pushed is cut to the ones below it and becomes the scissor
+// rect until it is popped again. The stack remembers the scissor state it set, so pushing the
+// same region again or popping back to it doesn't touch GL. Rects are in game coordinates
+// and meant for what is drawn straight into the viewport (not into the post-processor).
+
+pub struct ClipStack<G: GlApi = NativeGl> {
+    gl: G,
+    // where the game is shown in the framebuffer, and the size of the game
+    viewport: Viewport,
+    width: u32,
+    height: u32,
+    rects: Vec<Rect>,
+    // the scissor rect as last set, None while the scissor test is off
+    applied: Option<[i32; 4]>
+}
+
+impl ClipStack {
+    // constructor
+    pub fn new(viewport: Viewport, width: u32, height: u32) -> Self {
+        Self::with_gl(NativeGl, viewport, width, height)
+    }
+}
+
+impl<G: GlApi> ClipStack<G> {
+    // constructor issuing the GL calls through the given API
+    pub fn with_gl(gl: G, viewport: Viewport, width: u32, height: u32) -> Self {
+        Self { gl, viewport, width, height, rects: Vec::new(), applied: None }
+    }
+
+    // called when the viewport changes; the current clip follows it
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+        self.apply();
+    }
+
+    // clips everything drawn to the given rect (within the current clip) until the matching pop
+    pub fn push(&mut self, rect: Rect) {
+        let rect = match self.rects.last() {
+            Some(current) => current.intersect(&rect),
+            None => rect
+        };
+        self.rects.push(rect);
+        self.apply();
+    }
+
+    // goes back to the clip before the last push
+    pub fn pop(&mut self) {
+        if self.rects.pop().is_none() {
+            log::warn!("CLIP: Popped a clip rect that was never pushed");
+        }
+        self.apply();
+    }
+
+    // the rect drawing is clipped to, if any
+    pub fn current(&self) -> Option<Rect> {
+        self.rects.last().copied()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.rects.len()
+    }
+
+    fn apply(&mut self) {
+        let scissor = self.rects.last().map(|rect| self.viewport.to_framebuffer(self.width, self.height, rect));
+        if scissor == self.applied {
+            return;
+        }
+        match scissor {
+            Some([x, y, width, height]) => {
+                if self.applied.is_none() {
+                    self.gl.enable(gl::SCISSOR_TEST);
+                }
+                self.gl.scissor(x, y, width, height);
+            }
+            None => self.gl.disable(gl::SCISSOR_TEST)
+        }
+        self.applied = scissor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_api::{GlCall, RecordingGl};
+
+    fn stack() -> ClipStack<RecordingGl> {
+        ClipStack::with_gl(RecordingGl::default(), Viewport::fit(800, 600, 800, 600), 800, 600)
+    }
+
+    #[test]
+    fn clip_test_nesting() {
+        let mut clip = stack();
+        clip.push(Rect::new(100.0, 100.0, 200.0, 200.0));
+        // the inner rect is cut to the outer one
+        clip.push(Rect::new(250.0, 0.0, 200.0, 200.0));
+        assert_eq!(clip.current(), Some(Rect::new(250.0, 100.0, 50.0, 100.0)));
+        clip.pop();
+        clip.pop();
+        assert_eq!(clip.gl.take(), vec![
+            GlCall::Enable(gl::SCISSOR_TEST),
+            GlCall::Scissor(100, 300, 200, 200),
+            GlCall::Scissor(250, 400, 50, 100),
+            GlCall::Scissor(100, 300, 200, 200),
+            GlCall::Disable(gl::SCISSOR_TEST)
+        ]);
+        assert_eq!(clip.depth(), 0);
+    }
+
+    #[test]
+    fn clip_test_skips_redundant_calls() {
+        let mut clip = stack();
+        clip.push(Rect::new(0.0, 0.0, 100.0, 100.0));
+        clip.gl.take();
+        // the same region again (it lies within the current one) doesn't change anything
+        clip.push(Rect::new(0.0, 0.0, 100.0, 100.0));
+        clip.pop();
+        assert!(clip.gl.take().is_empty());
+        // a larger window scales the scissor rect
+        clip.set_viewport(Viewport::fit(800, 600, 1600, 1200));
+        assert_eq!(clip.gl.take(), vec![GlCall::Scissor(0, 1000, 200, 200)]);
+        // popping without a push leaves the scissor test off
+        clip.pop();
+        clip.pop();
+        assert_eq!(clip.gl.take(), vec![GlCall::Disable(gl::SCISSOR_TEST)]);
+    }
+}
@@ -0,0 +1,390 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use crate::ball_object::BallObject;
+use crate::game_object::GameObject;
+use crate::walls::Walls;
+
+// Represents the four possible (collision) directions
+#[derive(PartialOrd, PartialEq, Debug)]
+pub enum Direction {
+    Up = 0,
+    Right = 1,
+    Down = 2,
+    Left = 3
+}
+// Defines a Collision typedef that represents collision data
+pub type Collision = (bool, Direction, glm::TVec2<f32>);
+
+pub fn check_collision(one: &GameObject, two: &GameObject) -> bool { // AABB - AABB collision
+    // collision x-axis?
+    let collision_x = one.position.x + one.size.x >= two.position.x
+        && two.position.x + two.size.x >= one.position.x;
+    // collision y-axis?
+    let collision_y = one.position.y + one.size.y >= two.position.y
+        && two.position.y + two.size.y >= one.position.y;
+    // collision only if on both axes
+    collision_x && collision_y
+}
+
+pub fn check_collision_1(one: &BallObject, two: &GameObject) -> Collision { // AABB - Circle collision
+    // get center point circle first
+    let center = glm::vec2(one.game_obj.position.x + one.radius, one.game_obj.position.y + one.radius);
+    // calculate AABB info (center, half-extents)
+    let aabb_half_extents = glm::vec2(two.size.x / 2.0, two.size.y / 2.0);
+    let aabb_center = glm::vec2(two.position.x + aabb_half_extents.x, two.position.y + aabb_half_extents.y);
+    // get difference vector between both centers
+    let mut difference = center - aabb_center;
+    let clamped = glm::clamp_vec(&difference, &(-aabb_half_extents), &aabb_half_extents);
+    // now that we know the clamped values, add this to AABB_center and we get the value of box closest to circle
+    let closest = aabb_center + clamped;
+    // now retrieve vector between center circle and closest point AABB and check if length < radius
+    difference = closest - center;
+
+    if difference.x == 0.0 && difference.y == 0.0 {
+        return (false, Direction::Up, glm::vec2(0.0, 0.0));
+    }
+
+    if glm::length(&difference) < one.radius { // not <= since in that case a collision also occurs when object one exactly touches object two, which they are at the end of each collision resolution stage.
+        (true, vector_direction(difference), difference)
+    } else {
+        (false, Direction::Up, glm::vec2(0.0, 0.0))
+    }
+}
+
+pub fn check_collision_obb(one: &BallObject, two: &GameObject) -> Collision { // OBB - Circle collision
+    let center = glm::vec2(one.game_obj.position.x + one.radius, one.game_obj.position.y + one.radius);
+    let half_extents = glm::vec2(two.size.x / 2.0, two.size.y / 2.0);
+    let box_center = two.position + half_extents;
+    // in the frame of the box (rotated about its center, like its sprite) the box is axis-aligned
+    let local = rotate_vec2(center - box_center, -two.rotation);
+    let clamped = glm::clamp_vec(&local, &(-half_extents), &half_extents);
+    // the point of the box closest to the circle, turned back into the world
+    let closest = box_center + rotate_vec2(clamped, two.rotation);
+    let difference = closest - center;
+
+    if difference.x == 0.0 && difference.y == 0.0 {
+        return (false, Direction::Up, glm::vec2(0.0, 0.0));
+    }
+
+    if glm::length(&difference) < one.radius {
+        (true, vector_direction(difference), difference)
+    } else {
+        (false, Direction::Up, glm::vec2(0.0, 0.0))
+    }
+}
+
+// rotates a vector by the given angle in degrees (clockwise on screen, the way sprites are rotated)
+pub fn rotate_vec2(vector: glm::TVec2<f32>, degrees: f32) -> glm::TVec2<f32> {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    glm::vec2(cos * vector.x - sin * vector.y, sin * vector.x + cos * vector.y)
+}
+
+// calculates which direction a vector is facing (N,E,S or W)
+pub fn vector_direction(target: glm::TVec2<f32>) -> Direction {
+    let compass = [
+        glm::vec2(0.0f32, 1.0),	// up
+        glm::vec2(1.0, 0.0),	// right
+        glm::vec2(0.0, -1.0),	// down
+        glm::vec2(-1.0, 0.0)	// left
+    ];
+    let mut max = 0.0f32;
+    let mut best_match = -1isize;
+    for i in 0..4 {
+        let dot_product = glm::dot(&glm::normalize(&target), &compass[i]);
+        if dot_product > max {
+            max = dot_product;
+            best_match = i as isize;
+        }
+    }
+    match best_match {
+        0 => Direction::Up,
+        1 => Direction::Right,
+        2 => Direction::Down,
+        3 => Direction::Left,
+        _ => panic!("Wrong best_match value was produced within function vector_direction: {}", best_match)
+    }
+}
+
+// folds a coordinate moving between two walls back into [min, max], as if it bounced off them
+fn reflect_into(value: f32, min: f32, max: f32) -> f32 {
+    let span = max - min;
+    if span <= 0.0 {
+        return min;
+    }
+    let folded = (value - min).rem_euclid(span * 2.0);
+    min + if folded > span { span * 2.0 - folded } else { folded }
+}
+
+// predicts the x coordinate of the ball's center once the center reaches target_y, bouncing
+// off the left, right and top walls of a window with the given width (bricks are ignored);
+// there's no prediction for walls that are open, wrap around or change the ball's speed
+pub fn predict_landing(center: glm::TVec2<f32>, velocity: glm::TVec2<f32>, radius: f32, target_y: f32, walls: &Walls, window_width: f32) -> Option<f32> {
+    if !walls.is_closed() || walls.restitution != 1.0 || velocity.y == 0.0 {
+        return None;
+    }
+    // vertical distance to travel; a rising ball first bounces off the top wall
+    let distance = if velocity.y > 0.0 {
+        target_y - center.y
+    } else {
+        (center.y - radius) + (target_y - radius)
+    };
+    if distance < 0.0 {
+        return None;
+    }
+    let time = distance / velocity.y.abs();
+    Some(reflect_into(center.x + velocity.x * time, radius, window_width - radius))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walls::Side;
+    use std::f32::consts::FRAC_PI_2;
+    use proptest::prelude::*;
+    use crate::simulation;
+    use crate::texture::Texture2D;
+
+    // number of random cases each property is checked with
+    const CASES: u32 = 2000;
+
+    // position and size of a box
+    type Shape = (glm::TVec2<f32>, glm::TVec2<f32>);
+
+    fn vec2(range: f32) -> impl Strategy<Value = glm::TVec2<f32>> {
+        (-range..range, -range..range).prop_map(|(x, y)| glm::vec2(x, y))
+    }
+
+    fn shape() -> impl Strategy<Value = Shape> {
+        (vec2(500.0), 1.0f32..200.0, 1.0f32..200.0).prop_map(|(position, width, height)| (position, glm::vec2(width, height)))
+    }
+
+    fn game_box(shape: Shape) -> GameObject {
+        GameObject::new_ex0(shape.0, shape.1, Texture2D::empty())
+    }
+
+    // center and radius of a ball overlapping the box, with its center outside of it: off one of
+    // its sides (0..4) or corners (4..8), closer to it than the radius
+    fn overlapping(shape: Shape) -> impl Strategy<Value = (glm::TVec2<f32>, f32)> {
+        (1.0f32..30.0, 0usize..8, 0.0f32..1.0, 0.01f32..0.98).prop_map(move |(radius, place, along, depth)| {
+            let ((min, size), distance) = (shape, radius * depth);
+            let max = min + size;
+            let center = match place {
+                0 => glm::vec2(min.x + size.x * along, min.y - distance),
+                1 => glm::vec2(max.x + distance, min.y + size.y * along),
+                2 => glm::vec2(min.x + size.x * along, max.y + distance),
+                3 => glm::vec2(min.x - distance, min.y + size.y * along),
+                corner => {
+                    let (corner, outwards) = [
+                        (min, glm::vec2(-1.0, -1.0)),
+                        (glm::vec2(max.x, min.y), glm::vec2(1.0, -1.0)),
+                        (max, glm::vec2(1.0, 1.0)),
+                        (glm::vec2(min.x, max.y), glm::vec2(-1.0, 1.0))
+                    ][corner - 4];
+                    let angle = along * FRAC_PI_2;
+                    corner + glm::vec2(outwards.x * angle.cos(), outwards.y * angle.sin()) * distance
+                }
+            };
+            (center, radius)
+        })
+    }
+
+    // a box and the center, radius and velocity of a ball overlapping it
+    fn box_and_ball() -> impl Strategy<Value = (Shape, (glm::TVec2<f32>, f32), glm::TVec2<f32>)> {
+        shape().prop_flat_map(|shape| (Just(shape), overlapping(shape), vec2(500.0)))
+    }
+
+    fn ball((center, radius): (glm::TVec2<f32>, f32), velocity: glm::TVec2<f32>) -> BallObject {
+        BallObject::new_ex(center - glm::vec2(radius, radius), radius, velocity, Texture2D::empty())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+        #[test]
+        fn check_collision_test_random_symmetric(one in shape(), two in shape()) {
+            let (one, two) = (game_box(one), game_box(two));
+            prop_assert_eq!(check_collision(&one, &two), check_collision(&two, &one));
+        }
+
+        #[test]
+        fn check_collision_1_test_random_overlaps((shape, placed, velocity) in box_and_ball()) {
+            let (brick, ball) = (game_box(shape), ball(placed, velocity));
+            let (collided, dir, difference) = check_collision_1(&ball, &brick);
+            prop_assert!(collided);
+            // the difference points from the center of the ball to the closest point of the box
+            prop_assert!(glm::length(&difference) < ball.radius);
+            prop_assert_eq!(dir, vector_direction(difference));
+        }
+
+        #[test]
+        fn check_collision_obb_test_random_unrotated(shape in shape(), offset in vec2(250.0), radius in 1.0f32..30.0) {
+            let brick = game_box(shape);
+            let ball = BallObject::new_ex(brick.position + offset, radius, glm::vec2(0.0, 0.0), Texture2D::empty());
+            // without rotation the oriented box is the axis-aligned one
+            prop_assert_eq!(check_collision_obb(&ball, &brick), check_collision_1(&ball, &brick));
+        }
+
+        #[test]
+        fn hit_brick_test_random_reflections((shape, placed, velocity) in box_and_ball(), is_solid in any::<bool>(), hp in 1u32..4) {
+            let mut brick = game_box(shape);
+            brick.is_solid = is_solid;
+            brick.hp = hp;
+            let mut ball = ball(placed, velocity);
+            let speed = glm::length(&ball.game_obj.velocity);
+            let collision = check_collision_1(&ball, &brick);
+            simulation::hit_brick(&mut ball, &mut brick, &collision);
+            // reflecting keeps the speed of the ball
+            prop_assert_eq!(glm::length(&ball.game_obj.velocity), speed);
+            // and moves it out of the brick
+            let center = ball.game_obj.position + glm::vec2(ball.radius, ball.radius);
+            let closest = glm::clamp_vec(&center, &brick.position, &(brick.position + brick.size));
+            let distance = glm::length(&(closest - center));
+            prop_assert!(distance >= ball.radius * (1.0 - 1e-4), "{} of {} still overlaps", distance, ball.radius);
+        }
+
+        #[test]
+        fn bounce_off_paddle_test_random_hits((shape, placed, velocity) in box_and_ball(), sticky in any::<bool>()) {
+            prop_assume!(velocity.x != 0.0 || velocity.y != 0.0);
+            let paddle = game_box(shape);
+            let mut ball = ball(placed, velocity);
+            ball.stuck = false;
+            ball.sticky = sticky;
+            let speed = glm::length(&ball.game_obj.velocity);
+            prop_assert!(simulation::bounce_off_paddle(&mut ball, &paddle, false));
+            // the ball leaves upwards at the same speed, unless it sticks to the paddle
+            prop_assert!((glm::length(&ball.game_obj.velocity) - speed).abs() <= speed * 1e-5);
+            prop_assert!(ball.game_obj.velocity.y <= 0.0);
+            prop_assert_eq!(ball.stuck, ball.sticky);
+        }
+
+        #[test]
+        fn vector_direction_test_random_vectors(target in vec2(1000.0), factor in 0.01f32..100.0) {
+            prop_assume!(target.x != 0.0 || target.y != 0.0);
+            let compass = [
+                (Direction::Up, glm::vec2(0.0f32, 1.0)),
+                (Direction::Right, glm::vec2(1.0, 0.0)),
+                (Direction::Down, glm::vec2(0.0, -1.0)),
+                (Direction::Left, glm::vec2(-1.0, 0.0))
+            ];
+            let dir = vector_direction(target);
+            // the chosen direction is the closest one of the compass
+            let chosen = compass.iter().find(|it| it.0 == dir).unwrap().1;
+            for (_, other) in compass.iter() {
+                prop_assert!(glm::dot(&target, &chosen) >= glm::dot(&target, other));
+            }
+            // and doesn't depend on the length of the vector
+            prop_assert_eq!(vector_direction(target * factor), dir);
+        }
+
+        #[test]
+        fn predict_landing_test_random_within_walls(radius in 1.0f32..30.0, along in 0.0f32..1.0, y in 30.0f32..500.0, velocity in vec2(1000.0)) {
+            let center = glm::vec2(radius + (800.0 - radius * 2.0) * along, y);
+            if let Some(x) = predict_landing(center, velocity, radius, 550.0, &Walls::default(), 800.0) {
+                prop_assert!(x >= radius - 1e-3 && x <= 800.0 - radius + 1e-3, "{} lies outside the walls", x);
+            }
+        }
+    }
+
+    #[test]
+    fn check_collision_obb_test_tilted() {
+        let mut paddle = GameObject::new_ex0(glm::vec2(0.0, 100.0), glm::vec2(100.0, 20.0), Texture2D::empty());
+        paddle.rotation = 30.0;
+        // above the lowered right end, within the bounding box of the unrotated paddle
+        let ball = BallObject::new_ex(glm::vec2(85.0, 92.0), 5.0, glm::vec2(0.0, 0.0), Texture2D::empty());
+        assert!(check_collision_1(&ball, &paddle).0);
+        assert!(!check_collision_obb(&ball, &paddle).0);
+        // on the surface of the tilted paddle, seen from its raised left end
+        let ball = BallObject::new_ex(glm::vec2(16.86, 73.74), 5.0, glm::vec2(0.0, 0.0), Texture2D::empty());
+        assert!(!check_collision_1(&ball, &paddle).0);
+        let (collided, _, difference) = check_collision_obb(&ball, &paddle);
+        assert!(collided);
+        // pointing into the paddle, along its tilted normal
+        let normal = rotate_vec2(glm::vec2(0.0, -1.0), paddle.rotation);
+        assert!(glm::dot(&glm::normalize(&difference), &-normal) > 0.99);
+    }
+
+    #[test]
+    fn bounce_off_paddle_test_tilted() {
+        let mut paddle = GameObject::new_ex0(glm::vec2(0.0, 100.0), glm::vec2(100.0, 20.0), Texture2D::empty());
+        paddle.rotation = 10.0;
+        // a ball falling straight onto the middle of the paddle
+        let center = paddle.position + paddle.size / 2.0 + rotate_vec2(glm::vec2(0.0, -13.5), paddle.rotation);
+        let mut ball = BallObject::new_ex(center - glm::vec2(5.0, 5.0), 5.0, glm::vec2(0.0, 350.0), Texture2D::empty());
+        ball.stuck = false;
+        assert!(simulation::bounce_off_paddle(&mut ball, &paddle, false));
+        // and leaves along the normal of the tilted paddle
+        let velocity = ball.game_obj.velocity;
+        assert!((glm::length(&velocity) - 350.0).abs() < 1e-3);
+        assert!((velocity.x.atan2(-velocity.y).to_degrees() - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn bounce_off_paddle_test_catch() {
+        let mut paddle = GameObject::new_ex0(glm::vec2(0.0, 100.0), glm::vec2(100.0, 20.0), Texture2D::empty());
+        let mut ball = BallObject::new_ex(glm::vec2(45.0, 91.0), 5.0, glm::vec2(0.0, 350.0), Texture2D::empty());
+        ball.stuck = false;
+        assert!(simulation::bounce_off_paddle(&mut ball, &paddle, true));
+        assert!(ball.stuck);
+        assert_eq!(ball.game_obj.velocity.x, 0.0);
+        // the ball is held while the paddle is tilted to aim
+        paddle.rotation = 10.0;
+        simulation::update_catch(&mut ball, &paddle, 0.5, false);
+        assert!(ball.stuck);
+        // and thrown along the tilted paddle once the time is up
+        simulation::update_catch(&mut ball, &paddle, simulation::CATCH_TIME, false);
+        assert!(!ball.stuck);
+        assert_eq!(ball.catch_time, 0.0);
+        let velocity = ball.game_obj.velocity;
+        assert!((glm::length(&velocity) - 350.0).abs() < 1e-3);
+        assert!(velocity.x > 0.0 && velocity.y < 0.0);
+    }
+
+    #[test]
+    fn predict_landing_test_straight() {
+        let landing = predict_landing(glm::vec2(100.0, 100.0), glm::vec2(0.0, 200.0), 10.0, 500.0, &Walls::default(), 800.0);
+        assert_eq!(landing, Some(100.0));
+        // already below the target
+        assert_eq!(predict_landing(glm::vec2(100.0, 600.0), glm::vec2(0.0, 200.0), 10.0, 500.0, &Walls::default(), 800.0), None);
+        assert_eq!(predict_landing(glm::vec2(100.0, 100.0), glm::vec2(200.0, 0.0), 10.0, 500.0, &Walls::default(), 800.0), None);
+    }
+
+    #[test]
+    fn predict_landing_test_walls() {
+        // moves 200 units right: 100 to the right wall (center x = 790) and 100 back
+        let landing = predict_landing(glm::vec2(690.0, 300.0), glm::vec2(200.0, 200.0), 10.0, 500.0, &Walls::default(), 800.0);
+        assert_eq!(landing, Some(690.0));
+        // rises 90 units to the top wall and falls 490, moving 580 units left: 390 to the left wall and 190 back
+        let landing = predict_landing(glm::vec2(400.0, 100.0), glm::vec2(-100.0, -100.0), 10.0, 500.0, &Walls::default(), 800.0);
+        assert_eq!(landing, Some(200.0));
+    }
+
+    #[test]
+    fn predict_landing_test_other_walls() {
+        let predict = |walls: Walls| predict_landing(glm::vec2(690.0, 300.0), glm::vec2(200.0, 200.0), 10.0, 500.0, &walls, 800.0);
+        assert_eq!(predict(Walls { left: Side::Wrap, right: Side::Wrap, ..Walls::default() }), None);
+        assert_eq!(predict(Walls { top: Side::Open, ..Walls::default() }), None);
+        assert_eq!(predict(Walls { restitution: 1.2, ..Walls::default() }), None);
+    }
+
+    #[test]
+    fn vector_direction_test_case() {
+        assert_eq!(vector_direction(glm::vec2(0.2, 1.0)), Direction::Up);
+        assert_eq!(vector_direction(glm::vec2(-3.0, 1.0)), Direction::Left);
+    }
+}
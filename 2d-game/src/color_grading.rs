@@ -0,0 +1,198 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fs;
+use std::time::SystemTime;
+use std::ptr;
+use image::RgbImage;
+use crate::resource_manager;
+
+// Color grading LUTs of the level themes. A LUT is a PNG strip of size x size slices (so
+// size² pixels wide and size high): the slice is the blue channel, x within it the red one and
+// y the green one, with the darkest value at the top left. The final image of the
+// post-processor looks its colors up in it. The files are checked for changes every
+// RELOAD_INTERVAL, so they can be edited while the game runs.
+
+// directory the LUTs are loaded from, as <name>.png
+pub const LUTS_DIR: &str = "resources/textures/luts";
+// seconds between checks whether a LUT file changed
+pub const RELOAD_INTERVAL: f32 = 1.0;
+
+// the number of entries per channel of a LUT strip of the given size in pixels
+pub fn strip_size(width: u32, height: u32) -> Result<u32, String> {
+    if height < 2 || width != height * height {
+        return Err(format!("a LUT strip of {}x{} pixels is not N² x N (N >= 2)", width, height));
+    }
+    Ok(height)
+}
+
+// the entries of a LUT strip in the order of a 3D texture (red changing fastest, then green and blue)
+pub fn to_volume(strip: &RgbImage) -> Result<(u32, Vec<u8>), String> {
+    let size = strip_size(strip.width(), strip.height())?;
+    let mut volume = Vec::with_capacity((size * size * size * 3) as usize);
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                volume.extend_from_slice(&strip.get_pixel(b * size + r, g).0);
+            }
+        }
+    }
+    Ok((size, volume))
+}
+
+// a LUT strip which leaves the colors as they are
+pub fn identity(size: u32) -> RgbImage {
+    let value = |i: u32| (i * 255 / (size - 1)) as u8;
+    RgbImage::from_fn(size * size, size, |x, y| image::Rgb([value(x % size), value(y), value(x / size)]))
+}
+
+struct Lut {
+    // the texture and its size, none while the file can't be loaded
+    texture: Option<(u32, u32)>,
+    modified: Option<SystemTime>
+}
+
+/// The LUTs loaded so far, by name.
+pub struct Luts {
+    dir: String,
+    loaded: HashMap<String, Lut>,
+    since_check: f32
+}
+
+impl Luts {
+    pub fn new(dir: String) -> Self {
+        Self { dir, loaded: HashMap::new(), since_check: 0.0 }
+    }
+
+    fn path(&self, name: &str) -> String {
+        format!("{}/{}.png", self.dir, name)
+    }
+
+    // the 3D texture of the named LUT, loaded the first time it's asked for
+    pub fn texture(&mut self, name: &str) -> Option<u32> {
+        if !self.loaded.contains_key(name) {
+            let path = self.path(name);
+            let lut = Lut { texture: load(path.as_str(), None), modified: modified(path.as_str()) };
+            self.loaded.insert(name.to_string(), lut);
+            self.track_memory();
+        }
+        self.loaded[name].texture.map(|it| it.0)
+    }
+
+    // reloads the LUTs whose files changed (checked every RELOAD_INTERVAL)
+    pub fn update(&mut self, dt: f32) {
+        self.since_check += dt;
+        if self.since_check < RELOAD_INTERVAL {
+            return;
+        }
+        self.since_check = 0.0;
+        let mut reloaded = false;
+        for (name, lut) in self.loaded.iter_mut() {
+            let path = format!("{}/{}.png", self.dir, name);
+            let modified = modified(path.as_str());
+            if modified != lut.modified {
+                lut.modified = modified;
+                lut.texture = load(path.as_str(), lut.texture.map(|it| it.0)).or(lut.texture);
+                log::info!("LUT: Reloaded {}", path);
+                reloaded = true;
+            }
+        }
+        if reloaded {
+            self.track_memory();
+        }
+    }
+
+    fn track_memory(&self) {
+        let bytes = self.loaded.values().filter_map(|it| it.texture).map(|(_, size)| (size * size * size * 3) as usize).sum();
+        resource_manager::track_gpu_memory("luts", bytes);
+    }
+}
+
+impl Drop for Luts {
+    fn drop(&mut self) {
+        for (texture, _) in self.loaded.values().filter_map(|it| it.texture) {
+            unsafe {
+                gl::DeleteTextures(1, &texture);
+            }
+        }
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|it| it.modified()).ok()
+}
+
+// loads a LUT strip into a 3D texture (the given one, or a new one); yields the texture and its size
+fn load(path: &str, texture: Option<u32>) -> Option<(u32, u32)> {
+    let volume = image::open(path)
+        .map_err(|e| e.to_string())
+        .and_then(|image| to_volume(&image.to_rgb8()));
+    let (size, volume) = match volume {
+        Ok(volume) => volume,
+        Err(e) => {
+            log::error!("LUT: Failed to load {}: {}", path, e);
+            return None;
+        }
+    };
+    let mut texture = texture.unwrap_or(0);
+    unsafe {
+        if texture == 0 {
+            gl::GenTextures(1, &mut texture);
+        }
+        gl::BindTexture(gl::TEXTURE_3D, texture);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexImage3D(gl::TEXTURE_3D, 0, gl::RGB8 as _, size as _, size as _, size as _, 0, gl::RGB, gl::UNSIGNED_BYTE, ptr::addr_of!(volume[0]) as _);
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as _);
+        gl::BindTexture(gl::TEXTURE_3D, 0);
+    }
+    Some((texture, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn color_grading_test_volume() {
+        let (size, volume) = to_volume(&identity(4)).unwrap();
+        assert_eq!(size, 4);
+        assert_eq!(volume.len(), 4 * 4 * 4 * 3);
+        // entry (r, g, b) = (1, 2, 3) of the identity holds its own color
+        let i = ((3 * 4 + 2) * 4 + 1) * 3;
+        assert_eq!(&volume[i..i + 3], &[85, 170, 255]);
+        assert!(strip_size(16, 16).is_err());
+        assert!(strip_size(1, 1).is_err());
+    }
+
+    #[test]
+    fn color_grading_test_shipped_luts() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(LUTS_DIR);
+        let source = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/levels/themes.toml")).unwrap();
+        let themes = crate::post_effects::parse_themes(source.as_str()).unwrap();
+        for name in themes.themes.values().filter_map(|it| it.lut.as_ref()) {
+            let image = image::open(dir.join(format!("{}.png", name))).unwrap_or_else(|e| panic!("The {} LUT should load: {}", name, e));
+            if let Err(e) = to_volume(&image.to_rgb8()) {
+                panic!("{}.png must be a LUT strip: {}", name, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,321 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use glfw::Key;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::ball_object::BallObject;
+use crate::bindings::{Action, Bindings};
+use crate::collision::predict_landing;
+use crate::dash::DOUBLE_TAP_TIME;
+use crate::game::INITIAL_BALL_VELOCITY;
+use crate::game_object::GameObject;
+use crate::input::InputState;
+use crate::simulation::{StepInput, BOUNCE_STRENGTH};
+use crate::walls::Walls;
+
+// how long the AIs keep the ball on the paddle before launching it
+pub const LAUNCH_DELAY: f32 = 1.0;
+// the paddle stops moving once it's this close to where it's headed
+pub const DEADZONE: f32 = 8.0;
+// the ball is never aimed further out than this fraction of half the paddle
+const MAX_AIM_OFFSET: f32 = 0.8;
+
+/// What a paddle controller gets to see of the game.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct View {
+    pub paddle_position: glm::TVec2<f32>,
+    pub paddle_size: glm::TVec2<f32>,
+    pub ball_center: glm::TVec2<f32>,
+    pub ball_velocity: glm::TVec2<f32>,
+    pub ball_radius: f32,
+    pub ball_stuck: bool,
+    pub width: f32,
+    pub walls: Walls,
+    // where the predictive AI tries to send the ball (e.g. a brick that's left), or none
+    pub target: Option<glm::TVec2<f32>>
+}
+
+impl View {
+    pub fn of(player: &GameObject, ball: &BallObject, width: f32, walls: Walls) -> Self {
+        Self {
+            paddle_position: player.position,
+            paddle_size: player.size,
+            ball_center: ball.game_obj.position + glm::vec2(ball.radius, ball.radius),
+            ball_velocity: ball.game_obj.velocity,
+            ball_radius: ball.radius,
+            ball_stuck: ball.stuck,
+            width,
+            walls,
+            target: None
+        }
+    }
+
+    // with the AI sending the ball towards the given point
+    pub fn aiming_at(self, target: Option<glm::TVec2<f32>>) -> Self {
+        Self { target, ..self }
+    }
+
+    fn paddle_center(&self) -> f32 {
+        self.paddle_position.x + self.paddle_size.x / 2.0
+    }
+}
+
+/// Drives the paddle: the player's keys, or one of the AIs (e.g. in the demo shown when the
+/// menu is left alone, or when levels are played automatically).
+pub trait PaddleController {
+    // the input of the next step
+    fn control(&mut self, view: &View, dt: f32) -> StepInput;
+}
+
+/// How well an AI plays.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Skill {
+    Easy,
+    Normal,
+    Hard
+}
+
+pub const SKILLS: [Skill; 3] = [Skill::Easy, Skill::Normal, Skill::Hard];
+
+impl Skill {
+    pub fn id(self) -> &'static str {
+        match self {
+            Skill::Easy => "easy",
+            Skill::Normal => "normal",
+            Skill::Hard => "hard"
+        }
+    }
+
+    // largest aiming error, as a fraction of the width of the paddle (above 0.5 the ball can be missed)
+    pub fn max_error(self) -> f32 {
+        match self {
+            Skill::Easy => 0.9,
+            Skill::Normal => 0.6,
+            Skill::Hard => 0.3
+        }
+    }
+
+    // seconds the AI takes to react once the ball starts falling
+    pub fn reaction_time(self) -> f32 {
+        match self {
+            Skill::Easy => 0.35,
+            Skill::Normal => 0.2,
+            Skill::Hard => 0.05
+        }
+    }
+}
+
+/// The player's keys.
+pub struct PlayerController<'a> {
+    pub input: &'a InputState,
+    pub bindings: &'a Bindings
+}
+
+impl PaddleController for PlayerController<'_> {
+    fn control(&mut self, _view: &View, _dt: f32) -> StepInput {
+        let key = |action| self.bindings.key(action);
+        let (left, right): (Key, Key) = (key(Action::MoveLeft), key(Action::MoveRight));
+        StepInput {
+            left: self.input.held(left),
+            right: self.input.held(right),
+            launch: self.input.held(key(Action::Launch)),
+            // double-tapping a direction dashes as well
+            dash: self.input.double_tapped(left, DOUBLE_TAP_TIME)
+                || self.input.double_tapped(right, DOUBLE_TAP_TIME)
+                || self.input.just_pressed(key(Action::Dash)),
+            tilt_left: self.input.held(key(Action::TiltLeft)),
+            tilt_right: self.input.held(key(Action::TiltRight))
+        }
+    }
+}
+
+// what both AIs keep track of: when to launch, and their aiming error, which is rolled
+// again (after the reaction time) each time the ball starts falling
+struct Aim {
+    skill: Skill,
+    rng: StdRng,
+    error: f32,
+    falling: bool,
+    reaction_left: f32,
+    stuck_time: f32
+}
+
+impl Aim {
+    fn new(skill: Skill, seed: u64) -> Self {
+        Self { skill, rng: StdRng::seed_from_u64(seed), error: 0.0, falling: false, reaction_left: 0.0, stuck_time: 0.0 }
+    }
+
+    // launches a stuck ball after LAUNCH_DELAY; returns the input if there's nothing to steer yet
+    fn update(&mut self, view: &View, dt: f32) -> Option<StepInput> {
+        if view.ball_stuck {
+            self.stuck_time += dt;
+            return Some(StepInput { launch: self.stuck_time >= LAUNCH_DELAY, ..StepInput::default() });
+        }
+        self.stuck_time = 0.0;
+        let falling = view.ball_velocity.y > 0.0;
+        if falling && !self.falling {
+            let max_error = view.paddle_size.x * self.skill.max_error();
+            self.error = self.rng.random_range(-max_error..=max_error);
+            self.reaction_left = self.skill.reaction_time();
+        }
+        self.falling = falling;
+        if self.reaction_left > 0.0 {
+            self.reaction_left -= dt;
+            return Some(StepInput::default());
+        }
+        None
+    }
+}
+
+// moves the paddle towards the target x
+fn steer(view: &View, target: f32) -> StepInput {
+    let offset = target - view.paddle_center();
+    StepInput { left: offset < -DEADZONE, right: offset > DEADZONE, ..StepInput::default() }
+}
+
+/// Keeps the paddle under the ball, which is good enough for slow balls.
+pub struct TrackingController {
+    aim: Aim
+}
+
+impl TrackingController {
+    pub fn new(skill: Skill, seed: u64) -> Self {
+        Self { aim: Aim::new(skill, seed) }
+    }
+}
+
+impl PaddleController for TrackingController {
+    fn control(&mut self, view: &View, dt: f32) -> StepInput {
+        self.aim.update(view, dt).unwrap_or_else(|| steer(view, view.ball_center.x + self.aim.error))
+    }
+}
+
+/// Moves the paddle to where the falling ball is going to land (following the bounces off
+/// the side walls), so it keeps up with fast balls too.
+pub struct PredictiveController {
+    aim: Aim
+}
+
+impl PredictiveController {
+    pub fn new(skill: Skill, seed: u64) -> Self {
+        Self { aim: Aim::new(skill, seed) }
+    }
+}
+
+impl PaddleController for PredictiveController {
+    fn control(&mut self, view: &View, dt: f32) -> StepInput {
+        if let Some(input) = self.aim.update(view, dt) {
+            return input;
+        }
+        // a rising ball is followed until it's known where it comes down
+        let landing = predict_landing(view.ball_center, view.ball_velocity, view.ball_radius, view.paddle_position.y - view.ball_radius, &view.walls, view.width);
+        let target = match landing {
+            Some(x) if self.aim.falling => x - aim_offset(view, x) + self.aim.error,
+            _ => view.ball_center.x
+        };
+        steer(view, target)
+    }
+}
+
+// how far from the middle of the paddle a ball landing at the given x has to be hit to bounce
+// towards the target (in a straight line, see simulation::paddle_velocity)
+fn aim_offset(view: &View, landing: f32) -> f32 {
+    let Some(target) = view.target else {
+        return 0.0;
+    };
+    let rise = (view.paddle_position.y - target.y).max(1.0);
+    let slope = (target.x - landing) / rise;
+    let percentage = slope * view.ball_velocity.y.abs() / (INITIAL_BALL_VELOCITY.x * BOUNCE_STRENGTH);
+    percentage.clamp(-MAX_AIM_OFFSET, MAX_AIM_OFFSET) * view.paddle_size.x / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::drops::DropTable;
+    use crate::simulation::Simulation;
+
+    fn view() -> View {
+        View {
+            paddle_position: glm::vec2(350.0, 580.0),
+            paddle_size: glm::vec2(100.0, 20.0),
+            ball_center: glm::vec2(400.0, 300.0),
+            ball_velocity: glm::vec2(0.0, 0.0),
+            ball_radius: 12.5,
+            ball_stuck: true,
+            width: 800.0,
+            walls: Walls::default(),
+            target: None
+        }
+    }
+
+    // level one with the AI steering for the given number of seconds
+    fn play(controller: &mut dyn PaddleController, seconds: u32) -> Simulation {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/levels/one.lvl");
+        let mut simulation = Simulation::new(path.to_string_lossy().as_ref(), 1, DropTable::default(), 7, 800, 600).unwrap();
+        let dt = 1.0 / 120.0;
+        for _ in 0..seconds * 120 {
+            let view = View::of(&simulation.player, &simulation.ball, simulation.width as f32, simulation.level.bounds());
+            let input = controller.control(&view, dt);
+            simulation.step(dt, input);
+        }
+        simulation
+    }
+
+    #[test]
+    fn controller_test_launches_after_delay() {
+        let mut controller = PredictiveController::new(Skill::Normal, 1);
+        assert!(!controller.control(&view(), LAUNCH_DELAY / 2.0).launch);
+        assert!(controller.control(&view(), LAUNCH_DELAY / 2.0).launch);
+    }
+
+    #[test]
+    fn controller_test_tracks_landing() {
+        let mut controller = PredictiveController::new(Skill::Hard, 1);
+        // falling straight down far to the left of the paddle; nothing happens until the AI reacts
+        let view = View { ball_stuck: false, ball_center: glm::vec2(100.0, 300.0), ball_velocity: glm::vec2(0.0, 200.0), ..view() };
+        assert_eq!(controller.control(&view, Skill::Hard.reaction_time()), StepInput::default());
+        let input = controller.control(&view, 0.01);
+        assert!(input.left && !input.right);
+        assert!(controller.aim.error.abs() <= view.paddle_size.x * Skill::Hard.max_error());
+        // right under the aim, the paddle stays put
+        let aim = 100.0 + controller.aim.error;
+        let view = View { paddle_position: glm::vec2(aim - 50.0, 580.0), ..view };
+        let input = controller.control(&view, 0.01);
+        assert!(!input.left && !input.right);
+        // sending the ball to the right means hitting it left of the middle of the paddle
+        let aimed = View { target: Some(glm::vec2(700.0, 100.0)), ..view };
+        assert!(aim_offset(&aimed, 100.0) > 0.0);
+        assert!(controller.control(&aimed, 0.01).left);
+    }
+
+    #[test]
+    fn controller_test_skills() {
+        let mut last = f32::INFINITY;
+        for skill in SKILLS {
+            assert!(skill.max_error() < last, "Better AIs aim closer.");
+            last = skill.max_error();
+        }
+        // the best AI keeps the ball in play on the first level
+        let simulation = play(&mut PredictiveController::new(Skill::Hard, 3), 30);
+        assert_eq!(simulation.lives, 3);
+        assert!(simulation.score.points > 0);
+    }
+}
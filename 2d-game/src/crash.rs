@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::sync::Mutex;
+use learnopengl_shared::filesystem;
+use crate::logging;
+
+// Crash reporter. A panic hook writes the panic message, a backtrace, the GL driver
+// strings, a summary of the game state and the most recent log lines to a file.
+
+// directory the crash reports are written to
+const CRASH_DIR: &str = "userdata/crashes";
+
+static GL_INFO: Mutex<String> = Mutex::new(String::new());
+static STATE_SUMMARY: Mutex<String> = Mutex::new(String::new());
+
+// remembers the GL vendor, renderer and version strings for the crash report
+pub fn set_gl_info(info: String) {
+    *GL_INFO.lock().unwrap_or_else(|e| e.into_inner()) = info;
+}
+
+// remembers what the game was doing; called once per frame
+pub fn set_state_summary(summary: String) {
+    *STATE_SUMMARY.lock().unwrap_or_else(|e| e.into_inner()) = summary;
+}
+
+pub fn format_report(message: &str, backtrace: &str, gl_info: &str, state: &str, log_lines: &[String]) -> String {
+    let mut report = String::new();
+    report.push_str("Breakout crash report\n\n");
+    report.push_str(format!("Panic: {}\n\n", message).as_str());
+    report.push_str(format!("OpenGL: {}\n", if gl_info.is_empty() { "unknown" } else { gl_info }).as_str());
+    report.push_str(format!("Game state: {}\n\n", if state.is_empty() { "unknown" } else { state }).as_str());
+    report.push_str(format!("Recent log ({} lines):\n", log_lines.len()).as_str());
+    for line in log_lines.iter() {
+        report.push_str(line);
+        report.push('\n');
+    }
+    report.push_str("\nBacktrace:\n");
+    report.push_str(backtrace);
+    report
+}
+
+fn write_report(info: &PanicHookInfo) -> std::io::Result<String> {
+    let report = format_report(
+        info.to_string().as_str(),
+        Backtrace::force_capture().to_string().as_str(),
+        GL_INFO.lock().unwrap_or_else(|e| e.into_inner()).as_str(),
+        STATE_SUMMARY.lock().unwrap_or_else(|e| e.into_inner()).as_str(),
+        &logging::recent_lines()
+    );
+    let dir = filesystem::get_path(CRASH_DIR.to_string());
+    fs::create_dir_all(dir.as_str())?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|it| it.as_secs())
+        .unwrap_or(0);
+    let path = format!("{}/crash-{}.txt", dir, timestamp);
+    fs::write(path.as_str(), report)?;
+    Ok(path)
+}
+
+// installs the panic hook; the default hook still prints the panic as usual
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        match write_report(info) {
+            Ok(path) => eprintln!("The game crashed. A crash report was written to {}, please attach it when reporting the bug.", path),
+            Err(e) => eprintln!("The game crashed and the crash report couldn't be written: {}", e)
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_report_test_case() {
+        let lines = vec!["[WARN game] first".to_string(), "[ERROR game] second".to_string()];
+        let report = format_report("boom", "0: main", "Vendor / Renderer / 3.3", "state: Menu", &lines);
+        assert!(report.contains("Panic: boom"));
+        assert!(report.contains("OpenGL: Vendor / Renderer / 3.3"));
+        assert!(report.contains("Game state: state: Menu"));
+        assert!(report.contains("Recent log (2 lines):\n[WARN game] first\n[ERROR game] second\n"));
+        assert!(report.ends_with("Backtrace:\n0: main"));
+        assert!(format_report("boom", "", "", "", &[]).contains("OpenGL: unknown"));
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use serde::Deserialize;
+
+// vertical space taken by a section title and a line of text, and left between sections
+pub const TITLE_HEIGHT: f32 = 36.0;
+pub const LINE_HEIGHT: f32 = 24.0;
+pub const SECTION_GAP: f32 = 30.0;
+// scrolling speed in pixels per second, and the factor holding the fast-forward key applies
+pub const SCROLL_SPEED: f32 = 40.0;
+pub const FAST_SCROLL: f32 = 6.0;
+
+/// A section of the credits file: a title and the lines listed under it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Section {
+    pub title: String,
+    #[serde(default)]
+    pub lines: Vec<String>
+}
+
+#[derive(Deserialize)]
+struct CreditsFile {
+    #[serde(default)]
+    section: Vec<Section>
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LineKind {
+    Title,
+    Text
+}
+
+// a laid out line of the credits
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub text: String,
+    pub kind: LineKind,
+    // offset of the top of the line from the top of the credits
+    pub y: f32
+}
+
+/// Credits laid out line by line and scrolled up the screen.
+pub struct Credits {
+    pub lines: Vec<Line>,
+    // height of all lines
+    pub height: f32,
+    // how far the credits have scrolled up from the bottom of the screen
+    pub scroll: f32
+}
+
+impl Credits {
+    pub fn new(sections: &[Section]) -> Self {
+        let mut lines = Vec::new();
+        let mut y = 0.0;
+        for (i, section) in sections.iter().enumerate() {
+            if i > 0 {
+                y += SECTION_GAP;
+            }
+            lines.push(Line { text: section.title.clone(), kind: LineKind::Title, y });
+            y += TITLE_HEIGHT;
+            for text in section.lines.iter() {
+                lines.push(Line { text: text.clone(), kind: LineKind::Text, y });
+                y += LINE_HEIGHT;
+            }
+        }
+        Self { lines, height: y, scroll: 0.0 }
+    }
+
+    // scrolls by dt seconds at the given multiple of the scrolling speed (negative scrolls back)
+    pub fn update(&mut self, dt: f32, speed: f32) {
+        self.scroll = (self.scroll + SCROLL_SPEED * speed * dt).max(0.0);
+    }
+
+    // whether the last line has left the top of the screen
+    pub fn is_finished(&self, screen_height: f32) -> bool {
+        self.scroll >= self.height + screen_height
+    }
+
+    // the lines which are on the screen, with the screen y of their top
+    pub fn visible(&self, screen_height: f32) -> impl Iterator<Item = (&Line, f32)> {
+        let top = self.scroll - screen_height;
+        // the lines are sorted by their offset, so the ones above the screen are skipped at once
+        let first = self.lines.partition_point(|line| line.y + TITLE_HEIGHT < top);
+        self.lines[first..].iter()
+            .map(move |line| (line, line.y - top))
+            .take_while(move |(_, y)| *y < screen_height)
+    }
+}
+
+pub fn parse(source: &str) -> Result<Vec<Section>, String> {
+    let file: CreditsFile = toml::from_str(source).map_err(|e| e.to_string())?;
+    Ok(file.section)
+}
+
+// loads the credits file, yielding no sections if it is missing or invalid
+pub fn load(path: &str) -> Vec<Section> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            log::error!("CREDITS: Failed to read {}: {}", path, e);
+            return Vec::new();
+        }
+    };
+    parse(source.as_str()).unwrap_or_else(|e| {
+        log::error!("CREDITS: Failed to parse {}: {}", path, e);
+        Vec::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const CREDITS: &str = r#"
+[[section]]
+title = "Breakout"
+lines = ["by someone", "打砖块"]
+
+[[section]]
+title = "Thanks"
+"#;
+
+    #[test]
+    fn credits_test_layout() {
+        let credits = Credits::new(&parse(CREDITS).unwrap());
+        assert_eq!(credits.lines.len(), 4);
+        assert_eq!(credits.lines[2].text, "打砖块");
+        assert_eq!(credits.lines[2].y, TITLE_HEIGHT + LINE_HEIGHT);
+        assert_eq!(credits.lines[3].kind, LineKind::Title);
+        assert_eq!(credits.lines[3].y, TITLE_HEIGHT + LINE_HEIGHT * 2.0 + SECTION_GAP);
+        assert_eq!(credits.height, credits.lines[3].y + TITLE_HEIGHT);
+    }
+
+    #[test]
+    fn credits_test_scroll() {
+        let mut credits = Credits::new(&parse(CREDITS).unwrap());
+        // the credits start below the screen
+        assert_eq!(credits.visible(600.0).count(), 0);
+        credits.update(1.0, 1.0);
+        let visible: Vec<_> = credits.visible(600.0).collect();
+        // the title and the top of the line below it
+        assert_eq!(visible.len(), 2);
+        assert_eq!(visible[0].1, 600.0 - SCROLL_SPEED);
+        credits.update(1.0, -5.0);
+        assert_eq!(credits.scroll, 0.0, "Scrolling back stops at the start.");
+        credits.update(100.0, FAST_SCROLL);
+        assert!(credits.is_finished(600.0));
+        assert_eq!(credits.visible(600.0).count(), 0);
+    }
+
+    #[test]
+    fn credits_test_shipped_file() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/credits.toml");
+        let source = fs::read_to_string(path).expect("The credits file should exist.");
+        let sections = parse(source.as_str()).expect("credits.toml should be valid.");
+        assert!(sections.iter().all(|section| !section.title.is_empty()));
+    }
+}
@@ -0,0 +1,235 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use rand::{Rng, RngCore};
+use crate::brick_type;
+use crate::drops::DropTable;
+use crate::game_level::{validate_tile_data, BrickOverride, LevelFile};
+use crate::walls::Walls;
+
+// Daily challenge. The seed is derived from the date, and the seed alone determines the
+// level and which bricks drop which power-up, so everybody entering the same seed code
+// plays the identical challenge.
+
+// size of the generated level in tiles
+const DAILY_WIDTH: usize = 14;
+const DAILY_HEIGHT: usize = 8;
+// chances of a generated tile being empty or solid; all other tiles are colored bricks
+const EMPTY_CHANCE: f64 = 0.2;
+const SOLID_CHANCE: f64 = 0.06;
+// chance of a colored brick needing two hits
+const TOUGH_CHANCE: f64 = 0.1;
+
+// SplitMix64. The generator of the daily challenge is spelled out here, since StdRng
+// doesn't promise to produce the same numbers across rand releases.
+pub struct DailyRng(u64);
+
+impl DailyRng {
+    pub fn new(seed: u32) -> Self {
+        Self(seed as u64)
+    }
+}
+
+impl RngCore for DailyRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        rand::rand_core::impls::fill_bytes_via_next(self, dst);
+    }
+}
+
+/// The challenge of a single seed: its level and the power-up each brick drops.
+pub struct Daily {
+    pub seed: u32,
+    // the day the seed was derived from (empty for codes entered by hand)
+    pub date: String,
+    pub level: LevelFile,
+    // grid cell -> power-up dropped by the brick there
+    pub drops: HashMap<(usize, usize), String>
+}
+
+impl Daily {
+    pub fn new(seed: u32, drops: &DropTable) -> Self {
+        let mut rng = DailyRng::new(seed);
+        let level = generate_level(&mut rng);
+        let mut schedule = HashMap::new();
+        for (y, row) in level.tiles.iter().enumerate() {
+            for (x, code) in row.iter().enumerate() {
+                if brick_type::is_destructible(*code) {
+                    if let Some(type_str) = drops.roll(&mut rng, *code, 1) {
+                        schedule.insert((x, y), type_str);
+                    }
+                }
+            }
+        }
+        Self {
+            seed,
+            date: String::new(),
+            level,
+            drops: schedule
+        }
+    }
+
+    // today's challenge
+    pub fn today(drops: &DropTable) -> Self {
+        let date = today();
+        let mut daily = Self::new(seed_for_date(date), drops);
+        daily.date = format_date(date);
+        daily
+    }
+
+    pub fn code(&self) -> String {
+        format_code(self.seed)
+    }
+
+    // the power-up dropped by the brick at the given grid cell
+    pub fn drop_at(&self, x: usize, y: usize) -> Option<&str> {
+        self.drops.get(&(x, y)).map(|it| it.as_str())
+    }
+}
+
+// generates a level symmetric around its vertical axis, with tough bricks here and there
+pub fn generate_level<R: Rng + ?Sized>(rng: &mut R) -> LevelFile {
+    let half = DAILY_WIDTH.div_ceil(2);
+    let mut tiles = vec![vec![0u32; DAILY_WIDTH]; DAILY_HEIGHT];
+    let mut overrides = Vec::new();
+    for (y, row) in tiles.iter_mut().enumerate() {
+        for x in 0..half {
+            let code = if rng.random_bool(EMPTY_CHANCE) {
+                0
+            } else if rng.random_bool(SOLID_CHANCE) {
+                1
+            } else {
+                // rows share colors most of the time, so the level shows stripes
+                if rng.random_bool(0.7) { 2 + (y as u32 % 4) } else { rng.random_range(2..=5) }
+            };
+            let tough = code > 1 && rng.random_bool(TOUGH_CHANCE);
+            for x in [x, DAILY_WIDTH - 1 - x] {
+                row[x] = code;
+                if tough && !overrides.iter().any(|o: &BrickOverride| o.x == x && o.y == y) {
+                    overrides.push(BrickOverride { x, y, hp: Some(2), color: None, material: None, checkpoint: false });
+                }
+            }
+        }
+    }
+    // there has to be something to destroy
+    if validate_tile_data(&tiles).is_err() {
+        tiles[0][half - 1] = 2;
+        tiles[0][DAILY_WIDTH - half] = 2;
+    }
+    LevelFile { tiles, overrides, walls: Walls::default(), ambient: None, weather: None }
+}
+
+// (year, month, day) of the given number of days since 1970-01-01 (Howard Hinnant's civil_from_days)
+pub fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+    (year, month, day)
+}
+
+// the current date (UTC, so that everybody gets the same challenge at the same time)
+pub fn today() -> (i32, u32, u32) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|it| it.as_secs())
+        .unwrap_or(0);
+    civil_from_days((secs / 86400) as i64)
+}
+
+pub fn format_date((year, month, day): (i32, u32, u32)) -> String {
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// the seed of a date (32-bit FNV-1a over the formatted date)
+pub fn seed_for_date(date: (i32, u32, u32)) -> u32 {
+    format_date(date).bytes().fold(0x811C9DC5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x01000193))
+}
+
+// formats a seed as a shareable code like 1A2B-3C4D
+pub fn format_code(seed: u32) -> String {
+    format!("{:04X}-{:04X}", seed >> 16, seed & 0xFFFF)
+}
+
+// parses a seed code; the dash is optional and the case doesn't matter
+pub fn parse_code(code: &str) -> Option<u32> {
+    let digits: String = code.trim().chars().filter(|c| *c != '-').collect();
+    if digits.len() != 8 {
+        return None;
+    }
+    u32::from_str_radix(digits.as_str(), 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drops::parse_table;
+
+    #[test]
+    fn daily_test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20740), (2026, 10, 14));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn daily_test_codes() {
+        let seed = seed_for_date((2026, 10, 14));
+        assert_ne!(seed, seed_for_date((2026, 10, 15)));
+        let code = format_code(seed);
+        assert_eq!(code.len(), 9);
+        assert_eq!(parse_code(code.as_str()), Some(seed));
+        assert_eq!(parse_code(code.to_lowercase().replace('-', "").as_str()), Some(seed));
+        assert_eq!(parse_code("1A2B-3C4"), None);
+        assert_eq!(parse_code("XYZW-3C4D"), None);
+    }
+
+    #[test]
+    fn daily_test_same_seed_same_challenge() {
+        let table = parse_table("[default]\nchance = 0.5\nweights = { speed = 1, chaos = 1 }", &["speed", "chaos"]).unwrap();
+        let a = Daily::new(0xC0FFEE, &table);
+        let b = Daily::new(0xC0FFEE, &table);
+        assert_eq!(a.level, b.level);
+        assert_eq!(a.drops, b.drops);
+        assert!(validate_tile_data(&a.level.tiles).is_ok());
+        // mirrored around the vertical axis
+        for row in a.level.tiles.iter() {
+            assert!(row.iter().eq(row.iter().rev()));
+        }
+        // drops only come from destructible bricks
+        assert!(a.drops.keys().all(|(x, y)| a.level.tiles[*y][*x] > 1));
+        assert_ne!(Daily::new(0xC0FFEF, &table).level, a.level);
+    }
+}
@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// speed of the paddle during a dash, in pixels per second
+pub const DASH_SPEED: f32 = 1800.0;
+// how long a dash lasts
+pub const DASH_TIME: f32 = 0.12;
+// time after a dash before the next one can start
+pub const DASH_COOLDOWN: f32 = 0.8;
+// two presses of a direction within this time start a dash
+pub const DOUBLE_TAP_TIME: f32 = 0.25;
+
+/// A short burst of paddle speed, which has to cool down before it can be used again.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Dash {
+    // -1 for left, 1 for right
+    direction: f32,
+    // remaining time of the running dash
+    time: f32,
+    // remaining time until the next dash
+    cooldown: f32
+}
+
+impl Dash {
+    // starts a dash in the given direction (-1 or 1) unless it is still cooling down; returns whether it started
+    pub fn start(&mut self, direction: f32) -> bool {
+        if self.cooldown > 0.0 {
+            return false;
+        }
+        self.direction = direction.signum();
+        self.time = DASH_TIME;
+        self.cooldown = DASH_TIME + DASH_COOLDOWN;
+        true
+    }
+
+    // advances the dash by a step; returns the distance the paddle dashes in that step
+    pub fn update(&mut self, dt: f32) -> f32 {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        if self.time <= 0.0 {
+            return 0.0;
+        }
+        let time = self.time.min(dt);
+        self.time -= time;
+        self.direction * DASH_SPEED * time
+    }
+
+    pub fn is_dashing(&self) -> bool {
+        self.time > 0.0
+    }
+
+    // velocity the dash adds to the paddle
+    pub fn velocity(&self) -> f32 {
+        if self.is_dashing() { self.direction * DASH_SPEED } else { 0.0 }
+    }
+
+    // how far the cooldown is along, from 0 right after a dash to 1 once the next one is ready
+    pub fn readiness(&self) -> f32 {
+        1.0 - self.cooldown / (DASH_TIME + DASH_COOLDOWN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dash_test_distance_and_cooldown() {
+        let mut dash = Dash::default();
+        assert_eq!(dash.readiness(), 1.0);
+        assert!(dash.start(-1.0));
+        assert!(!dash.start(1.0), "A dash can't start while the last one cools down.");
+        let mut distance = 0.0;
+        while dash.is_dashing() {
+            distance += dash.update(0.01);
+        }
+        // the dash covers its full length, even though its time isn't a multiple of the step
+        assert!((distance + DASH_SPEED * DASH_TIME).abs() < 1e-3);
+        assert_eq!(dash.update(0.01), 0.0);
+        assert!(dash.readiness() < 1.0);
+        for _ in 0..100 {
+            dash.update(0.01);
+        }
+        assert_eq!(dash.readiness(), 1.0);
+        assert!(dash.start(1.0));
+        assert_eq!(dash.velocity(), DASH_SPEED);
+    }
+}
@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use glfw::{Action, Context, GlfwReceiver, PWindow, Window, WindowEvent, WindowMode};
+use crate::devtools::Egui;
+use crate::input::InputState;
+
+// size of the inspector window
+pub const DEBUG_WINDOW_WIDTH: u32 = 300;
+pub const DEBUG_WINDOW_HEIGHT: u32 = 560;
+
+// DebugWindow shows the inspector in a second window next to the game. Its context is
+// shared with the game window, but vertex arrays aren't shared, so it paints with an egui
+// of its own.
+pub struct DebugWindow {
+    pub window: PWindow,
+    events: GlfwReceiver<(f64, WindowEvent)>,
+    pub egui: Egui,
+    // input of this window, kept apart from the game's (for the keys of the inspector)
+    pub input: InputState
+}
+
+impl DebugWindow {
+    // opens the window; the context of the game window is current again afterwards
+    pub fn open(main: &mut Window) -> Option<Self> {
+        let (mut window, events) = main.create_shared(DEBUG_WINDOW_WIDTH, DEBUG_WINDOW_HEIGHT, "Breakout - Inspector", WindowMode::Windowed)?;
+        window.set_key_polling(true);
+        window.set_char_polling(true);
+        window.set_cursor_pos_polling(true);
+        window.set_mouse_button_polling(true);
+        window.set_scroll_polling(true);
+        window.make_current();
+        let egui = Egui::new(&mut window);
+        main.make_current();
+        Some(Self {
+            window,
+            events,
+            egui: egui?,
+            input: InputState::new()
+        })
+    }
+
+    // hands the events of the window to egui and its input state
+    pub fn poll(&mut self) {
+        self.egui.fit(&self.window);
+        for (_, event) in glfw::flush_messages(&self.events) {
+            match event {
+                WindowEvent::Key(key, _, Action::Press, _) => self.input.key_down(key),
+                WindowEvent::Key(key, _, Action::Release, _) => self.input.key_up(key),
+                _ => {}
+            }
+            self.egui.handle_event(&event);
+        }
+    }
+
+    // paints the inspector into the window and makes the context of the game window current again
+    pub fn render(&mut self, main: &mut Window) {
+        self.window.make_current();
+        let (width, height) = self.window.get_framebuffer_size();
+        unsafe {
+            gl::Viewport(0, 0, width, height);
+            gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        self.egui.paint();
+        self.window.swap_buffers();
+        main.make_current();
+    }
+
+    // closes the window, deleting the objects of its painter in its own context
+    pub fn close(self, main: &mut Window) {
+        let Self { mut window, egui, .. } = self;
+        window.make_current();
+        egui.destroy();
+        main.make_current();
+    }
+}
@@ -0,0 +1,424 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use egui::{ClippedPrimitive, DragValue, Pos2, RawInput, Slider, TexturesDelta, Ui, ViewportId};
+use glfw::{Action, Key, Modifiers, MouseButton, Window, WindowEvent};
+use crate::ball_object::BallObject;
+use crate::game_object::GameObject;
+use crate::inspect::{Inspect, Value};
+use crate::particle_generator::ParticleGenerator;
+use crate::post_processor::{PostProcessor, TONE_MAPPINGS};
+use crate::power_up::PowerUp;
+
+// The inspector is made of egui windows. Each glfw window showing it has an Egui of its own,
+// which turns the events of the window into egui input and paints with egui_glow into the
+// window's context; the windows are built while the game handles its input and painted
+// when it renders.
+
+/// Game objects shown in the entity list.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Entity {
+    Player,
+    Ball,
+    // index into the power-ups of the game
+    PowerUp(usize)
+}
+
+/// The parts of the running game the inspector changes.
+pub struct World<'a> {
+    pub player: &'a mut GameObject,
+    pub ball: &'a mut BallObject,
+    pub power_ups: &'a mut [PowerUp],
+    pub particles: &'a mut ParticleGenerator,
+    pub effects: &'a mut PostProcessor
+}
+
+// DevTools is an inspector for tuning the running game (toggled with F4).
+pub struct DevTools {
+    pub open: bool,
+    // shown in a window of its own instead of over the game (toggled by a button of the inspector)
+    pub detached: bool,
+    pub selected: Entity
+}
+
+impl DevTools {
+    // constructor
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            detached: false,
+            selected: Entity::Ball
+        }
+    }
+
+    // builds the inspector; over the game the pages are windows of their own, a detached
+    // inspector fills its window with them
+    pub fn show(&mut self, ctx: &egui::Context, world: &mut World) {
+        if self.detached {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    if ui.button("Attach to the game").clicked() {
+                        self.detached = false;
+                    }
+                    ui.collapsing("Entities", |ui| self.entities(ui, world));
+                    ui.collapsing("Particles", |ui| particles(ui, world.particles));
+                    ui.collapsing("Post effects", |ui| effects(ui, world.effects));
+                });
+            });
+            return;
+        }
+        let x = ctx.screen_rect().right() - 260.0;
+        egui::Window::new("Entities").default_pos([x, 10.0]).show(ctx, |ui| {
+            if ui.button("Detach into a window").clicked() {
+                self.detached = true;
+            }
+            self.entities(ui, world);
+        });
+        egui::Window::new("Particles").default_pos([x, 330.0]).show(ctx, |ui| particles(ui, world.particles));
+        egui::Window::new("Post effects").default_pos([x, 470.0]).show(ctx, |ui| effects(ui, world.effects));
+    }
+
+    // the entity list and the properties of the selected entity
+    fn entities(&mut self, ui: &mut Ui, world: &mut World) {
+        self.validate_selection(world.power_ups.len());
+        ui.selectable_value(&mut self.selected, Entity::Player, "Player");
+        ui.selectable_value(&mut self.selected, Entity::Ball, "Ball");
+        for (i, power_up) in world.power_ups.iter().enumerate() {
+            ui.selectable_value(&mut self.selected, Entity::PowerUp(i), format!("Power-up {} ({})", i, power_up.type_str));
+        }
+        ui.separator();
+        let target: &mut dyn Inspect = match self.selected {
+            Entity::Player => world.player,
+            Entity::Ball => world.ball,
+            Entity::PowerUp(i) => &mut world.power_ups[i]
+        };
+        properties(ui, target);
+    }
+
+    // forgets the selected entity if it doesn't exist anymore
+    pub fn validate_selection(&mut self, power_ups: usize) {
+        if let Entity::PowerUp(i) = self.selected {
+            if i >= power_ups {
+                self.selected = Entity::Ball;
+            }
+        }
+    }
+}
+
+impl Default for DevTools {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// editors for all properties of an inspectable object
+fn properties(ui: &mut Ui, target: &mut dyn Inspect) {
+    egui::Grid::new("properties").num_columns(2).show(ui, |ui| {
+        for name in target.property_names() {
+            let Some(mut value) = target.property(name) else {
+                continue;
+            };
+            ui.label(name);
+            // (all components are shown, so they're combined with | instead of ||)
+            let changed = match &mut value {
+                Value::Bool(v) => ui.checkbox(v, "").changed(),
+                Value::Int(v) => ui.add(DragValue::new(v)).changed(),
+                Value::Float(v) => ui.add(DragValue::new(v).speed(0.1)).changed(),
+                Value::Vec2(v) => ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut v.x)).changed() | ui.add(DragValue::new(&mut v.y)).changed()
+                }).inner,
+                Value::Vec3(v) => ui.horizontal(|ui| {
+                    v.iter_mut().fold(false, |changed, component| ui.add(DragValue::new(component).speed(0.01)).changed() | changed)
+                }).inner
+            };
+            if changed {
+                target.set_property(name, value);
+            }
+            ui.end_row();
+        }
+    });
+}
+
+fn particles(ui: &mut Ui, particles: &mut ParticleGenerator) {
+    ui.add(Slider::new(&mut particles.life, 0.1..=3.0).text("Particle life"));
+    ui.add(Slider::new(&mut particles.spread, 0.0..=20.0).text("Spawn spread"));
+    ui.add(Slider::new(&mut particles.velocity_factor, 0.0..=1.0).text("Velocity factor"));
+    ui.add(Slider::new(&mut particles.fade_rate, 0.0..=10.0).text("Fade rate"));
+}
+
+fn effects(ui: &mut Ui, effects: &mut PostProcessor) {
+    ui.checkbox(&mut effects.shake, "Shake");
+    ui.checkbox(&mut effects.chaos, "Chaos");
+    ui.checkbox(&mut effects.confuse, "Confuse");
+    ui.add(Slider::new(&mut effects.shake_intensity, 0.0..=1.0).text("Shake intensity"));
+    ui.add(Slider::new(&mut effects.exposure, 0.1..=4.0).text("Exposure"));
+    egui::ComboBox::from_label("Tone mapping").selected_text(effects.tone_mapping.id()).show_ui(ui, |ui| {
+        for tone_mapping in TONE_MAPPINGS {
+            ui.selectable_value(&mut effects.tone_mapping, tone_mapping, tone_mapping.id());
+        }
+    });
+    ui.add(Slider::new(&mut effects.ghosting.confuse, 0.0..=0.9).text("Confuse ghosting"));
+}
+
+// Egui runs egui in a glfw window: it collects the window's events, runs frames of the
+// windows built by the inspector and paints the last of them.
+pub struct Egui {
+    pub ctx: egui::Context,
+    painter: egui_glow::Painter,
+    events: Vec<egui::Event>,
+    modifiers: egui::Modifiers,
+    // where the cursor is, in points (the screen coordinates of glfw)
+    pointer: Pos2,
+    // size of the window in points and in pixels
+    size: (f32, f32),
+    framebuffer: [u32; 2],
+    start: Instant,
+    // the last frame, waiting to be painted; textures are kept until they're painted
+    primitives: Vec<ClippedPrimitive>,
+    pixels_per_point: f32,
+    textures: TexturesDelta
+}
+
+impl Egui {
+    // sets up the painter in the context of the window, which has to be current
+    // (egui_glow takes the context in an Arc, though it's only used on the GL thread)
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(window: &mut Window) -> Option<Self> {
+        let gl = unsafe { glow::Context::from_loader_function(|name| window.get_proc_address(name) as *const _) };
+        let painter = match egui_glow::Painter::new(Arc::new(gl), "", None) {
+            Ok(painter) => painter,
+            Err(e) => {
+                log::error!("DEVTOOLS: Failed to set up egui: {}", e);
+                return None;
+            }
+        };
+        let mut egui = Self {
+            ctx: egui::Context::default(),
+            painter,
+            events: Vec::new(),
+            modifiers: egui::Modifiers::default(),
+            pointer: Pos2::ZERO,
+            size: (0.0, 0.0),
+            framebuffer: [0, 0],
+            start: Instant::now(),
+            primitives: Vec::new(),
+            pixels_per_point: 1.0,
+            textures: TexturesDelta::default()
+        };
+        egui.fit(window);
+        Some(egui)
+    }
+
+    // follows the size of the window
+    pub fn fit(&mut self, window: &Window) {
+        let (width, height) = window.get_size();
+        let (framebuffer_width, framebuffer_height) = window.get_framebuffer_size();
+        self.size = (width as f32, height as f32);
+        self.framebuffer = [framebuffer_width.max(0) as u32, framebuffer_height.max(0) as u32];
+    }
+
+    // hands an event of the window to egui
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = to_egui(event, &mut self.modifiers, &mut self.pointer) {
+            self.events.push(event);
+        }
+    }
+
+    // runs a frame of the windows built by ui; returns true if egui wants to be painted again
+    // right away (e.g. while it animates)
+    pub fn run(&mut self, ui: impl FnMut(&egui::Context)) -> bool {
+        let mut input = RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(self.size.0, self.size.1))),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            focused: true,
+            ..Default::default()
+        };
+        // the framebuffer can be larger than the window (e.g. on retina displays)
+        let native_pixels_per_point = self.framebuffer[0] as f32 / self.size.0.max(1.0);
+        input.viewports.entry(ViewportId::ROOT).or_default().native_pixels_per_point = Some(native_pixels_per_point);
+        let output = self.ctx.run(input, ui);
+        self.primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+        self.pixels_per_point = output.pixels_per_point;
+        self.textures.append(output.textures_delta);
+        output.viewport_output.get(&ViewportId::ROOT).is_some_and(|it| it.repaint_delay == Duration::ZERO)
+    }
+
+    // whether egui uses the mouse or the keyboard, which the game shouldn't react to then
+    pub fn wants_input(&self) -> bool {
+        self.ctx.wants_pointer_input() || self.ctx.wants_keyboard_input()
+    }
+
+    // paints the last frame over the framebuffer of the window, whose context has to be
+    // current; the viewport has to be set again afterwards
+    pub fn paint(&mut self) {
+        self.painter.paint_and_update_textures(self.framebuffer, self.pixels_per_point, &self.primitives, &self.textures);
+        self.textures.clear();
+        // egui blends premultiplied colors
+        unsafe {
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+
+    // deletes the GPU objects of the painter; the context of the window has to be current
+    pub fn destroy(mut self) {
+        self.painter.destroy();
+    }
+}
+
+fn to_egui_modifiers(modifiers: Modifiers) -> egui::Modifiers {
+    let ctrl = modifiers.contains(Modifiers::Control);
+    let mac_cmd = cfg!(target_os = "macos") && modifiers.contains(Modifiers::Super);
+    egui::Modifiers {
+        alt: modifiers.contains(Modifiers::Alt),
+        ctrl,
+        shift: modifiers.contains(Modifiers::Shift),
+        mac_cmd,
+        command: if cfg!(target_os = "macos") { mac_cmd } else { ctrl }
+    }
+}
+
+// the keys egui uses for navigating and editing text
+fn to_egui_key(key: Key) -> Option<egui::Key> {
+    Some(match key {
+        Key::Up => egui::Key::ArrowUp,
+        Key::Down => egui::Key::ArrowDown,
+        Key::Left => egui::Key::ArrowLeft,
+        Key::Right => egui::Key::ArrowRight,
+        Key::Escape => egui::Key::Escape,
+        Key::Tab => egui::Key::Tab,
+        Key::Backspace => egui::Key::Backspace,
+        Key::Enter | Key::KpEnter => egui::Key::Enter,
+        Key::Space => egui::Key::Space,
+        Key::Delete => egui::Key::Delete,
+        Key::Home => egui::Key::Home,
+        Key::End => egui::Key::End,
+        Key::PageUp => egui::Key::PageUp,
+        Key::PageDown => egui::Key::PageDown,
+        Key::A => egui::Key::A,
+        Key::C => egui::Key::C,
+        Key::V => egui::Key::V,
+        Key::X => egui::Key::X,
+        Key::Z => egui::Key::Z,
+        _ => return None
+    })
+}
+
+// the egui event of a window event, keeping track of the modifiers and the cursor
+fn to_egui(event: &WindowEvent, modifiers: &mut egui::Modifiers, pointer: &mut Pos2) -> Option<egui::Event> {
+    match *event {
+        WindowEvent::CursorPos(x, y) => {
+            *pointer = egui::pos2(x as f32, y as f32);
+            Some(egui::Event::PointerMoved(*pointer))
+        }
+        WindowEvent::MouseButton(button, action, mods) => {
+            *modifiers = to_egui_modifiers(mods);
+            let button = match button {
+                MouseButton::Button1 => egui::PointerButton::Primary,
+                MouseButton::Button2 => egui::PointerButton::Secondary,
+                MouseButton::Button3 => egui::PointerButton::Middle,
+                _ => return None
+            };
+            Some(egui::Event::PointerButton { pos: *pointer, button, pressed: action == Action::Press, modifiers: *modifiers })
+        }
+        WindowEvent::Scroll(x, y) => Some(egui::Event::MouseWheel {
+            unit: egui::MouseWheelUnit::Line,
+            delta: egui::vec2(x as f32, y as f32),
+            modifiers: *modifiers
+        }),
+        WindowEvent::Key(key, _, action, mods) => {
+            *modifiers = to_egui_modifiers(mods);
+            Some(egui::Event::Key {
+                key: to_egui_key(key)?,
+                physical_key: None,
+                pressed: action != Action::Release,
+                repeat: action == Action::Repeat,
+                modifiers: *modifiers
+            })
+        }
+        // shortcuts like ctrl+a aren't text
+        WindowEvent::Char(c) if !c.is_control() && !modifiers.command => Some(egui::Event::Text(c.to_string())),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm as glm;
+    use super::*;
+
+    #[test]
+    fn devtools_test_events() {
+        let mut modifiers = egui::Modifiers::default();
+        let mut pointer = Pos2::ZERO;
+        assert_eq!(to_egui(&WindowEvent::CursorPos(10.0, 20.0), &mut modifiers, &mut pointer), Some(egui::Event::PointerMoved(egui::pos2(10.0, 20.0))));
+        // buttons are pressed where the cursor is
+        assert_eq!(
+            to_egui(&WindowEvent::MouseButton(MouseButton::Button1, Action::Press, Modifiers::empty()), &mut modifiers, &mut pointer),
+            Some(egui::Event::PointerButton { pos: egui::pos2(10.0, 20.0), button: egui::PointerButton::Primary, pressed: true, modifiers })
+        );
+        assert_eq!(to_egui(&WindowEvent::Char('5'), &mut modifiers, &mut pointer), Some(egui::Event::Text("5".to_string())));
+        let event = to_egui(&WindowEvent::Key(Key::Left, 0, Action::Repeat, Modifiers::Shift), &mut modifiers, &mut pointer);
+        assert!(matches!(event, Some(egui::Event::Key { key: egui::Key::ArrowLeft, pressed: true, repeat: true, modifiers: egui::Modifiers { shift: true, .. }, .. })));
+        // keys egui doesn't use are left to the game
+        assert_eq!(to_egui(&WindowEvent::Key(Key::F4, 0, Action::Press, Modifiers::empty()), &mut modifiers, &mut pointer), None);
+    }
+
+    struct Target {
+        position: glm::TVec2<f32>,
+        hp: i64
+    }
+
+    impl Inspect for Target {
+        fn property_names(&self) -> Vec<&'static str> {
+            vec!["position", "hp"]
+        }
+
+        fn property(&self, name: &str) -> Option<Value> {
+            match name {
+                "position" => Some(Value::Vec2(self.position)),
+                "hp" => Some(Value::Int(self.hp)),
+                _ => None
+            }
+        }
+
+        fn set_property(&mut self, name: &str, value: Value) {
+            match (name, value) {
+                ("position", Value::Vec2(v)) => self.position = v,
+                ("hp", Value::Int(v)) => self.hp = v,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn devtools_test_properties() {
+        // a frame of the property editors runs without a window
+        let mut target = Target { position: glm::vec2(1.0, 2.0), hp: 3 };
+        let ctx = egui::Context::default();
+        let _ = ctx.run(RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| properties(ui, &mut target));
+        });
+        let mut tools = DevTools::new();
+        tools.selected = Entity::PowerUp(2);
+        tools.validate_selection(2);
+        assert_eq!(tools.selected, Entity::Ball);
+        assert_eq!(target.position, glm::vec2(1.0, 2.0));
+    }
+}
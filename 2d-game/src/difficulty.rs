@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use serde::{Deserialize, Serialize};
+use crate::ball_object::BallObject;
+
+/// Difficulty preset, setting how quickly the ball speeds up and whether the paddle catches it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard
+}
+
+pub const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+/// How the ball speeds up over a life, as fractions of its speed at launch.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpeedRules {
+    // added with each bounce off the paddle
+    pub paddle_hit: f32,
+    // added with each row of bricks which is cleared
+    pub row_cleared: f32,
+    // the ball never gets faster than this multiple of its launch speed
+    pub cap: f32
+}
+
+impl SpeedRules {
+    // the ball keeps the speed it was launched with
+    pub const CONSTANT: SpeedRules = SpeedRules { paddle_hit: 0.0, row_cleared: 0.0, cap: 1.0 };
+}
+
+impl Difficulty {
+    // position in DIFFICULTIES
+    pub fn index(self) -> usize {
+        DIFFICULTIES.iter().position(|it| *it == self).unwrap()
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard"
+        }
+    }
+
+    pub fn speed_rules(self) -> SpeedRules {
+        match self {
+            Difficulty::Easy => SpeedRules { paddle_hit: 0.005, row_cleared: 0.02, cap: 1.25 },
+            Difficulty::Normal => SpeedRules { paddle_hit: 0.01, row_cleared: 0.04, cap: 1.5 },
+            Difficulty::Hard => SpeedRules { paddle_hit: 0.02, row_cleared: 0.06, cap: 1.8 }
+        }
+    }
+
+    // whether the paddle catches the ball without the Sticky power-up
+    pub fn catches_ball(self) -> bool {
+        self == Difficulty::Easy
+    }
+}
+
+/// The speed-up the ball has gained since it was launched.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BallSpeed {
+    pub rules: SpeedRules,
+    // current speed as a multiple of the launch speed
+    pub multiplier: f32
+}
+
+impl BallSpeed {
+    pub fn new(rules: SpeedRules) -> Self {
+        Self {
+            rules,
+            multiplier: 1.0
+        }
+    }
+
+    // speeds the ball up by the given fraction of its launch speed, up to the cap
+    pub fn increase(&mut self, amount: f32, ball: &mut BallObject) {
+        let multiplier = (self.multiplier + amount).min(self.rules.cap);
+        if multiplier > self.multiplier {
+            ball.game_obj.velocity *= multiplier / self.multiplier;
+            self.multiplier = multiplier;
+        }
+    }
+
+    // keeps a bounce off walls with a restitution above 1 from speeding the ball up past the cap
+    // (or past the speed it had before, if e.g. a power-up already made it faster than that);
+    // `launch_speed` is how fast it was served
+    pub fn limit(&self, launch_speed: f32, before: f32, ball: &mut BallObject) {
+        let max = (launch_speed * self.rules.cap).max(before);
+        let speed = glm::length(&ball.game_obj.velocity);
+        if speed > max {
+            ball.game_obj.velocity *= max / speed;
+        }
+    }
+
+    pub fn paddle_hit(&mut self, ball: &mut BallObject) {
+        self.increase(self.rules.paddle_hit, ball);
+    }
+
+    pub fn rows_cleared(&mut self, rows: usize, ball: &mut BallObject) {
+        self.increase(self.rules.row_cleared * rows as f32, ball);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::Texture2D;
+
+    #[test]
+    fn ball_speed_test_capped() {
+        let mut ball = BallObject::new_ex(glm::vec2(0.0, 0.0), 10.0, glm::vec2(100.0, -200.0), Texture2D::empty());
+        let mut speed = BallSpeed::new(SpeedRules { paddle_hit: 0.1, row_cleared: 0.25, cap: 1.5 });
+        speed.paddle_hit(&mut ball);
+        assert!((speed.multiplier - 1.1).abs() < 1e-6);
+        assert!((ball.game_obj.velocity - glm::vec2(110.0, -220.0)).norm() < 1e-3);
+        speed.rows_cleared(2, &mut ball);
+        assert_eq!(speed.multiplier, 1.5);
+        assert!((ball.game_obj.velocity - glm::vec2(150.0, -300.0)).norm() < 1e-3);
+        // no faster than the cap
+        speed.paddle_hit(&mut ball);
+        assert_eq!(speed.multiplier, 1.5);
+        assert!((ball.game_obj.velocity - glm::vec2(150.0, -300.0)).norm() < 1e-3);
+    }
+
+    #[test]
+    fn ball_speed_test_limit() {
+        let mut ball = BallObject::new_ex(glm::vec2(0.0, 0.0), 10.0, glm::vec2(300.0, -400.0), Texture2D::empty());
+        let speed = BallSpeed::new(SpeedRules { paddle_hit: 0.1, row_cleared: 0.25, cap: 1.5 });
+        // served at 200, so no faster than 300
+        speed.limit(200.0, 250.0, &mut ball);
+        assert!((ball.game_obj.velocity - glm::vec2(180.0, -240.0)).norm() < 1e-3);
+        speed.limit(400.0, 250.0, &mut ball);
+        assert!((ball.game_obj.velocity - glm::vec2(180.0, -240.0)).norm() < 1e-3);
+        // already faster than the cap before the bounce
+        speed.limit(100.0, 200.0, &mut ball);
+        assert!((ball.game_obj.velocity - glm::vec2(120.0, -160.0)).norm() < 1e-3);
+    }
+
+    #[test]
+    fn ball_speed_test_constant() {
+        let mut ball = BallObject::new_ex(glm::vec2(0.0, 0.0), 10.0, glm::vec2(100.0, -200.0), Texture2D::empty());
+        let mut speed = BallSpeed::new(SpeedRules::CONSTANT);
+        speed.paddle_hit(&mut ball);
+        speed.rows_cleared(3, &mut ball);
+        assert_eq!(speed.multiplier, 1.0);
+        assert_eq!(ball.game_obj.velocity, glm::vec2(100.0, -200.0));
+    }
+
+    #[test]
+    fn difficulty_test_rules() {
+        for pair in DIFFICULTIES.windows(2) {
+            let (easier, harder) = (pair[0].speed_rules(), pair[1].speed_rules());
+            assert!(easier.paddle_hit < harder.paddle_hit && easier.row_cleared < harder.row_cleared && easier.cap < harder.cap);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use glfw::{Glfw, Window, WindowMode};
+use serde::{Deserialize, Serialize};
+
+/// How the game window is shown.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    // exclusive fullscreen in the video mode of the primary monitor
+    Fullscreen,
+    // an undecorated window covering the primary monitor
+    Borderless
+}
+
+pub const DISPLAY_MODES: [DisplayMode; 3] = [DisplayMode::Windowed, DisplayMode::Fullscreen, DisplayMode::Borderless];
+
+// the UI is never scaled up by more than this to keep up with the content scale of the monitor
+const MAX_UI_SCALE_FACTOR: f32 = 2.0;
+
+impl DisplayMode {
+    // position in DISPLAY_MODES
+    pub fn index(self) -> usize {
+        DISPLAY_MODES.iter().position(|it| *it == self).unwrap()
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            DisplayMode::Windowed => "windowed",
+            DisplayMode::Fullscreen => "fullscreen",
+            DisplayMode::Borderless => "borderless"
+        }
+    }
+}
+
+// Display switches the game window between the display modes, remembering where the
+// window was while it was windowed.
+pub struct Display {
+    pub mode: DisplayMode,
+    // position and size of the window in windowed mode
+    windowed: (i32, i32, i32, i32)
+}
+
+impl Display {
+    // constructor, for a window which is still windowed
+    pub fn new(window: &Window) -> Self {
+        let ((x, y), (width, height)) = (window.get_pos(), window.get_size());
+        Self {
+            mode: DisplayMode::Windowed,
+            windowed: (x, y, width, height)
+        }
+    }
+
+    // switches the window to a display mode (if it isn't in it already), returning whether it did
+    pub fn apply(&mut self, glfw: &mut Glfw, window: &mut Window, mode: DisplayMode) -> bool {
+        if mode == self.mode {
+            return false;
+        }
+        if self.mode == DisplayMode::Windowed {
+            let ((x, y), (width, height)) = (window.get_pos(), window.get_size());
+            self.windowed = (x, y, width, height);
+        }
+        self.mode = mode;
+        if mode == DisplayMode::Windowed {
+            let (x, y, width, height) = self.windowed;
+            window.set_decorated(true);
+            window.set_monitor(WindowMode::Windowed, x, y, width as _, height as _, None);
+            return true;
+        }
+        glfw.with_primary_monitor(|_, monitor| {
+            let Some((monitor, video_mode)) = monitor.and_then(|it| it.get_video_mode().map(|mode| (it, mode))) else {
+                log::error!("DISPLAY: No monitor to switch to {} mode", mode.id());
+                return;
+            };
+            if mode == DisplayMode::Fullscreen {
+                window.set_monitor(WindowMode::FullScreen(monitor), 0, 0, video_mode.width, video_mode.height, Some(video_mode.refresh_rate));
+            } else {
+                let (x, y) = monitor.get_pos();
+                window.set_decorated(false);
+                window.set_monitor(WindowMode::Windowed, x, y, video_mode.width, video_mode.height, None);
+            }
+        });
+        true
+    }
+}
+
+// factor the UI is scaled by so that it isn't smaller than intended on HiDPI monitors: the
+// UI is laid out for the game shown at its own size in screen coordinates, which is
+// game_height * content_scale framebuffer pixels tall
+pub fn ui_scale_factor(viewport_height: i32, game_height: u32, content_scale: f32) -> f32 {
+    (game_height as f32 * content_scale / viewport_height.max(1) as f32).clamp(1.0, MAX_UI_SCALE_FACTOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_test_ui_scale_factor() {
+        assert_eq!(ui_scale_factor(600, 600, 1.0), 1.0);
+        // a 2x monitor showing the game at 600 pixels (300 points) doubles the UI
+        assert_eq!(ui_scale_factor(600, 600, 2.0), 2.0);
+        assert_eq!(ui_scale_factor(900, 600, 2.0), 1.0 + 1.0 / 3.0);
+        // the UI isn't shrunk when the game is shown larger
+        assert_eq!(ui_scale_factor(1200, 600, 1.5), 1.0);
+        assert_eq!(ui_scale_factor(100, 600, 3.0), MAX_UI_SCALE_FACTOR);
+    }
+}
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use rand::Rng;
+use serde::Deserialize;
+use crate::brick_type;
+
+/// Drop settings of a brick type or level. Unset values are taken from the
+/// rule below it (default < drops of the brick type < brick type < level); weights are merged per power-up,
+/// so a weight of 0 removes a power-up from the table.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct DropRule {
+    // probability of a destroyed brick dropping anything
+    #[serde(default)]
+    pub chance: Option<f32>,
+    // relative weight of each power-up type once a drop happens
+    #[serde(default)]
+    pub weights: BTreeMap<String, u32>
+}
+
+/// Power-up drop table, keyed by tile code (`[bricks.N]`) and 1-based level number (`[levels.N]`).
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct DropTable {
+    #[serde(default)]
+    pub default: DropRule,
+    #[serde(default)]
+    pub bricks: HashMap<String, DropRule>,
+    #[serde(default)]
+    pub levels: HashMap<String, DropRule>
+}
+
+impl DropRule {
+    fn apply(&mut self, other: &DropRule) {
+        if other.chance.is_some() {
+            self.chance = other.chance;
+        }
+        for (name, weight) in other.weights.iter() {
+            self.weights.insert(name.clone(), *weight);
+        }
+    }
+}
+
+impl DropTable {
+    // the rule applying to a brick with the given tile code in the given level
+    pub fn resolve(&self, tile_code: u32, level: usize) -> DropRule {
+        let mut rule = self.default.clone();
+        if let Some(drops) = brick_type::get(tile_code).and_then(|it| it.drops) {
+            rule.apply(&drops);
+        }
+        if let Some(brick) = self.bricks.get(&tile_code.to_string()) {
+            rule.apply(brick);
+        }
+        if let Some(level) = self.levels.get(&level.to_string()) {
+            rule.apply(level);
+        }
+        rule
+    }
+
+    // rolls the drop of a destroyed brick, returning the power-up type to spawn (if any)
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R, tile_code: u32, level: usize) -> Option<String> {
+        let rule = self.resolve(tile_code, level);
+        let total: u32 = rule.weights.values().sum();
+        if total == 0 || !rng.random_bool(rule.chance.unwrap_or(0.0).clamp(0.0, 1.0) as f64) {
+            return None;
+        }
+        let mut pick = rng.random_range(0..total);
+        for (name, weight) in rule.weights.iter() {
+            if pick < *weight {
+                return Some(name.clone());
+            }
+            pick -= weight;
+        }
+        None
+    }
+}
+
+// parses a drop table; every power-up named in it has to be one of `known`
+pub fn parse_table(source: &str, known: &[&str]) -> Result<DropTable, String> {
+    let table: DropTable = toml::from_str(source).map_err(|e| e.to_string())?;
+    let default_name = "default".to_string();
+    let rules = std::iter::once((&default_name, &table.default))
+        .chain(table.bricks.iter())
+        .chain(table.levels.iter());
+    for (name, rule) in rules {
+        if let Some(chance) = rule.chance {
+            if !(0.0..=1.0).contains(&chance) {
+                return Err(format!("chance of {} is not between 0 and 1", name));
+            }
+        }
+        if let Some(unknown) = rule.weights.keys().find(|it| !known.contains(&it.as_str())) {
+            return Err(format!("unknown power-up \"{}\" in {}", unknown, name));
+        }
+    }
+    Ok(table)
+}
+
+// loads a drop table, yielding an empty one (no drops) if it is missing or invalid
+pub fn load_table(path: &str, known: &[&str]) -> DropTable {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            log::error!("DROPS: Failed to read {}: {}", path, e);
+            return DropTable::default();
+        }
+    };
+    parse_table(source.as_str(), known).unwrap_or_else(|e| {
+        log::error!("DROPS: Failed to parse {}: {}", path, e);
+        DropTable::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const KNOWN: [&str; 3] = ["speed", "sticky", "chaos"];
+
+    const TABLE: &str = r#"
+[default]
+chance = 0.5
+weights = { speed = 1, sticky = 1 }
+
+[bricks.5]
+chance = 1.0
+
+[levels.2]
+weights = { sticky = 0, chaos = 3 }
+"#;
+
+    #[test]
+    fn drops_test_resolve_layers_rules() {
+        let table = parse_table(TABLE, &KNOWN).unwrap();
+        let rule = table.resolve(2, 1);
+        assert_eq!(rule.chance, Some(0.5));
+        assert_eq!(rule.weights.len(), 2);
+        let rule = table.resolve(5, 2);
+        assert_eq!(rule.chance, Some(1.0));
+        assert_eq!(rule.weights.get("sticky"), Some(&0));
+        assert_eq!(rule.weights.get("chaos"), Some(&3));
+    }
+
+    #[test]
+    fn drops_test_roll() {
+        let table = parse_table(TABLE, &KNOWN).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        // a certain drop with sticky disabled only yields speed or chaos
+        for _ in 0..100 {
+            let drop = table.roll(&mut rng, 5, 2).unwrap();
+            assert!(drop == "speed" || drop == "chaos");
+        }
+        let never = parse_table("[default]\nchance = 0.0\nweights = { speed = 1 }", &KNOWN).unwrap();
+        assert!((0..100).all(|_| never.roll(&mut rng, 2, 1).is_none()));
+    }
+
+    #[test]
+    fn drops_test_invalid_table() {
+        assert!(parse_table("[default]\nchance = 1.5", &KNOWN).is_err());
+        assert!(parse_table("[levels.1]\nweights = { shield = 1 }", &KNOWN).is_err());
+    }
+
+    #[test]
+    fn drops_test_shipped_table() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/levels/drops.toml");
+        let source = fs::read_to_string(path).expect("The drop table should exist.");
+        let known = ["speed", "sticky", "pass-through", "pad-size-increase", "confuse", "chaos"];
+        if let Err(e) = parse_table(source.as_str(), &known) {
+            panic!("drops.toml must be a valid drop table: {}", e);
+        }
+    }
+}
@@ -0,0 +1,326 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use crate::brick_type;
+use crate::game_level::{BrickOverride, LevelFile};
+
+// Undo/redo support of the level editor. Every modification of the edited level is
+// expressed as an EditCommand which knows how to apply and how to revert itself.
+
+// the amount of steps which can be undone
+pub const HISTORY_LIMIT: usize = 256;
+
+/// Everything stored for a single grid cell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellState {
+    pub code: u32,
+    pub brick_override: Option<BrickOverride>
+}
+
+pub fn cell_state(level: &LevelFile, x: usize, y: usize) -> CellState {
+    CellState {
+        code: level.tiles[y][x],
+        brick_override: level.overrides.iter().find(|o| o.x == x && o.y == y).cloned()
+    }
+}
+
+pub fn set_cell_state(level: &mut LevelFile, x: usize, y: usize, state: &CellState) {
+    level.tiles[y][x] = state.code;
+    level.overrides.retain(|o| o.x != x || o.y != y);
+    if let Some(o) = state.brick_override.as_ref() {
+        level.overrides.push(o.clone());
+    }
+}
+
+/// A reversible modification of the edited level.
+pub trait EditCommand {
+    fn apply(&self, level: &mut LevelFile);
+    fn revert(&self, level: &mut LevelFile);
+    // short description, shown in the editor status line
+    fn name(&self) -> &str;
+}
+
+/// Changes the contents of a set of cells; used for placing, erasing, filling and pasting.
+pub struct SetCells {
+    name: String,
+    // (x, y, state before, state after)
+    changes: Vec<(usize, usize, CellState, CellState)>
+}
+
+impl SetCells {
+    // records the given cell contents against the current level, returns None if nothing would change
+    pub fn new(
+        name: &str,
+        level: &LevelFile,
+        cells: impl IntoIterator<Item = (usize, usize, CellState)>
+    ) -> Option<Self> {
+        let mut changes = Vec::new();
+        for (x, y, mut after) in cells {
+            if y >= level.tiles.len() || x >= level.tiles[y].len() {
+                continue;
+            }
+            // overrides only apply to destructible bricks
+            if !brick_type::is_destructible(after.code) {
+                after.brick_override = None;
+            }
+            if let Some(o) = after.brick_override.as_mut() {
+                o.x = x;
+                o.y = y;
+            }
+            let before = cell_state(level, x, y);
+            if before != after {
+                changes.push((x, y, before, after));
+            }
+        }
+        if changes.is_empty() {
+            None
+        } else {
+            Some(Self { name: name.to_string(), changes })
+        }
+    }
+}
+
+impl EditCommand for SetCells {
+    fn apply(&self, level: &mut LevelFile) {
+        for (x, y, _, after) in self.changes.iter() {
+            set_cell_state(level, *x, *y, after);
+        }
+    }
+
+    fn revert(&self, level: &mut LevelFile) {
+        for (x, y, before, _) in self.changes.iter().rev() {
+            set_cell_state(level, *x, *y, before);
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+// color override of a brick; None means the default color of its tile code
+type BrickColor = Option<[f32; 3]>;
+
+/// Recolors bricks with a color of the editor palette (stored as per-brick color overrides).
+pub struct PaletteChange {
+    // (x, y, color before, color after)
+    changes: Vec<(usize, usize, BrickColor, BrickColor)>
+}
+
+impl PaletteChange {
+    // returns None if none of the cells is a destructible brick or all already have the color
+    pub fn new(
+        level: &LevelFile,
+        cells: impl IntoIterator<Item = (usize, usize)>,
+        color: Option<[f32; 3]>
+    ) -> Option<Self> {
+        let mut changes = Vec::new();
+        for (x, y) in cells {
+            if level.tiles.get(y).and_then(|row| row.get(x)).is_none_or(|code| !brick_type::is_destructible(*code)) {
+                continue;
+            }
+            let before = cell_state(level, x, y).brick_override.and_then(|o| o.color);
+            if before != color {
+                changes.push((x, y, before, color));
+            }
+        }
+        if changes.is_empty() {
+            None
+        } else {
+            Some(Self { changes })
+        }
+    }
+
+    fn set_color(level: &mut LevelFile, x: usize, y: usize, color: Option<[f32; 3]>) {
+        let mut state = cell_state(level, x, y);
+        let mut o = state.brick_override.unwrap_or(BrickOverride { x, y, hp: None, color: None, material: None, checkpoint: false });
+        o.color = color;
+        // drop overrides which don't override anything anymore
+        state.brick_override = if o.hp.is_none() && o.color.is_none() && o.material.is_none() && !o.checkpoint { None } else { Some(o) };
+        set_cell_state(level, x, y, &state);
+    }
+}
+
+impl EditCommand for PaletteChange {
+    fn apply(&self, level: &mut LevelFile) {
+        for (x, y, _, after) in self.changes.iter() {
+            Self::set_color(level, *x, *y, *after);
+        }
+    }
+
+    fn revert(&self, level: &mut LevelFile) {
+        for (x, y, before, _) in self.changes.iter().rev() {
+            Self::set_color(level, *x, *y, *before);
+        }
+    }
+
+    fn name(&self) -> &str {
+        "palette change"
+    }
+}
+
+/// Keeps the executed commands so they can be undone and redone.
+pub struct History {
+    undo_stack: VecDeque<Box<dyn EditCommand>>,
+    redo_stack: Vec<Box<dyn EditCommand>>,
+    limit: usize
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::new_ex(HISTORY_LIMIT)
+    }
+
+    pub fn new_ex(limit: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            limit
+        }
+    }
+
+    // applies the command and records it; anything undone before can't be redone anymore
+    pub fn execute(&mut self, command: Box<dyn EditCommand>, level: &mut LevelFile) {
+        command.apply(level);
+        self.undo_stack.push_back(command);
+        if self.undo_stack.len() > self.limit {
+            self.undo_stack.pop_front();
+        }
+        self.redo_stack.clear();
+    }
+
+    // reverts the last command, returns its name if there was one
+    pub fn undo(&mut self, level: &mut LevelFile) -> Option<String> {
+        let command = self.undo_stack.pop_back()?;
+        command.revert(level);
+        let name = command.name().to_string();
+        self.redo_stack.push(command);
+        Some(name)
+    }
+
+    // re-applies the last undone command, returns its name if there was one
+    pub fn redo(&mut self, level: &mut LevelFile) -> Option<String> {
+        let command = self.redo_stack.pop()?;
+        command.apply(level);
+        let name = command.name().to_string();
+        self.undo_stack.push_back(command);
+        Some(name)
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo_stack.len()
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walls::Walls;
+
+    fn sample_level() -> LevelFile {
+        LevelFile {
+            tiles: vec![vec![2, 2, 2], vec![0, 1, 0]],
+            overrides: vec![BrickOverride { x: 0, y: 0, hp: Some(3), color: None, material: None, checkpoint: false }],
+            walls: Walls::default(),
+            ambient: None,
+            weather: None
+        }
+    }
+
+    fn place(level: &LevelFile, x: usize, y: usize, code: u32) -> Box<dyn EditCommand> {
+        Box::new(SetCells::new("place", level, [(x, y, CellState { code, brick_override: None })]).unwrap())
+    }
+
+    #[test]
+    fn history_test_undo_redo() {
+        let mut level = sample_level();
+        let original = sample_level();
+        let mut history = History::new();
+        let command = place(&level, 0, 0, 0);
+        history.execute(command, &mut level);
+        assert_eq!(level.tiles[0][0], 0);
+        assert!(level.overrides.is_empty(), "Erasing a brick must remove its override.");
+
+        assert_eq!(history.undo(&mut level), Some("place".to_string()));
+        assert_eq!(level, original);
+        assert_eq!(history.redo(&mut level), Some("place".to_string()));
+        assert_eq!(level.tiles[0][0], 0);
+        assert_eq!(history.redo(&mut level), None);
+    }
+
+    #[test]
+    fn history_test_new_command_clears_redo() {
+        let mut level = sample_level();
+        let mut history = History::new();
+        let command = place(&level, 2, 1, 3);
+        history.execute(command, &mut level);
+        history.undo(&mut level);
+        assert_eq!(history.redo_len(), 1);
+        let command = place(&level, 0, 1, 4);
+        history.execute(command, &mut level);
+        assert_eq!(history.redo_len(), 0);
+    }
+
+    #[test]
+    fn history_test_limit() {
+        let mut level = LevelFile { tiles: vec![vec![0; 200]], overrides: Vec::new(), walls: Walls::default(), ambient: None, weather: None };
+        let mut history = History::new();
+        for x in 0..200 {
+            let command = place(&level, x, 0, 2);
+            history.execute(command, &mut level);
+            let command = place(&level, x, 0, 3);
+            history.execute(command, &mut level);
+        }
+        assert_eq!(history.undo_len(), HISTORY_LIMIT);
+        let mut undone = 0;
+        while history.undo(&mut level).is_some() {
+            undone += 1;
+        }
+        assert!(undone >= 100, "At least 100 steps must be undoable.");
+    }
+
+    #[test]
+    fn set_cells_test_no_change() {
+        let level = sample_level();
+        let unchanged = SetCells::new("place", &level, [(1, 1, CellState { code: 1, brick_override: None })]);
+        assert!(unchanged.is_none());
+    }
+
+    #[test]
+    fn palette_change_test() {
+        let mut level = sample_level();
+        let original = sample_level();
+        let mut history = History::new();
+        let command = PaletteChange::new(&level, [(0, 0), (1, 0), (1, 1)], Some([1.0, 0.0, 0.0])).unwrap();
+        history.execute(Box::new(command), &mut level);
+        let colored = level.overrides.iter().filter(|o| o.color == Some([1.0, 0.0, 0.0])).count();
+        assert_eq!(colored, 2, "Only destructible bricks can be recolored.");
+        assert_eq!(cell_state(&level, 0, 0).brick_override.unwrap().hp, Some(3));
+        history.undo(&mut level);
+        assert_eq!(level, original);
+    }
+}
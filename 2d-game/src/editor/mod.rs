@@ -0,0 +1,445 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::fs;
+use std::path::Path;
+use glfw::Key;
+use crate::editor::history::{CellState, EditCommand, History, PaletteChange, SetCells};
+use crate::editor::tools::{EditorTool, Mirror};
+use crate::brick_type;
+use crate::game_level;
+use crate::input::InputState;
+use crate::game_level::{GameLevel, LevelError, LevelFile};
+use crate::sprite_renderer::SpriteRenderer;
+use crate::resource_manager;
+use crate::res_name::res_name;
+use crate::texture::Texture2D;
+
+pub mod history;
+pub mod tools;
+
+// colors which can be applied to bricks (None keeps the default color of the tile code)
+pub const PALETTE: [Option<[f32; 3]>; 7] = [
+    None,
+    Some([1.0, 1.0, 1.0]),
+    Some([0.9, 0.2, 0.2]),
+    Some([0.6, 0.3, 0.9]),
+    Some([0.2, 0.8, 0.8]),
+    Some([1.0, 0.8, 0.1]),
+    Some([0.4, 0.4, 0.4])
+];
+
+/// A rectangular region of grid cells, both corners inclusive.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Selection {
+    pub min: (usize, usize),
+    pub max: (usize, usize)
+}
+
+impl Selection {
+    pub fn new(a: (usize, usize), b: (usize, usize)) -> Self {
+        Self {
+            min: (a.0.min(b.0), a.1.min(b.1)),
+            max: (a.0.max(b.0), a.1.max(b.1))
+        }
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.min.1..=self.max.1).flat_map(move |y| (self.min.0..=self.max.0).map(move |x| (x, y)))
+    }
+}
+
+// LevelEditor edits the tile grid of a level. All modifications go through the
+// undo/redo history; the level is rendered through a preview GameLevel which is
+// rebuilt whenever the grid changes.
+pub struct LevelEditor {
+    // the level being edited
+    pub level: LevelFile,
+    // file the level is saved to
+    pub file: String,
+    pub cursor: (usize, usize),
+    // tile code placed by the editor
+    pub brush: u32,
+    pub palette_index: usize,
+    pub tool: EditorTool,
+    pub mirror: Mirror,
+    pub show_grid: bool,
+    // the corner of the marquee selection opposite to the cursor, if any
+    anchor: Option<(usize, usize)>,
+    clipboard: Option<Vec<Vec<CellState>>>,
+    history: History,
+
+    // render state
+    preview: GameLevel,
+    level_width: u32,
+    level_height: u32,
+    white: Texture2D
+}
+
+impl LevelEditor {
+    // constructor
+    pub fn new(level: LevelFile, file: String, level_width: u32, level_height: u32) -> Self {
+        let white = resource_manager::get_texture(res_name!("white"));
+        let mut result = Self {
+            level,
+            file,
+            cursor: (0, 0),
+            brush: 2,
+            palette_index: 0,
+            tool: EditorTool::Brush,
+            mirror: Mirror::default(),
+            show_grid: true,
+            anchor: None,
+            clipboard: None,
+            history: History::new(),
+            preview: GameLevel::new(),
+            level_width,
+            level_height,
+            white
+        };
+        result.refresh();
+        result
+    }
+
+    pub fn columns(&self) -> usize {
+        self.level.tiles[0].len()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.level.tiles.len()
+    }
+
+    pub fn selection(&self) -> Selection {
+        Selection::new(self.anchor.unwrap_or(self.cursor), self.cursor)
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.history.undo_len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.history.redo_len()
+    }
+
+    // handles the editor's keyboard shortcuts
+    pub fn process_input(&mut self, input: &InputState) {
+        let ctrl = input.ctrl();
+        let shift = input.shift();
+        for (key, dx, dy) in [(Key::Left, -1, 0), (Key::Right, 1, 0), (Key::Up, 0, -1), (Key::Down, 0, 1)] {
+            if input.pressed_repeat(key) {
+                self.move_cursor(dx, dy, shift);
+            }
+        }
+        // number keys pick the brush of the brick types with single-digit tile codes
+        for (key, code) in [(Key::Num0, 0), (Key::Num1, 1), (Key::Num2, 2), (Key::Num3, 3), (Key::Num4, 4), (Key::Num5, 5), (Key::Num6, 6), (Key::Num7, 7), (Key::Num8, 8), (Key::Num9, 9)] {
+            if input.just_pressed(key) && brick_type::is_known(code) {
+                self.brush = code;
+            }
+        }
+        if ctrl {
+            if input.just_pressed(Key::Z) {
+                if shift { self.redo(); } else { self.undo(); }
+            }
+            if input.just_pressed(Key::Y) {
+                self.redo();
+            }
+            if input.just_pressed(Key::C) {
+                self.copy();
+            }
+            if input.just_pressed(Key::X) {
+                self.copy();
+                self.erase();
+            }
+            if input.just_pressed(Key::V) {
+                self.paste();
+            }
+            if input.just_pressed(Key::S) {
+                if let Err(e) = self.save() {
+                    log::error!("EDITOR: Failed to save level {}: {}", self.file, e);
+                }
+            }
+            return;
+        }
+        if input.just_pressed(Key::Space) {
+            self.apply_tool();
+        }
+        if input.just_pressed(Key::T) {
+            self.tool = self.tool.next();
+        }
+        if input.just_pressed(Key::H) {
+            self.mirror.horizontal = !self.mirror.horizontal;
+        }
+        if input.just_pressed(Key::V) {
+            self.mirror.vertical = !self.mirror.vertical;
+        }
+        if input.just_pressed(Key::G) {
+            self.show_grid = !self.show_grid;
+        }
+        if input.just_pressed(Key::Delete) || input.just_pressed(Key::Backspace) {
+            self.erase();
+        }
+        if input.just_pressed(Key::F) {
+            self.fill_selection();
+        }
+        if input.just_pressed(Key::P) {
+            self.palette_index = (self.palette_index + 1) % PALETTE.len();
+        }
+        if input.just_pressed(Key::C) {
+            self.apply_palette_color();
+        }
+    }
+
+    // moves the cursor, extending the marquee selection while `extend` is set
+    pub fn move_cursor(&mut self, dx: i32, dy: i32, extend: bool) {
+        if extend {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        let x = (self.cursor.0 as i32 + dx).clamp(0, self.columns() as i32 - 1);
+        let y = (self.cursor.1 as i32 + dy).clamp(0, self.rows() as i32 - 1);
+        self.cursor = (x as usize, y as usize);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.anchor = None;
+    }
+
+    // cells the current tool would paint, including their mirrored counterparts
+    pub fn pending_cells(&self) -> Vec<(usize, usize)> {
+        let cells = match self.tool {
+            EditorTool::Brush => vec![self.cursor],
+            EditorTool::Rectangle => self.selection().cells().collect(),
+            EditorTool::FloodFill => tools::flood_region(&self.level, self.cursor),
+            EditorTool::Eyedropper => return vec![self.cursor]
+        };
+        self.mirror.apply(&cells, self.columns(), self.rows())
+    }
+
+    // applies the current tool at the cursor
+    pub fn apply_tool(&mut self) {
+        if self.tool == EditorTool::Eyedropper {
+            self.pick();
+            return;
+        }
+        let cells = self.pending_cells();
+        self.paint(self.tool.name(), &cells, self.brush);
+    }
+
+    // places the brush at the cursor
+    pub fn place(&mut self) {
+        let cells = self.mirror.apply(&[self.cursor], self.columns(), self.rows());
+        self.paint("place", &cells, self.brush);
+    }
+
+    // empties all selected cells
+    pub fn erase(&mut self) {
+        let cells: Vec<_> = self.selection().cells().collect();
+        let cells = self.mirror.apply(&cells, self.columns(), self.rows());
+        self.paint("erase", &cells, 0);
+    }
+
+    // fills all selected cells with the brush
+    pub fn fill_selection(&mut self) {
+        let cells: Vec<_> = self.selection().cells().collect();
+        let cells = self.mirror.apply(&cells, self.columns(), self.rows());
+        self.paint("fill", &cells, self.brush);
+    }
+
+    // takes over the tile code and color under the cursor
+    pub fn pick(&mut self) {
+        let state = history::cell_state(&self.level, self.cursor.0, self.cursor.1);
+        self.brush = state.code;
+        let color = state.brick_override.and_then(|o| o.color);
+        self.palette_index = PALETTE.iter().position(|c| *c == color).unwrap_or(0);
+    }
+
+    fn paint(&mut self, name: &str, cells: &[(usize, usize)], code: u32) {
+        let cells: Vec<_> = cells.iter()
+            .map(|&(x, y)| (x, y, CellState { code, brick_override: None }))
+            .collect();
+        let command = SetCells::new(name, &self.level, cells);
+        self.execute(command);
+    }
+
+    // recolors the selected bricks with the current palette color
+    pub fn apply_palette_color(&mut self) {
+        let cells: Vec<_> = self.selection().cells().collect();
+        let command = PaletteChange::new(&self.level, cells, PALETTE[self.palette_index]);
+        self.execute(command);
+    }
+
+    pub fn copy(&mut self) {
+        let selection = self.selection();
+        let region = (selection.min.1..=selection.max.1)
+            .map(|y| (selection.min.0..=selection.max.0).map(|x| history::cell_state(&self.level, x, y)).collect())
+            .collect();
+        self.clipboard = Some(region);
+    }
+
+    // pastes the clipboard with its top-left corner at the cursor, clipped to the level
+    pub fn paste(&mut self) {
+        let Some(region) = self.clipboard.as_ref() else {
+            return;
+        };
+        let (cx, cy) = self.cursor;
+        let cells: Vec<_> = region.iter().enumerate()
+            .flat_map(|(dy, row)| row.iter().enumerate().map(move |(dx, state)| (cx + dx, cy + dy, state.clone())))
+            .collect();
+        let command = SetCells::new("paste", &self.level, cells);
+        self.execute(command);
+    }
+
+    pub fn undo(&mut self) {
+        if self.history.undo(&mut self.level).is_some() {
+            self.refresh();
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if self.history.redo(&mut self.level).is_some() {
+            self.refresh();
+        }
+    }
+
+    // saves the edited level to its file, refusing levels which couldn't be loaded again
+    // (e.g. without destructible bricks)
+    pub fn save(&self) -> Result<(), LevelError> {
+        LevelFile::parse(self.preview.current_state().to_toml().as_str())?;
+        if let Some(dir) = Path::new(&self.file).parent() {
+            fs::create_dir_all(dir).map_err(|e| LevelError::Io(format!("{}: {}", dir.display(), e)))?;
+        }
+        self.preview.save(self.file.as_str())?;
+        log::info!("EDITOR: Saved level {}", self.file);
+        Ok(())
+    }
+
+    fn execute<C: EditCommand + 'static>(&mut self, command: Option<C>) {
+        if let Some(command) = command {
+            self.history.execute(Box::new(command), &mut self.level);
+            self.refresh();
+        }
+    }
+
+    // rebuilds the preview level after the grid changed
+    fn refresh(&mut self) {
+        self.preview.load_state(&self.level, self.level_width, self.level_height);
+    }
+
+    // size of a single grid cell in pixels
+    pub fn cell_size(&self) -> glm::TVec2<f32> {
+        glm::vec2(self.level_width as f32 / self.columns() as f32, self.level_height as f32 / self.rows() as f32)
+    }
+
+    // the center of each cell holding a brick, with its tile code
+    pub fn labels(&self) -> impl Iterator<Item = (glm::TVec2<f32>, u32)> + '_ {
+        let cell = self.cell_size();
+        self.level.tiles.iter().enumerate().flat_map(move |(y, row)| row.iter().enumerate()
+            .filter(|(_, code)| **code != 0)
+            .map(move |(x, code)| (glm::vec2((x as f32 + 0.5) * cell.x, (y as f32 + 0.5) * cell.y), *code)))
+    }
+
+    // renders the level together with the grid, the tool preview, the selection and the cursor
+    pub fn draw(&self, renderer: &SpriteRenderer) {
+        self.preview.draw(renderer);
+        let cell = self.cell_size();
+        if self.show_grid {
+            let color = glm::vec3(0.25, 0.25, 0.25);
+            for x in 1..self.columns() {
+                renderer.draw_sprite_ex2(&self.white, glm::vec2(x as f32 * cell.x, 0.0), glm::vec2(1.0, self.level_height as f32), 0.0, color);
+            }
+            for y in 1..=self.rows() {
+                renderer.draw_sprite_ex2(&self.white, glm::vec2(0.0, y as f32 * cell.y), glm::vec2(self.level_width as f32, 1.0), 0.0, color);
+            }
+        }
+        // preview of the cells painted by the current tool
+        if self.tool != EditorTool::Eyedropper {
+            let color = match self.brush {
+                0 => glm::vec3(0.6, 0.1, 0.1),
+                code => game_level::tile_color(code)
+            };
+            for (x, y) in self.pending_cells() {
+                let position = glm::vec2(x as f32 * cell.x, y as f32 * cell.y) + glm::vec2(4.0, 4.0);
+                self.draw_outline(renderer, position, cell - glm::vec2(8.0, 8.0), color);
+            }
+        }
+        // marquee selection
+        if self.anchor.is_some() {
+            let selection = self.selection();
+            let position = glm::vec2(selection.min.0 as f32 * cell.x, selection.min.1 as f32 * cell.y);
+            let size = glm::vec2((selection.max.0 - selection.min.0 + 1) as f32 * cell.x, (selection.max.1 - selection.min.1 + 1) as f32 * cell.y);
+            self.draw_outline(renderer, position, size, glm::vec3(0.3, 0.6, 1.0));
+        }
+        // cursor
+        let position = glm::vec2(self.cursor.0 as f32 * cell.x, self.cursor.1 as f32 * cell.y);
+        self.draw_outline(renderer, position, cell, glm::vec3(1.0, 1.0, 0.0));
+    }
+
+    fn draw_outline(&self, renderer: &SpriteRenderer, position: glm::TVec2<f32>, size: glm::TVec2<f32>, color: glm::TVec3<f32>) {
+        let thickness = 2.0;
+        let bars = [
+            (position, glm::vec2(size.x, thickness)),
+            (position + glm::vec2(0.0, size.y - thickness), glm::vec2(size.x, thickness)),
+            (position, glm::vec2(thickness, size.y)),
+            (position + glm::vec2(size.x - thickness, 0.0), glm::vec2(thickness, size.y))
+        ];
+        for (position, size) in bars {
+            renderer.draw_sprite_ex2(&self.white, position, size, 0.0, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_test_case() {
+        let selection = Selection::new((3, 1), (1, 2));
+        assert_eq!(selection.min, (1, 1));
+        assert_eq!(selection.max, (3, 2));
+        let cells: Vec<_> = selection.cells().collect();
+        assert_eq!(cells, vec![(1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 2)]);
+    }
+
+    #[test]
+    fn editor_test_save() {
+        for name in ["white", "block", "block_solid"] {
+            if resource_manager::find_texture(name).is_none() {
+                resource_manager::add_texture(name.to_string(), Texture2D::empty());
+            }
+        }
+        let dir = std::env::temp_dir().join(format!("editor_test_save_{}", std::process::id()));
+        let file = dir.join("levels/one.toml");
+        let level = |tiles| LevelFile { tiles, overrides: Vec::new(), walls: Default::default(), ambient: None, weather: None };
+        // a level without destructible bricks couldn't be played, so it isn't written
+        let editor = LevelEditor::new(level(vec![vec![1, 0]]), file.to_string_lossy().into_owned(), 100, 50);
+        assert_eq!(editor.save(), Err(LevelError::NoDestructibleBricks));
+        assert!(!file.exists());
+        let playable = || {
+            let mut playable = level(vec![vec![1, 2]]);
+            playable.overrides.push(game_level::BrickOverride { x: 1, y: 0, hp: Some(2), color: None, material: None, checkpoint: false });
+            playable
+        };
+        let editor = LevelEditor::new(playable(), file.to_string_lossy().into_owned(), 100, 50);
+        let saved = editor.save().and_then(|_| fs::read_to_string(&file).map_err(|e| LevelError::Io(e.to_string())));
+        let _ = fs::remove_dir_all(&dir);
+        // the overrides are kept
+        assert_eq!(LevelFile::parse(saved.unwrap().as_str()), Ok(playable()));
+    }
+}
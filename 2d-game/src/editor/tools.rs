@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::game_level::LevelFile;
+
+// Painting tools of the level editor. The tools only compute which cells they would
+// touch, so the editor can preview the result before committing it to the history.
+
+/// The tool applied when pressing SPACE in the editor.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EditorTool {
+    // paints the cell under the cursor
+    Brush,
+    // fills the marquee selection
+    Rectangle,
+    // fills the connected area of equal tiles under the cursor
+    FloodFill,
+    // picks the tile code and color under the cursor as the brush
+    Eyedropper
+}
+
+impl EditorTool {
+    pub fn next(self) -> Self {
+        match self {
+            EditorTool::Brush => EditorTool::Rectangle,
+            EditorTool::Rectangle => EditorTool::FloodFill,
+            EditorTool::FloodFill => EditorTool::Eyedropper,
+            EditorTool::Eyedropper => EditorTool::Brush
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EditorTool::Brush => "brush",
+            EditorTool::Rectangle => "rectangle",
+            EditorTool::FloodFill => "flood",
+            EditorTool::Eyedropper => "eyedropper"
+        }
+    }
+}
+
+/// Symmetry axes applied while painting.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Mirror {
+    // mirror across the vertical center line (left <-> right)
+    pub horizontal: bool,
+    // mirror across the horizontal center line (top <-> bottom)
+    pub vertical: bool
+}
+
+impl Mirror {
+    // adds the mirrored counterparts of the given cells, without duplicates
+    pub fn apply(&self, cells: &[(usize, usize)], columns: usize, rows: usize) -> Vec<(usize, usize)> {
+        let mut result: Vec<(usize, usize)> = Vec::with_capacity(cells.len() * 4);
+        let mut push = |cell: (usize, usize)| {
+            if !result.contains(&cell) {
+                result.push(cell);
+            }
+        };
+        for &(x, y) in cells {
+            push((x, y));
+            if self.horizontal {
+                push((columns - 1 - x, y));
+            }
+            if self.vertical {
+                push((x, rows - 1 - y));
+            }
+            if self.horizontal && self.vertical {
+                push((columns - 1 - x, rows - 1 - y));
+            }
+        }
+        result
+    }
+}
+
+// collects the 4-connected area of cells sharing the tile code of `start`
+pub fn flood_region(level: &LevelFile, start: (usize, usize)) -> Vec<(usize, usize)> {
+    let rows = level.tiles.len();
+    let columns = level.tiles[0].len();
+    let code = level.tiles[start.1][start.0];
+    let mut visited = vec![vec![false; columns]; rows];
+    let mut region = Vec::new();
+    let mut stack = vec![start];
+    visited[start.1][start.0] = true;
+    while let Some((x, y)) = stack.pop() {
+        region.push((x, y));
+        let mut neighbours = Vec::with_capacity(4);
+        if x > 0 { neighbours.push((x - 1, y)); }
+        if x + 1 < columns { neighbours.push((x + 1, y)); }
+        if y > 0 { neighbours.push((x, y - 1)); }
+        if y + 1 < rows { neighbours.push((x, y + 1)); }
+        for (nx, ny) in neighbours {
+            if !visited[ny][nx] && level.tiles[ny][nx] == code {
+                visited[ny][nx] = true;
+                stack.push((nx, ny));
+            }
+        }
+    }
+    region.sort_by_key(|&(x, y)| (y, x));
+    region
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walls::Walls;
+
+    #[test]
+    fn mirror_test_case() {
+        let mirror = Mirror { horizontal: true, vertical: true };
+        let cells = mirror.apply(&[(0, 0)], 4, 3);
+        assert_eq!(cells, vec![(0, 0), (3, 0), (0, 2), (3, 2)]);
+        // cells on the mirror axis aren't duplicated
+        let mirror = Mirror { horizontal: true, vertical: false };
+        assert_eq!(mirror.apply(&[(1, 0)], 3, 1), vec![(1, 0)]);
+    }
+
+    #[test]
+    fn flood_region_test_case() {
+        let level = LevelFile {
+            tiles: vec![
+                vec![2, 2, 1, 3],
+                vec![1, 2, 1, 2],
+                vec![2, 2, 2, 2]
+            ],
+            overrides: Vec::new(),
+            walls: Walls::default(),
+            ambient: None,
+            weather: None
+        };
+        let region = flood_region(&level, (0, 0));
+        assert_eq!(region, vec![(0, 0), (1, 0), (1, 1), (3, 1), (0, 2), (1, 2), (2, 2), (3, 2)]);
+        assert_eq!(flood_region(&level, (3, 0)), vec![(3, 0)]);
+    }
+}
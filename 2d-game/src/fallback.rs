@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Mutex;
+
+// Optional assets which fail to load are replaced by placeholders so the game keeps running:
+// textures by a magenta checkerboard, fonts by a font built into the executable and sounds by
+// silence. Each missing asset is reported once; the game shows the reports as warnings.
+
+// side of the placeholder texture, and of its squares, in pixels
+pub const CHECKERBOARD_SIZE: u32 = 64;
+pub const CHECKER_SIZE: u32 = 8;
+
+// every asset reported so far, and those the game hasn't shown yet
+static REPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static PENDING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+// records that an asset couldn't be loaded and a placeholder is used instead
+pub fn report(kind: &str, path: &str, error: &str) {
+    let mut reported = REPORTED.lock().unwrap();
+    if reported.iter().any(|it| it == path) {
+        return;
+    }
+    reported.push(path.to_string());
+    log::warn!("FALLBACK: Failed to load {} {} ({}), using a placeholder", kind, path, error);
+    PENDING.lock().unwrap().push(path.to_string());
+}
+
+// the assets reported since the last call
+pub fn take_reports() -> Vec<String> {
+    std::mem::take(&mut *PENDING.lock().unwrap())
+}
+
+// RGBA pixels of a magenta and black checkerboard
+pub fn checkerboard(size: u32, checker: u32) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let magenta = (x / checker + y / checker).is_multiple_of(2);
+            pixels.extend_from_slice(if magenta { &[255, 0, 255, 255] } else { &[0, 0, 0, 255] });
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallback_test_checkerboard() {
+        let pixels = checkerboard(4, 2);
+        assert_eq!(pixels.len(), 4 * 4 * 4);
+        assert_eq!(&pixels[0..4], &[255, 0, 255, 255]);
+        // the square to the right, and the one below, are black
+        assert_eq!(&pixels[2 * 4..3 * 4], &[0, 0, 0, 255]);
+        assert_eq!(&pixels[(2 * 4) * 4..(2 * 4) * 4 + 4], &[0, 0, 0, 255]);
+        assert_eq!(&pixels[(2 * 4 + 2) * 4..(2 * 4 + 2) * 4 + 4], &[255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn fallback_test_report_once() {
+        report("texture", "fallback_test/missing.png", "not found");
+        report("texture", "fallback_test/missing.png", "not found");
+        let reports = take_reports();
+        assert_eq!(reports.iter().filter(|it| *it == "fallback_test/missing.png").count(), 1);
+        assert!(!take_reports().contains(&"fallback_test/missing.png".to_string()));
+    }
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use bumpalo::Bump;
+
+// The frame arena holds data which only lives while a frame is built, e.g. the sprites staged
+// for a batch or the instances of the particles before they are uploaded. Allocating from it
+// is a pointer bump, and everything is released at once when the frame ends, so the
+// renderers don't allocate Vecs of their own every frame. Allocations can't outlive the
+// closure passed to `with`, let alone the frame.
+
+thread_local! {
+    static ARENA: RefCell<Bump> = RefCell::new(Bump::new());
+}
+
+// runs `f` with the arena of this thread's frame (calls may nest)
+pub fn with<R>(f: impl FnOnce(&Bump) -> R) -> R {
+    ARENA.with(|arena| f(&arena.borrow()))
+}
+
+// frees everything allocated during the frame, keeping the memory for the next one
+pub fn reset() {
+    ARENA.with(|arena| arena.borrow_mut().reset());
+}
+
+// bytes the arena has reserved, used or not
+pub fn allocated_bytes() -> usize {
+    ARENA.with(|arena| arena.borrow().allocated_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bumpalo::collections::Vec as BumpVec;
+
+    #[test]
+    fn frame_arena_test_reuse() {
+        let sum = with(|arena| {
+            let mut values = BumpVec::with_capacity_in(1000, arena);
+            values.extend(0..1000u32);
+            // nested frames share the arena
+            let doubled = with(|arena| BumpVec::from_iter_in(values.iter().map(|it| it * 2), arena).len());
+            assert_eq!(doubled, 1000);
+            values.iter().sum::<u32>()
+        });
+        assert_eq!(sum, 499500);
+        reset();
+        let reserved = allocated_bytes();
+        assert!(reserved >= 1000 * 4, "The memory is kept after a reset.");
+        with(|arena| BumpVec::from_iter_in(0..1000u32, arena).len());
+        assert_eq!(allocated_bytes(), reserved, "The next frame fits into the same memory.");
+    }
+}
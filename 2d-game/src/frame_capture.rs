@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::{c_void, CString};
+use std::{mem, ptr};
+use gl::types::GLenum;
+
+// Integration with GPU frame debuggers (RenderDoc, apitrace, Nsight): debug groups around
+// the render phases, labels of GL objects and captures triggered through the in-application
+// API of RenderDoc. Without KHR_debug (GL 4.3) the groups and labels do nothing.
+
+// RENDERDOC_API_1_1_2 of renderdoc_app.h
+const RENDERDOC_API_VERSION: i32 = 10102;
+
+type GetApi = unsafe extern "C" fn(version: i32, api: *mut *mut c_void) -> i32;
+
+// the function table of RENDERDOC_API_1_1_2 up to the function used here
+#[repr(C)]
+struct RenderDocApi {
+    // GetAPIVersion up to GetCapture
+    _unused: [*const c_void; 15],
+    trigger_capture: unsafe extern "C" fn()
+}
+
+/// A debug group of the GL command stream, closed again when dropped.
+pub struct DebugGroup(bool);
+
+// opens a debug group, e.g. for a render phase
+pub fn group(name: &str) -> DebugGroup {
+    if !gl::PushDebugGroup::is_loaded() {
+        return DebugGroup(false);
+    }
+    unsafe {
+        gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, name.len() as _, name.as_ptr() as _);
+    }
+    DebugGroup(true)
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        if self.0 {
+            unsafe {
+                gl::PopDebugGroup();
+            }
+        }
+    }
+}
+
+// names a GL object of the given type (e.g. gl::TEXTURE) in frame debuggers
+pub fn label(identifier: GLenum, id: u32, name: &str) {
+    if !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(identifier, id, name.len() as _, name.as_ptr() as _);
+    }
+}
+
+// RenderDoc is the in-application API of RenderDoc, available while the game is launched from it
+pub struct RenderDoc {
+    api: *const RenderDocApi
+}
+
+impl RenderDoc {
+    // connects to RenderDoc if it's injected into the process (it's never loaded otherwise)
+    pub fn connect() -> Option<Self> {
+        let get_api = find_get_api()?;
+        let mut api = ptr::null_mut();
+        if unsafe { get_api(RENDERDOC_API_VERSION, &mut api) } != 1 || api.is_null() {
+            log::warn!("CAPTURE: RenderDoc doesn't provide API version 1.1.2");
+            return None;
+        }
+        Some(Self {
+            api: api as *const RenderDocApi
+        })
+    }
+
+    // captures the next frame
+    pub fn trigger_capture(&self) {
+        unsafe {
+            ((*self.api).trigger_capture)();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn find_get_api() -> Option<GetApi> {
+    let library = CString::new("librenderdoc.so").unwrap();
+    let symbol = CString::new("RENDERDOC_GetAPI").unwrap();
+    unsafe {
+        let handle = libc::dlopen(library.as_ptr(), libc::RTLD_NOW | libc::RTLD_NOLOAD);
+        if handle.is_null() {
+            return None;
+        }
+        let function = libc::dlsym(handle, symbol.as_ptr());
+        (!function.is_null()).then(|| mem::transmute::<*mut c_void, GetApi>(function))
+    }
+}
+
+#[cfg(windows)]
+fn find_get_api() -> Option<GetApi> {
+    extern "system" {
+        fn GetModuleHandleA(name: *const std::ffi::c_char) -> *mut c_void;
+        fn GetProcAddress(module: *mut c_void, name: *const std::ffi::c_char) -> *mut c_void;
+    }
+    let library = CString::new("renderdoc.dll").unwrap();
+    let symbol = CString::new("RENDERDOC_GetAPI").unwrap();
+    unsafe {
+        let module = GetModuleHandleA(library.as_ptr());
+        if module.is_null() {
+            return None;
+        }
+        let function = GetProcAddress(module, symbol.as_ptr());
+        (!function.is_null()).then(|| mem::transmute::<*mut c_void, GetApi>(function))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn find_get_api() -> Option<GetApi> {
+    None
+}
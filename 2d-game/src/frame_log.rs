@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::Serialize;
+
+// Frame log: run with --frame-log <file> to record every frame of the session (its delta time,
+// and how long updating and rendering it took), written to <file> when the game exits, as JSON
+// if it ends in .json and as CSV otherwise. Level loads, applied power-ups and the like are
+// marked on the frame they happened in, so hitches can be put down to what the game was doing.
+
+// frames whose delta time is this many times the median count as spikes
+pub const SPIKE_FACTOR: f32 = 2.0;
+
+/// A recorded frame; times in milliseconds.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FrameRecord {
+    pub frame: u64,
+    // time of the game clock at the start of the frame, in seconds
+    pub time: f64,
+    pub dt: f32,
+    pub update: f32,
+    pub render: f32,
+    // what happened during the frame
+    pub markers: Vec<String>
+}
+
+/// The frames recorded so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameLog {
+    pub records: Vec<FrameRecord>,
+    // markers of the frame being recorded
+    pending: Vec<String>
+}
+
+impl FrameLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // marks an event of the current frame
+    pub fn mark(&mut self, marker: String) {
+        self.pending.push(marker);
+    }
+
+    // records the frame which just ended, with the events marked during it
+    pub fn record(&mut self, time: f64, dt: f32, update: Duration, render: Duration) {
+        self.records.push(FrameRecord {
+            frame: self.records.len() as u64,
+            time,
+            dt: dt * 1000.0,
+            update: update.as_secs_f32() * 1000.0,
+            render: render.as_secs_f32() * 1000.0,
+            markers: std::mem::take(&mut self.pending)
+        });
+    }
+
+    // the frames taking more than SPIKE_FACTOR times the median delta time
+    pub fn spikes(&self) -> impl Iterator<Item = &FrameRecord> {
+        let mut dts: Vec<f32> = self.records.iter().map(|it| it.dt).collect();
+        dts.sort_by(f32::total_cmp);
+        let median = dts.get(dts.len() / 2).copied().unwrap_or(0.0);
+        self.records.iter().filter(move |it| it.dt > median * SPIKE_FACTOR)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = "frame,time,dt_ms,update_ms,render_ms,markers\n".to_string();
+        for record in self.records.iter() {
+            // markers are separated by semicolons, in quotes as they may contain commas
+            let markers = record.markers.join(";").replace('"', "\"\"");
+            csv.push_str(format!("{},{:.4},{:.3},{:.3},{:.3},\"{}\"\n",
+                record.frame, record.time, record.dt, record.update, record.render, markers).as_str());
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&self.records).map_err(|e| e.to_string())
+    }
+
+    // writes the log, as JSON or CSV by the extension of the file
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = if path.extension().is_some_and(|it| it == "json") {
+            self.to_json()?
+        } else {
+            self.to_csv()
+        };
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+// the log of the session, if frames are recorded
+static LOG: Mutex<Option<FrameLog>> = Mutex::new(None);
+
+// starts recording frames
+pub fn start() {
+    *LOG.lock().unwrap() = Some(FrameLog::new());
+}
+
+// marks an event (e.g. "level_load", with the level as detail) on the current frame,
+// if frames are recorded
+pub fn mark(event: &str, detail: impl Display) {
+    if let Some(log) = LOG.lock().unwrap().as_mut() {
+        log.mark(format!("{} {}", event, detail));
+    }
+}
+
+// records the frame which just ended, if frames are recorded
+pub fn record(time: f64, dt: f32, update: Duration, render: Duration) {
+    if let Some(log) = LOG.lock().unwrap().as_mut() {
+        log.record(time, dt, update, render);
+    }
+}
+
+// stops recording, writing the log to the given file
+pub fn finish(path: &Path) {
+    let Some(log) = LOG.lock().unwrap().take() else {
+        return;
+    };
+    match log.save(path) {
+        Ok(()) => log::info!("FRAME_LOG: Recorded {} frames ({} spikes) to {}",
+            log.records.len(), log.spikes().count(), path.display()),
+        Err(e) => log::error!("FRAME_LOG: Failed to write {}: {}", path.display(), e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> FrameLog {
+        let mut log = FrameLog::new();
+        for i in 0..9 {
+            if i == 4 {
+                log.mark("level_load 2".to_string());
+                log.mark("power_up speed".to_string());
+            }
+            let dt = if i == 4 { 0.05 } else { 0.016 };
+            log.record(i as f64 * 0.016, dt, Duration::from_millis(2), Duration::from_millis(5));
+        }
+        log
+    }
+
+    #[test]
+    fn frame_log_test_markers() {
+        let log = log();
+        assert_eq!(log.records.len(), 9);
+        assert_eq!(log.records[4].markers, vec!["level_load 2", "power_up speed"]);
+        assert!(log.records[5].markers.is_empty());
+        assert_eq!(log.records[4].update, 2.0);
+        // only the frame of the level load is a spike
+        let spikes: Vec<u64> = log.spikes().map(|it| it.frame).collect();
+        assert_eq!(spikes, vec![4]);
+    }
+
+    #[test]
+    fn frame_log_test_formats() {
+        let log = log();
+        let csv = log.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0], "frame,time,dt_ms,update_ms,render_ms,markers");
+        assert_eq!(lines[5], "4,0.0640,50.000,2.000,5.000,\"level_load 2;power_up speed\"");
+        let json: serde_json::Value = serde_json::from_str(log.to_json().unwrap().as_str()).unwrap();
+        assert_eq!(json[4]["markers"][1], "power_up speed");
+        assert_eq!(json.as_array().unwrap().len(), 9);
+    }
+}
@@ -0,0 +1,2654 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use glfw::{GamepadButton, Glfw, JoystickId, Key, MouseButton};
+use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rust_i18n::t;
+use learnopengl_shared::{filesystem, util};
+use crate::ball_object::BallObject;
+use crate::brick_type;
+use crate::collision::{check_collision, check_collision_1, predict_landing};
+use crate::daily;
+use crate::daily::Daily;
+use crate::dash::Dash;
+use crate::difficulty::{BallSpeed, Difficulty, SpeedRules};
+use crate::drops;
+use crate::fallback;
+use crate::frame_capture;
+use crate::frame_log;
+use crate::photo;
+use crate::photo::PhotoMode;
+use crate::post_effects;
+use crate::post_effects::Themes;
+use crate::drops::DropTable;
+use crate::frame_capture::RenderDoc;
+use crate::editor::LevelEditor;
+use crate::game_level;
+use crate::gl_debug;
+use crate::gpu_caps;
+use crate::game_level::{GameLevel, LevelFile, LevelSnapshot};
+use crate::game_object::GameObject;
+use crate::input::InputState;
+use crate::inspect;
+use crate::inspect::{Command, Inspect};
+use crate::logging;
+use crate::patterns;
+use crate::settings::{Setting, Settings};
+use crate::popups::ScorePopups;
+use crate::score::Score;
+use crate::simulation;
+use crate::simulation::{BrickHit, StepInput};
+use crate::walls::{Edge, WallEvent};
+use crate::speedrun;
+use crate::speedrun::{Speedrun, Stats};
+use crate::bindings;
+use crate::bindings::{Action, Bindings};
+use crate::particle_generator::ParticleGenerator;
+use crate::ambient::AmbientParticles;
+use crate::render_queue::{self, Layer, RenderQueue};
+use crate::weather::{self, Weather, WeatherOverlay};
+use crate::minimap::Minimap;
+use crate::post_processor::{Mask, PostProcessor};
+#[cfg(feature = "discord")]
+use crate::presence::{Activity, DiscordPresence};
+#[cfg(feature = "devtools")]
+use crate::devtools::{DevTools, Egui, World};
+#[cfg(feature = "devtools")]
+use crate::debug_window::DebugWindow;
+use crate::power_up::{Pickup, PowerUp, Rarity};
+use crate::power_up::effect::{find_effect, EffectTarget};
+use crate::resource_manager;
+use crate::res_name::res_name;
+use crate::sound_engine::SoundEngine;
+use crate::sprite_renderer::{Gradient, SpriteRenderer};
+use crate::tasks::Tasks;
+use crate::controller::{PaddleController, PlayerController, View};
+use crate::color_grading;
+use crate::color_grading::Luts;
+use crate::credits;
+use crate::replay::{ReplayController, Trace};
+use crate::credits::{Credits, LineKind};
+use crate::tutorial;
+use crate::tutorial::{Highlight, Tutorial};
+use crate::material::{Material, UniformValue};
+use crate::nine_slice::NineSlice;
+use crate::display;
+use crate::ui;
+use crate::ui::{Column, Rect, UiInput, UiState};
+use crate::viewport::Viewport;
+use crate::clip::ClipStack;
+use crate::text_renderer::ascii::TextRenderer;
+use crate::text_renderer::{FontChain, ITextRenderer, TextSpace};
+use crate::text_renderer::animation::{TextAnimation, TextEffect};
+use crate::text_renderer::utf8::TextRenderer as UTF8TextRenderer;
+
+pub mod states;
+
+use states::{ActiveState, CreditsState, DemoState, EditorState, MenuState, PausedState, SelectLanguageState, State};
+
+// Represents the current state of the game (see states for what each one does)
+#[derive(Copy, Clone, PartialOrd, PartialEq, Debug)]
+pub enum GameState {
+    SelectLanguage,
+    Active,
+    // the pause menu over an active game
+    Paused,
+    Menu,
+    Win,
+    Editor,
+    Options,
+    Credits,
+    // the game played by a controller while the menu is left alone
+    Demo
+}
+
+// items of the main menu
+#[derive(Copy, Clone, PartialEq)]
+enum MenuItem {
+    Start,
+    Level,
+    Practice,
+    Daily,
+    Editor,
+    Options,
+    Credits
+}
+
+// items of the pause menu
+#[derive(Copy, Clone, PartialEq)]
+enum PauseItem {
+    Resume,
+    Photo,
+    Restart,
+    Quit
+}
+
+// items of the options screen
+#[derive(Copy, Clone, PartialEq)]
+enum OptionsItem {
+    Binding(Action),
+    Setting(Setting),
+    ResetKeys,
+    Back
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Locale {
+    En = 0,
+    ZhCN = 1
+}
+
+impl Locale {
+    // the name of the language file
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::ZhCN => "zh-CN"
+        }
+    }
+}
+
+// Initial size of the player paddle
+lazy_static! {
+    pub static ref PLAYER_SIZE: glm::TVec2<f32> = glm::vec2(100.0, 20.0);
+}
+// Initial velocity of the player paddle
+pub const PLAYER_VELOCITY: f32 = 500.0;
+// Largest tilt of the paddle in degrees, and how fast it tilts (degrees per second)
+pub const PADDLE_TILT_MAX: f32 = 12.0;
+pub const PADDLE_TILT_SPEED: f32 = 120.0;
+// Initial velocity of the Ball
+lazy_static! {
+    pub static ref INITIAL_BALL_VELOCITY: glm::TVec2<f32> = glm::vec2(100.0, -350.0);
+}
+// Radius of the ball object
+pub const BALL_RADIUS: f32 = 12.5;
+// Color of the ball trail and the HUD label in fever
+lazy_static! {
+    static ref FEVER_TRAIL_COLOR: glm::TVec3<f32> = glm::vec3(1.0, 0.6, 0.2);
+    // Color the bottom of the menu panels fades to
+    static ref PANEL_SHADE: glm::TVec3<f32> = glm::vec3(0.75, 0.75, 0.8);
+}
+// Radius of the area around the ball chaos is confined to with reduced flashing
+const CHAOS_MASK_RADIUS: f32 = 120.0;
+// Saturation of the post-processed image in fever
+const FEVER_SATURATION: f32 = 1.3;
+// Size of the HUD slots showing the active power-ups
+lazy_static! {
+    static ref POWER_UP_SLOT_SIZE: glm::TVec2<f32> = glm::vec2(36.0, 12.0);
+}
+// Size of the HUD meter showing the dash cooldown
+lazy_static! {
+    static ref DASH_METER_SIZE: glm::TVec2<f32> = glm::vec2(80.0, 6.0);
+}
+// How long a launch press is remembered for when the ball respawns
+const LAUNCH_BUFFER_TIME: f32 = 0.1;
+// How long the menu has to be left alone for the demo to start
+pub const DEMO_IDLE_TIME: f32 = 30.0;
+// How fast tutorial prompts are typed out
+const TUTORIAL_TYPING_SPEED: f32 = 40.0;
+// Wave of the text announcing a win, and shake of the one announcing a lost ball
+const WIN_TEXT_WAVE: TextEffect = TextEffect::Wave { amplitude: 6.0, speed: 1.5, spread: 0.1 };
+const BALL_LOST_SHAKE: TextEffect = TextEffect::Shake { amplitude: 2.0, rate: 20.0 };
+// How long a banner announcing a lost ball stays up
+const BALL_LOST_BANNER_TIME: f32 = 1.5;
+// How long the note about an asset replaced by a placeholder stays up
+const MISSING_ASSET_TOAST_TIME: f64 = 4.0;
+// How long the end of the tutorial is announced for
+const TUTORIAL_DONE_BANNER_TIME: f32 = 2.0;
+// Space left around what the tutorial highlights
+const SPOTLIGHT_MARGIN: f32 = 12.0;
+// Height of a breakable ceiling as drawn along the top edge
+const CEILING_THICKNESS: f32 = 6.0;
+// Number of and distance between the paddle copies drawn behind a dashing paddle
+const DASH_STREAK_COPIES: u32 = 4;
+const DASH_STREAK_SPACING: f32 = 14.0;
+// Height of the bar timing a caught ball along the bottom of the paddle
+const CATCH_METER_HEIGHT: f32 = 4.0;
+// Range and step of the ball speed in practice mode
+const PRACTICE_SPEED_MIN: f32 = 0.5;
+const PRACTICE_SPEED_MAX: f32 = 2.0;
+const PRACTICE_SPEED_STEP: f32 = 0.25;
+// Number of log records shown in the debug overlay
+const DEBUG_OVERLAY_LINES: usize = 8;
+// Height kept clear of the credits at the bottom of the screen, for the hint
+const CREDITS_HINT_SPACE: f32 = 30.0;
+// File the key bindings are stored in
+const BINDINGS_FILE: &str = "userdata/bindings.toml";
+// File the player settings are stored in
+const SETTINGS_FILE: &str = "userdata/settings.toml";
+// File the personal bests are stored in
+const STATS_FILE: &str = "userdata/stats.toml";
+// Directory the level editor saves edited levels to (the shipped ones are left alone)
+const EDITED_LEVELS_DIR: &str = "userdata/levels";
+// Directory speedruns are exported to
+const RUNS_DIR: &str = "userdata/runs";
+// Directory photo mode saves screenshots to
+const PHOTOS_DIR: &str = "userdata/photos";
+// Photo mode screenshots are rendered at this multiple of the window size
+const PHOTO_SCALE: u32 = 2;
+// Shaders which draw the game world (and are seen through the photo camera)
+const SCENE_SHADERS: [&str; 7] = ["sprite", "sprite_instanced", "particle", "glass", "metal", "glow", "dissolve"];
+// Files the levels are loaded from
+pub const LEVEL_FILES: [&str; 4] = [
+    "resources/levels/one.lvl",
+    "resources/levels/two.lvl",
+    "resources/levels/three.lvl",
+    "resources/levels/four.lvl"
+];
+// File the power-up drop table is loaded from
+pub const DROPS_FILE: &str = "resources/levels/drops.toml";
+pub const BRICKS_FILE: &str = "resources/levels/bricks.toml";
+// Retro post effect themes of the levels
+const THEMES_FILE: &str = "resources/levels/themes.toml";
+// Credits shown from the main menu
+const CREDITS_FILE: &str = "resources/credits.toml";
+
+// Game holds all game-related state and functionality.
+// Combines all game-related data into a single class for
+// easy access to each of the components and manageability.
+pub struct Game {
+    // game states; the top one is the current state, the ones below it are
+    // overlaid (e.g. the pause menu over the game being played)
+    states: Vec<Box<dyn State>>,
+    pub input: InputState,
+    pub bindings: Bindings,
+    pub settings: Settings,
+    pub stats: Stats,
+    // the speedrun in progress, if the timer is enabled
+    speedrun: Option<Speedrun>,
+    // focus of the menu on screen
+    ui: UiState,
+    pub width: u32,
+    pub height: u32,
+    // where the game is shown in the window; the game itself always works in width x height
+    pub viewport: Viewport,
+    // clip rects of the UI being drawn
+    clip: RefCell<ClipStack>,
+    // content scale of the monitor the window is on (2.0 on a Retina display)
+    content_scale: f32,
+    pub levels: Vec<GameLevel>,
+    pub power_ups: Vec<PowerUp>,
+    // collected power-ups on their way to the HUD
+    pickups: Vec<Pickup>,
+    // points awarded by destroyed bricks, rising from where they were
+    popups: ScorePopups,
+    pub level: u32,
+    pub lives: u32,
+    // points and combo; a long combo starts fever
+    pub score: Score,
+    // state restored when the ball is lost, saved by hitting a checkpoint brick
+    checkpoint: Option<Checkpoint>,
+    // which power-ups destroyed bricks drop
+    pub drops: DropTable,
+    // retro post effects of the levels
+    pub themes: Themes,
+    // color grading LUTs of the themes
+    luts: Luts,
+    // random source of gameplay rolls (see seed_rng)
+    rng: StdRng,
+    pub locale: Locale,
+    // whether the current level is being playtested from the level editor
+    pub playtest: bool,
+    // practice mode: infinite lives, adjustable ball speed and power-ups on demand
+    pub practice: bool,
+    // ball speed multiplier in practice mode
+    pub practice_speed: f32,
+    // how much the ball has sped up since it was launched
+    pub ball_speed: BallSpeed,
+    // the paddle's dash and its cooldown
+    pub dash: Dash,
+    // the daily challenge being played
+    daily: Option<Daily>,
+    // seed of the daily challenge when playing a shared code (--daily) instead of today's
+    pub daily_seed: Option<u32>,
+    // set when the time since the last frame shouldn't be simulated (e.g. after the window was minimized)
+    pub reset_frame_time: bool,
+    // simulated time of the current step, less than the step itself when the game speed is lowered
+    world_dt: f32,
+    // set when the next frame has to be drawn even though the game is idle
+    redraw: bool,
+    window_focused: bool,
+    window_iconified: bool,
+
+    // Game-related State data
+    renderer: Option<Box<SpriteRenderer>>,
+    player: Option<Box<GameObject>>,
+    ball: Option<Box<BallObject>>,
+    particles: Option<Box<ParticleGenerator>>,
+    // auras of the falling rare power-ups
+    aura: Option<Box<ParticleGenerator>>,
+    // particles drifting through the current level, behind the bricks
+    ambient: Option<AmbientParticles>,
+    // rain and fog drawn over the game
+    pub weather: Option<WeatherOverlay>,
+    // the bricks left, shown in a corner of the HUD
+    minimap: Option<Minimap>,
+    effects: Option<Box<PostProcessor>>,
+    text: Option<Box<TextRenderer>>,
+    utf8_text: Option<Box<UTF8TextRenderer>>,
+    // projection of the game world, moved by the photo camera
+    scene_projection: glm::Mat4,
+    sound_engine: Option<Box<SoundEngine>>,
+    editor: Option<Box<LevelEditor>>,
+    #[cfg(feature = "discord")]
+    presence: Option<DiscordPresence>,
+    // inspector window for tuning the running game (toggled with F4)
+    #[cfg(feature = "devtools")]
+    devtools: DevTools,
+    // egui of the game window, set up when the inspector is first opened
+    #[cfg(feature = "devtools")]
+    egui: Option<Egui>,
+    // the window of the inspector while it's detached
+    #[cfg(feature = "devtools")]
+    debug_window: Option<DebugWindow>,
+    // scripted sequences running on the game loop
+    pub tasks: Tasks<Game>,
+    // line of text flashed in the middle of the screen
+    banner: Option<Banner>,
+    // notes about assets replaced by placeholders, with the time they disappear at
+    toasts: Vec<(String, f64)>,
+    // the step of the tutorial being shown
+    tutorial: Option<Tutorial>,
+    // drives the paddle instead of the player's keys (the replay of a visual test)
+    pub autopilot: Option<Box<dyn PaddleController>>,
+
+    shake_time: f32,
+    last_fps_shown: Instant,
+    fps: f32,
+    // shows the recent warnings and errors (toggled with F3)
+    pub debug_overlay: bool,
+    // in-application API of RenderDoc when the game runs under it (captures with F9)
+    render_doc: Option<RenderDoc>,
+
+    glfw: Glfw
+}
+
+// line of text flashed in the middle of the screen, how it moves and when it was put up
+pub struct Banner {
+    pub text: String,
+    pub effect: Option<TextEffect>,
+    pub shown: f64
+}
+
+// state of the level and the score when a checkpoint brick was hit
+struct Checkpoint {
+    level: LevelSnapshot,
+    score: Score
+}
+
+// state of the simulation after a step, as recorded for rewinding
+struct RewindFrame {
+    level: u32,
+    bricks: LevelSnapshot,
+    player: GameObject,
+    ball: BallObject,
+    power_ups: Vec<PowerUp>,
+    score: Score,
+    lives: u32,
+    ball_speed: BallSpeed,
+    dash: Dash,
+    shake_time: f32,
+    confuse: bool,
+    chaos: bool,
+    shake: bool,
+    rng: StdRng
+}
+
+// where a piece of HUD text is anchored when the UI is scaled
+enum HudAnchor {
+    TopLeft,
+    Center,
+    BottomLeft,
+    // the position already is in screen coordinates (e.g. laid out by the UI)
+    Absolute
+}
+
+// rounds each color channel to full or no intensity; black becomes white
+fn high_contrast_color(color: glm::TVec3<f32>) -> glm::TVec3<f32> {
+    let color = color.map(|c| if c >= 0.5 { 1.0 } else { 0.0 });
+    if color == glm::vec3(0.0, 0.0, 0.0) {
+        util::glm::scale_vec3(1.0)
+    } else {
+        color
+    }
+}
+
+fn is_other_power_up_active(
+    power_ups: &Vec<PowerUp>,
+    type_str: String
+) -> bool {
+    // Check if another PowerUp of the same type is still active
+    // in which case we don't disable its effect (yet)
+    for power_up in power_ups.iter() {
+        if power_up.activated {
+            if power_up.type_str == type_str {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// type, color, duration, texture and rarity of all power-ups (in the order of their HUD slots)
+pub const POWER_UP_TYPES: [(&str, [f32; 3], f32, &str, Rarity); 6] = [
+    ("speed", [0.5, 0.5, 1.0], 0.0, "powerup_speed", Rarity::Common),
+    ("sticky", [1.0, 0.5, 1.0], 20.0, "powerup_sticky", Rarity::Rare),
+    ("pass-through", [0.5, 1.0, 0.5], 10.0, "powerup_passthrough", Rarity::Epic),
+    ("pad-size-increase", [1.0, 0.6, 0.4], 0.0, "powerup_increase", Rarity::Common),
+    ("confuse", [1.0, 0.3, 0.3], 15.0, "powerup_confuse", Rarity::Common),
+    ("chaos", [0.9, 0.25, 0.25], 15.0, "powerup_chaos", Rarity::Rare)
+];
+
+// creates a PowerUp of the given type, returns None for unknown types
+pub fn create_power_up(type_str: &str, position: glm::TVec2<f32>) -> Option<PowerUp> {
+    let (_, color, duration, texture, rarity) = POWER_UP_TYPES.iter().find(|it| it.0 == type_str)?;
+    let mut power_up = PowerUp::new(
+        type_str.to_string(),
+        glm::vec3(color[0], color[1], color[2]),
+        *duration,
+        position,
+        resource_manager::get_texture(*texture),
+        *rarity
+    );
+    // rarer power-ups glow in the color of their tier
+    if rarity.glow_strength() > 0.0 {
+        power_up.game_obj.material = resource_manager::find_material(res_name!("glow")).map(|mut material| {
+            let glow = rarity.color();
+            material.set_uniform("glow_color", UniformValue::Vec3([glow.x, glow.y, glow.z]));
+            material.set_uniform("glow_strength", UniformValue::Float(rarity.glow_strength()));
+            material
+        });
+    }
+    Some(power_up)
+}
+
+// sets the projection of all shaders drawing the game world
+fn set_scene_projection(projection: &glm::Mat4) {
+    for name in SCENE_SHADERS {
+        resource_manager::get_shader(name).set_matrix4_ex("projection", projection, true);
+    }
+}
+
+// the file the level editor saves edits of a shipped level to, in the structured format
+// (which keeps the per-brick overrides)
+fn edited_level_file(level_file: &str) -> String {
+    let stem = Path::new(level_file).file_stem().map(|it| it.to_string_lossy().into_owned()).unwrap_or_default();
+    format!("{}/{}.toml", EDITED_LEVELS_DIR, stem)
+}
+
+fn load_level(level: &mut GameLevel, file: &str, level_width: u32, level_height: u32) {
+    if let Err(e) = level.load(file, level_width, level_height) {
+        panic!("ERROR::GAME_LEVEL: Failed to load level {}: {}", file, e);
+    }
+}
+
+impl Game {
+    // constructor
+    pub fn new(glfw: Glfw, width: u32, height: u32) -> Self {
+        Self {
+            states: vec![Box::new(SelectLanguageState)],
+            input: InputState::new(),
+            bindings: Bindings::default(),
+            settings: Settings::default(),
+            stats: Stats::default(),
+            speedrun: None,
+            ui: UiState::new(),
+            width,
+            height,
+            viewport: Viewport::fit(width, height, width as _, height as _),
+            clip: RefCell::new(ClipStack::new(Viewport::fit(width, height, width as _, height as _), width, height)),
+            content_scale: 1.0,
+            levels: Vec::new(),
+            power_ups: Vec::new(),
+            pickups: Vec::new(),
+            popups: ScorePopups::new(),
+            level: 0,
+            lives: 3,
+            score: Score::new(),
+            checkpoint: None,
+            drops: DropTable::default(),
+            themes: Themes::default(),
+            luts: Luts::new(filesystem::get_path(color_grading::LUTS_DIR.to_string())),
+            rng: StdRng::from_os_rng(),
+            locale: Locale::En,
+            playtest: false,
+            practice: false,
+            practice_speed: 1.0,
+            ball_speed: BallSpeed::new(Difficulty::default().speed_rules()),
+            dash: Dash::default(),
+            daily: None,
+            daily_seed: None,
+            reset_frame_time: false,
+            world_dt: 0.0,
+            redraw: true,
+            window_focused: true,
+            window_iconified: false,
+            renderer: None,
+            player: None,
+            ball: None,
+            particles: None,
+            ambient: None,
+            weather: None,
+            minimap: None,
+            aura: None,
+            effects: None,
+            text: None,
+            utf8_text: None,
+            scene_projection: glm::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0),
+            sound_engine: None,
+            editor: None,
+            #[cfg(feature = "discord")]
+            presence: None,
+            #[cfg(feature = "devtools")]
+            devtools: DevTools::new(),
+            #[cfg(feature = "devtools")]
+            egui: None,
+            #[cfg(feature = "devtools")]
+            debug_window: None,
+            tasks: Tasks::default(),
+            banner: None,
+            toasts: Vec::new(),
+            tutorial: None,
+            autopilot: None,
+            shake_time: 0.0,
+            last_fps_shown: Instant::now(),
+            fps: 0.0,
+            debug_overlay: false,
+            render_doc: RenderDoc::connect(),
+            glfw
+        }
+    }
+
+    // initialize game state (load all shaders/textures/levels)
+    pub fn init(&mut self) {
+        // load shaders
+        resource_manager::load_shader(
+            filesystem::get_path("resources/shaders/sprite.vs".to_string()).as_str(),
+            filesystem::get_path("resources/shaders/sprite.fs".to_string()).as_str(),
+            None,
+            "sprite".to_string()
+        );
+        resource_manager::load_shader_ex(
+            filesystem::get_path("resources/shaders/sprite.vs".to_string()).as_str(),
+            filesystem::get_path("resources/shaders/sprite.fs".to_string()).as_str(),
+            None,
+            "sprite_instanced".to_string(),
+            &[("INSTANCED", "1")]
+        );
+        resource_manager::load_shader_ex(
+            filesystem::get_path("resources/shaders/particle.vs".to_string()).as_str(),
+            filesystem::get_path("resources/shaders/particle.fs".to_string()).as_str(),
+            None,
+            "particle".to_string(),
+            &[("PARTICLE_SCALE", "10.0")]
+        );
+        resource_manager::load_shader(
+            filesystem::get_path("resources/shaders/post_processing.vs".to_string()).as_str(),
+            filesystem::get_path("resources/shaders/post_processing.fs".to_string()).as_str(),
+            None,
+            "postprocessing".to_string()
+        );
+        for name in ["glass", "metal", "glow", "dissolve"] {
+            resource_manager::load_shader(
+                filesystem::get_path("resources/shaders/sprite.vs".to_string()).as_str(),
+                filesystem::get_path(format!("resources/shaders/{}.fs", name)).as_str(),
+                None,
+                name.to_string()
+            );
+        }
+        self.configure_shaders();
+        // materials special bricks can be drawn with
+        resource_manager::add_material("glass".to_string(), Material::new(resource_manager::get_shader(res_name!("glass")))
+            .with_uniform("opacity", UniformValue::Float(0.45)));
+        resource_manager::add_material("metal".to_string(), Material::new(resource_manager::get_shader(res_name!("metal")))
+            .with_uniform("shine", UniformValue::Float(0.35)));
+        resource_manager::add_material("glow".to_string(), Material::new(resource_manager::get_shader(res_name!("glow")))
+            .with_uniform("glow_color", UniformValue::Vec3([1.0, 0.9, 0.5]))
+            .with_uniform("glow_strength", UniformValue::Float(0.6)));
+        // destroyed bricks burn away with a glowing edge (time and duration are set per brick)
+        resource_manager::add_material("dissolve".to_string(), Material::new(resource_manager::get_shader(res_name!("dissolve")))
+            .with_uniform("edge_color", UniformValue::Vec3([1.0, 0.6, 0.2])));
+        // load textures
+        resource_manager::load_texture(filesystem::get_path("resources/textures/background.jpg".to_string()).as_str(), true, "background".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/awesomeface.png".to_string()).as_str(), true, "face".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/block.png".to_string()).as_str(), true, "block".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/block_solid.png".to_string()).as_str(), true, "block_solid".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/paddle.png".to_string()).as_str(), true, "paddle".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/particle.png".to_string()).as_str(), true, "particle".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/powerup_speed.png".to_string()).as_str(), true, "powerup_speed".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/powerup_sticky.png".to_string()).as_str(), true, "powerup_sticky".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/powerup_increase.png".to_string()).as_str(), true, "powerup_increase".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/powerup_confuse.png".to_string()).as_str(), true, "powerup_confuse".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/powerup_chaos.png".to_string()).as_str(), true, "powerup_chaos".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/powerup_passthrough.png".to_string()).as_str(), true, "powerup_passthrough".to_string());
+        resource_manager::load_texture(filesystem::get_path("resources/textures/panel.png".to_string()).as_str(), true, "panel".to_string());
+        resource_manager::load_white_texture("white".to_string());
+        resource_manager::load_slices(filesystem::get_path("resources/textures/slices.toml".to_string()).as_str());
+        self.create_renderers();
+        // load key bindings
+        self.bindings = Bindings::load(filesystem::get_path(BINDINGS_FILE.to_string()).as_str());
+        // load settings
+        self.settings = Settings::load(filesystem::get_path(SETTINGS_FILE.to_string()).as_str());
+        // connect to discord if it's running
+        #[cfg(feature = "discord")]
+        {
+            self.presence = match option_env!("BREAKOUT_DISCORD_CLIENT_ID") {
+                Some(client_id) => DiscordPresence::connect(client_id),
+                None => {
+                    log::warn!("PRESENCE: Built without BREAKOUT_DISCORD_CLIENT_ID, rich presence is disabled");
+                    None
+                }
+            };
+        }
+        // load personal bests
+        self.stats = Stats::load(filesystem::get_path(STATS_FILE.to_string()).as_str());
+        patterns::load_colorblind_textures();
+        self.apply_settings();
+        // register brick types (before the levels which use them), with the textures they need
+        let power_up_names: Vec<&str> = POWER_UP_TYPES.iter().map(|it| it.0).collect();
+        brick_type::load_types(filesystem::get_path(BRICKS_FILE.to_string()).as_str(), &power_up_names);
+        for code in brick_type::codes() {
+            let texture = brick_type::get(code).unwrap().texture;
+            if resource_manager::find_texture(&texture).is_none() {
+                resource_manager::load_texture(filesystem::get_path(format!("resources/textures/{}.png", texture)).as_str(), true, texture);
+            }
+        }
+        // load levels
+        for file in LEVEL_FILES {
+            let mut level = GameLevel::new();
+            load_level(&mut level, filesystem::get_path(file.to_string()).as_str(), self.width, self.height / 2);
+            self.levels.push(level);
+        }
+        self.level = 0;
+        self.drops = drops::load_table(filesystem::get_path(DROPS_FILE.to_string()).as_str(), &power_up_names);
+        self.themes = post_effects::load_themes(filesystem::get_path(THEMES_FILE.to_string()).as_str());
+        // configure game objects
+        let player_pos = glm::vec2(self.width as f32 / 2.0 - PLAYER_SIZE.x / 2.0, self.height as f32 - PLAYER_SIZE.y);
+        let player = GameObject::new_ex0(player_pos, PLAYER_SIZE.clone(), resource_manager::get_texture(res_name!("paddle")));
+        let player = Box::new(player);
+        self.player = Some(player);
+        let ball_pos = player_pos + glm::vec2(PLAYER_SIZE.x / 2.0 - BALL_RADIUS, -BALL_RADIUS * 2.0);
+        let ball = BallObject::new_ex(ball_pos, BALL_RADIUS, INITIAL_BALL_VELOCITY.clone(), resource_manager::get_texture(res_name!("face")));
+        let ball = Box::new(ball);
+        self.ball = Some(ball);
+        let mut sound_engine = SoundEngine::new();
+        sound_engine.load_events(filesystem::get_path("resources/audio/audio_events.toml".to_string()).as_str());
+        // audio
+        sound_engine.play_music("music");
+        let sound_engine = Box::new(sound_engine);
+        self.sound_engine = Some(sound_engine);
+    }
+
+    // game loop
+    // dt is the length of the step, world_dt the time it simulates
+    pub fn process_input(&mut self, dt: f32, world_dt: f32) {
+        self.world_dt = world_dt;
+        if self.input.is_active() {
+            self.redraw = true;
+        }
+        if self.input.just_pressed(Key::F3) {
+            self.debug_overlay = !self.debug_overlay;
+        }
+        if self.input.just_pressed(Key::F9) {
+            match self.render_doc.as_ref() {
+                Some(render_doc) => {
+                    render_doc.trigger_capture();
+                    log::info!("CAPTURE: Capturing the next frame");
+                }
+                None => log::warn!("CAPTURE: RenderDoc is not attached")
+            }
+        }
+        #[cfg(feature = "devtools")]
+        if self.process_devtools_input(dt) {
+            return;
+        }
+        self.with_top_state(|state, game| state.handle_input(game, dt));
+    }
+
+    // forwards the buttons of the first gamepad to the input state
+    pub fn poll_gamepad(&mut self) {
+        let Some(state) = self.glfw.get_joystick(JoystickId::Joystick1).get_gamepad_state() else {
+            return;
+        };
+        for i in 0..=GamepadButton::ButtonDpadLeft as i32 {
+            if let Some(button) = GamepadButton::from_i32(i) {
+                self.input.set_gamepad_button(button, state.get_button_state(button) == glfw::Action::Press);
+            }
+        }
+    }
+
+    // updates the FPS counter with the time of the last rendered frame
+    pub fn update_fps(&mut self, frame_time: f32) {
+        let dur = self.last_fps_shown.elapsed();
+        if dur.as_millis() >= 1000 {
+            if frame_time == 0.0 {
+                self.fps = 0.0
+            } else {
+                self.fps = 1.0 / frame_time;
+            }
+            self.last_fps_shown = Instant::now();
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, world_dt: f32) {
+        self.world_dt = world_dt;
+        self.luts.update(dt);
+        self.update_post_effects();
+        self.update_toasts();
+        self.with_top_state(|state, game| state.update(game, dt));
+    }
+
+    // shows a note for each asset that was replaced by a placeholder
+    fn update_toasts(&mut self) {
+        let now = self.glfw.get_time();
+        let count = self.toasts.len();
+        self.toasts.retain(|(_, until)| *until > now);
+        let reports = fallback::take_reports();
+        let changed = self.toasts.len() != count || !reports.is_empty();
+        for path in reports {
+            self.toasts.push((t!("game_state.missing_asset", path=path).to_string(), now + MISSING_ASSET_TOAST_TIME));
+        }
+        if changed {
+            self.request_redraw();
+        }
+    }
+
+    // advances the world behind the current state by a step
+    fn update_world(&mut self, dt: f32) {
+        self.redraw = true;
+        // resume the scripted sequences (tasks they spawn end up in self.tasks and are kept)
+        let mut tasks = std::mem::take(&mut self.tasks);
+        tasks.pump(self, dt);
+        tasks.append(&mut self.tasks);
+        self.tasks = tasks;
+        // the tutorial dims everything but what its step is about
+        let spotlight = self.tutorial.map(|tutorial| self.spotlight(tutorial.step.highlight));
+        self.effects.as_mut().unwrap().spotlight = spotlight;
+        // update objects
+        self.player.as_mut().unwrap().update_status(dt);
+        self.ball.as_mut().unwrap().game_obj.update_status(dt);
+        self.levels[self.level as usize].update_status(dt);
+        let walls = self.levels[self.level as usize].bounds();
+        let pace = self.ball.as_ref().unwrap().game_obj.status.speed_factor();
+        let before = glm::length(&self.ball.as_ref().unwrap().game_obj.velocity);
+        let wall_event = self.ball.as_mut().unwrap().move_ball(dt * pace, self.width, &walls);
+        if wall_event == Some(WallEvent::Bounced(Edge::Top)) {
+            self.hit_ceiling();
+        }
+        if let Some(WallEvent::Bounced(_)) = wall_event {
+            self.ball_speed.limit(self.launch_speed(), before, self.ball.as_mut().unwrap());
+        }
+        // check for collisions
+        self.do_collisions();
+        self.update_fever();
+        // update particles (the ball leaves a denser, glowing trail in fever)
+        let particles = self.particles.as_mut().unwrap();
+        particles.tint = if self.score.fever { *FEVER_TRAIL_COLOR } else { util::glm::scale_vec3(1.0) };
+        particles.update_ex(
+            dt,
+            &self.ball.as_ref().unwrap().game_obj,
+            if self.score.fever { 4 } else { 2 },
+            util::glm::scale_vec2(self.ball.as_ref().unwrap().radius / 2.0)
+        );
+        self.update_ambient(dt);
+        self.update_weather(dt);
+        // update PowerUps
+        self.update_power_ups(dt);
+        self.update_power_up_visuals(dt);
+        self.popups.update(dt);
+        // fade out brick hit feedback
+        self.levels[self.level as usize].update(dt);
+        // reduce shake time
+        if self.shake_time > 0.0 {
+            self.shake_time -= dt;
+            if self.shake_time <= 0.0 {
+                self.effects.as_mut().unwrap().shake = false;
+            }
+        }
+        // check loss condition
+        // did ball reach bottom edge or leave through an open side?
+        if wall_event == Some(WallEvent::Escaped) || self.ball.as_ref().unwrap().game_obj.position.y >= self.height as f32 {
+            // practice mode has infinite lives
+            if !self.practice {
+                self.lives -= 1;
+            }
+            // did the player lose all his lives? : game over
+            if self.lives == 0 {
+                if self.playtest {
+                    self.stop_playtest();
+                    return;
+                }
+                self.finish_speedrun(false);
+                self.finish_daily();
+                self.reset_level();
+                self.set_state(MenuState::default());
+            }
+            // continue from the last checkpoint of the level
+            if let Some(checkpoint) = self.checkpoint.as_ref() {
+                self.levels[self.level as usize].restore(&checkpoint.level);
+                self.score = checkpoint.score;
+            }
+            self.reset_player();
+            // a launch pressed shortly before the respawn still counts
+            if self.state() == GameState::Active && self.input.pressed_within(self.bindings.key(Action::Launch), LAUNCH_BUFFER_TIME) {
+                self.ball.as_mut().unwrap().stuck = false;
+            }
+            if self.state() == GameState::Active && !self.practice {
+                self.announce_ex(t!("game_state.ball_lost", count=self.lives).to_string(), BALL_LOST_BANNER_TIME, Some(BALL_LOST_SHAKE));
+            }
+        }
+    }
+
+    // whether the game only shows menus or is paused, so it can get by with a low frame rate
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state(), GameState::SelectLanguage | GameState::Menu | GameState::Options | GameState::Paused) && self.photo().is_none()
+    }
+
+    // asks for the next frame to be drawn, e.g. because the window has to be refreshed
+    pub fn request_redraw(&mut self) {
+        self.redraw = true;
+    }
+
+    // whether the frame has to be drawn; idle frames are only drawn when something changed
+    pub fn take_redraw(&mut self) -> bool {
+        let redraw = self.redraw || !self.is_idle();
+        self.redraw = false;
+        redraw
+    }
+
+    pub fn render(&self) {
+        let mut queue = RenderQueue::new();
+        // the world is shown behind the game, the menus and the win screen
+        let world = matches!(self.states[0].id(), GameState::Active | GameState::Menu | GameState::Win | GameState::Options | GameState::Demo);
+        if world {
+            self.queue_scene(&mut queue, self.photo().is_some());
+            // the weather is in front of the game but behind the HUD
+            if let Some(weather) = self.weather.as_ref() {
+                queue.push(Layer::Foreground, move || {
+                    let _group = frame_capture::group("weather");
+                    weather.draw(self.renderer.as_ref().unwrap(), self.width, self.height);
+                });
+            }
+        }
+        match self.photo().filter(|_| world) {
+            // photo mode hides the HUD
+            Some(photo) => queue.push(Layer::Hud, move || self.render_photo_hints(photo)),
+            None => {
+                if world {
+                    queue.push(Layer::Hud, || self.render_hud());
+                }
+                // overlays are drawn over the states below them
+                for state in self.states.iter() {
+                    queue.push(Layer::Hud, move || state.render(self));
+                }
+                if !self.toasts.is_empty() {
+                    queue.push(Layer::Hud, || self.render_toasts());
+                }
+                if self.debug_overlay {
+                    queue.push(Layer::Debug, || self.render_debug_overlay());
+                }
+            }
+        }
+        if world {
+            // begin rendering to postprocessing framebuffer
+            self.effects.as_ref().unwrap().begin_render();
+            let group = frame_capture::group("scene");
+            queue.flush(render_queue::SCENE);
+            drop(group);
+            // end rendering to postprocessing framebuffer
+            self.effects.as_ref().unwrap().end_render();
+            // render postprocessing quad
+            let _group = frame_capture::group("post");
+            self.effects.as_ref().unwrap().render(self.glfw.get_time() as f32);
+        }
+        // everything else isn't post-processed
+        queue.flush(..);
+    }
+
+    // the texts and meters shown over the game
+    fn render_hud(&self) {
+        if self.practice {
+            let string = t!("game_state.practice_0", speed=format!("{:.2}", self.practice_speed)).to_string();
+            self.render_hud_text(string, 5.0, 5.0, 1.0, util::glm::scale_vec3(1.0), HudAnchor::TopLeft);
+        } else {
+            let string = t!("game_state.lives", count=self.lives).to_string();
+            self.render_hud_text(string, 5.0, 5.0, 1.0, util::glm::scale_vec3(1.0), HudAnchor::TopLeft);
+            let string = t!("game_state.score", points=self.score.points).to_string();
+            self.render_hud_text(string, 150.0, 5.0, 1.0, util::glm::scale_vec3(1.0), HudAnchor::TopLeft);
+            // turns red as the ball approaches its top speed
+            let speed = &self.ball_speed;
+            let top_speed = ((speed.multiplier - 1.0) / (speed.rules.cap - 1.0).max(f32::EPSILON)).clamp(0.0, 1.0);
+            let string = t!("game_state.ball_speed", speed=format!("{:.2}", speed.multiplier)).to_string();
+            self.render_hud_text(string, 330.0, 5.0, 1.0, glm::vec3(1.0, 1.0 - top_speed * 0.7, 1.0 - top_speed * 0.7), HudAnchor::TopLeft);
+        }
+        let fps = format!("{:.2}", self.fps);
+        let string = t!("game_state.fps", count=fps).to_string();
+        self.render_hud_text(string, 5.0, 25.0, 1.0, util::glm::scale_vec3(1.0), HudAnchor::TopLeft);
+        if self.score.fever {
+            self.render_hud_text(t!("game_state.fever").to_string(), 150.0, 25.0, 1.0, *FEVER_TRAIL_COLOR, HudAnchor::TopLeft);
+        }
+        self.render_power_up_slots();
+        self.render_dash_meter();
+        if self.settings.minimap {
+            self.render_minimap();
+        }
+    }
+
+    // the minimap in the bottom right corner, with the ball blinking on it while it's in play
+    fn render_minimap(&self) {
+        let minimap = self.minimap.as_ref().unwrap();
+        let size = minimap.size(self.ui_scale());
+        let position = glm::vec2(self.width as f32, self.height as f32) - size - glm::vec2(5.0, 5.0);
+        let ball = self.ball.as_ref().map(|it| it.game_obj.position + glm::vec2(it.radius, it.radius));
+        minimap.draw(
+            self.renderer.as_ref().unwrap(),
+            &self.levels[self.level as usize].bricks,
+            ball,
+            position,
+            size,
+            self.glfw.get_time() as f32
+        );
+    }
+
+    // the ambient particles follow the current level (and are spawned anew when it has others)
+    fn update_ambient(&mut self, dt: f32) {
+        let config = self.levels[self.level as usize].ambient;
+        if self.ambient.as_ref().map(|it| it.config) != config {
+            self.ambient = config.map(|config| AmbientParticles::new(
+                config,
+                resource_manager::get_shader(res_name!("particle")),
+                resource_manager::get_texture(res_name!("particle")),
+                self.width,
+                self.height
+            ));
+        }
+        if let Some(ambient) = self.ambient.as_mut() {
+            ambient.update(dt);
+        }
+    }
+
+    // the weather changes over to the one of the current level when another level is shown
+    fn update_weather(&mut self, dt: f32) {
+        let config = self.levels[self.level as usize].weather;
+        match self.weather.as_mut() {
+            Some(weather) if weather.level != config => {
+                weather.level = config;
+                weather.fade_to(config.unwrap_or_default(), weather::LEVEL_FADE);
+            }
+            None if config.is_some() => self.weather = Some(WeatherOverlay::new(config, self.width, self.height)),
+            _ => {}
+        }
+        if let Some(weather) = self.weather.as_mut() {
+            weather.update(dt);
+        }
+    }
+
+    // fades the weather to the given densities, e.g. from a script:
+    // `script.run(|game| game.fade_weather(Weather { rain: 1.0, fog: 0.0 }, 3.0))`
+    pub fn fade_weather(&mut self, weather: Weather, seconds: f32) {
+        let level = self.levels[self.level as usize].weather;
+        self.weather.get_or_insert_with(|| WeatherOverlay::new(level, self.width, self.height)).fade_to(weather, seconds);
+    }
+
+    // queues the game world (everything the post-processor gets to see); photos leave out
+    // the ghost ball
+    fn queue_scene<'a>(&'a self, queue: &mut RenderQueue<'a>, photo: bool) {
+        let renderer = self.renderer.as_ref().unwrap();
+        // draw background
+        queue.push(Layer::Background, move || renderer.draw_sprite_ex0(
+            &resource_manager::get_texture(res_name!("background")),
+            glm::vec2(0.0, 0.0),
+            glm::vec2(self.width as _, self.height as _)
+        ));
+        // draw the ambient particles in front of the background
+        if let Some(ambient) = self.ambient.as_ref() {
+            queue.push(Layer::Background, move || {
+                let _group = frame_capture::group("ambient");
+                ambient.draw();
+            });
+        }
+        // draw level
+        queue.push(Layer::Bricks, move || {
+            let _group = frame_capture::group("level");
+            self.levels[self.level as usize].draw(renderer);
+            self.draw_ceiling();
+        });
+        // draw particles
+        queue.push(Layer::Effects, move || {
+            let _group = frame_capture::group("particles");
+            self.aura.as_ref().unwrap().draw();
+            self.particles.as_ref().unwrap().draw();
+        });
+        queue.push(Layer::Effects, || self.draw_dash_streak());
+        queue.push(Layer::Effects, || self.draw_score_popups());
+        // draw player
+        queue.push(Layer::Ball, move || {
+            self.player.as_ref().unwrap().draw(renderer);
+            self.draw_catch_meter();
+        });
+        // draw PowerUps
+        queue.push(Layer::Ball, move || for power_up in self.power_ups.iter() {
+            if !power_up.game_obj.destroyed {
+                power_up.draw(renderer);
+            }
+        });
+        // draw the predicted landing spot of the ball
+        if self.settings.ghost_ball && self.states[0].id() == GameState::Active && !photo {
+            queue.push(Layer::Ball, || self.draw_ghost_ball());
+        }
+        // draw ball
+        queue.push(Layer::Ball, move || self.ball.as_ref().unwrap().draw(renderer));
+    }
+
+    // draws with everything outside of the given rect clipped away
+    fn clipped(&self, rect: Rect, draw: impl FnOnce()) {
+        self.clip.borrow_mut().push(rect);
+        draw();
+        self.clip.borrow_mut().pop();
+    }
+
+    // draws a background panel behind menu text, shaded darker towards its bottom
+    fn draw_panel(&self, x: f32, y: f32, width: f32, height: f32) {
+        let texture = resource_manager::get_texture(res_name!("panel"));
+        let slice = resource_manager::find_slice(res_name!("panel")).unwrap_or(NineSlice::new(0.0, 0.0, 0.0, 0.0));
+        self.renderer.as_ref().unwrap().draw_nine_slice_gradient(
+            &texture,
+            &slice,
+            glm::vec2(x, y),
+            glm::vec2(width, height),
+            &Gradient::vertical(util::glm::scale_vec3(1.0), *PANEL_SHADE)
+        );
+    }
+
+    // the most recent warnings and errors of the log
+    fn render_debug_overlay(&self) {
+        let memory = resource_manager::gpu_memory();
+        let mib = |bytes: usize| bytes as f32 / (1024.0 * 1024.0);
+        let string = format!("GPU memory: textures {:.1} MiB, other {:.1} MiB, {} evicted", mib(memory.textures), mib(memory.other), memory.evicted);
+        self.render_hud_text(string, 5.0, 85.0, 0.5, util::glm::scale_vec3(1.0), HudAnchor::TopLeft);
+        for (i, entry) in logging::recent_entries(tracing::Level::WARN, DEBUG_OVERLAY_LINES).iter().enumerate() {
+            let color = if entry.level == tracing::Level::ERROR { glm::vec3(1.0, 0.3, 0.3) } else { glm::vec3(1.0, 1.0, 0.0) };
+            self.render_hud_text(entry.format(), 5.0, 100.0 + i as f32 * 15.0, 0.5, color, HudAnchor::TopLeft);
+        }
+    }
+
+    fn render_toasts(&self) {
+        for (i, (text, _)) in self.toasts.iter().enumerate() {
+            let y = self.height as f32 - 40.0 - i as f32 * 15.0;
+            self.render_hud_text(text.clone(), 5.0, y, 0.5, glm::vec3(1.0, 0.6, 0.2), HudAnchor::BottomLeft);
+        }
+    }
+
+    // draws fading copies of the paddle along the way it dashed
+    fn draw_dash_streak(&self) {
+        if !self.dash.is_dashing() {
+            return;
+        }
+        let player = self.player.as_ref().unwrap();
+        let direction = player.velocity.x.signum();
+        for i in (1..=DASH_STREAK_COPIES).rev() {
+            let fade = 1.0 - i as f32 / (DASH_STREAK_COPIES + 1) as f32;
+            self.renderer.as_ref().unwrap().draw_sprite_ex2(
+                &player.sprite,
+                player.position - glm::vec2(direction * i as f32 * DASH_STREAK_SPACING, 0.0),
+                player.size,
+                player.rotation,
+                player.color * (fade * 0.6)
+            );
+        }
+    }
+
+    // while the ball is caught, a bar along the bottom of the paddle shows how long until it is thrown
+    fn draw_catch_meter(&self) {
+        let ball = self.ball.as_ref().unwrap();
+        if ball.catch_time <= 0.0 {
+            return;
+        }
+        let player = self.player.as_ref().unwrap();
+        let left = ball.catch_time / simulation::CATCH_TIME;
+        self.renderer.as_ref().unwrap().draw_sprite_ex2(
+            &resource_manager::get_texture(res_name!("paddle")),
+            player.position + glm::vec2(0.0, player.size.y - CATCH_METER_HEIGHT),
+            glm::vec2(player.size.x * left, CATCH_METER_HEIGHT),
+            0.0,
+            glm::mix(&glm::vec3(1.0, 0.3, 0.2), &glm::vec3(1.0, 0.9, 0.3), left)
+        );
+    }
+
+    // dash cooldown meter below the FPS counter, lit up once a dash is ready
+    fn render_dash_meter(&self) {
+        let scale = self.ui_scale();
+        let readiness = self.dash.readiness();
+        let renderer = self.renderer.as_ref().unwrap();
+        let texture = resource_manager::get_texture(res_name!("paddle"));
+        let position = glm::vec2(5.0, 48.0) * scale;
+        let size = *DASH_METER_SIZE * scale;
+        renderer.draw_sprite_ex2(&texture, position, size, 0.0, util::glm::scale_vec3(0.2));
+        let color = if readiness >= 1.0 { glm::vec3(0.4, 0.9, 1.0) } else { util::glm::scale_vec3(0.6) };
+        renderer.draw_sprite_ex2(&texture, position, glm::vec2(size.x * readiness, size.y), 0.0, color);
+    }
+
+    // draws an intact breakable ceiling, fading towards the background as it takes hits
+    fn draw_ceiling(&self) {
+        let level = &self.levels[self.level as usize];
+        let Some(hp) = level.ceiling_hp().filter(|hp| *hp > 0) else {
+            return;
+        };
+        let strength = hp as f32 / level.walls.ceiling_hp as f32;
+        self.renderer.as_ref().unwrap().draw_sprite_ex2(
+            &resource_manager::get_texture(res_name!("block_solid")),
+            glm::vec2(0.0, 0.0),
+            glm::vec2(self.width as f32, CEILING_THICKNESS),
+            0.0,
+            glm::mix(&glm::vec3(0.3, 0.3, 0.3), &glm::vec3(0.8, 0.8, 0.7), strength)
+        );
+    }
+
+    // the ball bounced off the top edge, which damages a breakable ceiling
+    fn hit_ceiling(&mut self) {
+        let level = &mut self.levels[self.level as usize];
+        if level.ceiling_hp().is_none_or(|hp| hp == 0) {
+            return;
+        }
+        let ball = &self.ball.as_ref().unwrap().game_obj;
+        let pan = (ball.position.x + ball.size.x / 2.0) / self.width as f32 * 2.0 - 1.0;
+        if level.hit_ceiling() {
+            // the ceiling broke open
+            if self.effects.as_ref().unwrap().shake_intensity > 0.0 {
+                self.shake_time = 0.1;
+                self.effects.as_mut().unwrap().shake = true;
+            }
+            self.sound_engine.as_ref().unwrap().play_sound_ex("brick_hit", 1.0, 1.0, pan);
+        } else {
+            self.sound_engine.as_ref().unwrap().play_sound_ex("brick_hit_solid", 1.0, 1.0, pan);
+        }
+    }
+
+    // draws a faint ball where the ball will reach the height of the paddle
+    fn draw_ghost_ball(&self) {
+        let ball = self.ball.as_ref().unwrap();
+        if ball.stuck {
+            return;
+        }
+        let center = ball.game_obj.position + glm::vec2(ball.radius, ball.radius);
+        let target_y = self.player.as_ref().unwrap().position.y - ball.radius;
+        let walls = self.levels[self.level as usize].bounds();
+        if let Some(x) = predict_landing(center, ball.game_obj.velocity, ball.radius, target_y, &walls, self.width as f32) {
+            self.renderer.as_ref().unwrap().draw_sprite_ex2(
+                &ball.game_obj.sprite,
+                glm::vec2(x - ball.radius, target_y - ball.radius),
+                ball.game_obj.size,
+                0.0,
+                util::glm::scale_vec3(0.3)
+            );
+        }
+    }
+
+    pub fn do_collisions(&mut self) {
+        let mut spawn_power_ups_indexes: Vec<usize> = Vec::new();
+        let mut hit_indexes: Vec<usize> = Vec::new();
+        for (i, box_obj) in self.levels[self.level as usize].bricks.iter_mut().enumerate() {
+            if !box_obj.destroyed {
+                let collision = check_collision_1(self.ball.as_ref().unwrap(), box_obj);
+                if collision.0 { // if collision is true
+                    // pan the hit sound towards the side of the screen the brick is on
+                    let pan = (box_obj.position.x + box_obj.size.x / 2.0) / self.width as f32 * 2.0 - 1.0;
+                    match simulation::hit_brick(self.ball.as_mut().unwrap(), box_obj, &collision) {
+                        BrickHit::Destroyed => {
+                            spawn_power_ups_indexes.push(i);
+                            self.sound_engine.as_ref().unwrap().play_sound_ex("brick_hit", 1.0, 1.0, pan);
+                        }
+                        BrickHit::Damaged => {
+                            hit_indexes.push(i);
+                            self.sound_engine.as_ref().unwrap().play_sound_ex("brick_hit", 1.0, 1.0, pan);
+                        }
+                        BrickHit::Solid => {
+                            hit_indexes.push(i);
+                            // if block is solid, enable shake effect
+                            if self.effects.as_ref().unwrap().shake_intensity > 0.0 {
+                                self.shake_time = 0.05;
+                                self.effects.as_mut().unwrap().shake = true;
+                            }
+                            self.sound_engine.as_ref().unwrap().play_sound_ex("brick_hit_solid", 1.0, 1.0, pan);
+                        }
+                    }
+                }
+            }
+        }
+        let level = &self.levels[self.level as usize];
+        for i in spawn_power_ups_indexes.iter() {
+            let points = self.score.brick_destroyed(level.brick_points(*i));
+            let brick = &level.bricks[*i];
+            self.popups.spawn(points, self.score.combo, brick.position + brick.size / 2.0);
+        }
+        let rows = level.cleared_rows(&spawn_power_ups_indexes);
+        self.ball_speed.rows_cleared(rows, self.ball.as_mut().unwrap());
+        if hit_indexes.iter().chain(spawn_power_ups_indexes.iter()).any(|i| level.is_checkpoint(*i)) {
+            self.checkpoint = Some(Checkpoint {
+                level: self.levels[self.level as usize].snapshot(),
+                score: self.score
+            });
+        }
+        for i in hit_indexes {
+            self.levels[self.level as usize].flash_brick(i);
+        }
+        for i in spawn_power_ups_indexes {
+            self.levels[self.level as usize].dissolve_brick(i);
+            self.spawn_power_ups(i);
+        }
+
+        // also check collisions on PowerUps and if so, activate them
+        let mut activate_power_up_indexes: Vec<usize> = Vec::new();
+        for (i, power_up) in self.power_ups.iter_mut().enumerate() {
+            if !power_up.game_obj.destroyed {
+                // first check if powerup passed bottom edge, if so: keep as inactive and destroy
+                if power_up.game_obj.position.y >= self.height as f32 {
+                    power_up.game_obj.destroyed = true;
+                }
+
+                if check_collision(self.player.as_ref().unwrap(), &power_up.game_obj) {
+                    // collided with player, now activate powerup
+                    activate_power_up_indexes.push(i);
+                    power_up.game_obj.destroyed = true;
+                    power_up.activated = true;
+                    self.sound_engine.as_ref().unwrap().play_sound("power_up_collected");
+                }
+            }
+        }
+        for i in activate_power_up_indexes {
+            let (slot_position, slot_size) = self.power_up_slot(self.power_ups[i].type_str.as_str());
+            self.pickups.push(Pickup::new(&self.power_ups[i], slot_position, slot_size));
+            self.activate_power_up(i);
+        }
+
+        // and finally check collisions for player pad (unless stuck)
+        let catching = self.difficulty().catches_ball();
+        if simulation::bounce_off_paddle(self.ball.as_mut().unwrap(), self.player.as_ref().unwrap(), catching) {
+            // touching the paddle ends the combo
+            self.score.end_combo();
+            self.ball_speed.paddle_hit(self.ball.as_mut().unwrap());
+        }
+    }
+
+    fn rewind_frame(&self) -> RewindFrame {
+        let effects = self.effects.as_ref().unwrap();
+        RewindFrame {
+            level: self.level,
+            bricks: self.levels[self.level as usize].snapshot(),
+            player: self.player.as_ref().unwrap().as_ref().clone(),
+            ball: self.ball.as_ref().unwrap().as_ref().clone(),
+            power_ups: self.power_ups.clone(),
+            score: self.score,
+            lives: self.lives,
+            ball_speed: self.ball_speed,
+            dash: self.dash,
+            shake_time: self.shake_time,
+            confuse: effects.confuse,
+            chaos: effects.chaos,
+            shake: effects.shake,
+            rng: self.rng.clone()
+        }
+    }
+
+    // puts the simulation back into the step under the rewind cursor (particles are left as they are)
+    fn apply_rewind_frame(&mut self, frame: &RewindFrame) {
+        self.level = frame.level;
+        self.levels[frame.level as usize].restore(&frame.bricks);
+        **self.player.as_mut().unwrap() = frame.player.clone();
+        **self.ball.as_mut().unwrap() = frame.ball.clone();
+        self.power_ups = frame.power_ups.clone();
+        self.score = frame.score;
+        self.lives = frame.lives;
+        self.ball_speed = frame.ball_speed;
+        self.dash = frame.dash;
+        self.shake_time = frame.shake_time;
+        self.rng = frame.rng.clone();
+        let effects = self.effects.as_mut().unwrap();
+        effects.confuse = frame.confuse;
+        effects.chaos = frame.chaos;
+        effects.shake = frame.shake;
+    }
+
+    // reset
+    pub fn reset_level(&mut self) {
+        frame_log::mark("level_load", self.level + 1);
+        if let Some(daily) = self.daily.as_ref() {
+            self.levels[self.level as usize].load_state(&daily.level, self.width, self.height / 2);
+        } else if let Some(file) = LEVEL_FILES.get(self.level as usize) {
+            load_level(&mut self.levels[self.level as usize], file, self.width, self.height / 2);
+        }
+
+        self.lives = 3;
+        self.score = Score::new();
+        self.checkpoint = None;
+        self.tasks.clear();
+        self.banner = None;
+        self.tutorial = None;
+        self.popups.clear();
+    }
+
+    // flashes a line of text in the middle of the screen for a while
+    fn announce(&mut self, text: String, seconds: f32) {
+        self.announce_ex(text, seconds, None);
+    }
+
+    // like announce, with the text animated
+    fn announce_ex(&mut self, text: String, seconds: f32, effect: Option<TextEffect>) {
+        self.tasks.spawn(|script| async move {
+            let shown = text.clone();
+            script.run(move |game: &mut Game| game.banner = Some(Banner { text: shown, effect, shown: game.glfw.get_time() }));
+            script.wait(seconds).await;
+            // a newer banner stays up
+            script.run(move |game| if game.banner.as_ref().is_some_and(|banner| banner.text == text) {
+                game.banner = None;
+            });
+        });
+    }
+
+    // draws the banner announced last
+    pub fn render_banner(&self, banner: &Banner) {
+        let (x, y, color) = (300.0, self.height as f32 / 2.0 + 60.0, glm::vec3(1.0, 0.5, 0.2));
+        match banner.effect {
+            Some(effect) => {
+                let animation = TextAnimation::new(effect, (self.glfw.get_time() - banner.shown) as f32);
+                self.render_text_animated(banner.text.clone(), x, y, 1.0, color, animation);
+            }
+            None => self.render_text_ex(banner.text.clone(), x, y, 1.0, color)
+        }
+    }
+
+    // the daily challenge is the same for everyone
+    fn difficulty(&self) -> Difficulty {
+        if self.daily.is_some() { Difficulty::Normal } else { self.settings.difficulty }
+    }
+
+    // practice keeps the speed it is set to
+    fn speed_rules(&self) -> SpeedRules {
+        if self.practice { SpeedRules::CONSTANT } else { self.difficulty().speed_rules() }
+    }
+
+    // how fast the ball is served
+    fn launch_speed(&self) -> f32 {
+        let speed = if self.practice { self.practice_speed } else { 1.0 };
+        glm::length(&INITIAL_BALL_VELOCITY) * speed
+    }
+
+    pub fn reset_player(&mut self) {
+        // reset player/ball stats
+        self.player.as_mut().unwrap().size = PLAYER_SIZE.clone();
+        self.player.as_mut().unwrap().rotation = 0.0;
+        self.player.as_mut().unwrap().status.clear();
+        self.player.as_mut().unwrap().position = glm::vec2(self.width as f32 / 2.0 - PLAYER_SIZE.x / 2.0, self.height as f32 - PLAYER_SIZE.y);
+        let velocity = glm::normalize(&INITIAL_BALL_VELOCITY) * self.launch_speed();
+        self.ball.as_mut().unwrap().reset(self.player.as_ref().unwrap().position + glm::vec2(PLAYER_SIZE.x / 2.0 - BALL_RADIUS, -(BALL_RADIUS * 2.0)), velocity);
+        self.ball_speed = BallSpeed::new(self.speed_rules());
+        self.dash = Dash::default();
+        self.score.end_combo();
+        // also disable all active powerups
+        self.effects.as_mut().unwrap().chaos = false;
+        self.effects.as_mut().unwrap().confuse = false;
+        self.ball.as_mut().unwrap().pass_through = false;
+        self.ball.as_mut().unwrap().sticky = false;
+        self.player.as_mut().unwrap().color = util::glm::scale_vec3(1.0);
+        self.ball.as_mut().unwrap().game_obj.color = util::glm::scale_vec3(1.0);
+    }
+
+    // options
+    // whether ESC quits the game (other screens use it to go back)
+    pub fn escape_quits(&self) -> bool {
+        match self.state() {
+            // ESC resumes a paused game
+            GameState::Editor | GameState::Options | GameState::Paused | GameState::Credits | GameState::Demo => false,
+            GameState::Active => !self.playtest && !self.practice,
+            _ => true
+        }
+    }
+
+    // forwards the settings to the parts of the game they affect
+    fn apply_settings(&mut self) {
+        game_level::set_colorblind_palette(self.settings.colorblind);
+        gl_debug::set_settings(&self.settings.gl_debug);
+        let budget = self.settings.texture_budget_mb as usize * 1024 * 1024;
+        resource_manager::set_texture_budget(if budget > 0 { Some(budget) } else { None });
+        resource_manager::set_texture_set(if self.settings.colorblind { Some(patterns::COLORBLIND_SET) } else { None });
+        self.resize_effects();
+        if let Some(effects) = self.effects.as_mut() {
+            effects.shake_intensity = self.settings.shake_intensity();
+            effects.reduce_flashing = self.settings.photosensitive;
+            effects.tone_mapping = self.settings.tone_mapping;
+            effects.exposure = self.settings.exposure;
+        }
+        self.apply_ui_scale();
+        // rebuild the bricks with the new colors and textures, keeping their progress
+        for level in self.levels.iter_mut() {
+            let state = level.current_state();
+            level.load_state(&state, self.width, self.height / 2);
+        }
+    }
+
+    // the scale of the UI option, enlarged where the monitor's content scale would make it too small
+    fn ui_scale(&self) -> f32 {
+        self.settings.ui_scale * display::ui_scale_factor(self.viewport.height, self.height, self.content_scale)
+    }
+
+    fn apply_ui_scale(&mut self) {
+        let ui_scale = self.ui_scale();
+        if let Some(text) = self.text.as_mut() {
+            text.ui_scale = ui_scale;
+        }
+        if let Some(utf8_text) = self.utf8_text.as_mut() {
+            utf8_text.ui_scale = ui_scale;
+        }
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.ui_scale = ui_scale;
+        }
+    }
+
+    // called with the content scale of the monitor the window is on
+    pub fn set_content_scale(&mut self, scale: f32) {
+        self.content_scale = scale;
+        self.apply_ui_scale();
+        self.redraw = true;
+    }
+
+    // called when the framebuffer of the window is resized; the game keeps its size and is
+    // letterboxed into it, so the projections stay the same
+    pub fn resize(&mut self, width: i32, height: i32) {
+        // a minimized window has no framebuffer
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        self.viewport = Viewport::fit(self.width, self.height, width, height);
+        self.viewport.apply();
+        self.redraw = true;
+        self.clip.borrow_mut().set_viewport(self.viewport);
+        self.resize_effects();
+        self.apply_ui_scale();
+    }
+
+    // sets the projection of everything drawing the game world
+    fn set_scene_projection(&mut self, projection: &glm::Mat4) {
+        set_scene_projection(projection);
+        self.scene_projection = *projection;
+    }
+
+    // converts a point in framebuffer pixels to game coordinates
+    pub fn to_game(&self, x: f32, y: f32) -> (f32, f32) {
+        self.viewport.to_game(self.width, self.height, x, y)
+    }
+
+    // sets the sampler and projection of the shaders drawing the game world
+    fn configure_shaders(&self) {
+        let projection = glm::ortho(0.0, self.width as f32, self.height as f32, 0.0, -1.0, 1.0);
+        for name in SCENE_SHADERS {
+            resource_manager::get_shader(name).use_shader().set_integer("sprite", 0);
+        }
+        set_scene_projection(&projection);
+    }
+
+    // set render-specific controls
+    fn create_renderers(&mut self) {
+        let renderer = SpriteRenderer::new(resource_manager::get_shader(res_name!("sprite")));
+        let renderer = Box::new(renderer);
+        self.renderer = Some(renderer);
+        self.minimap = Some(Minimap::new(self.width, self.height));
+        let particles = ParticleGenerator::new(resource_manager::get_shader(res_name!("particle")), resource_manager::get_texture(res_name!("particle")), 500);
+        let particles = Box::new(particles);
+        self.particles = Some(particles);
+        let mut aura = ParticleGenerator::new(resource_manager::get_shader(res_name!("particle")), resource_manager::get_texture(res_name!("particle")), 300);
+        aura.spread = 15.0;
+        aura.life = 0.6;
+        self.aura = Some(Box::new(aura));
+        let text = TextRenderer::new(self.width, self.height);
+        let text = Box::new(text);
+        self.text = Some(text);
+        let utf8_text = UTF8TextRenderer::new(self.width, self.height);
+        let utf8_text = Box::new(utf8_text);
+        self.utf8_text = Some(utf8_text);
+        // the language screen is shown in English
+        self.load_fonts(Locale::En);
+        if self.locale != Locale::En {
+            self.load_fonts(self.locale);
+        }
+    }
+
+    // switches the language, loading the fonts it declares
+    pub fn set_locale(&mut self, locale: Locale) {
+        rust_i18n::set_locale(locale.code());
+        self.locale = locale;
+        self.load_fonts(locale);
+        self.request_redraw();
+    }
+
+    // loads the fonts declared by a language into the text renderers showing it, rebuilding their
+    // glyphs (the UTF-8 renderer always has them, it draws what the ASCII one has no glyphs for)
+    fn load_fonts(&mut self, locale: Locale) {
+        let chain = FontChain::of_locale(locale.code());
+        if locale == Locale::En {
+            self.text.as_mut().unwrap().load_fonts(&chain.paths(), chain.size);
+        }
+        self.utf8_text.as_ref().unwrap().load_fonts(&chain.paths(), chain.size);
+    }
+
+    // recreates all GPU objects, e.g. after the driver dropped them on a display mode switch:
+    // the stored shaders and textures in place, everything owning vertex arrays or
+    // framebuffers anew (the ambient particles and the weather are spawned again as needed)
+    pub fn recreate_gpu_objects(&mut self) {
+        frame_log::mark("gpu_objects", "recreated");
+        resource_manager::recreate_all();
+        self.configure_shaders();
+        self.create_renderers();
+        for level in self.levels.iter_mut() {
+            level.recreate_batch();
+        }
+        self.ambient = None;
+        self.weather = None;
+        self.luts = Luts::new(filesystem::get_path(color_grading::LUTS_DIR.to_string()));
+        self.effects = None;
+        self.apply_settings();
+        self.update_post_effects();
+        self.request_redraw();
+    }
+
+    // creates the post-processor, or resizes its framebuffers, at the size of the viewport
+    // times the render scale; supersampling takes the place of MSAA
+    fn resize_effects(&mut self) {
+        let scale = self.settings.render_scale;
+        let (width, height) = ((self.viewport.width as f32 * scale).round() as u32, (self.viewport.height as f32 * scale).round() as u32);
+        let samples = if scale > 1.0 { 0 } else { gpu_caps::samples(4) };
+        match self.effects.as_mut() {
+            Some(effects) => effects.resize(width, height, samples),
+            None => self.effects = Some(Box::new(PostProcessor::new_ex(resource_manager::get_shader(res_name!("postprocessing")), width, height, samples, "post_processor")))
+        }
+    }
+
+    // switches the retro effects and color grading to those of the current level (or the retro filter)
+    fn update_post_effects(&mut self) {
+        let (effects, lut) = if self.settings.retro_filter {
+            (self.themes.effects(post_effects::SETTINGS_THEME), self.themes.lut(post_effects::SETTINGS_THEME))
+        } else {
+            (self.themes.level_effects(self.level as usize + 1), self.themes.level_lut(self.level as usize + 1))
+        };
+        let processor = self.effects.as_mut().unwrap();
+        processor.lut = lut.and_then(|name| self.luts.texture(name));
+        // with reduced flashing, chaos only takes over the area around the ball
+        let ball = self.ball.as_ref().unwrap();
+        processor.mask = (processor.chaos && processor.reduce_flashing)
+            .then(|| Mask::circle(ball.game_obj.position + util::glm::scale_vec2(ball.radius), CHAOS_MASK_RADIUS, self.width, self.height));
+        if processor.effects.as_slice() != effects {
+            processor.effects = effects.to_vec();
+        }
+    }
+
+    fn save_settings(&self) {
+        let path = filesystem::get_path(SETTINGS_FILE.to_string());
+        if let Err(e) = self.settings.save(path.as_str()) {
+            log::error!("SETTINGS: Failed to save {}: {}", path, e);
+        }
+    }
+
+    fn save_bindings(&mut self) {
+        let path = filesystem::get_path(BINDINGS_FILE.to_string());
+        if let Err(e) = self.bindings.save(path.as_str()) {
+            log::error!("BINDINGS: Failed to save {}: {}", path, e);
+        }
+    }
+
+    fn adjust_setting(&mut self, setting: Setting, direction: i32) {
+        self.settings.adjust(setting, direction);
+        self.apply_settings();
+        self.save_settings();
+    }
+
+    // menus
+    // the input of the current step as seen by the menus
+    fn ui_input(&self) -> UiInput {
+        self.ui_input_of(&self.input)
+    }
+
+    fn ui_input_of(&self, input: &InputState) -> UiInput {
+        UiInput {
+            up: input.pressed_repeat(Key::Up) || input.pressed_repeat(Key::W) || input.gamepad_just_pressed(GamepadButton::ButtonDpadUp),
+            down: input.pressed_repeat(Key::Down) || input.pressed_repeat(Key::S) || input.gamepad_just_pressed(GamepadButton::ButtonDpadDown),
+            left: input.pressed_repeat(Key::Left) || input.gamepad_just_pressed(GamepadButton::ButtonDpadLeft),
+            right: input.pressed_repeat(Key::Right) || input.gamepad_just_pressed(GamepadButton::ButtonDpadRight),
+            activate: input.just_pressed(Key::Enter) || input.gamepad_just_pressed(GamepadButton::ButtonA),
+            back: input.just_pressed(Key::Escape) || input.gamepad_just_pressed(GamepadButton::ButtonB),
+            cursor: input.cursor(),
+            cursor_moved: input.cursor_moved(),
+            click: input.mouse_just_pressed(MouseButton::Button1)
+        }
+    }
+
+    // starts a column of widgets laid out for a UI scale of 1.0; it's scaled relative to the center
+    fn ui_column<T: Copy + PartialEq>(&self, x: f32, y: f32, width: f32, row_height: f32) -> Column<T> {
+        let ui_scale = self.ui_scale();
+        let (screen_width, screen_height) = (self.width as f32, self.height as f32);
+        Column::new(
+            (screen_width / 2.0 + (x - screen_width / 2.0) * ui_scale).max(5.0),
+            screen_height / 2.0 + (y - screen_height / 2.0) * ui_scale,
+            width,
+            row_height,
+            ui_scale
+        )
+    }
+
+    fn main_menu(&self) -> Column<MenuItem> {
+        let mut menu = self.ui_column(250.0, self.height as f32 / 2.0 - 20.0, 300.0, 28.0);
+        menu.button(MenuItem::Start, t!("menu.start").to_string());
+        menu.button(MenuItem::Level, t!("menu.level", level=self.level + 1).to_string());
+        menu.button(MenuItem::Practice, t!("menu.practice").to_string());
+        let code = daily::format_code(self.daily_seed.unwrap_or_else(|| daily::seed_for_date(daily::today())));
+        let label = match self.stats.best_daily(code.as_str()) {
+            Some(best) => t!("menu.daily_best", code=code, points=best),
+            None => t!("menu.daily", code=code)
+        };
+        menu.button(MenuItem::Daily, label.to_string());
+        menu.button(MenuItem::Editor, t!("menu.editor").to_string());
+        menu.button(MenuItem::Options, t!("menu.options").to_string());
+        menu.button(MenuItem::Credits, t!("menu.credits").to_string());
+        menu
+    }
+
+    fn pause_menu(&self) -> Column<PauseItem> {
+        let mut menu = self.ui_column(250.0, self.height as f32 / 2.0 - 30.0, 300.0, 28.0);
+        menu.button(PauseItem::Resume, t!("pause.resume").to_string());
+        menu.button(PauseItem::Photo, t!("pause.photo").to_string());
+        menu.button(PauseItem::Restart, t!("pause.restart").to_string());
+        menu.button(PauseItem::Quit, t!("pause.quit").to_string());
+        menu
+    }
+
+    // draws a menu on a panel
+    fn render_menu<T: Copy + PartialEq>(&self, menu: &Column<T>) {
+        let bounds = menu.bounds().expand(10.0 * menu.scale);
+        self.draw_panel(bounds.x, bounds.y, bounds.width, bounds.height);
+        ui::draw(menu.widgets(), &self.ui, self.renderer.as_ref().unwrap(), &|text, x, y, scale, color| {
+            self.render_hud_text(text, x, y, scale, color, HudAnchor::Absolute);
+        });
+    }
+
+    // starts the level being played over
+    fn restart_level(&mut self) {
+        self.set_state(ActiveState::new());
+        if self.playtest {
+            self.stop_playtest();
+            self.start_playtest();
+        } else if self.practice {
+            self.restart_practice();
+        } else {
+            self.finish_speedrun(false);
+            self.reset_level();
+            self.power_ups.clear();
+            self.reset_player();
+            self.start_tutorial();
+        }
+    }
+
+    // leaves the level being played for the menu (or the editor when playtesting)
+    fn quit_level(&mut self) {
+        if self.playtest {
+            self.stop_playtest();
+        } else if self.practice {
+            self.stop_practice();
+        } else {
+            self.finish_speedrun(false);
+            // a daily challenge left early doesn't count
+            self.daily = None;
+            self.reset_level();
+            self.power_ups.clear();
+            self.reset_player();
+            self.effects.as_mut().unwrap().shake = false;
+            self.ui.reset();
+            self.set_state(MenuState::default());
+        }
+    }
+
+    // window events
+    pub fn on_focus_changed(&mut self, focused: bool) {
+        self.window_focused = focused;
+        if !focused {
+            self.auto_pause();
+        }
+        self.update_ducking();
+        self.redraw = true;
+    }
+
+    pub fn on_iconified(&mut self, iconified: bool) {
+        self.window_iconified = iconified;
+        if iconified {
+            self.auto_pause();
+        } else {
+            // don't simulate the time the window spent minimized
+            self.reset_frame_time = true;
+        }
+        self.update_ducking();
+        self.redraw = true;
+    }
+
+    fn auto_pause(&mut self) {
+        if self.state() == GameState::Active && self.autopilot.is_none() {
+            self.push_state(PausedState::default());
+        }
+        // keys released while the window had no focus never reach the key callback
+        self.input.clear();
+    }
+
+    // mutes the audio while minimized and turns it down while in the background
+    fn update_ducking(&mut self) {
+        let duck = if self.window_iconified {
+            0.0
+        } else if !self.window_focused {
+            0.3
+        } else {
+            1.0
+        };
+        if let Some(sound_engine) = self.sound_engine.as_mut() {
+            sound_engine.set_duck(duck);
+        }
+    }
+
+    // paddle input
+    // the input of a step from the player's keys (or the autopilot)
+    fn paddle_input(&mut self, dt: f32) -> StepInput {
+        let view = self.control_view();
+        match self.autopilot.as_mut() {
+            Some(autopilot) => autopilot.control(&view, dt),
+            None => PlayerController { input: &self.input, bindings: &self.bindings }.control(&view, dt)
+        }
+    }
+
+    // moves, dashes and tilts the paddle by the input of a step (from the keys or a controller)
+    fn drive_paddle(&mut self, input: StepInput, dt: f32) {
+        // a slowed paddle moves, dashes and tilts at a fraction of its speed, a frozen one not at all
+        let pace = self.player.as_ref().unwrap().status.speed_factor();
+        let velocity = PLAYER_VELOCITY * dt * pace;
+        // move playerboard
+        if input.left {
+            simulation::move_paddle(self.player.as_mut().unwrap(), self.ball.as_mut().unwrap(), -velocity, self.width as f32);
+        }
+        if input.right {
+            simulation::move_paddle(self.player.as_mut().unwrap(), self.ball.as_mut().unwrap(), velocity, self.width as f32);
+        }
+        // dashes go towards the held direction, and not out of a frozen paddle
+        let held = input.right as i32 - input.left as i32;
+        if input.dash && held != 0 && pace != 0.0 {
+            self.dash.start(held as f32);
+        }
+        let distance = self.dash.update(dt) * pace;
+        if distance != 0.0 {
+            simulation::dash_paddle(self.player.as_mut().unwrap(), self.ball.as_mut().unwrap(), distance, self.width as f32);
+        }
+        // tilting the paddle changes the angle the ball bounces off at
+        let tilt = input.tilt_right as i32 - input.tilt_left as i32;
+        simulation::tilt_paddle(self.player.as_mut().unwrap(), tilt as f32, dt * pace);
+        // the velocity isn't used to move the paddle, but it is what the dash streak is drawn from
+        self.player.as_mut().unwrap().velocity.x = (held as f32 * PLAYER_VELOCITY + self.dash.velocity()) * pace;
+        // a caught ball is thrown on launch or once it has been held for long enough
+        simulation::update_catch(self.ball.as_mut().unwrap(), self.player.as_ref().unwrap(), dt, input.launch);
+    }
+
+    // demo
+    fn start_demo(&mut self) {
+        self.restart_demo();
+        self.set_state(DemoState::new());
+    }
+
+    fn restart_demo(&mut self) {
+        self.reset_level();
+        self.power_ups.clear();
+        self.reset_player();
+    }
+
+    fn stop_demo(&mut self) {
+        self.restart_demo();
+        self.effects.as_mut().unwrap().shake = false;
+        self.ui.reset();
+        self.set_state(MenuState::default());
+    }
+
+    // what the controller of the demo gets to see
+    fn control_view(&self) -> View {
+        View::of(self.player.as_ref().unwrap(), self.ball.as_ref().unwrap(), self.width as f32, self.levels[self.level as usize].bounds())
+    }
+
+    // credits
+    fn open_credits(&mut self) {
+        let sections = credits::load(filesystem::get_path(CREDITS_FILE.to_string()).as_str());
+        self.set_state(CreditsState::new(Credits::new(&sections)));
+    }
+
+    fn close_credits(&mut self) {
+        self.ui.reset();
+        self.set_state(MenuState::default());
+    }
+
+    // the credits are always drawn by the UTF-8 renderer, names may be in any script
+    fn render_credits(&self, credits: &Credits) {
+        let renderer = self.utf8_text.as_deref().unwrap();
+        let width = self.width as f32;
+        // the lines scroll out before they reach the hint
+        self.clipped(Rect::new(0.0, 0.0, width, self.height as f32 - CREDITS_HINT_SPACE), || {
+            for (line, y) in credits.visible(self.height as f32) {
+                let (scale, color) = match line.kind {
+                    LineKind::Title => (1.0, glm::vec3(1.0, 1.0, 0.0)),
+                    LineKind::Text => (0.75, util::glm::scale_vec3(1.0))
+                };
+                let x = (width - renderer.text_width(line.text.as_str(), scale)) / 2.0;
+                renderer.render_text_ex(line.text.clone(), x.max(5.0), y, scale, color);
+            }
+        });
+        self.render_hud_text(t!("credits.hint").to_string(), 5.0, self.height as f32 - 20.0, 0.5, glm::vec3(0.5, 0.5, 0.5), HudAnchor::BottomLeft);
+    }
+
+    // level editor
+    fn open_editor(&mut self) {
+        let index = self.level as usize;
+        let file = filesystem::get_path(edited_level_file(LEVEL_FILES[index]));
+        // continues with the edits saved before, if any
+        let level = fs::read_to_string(&file).ok()
+            .and_then(|source| LevelFile::parse(source.as_str())
+                .inspect_err(|e| log::warn!("EDITOR: Ignoring the edited level {}: {}", file, e))
+                .ok())
+            .unwrap_or_else(|| self.levels[index].current_state());
+        let editor = LevelEditor::new(level, file, self.width, self.height / 2);
+        self.editor = Some(Box::new(editor));
+        self.set_state(EditorState);
+    }
+
+    // leaves the editor, carrying the edits over to the level being played
+    fn close_editor(&mut self) {
+        if let Some(editor) = self.editor.take() {
+            self.levels[self.level as usize].load_state(&editor.level, self.width, self.height / 2);
+        }
+        self.set_state(MenuState::default());
+    }
+
+    // plays the edited level; the editor (and its cursor) is kept for when the playtest ends
+    fn start_playtest(&mut self) {
+        let level = &self.editor.as_ref().unwrap().level;
+        if !level.tiles.iter().flatten().any(|code| brick_type::is_destructible(*code)) {
+            log::warn!("EDITOR: Can't playtest a level without destructible bricks");
+            return;
+        }
+        self.levels[self.level as usize].load_state(level, self.width, self.height / 2);
+        self.power_ups.clear();
+        self.reset_player();
+        self.lives = 3;
+        self.playtest = true;
+        self.set_state(ActiveState::new());
+    }
+
+    // short description of what the game is doing, for crash reports
+    pub fn state_summary(&self) -> String {
+        format!(
+            "{:?}, level {}, lives {}, bricks left {}, power-ups {}, playtest {}, practice {}",
+            self.states.iter().map(|state| state.id()).collect::<Vec<GameState>>(),
+            self.level + 1,
+            self.lives,
+            self.levels.get(self.level as usize).map(|level| level.bricks_left()).unwrap_or(0),
+            self.power_ups.len(),
+            self.playtest,
+            self.practice
+        )
+    }
+
+    // reports what the player is doing to discord
+    #[cfg(feature = "discord")]
+    pub fn update_presence(&mut self) {
+        if self.presence.is_none() {
+            return;
+        }
+        let level = self.level + 1;
+        let activity = match self.state() {
+            GameState::SelectLanguage | GameState::Menu | GameState::Credits | GameState::Demo => Activity { details: t!("presence.menu").to_string(), state: None },
+            GameState::Options => Activity { details: t!("presence.options").to_string(), state: None },
+            GameState::Editor => Activity { details: t!("presence.editor").to_string(), state: None },
+            GameState::Win => Activity { details: t!("presence.win", level=level).to_string(), state: None },
+            GameState::Active | GameState::Paused => {
+                let details = if self.practice {
+                    t!("presence.practice", level=level)
+                } else if self.playtest {
+                    t!("presence.playtest")
+                } else if self.daily.is_some() {
+                    t!("presence.daily")
+                } else {
+                    t!("presence.level", level=level)
+                };
+                let bricks = self.levels[self.level as usize].bricks_left();
+                Activity { details: details.to_string(), state: Some(t!("presence.bricks_left", count=bricks).to_string()) }
+            }
+        };
+        self.presence.as_mut().unwrap().update(activity);
+    }
+
+    // devtools
+    // toggles the inspector and, while it's open, runs a frame of it; returns true if it took the
+    // input of the game window (a detached inspector has input of its own)
+    #[cfg(feature = "devtools")]
+    fn process_devtools_input(&mut self, dt: f32) -> bool {
+        let pressed = |key| self.input.just_pressed(key) || self.debug_window.as_ref().is_some_and(|it| it.input.just_pressed(key));
+        if pressed(Key::F4) {
+            self.devtools.open = !self.devtools.open;
+            self.redraw = true;
+        }
+        if let Some(debug_window) = self.debug_window.as_mut() {
+            debug_window.input.end_step(dt);
+        }
+        if !self.devtools.open {
+            return false;
+        }
+        let egui = match self.debug_window.as_mut() {
+            Some(debug_window) if self.devtools.detached => &mut debug_window.egui,
+            // (the egui of the game window is set up once the inspector is opened)
+            _ => match self.egui.as_mut() {
+                Some(egui) => egui,
+                None => return false
+            }
+        };
+        let mut world = World {
+            player: self.player.as_deref_mut().unwrap(),
+            ball: self.ball.as_deref_mut().unwrap(),
+            power_ups: &mut self.power_ups,
+            particles: self.particles.as_deref_mut().unwrap(),
+            effects: self.effects.as_deref_mut().unwrap()
+        };
+        let devtools = &mut self.devtools;
+        if egui.run(|ctx| devtools.show(ctx, &mut world)) {
+            self.redraw = true;
+        }
+        !self.devtools.detached && egui.wants_input()
+    }
+
+    // hands an event of the game window to the inspector while it's shown over the game
+    #[cfg(feature = "devtools")]
+    pub fn devtools_event(&mut self, event: &glfw::WindowEvent) {
+        if let Some(egui) = self.egui.as_mut().filter(|_| self.devtools.open && !self.devtools.detached) {
+            egui.handle_event(event);
+        }
+    }
+
+    // sets up egui in the game window, opens or closes the window of a detached inspector and
+    // takes its events
+    #[cfg(feature = "devtools")]
+    pub fn poll_devtools(&mut self, main: &mut glfw::Window) {
+        if self.devtools.open && self.egui.is_none() {
+            self.egui = Egui::new(main);
+        }
+        if let Some(egui) = self.egui.as_mut() {
+            egui.fit(main);
+        }
+        let detached = self.devtools.open && self.devtools.detached;
+        if detached && self.debug_window.is_none() {
+            self.debug_window = DebugWindow::open(main);
+            if self.debug_window.is_none() {
+                log::error!("DEVTOOLS: Failed to open the inspector window");
+                self.devtools.detached = false;
+            }
+        } else if !detached {
+            self.close_debug_window(main);
+        }
+        if let Some(debug_window) = self.debug_window.as_mut() {
+            debug_window.poll();
+            // closing the window attaches the inspector to the game again
+            if debug_window.window.should_close() {
+                self.devtools.detached = false;
+                self.close_debug_window(main);
+            }
+        }
+    }
+
+    // paints the inspector over the rendered frame of the game window
+    #[cfg(feature = "devtools")]
+    pub fn render_devtools(&mut self) {
+        if !self.devtools.open || self.devtools.detached {
+            return;
+        }
+        if let Some(egui) = self.egui.as_mut() {
+            egui.paint();
+            self.viewport.apply();
+        }
+    }
+
+    #[cfg(feature = "devtools")]
+    pub fn render_debug_window(&mut self, main: &mut glfw::Window) {
+        if let Some(debug_window) = self.debug_window.as_mut() {
+            debug_window.render(main);
+        }
+    }
+
+    #[cfg(feature = "devtools")]
+    fn close_debug_window(&mut self, main: &mut glfw::Window) {
+        if let Some(debug_window) = self.debug_window.take() {
+            debug_window.close(main);
+        }
+    }
+
+    // deletes the GPU objects of the inspector, with the context of the game window current
+    #[cfg(feature = "devtools")]
+    pub fn close_devtools(&mut self, main: &mut glfw::Window) {
+        self.close_debug_window(main);
+        if let Some(egui) = self.egui.take() {
+            egui.destroy();
+        }
+    }
+
+    // inspector
+    // resolves the object a property path starts with ("player", "ball" or "powerup.<index>"),
+    // returning it with the rest of the path
+    fn inspect_target<'a>(&self, path: &'a str) -> Result<(&dyn Inspect, &'a str), String> {
+        let (root, rest) = path.split_once('.').unwrap_or((path, ""));
+        match root {
+            "player" => Ok((self.player.as_deref().unwrap(), rest)),
+            "ball" => Ok((self.ball.as_deref().unwrap(), rest)),
+            "powerup" => {
+                let (index, rest) = rest.split_once('.').unwrap_or((rest, ""));
+                let power_up = index.parse::<usize>().ok().and_then(|i| self.power_ups.get(i)).ok_or_else(|| format!("no power-up \"{}\"", index))?;
+                Ok((power_up, rest))
+            }
+            _ => Err(format!("unknown object \"{}\"", root))
+        }
+    }
+
+    fn inspect_target_mut<'a>(&mut self, path: &'a str) -> Result<(&mut dyn Inspect, &'a str), String> {
+        let (root, rest) = path.split_once('.').unwrap_or((path, ""));
+        match root {
+            "player" => Ok((self.player.as_deref_mut().unwrap(), rest)),
+            "ball" => Ok((self.ball.as_deref_mut().unwrap(), rest)),
+            "powerup" => {
+                let (index, rest) = rest.split_once('.').unwrap_or((rest, ""));
+                let power_up = index.parse::<usize>().ok().and_then(|i| self.power_ups.get_mut(i)).ok_or_else(|| format!("no power-up \"{}\"", index))?;
+                Ok((power_up, rest))
+            }
+            _ => Err(format!("unknown object \"{}\"", root))
+        }
+    }
+
+    // runs an inspector command such as "ball.velocity.x = 300", returning the resulting value
+    pub fn inspect(&mut self, line: &str) -> Result<String, String> {
+        match inspect::parse_command(line)? {
+            Command::Get(path) => {
+                let (target, property) = self.inspect_target(path)?;
+                // a bare object lists all of its properties
+                if property.is_empty() {
+                    Ok(inspect::describe(target))
+                } else {
+                    inspect::get(target, property).map(|value| value.to_string())
+                }
+            }
+            Command::Set(path, value) => {
+                let (target, property) = self.inspect_target_mut(path)?;
+                inspect::set(target, property, value).map(|value| value.to_string())
+            }
+        }
+    }
+
+    // speedrun
+    // records the split of the cleared level and moves the run on to the next level;
+    // returns false once the last level was cleared
+    fn advance_speedrun(&mut self) -> bool {
+        let level = self.level;
+        self.speedrun.as_mut().unwrap().complete_level(level);
+        if level as usize + 1 >= self.levels.len() {
+            return false;
+        }
+        self.reset_level();
+        self.level += 1;
+        self.power_ups.clear();
+        self.reset_player();
+        true
+    }
+
+    // ends the current run, updating the personal bests and exporting the run; returns the total
+    // time of a completed run and the personal best before it
+    fn finish_speedrun(&mut self, completed: bool) -> Option<(f32, Option<f32>)> {
+        let run = self.speedrun.take()?;
+        let record = run.record(completed);
+        let result = completed.then(|| (record.total, self.stats.best_run(record.start_level)));
+        self.stats.update(&record);
+        let path = filesystem::get_path(STATS_FILE.to_string());
+        if let Err(e) = self.stats.save(path.as_str()) {
+            log::error!("STATS: Failed to save {}: {}", path, e);
+        }
+        match run.export(completed, filesystem::get_path(RUNS_DIR.to_string()).as_str()) {
+            Ok(path) => log::info!("SPEEDRUN: Exported run to {}", path),
+            Err(e) => log::error!("SPEEDRUN: Failed to export run: {}", e)
+        }
+        result
+    }
+
+    // timer of the run and the last split compared to the personal best
+    fn render_speedrun(&self, run: &Speedrun) {
+        let white = util::glm::scale_vec3(1.0);
+        let string = t!("game_state.speedrun_time", time=speedrun::format_time(run.time())).to_string();
+        self.render_hud_text(string, 5.0, 45.0, 0.75, white, HudAnchor::TopLeft);
+        if let Some(split) = run.splits().last() {
+            let (delta, color) = match self.stats.best_split(split.level) {
+                Some(best) if split.time <= best => (format!(" ({})", speedrun::format_delta(split.time - best)), glm::vec3(0.0, 1.0, 0.0)),
+                Some(best) => (format!(" ({})", speedrun::format_delta(split.time - best)), glm::vec3(1.0, 0.3, 0.3)),
+                None => (String::new(), white)
+            };
+            let string = t!("game_state.speedrun_split", level=split.level + 1, time=speedrun::format_time(split.time), delta=delta).to_string();
+            self.render_hud_text(string, 5.0, 65.0, 0.75, color, HudAnchor::TopLeft);
+        }
+    }
+
+    // photo mode
+    // renders the scene into a supersampled offscreen target and saves it as a screenshot
+    fn save_photo(&self) {
+        let effects = self.effects.as_ref().unwrap();
+        let (width, height) = (self.width * PHOTO_SCALE, self.height * PHOTO_SCALE);
+        let mut target = PostProcessor::new_ex(effects.post_processing_shader, width, height, 4, "photo");
+        target.copy_options(effects);
+        // the shake is left out of the photo
+        target.shake = false;
+        let mut queue = RenderQueue::new();
+        self.queue_scene(&mut queue, true);
+        target.begin_render();
+        queue.flush(render_queue::SCENE);
+        target.end_render();
+        let pixels = target.capture(self.glfw.get_time() as f32);
+        let Some(image) = photo::image_from_gl(width, height, pixels) else {
+            log::error!("PHOTO: Failed to read back the screenshot");
+            return;
+        };
+        match photo::save_screenshot(&image, filesystem::get_path(PHOTOS_DIR.to_string()).as_str()) {
+            Ok(path) => log::info!("PHOTO: Saved {}", path),
+            Err(e) => log::error!("PHOTO: Failed to save the screenshot: {}", e)
+        }
+    }
+
+    fn render_photo_hints(&self, photo: &PhotoMode) {
+        if !photo.show_hints {
+            return;
+        }
+        let filter = t!(format!("photo.filter_{}", photo.filter.name())).to_string();
+        let string = t!("photo.hints_0", zoom=format!("{:.1}", photo.camera.zoom), filter=filter).to_string();
+        self.render_hud_text(string, 5.0, self.height as f32 - 40.0, 0.5, glm::vec3(1.0, 1.0, 0.0), HudAnchor::BottomLeft);
+        self.render_hud_text(t!("photo.hints_1").to_string(), 5.0, self.height as f32 - 25.0, 0.5, glm::vec3(1.0, 1.0, 0.0), HudAnchor::BottomLeft);
+    }
+
+    // daily challenge
+    fn start_daily(&mut self) {
+        let daily = match self.daily_seed {
+            Some(seed) => Daily::new(seed, &self.drops),
+            None => Daily::today(&self.drops)
+        };
+        log::info!("DAILY: Playing {} ({})", daily.code(), daily.date);
+        self.daily = Some(daily);
+        self.reset_level();
+        self.power_ups.clear();
+        self.reset_player();
+        self.set_state(ActiveState::new());
+    }
+
+    // ends the daily challenge, keeping its score if it's the best for the seed
+    fn finish_daily(&mut self) {
+        let Some(daily) = self.daily.take() else {
+            return;
+        };
+        if self.stats.update_daily(daily.code().as_str(), self.score.points) {
+            let path = filesystem::get_path(STATS_FILE.to_string());
+            if let Err(e) = self.stats.save(path.as_str()) {
+                log::error!("STATS: Failed to save {}: {}", path, e);
+            }
+        }
+    }
+
+    // tutorial
+    // walks the player through the controls on the first level until it has been completed once;
+    // each step is shown until its action has been performed
+    fn start_tutorial(&mut self) {
+        if self.level != 0 || !self.settings.tutorial || self.tutorial.is_some() {
+            return;
+        }
+        self.tasks.spawn(|script| async move {
+            for step in tutorial::STEPS {
+                script.run(move |game: &mut Game| game.tutorial = Some(Tutorial::new(step, game.tutorial_progress(), game.glfw.get_time())));
+                script.until(|game: &Game| game.tutorial.is_some_and(|tutorial| tutorial.is_done(&game.tutorial_progress()))).await;
+            }
+            script.run(|game| {
+                game.tutorial = None;
+                game.settings.tutorial = false;
+                game.save_settings();
+                game.announce(t!("tutorial.done").to_string(), TUTORIAL_DONE_BANNER_TIME);
+            });
+        });
+    }
+
+    fn tutorial_progress(&self) -> tutorial::Progress {
+        tutorial::Progress {
+            paddle_x: self.player.as_ref().unwrap().position.x,
+            launched: !self.ball.as_ref().unwrap().stuck,
+            bricks_left: self.levels[self.level as usize].bricks_left()
+        }
+    }
+
+    // the highlighted area in texture coordinates of the post-processor (whose y axis points up)
+    fn spotlight(&self, highlight: Highlight) -> [f32; 4] {
+        let (position, size) = match highlight {
+            Highlight::Paddle => (self.player.as_ref().unwrap().position, self.player.as_ref().unwrap().size),
+            Highlight::Ball => (self.ball.as_ref().unwrap().game_obj.position, self.ball.as_ref().unwrap().game_obj.size),
+            Highlight::Bricks => (glm::vec2(0.0, 0.0), glm::vec2(self.width as f32, self.height as f32 / 2.0))
+        };
+        let margin = util::glm::scale_vec2(SPOTLIGHT_MARGIN);
+        let (min, max) = (position - margin, position + size + margin);
+        let (width, height) = (self.width as f32, self.height as f32);
+        [min.x / width, 1.0 - max.y / height, max.x / width, 1.0 - min.y / height]
+    }
+
+    fn render_tutorial(&self, tutorial: &Tutorial) {
+        let key = |action| bindings::key_name(self.bindings.key(action));
+        let string = t!(
+            format!("tutorial.{}", tutorial.step.id),
+            left=key(Action::MoveLeft),
+            right=key(Action::MoveRight),
+            launch=key(Action::Launch)
+        ).to_string();
+        let typewriter = TextEffect::Typewriter { chars_per_second: TUTORIAL_TYPING_SPEED };
+        let animation = TextAnimation::new(typewriter, (self.glfw.get_time() - tutorial.shown) as f32);
+        self.render_text_animated(string, 200.0, self.height as f32 / 2.0 + 100.0, 1.0, glm::vec3(1.0, 1.0, 0.0), animation);
+    }
+
+    // the text of the win screen, waving
+    pub fn render_win_text(&self) {
+        let animation = TextAnimation::new(WIN_TEXT_WAVE, self.glfw.get_time() as f32);
+        self.render_text_animated(t!("game_state.win_0").to_string(), 320.0, self.height as f32 / 2.0 - 20.0, 1.0, glm::vec3(0.0, 1.0, 0.0), animation);
+    }
+
+    // practice
+    fn start_practice(&mut self) {
+        self.practice = true;
+        self.set_state(ActiveState::new());
+        self.restart_practice();
+    }
+
+    // restarts the practice level from scratch (also used once it's cleared)
+    fn restart_practice(&mut self) {
+        self.reset_level();
+        self.power_ups.clear();
+        self.reset_player();
+    }
+
+    fn stop_practice(&mut self) {
+        self.practice = false;
+        self.restart_practice();
+        self.effects.as_mut().unwrap().shake = false;
+        self.set_state(MenuState::default());
+    }
+
+    // practice keys; keys bound to an action keep their gameplay meaning
+    fn process_practice_input(&mut self) {
+        let free = |game: &Self, key: Key| game.bindings.action_for(key).is_none() && game.input.just_pressed(key);
+        if free(self, Key::R) {
+            self.restart_practice();
+        }
+        for (direction, key) in [(1.0, Key::Up), (-1.0, Key::Down)] {
+            if free(self, key) {
+                let speed = (self.practice_speed + direction * PRACTICE_SPEED_STEP).clamp(PRACTICE_SPEED_MIN, PRACTICE_SPEED_MAX);
+                // keep the ball in flight, just faster or slower
+                self.ball.as_mut().unwrap().game_obj.velocity *= speed / self.practice_speed;
+                self.practice_speed = speed;
+            }
+        }
+        // number keys drop the matching power-up right above the paddle
+        let keys = [Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6];
+        for (key, (type_str, ..)) in keys.iter().zip(POWER_UP_TYPES.iter()) {
+            if free(self, *key) {
+                let player = self.player.as_ref().unwrap();
+                let position = glm::vec2(player.position.x + player.size.x / 2.0 - 30.0, player.position.y - 100.0);
+                self.power_ups.push(create_power_up(type_str, position).unwrap());
+            }
+        }
+    }
+
+    // returns from a playtest into the editor
+    fn stop_playtest(&mut self) {
+        self.playtest = false;
+        self.power_ups.clear();
+        self.reset_player();
+        self.lives = 3;
+        self.effects.as_mut().unwrap().shake = false;
+        self.levels[self.level as usize].load_state(&self.editor.as_ref().unwrap().level, self.width, self.height / 2);
+        self.set_state(EditorState);
+    }
+
+    fn render_editor(&self) {
+        let editor = self.editor.as_ref().unwrap();
+        self.renderer.as_ref().unwrap().draw_sprite_ex0(
+            &resource_manager::get_texture(res_name!("background")),
+            glm::vec2(0.0, 0.0),
+            glm::vec2(self.width as _, self.height as _)
+        );
+        editor.draw(self.renderer.as_ref().unwrap());
+        // the tile code of each brick, on the grid whatever the UI scale
+        let text = self.text.as_ref().unwrap();
+        text.set_space(TextSpace::World(self.scene_projection));
+        for (center, code) in editor.labels() {
+            let label = code.to_string();
+            let x = center.x - text.text_width(label.as_str(), 0.35) / 2.0;
+            let y = center.y - text.text_height(0.35) / 2.0;
+            text.render_text_ex(label, x, y, 0.35, glm::vec3(0.1, 0.1, 0.1));
+        }
+        text.set_space(TextSpace::Screen);
+        let string = t!(
+            "game_state.editor_0",
+            brush=editor.brush,
+            color=editor.palette_index,
+            x=editor.cursor.0,
+            y=editor.cursor.1,
+            undo=editor.undo_len(),
+            redo=editor.redo_len()
+        ).to_string();
+        let white = util::glm::scale_vec3(1.0);
+        self.render_hud_text(string, 5.0, self.height as f32 - 80.0, 0.75, white, HudAnchor::BottomLeft);
+        let mirror = match (editor.mirror.horizontal, editor.mirror.vertical) {
+            (true, true) => "H+V",
+            (true, false) => "H",
+            (false, true) => "V",
+            (false, false) => "-"
+        };
+        let string = t!("game_state.editor_3", tool=editor.tool.name(), mirror=mirror).to_string();
+        self.render_hud_text(string, 5.0, self.height as f32 - 60.0, 0.75, white, HudAnchor::BottomLeft);
+        self.render_hud_text(t!("game_state.editor_1").to_string(), 5.0, self.height as f32 - 40.0, 0.5, white, HudAnchor::BottomLeft);
+        self.render_hud_text(t!("game_state.editor_2").to_string(), 5.0, self.height as f32 - 25.0, 0.5, white, HudAnchor::BottomLeft);
+    }
+
+    // plays the level of a replay with its inputs (see visual_test)
+    pub fn start_replay(&mut self, trace: &Trace) -> Result<(), String> {
+        self.autopilot = Some(Box::new(ReplayController::new(trace)?));
+        self.set_locale(Locale::En);
+        self.level = trace.level as u32 - 1;
+        self.settings.difficulty = trace.difficulty;
+        self.seed_rng(trace.seed);
+        self.reset_level();
+        self.power_ups.clear();
+        self.reset_player();
+        self.set_state(ActiveState::new());
+        Ok(())
+    }
+
+    // makes the gameplay rolls (such as power-up drops) reproducible
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // music, post-processing and the ball trail follow the fever state of the score
+    fn update_fever(&mut self) {
+        let fever = self.score.fever;
+        self.effects.as_mut().unwrap().saturation = if fever { FEVER_SATURATION } else { 1.0 };
+        self.sound_engine.as_mut().unwrap().set_music_intensity(if fever { 1.0 } else { 0.0 });
+    }
+
+    // screen position and size of the HUD slot of a power-up type
+    fn power_up_slot(&self, type_str: &str) -> (glm::TVec2<f32>, glm::TVec2<f32>) {
+        let index = POWER_UP_TYPES.iter().position(|it| it.0 == type_str).unwrap_or(0);
+        let size = *POWER_UP_SLOT_SIZE * self.ui_scale();
+        let x = self.width as f32 - (POWER_UP_TYPES.len() - index) as f32 * (size.x + 5.0);
+        (glm::vec2(x, 5.0), size)
+    }
+
+    // spawns the auras of falling power-ups and moves collected ones towards the HUD
+    fn update_power_up_visuals(&mut self, dt: f32) {
+        let aura = self.aura.as_mut().unwrap();
+        for power_up in self.power_ups.iter().filter(|it| !it.game_obj.destroyed) {
+            aura.tint = power_up.rarity.color();
+            aura.emit(&power_up.game_obj, power_up.rarity.aura_particles(), power_up.game_obj.size / 2.0);
+        }
+        aura.advance(dt);
+        for pickup in self.pickups.iter_mut() {
+            pickup.update(dt);
+        }
+        self.pickups.retain(|pickup| !pickup.is_finished());
+    }
+
+    // the points of destroyed bricks, centered on where they rise in the world
+    // (only digits, which the English fonts have)
+    // drawn by the UTF-8 renderer, combos earn them an emoji
+    fn draw_score_popups(&self) {
+        let text = self.utf8_text.as_ref().unwrap();
+        text.set_space(TextSpace::World(self.scene_projection));
+        for popup in self.popups.active() {
+            let center = popup.position.value();
+            let x = center.x - text.text_width(popup.text.as_str(), popup.scale) / 2.0;
+            let y = center.y - text.text_height(popup.scale) / 2.0;
+            text.render_text_faded(popup.text.as_str(), x, y, popup.scale, popup.color, popup.fade.value());
+        }
+        text.set_space(TextSpace::Screen);
+    }
+
+    // icons of the active power-ups in the top right corner, and the ones flying there
+    fn render_power_up_slots(&self) {
+        let renderer = self.renderer.as_ref().unwrap();
+        for (type_str, color, _, texture, _) in POWER_UP_TYPES.iter() {
+            let active = self.power_ups.iter().any(|it| it.activated && it.type_str == *type_str);
+            let arriving = self.pickups.iter().any(|it| it.type_str == *type_str);
+            if active && !arriving {
+                let (position, size) = self.power_up_slot(type_str);
+                renderer.draw_sprite_ex2(&resource_manager::get_texture(*texture), position, size, 0.0, glm::vec3(color[0], color[1], color[2]));
+            }
+        }
+        for pickup in self.pickups.iter() {
+            pickup.draw(renderer);
+        }
+    }
+
+    // powerups
+    pub fn spawn_power_ups(&mut self, block_index: usize) {
+        let level = &self.levels[self.level as usize];
+        let position = level.bricks[block_index].position;
+        let (x, y) = level.brick_cells[block_index];
+        let tile_code = level.tiles[y][x];
+        // power-up spawners placed in the level always drop their power-up
+        let spawned = simulation::spawner_drops(level, block_index);
+        for type_str in spawned.iter() {
+            match create_power_up(type_str, position) {
+                Some(power_up) => self.power_ups.push(power_up),
+                None => log::warn!("Level contains a spawner of unknown power-up \"{}\"", type_str)
+            }
+        }
+        if !spawned.is_empty() {
+            return;
+        }
+        // the daily challenge decides the drops up front
+        if let Some(daily) = self.daily.as_ref() {
+            if let Some(type_str) = daily.drop_at(x, y) {
+                self.power_ups.push(create_power_up(type_str, position).unwrap());
+            }
+            return;
+        }
+        if let Some(type_str) = self.drops.roll(&mut self.rng, tile_code, self.level as usize + 1) {
+            self.power_ups.push(create_power_up(type_str.as_str(), position).unwrap());
+        }
+    }
+
+    pub fn update_power_ups(&mut self, dt: f32) {
+        let mut expired = Vec::new();
+        for power_up in self.power_ups.iter_mut() {
+            power_up.game_obj.position += power_up.game_obj.velocity * dt;
+            if power_up.activated {
+                power_up.duration -= dt;
+                
+                if power_up.duration <= 0.0 {
+                    // remove powerup from list (will later be removed)
+                    power_up.activated = false;
+                    expired.push(power_up.type_str.clone());
+                }
+            }
+        }
+        let mut target = EffectTarget {
+            ball: self.ball.as_mut().unwrap(),
+            player: self.player.as_mut().unwrap(),
+            screen: self.effects.as_deref_mut()
+        };
+        for effect in self.power_ups.iter().filter(|it| it.activated).filter_map(|it| find_effect(&it.type_str)) {
+            effect.on_update(&mut target, dt);
+        }
+        // deactivate effects
+        for type_str in expired {
+            // only reset if no other PowerUp of the same type is active
+            if let Some(effect) = find_effect(&type_str).filter(|_| !is_other_power_up_active(&self.power_ups, type_str.clone())) {
+                effect.on_expire(&mut target);
+            }
+        }
+        // Remove all PowerUps from vector that are destroyed AND !activated (thus either off the map or finished)
+        // Note we use a lambda expression to remove each PowerUp which is destroyed and not activated
+        self.power_ups.retain(|power_up| !(power_up.game_obj.destroyed && !power_up.activated));
+    }
+
+    fn activate_power_up(&mut self, power_up_index: usize) {
+        let type_str = self.power_ups[power_up_index].type_str.as_str();
+        frame_log::mark("power_up", type_str);
+        let Some(effect) = find_effect(type_str) else {
+            return;
+        };
+        effect.on_apply(&mut EffectTarget {
+            ball: self.ball.as_mut().unwrap(),
+            player: self.player.as_mut().unwrap(),
+            screen: self.effects.as_deref_mut()
+        });
+    }
+
+    fn render_text(
+        &self,
+        text: String,
+        x: f32,
+        y: f32,
+        scale: f32
+    ) {
+        self.render_text_ex(text, x, y, scale, util::glm::scale_vec3(1.0));
+    }
+
+    fn render_text_ex(
+        &self,
+        text: String,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>
+    ) {
+        self.render_hud_text(text, x, y, scale, color, HudAnchor::Center);
+    }
+
+    // like render_text_ex, with the glyphs animated
+    fn render_text_animated(
+        &self,
+        text: String,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        animation: TextAnimation
+    ) {
+        let (renderer, x, y, color) = self.place_hud_text(text.as_str(), x, y, scale, color, HudAnchor::Center);
+        renderer.render_text_animated(text, x, y, scale, color, animation);
+    }
+
+    // renders text laid out for a UI scale of 1.0; the position is scaled relative to the anchor
+    fn render_hud_text(
+        &self,
+        text: String,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        anchor: HudAnchor
+    ) {
+        let (renderer, x, y, color) = self.place_hud_text(text.as_str(), x, y, scale, color, anchor);
+        renderer.render_text_ex(text, x, y, scale, color);
+    }
+
+    // the renderer, screen position and color of a piece of HUD text (after drawing its backdrop)
+    fn place_hud_text(
+        &self,
+        text: &str,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        anchor: HudAnchor
+    ) -> (&dyn ITextRenderer, f32, f32, glm::TVec3<f32>) {
+        let ui_scale = self.ui_scale();
+        let (width, height) = (self.width as f32, self.height as f32);
+        let (x, y) = match anchor {
+            HudAnchor::TopLeft => (x * ui_scale, y * ui_scale),
+            HudAnchor::Center => ((width / 2.0 + (x - width / 2.0) * ui_scale).max(5.0), height / 2.0 + (y - height / 2.0) * ui_scale),
+            HudAnchor::BottomLeft => (x * ui_scale, height - (height - y) * ui_scale),
+            HudAnchor::Absolute => (x, y)
+        };
+        let renderer: &dyn ITextRenderer = match self.locale {
+            // e.g. the emoji of the lives aren't ASCII
+            Locale::En if text.is_ascii() => self.text.as_deref().unwrap(),
+            _ => self.utf8_text.as_deref().unwrap()
+        };
+        let mut color = color;
+        if self.settings.high_contrast {
+            // solid backdrop behind the text and fully saturated text colors
+            let padding = 3.0;
+            let size = glm::vec2(renderer.text_width(text, scale), renderer.text_height(scale)) / ui_scale + glm::vec2(padding * 2.0, padding * 2.0);
+            self.renderer.as_ref().unwrap().draw_hud_sprite(
+                &resource_manager::get_texture(res_name!("white")),
+                glm::vec2(x, y) - glm::vec2(padding, padding) * ui_scale,
+                size,
+                glm::vec3(0.0, 0.0, 0.0)
+            );
+            color = high_contrast_color(color);
+        }
+        (renderer, x, y, color)
+    }
+}
+
+impl Drop for Game {
+    // destructor
+    fn drop(&mut self) {
+        if let Some(it) = self.renderer.take() {
+            drop(it);
+        }
+        if let Some(it) = self.player.take() {
+            drop(it);
+        }
+        if let Some(it) = self.ball.take() {
+            drop(it);
+        }
+        if let Some(it) = self.particles.take() {
+            drop(it);
+        }
+        if let Some(it) = self.effects.take() {
+            drop(it);
+        }
+        if let Some(it) = self.text.take() {
+            drop(it);
+        }
+        if let Some(it) = self.utf8_text.take() {
+            drop(it);
+        }
+        if let Some(it) = self.sound_engine.take() {
+            drop(it);
+        }
+        if let Some(it) = self.editor.take() {
+            drop(it);
+        }
+    }
+}
\ No newline at end of file
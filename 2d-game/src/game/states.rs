@@ -0,0 +1,720 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::any::Any;
+use glfw::Key;
+use rust_i18n::t;
+use crate::bindings;
+use crate::bindings::{Action, BindError, Bindings, ACTIONS};
+use crate::controller::{PaddleController, PredictiveController, Skill};
+use crate::credits;
+use crate::credits::Credits;
+use crate::game::{Game, GameState, HudAnchor, Locale, MenuItem, OptionsItem, PauseItem, RewindFrame, DEMO_IDLE_TIME};
+use crate::photo;
+use crate::photo::PhotoMode;
+use crate::rewind::Rewind;
+use crate::settings::{SettingValue, SETTINGS};
+use crate::speedrun;
+use crate::speedrun::Speedrun;
+use crate::ui::{Column, UiEvent};
+
+// Position of the first row and height of the rows of the options screen
+const OPTIONS_TOP: f32 = 70.0;
+const OPTIONS_ROW_HEIGHT: f32 = 21.0;
+// Number of simulation steps kept for rewinding (ten seconds at 120 steps per second)
+const REWIND_STEPS: usize = 1200;
+const REWIND_SECONDS: f32 = 10.0;
+// Photo camera speeds, in pixels and zoom factor per second
+const PHOTO_PAN_SPEED: f32 = 300.0;
+const PHOTO_ZOOM_SPEED: f32 = 2.0;
+
+/// One state of the game's state machine, with the data only it needs. A state handles
+/// the input while it's on top of the state stack; the states below it are still
+/// rendered, so overlays like the pause menu are drawn over the game they pause.
+pub trait State: Any {
+    // which state this is
+    fn id(&self) -> GameState;
+    // handles the input of a frame, only called for the state on top of the stack
+    fn handle_input(&mut self, game: &mut Game, dt: f32);
+    // advances the state by a step, only called for the state on top of the stack
+    fn update(&mut self, _game: &mut Game, _dt: f32) {}
+    // draws the state over the ones below it
+    fn render(&self, _game: &Game) {}
+}
+
+pub struct SelectLanguageState;
+
+#[derive(Default)]
+pub struct MenuState {
+    // how long the menu has been left alone
+    idle: f32
+}
+
+#[derive(Default)]
+pub struct OptionsState {
+    // the action waiting for a key press
+    capturing: Option<Action>,
+    // why the last key couldn't be bound
+    message: Option<String>
+}
+
+pub struct ActiveState {
+    // recent simulation states to scrub through (F6)
+    rewind: Rewind<RewindFrame>
+}
+
+#[derive(Default)]
+pub struct PausedState {
+    // photo mode, opened from the pause menu
+    photo: Option<PhotoMode>
+}
+
+pub struct WinState {
+    // total and previous personal best of the speedrun finished with the win
+    speedrun_result: Option<(f32, Option<f32>)>
+}
+
+pub struct EditorState;
+
+pub struct CreditsState {
+    credits: Credits
+}
+
+pub struct DemoState {
+    // the controller playing the demo
+    controller: Box<dyn PaddleController>
+}
+
+// stands in for the state on top of the stack while it's taken off it (see with_top_state)
+struct Vacant(GameState);
+
+impl State for Vacant {
+    fn id(&self) -> GameState {
+        self.0
+    }
+
+    fn handle_input(&mut self, _game: &mut Game, _dt: f32) {}
+}
+
+// the state machine
+impl Game {
+    // the state on top of the stack, the one handling the input
+    pub fn state(&self) -> GameState {
+        self.states.last().unwrap().id()
+    }
+
+    // switches to another state, dropping any overlays
+    pub fn set_state(&mut self, state: impl State) {
+        self.states.clear();
+        self.states.push(Box::new(state));
+    }
+
+    // opens an overlay over the current state
+    pub fn push_state(&mut self, state: impl State) {
+        self.states.push(Box::new(state));
+    }
+
+    // closes the overlay on top, going back to the state below it
+    pub fn pop_state(&mut self) {
+        if self.states.len() > 1 {
+            self.states.pop();
+        }
+    }
+
+    // the topmost state of the given type, e.g. the pause menu over the game
+    pub(super) fn find_state<S: State>(&self) -> Option<&S> {
+        self.states.iter().rev().find_map(|state| (state.as_ref() as &dyn Any).downcast_ref::<S>())
+    }
+
+    // runs f with the state on top of the stack taken off it, so the state can change both
+    // itself and the game; it's put back unless it was left in the meantime (set_state or
+    // pop_state). Until then its data can't be found on the stack.
+    pub(super) fn with_top_state<R>(&mut self, f: impl FnOnce(&mut dyn State, &mut Game) -> R) -> R {
+        let depth = self.states.len() - 1;
+        let vacant = Box::new(Vacant(self.states[depth].id()));
+        let mut state = std::mem::replace(&mut self.states[depth], vacant);
+        let result = f(state.as_mut(), self);
+        if self.states.get(depth).is_some_and(|it| (it.as_ref() as &dyn Any).is::<Vacant>()) {
+            self.states[depth] = state;
+        }
+        result
+    }
+
+    // photo mode, if it's open
+    pub(super) fn photo(&self) -> Option<&PhotoMode> {
+        self.find_state::<PausedState>().and_then(|paused| paused.photo.as_ref())
+    }
+
+    // whether the options screen waits for a key to bind
+    pub fn is_capturing(&self) -> bool {
+        self.find_state::<OptionsState>().is_some_and(|options| options.capturing.is_some())
+    }
+
+    // binds the key pressed while capturing to the selected action; ESC cancels
+    pub fn capture_key(&mut self, key: Key) {
+        self.with_top_state(|state, game| {
+            if let Some(options) = (state as &mut dyn Any).downcast_mut::<OptionsState>() {
+                options.capture_key(game, key);
+            }
+        });
+    }
+}
+
+impl State for SelectLanguageState {
+    fn id(&self) -> GameState {
+        GameState::SelectLanguage
+    }
+
+    fn handle_input(&mut self, game: &mut Game, _dt: f32) {
+        if game.input.just_pressed(Key::Num1) {
+            game.set_locale(Locale::En);
+            game.set_state(MenuState::default());
+        }
+        if game.input.just_pressed(Key::Num2) {
+            game.set_locale(Locale::ZhCN);
+            game.set_state(MenuState::default());
+        }
+    }
+
+    fn update(&mut self, game: &mut Game, _dt: f32) {
+        game.update_world(game.world_dt);
+    }
+
+    fn render(&self, game: &Game) {
+        game.render_text(t!("game_state.select_language_0").to_string(), 250.0, game.height as f32 / 2.0, 1.0);
+        game.render_text(t!("game_state.select_language_1").to_string(), 245.0, game.height as f32 / 2.0 + 20.0, 0.75);
+        game.render_text(t!("game_state.select_language_2").to_string(), 245.0, game.height as f32 / 2.0 + 40.0, 0.75);
+    }
+}
+
+impl State for MenuState {
+    fn id(&self) -> GameState {
+        GameState::Menu
+    }
+
+    fn handle_input(&mut self, game: &mut Game, _dt: f32) {
+        let input = game.ui_input();
+        let menu = game.main_menu();
+        let level_count = game.levels.len() as u32;
+        match game.ui.update(menu.widgets(), &input) {
+            Some(UiEvent::Activate(MenuItem::Start)) => {
+                game.set_state(ActiveState::new());
+                game.start_tutorial();
+            }
+            Some(UiEvent::Activate(MenuItem::Level)) => game.level = (game.level + 1) % level_count,
+            Some(UiEvent::Adjust(MenuItem::Level, direction)) => {
+                game.level = (game.level as i32 + direction).rem_euclid(level_count as i32) as u32;
+            }
+            Some(UiEvent::Activate(MenuItem::Practice)) => game.start_practice(),
+            Some(UiEvent::Activate(MenuItem::Daily)) => game.start_daily(),
+            Some(UiEvent::Activate(MenuItem::Editor)) => game.open_editor(),
+            Some(UiEvent::Activate(MenuItem::Credits)) => game.open_credits(),
+            Some(UiEvent::Activate(MenuItem::Options)) => {
+                game.ui.reset();
+                game.set_state(OptionsState::default());
+            }
+            _ => {}
+        }
+    }
+
+    // a menu left alone for a while starts the demo
+    fn update(&mut self, game: &mut Game, dt: f32) {
+        game.update_world(game.world_dt);
+        if game.input.any_pressed() || game.input.cursor_moved() {
+            self.idle = 0.0;
+        } else {
+            self.idle += dt;
+        }
+        if game.state() == GameState::Menu && self.idle >= DEMO_IDLE_TIME {
+            game.start_demo();
+        }
+    }
+
+    fn render(&self, game: &Game) {
+        game.render_menu(&game.main_menu());
+    }
+}
+
+impl OptionsState {
+    fn menu(&self, game: &Game) -> Column<OptionsItem> {
+        let mut menu = game.ui_column(230.0, OPTIONS_TOP, 360.0, OPTIONS_ROW_HEIGHT);
+        for action in ACTIONS.iter() {
+            let key = if self.capturing == Some(*action) {
+                t!("options.press_key").to_string()
+            } else {
+                bindings::key_name(game.bindings.key(*action)).to_string()
+            };
+            menu.button(OptionsItem::Binding(*action), format!("{}: {}", t!(format!("options.action_{}", action.id())), key));
+        }
+        for setting in SETTINGS.iter() {
+            let name = t!(format!("options.setting_{}", setting.id())).to_string();
+            let value = game.settings.value(*setting);
+            match value {
+                SettingValue::Toggle(on) => menu.toggle(OptionsItem::Setting(*setting), name, on),
+                SettingValue::Percent(percent) => menu.slider(OptionsItem::Setting(*setting), format!("{}: {:.0}%", name, percent * 100.0), value.fraction()),
+                SettingValue::Scale(scale, ..) => menu.slider(OptionsItem::Setting(*setting), format!("{}: {:.2}x", name, scale), value.fraction()),
+                SettingValue::Choice(id, ..) => menu.slider(OptionsItem::Setting(*setting), format!("{}: {}", name, t!(format!("options.choice_{}", id))), value.fraction())
+            }
+        }
+        menu.button(OptionsItem::ResetKeys, t!("options.reset_keys").to_string());
+        menu.button(OptionsItem::Back, t!("options.back").to_string());
+        menu
+    }
+
+    fn capture_key(&mut self, game: &mut Game, key: Key) {
+        let Some(action) = self.capturing.take() else {
+            return;
+        };
+        if key == Key::Escape {
+            return;
+        }
+        match game.bindings.bind(action, key) {
+            Ok(()) => game.save_bindings(),
+            Err(BindError::Conflict(other)) => {
+                let string = t!("options.conflict", key=bindings::key_name(key), action=t!(format!("options.action_{}", other.id()))).to_string();
+                self.message = Some(string);
+            }
+            Err(_) => {
+                self.message = Some(t!("options.invalid").to_string());
+            }
+        }
+    }
+}
+
+impl State for OptionsState {
+    fn id(&self) -> GameState {
+        GameState::Options
+    }
+
+    fn handle_input(&mut self, game: &mut Game, _dt: f32) {
+        if self.capturing.is_some() {
+            return;
+        }
+        let input = game.ui_input();
+        let menu = self.menu(game);
+        match game.ui.update(menu.widgets(), &input) {
+            Some(UiEvent::Activate(OptionsItem::Binding(action))) => {
+                // the next key press is delivered to capture_key by the key callback
+                self.capturing = Some(action);
+                self.message = None;
+            }
+            Some(UiEvent::Activate(OptionsItem::Setting(setting))) => game.adjust_setting(setting, 1),
+            Some(UiEvent::Adjust(OptionsItem::Setting(setting), direction)) => game.adjust_setting(setting, direction),
+            Some(UiEvent::Activate(OptionsItem::ResetKeys)) => {
+                game.bindings = Bindings::default();
+                game.save_bindings();
+            }
+            Some(UiEvent::Activate(OptionsItem::Back)) | Some(UiEvent::Back) => {
+                game.ui.reset();
+                game.set_state(MenuState::default());
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, game: &mut Game, _dt: f32) {
+        game.update_world(game.world_dt);
+    }
+
+    fn render(&self, game: &Game) {
+        game.render_text(t!("options.title").to_string(), 250.0, OPTIONS_TOP - 40.0, 1.0);
+        let menu = self.menu(game);
+        game.render_menu(&menu);
+        let y = OPTIONS_TOP + menu.widgets().len() as f32 * OPTIONS_ROW_HEIGHT + 15.0;
+        if let Some(message) = self.message.as_ref() {
+            game.render_text_ex(message.clone(), 250.0, y, 0.75, glm::vec3(1.0, 0.3, 0.3));
+        }
+        game.render_text(t!("options.hint").to_string(), 130.0, y + 25.0, 0.75);
+    }
+}
+
+impl ActiveState {
+    // constructor
+    pub fn new() -> Self {
+        Self {
+            rewind: Rewind::new(REWIND_STEPS)
+        }
+    }
+
+    // F6 freezes the game and scrubs through the recent simulation steps; returns whether
+    // the game is frozen (no other input is handled then)
+    fn process_rewind_input(&mut self, game: &mut Game) -> bool {
+        let scrubbing = self.rewind.is_scrubbing();
+        if game.input.just_pressed(Key::F6) {
+            if scrubbing {
+                // continue from the shown step; the steps after it are played anew
+                self.rewind.resume();
+                return false;
+            }
+            let Some(frame) = self.rewind.start() else {
+                return false;
+            };
+            game.apply_rewind_frame(frame);
+            // a rewound run is no fair speedrun
+            game.speedrun = None;
+            return true;
+        }
+        if !scrubbing {
+            return false;
+        }
+        let skip = (REWIND_STEPS / 20) as i32;
+        let steps = if game.input.pressed_repeat(Key::Left) {
+            -1
+        } else if game.input.pressed_repeat(Key::Right) {
+            1
+        } else if game.input.pressed_repeat(Key::PageUp) {
+            -skip
+        } else if game.input.pressed_repeat(Key::PageDown) {
+            skip
+        } else {
+            0
+        };
+        if steps != 0 {
+            if let Some(frame) = self.rewind.scrub(steps) {
+                game.apply_rewind_frame(frame);
+            }
+        }
+        true
+    }
+
+    fn render_rewind_hint(&self, game: &Game) {
+        let seconds = self.rewind.offset() as f32 * REWIND_SECONDS / REWIND_STEPS as f32;
+        let string = format!("Rewind -{:.2}s ({} steps recorded) | Left/Right: step, PageUp/PageDown: skip, F6: resume", seconds, self.rewind.len());
+        game.render_hud_text(string, 5.0, game.height as f32 - 20.0, 0.5, glm::vec3(0.5, 0.8, 1.0), HudAnchor::BottomLeft);
+    }
+}
+
+impl Default for ActiveState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl State for ActiveState {
+    fn id(&self) -> GameState {
+        GameState::Active
+    }
+
+    fn handle_input(&mut self, game: &mut Game, _dt: f32) {
+        if self.process_rewind_input(game) {
+            return;
+        }
+        if game.playtest && game.input.just_pressed(Key::Escape) {
+            game.stop_playtest();
+            return;
+        }
+        if game.practice {
+            if game.input.just_pressed(Key::Escape) {
+                game.stop_practice();
+                return;
+            }
+            game.process_practice_input();
+        }
+        if game.input.just_pressed(game.bindings.key(Action::Pause)) {
+            game.push_state(PausedState::default());
+            game.ui.reset();
+            return;
+        }
+        let input = game.paddle_input(game.world_dt);
+        game.drive_paddle(input, game.world_dt);
+    }
+
+    fn update(&mut self, game: &mut Game, dt: f32) {
+        // the game stands still while a rewind is being scrubbed through
+        if self.rewind.is_scrubbing() {
+            return;
+        }
+        self.rewind.record(game.rewind_frame());
+        // the speedrun timer starts with the first launch
+        if game.settings.speedrun_timer && !game.playtest && !game.practice && game.daily.is_none() {
+            if game.speedrun.is_none() && !game.ball.as_ref().unwrap().stuck {
+                game.speedrun = Some(Speedrun::new(game.level));
+            }
+            if let Some(run) = game.speedrun.as_mut() {
+                run.tick(dt);
+            }
+        }
+        game.update_world(game.world_dt);
+        // losing the last life leaves the level
+        if game.state() != GameState::Active {
+            return;
+        }
+        // check win condition
+        if !game.levels[game.level as usize].is_completed() {
+            return;
+        }
+        if game.playtest {
+            game.stop_playtest();
+        } else if game.practice {
+            game.restart_practice();
+            self.rewind.clear();
+        } else if game.speedrun.is_some() && game.advance_speedrun() {
+            // the rewind doesn't reach back into the level before
+            self.rewind.clear();
+        } else {
+            let speedrun_result = game.finish_speedrun(true);
+            game.finish_daily();
+            game.reset_level();
+            game.reset_player();
+            game.effects.as_mut().unwrap().chaos = true;
+            game.set_state(WinState { speedrun_result });
+        }
+    }
+
+    fn render(&self, game: &Game) {
+        if let Some(daily) = game.daily.as_ref() {
+            let string = if daily.date.is_empty() {
+                t!("game_state.daily_code", code=daily.code()).to_string()
+            } else {
+                t!("game_state.daily", date=daily.date.clone(), code=daily.code()).to_string()
+            };
+            game.render_hud_text(string, 5.0, 45.0, 0.75, glm::vec3(1.0, 1.0, 0.0), HudAnchor::TopLeft);
+        }
+        if game.playtest {
+            game.render_hud_text(t!("game_state.playtest").to_string(), 5.0, 45.0, 0.75, glm::vec3(1.0, 1.0, 0.0), HudAnchor::TopLeft);
+        }
+        if let Some(run) = game.speedrun.as_ref() {
+            game.render_speedrun(run);
+        }
+        if game.practice {
+            game.render_hud_text(t!("game_state.practice_1").to_string(), 5.0, 45.0, 0.5, glm::vec3(1.0, 1.0, 0.0), HudAnchor::TopLeft);
+            game.render_hud_text(t!("game_state.practice_2").to_string(), 5.0, 60.0, 0.5, glm::vec3(1.0, 1.0, 0.0), HudAnchor::TopLeft);
+        }
+        if let Some(tutorial) = game.tutorial.as_ref() {
+            game.render_tutorial(tutorial);
+        }
+        if let Some(banner) = game.banner.as_ref() {
+            game.render_banner(banner);
+        }
+        if self.rewind.is_scrubbing() {
+            self.render_rewind_hint(game);
+        }
+    }
+}
+
+impl PausedState {
+    fn process_photo_input(&mut self, game: &mut Game, dt: f32) {
+        let (width, height) = (game.width as f32, game.height as f32);
+        if game.input.just_pressed(Key::Escape) || game.input.just_pressed(game.bindings.key(Action::Pause)) {
+            self.close_photo(game);
+            return;
+        }
+        let photo = self.photo.as_mut().unwrap();
+        let mut pan = glm::vec2(0.0, 0.0);
+        for (key, direction) in [(Key::Left, glm::vec2(-1.0, 0.0)), (Key::Right, glm::vec2(1.0, 0.0)), (Key::Up, glm::vec2(0.0, -1.0)), (Key::Down, glm::vec2(0.0, 1.0))] {
+            if game.input.held(key) {
+                pan += direction * PHOTO_PAN_SPEED * dt;
+            }
+        }
+        photo.camera.pan(pan, width, height);
+        if game.input.held(Key::Equal) || game.input.held(Key::KpAdd) {
+            photo.camera.zoom_by(PHOTO_ZOOM_SPEED.powf(dt), width, height);
+        }
+        if game.input.held(Key::Minus) || game.input.held(Key::KpSubtract) {
+            photo.camera.zoom_by(PHOTO_ZOOM_SPEED.powf(-dt), width, height);
+        }
+        if game.input.just_pressed(Key::F) {
+            photo.filter = photo.filter.next();
+        }
+        if game.input.just_pressed(Key::H) {
+            photo.show_hints = !photo.show_hints;
+        }
+        game.effects.as_mut().unwrap().filter = photo.filter;
+        let projection = photo.camera.projection(width, height);
+        game.set_scene_projection(&projection);
+        if game.input.just_pressed(Key::P) {
+            game.save_photo();
+        }
+    }
+
+    fn close_photo(&mut self, game: &mut Game) {
+        self.photo = None;
+        game.effects.as_mut().unwrap().filter = photo::PhotoFilter::None;
+        game.set_scene_projection(&glm::ortho(0.0, game.width as f32, game.height as f32, 0.0, -1.0, 1.0));
+        game.ui.reset();
+    }
+}
+
+// paused over the game being played; photo mode is entered from here
+impl State for PausedState {
+    fn id(&self) -> GameState {
+        GameState::Paused
+    }
+
+    fn handle_input(&mut self, game: &mut Game, dt: f32) {
+        if self.photo.is_some() {
+            self.process_photo_input(game, dt);
+            return;
+        }
+        let input = game.ui_input();
+        let menu = game.pause_menu();
+        match game.ui.update(menu.widgets(), &input) {
+            Some(UiEvent::Activate(PauseItem::Resume)) | Some(UiEvent::Back) => game.pop_state(),
+            Some(UiEvent::Activate(PauseItem::Photo)) => {
+                self.photo = Some(PhotoMode::new(game.width as f32, game.height as f32));
+                return;
+            }
+            Some(UiEvent::Activate(PauseItem::Restart)) => game.restart_level(),
+            Some(UiEvent::Activate(PauseItem::Quit)) => game.quit_level(),
+            _ => {}
+        }
+        if game.state() == GameState::Paused && game.input.just_pressed(game.bindings.key(Action::Pause)) {
+            game.pop_state();
+        }
+    }
+
+    fn render(&self, game: &Game) {
+        game.render_text_ex(t!("pause.title").to_string(), 330.0, game.height as f32 / 2.0 - 70.0, 1.0, glm::vec3(1.0, 1.0, 0.0));
+        game.render_menu(&game.pause_menu());
+    }
+}
+
+impl State for WinState {
+    fn id(&self) -> GameState {
+        GameState::Win
+    }
+
+    fn handle_input(&mut self, game: &mut Game, _dt: f32) {
+        if game.input.just_pressed(Key::Enter) {
+            game.effects.as_mut().unwrap().chaos = false;
+            game.set_state(MenuState::default());
+        }
+    }
+
+    fn update(&mut self, game: &mut Game, _dt: f32) {
+        game.update_world(game.world_dt);
+    }
+
+    fn render(&self, game: &Game) {
+        game.render_win_text();
+        game.render_text_ex(t!("game_state.win_1").to_string(), 130.0, game.height as f32 / 2.0, 1.0, glm::vec3(1.0, 1.0, 0.0));
+        if let Some((total, best)) = self.speedrun_result {
+            let delta = best.map(|best| format!(" ({})", speedrun::format_delta(total - best))).unwrap_or_default();
+            let string = t!("game_state.speedrun_result", time=speedrun::format_time(total), delta=delta).to_string();
+            game.render_text(string, 250.0, game.height as f32 / 2.0 + 30.0, 0.75);
+        }
+    }
+}
+
+impl State for EditorState {
+    fn id(&self) -> GameState {
+        GameState::Editor
+    }
+
+    fn handle_input(&mut self, game: &mut Game, _dt: f32) {
+        if game.input.just_pressed(Key::Escape) {
+            game.close_editor();
+        } else if game.input.just_pressed(Key::F5) {
+            game.start_playtest();
+        } else {
+            game.editor.as_mut().unwrap().process_input(&game.input);
+        }
+    }
+
+    fn render(&self, game: &Game) {
+        game.render_editor();
+    }
+}
+
+impl CreditsState {
+    // constructor
+    pub fn new(credits: Credits) -> Self {
+        Self {
+            credits
+        }
+    }
+}
+
+impl State for CreditsState {
+    fn id(&self) -> GameState {
+        GameState::Credits
+    }
+
+    fn handle_input(&mut self, game: &mut Game, _dt: f32) {
+        if game.input.just_pressed(Key::Escape) || game.input.just_pressed(Key::Enter) {
+            game.close_credits();
+        }
+    }
+
+    // holding down (or the launch key) fast-forwards, holding up scrolls back
+    fn update(&mut self, game: &mut Game, dt: f32) {
+        let speed = if game.input.held(Key::Down) || game.input.held(game.bindings.key(Action::Launch)) {
+            credits::FAST_SCROLL
+        } else if game.input.held(Key::Up) {
+            -1.0
+        } else {
+            1.0
+        };
+        self.credits.update(dt, speed);
+        if self.credits.is_finished(game.height as f32) {
+            game.close_credits();
+        }
+    }
+
+    fn render(&self, game: &Game) {
+        game.render_credits(&self.credits);
+    }
+}
+
+impl DemoState {
+    // constructor
+    pub fn new() -> Self {
+        Self {
+            controller: Box::new(PredictiveController::new(Skill::Normal, rand::random()))
+        }
+    }
+}
+
+impl Default for DemoState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the game played by a controller until any key is pressed
+impl State for DemoState {
+    fn id(&self) -> GameState {
+        GameState::Demo
+    }
+
+    fn handle_input(&mut self, game: &mut Game, _dt: f32) {
+        if game.input.any_pressed() {
+            game.stop_demo();
+            return;
+        }
+        let view = game.control_view();
+        let input = self.controller.control(&view, game.world_dt);
+        game.drive_paddle(input, game.world_dt);
+    }
+
+    fn update(&mut self, game: &mut Game, _dt: f32) {
+        game.update_world(game.world_dt);
+        // the demo starts over once it has cleared the level (losing all lives returns to the menu)
+        if game.state() == GameState::Demo && game.levels[game.level as usize].is_completed() {
+            game.restart_demo();
+        }
+    }
+
+    fn render(&self, game: &Game) {
+        game.render_text_ex(t!("demo.title").to_string(), 360.0, game.height as f32 / 2.0 + 40.0, 1.0, glm::vec3(1.0, 1.0, 0.0));
+        // the prompt blinks
+        if game.glfw.get_time().fract() < 0.6 {
+            game.render_text(t!("demo.press_any_key").to_string(), 300.0, game.height as f32 / 2.0 + 70.0, 0.75);
+        }
+    }
+}
@@ -16,58 +16,466 @@
 
 extern crate nalgebra_glm as glm;
 
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use learnopengl_shared::util;
+use std::borrow::Cow;
+use bumpalo::collections::Vec as BumpVec;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
+use learnopengl_shared::{filesystem, util};
+use crate::ambient::Ambient;
+use crate::weather::Weather;
+use crate::brick_type;
+use crate::frame_arena;
 use crate::game_object::GameObject;
+use crate::material::UniformValue;
 use crate::resource_manager;
+use crate::res_name::res_name;
+use crate::score::BRICK_POINTS;
+use crate::sprite_batch::SpriteBatch;
 use crate::sprite_renderer::SpriteRenderer;
+use crate::tiled;
+use crate::tiled::GidTable;
+use crate::walls::{Side, Walls};
+
+// how long a brick flashes after being hit without breaking
+const BRICK_FLASH_TIME: f32 = 0.1;
+// size increase of a brick at the start of its flash
+const BRICK_FLASH_SCALE: f32 = 0.08;
+// how long a destroyed brick takes to dissolve
+const BRICK_DISSOLVE_TIME: f32 = 0.3;
+
+/// Describes why a level could not be loaded. Lines and columns are 1-based.
+#[derive(Debug, PartialEq)]
+pub enum LevelError {
+    Io(String),
+    InvalidToken { line: usize, column: usize, token: String },
+    UnknownTile { line: usize, column: usize, code: u32 },
+    InconsistentRowWidth { line: usize, expected: usize, found: usize },
+    Empty,
+    NoDestructibleBricks,
+    // errors of structured/imported level formats
+    Import(String)
+}
+
+impl Display for LevelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelError::Io(message) => write!(f, "failed to read level file: {}", message),
+            LevelError::InvalidToken { line, column, token } =>
+                write!(f, "line {}, column {}: \"{}\" is not a tile code", line, column, token),
+            LevelError::UnknownTile { line, column, code } =>
+                write!(f, "line {}, column {}: unknown tile code {} (no brick type has it)", line, column, code),
+            LevelError::InconsistentRowWidth { line, expected, found } =>
+                write!(f, "line {}: row has {} tiles but the first row has {}", line, found, expected),
+            LevelError::Empty => write!(f, "level contains no rows"),
+            LevelError::NoDestructibleBricks => write!(f, "level contains no destructible bricks"),
+            LevelError::Import(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+impl Error for LevelError {}
+
+/// Parses and validates the textual level format, returning the tile grid on success.
+pub fn validate_level(source: &str) -> Result<Vec<Vec<u32>>, LevelError> {
+    let mut tile_data: Vec<Vec<u32>> = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let mut row: Vec<u32> = Vec::new();
+        let mut column = 0;
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            column += 1;
+            if c.is_whitespace() {
+                continue;
+            }
+            // consume the whole token
+            let token_column = column;
+            let mut end = start + c.len_utf8();
+            while let Some((i, c)) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                end = i + c.len_utf8();
+                column += 1;
+                chars.next();
+            }
+            let token = &line[start..end];
+            let code: u32 = token.parse().map_err(|_| LevelError::InvalidToken {
+                line: line_number,
+                column: token_column,
+                token: token.to_string()
+            })?;
+            if !brick_type::is_known(code) {
+                return Err(LevelError::UnknownTile { line: line_number, column: token_column, code });
+            }
+            row.push(code);
+        }
+        // blank lines carry no tiles
+        if row.is_empty() {
+            continue;
+        }
+        if let Some(first) = tile_data.first() {
+            if first.len() != row.len() {
+                return Err(LevelError::InconsistentRowWidth {
+                    line: line_number,
+                    expected: first.len(),
+                    found: row.len()
+                });
+            }
+        }
+        tile_data.push(row);
+    }
+    validate_tile_data(&tile_data)?;
+    Ok(tile_data)
+}
+
+/// Validates an already parsed tile grid (e.g. from an imported level).
+pub fn validate_tile_data(tile_data: &[Vec<u32>]) -> Result<(), LevelError> {
+    if tile_data.is_empty() || tile_data[0].is_empty() {
+        return Err(LevelError::Empty);
+    }
+    for (y, row) in tile_data.iter().enumerate() {
+        if row.len() != tile_data[0].len() {
+            return Err(LevelError::InconsistentRowWidth { line: y + 1, expected: tile_data[0].len(), found: row.len() });
+        }
+        if let Some(x) = row.iter().position(|code| !brick_type::is_known(*code)) {
+            return Err(LevelError::UnknownTile { line: y + 1, column: x + 1, code: row[x] });
+        }
+    }
+    if !tile_data.iter().flatten().any(|code| brick_type::is_destructible(*code)) {
+        return Err(LevelError::NoDestructibleBricks);
+    }
+    Ok(())
+}
+
+/// Computes a checksum (32-bit FNV-1a) over the dimensions and tile codes of a level,
+/// so that two levels can cheaply be compared for identical layouts.
+pub fn level_checksum(tile_data: &[Vec<u32>]) -> u32 {
+    let mut hash = 0x811c9dc5u32;
+    let mut feed = |value: u32| {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+    };
+    feed(tile_data.len() as u32);
+    for row in tile_data {
+        feed(row.len() as u32);
+        for code in row {
+            feed(*code);
+        }
+    }
+    hash
+}
+
+// whether bricks use the colorblind-safe palette
+static COLORBLIND_PALETTE: AtomicBool = AtomicBool::new(false);
+
+// switches tile_color to the colorblind-safe palette (applies to levels loaded afterwards)
+pub fn set_colorblind_palette(enabled: bool) {
+    COLORBLIND_PALETTE.store(enabled, Ordering::Relaxed);
+}
+
+// default color of the bricks with the given tile code (white for unknown codes)
+pub fn tile_color(code: u32) -> glm::TVec3<f32> {
+    let Some(brick_type) = brick_type::get(code) else {
+        return util::glm::scale_vec3(1.0);
+    };
+    let color = if COLORBLIND_PALETTE.load(Ordering::Relaxed) {
+        brick_type.colorblind_color.unwrap_or(brick_type.color)
+    } else {
+        brick_type.color
+    };
+    glm::vec3(color[0], color[1], color[2])
+}
+
+/// Overrides the properties of the brick at the given grid cell (0-based).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BrickOverride {
+    pub x: usize,
+    pub y: usize,
+    // number of hits needed to destroy the brick
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hp: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[f32; 3]>,
+    // name of the material the brick is drawn with (e.g. "glass", "metal" or "glow")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+    // hitting the brick saves a checkpoint the player respawns from
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub checkpoint: bool
+}
+
+/// The structured (TOML) level format, which in contrast to the textual format
+/// can also hold per-brick overrides.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelFile {
+    pub tiles: Vec<Vec<u32>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<BrickOverride>,
+    #[serde(default, skip_serializing_if = "Walls::is_default")]
+    pub walls: Walls,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ambient: Option<Ambient>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weather: Option<Weather>
+}
+
+impl LevelFile {
+    pub fn parse(source: &str) -> Result<Self, LevelError> {
+        let level: Self = toml::from_str(source)
+            .map_err(|e| LevelError::Import(format!("invalid level file: {}", e)))?;
+        validate_tile_data(&level.tiles)?;
+        for o in level.overrides.iter() {
+            match level.tiles.get(o.y).and_then(|row| row.get(o.x)) {
+                Some(code) if brick_type::is_destructible(*code) => {}
+                Some(_) => return Err(LevelError::Import(format!("override at row {}, column {} doesn't target a destructible brick", o.y + 1, o.x + 1))),
+                None => return Err(LevelError::Import(format!("override at row {}, column {} lies outside of the level", o.y + 1, o.x + 1)))
+            }
+            if o.hp == Some(0) {
+                return Err(LevelError::Import(format!("override at row {}, column {} has zero hp", o.y + 1, o.x + 1)));
+            }
+        }
+        level.walls.validate().map_err(|e| LevelError::Import(format!("invalid walls: {}", e)))?;
+        if let Some(ambient) = level.ambient {
+            ambient.validate().map_err(|e| LevelError::Import(format!("invalid ambient particles: {}", e)))?;
+        }
+        if let Some(weather) = level.weather {
+            weather.validate().map_err(|e| LevelError::Import(format!("invalid weather: {}", e)))?;
+        }
+        Ok(level)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+}
+
+/// Serializes a tile grid into the textual level format.
+pub fn level_to_text(tile_data: &[Vec<u32>]) -> String {
+    let mut text = String::new();
+    for row in tile_data {
+        let codes: Vec<String> = row.iter().map(|code| code.to_string()).collect();
+        text.push_str(codes.join(" ").as_str());
+        text.push('\n');
+    }
+    text
+}
+
+/// What a level object placed in a level editor (e.g. on a Tiled object layer) represents.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelObjectKind {
+    // always drops the named power-up when a brick within its area is destroyed
+    PowerUpSpawner(String),
+    // all bricks within its area are checkpoint bricks
+    Checkpoint
+}
+
+/// State of the bricks of a level (destroyed, remaining hp), as saved by a checkpoint.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelSnapshot {
+    pub bricks: Vec<(bool, u32)>,
+    // hits the ceiling can still take
+    pub ceiling: u32
+}
+
+/// A non-tile object of a level, positioned in level coordinates.
+#[derive(Clone, Debug)]
+pub struct LevelObject {
+    pub kind: LevelObjectKind,
+    pub position: glm::TVec2<f32>,
+    pub size: glm::TVec2<f32>
+}
+
+impl LevelObject {
+    // checks whether the given point lies within the object's area
+    pub fn contains(&self, point: glm::TVec2<f32>) -> bool {
+        point.x >= self.position.x && point.x <= self.position.x + self.size.x
+            && point.y >= self.position.y && point.y <= self.position.y + self.size.y
+    }
+}
 
 /// GameLevel holds all Tiles as part of a Breakout level and
 /// hosts functionality to Load/render levels from the harddisk.
 pub struct GameLevel {
     // level state
-    pub bricks: Vec<GameObject>
+    pub bricks: Vec<GameObject>,
+    // the tile grid the bricks were generated from
+    pub tiles: Vec<Vec<u32>>,
+    pub overrides: Vec<BrickOverride>,
+    // grid cell (x, y) of each brick in `bricks`
+    pub brick_cells: Vec<(usize, usize)>,
+    pub objects: Vec<LevelObject>,
+    pub checksum: u32,
+    // the playfield boundary
+    pub walls: Walls,
+    // particles drifting through the level
+    pub ambient: Option<Ambient>,
+    // rain and fog over the level
+    pub weather: Option<Weather>,
+    // hits the ceiling can still take before it breaks open
+    ceiling: u32,
+    // whether hitting a brick saves a checkpoint
+    checkpoints: Vec<bool>,
+    // remaining hit flash time of each brick
+    flashes: Vec<f32>,
+    // time since each destroyed brick started dissolving (None once it's gone)
+    dissolves: Vec<Option<f32>>,
+    // render state
+    batch: RefCell<SpriteBatch>
 }
 
 impl GameLevel {
     // constructor
     pub fn new() -> Self {
         Self {
-            bricks: Vec::new()
+            bricks: Vec::new(),
+            tiles: Vec::new(),
+            overrides: Vec::new(),
+            brick_cells: Vec::new(),
+            objects: Vec::new(),
+            checksum: 0,
+            walls: Walls::default(),
+            ambient: None,
+            weather: None,
+            ceiling: 0,
+            checkpoints: Vec::new(),
+            flashes: Vec::new(),
+            dissolves: Vec::new(),
+            batch: RefCell::new(SpriteBatch::new())
         }
     }
 
-    // loads level from file
+    // loads level from file; maps made with the Tiled editor (.tmx/.json) are imported
+    // using the default GID table
     pub fn load(
         &mut self,
         file: &str,
         level_width: u32,
         level_height: u32
+    ) -> Result<(), LevelError> {
+        if tiled::is_tiled_file(file) {
+            let gid_table = GidTable::load(filesystem::get_path("resources/levels/tiled_gids.toml".to_string()).as_str())?;
+            return self.load_tiled(file, &gid_table, level_width, level_height);
+        }
+        let source = fs::read_to_string(file)
+            .map_err(|e| LevelError::Io(format!("{}: {}", file, e)))?;
+        let level = if file.ends_with(".toml") {
+            LevelFile::parse(source.as_str())?
+        } else {
+            LevelFile { tiles: validate_level(source.as_str())?, overrides: Vec::new(), walls: Walls::default(), ambient: None, weather: None }
+        };
+        // clear old data
+        self.bricks.clear();
+        self.objects.clear();
+        self.overrides = level.overrides;
+        self.walls = level.walls;
+        self.ambient = level.ambient;
+        self.weather = level.weather;
+        self.checksum = level_checksum(&level.tiles);
+        log::debug!("Loaded level {} (checksum {:08x})", file, self.checksum);
+        self.init(level.tiles, level_width, level_height);
+        Ok(())
+    }
+
+    // imports a level from a Tiled map, translating GIDs to tile codes with the given table
+    pub fn load_tiled(
+        &mut self,
+        file: &str,
+        gid_table: &GidTable,
+        level_width: u32,
+        level_height: u32
+    ) -> Result<(), LevelError> {
+        let imported = tiled::import(file, gid_table)?;
+        validate_tile_data(&imported.tiles)?;
+        // clear old data
+        self.bricks.clear();
+        self.overrides.clear();
+        self.walls = Walls::default();
+        self.ambient = None;
+        self.weather = None;
+        // map the objects from map pixels onto the level area
+        let scale = glm::vec2(level_width as f32 / imported.pixel_size.x, level_height as f32 / imported.pixel_size.y);
+        self.objects = imported.objects.into_iter()
+            .map(|object| LevelObject {
+                kind: object.kind,
+                position: object.position.component_mul(&scale),
+                size: object.size.component_mul(&scale)
+            })
+            .collect();
+        self.checksum = level_checksum(&imported.tiles);
+        log::debug!("Imported Tiled level {} (checksum {:08x})", file, self.checksum);
+        self.init(imported.tiles, level_width, level_height);
+        Ok(())
+    }
+
+    // builds the level from an in-memory level file (as produced by the level editor)
+    pub fn load_state(
+        &mut self,
+        level: &LevelFile,
+        level_width: u32,
+        level_height: u32
     ) {
         // clear old data
         self.bricks.clear();
-        // load from file
-        let f = File::open(file).unwrap();
-        let lines = BufReader::new(f).lines();
-        let mut tile_data: Vec<Vec<u32>> = Vec::new();
-        for line in lines {
-            if let Ok(line) = line {
-                let num_strs: Vec<_> = line.split(" ").collect();
-                let mut row: Vec<u32> = Vec::new();
-                for num_str in num_strs {
-                    let num_str = num_str.trim();
-                    let num: Result<u32, _> = num_str.parse();
-                    if let Ok(num) = num {
-                        row.push(num);
-                    }
-                }
-                tile_data.push(row);
+        self.objects.clear();
+        self.overrides = level.overrides.clone();
+        self.walls = level.walls;
+        self.ambient = level.ambient;
+        self.weather = level.weather;
+        self.checksum = level_checksum(&level.tiles);
+        self.init(level.tiles.clone(), level_width, level_height);
+    }
+
+    // saves the current state of the level; destroyed bricks are saved as empty tiles and damaged
+    // bricks with their remaining hp. Files ending with .toml are written in the structured format,
+    // all others in the textual format (which can't hold per-brick overrides)
+    pub fn save(&self, file: &str) -> Result<(), LevelError> {
+        let level = self.current_state();
+        let contents = if file.ends_with(".toml") {
+            level.to_toml()
+        } else {
+            if !level.overrides.is_empty() {
+                log::warn!("Saving level {} in the textual format drops {} brick override(s)", file, level.overrides.len());
+            }
+            level_to_text(&level.tiles)
+        };
+        fs::write(file, contents).map_err(|e| LevelError::Io(format!("{}: {}", file, e)))
+    }
+
+    // captures the in-memory brick grid, including the remaining hp of each brick
+    pub fn current_state(&self) -> LevelFile {
+        let mut tiles = self.tiles.clone();
+        let mut overrides = self.overrides.clone();
+        for (brick, (x, y)) in self.bricks.iter().zip(self.brick_cells.iter()) {
+            if brick.destroyed {
+                tiles[*y][*x] = 0;
+                overrides.retain(|o| o.x != *x || o.y != *y);
+                continue;
+            }
+            if brick.is_solid || brick.hp == self.initial_hp(*x, *y) {
+                continue;
+            }
+            match overrides.iter_mut().find(|o| o.x == *x && o.y == *y) {
+                Some(o) => o.hp = Some(brick.hp),
+                None => overrides.push(BrickOverride { x: *x, y: *y, hp: Some(brick.hp), color: None, material: None, checkpoint: false })
             }
         }
-        if tile_data.len() > 0 {
-            self.init(tile_data, level_width, level_height);
-        }
+        LevelFile { tiles, overrides, walls: self.walls, ambient: self.ambient, weather: self.weather }
+    }
+
+    fn initial_hp(&self, x: usize, y: usize) -> u32 {
+        self.override_at(x, y).and_then(|o| o.hp)
+            .unwrap_or_else(|| brick_type::get(self.tiles[y][x]).map_or(1, |t| t.hp))
+    }
+
+    fn override_at(&self, x: usize, y: usize) -> Option<&BrickOverride> {
+        self.overrides.iter().find(|o| o.x == x && o.y == y)
     }
 
     // initialize level from tile data
@@ -77,6 +485,7 @@ impl GameLevel {
         level_width: u32,
         level_height: u32
     ) {
+        self.brick_cells.clear();
         // calculate dimensions
         let height = tile_data.len();
         let width = tile_data[0].len();
@@ -86,45 +495,237 @@ impl GameLevel {
         for y in 0..height {
             for x in 0..width {
                 // check block type from level data (2D level array)
-                if tile_data[y][x] == 1 { // solid
-                    let pos = glm::vec2(unit_width * x as f32, unit_height * y as f32);
-                    let size = glm::vec2(unit_width, unit_height);
-                    let mut obj = GameObject::new_ex1(pos, size, resource_manager::get_texture("block_solid".to_string()), glm::vec3(0.8, 0.8, 0.7), util::glm::empty_vec2());
+                let code = tile_data[y][x];
+                let Some(brick_type) = brick_type::get(code) else {
+                    continue;
+                };
+                let pos = glm::vec2(unit_width * x as f32, unit_height * y as f32);
+                let size = glm::vec2(unit_width, unit_height);
+                if brick_type.is_solid() {
+                    let mut obj = GameObject::new_ex1(pos, size, resource_manager::get_texture(&brick_type.texture), tile_color(code), util::glm::empty_vec2());
                     obj.is_solid = true;
                     self.bricks.push(obj);
-                } else if tile_data[y][x] > 1 { // non-solid; now determine its color based on level data
-                    let mut color = util::glm::scale_vec3(1.0); // original: white
-                    match tile_data[y][x] {
-                        2 => {
-                            color = glm::vec3(0.2, 0.6, 1.0);
-                        }
-                        3 => {
-                            color = glm::vec3(0.0, 0.7, 0.0);
-                        }
-                        4 => {
-                            color = glm::vec3(0.8, 0.8, 0.4);
-                        }
-                        5 => {
-                            color = glm::vec3(1.0, 0.5, 0.0);
-                        }
-                        _ => {}
+                    self.brick_cells.push((x, y));
+                } else { // non-solid; now determine its color based on level data
+                    let mut color = tile_color(code);
+
+                    let mut hp = brick_type.hp;
+                    let mut material = None;
+                    if let Some(o) = self.override_at(x, y) {
+                        hp = o.hp.unwrap_or(hp);
+                        color = o.color.map(|c| glm::vec3(c[0], c[1], c[2])).unwrap_or(color);
+                        material = o.material.as_ref().and_then(|name| {
+                            let found = resource_manager::find_material(name);
+                            if found.is_none() {
+                                log::warn!("GAME_LEVEL: Brick at {}, {} uses unknown material \"{}\"", x + 1, y + 1, name);
+                            }
+                            found
+                        });
                     }
 
-                    let pos = glm::vec2(unit_width * x as f32, unit_height * y as f32);
-                    let size = glm::vec2(unit_width, unit_height);
-                    self.bricks.push(GameObject::new_ex1(pos, size, resource_manager::get_texture("block".to_string()), color, util::glm::empty_vec2()));
+                    // patterned texture sets provide a texture per tile code (and for multi-hit bricks)
+                    let texture_name = if hp > 1 { format!("block_{}_multi", code) } else { format!("block_{}", code) };
+                    let texture = resource_manager::find_texture(texture_name)
+                        .unwrap_or_else(|| resource_manager::get_texture(&brick_type.texture));
+                    let mut obj = GameObject::new_ex1(pos, size, texture, color, util::glm::empty_vec2());
+                    obj.hp = hp;
+                    obj.material = material;
+                    self.bricks.push(obj);
+                    self.brick_cells.push((x, y));
                 }
             }
         }
+        self.tiles = tile_data;
+        self.ceiling = self.walls.ceiling_hp;
+        self.flashes = vec![0.0; self.bricks.len()];
+        self.dissolves = vec![None; self.bricks.len()];
+        self.init_checkpoints();
+    }
+
+    // marks the bricks with a checkpoint override or within a checkpoint object;
+    // they glow green unless they already use a material
+    fn init_checkpoints(&mut self) {
+        self.checkpoints = (0..self.bricks.len())
+            .map(|i| {
+                let (x, y) = self.brick_cells[i];
+                let center = self.bricks[i].position + self.bricks[i].size / 2.0;
+                self.override_at(x, y).is_some_and(|o| o.checkpoint)
+                    || self.objects.iter().any(|object| object.kind == LevelObjectKind::Checkpoint && object.contains(center))
+            })
+            .collect();
+        for (brick, checkpoint) in self.bricks.iter_mut().zip(self.checkpoints.iter()) {
+            if *checkpoint && brick.material.is_none() {
+                brick.material = resource_manager::find_material(res_name!("glow")).map(|mut material| {
+                    material.set_uniform("glow_color", UniformValue::Vec3([0.3, 1.0, 0.4]));
+                    material
+                });
+            }
+        }
+    }
+
+    pub fn is_checkpoint(&self, index: usize) -> bool {
+        self.checkpoints.get(index).copied().unwrap_or(false)
+    }
+
+    // captures which bricks are left and how many hits they still need
+    pub fn snapshot(&self) -> LevelSnapshot {
+        LevelSnapshot {
+            bricks: self.bricks.iter().map(|brick| (brick.destroyed, brick.hp)).collect(),
+            ceiling: self.ceiling
+        }
+    }
+
+    // puts the bricks back into a captured state
+    pub fn restore(&mut self, snapshot: &LevelSnapshot) {
+        for (brick, (destroyed, hp)) in self.bricks.iter_mut().zip(snapshot.bricks.iter()) {
+            brick.destroyed = *destroyed;
+            brick.hp = *hp;
+            brick.status.clear();
+        }
+        self.ceiling = snapshot.ceiling;
+        self.flashes.iter_mut().for_each(|flash| *flash = 0.0);
+        self.dissolves.iter_mut().for_each(|dissolve| *dissolve = None);
+    }
+
+    // the walls as they currently are, with the top open once a breakable ceiling is broken
+    pub fn bounds(&self) -> Walls {
+        let mut walls = self.walls;
+        if self.walls.ceiling_hp > 0 && self.ceiling == 0 {
+            walls.top = Side::Open;
+        }
+        walls
+    }
+
+    // whether the ceiling breaks and how many hits it can still take
+    pub fn ceiling_hp(&self) -> Option<u32> {
+        (self.walls.ceiling_hp > 0).then_some(self.ceiling)
+    }
+
+    // damages a breakable ceiling the ball bounced off; returns whether it broke open
+    pub fn hit_ceiling(&mut self) -> bool {
+        if self.walls.ceiling_hp == 0 || self.ceiling == 0 {
+            return false;
+        }
+        self.ceiling -= 1;
+        self.ceiling == 0
+    }
+
+    // starts the hit feedback of a brick which was struck but not destroyed
+    pub fn flash_brick(&mut self, index: usize) {
+        if let Some(flash) = self.flashes.get_mut(index) {
+            *flash = BRICK_FLASH_TIME;
+        }
+    }
+
+    // lets a destroyed brick dissolve before it disappears
+    pub fn dissolve_brick(&mut self, index: usize) {
+        if let Some(dissolve) = self.dissolves.get_mut(index) {
+            *dissolve = Some(0.0);
+        }
+    }
+
+    // advances the status effects of the bricks; bricks losing hit points to a burn flash like when hit
+    pub fn update_status(&mut self, dt: f32) {
+        for (brick, flash) in self.bricks.iter_mut().zip(self.flashes.iter_mut()) {
+            if !brick.destroyed && brick.update_status(dt) > 0 {
+                *flash = BRICK_FLASH_TIME;
+            }
+        }
+    }
+
+    // advances the transient visual state of the bricks
+    pub fn update(&mut self, dt: f32) {
+        for flash in self.flashes.iter_mut() {
+            *flash = (*flash - dt).max(0.0);
+        }
+        for dissolve in self.dissolves.iter_mut() {
+            *dissolve = dissolve.map(|time| time + dt).filter(|time| *time < BRICK_DISSOLVE_TIME);
+        }
+    }
+
+    // whether a destroyed brick is still being drawn while it dissolves
+    pub fn is_dissolving(&self, index: usize) -> bool {
+        self.dissolves.get(index).is_some_and(|dissolve| dissolve.is_some())
+    }
+
+    // strength of a brick's hit flash, from 1 right after the hit down to 0
+    pub fn flash_amount(&self, index: usize) -> f32 {
+        self.flashes.get(index).map_or(0.0, |flash| flash / BRICK_FLASH_TIME)
+    }
+
+    // the brick as it is drawn: flashing bricks are tinted white and slightly enlarged,
+    // dissolving ones are drawn with the dissolve material in place of their own
+    fn displayed_brick(&self, index: usize) -> Cow<'_, GameObject> {
+        let brick = &self.bricks[index];
+        if let Some(time) = self.dissolves.get(index).copied().flatten() {
+            let mut shown = brick.clone();
+            shown.material = resource_manager::find_material(res_name!("dissolve")).map(|mut material| {
+                material.set_uniform("time", UniformValue::Float(time));
+                material.set_uniform("duration", UniformValue::Float(BRICK_DISSOLVE_TIME));
+                // so that neighbouring bricks don't dissolve in the same pattern
+                material.set_uniform("seed", UniformValue::Float(index as f32));
+                material
+            });
+            return Cow::Owned(shown);
+        }
+        let amount = self.flash_amount(index);
+        if amount <= 0.0 {
+            return Cow::Borrowed(brick);
+        }
+        let mut shown = brick.clone();
+        let grow = brick.size * (BRICK_FLASH_SCALE * amount);
+        shown.position -= grow / 2.0;
+        shown.size += grow;
+        shown.color = glm::mix(&brick.color, &util::glm::scale_vec3(1.0), amount);
+        Cow::Owned(shown)
+    }
+
+    // replaces the instance batch of the bricks, e.g. after its buffers were lost
+    pub fn recreate_batch(&mut self) {
+        self.batch = RefCell::new(SpriteBatch::new());
     }
 
     // render level
     pub fn draw(&self, renderer: &SpriteRenderer) {
-        for tile in self.bricks.iter() {
-            if !tile.destroyed {
-                tile.draw(renderer);
+        frame_arena::with(|arena| {
+            let shown: BumpVec<Cow<GameObject>> = BumpVec::from_iter_in((0..self.bricks.len())
+                .filter(|i| !self.bricks[*i].destroyed || self.is_dissolving(*i))
+                .map(|i| self.displayed_brick(i)), arena);
+            // bricks with a material need their own shader, all others are drawn instanced
+            let batched: BumpVec<&GameObject> = BumpVec::from_iter_in(shown.iter()
+                .map(|tile| tile.as_ref())
+                .filter(|tile| tile.material.is_none()), arena);
+            let mut batch = self.batch.borrow_mut();
+            batch.set_sprites(&batched);
+            batch.draw(&resource_manager::get_shader(res_name!("sprite_instanced")));
+            for tile in shown.iter() {
+                if tile.material.is_some() {
+                    tile.draw(renderer);
+                }
             }
-        }
+        });
+    }
+
+    // number of distinct rows the given (just destroyed) bricks were the last destructible bricks of
+    pub fn cleared_rows(&self, destroyed: &[usize]) -> usize {
+        let mut rows: Vec<usize> = destroyed.iter().map(|i| self.brick_cells[*i].1).collect();
+        rows.sort_unstable();
+        rows.dedup();
+        rows.into_iter()
+            .filter(|row| self.bricks.iter().zip(self.brick_cells.iter())
+                .all(|(brick, (_, y))| y != row || brick.is_solid || brick.destroyed))
+            .count()
+    }
+
+    // points for destroying the brick with the given index, as its brick type is worth
+    pub fn brick_points(&self, index: usize) -> u32 {
+        let (x, y) = self.brick_cells[index];
+        brick_type::get(self.tiles[y][x]).map_or(BRICK_POINTS, |it| it.points)
+    }
+
+    // number of non-solid tiles which aren't destroyed yet
+    pub fn bricks_left(&self) -> usize {
+        self.bricks.iter().filter(|tile| !tile.is_solid && !tile.destroyed).count()
     }
 
     // check if the level is completed (all non-solid tiles are destroyed)
@@ -136,4 +737,220 @@ impl GameLevel {
         }
         true
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ambient::AmbientKind;
+    use std::path::Path;
+    use crate::texture::Texture2D;
+    use crate::status::{StatusKind, BURN_TICK};
+
+    #[test]
+    fn validate_level_test_valid() {
+        let tiles = validate_level("1 2 3\n0 4 5 \t\n\n").expect("The level should be valid.");
+        assert_eq!(tiles, vec![vec![1, 2, 3], vec![0, 4, 5]]);
+    }
+
+    #[test]
+    fn validate_level_test_inconsistent_row_width() {
+        let result = validate_level("2 2 2\n2 2\n");
+        assert_eq!(result, Err(LevelError::InconsistentRowWidth { line: 2, expected: 3, found: 2 }));
+    }
+
+    #[test]
+    fn validate_level_test_unknown_tile() {
+        let result = validate_level("2 2 2\n2  9 2\n");
+        assert_eq!(result, Err(LevelError::UnknownTile { line: 2, column: 4, code: 9 }));
+    }
+
+    #[test]
+    fn validate_level_test_invalid_token() {
+        let result = validate_level("2 2 2\n2 2 x1\n");
+        assert_eq!(result, Err(LevelError::InvalidToken { line: 2, column: 5, token: "x1".to_string() }));
+    }
+
+    #[test]
+    fn validate_level_test_empty() {
+        assert_eq!(validate_level(" \n\n"), Err(LevelError::Empty));
+    }
+
+    #[test]
+    fn validate_level_test_no_destructible_bricks() {
+        assert_eq!(validate_level("1 0 1\n0 1 0\n"), Err(LevelError::NoDestructibleBricks));
+    }
+
+    #[test]
+    fn validate_level_test_shipped_levels() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/levels");
+        for name in ["one.lvl", "two.lvl", "three.lvl", "four.lvl"] {
+            let source = fs::read_to_string(dir.join(name)).expect("The level file should exist.");
+            if let Err(e) = validate_level(source.as_str()) {
+                panic!("{} must be a valid level: {}", name, e);
+            }
+        }
+    }
+
+    #[test]
+    fn level_to_text_test_roundtrip() {
+        let tiles = vec![vec![1, 2, 3], vec![0, 4, 5]];
+        let text = level_to_text(&tiles);
+        assert_eq!(text, "1 2 3\n0 4 5\n");
+        assert_eq!(validate_level(text.as_str()), Ok(tiles));
+    }
+
+    #[test]
+    fn level_file_test_roundtrip() {
+        let level = LevelFile {
+            tiles: vec![vec![1, 2, 3], vec![0, 4, 5]],
+            overrides: vec![
+                BrickOverride { x: 1, y: 0, hp: Some(3), color: None, material: None, checkpoint: true },
+                BrickOverride { x: 2, y: 1, hp: None, color: Some([1.0, 0.0, 0.5]), material: Some("glass".to_string()), checkpoint: false }
+            ],
+            walls: Walls { left: Side::Wrap, right: Side::Wrap, ceiling_hp: 3, restitution: 1.25, ..Walls::default() },
+            ambient: Some(Ambient { kind: AmbientKind::Snow, rate: 30.0, color: Some([0.8, 0.9, 1.0]) }),
+            weather: Some(Weather { rain: 0.6, fog: 0.25 })
+        };
+        let parsed = LevelFile::parse(level.to_toml().as_str()).expect("The saved level should be valid.");
+        assert_eq!(parsed, level);
+    }
+
+    #[test]
+    fn level_file_test_invalid_override() {
+        let source = "tiles = [[1, 2], [2, 2]]\n\n[[overrides]]\nx = 0\ny = 0\nhp = 2\n";
+        assert!(matches!(LevelFile::parse(source), Err(LevelError::Import(_))), "Overrides on solid bricks must be rejected.");
+        let source = "tiles = [[1, 2], [2, 2]]\n\n[[overrides]]\nx = 5\ny = 0\nhp = 2\n";
+        assert!(matches!(LevelFile::parse(source), Err(LevelError::Import(_))), "Overrides outside of the level must be rejected.");
+    }
+
+    #[test]
+    fn level_file_test_walls() {
+        let source = "tiles = [[1, 2], [2, 2]]\n\n[walls]\nleft = \"open\"\nceiling-hp = 2\n";
+        let level = LevelFile::parse(source).expect("The level should be valid.");
+        assert_eq!(level.walls, Walls { left: Side::Open, ceiling_hp: 2, ..Walls::default() });
+        let source = "tiles = [[1, 2], [2, 2]]\n\n[walls]\ntop = \"open\"\nceiling-hp = 2\n";
+        assert!(matches!(LevelFile::parse(source), Err(LevelError::Import(_))), "A breakable ceiling needs a top wall.");
+    }
+
+    #[test]
+    fn game_level_test_save_round_trip() {
+        // a multi-hit type with a code no other test uses, as the registry is shared
+        brick_type::register(44, brick_type::BrickType { name: "test".to_string(), hp: 3, ..brick_type::BrickType::default() });
+        for name in ["block", "block_solid"] {
+            if resource_manager::find_texture(name).is_none() {
+                resource_manager::add_texture(name.to_string(), Texture2D::empty());
+            }
+        }
+        let source = LevelFile { tiles: vec![vec![44, 44, 1], vec![2, 44, 2]], overrides: Vec::new(), walls: Walls::default(), ambient: None, weather: None };
+        let mut level = GameLevel::new();
+        level.load_state(&source, 300, 200);
+        // untouched bricks don't need overrides
+        assert_eq!(level.current_state(), source);
+        // damage one brick down to 1 hp, another by a hit, and destroy a third
+        level.bricks[0].hp = 1;
+        level.bricks[1].hp = 2;
+        level.bricks[3].destroyed = true;
+        let file = std::env::temp_dir().join(format!("game_level_test_{}.toml", std::process::id()));
+        level.save(file.to_str().unwrap()).unwrap();
+        let mut loaded = GameLevel::new();
+        let result = loaded.load(file.to_str().unwrap(), 300, 200);
+        let _ = fs::remove_file(&file);
+        result.unwrap();
+        assert_eq!(loaded.tiles, vec![vec![44, 44, 1], vec![0, 44, 2]]);
+        let hp: Vec<u32> = loaded.bricks.iter().filter(|it| !it.is_solid).map(|it| it.hp).collect();
+        assert_eq!(hp, vec![1, 2, 3, 1]);
+        assert_eq!(loaded.current_state(), level.current_state());
+    }
+
+    #[test]
+    fn game_level_test_cleared_rows() {
+        let mut level = GameLevel::new();
+        for (x, y, solid) in [(0, 0, false), (1, 0, true), (0, 1, false), (1, 1, false)] {
+            let mut brick = GameObject::new_ex0(glm::vec2(x as f32, y as f32), glm::vec2(1.0, 1.0), Texture2D::empty());
+            brick.is_solid = solid;
+            level.bricks.push(brick);
+            level.brick_cells.push((x, y));
+        }
+        // solid bricks don't keep a row from being cleared
+        level.bricks[0].destroyed = true;
+        assert_eq!(level.cleared_rows(&[0]), 1);
+        level.bricks[2].destroyed = true;
+        assert_eq!(level.cleared_rows(&[2]), 0);
+        level.bricks[3].destroyed = true;
+        assert_eq!(level.cleared_rows(&[2, 3]), 1);
+    }
+
+    #[test]
+    fn game_level_test_ceiling_breaks() {
+        let mut level = GameLevel::new();
+        level.walls = Walls { ceiling_hp: 2, ..Walls::default() };
+        level.ceiling = 2;
+        let snapshot = level.snapshot();
+        assert!(!level.hit_ceiling());
+        assert_eq!(level.bounds().top, Side::Wall);
+        assert!(level.hit_ceiling());
+        assert_eq!(level.ceiling_hp(), Some(0));
+        assert_eq!(level.bounds().top, Side::Open);
+        assert!(!level.hit_ceiling());
+        level.restore(&snapshot);
+        assert_eq!(level.ceiling_hp(), Some(2));
+        // ceilings without hp never break
+        level.walls = Walls::default();
+        assert!(!level.hit_ceiling());
+        assert_eq!(level.ceiling_hp(), None);
+    }
+
+    #[test]
+    fn game_level_test_brick_flash_fades() {
+        let mut level = GameLevel::new();
+        level.flashes = vec![0.0; 2];
+        level.flash_brick(1);
+        level.flash_brick(5); // out of range, ignored
+        assert_eq!(level.flash_amount(0), 0.0);
+        assert_eq!(level.flash_amount(1), 1.0);
+        level.update(BRICK_FLASH_TIME / 2.0);
+        assert!((level.flash_amount(1) - 0.5).abs() < 1e-5);
+        level.update(BRICK_FLASH_TIME);
+        assert_eq!(level.flash_amount(1), 0.0);
+    }
+
+    #[test]
+    fn game_level_test_brick_burns() {
+        let mut level = GameLevel::new();
+        let mut brick = GameObject::new_ex0(glm::vec2(0.0, 0.0), glm::vec2(10.0, 10.0), Texture2D::empty());
+        brick.hp = 3;
+        brick.status.apply(StatusKind::Burn, 10.0);
+        level.bricks = vec![brick];
+        level.flashes = vec![0.0];
+        level.update_status(BURN_TICK);
+        assert_eq!(level.bricks[0].hp, 2);
+        assert_eq!(level.flash_amount(0), 1.0);
+        // the burn never takes the last hit point
+        level.update_status(BURN_TICK);
+        level.update_status(BURN_TICK);
+        assert_eq!(level.bricks[0].hp, 1);
+        assert!(!level.bricks[0].destroyed);
+    }
+
+    #[test]
+    fn game_level_test_brick_dissolve_ends() {
+        let mut level = GameLevel::new();
+        level.dissolves = vec![None; 2];
+        level.dissolve_brick(0);
+        assert!(level.is_dissolving(0));
+        assert!(!level.is_dissolving(1));
+        level.update(BRICK_DISSOLVE_TIME / 2.0);
+        assert!(level.is_dissolving(0));
+        level.update(BRICK_DISSOLVE_TIME);
+        assert!(!level.is_dissolving(0));
+    }
+
+    #[test]
+    fn level_checksum_test() {
+        let one = vec![vec![1, 2], vec![3, 4]];
+        let two = vec![vec![1, 2, 3, 4]];
+        assert_eq!(level_checksum(&one), level_checksum(&one.clone()));
+        assert_ne!(level_checksum(&one), level_checksum(&two));
+    }
 }
\ No newline at end of file
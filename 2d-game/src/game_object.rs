@@ -17,7 +17,10 @@
 extern crate nalgebra_glm as glm;
 
 use learnopengl_shared::util;
+use crate::inspect::{Inspect, Value};
+use crate::material::Material;
 use crate::sprite_renderer::SpriteRenderer;
+use crate::status::StatusEffects;
 use crate::texture::Texture2D;
 
 // Container object for holding all state relevant for a single
@@ -33,8 +36,14 @@ pub struct GameObject {
     pub rotation: f32,
     pub is_solid: bool,
     pub destroyed: bool,
+    // hits needed until the object is destroyed (used by bricks)
+    pub hp: u32,
+    // slow, burn, frozen, ...
+    pub status: StatusEffects,
     // render state
-    pub sprite: Texture2D
+    pub sprite: Texture2D,
+    // drawn with the sprite shader if None
+    pub material: Option<Material>
 }
 
 impl GameObject {
@@ -48,7 +57,10 @@ impl GameObject {
             rotation: 0.0,
             is_solid: false,
             destroyed: false,
-            sprite: Texture2D::new()
+            hp: 1,
+            status: StatusEffects::default(),
+            sprite: Texture2D::new(),
+            material: None
         }
     }
 
@@ -81,18 +93,75 @@ impl GameObject {
             rotation: 0.0,
             is_solid: false,
             destroyed: false,
-            sprite
+            hp: 1,
+            status: StatusEffects::default(),
+            sprite,
+            material: None
+        }
+    }
+
+    // advances the status effects; burns take hit points from objects which have more than one left
+    // (they never destroy anything themselves). Returns the hit points taken
+    pub fn update_status(&mut self, dt: f32) -> u32 {
+        if self.status.is_empty() {
+            return 0;
         }
+        let damage = self.status.update(dt);
+        if self.is_solid {
+            return 0;
+        }
+        let damage = damage.min(self.hp.saturating_sub(1));
+        self.hp -= damage;
+        damage
+    }
+
+    // the color the object is drawn with, tinted by its status effects
+    pub fn shown_color(&self) -> glm::TVec3<f32> {
+        self.status.tinted(self.color)
     }
 
     // draw sprite
     pub fn draw(&self, renderer: &SpriteRenderer) {
-        renderer.draw_sprite_ex2(
+        renderer.draw_sprite_ex3(
             &self.sprite,
             self.position,
             self.size,
             self.rotation,
-            self.color
+            self.shown_color(),
+            self.material.as_ref()
         );
     }
-}
\ No newline at end of file
+}
+impl Inspect for GameObject {
+    fn property_names(&self) -> Vec<&'static str> {
+        vec!["position", "size", "velocity", "color", "rotation", "solid", "destroyed", "hp"]
+    }
+
+    fn property(&self, name: &str) -> Option<Value> {
+        Some(match name {
+            "position" => Value::Vec2(self.position),
+            "size" => Value::Vec2(self.size),
+            "velocity" => Value::Vec2(self.velocity),
+            "color" => Value::Vec3(self.color),
+            "rotation" => Value::Float(self.rotation),
+            "solid" => Value::Bool(self.is_solid),
+            "destroyed" => Value::Bool(self.destroyed),
+            "hp" => Value::Int(self.hp as i64),
+            _ => return None
+        })
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        match (name, value) {
+            ("position", Value::Vec2(v)) => self.position = v,
+            ("size", Value::Vec2(v)) => self.size = v,
+            ("velocity", Value::Vec2(v)) => self.velocity = v,
+            ("color", Value::Vec3(v)) => self.color = v,
+            ("rotation", Value::Float(v)) => self.rotation = v,
+            ("solid", Value::Bool(v)) => self.is_solid = v,
+            ("destroyed", Value::Bool(v)) => self.destroyed = v,
+            ("hp", Value::Int(v)) => self.hp = v.max(0) as u32,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,329 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(test)]
+use std::cell::{Cell, RefCell};
+use std::ffi::c_void;
+
+// The GL calls issued by the batched renderers, behind a trait so their tests can put a
+// recording mock in place of the driver and check the command sequence without a GPU.
+// SpriteBatch is generic over it (defaulting to NativeGl, which forwards to the gl crate)
+// and the text renderers draw their glyphs through text_renderer::draw_glyphs.
+
+/// The subset of GL used by the sprite batch, the glyph drawing and the clip rects.
+pub trait GlApi {
+    fn gen_vertex_array(&self) -> u32;
+    fn gen_buffer(&self) -> u32;
+    fn delete_vertex_array(&self, vao: u32);
+    fn delete_buffer(&self, buffer: u32);
+    fn bind_vertex_array(&self, vao: u32);
+    fn bind_buffer(&self, target: u32, buffer: u32);
+    fn buffer_data(&self, target: u32, data: &[u8], usage: u32);
+    fn buffer_sub_data(&self, target: u32, offset: usize, data: &[u8]);
+    fn enable_vertex_attrib_array(&self, index: u32);
+    // float attribute of `size` components, `offset` bytes into the bound buffer
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, stride: usize, offset: usize);
+    fn vertex_attrib_divisor(&self, index: u32, divisor: u32);
+    fn use_program(&self, program: u32);
+    fn active_texture(&self, unit: u32);
+    fn bind_texture(&self, target: u32, texture: u32);
+    fn draw_arrays(&self, mode: u32, first: usize, count: usize);
+    fn draw_arrays_instanced(&self, mode: u32, first: usize, count: usize, instances: usize);
+    fn enable(&self, capability: u32);
+    fn disable(&self, capability: u32);
+    fn scissor(&self, x: i32, y: i32, width: i32, height: i32);
+}
+
+/// Issues the calls to the driver.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NativeGl;
+
+impl GlApi for NativeGl {
+    fn gen_vertex_array(&self) -> u32 {
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+        }
+        vao
+    }
+
+    fn gen_buffer(&self) -> u32 {
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+        }
+        buffer
+    }
+
+    fn delete_vertex_array(&self, vao: u32) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &vao);
+        }
+    }
+
+    fn delete_buffer(&self, buffer: u32) {
+        unsafe {
+            gl::DeleteBuffers(1, &buffer);
+        }
+    }
+
+    fn bind_vertex_array(&self, vao: u32) {
+        unsafe {
+            gl::BindVertexArray(vao);
+        }
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: u32) {
+        unsafe {
+            gl::BindBuffer(target, buffer);
+        }
+    }
+
+    fn buffer_data(&self, target: u32, data: &[u8], usage: u32) {
+        unsafe {
+            gl::BufferData(target, data.len() as _, data.as_ptr() as *const c_void, usage);
+        }
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: usize, data: &[u8]) {
+        unsafe {
+            gl::BufferSubData(target, offset as _, data.len() as _, data.as_ptr() as *const c_void);
+        }
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        unsafe {
+            gl::EnableVertexAttribArray(index);
+        }
+    }
+
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, stride: usize, offset: usize) {
+        unsafe {
+            gl::VertexAttribPointer(index, size, gl::FLOAT, gl::FALSE, stride as _, offset as *const c_void);
+        }
+    }
+
+    fn vertex_attrib_divisor(&self, index: u32, divisor: u32) {
+        unsafe {
+            gl::VertexAttribDivisor(index, divisor);
+        }
+    }
+
+    fn use_program(&self, program: u32) {
+        unsafe {
+            gl::UseProgram(program);
+        }
+    }
+
+    fn active_texture(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(unit);
+        }
+    }
+
+    fn bind_texture(&self, target: u32, texture: u32) {
+        unsafe {
+            gl::BindTexture(target, texture);
+        }
+    }
+
+    fn draw_arrays(&self, mode: u32, first: usize, count: usize) {
+        unsafe {
+            gl::DrawArrays(mode, first as _, count as _);
+        }
+    }
+
+    fn draw_arrays_instanced(&self, mode: u32, first: usize, count: usize, instances: usize) {
+        unsafe {
+            gl::DrawArraysInstanced(mode, first as _, count as _, instances as _);
+        }
+    }
+
+    fn enable(&self, capability: u32) {
+        unsafe {
+            gl::Enable(capability);
+        }
+    }
+
+    fn disable(&self, capability: u32) {
+        unsafe {
+            gl::Disable(capability);
+        }
+    }
+
+    fn scissor(&self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe {
+            gl::Scissor(x, y, width, height);
+        }
+    }
+}
+
+/// A call as recorded by RecordingGl (buffer contents are kept by their size).
+#[cfg(test)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum GlCall {
+    GenVertexArray(u32),
+    GenBuffer(u32),
+    DeleteVertexArray(u32),
+    DeleteBuffer(u32),
+    BindVertexArray(u32),
+    BindBuffer(u32, u32),
+    BufferData(u32, usize),
+    BufferSubData(u32, usize, usize),
+    EnableVertexAttribArray(u32),
+    VertexAttribPointer(u32, i32, usize, usize),
+    VertexAttribDivisor(u32, u32),
+    UseProgram(u32),
+    ActiveTexture(u32),
+    BindTexture(u32, u32),
+    DrawArrays(u32, usize, usize),
+    DrawArraysInstanced(u32, usize, usize, usize),
+    Enable(u32),
+    Disable(u32),
+    Scissor(i32, i32, i32, i32)
+}
+
+// records the calls instead of issuing them; generated names count up from 1
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingGl {
+    pub calls: RefCell<Vec<GlCall>>,
+    next_name: Cell<u32>
+}
+
+#[cfg(test)]
+impl RecordingGl {
+    // takes the calls recorded so far
+    pub fn take(&self) -> Vec<GlCall> {
+        self.calls.take()
+    }
+
+    fn record(&self, call: GlCall) {
+        self.calls.borrow_mut().push(call);
+    }
+
+    fn name(&self) -> u32 {
+        self.next_name.set(self.next_name.get() + 1);
+        self.next_name.get()
+    }
+}
+
+#[cfg(test)]
+impl GlApi for RecordingGl {
+    fn gen_vertex_array(&self) -> u32 {
+        let vao = self.name();
+        self.record(GlCall::GenVertexArray(vao));
+        vao
+    }
+
+    fn gen_buffer(&self) -> u32 {
+        let buffer = self.name();
+        self.record(GlCall::GenBuffer(buffer));
+        buffer
+    }
+
+    fn delete_vertex_array(&self, vao: u32) {
+        self.record(GlCall::DeleteVertexArray(vao));
+    }
+
+    fn delete_buffer(&self, buffer: u32) {
+        self.record(GlCall::DeleteBuffer(buffer));
+    }
+
+    fn bind_vertex_array(&self, vao: u32) {
+        self.record(GlCall::BindVertexArray(vao));
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: u32) {
+        self.record(GlCall::BindBuffer(target, buffer));
+    }
+
+    fn buffer_data(&self, target: u32, data: &[u8], _usage: u32) {
+        self.record(GlCall::BufferData(target, data.len()));
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: usize, data: &[u8]) {
+        self.record(GlCall::BufferSubData(target, offset, data.len()));
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        self.record(GlCall::EnableVertexAttribArray(index));
+    }
+
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, stride: usize, offset: usize) {
+        self.record(GlCall::VertexAttribPointer(index, size, stride, offset));
+    }
+
+    fn vertex_attrib_divisor(&self, index: u32, divisor: u32) {
+        self.record(GlCall::VertexAttribDivisor(index, divisor));
+    }
+
+    fn use_program(&self, program: u32) {
+        self.record(GlCall::UseProgram(program));
+    }
+
+    fn active_texture(&self, unit: u32) {
+        self.record(GlCall::ActiveTexture(unit));
+    }
+
+    fn bind_texture(&self, target: u32, texture: u32) {
+        self.record(GlCall::BindTexture(target, texture));
+    }
+
+    fn draw_arrays(&self, mode: u32, first: usize, count: usize) {
+        self.record(GlCall::DrawArrays(mode, first, count));
+    }
+
+    fn draw_arrays_instanced(&self, mode: u32, first: usize, count: usize, instances: usize) {
+        self.record(GlCall::DrawArraysInstanced(mode, first, count, instances));
+    }
+
+    fn enable(&self, capability: u32) {
+        self.record(GlCall::Enable(capability));
+    }
+
+    fn disable(&self, capability: u32) {
+        self.record(GlCall::Disable(capability));
+    }
+
+    fn scissor(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.record(GlCall::Scissor(x, y, width, height));
+    }
+}
+
+// calls which change GL state to what it already was: repeated binds of the same object
+#[cfg(test)]
+pub fn redundant_calls(calls: &[GlCall]) -> Vec<GlCall> {
+    use std::collections::HashMap;
+    let mut bound: HashMap<(u8, u32), u32> = HashMap::new();
+    let mut redundant = Vec::new();
+    for call in calls {
+        let binding = match call {
+            GlCall::BindVertexArray(vao) => Some(((0, 0), *vao)),
+            GlCall::BindBuffer(target, buffer) => Some(((1, *target), *buffer)),
+            GlCall::UseProgram(program) => Some(((2, 0), *program)),
+            GlCall::ActiveTexture(unit) => Some(((3, 0), *unit)),
+            GlCall::BindTexture(target, texture) => Some(((4, *target), *texture)),
+            _ => None
+        };
+        if let Some((slot, name)) = binding {
+            if bound.insert(slot, name) == Some(name) {
+                redundant.push(call.clone());
+            }
+        }
+    }
+    redundant
+}
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::RwLock;
+use gl::types::{GLenum, GLuint};
+use log::Level;
+use serde::{Deserialize, Serialize};
+
+// Formatting and filtering of the messages reported through the GL debug output callback.
+
+// non-significant driver messages which are always ignored
+const IGNORED_IDS: [GLuint; 4] = [131169, 131185, 131218, 131204];
+
+/// Which GL debug messages are logged, stored in the [gl_debug] table of the settings file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlDebugSettings {
+    // source names (e.g. "Third Party") whose messages are ignored
+    pub ignore_sources: Vec<String>,
+    // type names (e.g. "Performance") whose messages are ignored
+    pub ignore_types: Vec<String>,
+    // stop at high-severity messages in debug builds
+    pub break_on_error: bool
+}
+
+static SETTINGS: RwLock<GlDebugSettings> = RwLock::new(GlDebugSettings {
+    ignore_sources: Vec::new(),
+    ignore_types: Vec::new(),
+    break_on_error: false
+});
+
+pub fn set_settings(settings: &GlDebugSettings) {
+    *SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = settings.clone();
+}
+
+pub fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "API",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "Window System",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "Shader Compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "Third Party",
+        gl::DEBUG_SOURCE_APPLICATION => "Application",
+        _ => "Other"
+    }
+}
+
+pub fn type_name(gltype: GLenum) -> &'static str {
+    match gltype {
+        gl::DEBUG_TYPE_ERROR => "Error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "Deprecated Behaviour",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "Undefined Behaviour",
+        gl::DEBUG_TYPE_PORTABILITY => "Portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "Performance",
+        gl::DEBUG_TYPE_MARKER => "Marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "Push Group",
+        gl::DEBUG_TYPE_POP_GROUP => "Pop Group",
+        _ => "Other"
+    }
+}
+
+pub fn severity_name(severity: GLenum) -> &'static str {
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => "high",
+        gl::DEBUG_SEVERITY_MEDIUM => "medium",
+        gl::DEBUG_SEVERITY_LOW => "low",
+        _ => "notification"
+    }
+}
+
+// log level matching the severity of a message
+pub fn severity_level(severity: GLenum) -> Level {
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => Level::Error,
+        gl::DEBUG_SEVERITY_MEDIUM => Level::Warn,
+        gl::DEBUG_SEVERITY_LOW => Level::Info,
+        _ => Level::Debug
+    }
+}
+
+impl GlDebugSettings {
+    // whether a message with the given id, source and type should be logged
+    pub fn accepts(&self, id: GLuint, source: GLenum, gltype: GLenum) -> bool {
+        let ignored = |names: &[String], name: &str| names.iter().any(|it| it.eq_ignore_ascii_case(name));
+        // the debug groups of frame_capture echo back through the callback
+        !IGNORED_IDS.contains(&id)
+            && gltype != gl::DEBUG_TYPE_PUSH_GROUP
+            && gltype != gl::DEBUG_TYPE_POP_GROUP
+            && !ignored(&self.ignore_sources, source_name(source))
+            && !ignored(&self.ignore_types, type_name(gltype))
+    }
+}
+
+pub fn format_message(id: GLuint, source: GLenum, gltype: GLenum, severity: GLenum, message: &str) -> String {
+    format!(
+        "Debug message ({}) [source: {}, type: {}, severity: {}]: {}",
+        id,
+        source_name(source),
+        type_name(gltype),
+        severity_name(severity),
+        message.trim_end()
+    )
+}
+
+// logs a message reported by the GL debug output callback as a single record
+pub fn handle_message(id: GLuint, source: GLenum, gltype: GLenum, severity: GLenum, message: &str) {
+    let settings = SETTINGS.read().unwrap_or_else(|e| e.into_inner());
+    if !settings.accepts(id, source, gltype) {
+        return;
+    }
+    log::log!(target: "gl", severity_level(severity), "{}", format_message(id, source, gltype, severity, message));
+    if settings.break_on_error && severity == gl::DEBUG_SEVERITY_HIGH {
+        debug_assert!(false, "GL error: {}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gl_debug_settings_test_accepts() {
+        let settings = GlDebugSettings {
+            ignore_sources: vec!["third party".to_string()],
+            ignore_types: vec!["Performance".to_string()],
+            break_on_error: false
+        };
+        assert!(settings.accepts(1, gl::DEBUG_SOURCE_API, gl::DEBUG_TYPE_ERROR));
+        assert!(!settings.accepts(131185, gl::DEBUG_SOURCE_API, gl::DEBUG_TYPE_ERROR));
+        assert!(!settings.accepts(1, gl::DEBUG_SOURCE_THIRD_PARTY, gl::DEBUG_TYPE_ERROR));
+        assert!(!settings.accepts(1, gl::DEBUG_SOURCE_API, gl::DEBUG_TYPE_PERFORMANCE));
+        assert!(!settings.accepts(1, gl::DEBUG_SOURCE_APPLICATION, gl::DEBUG_TYPE_PUSH_GROUP));
+    }
+
+    #[test]
+    fn format_message_test_case() {
+        let string = format_message(1280, gl::DEBUG_SOURCE_API, gl::DEBUG_TYPE_ERROR, gl::DEBUG_SEVERITY_HIGH, "invalid enum\n");
+        assert_eq!(string, "Debug message (1280) [source: API, type: Error, severity: high]: invalid enum");
+        assert_eq!(severity_level(gl::DEBUG_SEVERITY_HIGH), Level::Error);
+        assert_eq!(severity_level(gl::DEBUG_SEVERITY_NOTIFICATION), Level::Debug);
+    }
+}
@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::CStr;
+use std::sync::Mutex;
+use glfw::{Action, Context, Glfw, Key, Window};
+use learnopengl_shared::filesystem;
+use crate::text_renderer::ascii::TextRenderer;
+use crate::text_renderer::ITextRenderer;
+
+// What the GPU and driver offer is gathered right after the context is created and checked
+// against what the game needs, so an unsuitable machine gets a readable explanation instead of
+// a panic somewhere in the renderer. Missing optional features (debug output, enough MSAA
+// samples) only switch the game to a fallback.
+
+// the OpenGL version the shaders are written for
+pub const REQUIRED_VERSION: (u32, u32) = (3, 3);
+// the largest texture or framebuffer side the game uses (the background, supersampled targets)
+pub const REQUIRED_TEXTURE_SIZE: i32 = 2048;
+// MSAA samples of the post-processor when the GPU isn't queried
+const DEFAULT_SAMPLES: u32 = 4;
+
+/// What the OpenGL context offers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GpuCaps {
+    pub vendor: String,
+    pub renderer: String,
+    pub version: String,
+    // parsed from the version string, (0, 0) if it can't be
+    pub major: u32,
+    pub minor: u32,
+    pub extensions: Vec<String>,
+    pub max_texture_size: i32,
+    pub max_samples: i32
+}
+
+static CAPS: Mutex<Option<GpuCaps>> = Mutex::new(None);
+
+// "4.6.0 NVIDIA 535.54" or "OpenGL ES 3.2 Mesa 23.0" -> (4, 6)
+pub fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let number = version.split_whitespace().find(|it| it.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = number.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().ok()?;
+    Some((major, minor))
+}
+
+fn gl_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(string as *const _).to_string_lossy().into_owned()
+    }
+}
+
+impl GpuCaps {
+    // queries the current context (which may be older than the required version)
+    pub fn query() -> Self {
+        let version = gl_string(gl::VERSION);
+        let (major, minor) = parse_version(version.as_str()).unwrap_or((0, 0));
+        let mut caps = Self {
+            vendor: gl_string(gl::VENDOR),
+            renderer: gl_string(gl::RENDERER),
+            version,
+            major,
+            minor,
+            ..Self::default()
+        };
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut caps.max_texture_size);
+            if major >= 3 {
+                gl::GetIntegerv(gl::MAX_SAMPLES, &mut caps.max_samples);
+                let mut count = 0;
+                gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+                for i in 0..count.max(0) as u32 {
+                    let name = gl::GetStringi(gl::EXTENSIONS, i);
+                    if !name.is_null() {
+                        caps.extensions.push(CStr::from_ptr(name as *const _).to_string_lossy().into_owned());
+                    }
+                }
+            } else {
+                caps.extensions = gl_string(gl::EXTENSIONS).split_whitespace().map(|it| it.to_string()).collect();
+            }
+        }
+        caps
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|it| it == name)
+    }
+
+    pub fn is_at_least(&self, major: u32, minor: u32) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+
+    // debug output is core since 4.3
+    pub fn has_debug_output(&self) -> bool {
+        self.is_at_least(4, 3) || self.has_extension("GL_KHR_debug")
+    }
+
+    // vendor, renderer and version, e.g. for crash reports
+    pub fn summary(&self) -> String {
+        format!("{} / {} / {}", self.vendor, self.renderer, self.version)
+    }
+
+    // the requirements of the game which aren't met, as readable sentences
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let (major, minor) = REQUIRED_VERSION;
+        if !self.is_at_least(major, minor) {
+            problems.push(format!("OpenGL {}.{} is needed, but the driver only offers {}.", major, minor,
+                if self.version.is_empty() { "an unknown version" } else { self.version.as_str() }));
+        }
+        if self.max_texture_size < REQUIRED_TEXTURE_SIZE {
+            problems.push(format!("Textures of {} pixels are needed, but at most {} are supported.",
+                REQUIRED_TEXTURE_SIZE, self.max_texture_size));
+        }
+        problems
+    }
+}
+
+// remembers the capabilities of the game's context for the fallbacks
+pub fn set(caps: GpuCaps) {
+    *CAPS.lock().unwrap() = Some(caps);
+}
+
+// MSAA samples to use when the given number is wanted
+pub fn samples(wanted: u32) -> u32 {
+    match CAPS.lock().unwrap().as_ref() {
+        Some(caps) => wanted.min(caps.max_samples.max(0) as u32),
+        None => wanted.min(DEFAULT_SAMPLES)
+    }
+}
+
+// the lines shown when the game can't run, ending in what the GPU offers
+pub fn report(problems: &[String], caps: Option<&GpuCaps>) -> Vec<String> {
+    let mut lines = vec!["Breakout can't run on this computer:".to_string(), String::new()];
+    lines.extend(problems.iter().cloned());
+    lines.push(String::new());
+    match caps {
+        Some(caps) => lines.push(format!("GPU: {}", caps.summary())),
+        None => lines.push("GPU: no OpenGL context could be created".to_string())
+    }
+    lines.push("Updating the graphics driver may help.".to_string());
+    lines
+}
+
+fn log_report(lines: &[String]) {
+    for line in lines.iter().filter(|it| !it.is_empty()) {
+        log::error!("GPU_CAPS: {}", line);
+    }
+}
+
+fn wait_for_close(glfw: &mut Glfw, window: &mut Window, mut draw: impl FnMut(&mut Window)) {
+    while !window.should_close() && window.get_key(Key::Escape) != Action::Press {
+        draw(window);
+        window.swap_buffers();
+        glfw.wait_events_timeout(0.1);
+    }
+}
+
+// explains that no context of the required version could be created, in a window with a
+// context of any version if there is one (its title tells what is wrong), and exits
+pub fn report_unsupported_context(glfw: &mut Glfw) -> ! {
+    glfw.default_window_hints();
+    let Some((mut window, _)) = glfw.create_window(640, 120, "Breakout", glfw::WindowMode::Windowed) else {
+        let (major, minor) = REQUIRED_VERSION;
+        log_report(&report(&[format!("No OpenGL context could be created (OpenGL {}.{} is needed).", major, minor)], None));
+        std::process::exit(1);
+    };
+    window.make_current();
+    gl::load_with(|s| window.get_proc_address(s) as *const _);
+    let caps = GpuCaps::query();
+    let mut problems = caps.problems();
+    if problems.is_empty() {
+        problems.push("The driver refused to create an OpenGL 3.3 core profile context.".to_string());
+    }
+    let lines = report(&problems, Some(&caps));
+    log_report(&lines);
+    // our shaders need the missing version, so the window can only show the problem in its title
+    window.set_title(format!("Breakout can't run: {}", problems[0]).as_str());
+    wait_for_close(glfw, &mut window, |_| unsafe {
+        gl::ClearColor(0.35, 0.05, 0.05, 1.0);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+    });
+    std::process::exit(1);
+}
+
+// shows the unmet requirements in the game's window until it is closed, and exits
+pub fn show_problems(glfw: &mut Glfw, window: &mut Window, caps: &GpuCaps, width: u32, height: u32) -> ! {
+    let lines = report(&caps.problems(), Some(caps));
+    log_report(&lines);
+    unsafe {
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    }
+    let mut text = TextRenderer::new(width, height);
+    text.load(filesystem::get_path("resources/fonts/OCRAEXT.TTF".to_string()), 24);
+    wait_for_close(glfw, window, |window| {
+        let (framebuffer_width, framebuffer_height) = window.get_framebuffer_size();
+        unsafe {
+            gl::Viewport(0, 0, framebuffer_width, framebuffer_height);
+            gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        for (i, line) in lines.iter().enumerate() {
+            text.render_text(line.clone(), 40.0, 40.0 + i as f32 * 32.0, 0.7);
+        }
+    });
+    std::process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(version: &str, max_texture_size: i32) -> GpuCaps {
+        let (major, minor) = parse_version(version).unwrap_or((0, 0));
+        GpuCaps { version: version.to_string(), major, minor, max_texture_size, max_samples: 8, ..GpuCaps::default() }
+    }
+
+    #[test]
+    fn gpu_caps_test_parse_version() {
+        assert_eq!(parse_version("4.6.0 NVIDIA 535.54.03"), Some((4, 6)));
+        assert_eq!(parse_version("3.3 (Core Profile) Mesa 23.2.1"), Some((3, 3)));
+        assert_eq!(parse_version("OpenGL ES 3.2 Mesa 23.0"), Some((3, 2)));
+        assert_eq!(parse_version("2.1 Metal - 83.1"), Some((2, 1)));
+        assert_eq!(parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn gpu_caps_test_problems() {
+        assert!(caps("4.6.0 NVIDIA", 16384).problems().is_empty());
+        assert!(caps("3.3 (Core Profile) Mesa", 4096).problems().is_empty());
+        let problems = caps("2.1 Mesa 20.0", 1024).problems();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("2.1 Mesa 20.0"));
+        let mut old = caps("4.1 Metal", 16384);
+        assert!(!old.has_debug_output());
+        old.extensions.push("GL_KHR_debug".to_string());
+        assert!(old.has_debug_output());
+    }
+
+    #[test]
+    fn gpu_caps_test_report() {
+        let lines = report(&["OpenGL 3.3 is needed.".to_string()], None);
+        assert!(lines.iter().any(|it| it == "OpenGL 3.3 is needed."));
+        assert!(lines.iter().any(|it| it.contains("no OpenGL context")));
+    }
+}
@@ -0,0 +1,374 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use glfw::{GamepadButton, Key, MouseButton};
+
+// amount of key codes tracked (GLFW key codes are below this)
+const KEY_COUNT: usize = 1024;
+// amount of mouse buttons and gamepad buttons tracked
+const MOUSE_BUTTON_COUNT: usize = 8;
+const GAMEPAD_BUTTON_COUNT: usize = 15;
+// time a key has to be held before it starts repeating
+pub const REPEAT_DELAY: f32 = 0.35;
+// time between two repeats of a held key
+pub const REPEAT_INTERVAL: f32 = 0.1;
+
+// InputState collects the keyboard events delivered by the key callback and exposes
+// them per simulation step. Presses and releases stay visible until the end of the next
+// step, so short taps aren't lost when a frame runs no simulation step at all.
+pub struct InputState {
+    held: [bool; KEY_COUNT],
+    pressed: [bool; KEY_COUNT],
+    released: [bool; KEY_COUNT],
+    // whether a held key repeats during the current step
+    repeated: [bool; KEY_COUNT],
+    // time each key has been held for
+    held_time: [f32; KEY_COUNT],
+    // time since each key was last pressed
+    since_pressed: [f32; KEY_COUNT],
+    // time between the last two presses of each key
+    between_presses: [f32; KEY_COUNT],
+    // cursor position in screen coordinates and whether it moved since the last step
+    cursor: glm::TVec2<f32>,
+    cursor_moved: bool,
+    mouse_held: [bool; MOUSE_BUTTON_COUNT],
+    mouse_pressed: [bool; MOUSE_BUTTON_COUNT],
+    // buttons of the first gamepad, as polled each frame
+    gamepad_held: [bool; GAMEPAD_BUTTON_COUNT],
+    gamepad_pressed: [bool; GAMEPAD_BUTTON_COUNT]
+}
+
+fn index(key: Key) -> Option<usize> {
+    let code = key as i32;
+    if (0..KEY_COUNT as i32).contains(&code) {
+        Some(code as usize)
+    } else {
+        None
+    }
+}
+
+// number of repeats of a key which has been held for the given time
+fn repeat_ticks(held_time: f32) -> u32 {
+    if held_time < REPEAT_DELAY {
+        0
+    } else {
+        1 + ((held_time - REPEAT_DELAY) / REPEAT_INTERVAL) as u32
+    }
+}
+
+impl InputState {
+    // constructor
+    pub fn new() -> Self {
+        Self {
+            held: [false; KEY_COUNT],
+            pressed: [false; KEY_COUNT],
+            released: [false; KEY_COUNT],
+            repeated: [false; KEY_COUNT],
+            held_time: [0.0; KEY_COUNT],
+            since_pressed: [f32::INFINITY; KEY_COUNT],
+            between_presses: [f32::INFINITY; KEY_COUNT],
+            cursor: glm::vec2(0.0, 0.0),
+            cursor_moved: false,
+            mouse_held: [false; MOUSE_BUTTON_COUNT],
+            mouse_pressed: [false; MOUSE_BUTTON_COUNT],
+            gamepad_held: [false; GAMEPAD_BUTTON_COUNT],
+            gamepad_pressed: [false; GAMEPAD_BUTTON_COUNT]
+        }
+    }
+
+    // called by the key callback
+    pub fn key_down(&mut self, key: Key) {
+        if let Some(i) = index(key) {
+            if !self.held[i] {
+                self.pressed[i] = true;
+                self.held_time[i] = 0.0;
+                self.between_presses[i] = self.since_pressed[i];
+                self.since_pressed[i] = 0.0;
+            }
+            self.held[i] = true;
+        }
+    }
+
+    // called by the key callback
+    pub fn key_up(&mut self, key: Key) {
+        if let Some(i) = index(key) {
+            if self.held[i] {
+                self.released[i] = true;
+            }
+            self.held[i] = false;
+        }
+    }
+
+    // called by the cursor position callback
+    pub fn cursor_moved_to(&mut self, x: f32, y: f32) {
+        self.cursor = glm::vec2(x, y);
+        self.cursor_moved = true;
+    }
+
+    // called by the mouse button callback
+    pub fn mouse_down(&mut self, button: MouseButton) {
+        let i = button as usize;
+        if !self.mouse_held[i] {
+            self.mouse_pressed[i] = true;
+        }
+        self.mouse_held[i] = true;
+    }
+
+    // called by the mouse button callback
+    pub fn mouse_up(&mut self, button: MouseButton) {
+        self.mouse_held[button as usize] = false;
+    }
+
+    // called with the polled state of each gamepad button
+    pub fn set_gamepad_button(&mut self, button: GamepadButton, down: bool) {
+        let i = button as usize;
+        if down && !self.gamepad_held[i] {
+            self.gamepad_pressed[i] = true;
+        }
+        self.gamepad_held[i] = down;
+    }
+
+    pub fn cursor(&self) -> glm::TVec2<f32> {
+        self.cursor
+    }
+
+    // whether the cursor moved since the last step
+    pub fn cursor_moved(&self) -> bool {
+        self.cursor_moved
+    }
+
+    // whether the mouse button was pressed since the last step
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_pressed[button as usize]
+    }
+
+    // whether the gamepad button was pressed since the last step
+    pub fn gamepad_just_pressed(&self, button: GamepadButton) -> bool {
+        self.gamepad_pressed[button as usize]
+    }
+
+    // whether any key or button was pressed since the last step
+    pub fn any_pressed(&self) -> bool {
+        self.pressed.contains(&true) || self.mouse_pressed.contains(&true) || self.gamepad_pressed.contains(&true)
+    }
+
+    // whether anything was pressed, released or moved since the last step, or is still held
+    pub fn is_active(&self) -> bool {
+        self.cursor_moved || self.any_pressed() || self.released.contains(&true)
+            || self.held.contains(&true) || self.mouse_held.contains(&true) || self.gamepad_held.contains(&true)
+    }
+
+    // whether the key is currently held down
+    pub fn held(&self, key: Key) -> bool {
+        index(key).is_some_and(|i| self.held[i])
+    }
+
+    // whether the key was pressed since the last step
+    pub fn just_pressed(&self, key: Key) -> bool {
+        index(key).is_some_and(|i| self.pressed[i])
+    }
+
+    // whether the key was released since the last step
+    pub fn just_released(&self, key: Key) -> bool {
+        index(key).is_some_and(|i| self.released[i])
+    }
+
+    // like just_pressed, but repeats while the key is held (after REPEAT_DELAY, every REPEAT_INTERVAL)
+    pub fn pressed_repeat(&self, key: Key) -> bool {
+        index(key).is_some_and(|i| self.pressed[i] || self.repeated[i])
+    }
+
+    // whether the key was pressed at most `seconds` ago, even if it has been released since
+    pub fn pressed_within(&self, key: Key, seconds: f32) -> bool {
+        index(key).is_some_and(|i| self.since_pressed[i] <= seconds)
+    }
+
+    // whether the key was pressed since the last step for the second time within `seconds`
+    pub fn double_tapped(&self, key: Key, seconds: f32) -> bool {
+        index(key).is_some_and(|i| self.pressed[i] && self.between_presses[i] <= seconds)
+    }
+
+    // either of the shift keys is held
+    pub fn shift(&self) -> bool {
+        self.held(Key::LeftShift) || self.held(Key::RightShift)
+    }
+
+    // either of the control keys is held
+    pub fn ctrl(&self) -> bool {
+        self.held(Key::LeftControl) || self.held(Key::RightControl)
+    }
+
+    // finishes a simulation step of the given length
+    pub fn end_step(&mut self, dt: f32) {
+        self.cursor_moved = false;
+        self.mouse_pressed = [false; MOUSE_BUTTON_COUNT];
+        self.gamepad_pressed = [false; GAMEPAD_BUTTON_COUNT];
+        for i in 0..KEY_COUNT {
+            self.pressed[i] = false;
+            self.released[i] = false;
+            self.since_pressed[i] += dt;
+            if self.held[i] {
+                let before = repeat_ticks(self.held_time[i]);
+                self.held_time[i] += dt;
+                self.repeated[i] = repeat_ticks(self.held_time[i]) > before;
+            } else {
+                self.repeated[i] = false;
+            }
+        }
+    }
+
+    // forgets all keys, e.g. because releases can't be observed while the window has no focus
+    pub fn clear(&mut self) {
+        let cursor = self.cursor;
+        *self = Self::new();
+        self.cursor = cursor;
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_state_test_edges() {
+        let mut input = InputState::new();
+        input.key_down(Key::Space);
+        assert!(input.just_pressed(Key::Space));
+        assert!(input.held(Key::Space));
+        input.end_step(0.01);
+        assert!(!input.just_pressed(Key::Space));
+        assert!(input.held(Key::Space));
+        input.key_up(Key::Space);
+        assert!(input.just_released(Key::Space));
+        input.end_step(0.01);
+        assert!(!input.just_released(Key::Space));
+    }
+
+    #[test]
+    fn input_state_test_any_pressed() {
+        let mut input = InputState::new();
+        assert!(!input.any_pressed());
+        input.set_gamepad_button(GamepadButton::ButtonA, true);
+        assert!(input.any_pressed());
+        input.end_step(0.01);
+        input.key_down(Key::Q);
+        assert!(input.any_pressed());
+        input.end_step(0.01);
+        assert!(!input.any_pressed(), "Held keys don't count.");
+    }
+
+    #[test]
+    fn input_state_test_is_active() {
+        let mut input = InputState::new();
+        assert!(!input.is_active());
+        input.key_down(Key::Q);
+        input.end_step(0.01);
+        assert!(input.is_active(), "Held keys count.");
+        input.key_up(Key::Q);
+        input.end_step(0.01);
+        input.end_step(0.01);
+        assert!(!input.is_active());
+        input.cursor_moved_to(5.0, 5.0);
+        assert!(input.is_active());
+    }
+
+    #[test]
+    fn input_state_test_tap_between_steps() {
+        let mut input = InputState::new();
+        // pressed and released before the next step ran
+        input.key_down(Key::Enter);
+        input.key_up(Key::Enter);
+        assert!(input.just_pressed(Key::Enter));
+        assert!(!input.held(Key::Enter));
+    }
+
+    #[test]
+    fn input_state_test_repeat() {
+        let mut input = InputState::new();
+        input.key_down(Key::W);
+        assert!(input.pressed_repeat(Key::W));
+        let mut repeats = 0;
+        // hold the key for one second in 10ms steps
+        for _ in 0..100 {
+            input.end_step(0.01);
+            if input.pressed_repeat(Key::W) {
+                repeats += 1;
+            }
+        }
+        // repeats at 0.35, 0.45, ..., 0.95
+        assert_eq!(repeats, 7);
+    }
+
+    #[test]
+    fn input_state_test_pressed_within() {
+        let mut input = InputState::new();
+        assert!(!input.pressed_within(Key::Space, 0.1));
+        input.key_down(Key::Space);
+        input.key_up(Key::Space);
+        for _ in 0..8 {
+            input.end_step(0.01);
+        }
+        assert!(input.pressed_within(Key::Space, 0.1));
+        for _ in 0..4 {
+            input.end_step(0.01);
+        }
+        assert!(!input.pressed_within(Key::Space, 0.1));
+        assert!(!input.pressed_within(Key::Unknown, 0.1));
+    }
+
+    #[test]
+    fn input_state_test_double_tap() {
+        let mut input = InputState::new();
+        input.key_down(Key::Left);
+        assert!(!input.double_tapped(Key::Left, 0.2));
+        input.key_up(Key::Left);
+        input.end_step(0.1);
+        input.key_down(Key::Left);
+        assert!(input.double_tapped(Key::Left, 0.2));
+        input.end_step(0.01);
+        assert!(!input.double_tapped(Key::Left, 0.2), "A held key only counts as tapped in the step it was pressed.");
+        input.key_up(Key::Left);
+        input.end_step(0.3);
+        input.key_down(Key::Left);
+        assert!(!input.double_tapped(Key::Left, 0.2));
+    }
+
+    #[test]
+    fn input_state_test_mouse_and_gamepad() {
+        let mut input = InputState::new();
+        input.cursor_moved_to(10.0, 20.0);
+        input.mouse_down(MouseButton::Button1);
+        input.set_gamepad_button(GamepadButton::ButtonA, true);
+        assert!(input.cursor_moved());
+        assert!(input.mouse_just_pressed(MouseButton::Button1));
+        assert!(input.gamepad_just_pressed(GamepadButton::ButtonA));
+        input.end_step(0.01);
+        // still held, but no new presses
+        input.set_gamepad_button(GamepadButton::ButtonA, true);
+        assert!(!input.cursor_moved());
+        assert!(!input.mouse_just_pressed(MouseButton::Button1));
+        assert!(!input.gamepad_just_pressed(GamepadButton::ButtonA));
+        assert_eq!(input.cursor(), glm::vec2(10.0, 20.0));
+    }
+}
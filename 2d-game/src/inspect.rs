@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::fmt;
+
+/// Value of an inspectable property.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    Vec2(glm::TVec2<f32>),
+    Vec3(glm::TVec3<f32>)
+}
+
+// Types implement Inspect to expose their tweakable fields to the inspector by name.
+// Values passed to set_property always have the type property returned for the name.
+pub trait Inspect {
+    // names of the properties, in the order they are listed
+    fn property_names(&self) -> Vec<&'static str>;
+
+    fn property(&self, name: &str) -> Option<Value>;
+
+    fn set_property(&mut self, name: &str, value: Value);
+}
+
+fn parse_float(text: &str) -> Result<f32, String> {
+    text.trim().parse::<f32>().map_err(|_| format!("\"{}\" isn't a number", text.trim()))
+}
+
+impl Value {
+    // parses text into a value of the same type as this one
+    pub fn parse_as(&self, text: &str) -> Result<Value, String> {
+        let text = text.trim();
+        let floats = |count: usize| -> Result<Vec<f32>, String> {
+            let values = text.trim_matches(|c| c == '(' || c == ')')
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|part| !part.is_empty())
+                .map(parse_float)
+                .collect::<Result<Vec<f32>, String>>()?;
+            if values.len() != count {
+                return Err(format!("expected {} numbers", count));
+            }
+            Ok(values)
+        };
+        match self {
+            Value::Bool(_) => match text {
+                "true" | "on" | "1" => Ok(Value::Bool(true)),
+                "false" | "off" | "0" => Ok(Value::Bool(false)),
+                _ => Err(format!("\"{}\" isn't true or false", text))
+            },
+            Value::Int(_) => text.parse::<i64>().map(Value::Int).map_err(|_| format!("\"{}\" isn't an integer", text)),
+            Value::Float(_) => parse_float(text).map(Value::Float),
+            Value::Vec2(_) => floats(2).map(|v| Value::Vec2(glm::vec2(v[0], v[1]))),
+            Value::Vec3(_) => floats(3).map(|v| Value::Vec3(glm::vec3(v[0], v[1], v[2])))
+        }
+    }
+
+    // index of a named vector component (x/y/z or r/g/b)
+    fn component_index(&self, name: &str) -> Option<usize> {
+        let count = match self {
+            Value::Vec2(_) => 2,
+            Value::Vec3(_) => 3,
+            _ => 0
+        };
+        let index = match name {
+            "x" | "r" => 0,
+            "y" | "g" => 1,
+            "z" | "b" => 2,
+            _ => return None
+        };
+        if index < count { Some(index) } else { None }
+    }
+
+    fn component(&self, index: usize) -> f32 {
+        match self {
+            Value::Vec2(v) => v[index],
+            Value::Vec3(v) => v[index],
+            _ => 0.0
+        }
+    }
+
+    fn with_component(&self, index: usize, component: f32) -> Value {
+        match *self {
+            Value::Vec2(mut v) => {
+                v[index] = component;
+                Value::Vec2(v)
+            }
+            Value::Vec3(mut v) => {
+                v[index] = component;
+                Value::Vec3(v)
+            }
+            value => value
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Vec2(v) => write!(f, "({}, {})", v.x, v.y),
+            Value::Vec3(v) => write!(f, "({}, {}, {})", v.x, v.y, v.z)
+        }
+    }
+}
+
+// splits "velocity.x" into the property and the component
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    match path.split_once('.') {
+        Some((property, component)) => (property, Some(component)),
+        None => (path, None)
+    }
+}
+
+// reads a property or a component of it ("velocity" or "velocity.x")
+pub fn get(target: &dyn Inspect, path: &str) -> Result<Value, String> {
+    let (name, component) = split_path(path);
+    let value = target.property(name).ok_or_else(|| format!("no property \"{}\"", name))?;
+    match component {
+        None => Ok(value),
+        Some(component) => {
+            let index = value.component_index(component).ok_or_else(|| format!("{} has no component \"{}\"", name, component))?;
+            Ok(Value::Float(value.component(index)))
+        }
+    }
+}
+
+// writes a property or a component of it from text, returning the new value of the property
+pub fn set(target: &mut dyn Inspect, path: &str, text: &str) -> Result<Value, String> {
+    let (name, component) = split_path(path);
+    let current = target.property(name).ok_or_else(|| format!("no property \"{}\"", name))?;
+    let value = match component {
+        None => current.parse_as(text)?,
+        Some(component) => {
+            let index = current.component_index(component).ok_or_else(|| format!("{} has no component \"{}\"", name, component))?;
+            current.with_component(index, parse_float(text)?)
+        }
+    };
+    target.set_property(name, value);
+    Ok(value)
+}
+
+// all properties and their values, e.g. "position=(10, 20) rotation=0"
+pub fn describe(target: &dyn Inspect) -> String {
+    target.property_names().iter()
+        .filter_map(|name| target.property(name).map(|value| format!("{}={}", name, value)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// A command of the inspector: "ball.velocity.x" reads, "ball.velocity.x = 300" writes.
+#[derive(Debug, PartialEq)]
+pub enum Command<'a> {
+    Get(&'a str),
+    Set(&'a str, &'a str)
+}
+
+pub fn parse_command(line: &str) -> Result<Command<'_>, String> {
+    let (path, value) = match line.split_once('=') {
+        Some((path, value)) => (path.trim(), Some(value.trim())),
+        None => (line.trim(), None)
+    };
+    if path.is_empty() || path.contains(char::is_whitespace) {
+        return Err(format!("invalid path \"{}\"", path));
+    }
+    match value {
+        Some("") => Err("missing value".to_string()),
+        Some(value) => Ok(Command::Set(path, value)),
+        None => Ok(Command::Get(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Target {
+        position: glm::TVec2<f32>,
+        solid: bool
+    }
+
+    impl Inspect for Target {
+        fn property_names(&self) -> Vec<&'static str> {
+            vec!["position", "solid"]
+        }
+
+        fn property(&self, name: &str) -> Option<Value> {
+            match name {
+                "position" => Some(Value::Vec2(self.position)),
+                "solid" => Some(Value::Bool(self.solid)),
+                _ => None
+            }
+        }
+
+        fn set_property(&mut self, name: &str, value: Value) {
+            match (name, value) {
+                ("position", Value::Vec2(v)) => self.position = v,
+                ("solid", Value::Bool(v)) => self.solid = v,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn inspect_test_get_set() {
+        let mut target = Target { position: glm::vec2(1.0, 2.0), solid: false };
+        assert_eq!(get(&target, "position.y"), Ok(Value::Float(2.0)));
+        assert_eq!(set(&mut target, "position.x", "300"), Ok(Value::Vec2(glm::vec2(300.0, 2.0))));
+        assert_eq!(set(&mut target, "position", "(5, 6)"), Ok(Value::Vec2(glm::vec2(5.0, 6.0))));
+        assert_eq!(set(&mut target, "solid", "on"), Ok(Value::Bool(true)));
+        assert!(target.solid);
+        assert_eq!(describe(&target), "position=(5, 6) solid=true");
+    }
+
+    #[test]
+    fn inspect_test_errors() {
+        let mut target = Target { position: glm::vec2(1.0, 2.0), solid: false };
+        assert!(get(&target, "velocity").is_err());
+        assert!(get(&target, "position.z").is_err());
+        assert!(set(&mut target, "position", "1").is_err());
+        assert!(set(&mut target, "solid", "maybe").is_err());
+        assert_eq!(target.position, glm::vec2(1.0, 2.0));
+    }
+
+    #[test]
+    fn inspect_test_parse_command() {
+        assert_eq!(parse_command("ball.velocity.x = 300"), Ok(Command::Set("ball.velocity.x", "300")));
+        assert_eq!(parse_command(" ball.position "), Ok(Command::Get("ball.position")));
+        assert!(parse_command("ball.velocity =").is_err());
+        assert!(parse_command("= 3").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[macro_use]
+extern crate rust_i18n;
+
+// The game as a library: main.rs runs it in a window, the benchmarks in benches/ use it too.
+
+pub mod game;
+pub mod game_level;
+pub mod game_object;
+pub mod texture;
+pub mod power_up;
+pub mod sprite_renderer;
+pub mod sprite_batch;
+pub mod affine;
+pub mod shader;
+pub mod resource_manager;
+pub mod res_name;
+pub mod ball_object;
+pub mod particle_generator;
+pub mod post_processor;
+pub mod render_graph;
+pub mod render_queue;
+pub mod minimap;
+pub mod text_renderer;
+pub mod sound_engine;
+pub mod tiled;
+pub mod editor;
+pub mod timestep;
+pub mod rewind;
+pub mod simulation;
+pub mod replay;
+pub mod visual_test;
+pub mod bot;
+pub mod clip;
+pub mod tween;
+pub mod input;
+pub mod bindings;
+pub mod settings;
+pub mod patterns;
+pub mod collision;
+pub mod walls;
+pub mod difficulty;
+pub mod dash;
+pub mod status;
+pub mod drops;
+pub mod brick_type;
+pub mod speedrun;
+pub mod tasks;
+pub mod tutorial;
+pub mod color_grading;
+pub mod ambient;
+pub mod weather;
+pub mod credits;
+pub mod controller;
+pub mod daily;
+pub mod display;
+pub mod photo;
+pub mod post_effects;
+pub mod score;
+pub mod popups;
+pub mod logging;
+pub mod crash;
+pub mod gl_debug;
+pub mod gl_api;
+pub mod gpu_caps;
+pub mod frame_capture;
+pub mod frame_log;
+pub mod frame_arena;
+pub mod fallback;
+pub mod builtin;
+pub mod shader_cache;
+pub mod stream_buffer;
+pub mod material;
+pub mod nine_slice;
+pub mod ui;
+pub mod viewport;
+pub mod inspect;
+#[cfg(feature = "discord")]
+pub mod presence;
+#[cfg(feature = "devtools")]
+pub mod devtools;
+#[cfg(feature = "devtools")]
+pub mod debug_window;
+
+i18n!("locales");
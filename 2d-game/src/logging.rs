@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+// Logging goes through tracing (the records of the `log` macros used across the game are
+// forwarded to it): one layer prints them, another keeps the most recent ones in memory, so
+// they can be put into crash reports and shown in the debug overlay. What gets printed is
+// filtered per module through RUST_LOG, e.g. `RUST_LOG=warn,gl=error` (GL debug output is
+// logged with the target "gl").
+
+// number of records kept in memory
+pub const RECENT_CAPACITY: usize = 100;
+// records at least this severe are kept, regardless of the RUST_LOG filter
+const RECENT_LEVEL: LevelFilter = LevelFilter::INFO;
+
+static RECENT: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+/// A log record kept in memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogEntry {
+    pub level: Level,
+    pub target: String,
+    pub message: String
+}
+
+impl LogEntry {
+    pub fn format(&self) -> String {
+        format!("[{} {}] {}", self.level, self.target, self.message)
+    }
+}
+
+// keeps the events it sees in the ring buffer
+struct RecentLayer;
+
+impl<S: Subscriber> Layer<S> for RecentLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // the metadata of a forwarded `log` record has its actual target
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+        push_recent(LogEntry {
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: message.0
+        });
+    }
+}
+
+// the message of an event, with its other fields after it
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.0.insert_str(0, format!("{:?}", value).as_str()),
+            // left by the forwarding of `log` records
+            name if name.starts_with("log.") => {}
+            name => self.0.push_str(format!(" {}={:?}", name, value).as_str())
+        }
+    }
+}
+
+fn push_recent(entry: LogEntry) {
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    if recent.len() == RECENT_CAPACITY {
+        recent.pop_front();
+    }
+    recent.push_back(entry);
+}
+
+// installs the subscriber (and forwards `log` records to it); the RUST_LOG environment
+// variable filters what's printed
+pub fn init() {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(RecentLayer.with_filter(RECENT_LEVEL))
+        .try_init()
+        .expect("ERROR::LOGGING: Failed to install the subscriber");
+}
+
+// the most recent log records, oldest first
+pub fn recent_lines() -> Vec<String> {
+    RECENT.lock().unwrap_or_else(|e| e.into_inner()).iter().map(|entry| entry.format()).collect()
+}
+
+// the last `count` records at least as severe as the given level, oldest first
+pub fn recent_entries(level: Level, count: usize) -> Vec<LogEntry> {
+    let recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries: Vec<_> = recent.iter().rev().filter(|entry| entry.level <= level).take(count).cloned().collect();
+    entries.reverse();
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: Level, message: String) -> LogEntry {
+        LogEntry { level, target: "test".to_string(), message }
+    }
+
+    // both cases share the global buffer, so they run as one test
+    #[test]
+    fn push_recent_test_case() {
+        for i in 0..RECENT_CAPACITY + 5 {
+            let level = if i % 10 == 0 { Level::ERROR } else { Level::INFO };
+            push_recent(entry(level, format!("line {}", i)));
+        }
+        let lines = recent_lines();
+        assert_eq!(lines.len(), RECENT_CAPACITY);
+        assert_eq!(lines.last().unwrap(), &format!("[INFO test] line {}", RECENT_CAPACITY + 4));
+        assert_eq!(lines[0], "[INFO test] line 5");
+        let errors = recent_entries(Level::WARN, 2);
+        assert_eq!(errors, vec![entry(Level::ERROR, "line 90".to_string()), entry(Level::ERROR, "line 100".to_string())]);
+        // events are kept by the layer, those less severe than RECENT_LEVEL aren't
+        let subscriber = tracing_subscriber::registry().with(RecentLayer.with_filter(RECENT_LEVEL));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "gl", id = 7, "shader {} recompiled", "text");
+            tracing::debug!(target: "gl", "ignored");
+        });
+        let lines = recent_lines();
+        assert_eq!(lines.last().unwrap(), "[WARN gl] shader text recompiled id=7");
+    }
+}
@@ -14,35 +14,32 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-#[macro_use]
-extern crate rust_i18n;
-
 use std::ffi::{c_void, CStr};
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::time::{Duration, Instant};
 use gl::types::{GLchar, GLenum, GLsizei, GLuint};
-use glfw::{Action, Context, Glfw, Key, Modifiers, OpenGlProfileHint, Scancode, Window, WindowHint};
-use crate::game::Game;
-
-pub mod game;
-pub mod game_level;
-pub mod game_object;
-pub mod texture;
-pub mod power_up;
-pub mod sprite_renderer;
-pub mod shader;
-pub mod resource_manager;
-pub mod ball_object;
-pub mod particle_generator;
-pub mod post_processor;
-pub mod text_renderer;
-pub mod sound_engine;
-
-i18n!("locales");
+use glfw::{Action, Context, Glfw, Key, Modifiers, MouseButton, OpenGlProfileHint, Scancode, Window, WindowHint};
+#[cfg(feature = "devtools")]
+use glfw::WindowEvent;
+use learnopengl_2d_game::{bot, crash, daily, frame_arena, frame_log, gl_debug, gpu_caps, logging, replay, resource_manager, texture, visual_test};
+use learnopengl_2d_game::display::Display;
+use learnopengl_2d_game::game::Game;
+use learnopengl_2d_game::gpu_caps::GpuCaps;
+use learnopengl_2d_game::timestep::FixedTimestep;
 
 // The Width of the screen
 const SCREEN_WIDTH: u32 = 800;
 // The height of the screen
 const SCREEN_HEIGHT: u32 = 600;
+// Length of a single simulation step
+const FIXED_TIME_STEP: f32 = 1.0 / 120.0;
+// Longest frame time which gets simulated
+const MAX_FRAME_TIME: f32 = 0.25;
+// Maximum number of simulation steps per frame
+const MAX_UPDATES_PER_FRAME: u32 = 8;
+// Longest wait for events between two frames in menus (about 15 FPS)
+const IDLE_FRAME_TIME: f64 = 1.0 / 15.0;
 
 static mut GAME_OBJ_PTR: *mut Game = ptr::null_mut();
 
@@ -55,82 +52,114 @@ extern "system" fn gl_debug_output(
     message: *const GLchar,
     _user_param: *mut c_void
 ) {
-    if id == 131169 || id == 131185 || id == 131218 || id == 131204 { // ignore these non-significant error codes
-        return;
-    }
-
     let message_c_str;
     unsafe {
         message_c_str = CStr::from_ptr(message);
     }
-    let message_str = message_c_str.to_str().unwrap();
-    log::info!("Debug message ({}): {}", id, message_str);
-    match source {
-        gl::DEBUG_SOURCE_API => log::info!("Source: API"),
-        gl::DEBUG_SOURCE_WINDOW_SYSTEM => log::info!("Source: Window System"),
-        gl::DEBUG_SOURCE_SHADER_COMPILER => log::info!("Source: Shader Compiler"),
-        gl::DEBUG_SOURCE_THIRD_PARTY => log::info!("Source: Third Party"),
-        gl::DEBUG_SOURCE_APPLICATION => log::info!("Source: Application"),
-        gl::DEBUG_SOURCE_OTHER => log::info!("Source: Other"),
-        _ => {}
-    }
-    match gltype {
-        gl::DEBUG_TYPE_ERROR => log::info!("Type: Error"),
-        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => log::info!("Type: Deprecated Behaviour"),
-        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => log::info!("Type: Undefined Behaviour"),
-        gl::DEBUG_TYPE_PORTABILITY => log::info!("Type: Portability"),
-        gl::DEBUG_TYPE_PERFORMANCE => log::info!("Type: Performance"),
-        gl::DEBUG_TYPE_MARKER => log::info!("Type: Marker"),
-        gl::DEBUG_TYPE_PUSH_GROUP => log::info!("Type: Push Group"),
-        gl::DEBUG_TYPE_POP_GROUP => log::info!("Type: Pop Group"),
-        gl::DEBUG_TYPE_OTHER => log::info!("Type: Other"),
-        _ => {}
-    }
-    match severity {
-        gl::DEBUG_SEVERITY_HIGH => log::info!("Severity: high"),
-        gl::DEBUG_SEVERITY_MEDIUM => log::info!("Severity: medium"),
-        gl::DEBUG_SEVERITY_LOW => log::info!("Severity: low"),
-        gl::DEBUG_SEVERITY_NOTIFICATION => log::info!("Severity: notification"),
-        _ => {}
-    }
-    log::info!("---------------");
+    gl_debug::handle_message(id, source, gltype, severity, message_c_str.to_string_lossy().as_ref());
 }
 
 fn main() {
-    env_logger::init();
+    logging::init();
+    crash::install_panic_hook();
+    let args: Vec<String> = std::env::args().collect();
+    // --replay <file> plays a replay back without opening a window and checks the state it ends in
+    if let Some(file) = args.iter().position(|it| it == "--replay").and_then(|i| args.get(i + 1)) {
+        let root = std::env::current_dir().expect("Failed to obtain current directory.");
+        if let Err(e) = replay::check(Path::new(file), &root) {
+            log::error!("REPLAY: {}: {}", file, e);
+            std::process::exit(1);
+        }
+        log::info!("REPLAY: {} ended in the recorded state", file);
+        return;
+    }
+
+    // --bot [level files...] lets the AI play the levels (all of the game's by default) without
+    // opening a window and reports which of them it could clear
+    if let Some(i) = args.iter().position(|it| it == "--bot") {
+        let root = std::env::current_dir().expect("Failed to obtain current directory.");
+        let levels: Vec<PathBuf> = args[i + 1..].iter().take_while(|it| !it.starts_with("--")).map(PathBuf::from).collect();
+        match bot::run(&levels, &root) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                log::error!("BOT: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // --visual-test <file> [--update] plays the replay of a visual test in the window and compares
+    // screenshots against the references (or records them)
+    let mut visual_test = None;
+    if let Some(file) = args.iter().position(|it| it == "--visual-test").and_then(|i| args.get(i + 1)) {
+        let root = std::env::current_dir().expect("Failed to obtain current directory.");
+        match visual_test::Run::load(Path::new(file), &root, args.iter().any(|it| it == "--update")) {
+            Ok(run) => visual_test = Some(run),
+            Err(e) => {
+                log::error!("VISUAL_TEST: {}: {}", file, e);
+                std::process::exit(1);
+            }
+        }
+    }
 
     // glfw: initialize and configure
     // ------------------------------
-    let mut glfw = glfw::init(glfw::fail_on_errors)
+    let mut glfw = glfw::init(glfw_error_callback)
         .expect("Failed to initialise GLFW.");
 
     glfw.window_hint(WindowHint::ContextVersionMajor(3));
     glfw.window_hint(WindowHint::ContextVersionMinor(3));
     glfw.window_hint(WindowHint::OpenGlProfile(OpenGlProfileHint::Core));
     glfw.window_hint(WindowHint::OpenGlForwardCompat(true));
-    glfw.window_hint(WindowHint::Resizable(false));
+    glfw.window_hint(WindowHint::Resizable(true));
+    // the window is enlarged on HiDPI monitors (where the platform measures windows in pixels)
+    glfw.window_hint(WindowHint::ScaleToMonitor(true));
 
     // glfw window creation
     // --------------------
-    let (mut window, _) = glfw.create_window(
+    let Some((mut window, _)) = glfw.create_window(
         SCREEN_WIDTH, SCREEN_HEIGHT,
-        "Breakout", glfw::WindowMode::Windowed)
-        .expect("Failed to create GLFW window.");
+        "Breakout", glfw::WindowMode::Windowed) else {
+        gpu_caps::report_unsupported_context(&mut glfw);
+    };
 
     // load all OpenGL function pointers
     // ---------------------------------
     gl::load_with(|s| window.get_proc_address(s) as *const _);
+    texture::mark_gl_thread();
+    // check what the GPU offers before anything relies on it
+    let caps = GpuCaps::query();
+    crash::set_gl_info(caps.summary());
+    if !caps.problems().is_empty() {
+        gpu_caps::show_problems(&mut glfw, &mut window, &caps, SCREEN_WIDTH, SCREEN_HEIGHT);
+    }
+    let debug_output = caps.has_debug_output();
+    gpu_caps::set(caps);
 
     window.set_key_callback(key_callback);
+    window.set_cursor_pos_callback(cursor_pos_callback);
+    window.set_mouse_button_callback(mouse_button_callback);
+    // the inspector also takes typed text and the mouse wheel
+    #[cfg(feature = "devtools")]
+    window.set_char_callback(|_, c| game_obj_mut().devtools_event(&WindowEvent::Char(c)));
+    #[cfg(feature = "devtools")]
+    window.set_scroll_callback(|_, x, y| game_obj_mut().devtools_event(&WindowEvent::Scroll(x, y)));
+    window.set_focus_callback(focus_callback);
+    window.set_iconify_callback(iconify_callback);
     window.set_framebuffer_size_callback(framebuffer_size_callback);
+    window.set_content_scale_callback(content_scale_callback);
+    window.set_refresh_callback(refresh_callback);
 
     // OpenGL configuration
     // --------------------
     unsafe {
-        gl::Enable(gl::DEBUG_OUTPUT);
-        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS); // makes sure errors are displayed synchronously
-        gl::DebugMessageCallback(Some(gl_debug_output), ptr::null());
-        gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, 0, ptr::null(), gl::TRUE);
+        if debug_output {
+            gl::Enable(gl::DEBUG_OUTPUT);
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS); // makes sure errors are displayed synchronously
+            gl::DebugMessageCallback(Some(gl_debug_output), ptr::null());
+            gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, 0, ptr::null(), gl::TRUE);
+        }
 
         gl::Viewport(0, 0, SCREEN_WIDTH as _, SCREEN_HEIGHT as _);
         gl::Enable(gl::BLEND);
@@ -140,44 +169,161 @@ fn main() {
     // initialize game
     // ---------------
     init_game_obj(glfw.clone(), SCREEN_WIDTH, SCREEN_HEIGHT);
+    // --daily <code> plays the daily challenge of a shared seed code
+    if let Some(code) = args.iter().position(|it| it == "--daily").and_then(|i| args.get(i + 1)) {
+        match daily::parse_code(code) {
+            Some(seed) => game_obj_mut().daily_seed = Some(seed),
+            None => log::error!("DAILY: Invalid seed code {}", code)
+        }
+    }
     game_obj_mut().init();
+    // the framebuffer can be larger than the window (e.g. on retina displays)
+    let (framebuffer_width, framebuffer_height) = window.get_framebuffer_size();
+    game_obj_mut().resize(framebuffer_width, framebuffer_height);
+    game_obj_mut().set_content_scale(window.get_content_scale().0);
+    let mut display = Display::new(&window);
+    // --frame-log <file> records the time of every frame into a file (see frame_log)
+    let frame_log_file = args.iter().position(|it| it == "--frame-log").and_then(|i| args.get(i + 1)).map(PathBuf::from);
+    if frame_log_file.is_some() {
+        frame_log::start();
+    }
+    // --recreate-gpu-objects recreates all GPU objects after switching the display mode, for
+    // drivers which lose them then
+    let recreate_on_switch = args.iter().any(|it| it == "--recreate-gpu-objects");
+    if let Some(run) = visual_test.as_ref() {
+        if let Err(e) = game_obj_mut().start_replay(&run.trace) {
+            log::error!("VISUAL_TEST: {}", e);
+            std::process::exit(1);
+        }
+    }
 
     // deltaTime variables
     // -------------------
     let mut last_frame = 0f32;
+    let mut timestep = FixedTimestep::new(FIXED_TIME_STEP, MAX_FRAME_TIME, MAX_UPDATES_PER_FRAME);
 
     while !window.should_close() {
         // calculate delta time
         // --------------------
         let current_frame = glfw.get_time() as f32;
-        let delta_time = current_frame - last_frame;
+        let mut delta_time = current_frame - last_frame;
         last_frame = current_frame;
-        glfw.poll_events();
-
-        // manage user input
-        // -----------------
-        game_obj_mut().process_input(delta_time);
-
-        // update game state
-        // -----------------
-        game_obj_mut().update(delta_time);
+        // menus and the pause screen wait for events instead of rendering as fast as possible
+        if visual_test.is_none() && game_obj().is_idle() {
+            glfw.wait_events_timeout(IDLE_FRAME_TIME);
+        } else {
+            glfw.poll_events();
+        }
+        game_obj_mut().poll_gamepad();
+        #[cfg(feature = "devtools")]
+        game_obj_mut().poll_devtools(&mut window);
+        if game_obj().reset_frame_time {
+            // the window was restored after a stall; continue as if no time had passed
+            game_obj_mut().reset_frame_time = false;
+            last_frame = glfw.get_time() as f32;
+            delta_time = 0.0;
+            timestep.reset();
+        }
+        if visual_test.is_none() {
+            game_obj_mut().update_fps(delta_time);
+        }
+        // follows the display mode of the settings
+        if display.apply(&mut glfw, &mut window, game_obj().settings.display_mode) && recreate_on_switch {
+            game_obj_mut().recreate_gpu_objects();
+        }
+        #[cfg(feature = "discord")]
+        game_obj_mut().update_presence();
+
+        // advance the simulation in fixed steps
+        // -------------------------------------
+        // (a visual test plays a step of its replay each frame, at the time of the replay)
+        // the game speed setting slows down the world, but not the menus or the input
+        timestep.speed = game_obj().settings.game_speed;
+        let (steps, step, world_step) = match visual_test.as_mut() {
+            Some(run) => {
+                run.step += 1;
+                glfw.set_time(run.time());
+                (1, run.dt(), run.dt())
+            }
+            None => (timestep.advance(delta_time), timestep.step, timestep.world_step())
+        };
+        let update_start = Instant::now();
+        for _ in 0..steps {
+            // manage user input
+            game_obj_mut().process_input(step, world_step);
+            // update game state
+            game_obj_mut().update(step, world_step);
+            game_obj_mut().input.end_step(step);
+        }
+        let update_time = update_start.elapsed();
 
         // render
         // ------
-        unsafe {
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+        // (idle frames where nothing changed keep showing the last one)
+        let redraw = game_obj_mut().take_redraw() || visual_test.is_some();
+        let mut render_time = Duration::ZERO;
+        if redraw {
+            let render_start = Instant::now();
+            unsafe {
+                gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+            game_obj().render();
+            #[cfg(feature = "devtools")]
+            game_obj_mut().render_devtools();
+            render_time = render_start.elapsed();
+        }
+        crash::set_state_summary(game_obj().state_summary());
+        if let Some(run) = visual_test.as_mut() {
+            if run.is_capture_due() {
+                let (width, height) = window.get_framebuffer_size();
+                run.capture(width as u32, height as u32, visual_test::read_frame(width as u32, height as u32));
+            }
+            if run.is_finished() {
+                window.set_should_close(true);
+            }
+        }
+
+        if redraw {
+            window.swap_buffers();
         }
-        game_obj().render();
+        #[cfg(feature = "devtools")]
+        game_obj_mut().render_debug_window(&mut window);
+        resource_manager::end_frame();
+        texture::delete_pending();
+        frame_arena::reset();
+        frame_log::record(current_frame as f64, delta_time, update_time, render_time);
+    }
 
-        window.swap_buffers();
+    if let Some(file) = frame_log_file.as_ref() {
+        frame_log::finish(file);
     }
 
+    #[cfg(feature = "devtools")]
+    game_obj_mut().close_devtools(&mut window);
+
     // delete all resources as loaded using the resource manager
     // ---------------------------------------------------------
     resource_manager::clear();
 
     drop_game_obj();
+    texture::delete_pending();
+    texture::report_leaks();
+
+    if let Some(run) = visual_test {
+        for failure in run.failures.iter() {
+            log::error!("VISUAL_TEST: {}", failure);
+        }
+        if !run.failures.is_empty() {
+            std::process::exit(1);
+        }
+        log::info!("VISUAL_TEST: {} screenshots checked", run.test.captures.len());
+    }
+}
+
+// GLFW errors are logged; failures it reports are handled where they surface (e.g. no window)
+fn glfw_error_callback(error: glfw::Error, description: String) {
+    log::error!("GLFW: {} ({:?})", description, error);
 }
 
 fn init_game_obj(glfw: Glfw, width: u32, height: u32) {
@@ -207,32 +353,99 @@ pub fn game_obj_mut() -> &'static mut Game {
 fn key_callback(
     window: &mut Window,
     key: Key,
-    _: Scancode,
+    scancode: Scancode,
     action: Action,
-    _: Modifiers
+    modifiers: Modifiers
 ) {
+    #[cfg(feature = "devtools")]
+    game_obj_mut().devtools_event(&WindowEvent::Key(key, scancode, action, modifiers));
+    #[cfg(not(feature = "devtools"))]
+    let _ = (scancode, modifiers);
     // when a user presses the escape key, we set the WindowShouldClose property to true, closing the application
-    if key == Key::Escape && action == Action::Press {
+    // (other screens use escape to go back instead)
+    if key == Key::Escape && action == Action::Press && game_obj().escape_quits() {
         window.set_should_close(true);
     }
-    if (0..1024).contains(&(key as i32)) {
+    // the options screen waits for a key to bind
+    if game_obj().is_capturing() {
         if action == Action::Press {
-            game_obj_mut().keys[key as usize] = true;
-        } else if action == Action::Release {
-            game_obj_mut().keys[key as usize] = false;
-            game_obj_mut().keys_processed[key as usize] = false;
+            game_obj_mut().capture_key(key);
         }
+        return;
+    }
+    if action == Action::Press {
+        game_obj_mut().input.key_down(key);
+    } else if action == Action::Release {
+        game_obj_mut().input.key_up(key);
+    }
+}
+
+fn cursor_pos_callback(
+    window: &mut Window,
+    x: f64,
+    y: f64
+) {
+    #[cfg(feature = "devtools")]
+    game_obj_mut().devtools_event(&WindowEvent::CursorPos(x, y));
+    // the cursor is in screen coordinates, which are smaller than framebuffer pixels on retina displays
+    let (window_width, _) = window.get_size();
+    let (framebuffer_width, _) = window.get_framebuffer_size();
+    let ratio = framebuffer_width as f32 / window_width.max(1) as f32;
+    let (x, y) = game_obj().to_game(x as f32 * ratio, y as f32 * ratio);
+    game_obj_mut().input.cursor_moved_to(x, y);
+}
+
+fn mouse_button_callback(
+    _: &mut Window,
+    button: MouseButton,
+    action: Action,
+    modifiers: Modifiers
+) {
+    #[cfg(feature = "devtools")]
+    game_obj_mut().devtools_event(&WindowEvent::MouseButton(button, action, modifiers));
+    #[cfg(not(feature = "devtools"))]
+    let _ = modifiers;
+    if action == Action::Press {
+        game_obj_mut().input.mouse_down(button);
+    } else if action == Action::Release {
+        game_obj_mut().input.mouse_up(button);
     }
 }
 
+fn focus_callback(
+    _: &mut Window,
+    focused: bool
+) {
+    game_obj_mut().on_focus_changed(focused);
+}
+
+fn iconify_callback(
+    _: &mut Window,
+    iconified: bool
+) {
+    game_obj_mut().on_iconified(iconified);
+}
+
+fn content_scale_callback(
+    _: &mut Window,
+    x_scale: f32,
+    _: f32
+) {
+    // the window was moved to a monitor with another content scale
+    game_obj_mut().set_content_scale(x_scale);
+}
+
+fn refresh_callback(_: &mut Window) {
+    // the window was uncovered or its contents got lost
+    game_obj_mut().request_redraw();
+}
+
 fn framebuffer_size_callback(
     _: &mut Window,
     width: i32,
     height: i32
 ) {
-    unsafe {
-        // make sure the viewport matches the new window dimensions; note that width and
-        // height will be significantly larger than specified on retina displays
-        gl::Viewport(0, 0, width, height);
-    }
+    // make sure the viewport matches the new window dimensions; note that width and
+    // height will be significantly larger than specified on retina displays
+    game_obj_mut().resize(width, height);
 }
\ No newline at end of file
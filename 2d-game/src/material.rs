@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::shader::Shader;
+use crate::texture::Texture2D;
+
+/// Value of a uniform set by a material.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UniformValue {
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4])
+}
+
+// A Material combines a shader with the extra textures and the uniform values it is
+// drawn with. Material shaders share the sprite shader's interface (model, projection,
+// spriteColor and the sprite texture on unit 0, optionally the corner colors in Tint);
+// extra textures use the units after it.
+#[derive(Clone)]
+pub struct Material {
+    pub shader: Shader,
+    // sampler name and texture of the extra texture slots
+    pub textures: Vec<(String, Texture2D)>,
+    pub uniforms: Vec<(String, UniformValue)>
+}
+
+impl Material {
+    // constructor
+    pub fn new(shader: Shader) -> Self {
+        Self {
+            shader,
+            textures: Vec::new(),
+            uniforms: Vec::new()
+        }
+    }
+
+    pub fn with_texture(mut self, sampler: &str, texture: Texture2D) -> Self {
+        self.textures.push((sampler.to_string(), texture));
+        self
+    }
+
+    pub fn with_uniform(mut self, name: &str, value: UniformValue) -> Self {
+        self.set_uniform(name, value);
+        self
+    }
+
+    // sets the value of a uniform, replacing its previous value
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        match self.uniforms.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.uniforms.push((name.to_string(), value))
+        }
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<UniformValue> {
+        self.uniforms.iter().find(|(n, _)| n == name).map(|(_, v)| *v)
+    }
+
+    // activates the shader, uploads the uniform values and binds the extra textures
+    pub fn apply(&self) -> &Shader {
+        self.shader.use_shader();
+        for (name, value) in self.uniforms.iter() {
+            match *value {
+                UniformValue::Int(v) => self.shader.set_integer(name, v),
+                UniformValue::Float(v) => self.shader.set_float(name, v),
+                UniformValue::Vec2([x, y]) => self.shader.set_vector2f_vals(name, x, y),
+                UniformValue::Vec3([x, y, z]) => self.shader.set_vector3f_vals(name, x, y, z),
+                UniformValue::Vec4([x, y, z, w]) => self.shader.set_vector4f_vals(name, x, y, z, w)
+            }
+        }
+        for (i, (sampler, texture)) in self.textures.iter().enumerate() {
+            let unit = i as u32 + 1;
+            self.shader.set_integer(sampler, unit as i32);
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0 + unit);
+            }
+            texture.bind();
+        }
+        &self.shader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn material_test_uniforms() {
+        let mut material = Material::new(Shader::new())
+            .with_uniform("opacity", UniformValue::Float(0.5))
+            .with_uniform("tint", UniformValue::Vec3([1.0, 0.0, 0.0]));
+        material.set_uniform("opacity", UniformValue::Float(0.75));
+        assert_eq!(material.uniforms.len(), 2);
+        assert_eq!(material.uniform("opacity"), Some(UniformValue::Float(0.75)));
+        assert_eq!(material.uniform("missing"), None);
+    }
+}
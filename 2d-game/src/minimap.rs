@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use crate::game_object::GameObject;
+use crate::resource_manager;
+use crate::res_name::res_name;
+use crate::render_graph::{Format, RenderGraph, TargetDesc};
+use crate::sprite_renderer::{SpriteRenderer, SpriteTransform};
+use crate::texture::Texture2D;
+
+// The minimap is a small picture of the remaining bricks in a corner of the HUD. The bricks
+// are rendered into a texture of their own, which is only rendered again when they change
+// (a brick is destroyed or the level changes); the ball is drawn over it every frame.
+
+// size of the minimap texture relative to the game world
+const SCALE: f32 = 0.2;
+// color of the texture where there are no bricks
+const BACKDROP: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+// blinks of the ball per second
+const BLINK_RATE: f32 = 2.0;
+// side of the ball's dot on the minimap, in pixels
+const DOT_SIZE: f32 = 4.0;
+
+// identifies what the minimap shows of the given bricks, so it changes whenever they look different
+pub fn bricks_key(bricks: &[GameObject]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (i, brick) in bricks.iter().enumerate().filter(|(_, it)| !it.destroyed) {
+        i.hash(&mut hasher);
+        for value in [brick.position.x, brick.position.y, brick.size.x, brick.size.y, brick.color.x, brick.color.y, brick.color.z] {
+            value.to_bits().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+// whether the blinking dot of the ball is shown at the given time
+pub fn is_dot_shown(time: f32) -> bool {
+    (time * BLINK_RATE).fract() < 0.5
+}
+
+/// A minimap of the bricks of a level.
+pub struct Minimap {
+    // size of the game world it shows
+    world: glm::TVec2<f32>,
+    graph: RenderGraph,
+    // key of the bricks last rendered into the texture
+    rendered: Cell<Option<u64>>
+}
+
+impl Minimap {
+    // constructor, allocating the texture for a game world of the given size
+    pub fn new(width: u32, height: u32) -> Self {
+        let target = TargetDesc { name: "minimap", format: Format::Rgba8, samples: 0, history: false, stencil: false };
+        let (texture_width, texture_height) = ((width as f32 * SCALE).round() as u32, (height as f32 * SCALE).round() as u32);
+        Self {
+            world: glm::vec2(width as f32, height as f32),
+            graph: RenderGraph::new(vec![target], Vec::new(), texture_width.max(1), texture_height.max(1), "minimap").unwrap(),
+            rendered: Cell::new(None)
+        }
+    }
+
+    // size of the minimap on screen at the given UI scale
+    pub fn size(&self, ui_scale: f32) -> glm::TVec2<f32> {
+        glm::vec2(self.graph.width as f32, self.graph.height as f32) * ui_scale
+    }
+
+    // renders the bricks into the texture, unless it already shows them
+    fn render(&self, renderer: &SpriteRenderer, bricks: &[GameObject]) {
+        let key = bricks_key(bricks);
+        if self.rendered.get() == Some(key) {
+            return;
+        }
+        self.rendered.set(Some(key));
+        // the sprite projection spans the game world, which the viewport shrinks to the texture
+        self.graph.begin_target("minimap");
+        unsafe {
+            gl::ClearColor(BACKDROP[0], BACKDROP[1], BACKDROP[2], BACKDROP[3]);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        }
+        for brick in bricks.iter().filter(|it| !it.destroyed) {
+            renderer.draw_sprite_ex2(&brick.sprite, brick.position, brick.size, brick.rotation, brick.color);
+        }
+        self.graph.end_target();
+    }
+
+    // draws the minimap at the given position, with the ball (if any) blinking on it
+    pub fn draw(
+        &self,
+        renderer: &SpriteRenderer,
+        bricks: &[GameObject],
+        ball: Option<glm::TVec2<f32>>,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        time: f32
+    ) {
+        self.render(renderer, bricks);
+        let texture = Texture2D::borrowed(self.graph.texture("minimap"), self.graph.width, self.graph.height);
+        // the rows of the texture start at the bottom
+        let transform = SpriteTransform { flip_y: true, ..SpriteTransform::default() };
+        renderer.draw_sprite_ex4(&texture, position, size, 0.0, glm::vec3(1.0, 1.0, 1.0), &transform);
+        if let Some(ball) = ball.filter(|_| is_dot_shown(time)) {
+            let center = position + ball.component_div(&self.world).component_mul(&size);
+            let dot_size = glm::vec2(DOT_SIZE, DOT_SIZE);
+            renderer.draw_sprite_ex2(&resource_manager::get_texture(res_name!("white")), center - dot_size / 2.0, dot_size, 0.0, glm::vec3(1.0, 1.0, 1.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimap_test_bricks_key() {
+        let brick = |x: f32| GameObject::new_ex0(glm::vec2(x, 0.0), glm::vec2(10.0, 5.0), Texture2D::empty());
+        let mut bricks = vec![brick(0.0), brick(10.0), brick(20.0)];
+        let key = bricks_key(&bricks);
+        assert_eq!(bricks_key(&bricks.clone()), key);
+        bricks[1].destroyed = true;
+        let destroyed = bricks_key(&bricks);
+        assert_ne!(destroyed, key, "Destroying a brick changes the minimap.");
+        bricks[2].position.x = 30.0;
+        assert_ne!(bricks_key(&bricks), destroyed);
+        assert!(is_dot_shown(0.0) && !is_dot_shown(0.3) && is_dot_shown(0.5));
+    }
+}
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::collections::HashMap;
+use std::fs;
+use serde::Deserialize;
+
+/// Margins (in texture pixels) splitting a texture into nine parts. The corners keep
+/// their size, the edges stretch along one axis and the center stretches along both.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct NineSlice {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32
+}
+
+// one of the nine quads of a sliced sprite
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SlicePiece {
+    pub position: glm::TVec2<f32>,
+    pub size: glm::TVec2<f32>,
+    // region of the texture the piece shows: <offset, size> in texture coordinates
+    pub tex_rect: glm::TVec4<f32>
+}
+
+impl NineSlice {
+    pub fn new(left: f32, right: f32, top: f32, bottom: f32) -> Self {
+        Self { left, right, top, bottom }
+    }
+
+    // splits a sprite of the given size into its nine pieces, row by row from the top left
+    pub fn pieces(
+        &self,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        texture_size: glm::TVec2<f32>
+    ) -> [SlicePiece; 9] {
+        // shrink the borders of sprites smaller than both margins together
+        let scale_x = if self.left + self.right > size.x { size.x / (self.left + self.right) } else { 1.0 };
+        let scale_y = if self.top + self.bottom > size.y { size.y / (self.top + self.bottom) } else { 1.0 };
+        let xs = [0.0, self.left * scale_x, size.x - self.right * scale_x, size.x];
+        let ys = [0.0, self.top * scale_y, size.y - self.bottom * scale_y, size.y];
+        let us = [0.0, self.left / texture_size.x, 1.0 - self.right / texture_size.x, 1.0];
+        let vs = [0.0, self.top / texture_size.y, 1.0 - self.bottom / texture_size.y, 1.0];
+
+        std::array::from_fn(|i| {
+            let (col, row) = (i % 3, i / 3);
+            SlicePiece {
+                position: position + glm::vec2(xs[col], ys[row]),
+                size: glm::vec2(xs[col + 1] - xs[col], ys[row + 1] - ys[row]),
+                tex_rect: glm::vec4(us[col], vs[row], us[col + 1] - us[col], vs[row + 1] - vs[row])
+            }
+        })
+    }
+}
+
+// parses a descriptor file mapping texture names to their margins
+pub fn parse_descriptors(source: &str) -> Result<HashMap<String, NineSlice>, String> {
+    let slices: HashMap<String, NineSlice> = toml::from_str(source).map_err(|e| e.to_string())?;
+    for (name, slice) in slices.iter() {
+        if slice.left < 0.0 || slice.right < 0.0 || slice.top < 0.0 || slice.bottom < 0.0 {
+            return Err(format!("negative margin for {}", name));
+        }
+    }
+    Ok(slices)
+}
+
+// loads a descriptor file, yielding no descriptors if it is missing or invalid
+pub fn load_descriptors(path: &str) -> HashMap<String, NineSlice> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            log::error!("NINE_SLICE: Failed to read {}: {}", path, e);
+            return HashMap::new();
+        }
+    };
+    parse_descriptors(source.as_str()).unwrap_or_else(|e| {
+        log::error!("NINE_SLICE: Failed to parse {}: {}", path, e);
+        HashMap::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nine_slice_test_pieces() {
+        let slice = NineSlice::new(8.0, 8.0, 4.0, 4.0);
+        let pieces = slice.pieces(glm::vec2(10.0, 20.0), glm::vec2(100.0, 50.0), glm::vec2(32.0, 16.0));
+        // corners keep their size
+        assert_eq!(pieces[0].position, glm::vec2(10.0, 20.0));
+        assert_eq!(pieces[0].size, glm::vec2(8.0, 4.0));
+        assert_eq!(pieces[8].position, glm::vec2(102.0, 66.0));
+        assert_eq!(pieces[8].size, glm::vec2(8.0, 4.0));
+        // the center stretches
+        assert_eq!(pieces[4].size, glm::vec2(84.0, 42.0));
+        assert_eq!(pieces[4].tex_rect, glm::vec4(0.25, 0.25, 0.5, 0.5));
+        assert_eq!(pieces[2].tex_rect, glm::vec4(0.75, 0.0, 0.25, 0.25));
+    }
+
+    #[test]
+    fn nine_slice_test_small_size() {
+        let slice = NineSlice::new(8.0, 8.0, 8.0, 8.0);
+        let pieces = slice.pieces(glm::vec2(0.0, 0.0), glm::vec2(8.0, 32.0), glm::vec2(32.0, 32.0));
+        assert_eq!(pieces[0].size, glm::vec2(4.0, 8.0));
+        assert_eq!(pieces[1].size, glm::vec2(0.0, 8.0));
+        assert_eq!(pieces[2].position, glm::vec2(4.0, 0.0));
+    }
+
+    #[test]
+    fn nine_slice_test_parse_descriptors() {
+        let slices = parse_descriptors("[panel]\nleft = 6\nright = 6\ntop = 6\nbottom = 6\n").unwrap();
+        assert_eq!(slices["panel"], NineSlice::new(6.0, 6.0, 6.0, 6.0));
+        assert!(parse_descriptors("[panel]\nleft = 6\n").is_err());
+        assert!(parse_descriptors("[panel]\nleft = -1\nright = 6\ntop = 6\nbottom = 6\n").is_err());
+    }
+}
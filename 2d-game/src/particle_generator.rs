@@ -17,10 +17,15 @@
 extern crate nalgebra_glm as glm;
 
 use std::{mem, ptr};
+use std::cell::{Cell, RefCell};
 use rand::Rng;
+use bumpalo::collections::Vec as BumpVec;
+use rayon::prelude::*;
 use learnopengl_shared::util;
+use crate::frame_arena;
 use crate::game_object::GameObject;
 use crate::shader::Shader;
+use crate::stream_buffer::{StreamBuffer, DEFAULT_STREAM_SIZE};
 use crate::texture::Texture2D;
 
 // Represents a single particle and its state
@@ -34,15 +39,26 @@ pub struct Particle {
 
 // ParticleGenerator acts as a container for rendering a large number of
 // particles by repeatedly spawning and updating particles and killing
-// them after a given amount of time.
+// them after a given amount of time. GL objects are created on the first draw, so
+// particles can be simulated without a context.
 pub struct ParticleGenerator {
+    // tuning: lifetime of new particles, how far they spawn from the object, the part of
+    // the object's velocity they inherit and how fast they fade
+    pub life: f32,
+    pub spread: f32,
+    pub velocity_factor: f32,
+    pub fade_rate: f32,
+    // color new particles are multiplied with
+    pub tint: glm::TVec3<f32>,
     // state
     particles: Vec<Particle>,
     amount: u32,
     // render state
     shader: Shader,
     texture: Texture2D,
-    vao: u32
+    vao: Cell<u32>,
+    // per-particle offset and color of the alive particles
+    instances: RefCell<Option<StreamBuffer>>
 }
 
 impl Default for Particle {
@@ -56,6 +72,23 @@ impl Default for Particle {
     }
 }
 
+// pools at least this large are updated on all cores, in chunks of PARALLEL_CHUNK particles
+// (smaller ones are done before the threads would have started)
+const PARALLEL_THRESHOLD: usize = 4096;
+const PARALLEL_CHUNK: usize = 1024;
+
+// moves and fades the particles by a step
+fn advance_particles(particles: &mut [Particle], dt: f32, fade_rate: f32) {
+    for p in particles.iter_mut() {
+        p.life -= dt; // reduce life
+        if p.life > 0.0 {
+            // particle is alive, thus update
+            p.position -= p.velocity * dt;
+            p.color.x -= dt * fade_rate;
+        }
+    }
+}
+
 // stores the index of the last particle used (for quick access to next dead particle)
 static mut LAST_USED_PARTICLE: u32 = 0;
 
@@ -66,15 +99,20 @@ impl ParticleGenerator {
         texture: Texture2D,
         amount: u32
     ) -> Self {
-        let mut result = Self {
-            particles: Vec::new(),
+        Self {
+            life: 1.0,
+            spread: 5.0,
+            velocity_factor: 0.1,
+            fade_rate: 2.5,
+            tint: util::glm::scale_vec3(1.0),
+            // create this->amount default particle instances
+            particles: vec![Particle::default(); amount as usize],
             amount,
             shader,
             texture,
-            vao: 0
-        };
-        result.init();
-        result
+            vao: Cell::new(0),
+            instances: RefCell::new(None)
+        }
     }
 
     // update all particles
@@ -99,50 +137,82 @@ impl ParticleGenerator {
         new_particles: u32,
         offset: glm::TVec2<f32>
     ) {
-        // add new particles
+        self.emit(object, new_particles, offset);
+        self.advance(dt);
+    }
+
+    // spawns new particles at the given object without updating the existing ones
+    // (for generators shared by several objects; call advance once per step)
+    pub fn emit(
+        &mut self,
+        object: &GameObject,
+        new_particles: u32,
+        offset: glm::TVec2<f32>
+    ) {
         for _ in 0..new_particles {
             let unused_particle = self.first_unused_particle() as usize;
             self.respawn_particle(unused_particle, object, offset);
         }
-        // update all particles
-        for i in 0..self.amount as usize {
-            let p = &mut self.particles[i];
-            p.life -= dt; // reduce life
-            if p.life > 0.0 {
-                // particle is alive, thus update
-                p.position -= p.velocity * dt;
-                p.color.x -= dt * 2.5;
-            }
+    }
+
+    // updates all particles; this is CPU only (GL is only touched by draw, on the main thread),
+    // so large pools are split across threads
+    pub fn advance(&mut self, dt: f32) {
+        let fade_rate = self.fade_rate;
+        if self.particles.len() >= PARALLEL_THRESHOLD {
+            self.particles.par_chunks_mut(PARALLEL_CHUNK).for_each(|chunk| advance_particles(chunk, dt, fade_rate));
+        } else {
+            advance_particles(&mut self.particles, dt, fade_rate);
         }
     }
 
+    // number of particles alive
+    pub fn alive(&self) -> usize {
+        self.particles.iter().filter(|p| p.life > 0.0).count()
+    }
+
     // render all particles
     pub fn draw(&self) {
+        if self.vao.get() == 0 {
+            self.init_render_data();
+        }
         unsafe {
             // use additive blending to give it a 'glow' effect
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
         }
+        frame_arena::with(|arena| {
+            let instances = BumpVec::from_iter_in(self.particles.iter()
+                .filter(|p| p.life > 0.0)
+                .map(|p| [p.position.x, p.position.y, p.color.x, p.color.y, p.color.z, p.color.w]), arena);
+            self.draw_instances(&instances);
+        });
+    }
+
+    // draws the given offsets and colors of the alive particles
+    fn draw_instances(&self, instances: &[[f32; 6]]) {
         self.shader.use_shader();
-        for particle in self.particles.iter() {
-            if particle.life > 0.0 {
-                self.shader.set_vector2f("offset", &particle.position);
-                self.shader.set_vector4f("color", &particle.color);
-                self.texture.bind();
-                unsafe {
-                    gl::BindVertexArray(self.vao);
-                    gl::DrawArrays(gl::TRIANGLES, 0, 6);
-                    gl::BindVertexArray(0);
-                }
-            }
-        }
+        self.texture.bind();
         unsafe {
+            if !instances.is_empty() {
+                // draw all alive particles at once, pointing the instance attributes at this frame's data
+                gl::BindVertexArray(self.vao.get());
+                let offset = self.instances.borrow_mut().as_mut().unwrap().push(instances);
+                let stride = mem::size_of::<[f32; 6]>() as _;
+                gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, offset as _);
+                gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, (offset + 2 * mem::size_of::<f32>()) as _);
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+                gl::DrawArraysInstanced(gl::TRIANGLES, 0, 6, instances.len() as _);
+                gl::BindVertexArray(0);
+            }
             // don't forget to reset to default blending mode
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
         }
     }
 
     // initializes buffer and vertex attributes
-    fn init(&mut self) {
+    fn init_render_data(&self) {
+        let instances = StreamBuffer::new(DEFAULT_STREAM_SIZE);
+        let mut vao = 0u32;
         // set up mesh and attribute properties
         let mut vbo = 0u32;
         let particle_quad = [
@@ -155,22 +225,28 @@ impl ParticleGenerator {
             1.0, 0.0, 1.0, 0.0
         ];
         unsafe {
-            gl::GenVertexArrays(1, &mut self.vao);
+            gl::GenVertexArrays(1, &mut vao);
             gl::GenBuffers(1, &mut vbo);
-            gl::BindVertexArray(self.vao);
+            gl::BindVertexArray(vao);
             // fill mesh buffer
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
             gl::BufferData(gl::ARRAY_BUFFER, mem::size_of_val(&particle_quad) as _, ptr::addr_of!(particle_quad) as _, gl::STATIC_DRAW);
             // set mesh attributes
             gl::EnableVertexAttribArray(0);
             gl::VertexAttribPointer(0, 4, gl::FLOAT, gl::FALSE, (4 * mem::size_of::<f32>()) as _, ptr::null());
+            // set instance attributes (offset and color), advanced once per particle
+            gl::BindBuffer(gl::ARRAY_BUFFER, instances.id());
+            gl::EnableVertexAttribArray(1);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+            gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, 0, ptr::null());
+            gl::VertexAttribDivisor(1, 1);
+            gl::VertexAttribDivisor(2, 1);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::BindVertexArray(0);
-
-            // create this->amount default particle instances
-            for _ in 0..self.amount {
-                self.particles.push(Particle::default());
-            }
         }
+        self.vao.set(vao);
+        *self.instances.borrow_mut() = Some(instances);
     }
 
     // returns the first Particle index that's currently unused e.g. Life <= 0.0f or 0 if no particle is currently inactive
@@ -206,11 +282,40 @@ impl ParticleGenerator {
     ) {
         let particle = &mut self.particles[particle_index];
         let mut rng = rand::thread_rng();
-        let random = ((rng.gen::<u32>() % 100) as f32 - 50.0) / 10.0;
+        let random = ((rng.gen::<u32>() % 100) as f32 - 50.0) / 50.0 * self.spread;
         let r_color = 0.5 + ((rng.gen::<u32>() % 100) as f32 / 100.0);
         particle.position = glm::vec2(object.position.x + random + offset.x, object.position.y + random + offset.y);
-        particle.color = glm::vec4(r_color, r_color, r_color, 1.0);
-        particle.life = 1.0;
-        particle.velocity = object.velocity * 0.1;
+        particle.color = glm::vec4(r_color * self.tint.x, r_color * self.tint.y, r_color * self.tint.z, 1.0);
+        particle.life = self.life;
+        particle.velocity = object.velocity * self.velocity_factor;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emitter() -> GameObject {
+        GameObject::new_ex1(glm::vec2(400.0, 300.0), glm::vec2(25.0, 25.0), Texture2D::empty(), util::glm::scale_vec3(1.0), glm::vec2(100.0, -350.0))
+    }
+
+    #[test]
+    fn particle_generator_test_lifetime() {
+        let mut particles = ParticleGenerator::new(Shader::new(), Texture2D::empty(), 10);
+        particles.update(0.25, &emitter(), 4);
+        assert_eq!(particles.particles.iter().filter(|p| p.life > 0.0).count(), 4);
+        // particles die once their life is used up
+        particles.advance(particles.life);
+        assert!(particles.particles.iter().all(|p| p.life <= 0.0));
+    }
+
+    #[test]
+    fn particle_generator_test_parallel() {
+        // a pool large enough to be split, updated just like a small one
+        let mut large = ParticleGenerator::new(Shader::new(), Texture2D::empty(), PARALLEL_THRESHOLD as u32 * 2);
+        large.emit(&emitter(), PARALLEL_THRESHOLD as u32 * 2, glm::vec2(6.0, 6.0));
+        let mut expected = large.particles.clone();
+        large.advance(0.1);
+        advance_particles(&mut expected, 0.1, large.fade_rate);
+        assert!(large.particles.iter().zip(expected.iter()).all(|(a, b)| a.position == b.position && a.life == b.life && a.color == b.color));
+    }
+}
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use image::RgbaImage;
+use learnopengl_shared::{filesystem, util};
+use crate::resource_manager;
+use crate::texture::Texture2D;
+
+// Brick patterns of the colorblind texture set. Each brick type gets its own pattern
+// painted over the regular brick texture, so bricks can be told apart without relying
+// on their color.
+
+// name of the texture set holding the patterned bricks
+pub const COLORBLIND_SET: &str = "colorblind";
+
+/// A pattern which can be painted over a brick texture.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BrickPattern {
+    Stripes,
+    Dots,
+    Checker,
+    Diagonal,
+    CrossHatch,
+    // thick inner border, marking bricks which need several hits
+    Frame
+}
+
+impl BrickPattern {
+    // pattern of the bricks with the given tile code
+    pub fn for_tile(code: u32) -> Option<Self> {
+        match code {
+            1 => Some(BrickPattern::CrossHatch),
+            2 => Some(BrickPattern::Stripes),
+            3 => Some(BrickPattern::Dots),
+            4 => Some(BrickPattern::Checker),
+            5 => Some(BrickPattern::Diagonal),
+            _ => None
+        }
+    }
+
+    // whether the pattern covers the pixel (x, y) of a square texture with the given size
+    pub fn covers(self, x: u32, y: u32, size: u32) -> bool {
+        let cell = (size / 8).max(1);
+        match self {
+            BrickPattern::Stripes => (y / cell).is_multiple_of(2),
+            BrickPattern::Dots => {
+                let (cx, cy) = (x % (cell * 2), y % (cell * 2));
+                let (dx, dy) = (cx as i32 - cell as i32, cy as i32 - cell as i32);
+                dx * dx + dy * dy <= (cell as i32 / 2).pow(2)
+            }
+            BrickPattern::Checker => (x / cell + y / cell).is_multiple_of(2),
+            BrickPattern::Diagonal => ((x + y) / cell).is_multiple_of(2),
+            BrickPattern::CrossHatch => (x + y) % (cell * 2) < cell / 3 + 1 || (x + size - y) % (cell * 2) < cell / 3 + 1,
+            BrickPattern::Frame => {
+                let border = cell;
+                let inner = cell * 2;
+                let outside = x < border || y < border || x >= size - border || y >= size - border;
+                let inside = x >= inner && y >= inner && x < size - inner && y < size - inner;
+                !outside && !inside
+            }
+        }
+    }
+}
+
+// darkens the pixels covered by the pattern
+pub fn apply_pattern(image: &mut RgbaImage, pattern: BrickPattern) {
+    let size = image.width().min(image.height());
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        if pattern.covers(x, y, size) {
+            for channel in pixel.0.iter_mut().take(3) {
+                *channel = (*channel as f32 * 0.45) as u8;
+            }
+        }
+    }
+}
+
+// generates a texture from the patterned image and stores it along with the image
+fn store_pattern_texture(name: String, image: &RgbaImage) {
+    let mut texture = Texture2D::new();
+    texture.internal_format = gl::RGBA;
+    texture.image_format = gl::RGBA;
+    texture.generate(image.width(), image.height(), image.as_raw().as_slice());
+    resource_manager::add_generated_texture(name, texture, image.as_raw().clone());
+}
+
+// generates the patterned brick textures and stores them in the colorblind texture set
+pub fn load_colorblind_textures() {
+    let block = util::image::load_image_data_rgba_without_flip(filesystem::get_path("resources/textures/block.png".to_string()))
+        .expect("ERROR::PATTERNS: Failed to load block texture");
+    let mut solid = util::image::load_image_data_rgba_without_flip(filesystem::get_path("resources/textures/block_solid.png".to_string()))
+        .expect("ERROR::PATTERNS: Failed to load solid block texture");
+    apply_pattern(&mut solid, BrickPattern::CrossHatch);
+    store_pattern_texture(format!("{}/block_solid", COLORBLIND_SET), &solid);
+    for code in 2..=5 {
+        let mut image = block.clone();
+        apply_pattern(&mut image, BrickPattern::for_tile(code).unwrap());
+        store_pattern_texture(format!("{}/block_{}", COLORBLIND_SET, code), &image);
+        apply_pattern(&mut image, BrickPattern::Frame);
+        store_pattern_texture(format!("{}/block_{}_multi", COLORBLIND_SET, code), &image);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_test_distinct() {
+        // every pair of brick patterns must differ noticeably
+        let size = 64;
+        let patterns: Vec<_> = (1..=5).map(|code| BrickPattern::for_tile(code).unwrap()).collect();
+        for (i, a) in patterns.iter().enumerate() {
+            for b in patterns.iter().skip(i + 1) {
+                let differing = (0..size * size)
+                    .filter(|p| a.covers(p % size, p / size, size) != b.covers(p % size, p / size, size))
+                    .count();
+                assert!(differing > (size * size / 10) as usize, "{:?} and {:?} look too similar", a, b);
+            }
+        }
+        assert_eq!(BrickPattern::for_tile(0), None);
+    }
+
+    #[test]
+    fn apply_pattern_test_case() {
+        let mut image = RgbaImage::from_pixel(16, 16, image::Rgba([200, 200, 200, 255]));
+        apply_pattern(&mut image, BrickPattern::Stripes);
+        assert_eq!(image.get_pixel(0, 0).0, [90, 90, 90, 255]);
+        assert_eq!(image.get_pixel(0, 2).0, [200, 200, 200, 255]);
+    }
+}
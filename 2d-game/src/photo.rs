@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::fs;
+use image::RgbaImage;
+
+// Photo mode: the simulation is frozen (the game stays paused), the camera can be panned
+// and zoomed, the HUD is hidden and the picture can be saved as a supersampled screenshot.
+
+// zoom range of the photo camera
+pub const MIN_ZOOM: f32 = 1.0;
+pub const MAX_ZOOM: f32 = 4.0;
+
+/// A filter the post-processor puts over the final image (used by photo mode).
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum PhotoFilter {
+    #[default]
+    None,
+    Grayscale,
+    Sepia,
+    Vignette
+}
+
+impl PhotoFilter {
+    // value of the photo_filter uniform
+    pub fn id(self) -> i32 {
+        match self {
+            PhotoFilter::None => 0,
+            PhotoFilter::Grayscale => 1,
+            PhotoFilter::Sepia => 2,
+            PhotoFilter::Vignette => 3
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PhotoFilter::None => "none",
+            PhotoFilter::Grayscale => "grayscale",
+            PhotoFilter::Sepia => "sepia",
+            PhotoFilter::Vignette => "vignette"
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            PhotoFilter::None => PhotoFilter::Grayscale,
+            PhotoFilter::Grayscale => PhotoFilter::Sepia,
+            PhotoFilter::Sepia => PhotoFilter::Vignette,
+            PhotoFilter::Vignette => PhotoFilter::None
+        }
+    }
+}
+
+/// The camera of photo mode, looking at `center` (in screen coordinates) and
+/// magnifying the scene by `zoom`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub center: glm::Vec2,
+    pub zoom: f32
+}
+
+impl Camera {
+    // a camera showing the whole screen, as during gameplay
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            center: glm::vec2(width / 2.0, height / 2.0),
+            zoom: MIN_ZOOM
+        }
+    }
+
+    // moves the camera by the given distance on the screen
+    pub fn pan(&mut self, delta: glm::Vec2, width: f32, height: f32) {
+        self.center += delta / self.zoom;
+        self.clamp(width, height);
+    }
+
+    pub fn zoom_by(&mut self, factor: f32, width: f32, height: f32) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.clamp(width, height);
+    }
+
+    // keeps the view inside of the screen
+    fn clamp(&mut self, width: f32, height: f32) {
+        let (half_width, half_height) = (width / 2.0 / self.zoom, height / 2.0 / self.zoom);
+        self.center.x = self.center.x.clamp(half_width, width - half_width);
+        self.center.y = self.center.y.clamp(half_height, height - half_height);
+    }
+
+    // the projection of the scene as seen through the camera
+    pub fn projection(&self, width: f32, height: f32) -> glm::Mat4 {
+        let (half_width, half_height) = (width / 2.0 / self.zoom, height / 2.0 / self.zoom);
+        glm::ortho(
+            self.center.x - half_width,
+            self.center.x + half_width,
+            self.center.y + half_height,
+            self.center.y - half_height,
+            -1.0,
+            1.0
+        )
+    }
+}
+
+/// State of photo mode while it's open.
+pub struct PhotoMode {
+    pub camera: Camera,
+    pub filter: PhotoFilter,
+    // whether the key hints are shown (they never show up on saved screenshots)
+    pub show_hints: bool
+}
+
+impl PhotoMode {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            camera: Camera::new(width, height),
+            filter: PhotoFilter::None,
+            show_hints: true
+        }
+    }
+}
+
+// turns pixels as read back from OpenGL (bottom row first) into an image
+pub fn image_from_gl(width: u32, height: u32, pixels: Vec<u8>) -> Option<RgbaImage> {
+    let mut image = RgbaImage::from_raw(width, height, pixels)?;
+    image::imageops::flip_vertical_in_place(&mut image);
+    Some(image)
+}
+
+// saves a screenshot into the given directory, named after the current time
+pub fn save_screenshot(image: &RgbaImage, dir: &str) -> Result<String, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|it| it.as_millis())
+        .unwrap_or(0);
+    let path = format!("{}/photo-{}.png", dir, timestamp);
+    image.save(path.as_str()).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn photo_test_camera() {
+        let mut camera = Camera::new(800.0, 600.0);
+        assert_eq!(camera.projection(800.0, 600.0), glm::ortho(0.0, 800.0, 600.0, 0.0, -1.0, 1.0));
+        // at the lowest zoom the whole screen is visible, so there's nothing to pan
+        camera.pan(glm::vec2(100.0, 0.0), 800.0, 600.0);
+        assert_eq!(camera.center, glm::vec2(400.0, 300.0));
+        camera.zoom_by(2.0, 800.0, 600.0);
+        camera.pan(glm::vec2(-1000.0, 0.0), 800.0, 600.0);
+        assert_eq!(camera.center, glm::vec2(200.0, 300.0));
+        camera.zoom_by(100.0, 800.0, 600.0);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+        camera.zoom_by(0.01, 800.0, 600.0);
+        assert_eq!(camera, Camera::new(800.0, 600.0));
+    }
+
+    #[test]
+    fn photo_test_image_from_gl() {
+        // two rows of one pixel each, bottom row first
+        let image = image_from_gl(1, 2, vec![1, 1, 1, 255, 2, 2, 2, 255]).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [2, 2, 2, 255]);
+        assert!(image_from_gl(2, 2, vec![0; 4]).is_none());
+        assert_eq!(PhotoFilter::Vignette.next(), PhotoFilter::None);
+    }
+}
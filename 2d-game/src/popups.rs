@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::fmt::Write;
+use crate::score::FEVER_COMBO;
+use crate::tween::{Ease, Tween};
+
+// Score popups: the points a destroyed brick awarded rise from where it was as text and
+// fade out, bigger and warmer the longer the combo. Chain explosions destroy many bricks at
+// once, so the popups live in a pool of fixed size whose text buffers are reused; when all
+// of them are showing, the oldest makes room.
+
+// most popups shown at once
+pub const POPUP_CAPACITY: usize = 32;
+// how long a popup is shown, in seconds
+pub const POPUP_TIME: f32 = 0.9;
+// how far a popup rises while shown
+pub const POPUP_RISE: f32 = 40.0;
+// text scale of a popup without combo
+pub const POPUP_SCALE: f32 = 0.5;
+// combo from which popups sparkle (an emoji after the points)
+pub const SPARKLE_COMBO: u32 = FEVER_COMBO / 2;
+
+// color and text scale of a popup for the given combo
+pub fn combo_style(combo: u32) -> (glm::TVec3<f32>, f32) {
+    let color = if combo >= FEVER_COMBO {
+        glm::vec3(1.0, 0.45, 0.2)
+    } else if combo >= SPARKLE_COMBO {
+        glm::vec3(1.0, 0.85, 0.3)
+    } else {
+        glm::vec3(1.0, 1.0, 1.0)
+    };
+    // grows with the combo, up to twice the size in fever
+    let growth = combo.saturating_sub(1).min(FEVER_COMBO) as f32 / FEVER_COMBO as f32;
+    (color, POPUP_SCALE * (1.0 + growth))
+}
+
+/// A pooled popup; only drawn while `active`.
+#[derive(Clone, Debug)]
+pub struct ScorePopup {
+    pub text: String,
+    // position of the center of its text
+    pub position: Tween<glm::TVec2<f32>>,
+    // fraction the text is faded out by (slowly at first)
+    pub fade: Tween<f32>,
+    pub color: glm::TVec3<f32>,
+    pub scale: f32,
+    pub active: bool,
+    // when it was spawned (to find the oldest)
+    spawned: u64
+}
+
+impl ScorePopup {
+    fn new() -> Self {
+        Self {
+            text: String::with_capacity(16),
+            position: Tween::new(glm::vec2(0.0, 0.0), glm::vec2(0.0, 0.0), 0.0, Ease::Linear),
+            fade: Tween::new(0.0, 0.0, 0.0, Ease::Linear),
+            color: glm::vec3(1.0, 1.0, 1.0),
+            scale: POPUP_SCALE,
+            active: false,
+            spawned: 0
+        }
+    }
+}
+
+/// The pool of score popups.
+#[derive(Clone, Debug)]
+pub struct ScorePopups {
+    popups: Vec<ScorePopup>,
+    spawned: u64
+}
+
+impl Default for ScorePopups {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScorePopups {
+    pub fn new() -> Self {
+        Self {
+            popups: (0..POPUP_CAPACITY).map(|_| ScorePopup::new()).collect(),
+            spawned: 0
+        }
+    }
+
+    // shows the points awarded at the given point (the center of the text), styled by the combo
+    pub fn spawn(&mut self, points: u32, combo: u32, center: glm::TVec2<f32>) {
+        self.spawned += 1;
+        let popup = match self.popups.iter().position(|it| !it.active) {
+            Some(free) => &mut self.popups[free],
+            None => self.popups.iter_mut().min_by_key(|it| it.spawned).unwrap()
+        };
+        let (color, scale) = combo_style(combo);
+        popup.text.clear();
+        let _ = write!(popup.text, "+{}", points);
+        if combo >= SPARKLE_COMBO {
+            popup.text.push_str(" ✨");
+        }
+        popup.position = Tween::new(center, center - glm::vec2(0.0, POPUP_RISE), POPUP_TIME, Ease::OutQuad);
+        popup.fade = Tween::new(0.0, 1.0, POPUP_TIME, Ease::InQuad);
+        popup.color = color;
+        popup.scale = scale;
+        popup.active = true;
+        popup.spawned = self.spawned;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for popup in self.popups.iter_mut().filter(|it| it.active) {
+            popup.position.update(dt);
+            popup.fade.update(dt);
+            popup.active = !popup.fade.is_finished();
+        }
+    }
+
+    // the popups being shown
+    pub fn active(&self) -> impl Iterator<Item = &ScorePopup> {
+        self.popups.iter().filter(|it| it.active)
+    }
+
+    pub fn clear(&mut self) {
+        for popup in self.popups.iter_mut() {
+            popup.active = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn popups_test_rise_and_fade() {
+        let mut popups = ScorePopups::new();
+        popups.spawn(50, 1, glm::vec2(100.0, 200.0));
+        let popup = popups.active().next().unwrap();
+        assert_eq!(popup.text, "+50");
+        assert_eq!(popup.fade.value(), 0.0);
+        popups.update(POPUP_TIME / 2.0);
+        let popup = popups.active().next().unwrap();
+        assert!(popup.position.value().y < 200.0);
+        assert!(popup.fade.value() > 0.0 && popup.fade.value() < 0.5);
+        popups.update(POPUP_TIME);
+        assert_eq!(popups.active().count(), 0);
+    }
+
+    #[test]
+    fn popups_test_pool_reuses_oldest() {
+        let mut popups = ScorePopups::new();
+        for i in 0..POPUP_CAPACITY as u32 + 3 {
+            popups.spawn(i, 1, glm::vec2(0.0, 0.0));
+        }
+        assert_eq!(popups.active().count(), POPUP_CAPACITY);
+        // the first three were replaced by the last ones
+        assert!(popups.active().all(|it| it.text != "+0" && it.text != "+2"));
+        assert!(popups.active().any(|it| it.text == "+34"));
+        popups.spawn(10, SPARKLE_COMBO, glm::vec2(0.0, 0.0));
+        assert!(popups.active().any(|it| it.text == "+10 ✨"));
+        popups.clear();
+        assert_eq!(popups.active().count(), 0);
+    }
+
+    #[test]
+    fn popups_test_combo_style() {
+        let (color, scale) = combo_style(1);
+        assert_eq!(color, glm::vec3(1.0, 1.0, 1.0));
+        assert_eq!(scale, POPUP_SCALE);
+        let (fever, fever_scale) = combo_style(FEVER_COMBO);
+        assert_ne!(fever, color);
+        assert!(fever_scale > combo_style(FEVER_COMBO / 2).1);
+        assert_eq!(combo_style(100).1, POPUP_SCALE * 2.0);
+    }
+}
@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fs;
+use serde::Deserialize;
+
+// Retro post effects. A level theme (or the retro filter setting) stacks up to
+// MAX_POST_EFFECTS of them: the distortions warp the position the scene is sampled at,
+// in order, after which the color effects are applied, in order. A theme can also grade the
+// final colors with a LUT (see color_grading).
+
+// number of effect slots of the post-processing shader
+pub const MAX_POST_EFFECTS: usize = 4;
+// theme used by the retro filter setting
+pub const SETTINGS_THEME: &str = "crt";
+
+fn default_strength() -> f32 {
+    0.5
+}
+
+fn default_lines() -> f32 {
+    240.0
+}
+
+fn default_offset() -> f32 {
+    0.003
+}
+
+fn default_levels() -> u32 {
+    4
+}
+
+/// A retro effect of the final post pass and its parameters.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PostEffect {
+    // `lines` dark rows across the screen, darkened by up to `strength`
+    Scanlines {
+        #[serde(default = "default_strength")]
+        strength: f32,
+        #[serde(default = "default_lines")]
+        lines: f32
+    },
+    // bulges the image like a curved tube; the corners turn black
+    Barrel {
+        #[serde(default = "default_strength")]
+        strength: f32
+    },
+    // samples red and blue `offset` (in texture coordinates) apart towards the edges
+    ChromaticAberration {
+        #[serde(default = "default_offset")]
+        offset: f32
+    },
+    // quantizes each channel to `levels` values with ordered dithering
+    Dither {
+        #[serde(default = "default_levels")]
+        levels: u32
+    }
+}
+
+impl PostEffect {
+    // value of the effect_kinds uniform; 0 marks an unused slot
+    pub fn kind(&self) -> i32 {
+        match self {
+            PostEffect::Scanlines { .. } => 1,
+            PostEffect::Barrel { .. } => 2,
+            PostEffect::ChromaticAberration { .. } => 3,
+            PostEffect::Dither { .. } => 4
+        }
+    }
+
+    // value of the effect_params uniform
+    pub fn params(&self) -> [f32; 4] {
+        match *self {
+            PostEffect::Scanlines { strength, lines } => [strength, lines, 0.0, 0.0],
+            PostEffect::Barrel { strength } => [strength, 0.0, 0.0, 0.0],
+            PostEffect::ChromaticAberration { offset } => [offset, 0.0, 0.0, 0.0],
+            PostEffect::Dither { levels } => [levels as f32, 0.0, 0.0, 0.0]
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match *self {
+            PostEffect::Scanlines { strength, lines } if !(0.0..=1.0).contains(&strength) || lines < 1.0 =>
+                Err("scanlines need a strength between 0 and 1 and at least one line".to_string()),
+            PostEffect::Barrel { strength } if !(0.0..=1.0).contains(&strength) =>
+                Err("barrel strength is not between 0 and 1".to_string()),
+            PostEffect::ChromaticAberration { offset } if !(0.0..=0.05).contains(&offset) =>
+                Err("chromatic aberration offset is not between 0 and 0.05".to_string()),
+            PostEffect::Dither { levels } if levels < 2 =>
+                Err("dithering needs at least 2 levels".to_string()),
+            _ => Ok(())
+        }
+    }
+}
+
+/// A named stack of post effects, and the color grading LUT applied after them.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Theme {
+    #[serde(default)]
+    pub effects: Vec<PostEffect>,
+    // name of the LUT in color_grading::LUTS_DIR
+    #[serde(default)]
+    pub lut: Option<String>
+}
+
+/// The themes and which 1-based level uses which of them.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct Themes {
+    #[serde(default)]
+    pub themes: HashMap<String, Theme>,
+    #[serde(default)]
+    pub levels: HashMap<String, String>
+}
+
+impl Themes {
+    pub fn effects(&self, theme: &str) -> &[PostEffect] {
+        self.themes.get(theme).map(|it| it.effects.as_slice()).unwrap_or(&[])
+    }
+
+    // the effects of the given level (counted from 1); levels without a theme have none
+    pub fn level_effects(&self, level: usize) -> &[PostEffect] {
+        self.levels.get(&level.to_string()).map(|theme| self.effects(theme)).unwrap_or(&[])
+    }
+
+    pub fn lut(&self, theme: &str) -> Option<&str> {
+        self.themes.get(theme).and_then(|it| it.lut.as_deref())
+    }
+
+    pub fn level_lut(&self, level: usize) -> Option<&str> {
+        self.levels.get(&level.to_string()).and_then(|theme| self.lut(theme))
+    }
+}
+
+pub fn parse_themes(source: &str) -> Result<Themes, String> {
+    let themes: Themes = toml::from_str(source).map_err(|e| e.to_string())?;
+    for (name, theme) in themes.themes.iter() {
+        if theme.effects.len() > MAX_POST_EFFECTS {
+            return Err(format!("theme {} stacks more than {} effects", name, MAX_POST_EFFECTS));
+        }
+        for effect in theme.effects.iter() {
+            effect.validate().map_err(|e| format!("theme {}: {}", name, e))?;
+        }
+        if let Some(lut) = theme.lut.as_ref() {
+            if lut.is_empty() || !lut.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                return Err(format!("theme {} uses the invalid LUT name \"{}\"", name, lut));
+            }
+        }
+    }
+    if let Some((level, theme)) = themes.levels.iter().find(|(_, theme)| !themes.themes.contains_key(*theme)) {
+        return Err(format!("level {} uses the unknown theme \"{}\"", level, theme));
+    }
+    Ok(themes)
+}
+
+// loads the themes, yielding none (no effects) if the file is missing or invalid
+pub fn load_themes(path: &str) -> Themes {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            log::error!("THEMES: Failed to read {}: {}", path, e);
+            return Themes::default();
+        }
+    };
+    parse_themes(source.as_str()).unwrap_or_else(|e| {
+        log::error!("THEMES: Failed to parse {}: {}", path, e);
+        Themes::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const THEMES: &str = r#"
+[themes.arcade]
+effects = [
+    { kind = "barrel", strength = 0.2 },
+    { kind = "scanlines" },
+]
+
+[themes.dusk]
+lut = "warm"
+
+[levels]
+2 = "arcade"
+3 = "dusk"
+"#;
+
+    #[test]
+    fn post_effects_test_parse() {
+        let themes = parse_themes(THEMES).unwrap();
+        assert_eq!(themes.level_effects(1), &[]);
+        let effects = themes.level_effects(2);
+        assert_eq!(effects[0], PostEffect::Barrel { strength: 0.2 });
+        assert_eq!(effects[1].params(), [0.5, 240.0, 0.0, 0.0]);
+        assert_eq!(effects[1].kind(), 1);
+        assert_eq!(themes.level_lut(2), None);
+        assert_eq!(themes.level_lut(3), Some("warm"));
+        assert_eq!(themes.level_effects(3), &[]);
+    }
+
+    #[test]
+    fn post_effects_test_invalid() {
+        assert!(parse_themes("[levels]\n1 = \"missing\"").is_err());
+        assert!(parse_themes("[themes.a]\neffects = [{ kind = \"dither\", levels = 1 }]").is_err());
+        assert!(parse_themes("[themes.a]\neffects = [{ kind = \"wobble\" }]").is_err());
+        assert!(parse_themes("[themes.a]\nlut = \"../settings\"").is_err());
+        let stack = ["{ kind = \"scanlines\" }"; MAX_POST_EFFECTS + 1].join(", ");
+        assert!(parse_themes(format!("[themes.a]\neffects = [{}]", stack).as_str()).is_err());
+    }
+
+    #[test]
+    fn post_effects_test_shipped_themes() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/levels/themes.toml");
+        let source = fs::read_to_string(path).expect("The themes should exist.");
+        match parse_themes(source.as_str()) {
+            Ok(themes) => assert!(!themes.effects(SETTINGS_THEME).is_empty(), "themes.toml must define the {} theme", SETTINGS_THEME),
+            Err(e) => panic!("themes.toml must be valid: {}", e)
+        }
+    }
+}
@@ -14,10 +14,122 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+extern crate nalgebra_glm as glm;
+
 use std::ffi::CString;
 use std::{mem, ptr};
+use serde::{Deserialize, Serialize};
+use crate::photo::PhotoFilter;
+use crate::post_effects::{PostEffect, MAX_POST_EFFECTS};
 use crate::shader::Shader;
-use crate::texture::Texture2D;
+use crate::render_graph::{Format, Output, Pass, PassKind, RenderGraph, TargetDesc};
+
+// how much darker the scene outside of the spotlight gets
+const SPOTLIGHT_DIM: f32 = 0.65;
+
+/// Operator mapping the HDR colors of the scene to the range of the screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMapping {
+    // colors above 1.0 are clipped
+    #[default]
+    None,
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve
+    Aces
+}
+
+pub const TONE_MAPPINGS: [ToneMapping; 3] = [ToneMapping::None, ToneMapping::Reinhard, ToneMapping::Aces];
+
+impl ToneMapping {
+    // value of the tone_mapping uniform (and position in TONE_MAPPINGS)
+    pub fn index(self) -> usize {
+        TONE_MAPPINGS.iter().position(|it| *it == self).unwrap()
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            ToneMapping::None => "none",
+            ToneMapping::Reinhard => "reinhard",
+            ToneMapping::Aces => "aces"
+        }
+    }
+}
+
+/// How much of the previous frames shows through while an effect is active (0.0 - 1.0),
+/// leaving ghost trails behind everything that moves.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ghosting {
+    pub confuse: f32,
+    pub chaos: f32,
+    pub shake: f32
+}
+
+impl Ghosting {
+    // the strongest ghosting of the active effects
+    pub fn amount(&self, confuse: bool, chaos: bool, shake: bool) -> f32 {
+        [(confuse, self.confuse), (chaos, self.chaos), (shake, self.shake)].iter()
+            .filter(|(active, _)| *active)
+            .map(|(_, amount)| amount.clamp(0.0, 1.0))
+            .fold(0.0, f32::max)
+    }
+}
+
+impl Default for Ghosting {
+    fn default() -> Self {
+        Self {
+            confuse: 0.6,
+            chaos: 0.0,
+            shake: 0.0
+        }
+    }
+}
+
+/// Shape of a mask, in texture coordinates of the scene (y from the bottom).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MaskShape {
+    // the radius is a fraction of the height, so the circle stays round
+    Circle { center: [f32; 2], radius: f32 },
+    // min x, min y, max x, max y
+    Rect([f32; 4])
+}
+
+/// Restricts the masked effects (confuse, chaos and the photo filter) to the inside of a
+/// shape, or to its outside if inverted. The shape is drawn into the stencil buffer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mask {
+    pub shape: MaskShape,
+    pub inverted: bool
+}
+
+impl Mask {
+    // a circle around a point of a game of the given size (game coordinates, y from the top)
+    pub fn circle(center: glm::TVec2<f32>, radius: f32, width: u32, height: u32) -> Self {
+        Self {
+            shape: MaskShape::Circle { center: [center.x / width as f32, 1.0 - center.y / height as f32], radius: radius / height as f32 },
+            inverted: false
+        }
+    }
+
+    // whether the shape covers a point, for a target of the given aspect ratio (as in_mask of the shader)
+    pub fn covers(&self, point: [f32; 2], aspect: f32) -> bool {
+        match self.shape {
+            MaskShape::Circle { center, radius } => {
+                let (dx, dy) = ((point[0] - center[0]) * aspect, point[1] - center[1]);
+                dx * dx + dy * dy <= radius * radius
+            }
+            MaskShape::Rect([min_x, min_y, max_x, max_y]) => (min_x..=max_x).contains(&point[0]) && (min_y..=max_y).contains(&point[1])
+        }
+    }
+
+    // values of the mask_kind and mask_params uniforms
+    fn uniforms(&self) -> (i32, [f32; 4]) {
+        match self.shape {
+            MaskShape::Circle { center, radius } => (1, [center[0], center[1], radius, 0.0]),
+            MaskShape::Rect(rect) => (2, rect)
+        }
+    }
+}
 
 // PostProcessor hosts all PostProcessing effects for the Breakout
 // Game. It renders the game on a textured quad after which one can
@@ -28,19 +140,37 @@ use crate::texture::Texture2D;
 pub struct PostProcessor {
     // state
     pub post_processing_shader: Shader,
-    pub texture: Texture2D,
     pub width: u32,
     pub height: u32,
     // options
     pub confuse: bool,
     pub chaos: bool,
     pub shake: bool,
+    // accessibility: strength of the shake (0.0 - 1.0) and gentler chaos/confuse effects
+    pub shake_intensity: f32,
+    pub reduce_flashing: bool,
+    // color saturation of the final image (1.0 leaves it unchanged)
+    pub saturation: f32,
+    // photo mode filter over the final image
+    pub filter: PhotoFilter,
+    // the scene is rendered in HDR; exposure scales its colors before they are tone mapped
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32,
+    // retro effects of the current level theme (at most MAX_POST_EFFECTS)
+    pub effects: Vec<PostEffect>,
+    pub ghosting: Ghosting,
+    // the scene outside of this rect (min x, min y, max x, max y in texture coordinates) is dimmed
+    pub spotlight: Option<[f32; 4]>,
+    // 3D texture the final colors are graded with (see color_grading)
+    pub lut: Option<u32>,
+    // where the masked effects show
+    pub mask: Option<Mask>,
 
     // render state
-    // MSFBO = Multisampled FBO. FBO is regular, used for blitting MS color-buffer to texture
-    msfbo: u32,
-    fbo: u32,
-    rbo: u32, // RBO is used for multisampled color buffer
+    // the multisampled scene is resolved into a texture the effects are drawn from; while
+    // ghosting, the effects are drawn into the ghost target (which keeps the previous frame)
+    // and copied to the screen from there
+    graph: RenderGraph,
     vao: u32
 }
 
@@ -50,45 +180,48 @@ impl PostProcessor {
         shader: Shader,
         width: u32,
         height: u32
+    ) -> Self {
+        Self::new_ex(shader, width, height, 4, "post_processor")
+    }
+
+    // constructor (with the number of MSAA samples, 0 for none, and a separate GPU
+    // memory record, e.g. for offscreen targets)
+    pub fn new_ex(
+        shader: Shader,
+        width: u32,
+        height: u32,
+        samples: u32,
+        memory_name: &str
     ) -> Self {
         let mut result = Self {
             post_processing_shader: shader,
-            texture: Texture2D::new(),
             width,
             height,
             confuse: false,
             chaos: false,
             shake: false,
-            msfbo: u32::default(),
-            fbo: u32::default(),
-            rbo: u32::default(),
+            shake_intensity: 1.0,
+            reduce_flashing: false,
+            saturation: 1.0,
+            filter: PhotoFilter::None,
+            tone_mapping: ToneMapping::None,
+            exposure: 1.0,
+            effects: Vec::new(),
+            ghosting: Ghosting::default(),
+            spotlight: None,
+            lut: None,
+            mask: None,
+            graph: RenderGraph::new(targets(samples), passes(), width, height, memory_name)
+                .expect("The post-processing passes should form a valid render graph."),
             vao: u32::default()
         };
 
         unsafe {
-            // initialize renderbuffer/framebuffer object
-            gl::GenFramebuffers(1, &mut result.msfbo);
-            gl::GenFramebuffers(1, &mut result.fbo);
-            gl::GenRenderbuffers(1, &mut result.rbo);
-            // initialize renderbuffer storage with a multisampled color buffer (don't need a depth/stencil buffer)
-            gl::BindFramebuffer(gl::FRAMEBUFFER, result.msfbo);
-            gl::BindRenderbuffer(gl::RENDERBUFFER, result.rbo);
-            gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, 4, gl::RGB, width as _, height as _); // allocate storage for render buffer object
-            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, result.rbo); // attach MS render buffer object to framebuffer
-            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
-                log::error!("ERROR::POSTPROCESSOR: Failed to initialize MSFBO");
-            }
-            // also initialize the FBO/texture to blit multisampled color-buffer to; used for shader operations (for postprocessing effects)
-            gl::BindFramebuffer(gl::FRAMEBUFFER, result.fbo);
-            result.texture.generate(width, height, &[]);
-            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, result.texture.id, 0); // attach texture to framebuffer as its color attachment
-            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
-                log::error!("ERROR::POSTPROCESSOR: Failed to initialize FBO");
-            }
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
             // initialize render data and uniforms
             result.init_render_data();
             result.post_processing_shader.set_integer_ex("scene", 0, true);
+            result.post_processing_shader.set_integer("history", 1);
+            result.post_processing_shader.set_integer("lut", 2);
             let offset = 1.0f32 / 300.0;
             let offsets = [
                 [-offset, offset ], // top-left
@@ -102,21 +235,21 @@ impl PostProcessor {
                 [offset , -offset]  // bottom-right
             ];
             let c_string = CString::new("offsets").unwrap();
-            gl::Uniform2fv(gl::GetUniformLocation(result.post_processing_shader.id, c_string.as_ptr()), 9, ptr::addr_of!(offsets) as _);
+            gl::Uniform2fv(gl::GetUniformLocation(result.post_processing_shader.program(), c_string.as_ptr()), 9, ptr::addr_of!(offsets) as _);
             let edge_kernel = [
                 -1i32, -1, -1,
                 -1,  8, -1,
                 -1, -1, -1
             ];
             let c_string = CString::new("edge_kernel").unwrap();
-            gl::Uniform1iv(gl::GetUniformLocation(result.post_processing_shader.id, c_string.as_ptr()), 9, ptr::addr_of!(edge_kernel) as _);
+            gl::Uniform1iv(gl::GetUniformLocation(result.post_processing_shader.program(), c_string.as_ptr()), 9, ptr::addr_of!(edge_kernel) as _);
             let blur_kernel = [
                 1.0f32 / 16.0, 2.0 / 16.0, 1.0 / 16.0,
                 2.0 / 16.0, 4.0 / 16.0, 2.0 / 16.0,
                 1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0
             ];
             let c_string = CString::new("blur_kernel").unwrap();
-            gl::Uniform1fv(gl::GetUniformLocation(result.post_processing_shader.id, c_string.as_ptr()), 9, ptr::addr_of!(blur_kernel) as _);
+            gl::Uniform1fv(gl::GetUniformLocation(result.post_processing_shader.program(), c_string.as_ptr()), 9, ptr::addr_of!(blur_kernel) as _);
         }
 
         result
@@ -124,22 +257,25 @@ impl PostProcessor {
 
     // prepares the postprocessor's framebuffer operations before rendering the game
     pub fn begin_render(&self) {
+        // the framebuffer doesn't have to match the window in size
+        self.graph.begin_target("scene_ms");
         unsafe {
-            gl::BindFramebuffer(gl::FRAMEBUFFER, self.msfbo);
             gl::ClearColor(0.0, 0.0, 0.0, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
         }
     }
 
-    // should be called after rendering the game, so it stores all the rendered data into a texture object
+    // should be called after rendering the game, it restores the framebuffer rendered to before
     pub fn end_render(&self) {
-        unsafe {
-            // now resolve multisampled color-buffer into intermediate FBO to store to texture
-            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.msfbo);
-            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.fbo);
-            gl::BlitFramebuffer(0, 0, self.width as _, self.height as _, 0, 0, self.width as _, self.height as _, gl::COLOR_BUFFER_BIT, gl::NEAREST);
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0); // binds both READ and WRITE framebuffer to default framebuffer
-        }
+        self.graph.end_target();
+    }
+
+    // reallocates the framebuffers at another size (or number of MSAA samples)
+    pub fn resize(&mut self, width: u32, height: u32, samples: u32) {
+        self.graph.set_samples("scene_ms", samples);
+        self.graph.resize(width, height);
+        self.width = width;
+        self.height = height;
     }
 
     // renders the PostProcessor texture quad (as a screen-encompassing large sprite)
@@ -150,16 +286,140 @@ impl PostProcessor {
         self.post_processing_shader.set_integer("confuse", if self.confuse { 1 } else { 0 });
         self.post_processing_shader.set_integer("chaos", if self.chaos { 1 } else { 0 });
         self.post_processing_shader.set_integer("shake", if self.shake { 1 } else { 0 });
+        self.post_processing_shader.set_float("shake_strength", self.shake_intensity);
+        self.post_processing_shader.set_integer("reduce_flashing", if self.reduce_flashing { 1 } else { 0 });
+        self.post_processing_shader.set_float("saturation", self.saturation);
+        self.post_processing_shader.set_integer("photo_filter", self.filter.id());
+        self.post_processing_shader.set_integer("tone_mapping", self.tone_mapping.index() as _);
+        self.post_processing_shader.set_float("exposure", self.exposure);
+        let spotlight = self.spotlight.unwrap_or([0.0, 0.0, 1.0, 1.0]);
+        self.post_processing_shader.set_vector4f_vals("spotlight", spotlight[0], spotlight[1], spotlight[2], spotlight[3]);
+        self.post_processing_shader.set_float("spotlight_dim", if self.spotlight.is_some() { SPOTLIGHT_DIM } else { 0.0 });
+        self.post_processing_shader.set_integer("grading", if self.lut.is_some() { 1 } else { 0 });
         unsafe {
-            // render textured quad
+            // units 0 and 1 are the inputs of the render graph
+            gl::ActiveTexture(gl::TEXTURE2);
+            gl::BindTexture(gl::TEXTURE_3D, self.lut.unwrap_or(0));
             gl::ActiveTexture(gl::TEXTURE0);
-            self.texture.bind();
+        }
+        for i in 0..MAX_POST_EFFECTS {
+            let (kind, params) = self.effects.get(i).map(|it| (it.kind(), it.params())).unwrap_or((0, [0.0; 4]));
+            self.post_processing_shader.set_integer(format!("effect_kinds[{}]", i).as_str(), kind);
+            self.post_processing_shader.set_vector4f_vals(format!("effect_params[{}]", i).as_str(), params[0], params[1], params[2], params[3]);
+        }
+        let ghosting = self.ghosting.amount(self.confuse, self.chaos, self.shake);
+        // the first frame of ghosting has nothing to blend in yet
+        let history = if self.graph.has_history("ghost") { ghosting } else { 0.0 };
+        // the screen may have no stencil buffer, so masks are drawn into the ghost target as well
+        let offscreen = ghosting > 0.0 || self.mask.is_some();
+        self.graph.execute(
+            |pass| match pass.name {
+                "effects" => !offscreen,
+                "ghosting" | "present" => offscreen,
+                _ => true
+            },
+            |pass| {
+                self.post_processing_shader.set_float("ghosting", if pass.name == "ghosting" { history } else { 0.0 });
+                match self.mask.filter(|_| pass.name == "ghosting") {
+                    Some(mask) => self.draw_masked(&mask),
+                    None => self.draw_quad()
+                }
+            }
+        );
+    }
+
+    fn draw_quad(&self) {
+        unsafe {
+            // render textured quad
             gl::BindVertexArray(self.vao);
             gl::DrawArrays(gl::TRIANGLES, 0, 6);
             gl::BindVertexArray(0);
         }
     }
 
+    // draws the shape of the mask into the stencil buffer, then the quad with all effects where
+    // the mask lets them show and without the masked ones everywhere else
+    fn draw_masked(&self, mask: &Mask) {
+        let shader = &self.post_processing_shader;
+        let (kind, params) = mask.uniforms();
+        shader.set_integer("mask_kind", kind);
+        shader.set_vector4f_vals("mask_params", params[0], params[1], params[2], params[3]);
+        let (inside, outside) = if mask.inverted { (gl::NOTEQUAL, gl::EQUAL) } else { (gl::EQUAL, gl::NOTEQUAL) };
+        unsafe {
+            gl::Enable(gl::STENCIL_TEST);
+            gl::StencilMask(0xFF);
+            gl::ClearStencil(0);
+            gl::Clear(gl::STENCIL_BUFFER_BIT);
+            gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            shader.set_integer("mask_pass", 1);
+            self.draw_quad();
+            shader.set_integer("mask_pass", 0);
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+            gl::StencilFunc(inside, 1, 0xFF);
+            self.draw_quad();
+            gl::StencilFunc(outside, 1, 0xFF);
+            shader.set_integer("confuse", 0);
+            shader.set_integer("chaos", 0);
+            shader.set_integer("photo_filter", 0);
+            self.draw_quad();
+            shader.set_integer("confuse", if self.confuse { 1 } else { 0 });
+            shader.set_integer("chaos", if self.chaos { 1 } else { 0 });
+            shader.set_integer("photo_filter", self.filter.id());
+            gl::Disable(gl::STENCIL_TEST);
+        }
+    }
+
+    // takes over the effects and options of another post-processor
+    pub fn copy_options(&mut self, other: &PostProcessor) {
+        self.confuse = other.confuse;
+        self.chaos = other.chaos;
+        self.shake = other.shake;
+        self.shake_intensity = other.shake_intensity;
+        self.reduce_flashing = other.reduce_flashing;
+        self.saturation = other.saturation;
+        self.filter = other.filter;
+        self.tone_mapping = other.tone_mapping;
+        self.exposure = other.exposure;
+        self.effects = other.effects.clone();
+        self.ghosting = other.ghosting;
+        self.spotlight = other.spotlight;
+        self.lut = other.lut;
+        self.mask = other.mask;
+    }
+
+    // renders the PostProcessor texture quad into an offscreen texture and reads it back
+    // as RGBA pixels (bottom row first)
+    pub fn capture(&self, time: f32) -> Vec<u8> {
+        let mut pixels = vec![0u8; self.width as usize * self.height as usize * 4];
+        unsafe {
+            let (mut fbo, mut texture) = (0u32, 0u32);
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as _, self.width as _, self.height as _, 0, gl::RGBA, gl::UNSIGNED_BYTE, ptr::null());
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, texture, 0);
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+                log::error!("ERROR::POSTPROCESSOR: Failed to initialize capture FBO");
+            }
+            let mut viewport = [0i32; 4];
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::Viewport(0, 0, self.width as _, self.height as _);
+            self.render(time);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(0, 0, self.width as _, self.height as _, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as _);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &fbo);
+            gl::DeleteTextures(1, &texture);
+        }
+        pixels
+    }
+
     // initialize quad for rendering postprocessing texture
     fn init_render_data(&mut self) {
         // configure VAO/VBO
@@ -188,4 +448,67 @@ impl PostProcessor {
             gl::BindVertexArray(0);
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for PostProcessor {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+// targets of the post-processing passes (the scene with the given number of MSAA samples)
+fn targets(samples: u32) -> Vec<TargetDesc> {
+    vec![
+        TargetDesc { name: "scene_ms", format: Format::Rgba16F, samples, history: false, stencil: false },
+        TargetDesc { name: "scene", format: Format::Rgba16F, samples: 0, history: false, stencil: false },
+        // holds the stencil of the mask
+        TargetDesc { name: "ghost", format: Format::Rgba8, samples: 0, history: true, stencil: true }
+    ]
+}
+
+fn passes() -> Vec<Pass> {
+    vec![
+        Pass { name: "resolve", kind: PassKind::Blit, inputs: vec!["scene_ms"], history: vec![], output: Output::Target("scene") },
+        // either the effects are drawn straight to the screen, or blended with the previous frame
+        Pass { name: "effects", kind: PassKind::Draw, inputs: vec!["scene"], history: vec![], output: Output::Screen },
+        Pass { name: "ghosting", kind: PassKind::Draw, inputs: vec!["scene"], history: vec!["ghost"], output: Output::Target("ghost") },
+        Pass { name: "present", kind: PassKind::Blit, inputs: vec!["ghost"], history: vec![], output: Output::Screen }
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghosting_test_amount() {
+        let ghosting = Ghosting { confuse: 0.6, chaos: 0.2, shake: 1.5 };
+        assert_eq!(ghosting.amount(false, false, false), 0.0);
+        assert_eq!(ghosting.amount(true, true, false), 0.6);
+        assert_eq!(ghosting.amount(false, true, true), 1.0);
+    }
+
+    #[test]
+    fn mask_test_covers() {
+        // a circle of 60 pixels around the middle of the bottom half of the game
+        let mask = Mask::circle(glm::vec2(400.0, 450.0), 60.0, 800, 600);
+        assert_eq!(mask.shape, MaskShape::Circle { center: [0.5, 0.25], radius: 0.1 });
+        let aspect = 800.0 / 600.0;
+        assert!(mask.covers([0.5, 0.3], aspect));
+        // 60 pixels to the side are 0.075 of the width
+        assert!(mask.covers([0.57, 0.25], aspect));
+        assert!(!mask.covers([0.58, 0.25], aspect));
+        let rect = Mask { shape: MaskShape::Rect([0.25, 0.25, 0.75, 0.75]), inverted: true };
+        assert!(rect.covers([0.5, 0.5], aspect) && !rect.covers([0.1, 0.5], aspect));
+        assert_eq!(rect.uniforms(), (2, [0.25, 0.25, 0.75, 0.75]));
+    }
+
+    #[test]
+    fn post_processor_test_graph() {
+        let order = crate::render_graph::order_passes(&targets(4), &passes()).unwrap();
+        let names: Vec<&str> = order.iter().map(|i| passes()[*i].name).collect();
+        assert_eq!(names, ["resolve", "effects", "ghosting", "present"]);
+    }
+}
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use learnopengl_shared::util;
+use crate::power_up::effect::{EffectTarget, PowerUpEffect};
+
+/// Speeds the ball up by a fifth for the rest of the life.
+pub struct Speed;
+
+impl PowerUpEffect for Speed {
+    fn id(&self) -> &'static str {
+        "speed"
+    }
+
+    fn on_apply(&self, target: &mut EffectTarget) {
+        target.ball.game_obj.velocity *= 1.2;
+    }
+}
+
+/// Makes the paddle catch the ball.
+pub struct Sticky;
+
+impl PowerUpEffect for Sticky {
+    fn id(&self) -> &'static str {
+        "sticky"
+    }
+
+    fn on_apply(&self, target: &mut EffectTarget) {
+        target.ball.sticky = true;
+        target.player.color = glm::vec3(1.0, 0.5, 1.0);
+    }
+
+    fn on_expire(&self, target: &mut EffectTarget) {
+        target.ball.sticky = false;
+        target.player.color = util::glm::scale_vec3(1.0);
+    }
+}
+
+/// Lets the ball pass through the bricks it breaks.
+pub struct PassThrough;
+
+impl PowerUpEffect for PassThrough {
+    fn id(&self) -> &'static str {
+        "pass-through"
+    }
+
+    fn on_apply(&self, target: &mut EffectTarget) {
+        target.ball.pass_through = true;
+        target.ball.game_obj.color = glm::vec3(1.0, 0.5, 0.5);
+    }
+
+    fn on_expire(&self, target: &mut EffectTarget) {
+        target.ball.pass_through = false;
+        target.ball.game_obj.color = util::glm::scale_vec3(1.0);
+    }
+}
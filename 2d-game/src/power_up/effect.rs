@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::ball_object::BallObject;
+use crate::game_object::GameObject;
+use crate::post_processor::PostProcessor;
+use crate::power_up::{ball, paddle, screen};
+
+/// What a power-up effect acts on. The screen effects only exist in the game, the headless
+/// simulation has none.
+pub struct EffectTarget<'a> {
+    pub ball: &'a mut BallObject,
+    pub player: &'a mut GameObject,
+    pub screen: Option<&'a mut PostProcessor>
+}
+
+/// The behavior of a power-up type, from being collected until it runs out.
+pub trait PowerUpEffect: Sync {
+    // the type name used by level files, drop tables and the HUD
+    fn id(&self) -> &'static str;
+
+    // the power-up was collected
+    fn on_apply(&self, target: &mut EffectTarget);
+
+    // called every simulation step while the power-up is active
+    fn on_update(&self, _target: &mut EffectTarget, _dt: f32) {}
+
+    // the last active power-up of the type ran out
+    fn on_expire(&self, _target: &mut EffectTarget) {}
+}
+
+// the effects of all power-up types
+pub static EFFECTS: [&dyn PowerUpEffect; 6] = [
+    &ball::Speed,
+    &ball::Sticky,
+    &ball::PassThrough,
+    &paddle::PadSizeIncrease,
+    &screen::Confuse,
+    &screen::Chaos
+];
+
+// the effect of the power-up type with the given name
+pub fn find_effect(id: &str) -> Option<&'static dyn PowerUpEffect> {
+    EFFECTS.iter().copied().find(|effect| effect.id() == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::POWER_UP_TYPES;
+
+    #[test]
+    fn power_up_effect_test_registry() {
+        // every power-up type has exactly one effect
+        assert_eq!(EFFECTS.len(), POWER_UP_TYPES.len());
+        for (type_str, ..) in POWER_UP_TYPES.iter() {
+            assert_eq!(find_effect(type_str).map(|it| it.id()), Some(*type_str));
+        }
+        assert!(find_effect("shield").is_none());
+    }
+}
@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use lazy_static::lazy_static;
+use crate::game_object::GameObject;
+use crate::inspect::{Inspect, Value};
+use crate::sprite_renderer::SpriteRenderer;
+use crate::texture::Texture2D;
+use crate::tween::{Ease, Tween};
+
+pub mod effect;
+pub mod ball;
+pub mod paddle;
+pub mod screen;
+
+lazy_static! {
+    // The size of a PowerUp block
+    static ref POWERUP_SIZE: glm::TVec2<f32> = glm::vec2(60.0, 20.0);
+    // Velocity a PowerUp block has when spawned
+    static ref VELOCITY: glm::TVec2<f32> = glm::vec2(0.0, 150.0);
+}
+
+/// Rarity tier of a power-up type. Rarer power-ups glow and trail an aura while falling.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rarity {
+    Common,
+    Rare,
+    Epic
+}
+
+impl Rarity {
+    // color of the glow and the aura particles
+    pub fn color(&self) -> glm::TVec3<f32> {
+        match self {
+            Rarity::Common => glm::vec3(1.0, 1.0, 1.0),
+            Rarity::Rare => glm::vec3(0.4, 0.7, 1.0),
+            Rarity::Epic => glm::vec3(1.0, 0.75, 0.2)
+        }
+    }
+
+    pub fn glow_strength(&self) -> f32 {
+        match self {
+            Rarity::Common => 0.0,
+            Rarity::Rare => 0.5,
+            Rarity::Epic => 0.9
+        }
+    }
+
+    // aura particles spawned per simulation step while falling
+    pub fn aura_particles(&self) -> u32 {
+        match self {
+            Rarity::Common => 0,
+            Rarity::Rare => 1,
+            Rarity::Epic => 2
+        }
+    }
+}
+
+// PowerUp inherits its state and rendering functions from
+// GameObject but also holds extra information to state its
+// active duration and whether it is activated or not. 
+// The type of PowerUp is stored as a string.
+#[derive(Clone)]
+pub struct PowerUp {
+    pub game_obj: GameObject,
+    // powerup state
+    pub type_str: String,
+    pub duration: f32,
+    pub activated: bool,
+    pub rarity: Rarity
+}
+
+impl PowerUp {
+    // constructor
+    pub fn new(
+        type_str: String,
+        color: glm::TVec3<f32>,
+        duration: f32,
+        position: glm::TVec2<f32>,
+        texture: Texture2D,
+        rarity: Rarity
+    ) -> Self {
+        let game_obj = GameObject::new_ex1(
+            position,
+            POWERUP_SIZE.clone(),
+            texture,
+            color,
+            VELOCITY.clone()
+        );
+        Self {
+            game_obj,
+            type_str,
+            duration,
+            activated: false,
+            rarity
+        }
+    }
+
+    pub fn draw(&self, renderer: &SpriteRenderer) {
+        self.game_obj.draw(renderer);
+    }
+}
+// how long a collected power-up takes to reach its HUD slot
+const PICKUP_TIME: f32 = 0.45;
+
+/// A collected power-up flying from where it was caught to its slot in the HUD.
+#[derive(Clone)]
+pub struct Pickup {
+    pub type_str: String,
+    sprite: Texture2D,
+    color: glm::TVec3<f32>,
+    position: Tween<glm::TVec2<f32>>,
+    size: Tween<glm::TVec2<f32>>
+}
+
+impl Pickup {
+    pub fn new(power_up: &PowerUp, slot_position: glm::TVec2<f32>, slot_size: glm::TVec2<f32>) -> Self {
+        let game_obj = &power_up.game_obj;
+        Self {
+            type_str: power_up.type_str.clone(),
+            sprite: game_obj.sprite.clone(),
+            color: game_obj.color,
+            position: Tween::new(game_obj.position, slot_position, PICKUP_TIME, Ease::InOutQuad),
+            size: Tween::new(game_obj.size, slot_size, PICKUP_TIME, Ease::OutBack)
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.position.update(dt);
+        self.size.update(dt);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position.is_finished()
+    }
+
+    pub fn draw(&self, renderer: &SpriteRenderer) {
+        renderer.draw_sprite_ex2(&self.sprite, self.position.value(), self.size.value(), 0.0, self.color);
+    }
+}
+
+impl Inspect for PowerUp {
+    fn property_names(&self) -> Vec<&'static str> {
+        let mut names = self.game_obj.property_names();
+        names.extend(["duration", "activated"]);
+        names
+    }
+
+    fn property(&self, name: &str) -> Option<Value> {
+        match name {
+            "duration" => Some(Value::Float(self.duration)),
+            "activated" => Some(Value::Bool(self.activated)),
+            _ => self.game_obj.property(name)
+        }
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        match (name, value) {
+            ("duration", Value::Float(v)) => self.duration = v,
+            ("activated", Value::Bool(v)) => self.activated = v,
+            _ => self.game_obj.set_property(name, value)
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::power_up::effect::{EffectTarget, PowerUpEffect};
+
+/// Widens the paddle for the rest of the life.
+pub struct PadSizeIncrease;
+
+impl PowerUpEffect for PadSizeIncrease {
+    fn id(&self) -> &'static str {
+        "pad-size-increase"
+    }
+
+    fn on_apply(&self, target: &mut EffectTarget) {
+        target.player.size.x += 50.0;
+    }
+}
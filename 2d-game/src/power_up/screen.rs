@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::power_up::effect::{EffectTarget, PowerUpEffect};
+
+/// Turns the screen upside down (unless it is already in chaos).
+pub struct Confuse;
+
+impl PowerUpEffect for Confuse {
+    fn id(&self) -> &'static str {
+        "confuse"
+    }
+
+    fn on_apply(&self, target: &mut EffectTarget) {
+        if let Some(screen) = target.screen.as_mut() {
+            // only activate if chaos wasn't already active
+            if !screen.chaos {
+                screen.confuse = true;
+            }
+        }
+    }
+
+    fn on_expire(&self, target: &mut EffectTarget) {
+        if let Some(screen) = target.screen.as_mut() {
+            screen.confuse = false;
+        }
+    }
+}
+
+/// Swirls the screen around (unless it is already confused).
+pub struct Chaos;
+
+impl PowerUpEffect for Chaos {
+    fn id(&self) -> &'static str {
+        "chaos"
+    }
+
+    fn on_apply(&self, target: &mut EffectTarget) {
+        if let Some(screen) = target.screen.as_mut() {
+            if !screen.confuse {
+                screen.chaos = true;
+            }
+        }
+    }
+
+    fn on_expire(&self, target: &mut EffectTarget) {
+        if let Some(screen) = target.screen.as_mut() {
+            screen.chaos = false;
+        }
+    }
+}
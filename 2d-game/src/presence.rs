@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{Read, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde_json::{json, Value};
+
+// Discord Rich Presence over Discord's local IPC socket. Messages are frames of a
+// little-endian opcode and payload length, followed by the JSON payload.
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+// Discord drops activity updates sent more often than this
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(4);
+
+/// What the player is currently doing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Activity {
+    // first line, e.g. "In Menu" or "Level 3"
+    pub details: String,
+    // second line, e.g. "12 bricks left"
+    pub state: Option<String>
+}
+
+trait Connection: Read + Write {}
+impl<T: Read + Write> Connection for T {}
+
+pub struct DiscordPresence {
+    connection: Box<dyn Connection>,
+    // activity shown on discord and the activity waiting to be sent
+    sent: Option<Activity>,
+    pending: Option<Activity>,
+    last_update: Option<Instant>,
+    // unix time the current details started at, shown as elapsed time
+    start: u64,
+    nonce: u64
+}
+
+pub fn encode_frame(opcode: u32, payload: &Value) -> Vec<u8> {
+    let payload = payload.to_string();
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    frame.extend_from_slice(&opcode.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload.as_bytes());
+    frame
+}
+
+pub fn activity_payload(activity: &Activity, start: u64, pid: u32, nonce: u64) -> Value {
+    let mut value = json!({
+        "details": activity.details,
+        "timestamps": { "start": start }
+    });
+    if let Some(state) = &activity.state {
+        value["state"] = json!(state);
+    }
+    json!({
+        "cmd": "SET_ACTIVITY",
+        "args": { "pid": pid, "activity": value },
+        "nonce": nonce.to_string()
+    })
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|it| it.as_secs()).unwrap_or(0)
+}
+
+#[cfg(unix)]
+fn open_socket(index: u32) -> Option<Box<dyn Connection>> {
+    let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"].iter()
+        .find_map(|name| std::env::var(name).ok())
+        .unwrap_or("/tmp".to_string());
+    let stream = std::os::unix::net::UnixStream::connect(format!("{}/discord-ipc-{}", dir, index)).ok()?;
+    Some(Box::new(stream))
+}
+
+#[cfg(windows)]
+fn open_socket(index: u32) -> Option<Box<dyn Connection>> {
+    let pipe = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!(r"\\.\pipe\discord-ipc-{}", index))
+        .ok()?;
+    Some(Box::new(pipe))
+}
+
+impl DiscordPresence {
+    // connects to a running discord client; None if discord isn't running
+    pub fn connect(client_id: &str) -> Option<Self> {
+        let mut connection = (0..10).find_map(open_socket)?;
+        connection.write_all(&encode_frame(OP_HANDSHAKE, &json!({ "v": 1, "client_id": client_id }))).ok()?;
+        // the reply is the READY event (or an error, after which discord closes the socket)
+        let mut header = [0u8; 8];
+        connection.read_exact(&mut header).ok()?;
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let mut reply = vec![0u8; length as usize];
+        connection.read_exact(&mut reply).ok()?;
+        Some(Self {
+            connection,
+            sent: None,
+            pending: None,
+            last_update: None,
+            start: unix_time(),
+            nonce: 0
+        })
+    }
+
+    // sets the activity; changes are sent no faster than discord accepts them
+    pub fn update(&mut self, activity: Activity) {
+        if self.sent.as_ref() == Some(&activity) {
+            self.pending = None;
+        } else {
+            self.pending = Some(activity);
+        }
+        if self.last_update.is_some_and(|time| time.elapsed() < MIN_UPDATE_INTERVAL) {
+            return;
+        }
+        let Some(activity) = self.pending.take() else {
+            return;
+        };
+        if self.sent.as_ref().is_none_or(|sent| sent.details != activity.details) {
+            self.start = unix_time();
+        }
+        self.nonce += 1;
+        let frame = encode_frame(OP_FRAME, &activity_payload(&activity, self.start, std::process::id(), self.nonce));
+        if let Err(e) = self.connection.write_all(&frame) {
+            log::warn!("PRESENCE: Failed to update the activity: {}", e);
+        }
+        self.last_update = Some(Instant::now());
+        self.sent = Some(activity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_frame_test_case() {
+        let frame = encode_frame(OP_FRAME, &json!({ "a": 1 }));
+        assert_eq!(&frame[0..4], &[1, 0, 0, 0]);
+        assert_eq!(&frame[4..8], &[7, 0, 0, 0]);
+        assert_eq!(&frame[8..], b"{\"a\":1}");
+    }
+
+    #[test]
+    fn activity_payload_test_case() {
+        let activity = Activity { details: "Level 3".to_string(), state: Some("12 bricks left".to_string()) };
+        let payload = activity_payload(&activity, 100, 42, 7);
+        assert_eq!(payload["cmd"], "SET_ACTIVITY");
+        assert_eq!(payload["args"]["pid"], 42);
+        assert_eq!(payload["args"]["activity"]["state"], "12 bricks left");
+        assert_eq!(payload["args"]["activity"]["timestamps"]["start"], 100);
+        let payload = activity_payload(&Activity { details: "In Menu".to_string(), state: None }, 100, 42, 8);
+        assert!(payload["args"]["activity"].get("state").is_none());
+    }
+}
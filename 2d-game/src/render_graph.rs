@@ -0,0 +1,463 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::ptr;
+use crate::frame_capture;
+use crate::resource_manager;
+
+/// Pixel format of a render target.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Rgba8,
+    // half-float, for HDR colors
+    Rgba16F
+}
+
+impl Format {
+    fn internal_format(self) -> u32 {
+        match self {
+            Format::Rgba8 => gl::RGBA8,
+            Format::Rgba16F => gl::RGBA16F
+        }
+    }
+
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Format::Rgba8 => 4,
+            Format::Rgba16F => 8
+        }
+    }
+}
+
+/// A render target of the graph, as large as the graph itself. Multisampled targets are
+/// renderbuffers which can only be blitted from, all others are textures. A history
+/// target is double-buffered so passes can read what was written to it in the previous frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetDesc {
+    pub name: &'static str,
+    pub format: Format,
+    pub samples: u32,
+    pub history: bool,
+    // whether the target has a depth/stencil buffer next to its colors, e.g. for masks
+    pub stencil: bool
+}
+
+/// Where a pass renders to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Output {
+    Target(&'static str),
+    // the framebuffer (and viewport) bound when the graph is executed
+    Screen
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PassKind {
+    // copies its single input into the output, filtered linearly if their sizes differ
+    Blit,
+    // drawn by the caller with the inputs, then the history inputs, bound to texture units 0, 1, ...
+    Draw
+}
+
+/// A pass of the graph. It runs after every pass writing one of its inputs;
+/// history inputs are the previous frame of a history target and don't order passes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pass {
+    pub name: &'static str,
+    pub kind: PassKind,
+    pub inputs: Vec<&'static str>,
+    pub history: Vec<&'static str>,
+    pub output: Output
+}
+
+struct Target {
+    desc: TargetDesc,
+    // one framebuffer and attachment (texture or renderbuffer), two for history targets
+    fbos: [u32; 2],
+    attachments: [u32; 2],
+    // depth/stencil renderbuffer of each framebuffer (0 without one)
+    depth_stencils: [u32; 2],
+    // which of the two is written this frame
+    current: Cell<usize>,
+    // whether the other one holds the previous frame
+    filled: Cell<bool>,
+    written: Cell<bool>
+}
+
+// RenderGraph owns the framebuffers of a chain of passes. It allocates the targets at
+// its size (again on resize), orders the passes by their inputs and binds the
+// targets of each pass while executing.
+pub struct RenderGraph {
+    pub width: u32,
+    pub height: u32,
+    targets: Vec<Target>,
+    passes: Vec<Pass>,
+    order: Vec<usize>,
+    // framebuffer and viewport to restore after rendering into a target from outside the graph
+    restore: Cell<(i32, [i32; 4])>,
+    // name the GPU memory is tracked under
+    memory_name: String
+}
+
+// orders the passes so that each one comes after the passes writing its inputs, keeping
+// the declared order where it doesn't matter
+pub fn order_passes(targets: &[TargetDesc], passes: &[Pass]) -> Result<Vec<usize>, String> {
+    let find = |name: &str| targets.iter().find(|it| it.name == name)
+        .ok_or_else(|| format!("unknown target \"{}\"", name));
+    for pass in passes.iter() {
+        for input in pass.inputs.iter() {
+            let target = find(input)?;
+            if pass.kind == PassKind::Draw && target.samples > 0 {
+                return Err(format!("{} can't draw with the multisampled target {}", pass.name, input));
+            }
+            if pass.output == Output::Target(input) {
+                return Err(format!("{} reads its own output {}", pass.name, input));
+            }
+        }
+        for input in pass.history.iter() {
+            let target = find(input)?;
+            if !target.history || target.samples > 0 {
+                return Err(format!("{} has no history to read from {}", input, pass.name));
+            }
+        }
+        if let Output::Target(output) = pass.output {
+            find(output)?;
+        }
+        if pass.kind == PassKind::Blit && (pass.inputs.len() != 1 || !pass.history.is_empty()) {
+            return Err(format!("{} has to blit exactly one input", pass.name));
+        }
+    }
+    let depends = |pass: &Pass, on: &Pass| match on.output {
+        Output::Target(output) => pass.inputs.contains(&output),
+        Output::Screen => false
+    };
+    let mut order = Vec::with_capacity(passes.len());
+    while order.len() < passes.len() {
+        let next = (0..passes.len()).find(|&i| !order.contains(&i) && (0..passes.len())
+            .all(|j| j == i || order.contains(&j) || !depends(&passes[i], &passes[j])));
+        match next {
+            Some(next) => order.push(next),
+            None => {
+                let cycle: Vec<&str> = (0..passes.len()).filter(|i| !order.contains(i)).map(|i| passes[i].name).collect();
+                return Err(format!("passes depend on each other: {}", cycle.join(", ")));
+            }
+        }
+    }
+    Ok(order)
+}
+
+impl Target {
+    // the framebuffer or attachment holding the current frame
+    fn fbo(&self) -> u32 {
+        self.fbos[self.current.get()]
+    }
+
+    fn attachment(&self) -> u32 {
+        self.attachments[self.current.get()]
+    }
+
+    // the attachment holding the previous frame
+    fn previous(&self) -> u32 {
+        self.attachments[1 - self.current.get()]
+    }
+
+    fn count(&self) -> usize {
+        if self.desc.history { 2 } else { 1 }
+    }
+}
+
+impl RenderGraph {
+    // constructor, allocating the targets
+    pub fn new(
+        targets: Vec<TargetDesc>,
+        passes: Vec<Pass>,
+        width: u32,
+        height: u32,
+        memory_name: &str
+    ) -> Result<Self, String> {
+        let order = order_passes(&targets, &passes)?;
+        let mut result = Self {
+            width,
+            height,
+            targets: targets.into_iter().map(|desc| Target {
+                desc,
+                fbos: [0; 2],
+                attachments: [0; 2],
+                depth_stencils: [0; 2],
+                current: Cell::new(0),
+                filled: Cell::new(false),
+                written: Cell::new(false)
+            }).collect(),
+            passes,
+            order,
+            restore: Cell::new((0, [0; 4])),
+            memory_name: memory_name.to_string()
+        };
+        result.allocate();
+        Ok(result)
+    }
+
+    // reallocates the targets at a new size
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if self.width == width && self.height == height {
+            return;
+        }
+        self.free();
+        self.width = width;
+        self.height = height;
+        self.allocate();
+    }
+
+    // reallocates a target with another number of MSAA samples
+    pub fn set_samples(&mut self, name: &str, samples: u32) {
+        let index = self.index(name);
+        if self.targets[index].desc.samples == samples {
+            return;
+        }
+        self.free();
+        self.targets[index].desc.samples = samples;
+        self.allocate();
+    }
+
+    // whether a history target holds the previous frame, i.e. it was written last time
+    pub fn has_history(&self, name: &str) -> bool {
+        self.targets[self.index(name)].filled.get()
+    }
+
+    // binds a target to render into from outside the graph, e.g. the scene
+    pub fn begin_target(&self, name: &str) {
+        let target = &self.targets[self.index(name)];
+        unsafe {
+            let mut fbo = 0i32;
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut fbo);
+            let mut viewport = [0i32; 4];
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            self.restore.set((fbo, viewport));
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbo());
+            gl::Viewport(0, 0, self.width as _, self.height as _);
+        }
+    }
+
+    // restores the framebuffer and viewport bound before begin_target
+    pub fn end_target(&self) {
+        let (fbo, [x, y, width, height]) = self.restore.get();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo as _);
+            gl::Viewport(x, y, width, height);
+        }
+    }
+
+    // the texture of a target which isn't multisampled, holding what was last rendered into it
+    pub fn texture(&self, name: &str) -> u32 {
+        self.target(name).attachment()
+    }
+
+    // runs the enabled passes in order; draw passes are drawn by `draw` with their
+    // output and inputs bound
+    pub fn execute(&self, enabled: impl Fn(&Pass) -> bool, mut draw: impl FnMut(&Pass)) {
+        let (mut screen, mut viewport) = (0i32, [0i32; 4]);
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut screen);
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        for target in self.targets.iter() {
+            target.written.set(false);
+        }
+        for pass in self.order.iter().map(|i| &self.passes[*i]) {
+            if !enabled(pass) {
+                continue;
+            }
+            let _group = frame_capture::group(pass.name);
+            let (fbo, [x, y, width, height]) = match pass.output {
+                Output::Target(name) => (self.target(name).fbo(), [0, 0, self.width as i32, self.height as i32]),
+                Output::Screen => (screen as u32, viewport)
+            };
+            unsafe {
+                match pass.kind {
+                    PassKind::Blit => {
+                        let filter = if width as u32 == self.width && height as u32 == self.height { gl::NEAREST } else { gl::LINEAR };
+                        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.target(pass.inputs[0]).fbo());
+                        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, fbo);
+                        gl::BlitFramebuffer(0, 0, self.width as _, self.height as _, x, y, x + width, y + height, gl::COLOR_BUFFER_BIT, filter);
+                    }
+                    PassKind::Draw => {
+                        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+                        gl::Viewport(x, y, width, height);
+                        let textures = pass.inputs.iter().map(|it| self.target(it).attachment())
+                            .chain(pass.history.iter().map(|it| self.target(it).previous()));
+                        for (unit, texture) in textures.enumerate() {
+                            gl::ActiveTexture(gl::TEXTURE0 + unit as u32);
+                            gl::BindTexture(gl::TEXTURE_2D, texture);
+                        }
+                        gl::ActiveTexture(gl::TEXTURE0);
+                        draw(pass);
+                    }
+                }
+            }
+            if let Output::Target(name) = pass.output {
+                self.target(name).written.set(true);
+            }
+        }
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, screen as _);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        // what was written to history targets becomes their previous frame
+        for target in self.targets.iter().filter(|it| it.desc.history) {
+            if target.written.get() {
+                target.current.set(1 - target.current.get());
+            }
+            target.filled.set(target.written.get());
+        }
+    }
+
+    // size of all targets in GPU memory, in bytes
+    pub fn memory_size(&self) -> usize {
+        self.targets.iter()
+            .map(|it| {
+                // depth and stencil are packed into 4 bytes (DEPTH24_STENCIL8)
+                let bytes_per_pixel = it.desc.format.bytes_per_pixel() + if it.desc.stencil { 4 } else { 0 };
+                self.width as usize * self.height as usize * bytes_per_pixel * it.desc.samples.max(1) as usize * it.count()
+            })
+            .sum()
+    }
+
+    fn index(&self, name: &str) -> usize {
+        self.targets.iter().position(|it| it.desc.name == name)
+            .unwrap_or_else(|| panic!("Render target {} does not exist.", name))
+    }
+
+    fn target(&self, name: &str) -> &Target {
+        &self.targets[self.index(name)]
+    }
+
+    fn allocate(&mut self) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        for target in self.targets.iter_mut() {
+            let format = target.desc.format.internal_format();
+            for i in 0..target.count() {
+                unsafe {
+                    gl::GenFramebuffers(1, &mut target.fbos[i]);
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbos[i]);
+                    if target.desc.samples > 0 {
+                        gl::GenRenderbuffers(1, &mut target.attachments[i]);
+                        gl::BindRenderbuffer(gl::RENDERBUFFER, target.attachments[i]);
+                        gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, target.desc.samples as _, format, width, height);
+                        gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, target.attachments[i]);
+                    } else {
+                        let pixel_type = if target.desc.format == Format::Rgba16F { gl::FLOAT } else { gl::UNSIGNED_BYTE };
+                        gl::GenTextures(1, &mut target.attachments[i]);
+                        gl::BindTexture(gl::TEXTURE_2D, target.attachments[i]);
+                        gl::TexImage2D(gl::TEXTURE_2D, 0, format as _, width, height, 0, gl::RGBA, pixel_type, ptr::null());
+                        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+                        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+                        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+                        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+                        gl::BindTexture(gl::TEXTURE_2D, 0);
+                        gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, target.attachments[i], 0);
+                    }
+                    if target.desc.stencil {
+                        // multisampled like the colors, as attachments have to match
+                        gl::GenRenderbuffers(1, &mut target.depth_stencils[i]);
+                        gl::BindRenderbuffer(gl::RENDERBUFFER, target.depth_stencils[i]);
+                        gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, target.desc.samples as _, gl::DEPTH24_STENCIL8, width, height);
+                        gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, target.depth_stencils[i]);
+                    }
+                    if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+                        log::error!("ERROR::RENDERGRAPH: Failed to initialize the framebuffer of {}", target.desc.name);
+                    }
+                }
+                let attachment = if target.desc.samples > 0 { gl::RENDERBUFFER } else { gl::TEXTURE };
+                frame_capture::label(gl::FRAMEBUFFER, target.fbos[i], target.desc.name);
+                frame_capture::label(attachment, target.attachments[i], target.desc.name);
+            }
+            target.current.set(0);
+            target.filled.set(false);
+        }
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        resource_manager::track_gpu_memory(self.memory_name.as_str(), self.memory_size());
+    }
+
+    fn free(&mut self) {
+        for target in self.targets.iter_mut() {
+            for i in 0..target.count() {
+                unsafe {
+                    gl::DeleteFramebuffers(1, &target.fbos[i]);
+                    if target.desc.samples > 0 {
+                        gl::DeleteRenderbuffers(1, &target.attachments[i]);
+                    } else {
+                        gl::DeleteTextures(1, &target.attachments[i]);
+                    }
+                    if target.depth_stencils[i] != 0 {
+                        gl::DeleteRenderbuffers(1, &target.depth_stencils[i]);
+                    }
+                }
+            }
+            target.fbos = [0; 2];
+            target.attachments = [0; 2];
+            target.depth_stencils = [0; 2];
+        }
+        resource_manager::track_gpu_memory(self.memory_name.as_str(), 0);
+    }
+}
+
+impl Drop for RenderGraph {
+    fn drop(&mut self) {
+        self.free();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &'static str, samples: u32, history: bool) -> TargetDesc {
+        TargetDesc { name, format: Format::Rgba8, samples, history, stencil: false }
+    }
+
+    fn pass(name: &'static str, kind: PassKind, inputs: &[&'static str], history: &[&'static str], output: Output) -> Pass {
+        Pass { name, kind, inputs: inputs.to_vec(), history: history.to_vec(), output }
+    }
+
+    #[test]
+    fn render_graph_test_order() {
+        let targets = [target("ms", 4, false), target("a", 0, false), target("b", 0, true)];
+        let passes = [
+            pass("present", PassKind::Blit, &["b"], &[], Output::Screen),
+            pass("draw", PassKind::Draw, &["a"], &["b"], Output::Target("b")),
+            pass("resolve", PassKind::Blit, &["ms"], &[], Output::Target("a"))
+        ];
+        assert_eq!(order_passes(&targets, &passes), Ok(vec![2, 1, 0]));
+    }
+
+    #[test]
+    fn render_graph_test_invalid() {
+        let targets = [target("ms", 4, false), target("a", 0, false), target("b", 0, false)];
+        let cycle = [
+            pass("one", PassKind::Draw, &["a"], &[], Output::Target("b")),
+            pass("two", PassKind::Draw, &["b"], &[], Output::Target("a"))
+        ];
+        assert!(order_passes(&targets, &cycle).is_err());
+        let unknown = [pass("one", PassKind::Draw, &["c"], &[], Output::Screen)];
+        assert!(order_passes(&targets, &unknown).is_err());
+        let multisampled = [pass("one", PassKind::Draw, &["ms"], &[], Output::Screen)];
+        assert!(order_passes(&targets, &multisampled).is_err());
+        let no_history = [pass("one", PassKind::Draw, &["a"], &["b"], Output::Target("b"))];
+        assert!(order_passes(&targets, &no_history).is_err());
+    }
+}
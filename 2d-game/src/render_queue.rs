@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::{RangeBounds, RangeTo};
+
+// Drawing is queued onto layers instead of depending on the order of the calls that draw:
+// the layers are drawn in the order below, the draws of a layer by their sort key and then
+// in the order they were queued in.
+
+/// Where a draw ends up in the frame, back to front.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    // the background image and what drifts in front of it
+    Background,
+    // the level and its ceiling
+    Bricks,
+    // particles and trails
+    Effects,
+    // the ball, the paddle and the falling power-ups
+    Ball,
+    // drawn over the post-processed game, e.g. the weather
+    Foreground,
+    // texts, meters and menus
+    Hud,
+    // overlays for developers
+    Debug
+}
+
+// the layers the post-processor gets to see
+pub const SCENE: RangeTo<Layer> = ..Layer::Foreground;
+
+struct Item<'a> {
+    layer: Layer,
+    key: i32,
+    draw: Box<dyn FnOnce() + 'a>
+}
+
+/// The draws of a frame, waiting to be flushed.
+#[derive(Default)]
+pub struct RenderQueue<'a> {
+    items: Vec<Item<'a>>
+}
+
+impl<'a> RenderQueue<'a> {
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    pub fn push(&mut self, layer: Layer, draw: impl FnOnce() + 'a) {
+        self.push_keyed(layer, 0, draw);
+    }
+
+    // draws with a lower key are drawn first within the layer
+    pub fn push_keyed(&mut self, layer: Layer, key: i32, draw: impl FnOnce() + 'a) {
+        self.items.push(Item { layer, key, draw: Box::new(draw) });
+    }
+
+    // runs the draws queued onto the given layers, leaving those of the others queued
+    pub fn flush(&mut self, layers: impl RangeBounds<Layer>) {
+        let (mut flushed, kept): (Vec<_>, Vec<_>) = self.items.drain(..).partition(|item| layers.contains(&item.layer));
+        self.items = kept;
+        // the sort is stable, so equal keys keep the order they were queued in
+        flushed.sort_by_key(|item| (item.layer, item.key));
+        for item in flushed {
+            (item.draw)();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn render_queue_test_order() {
+        let drawn = RefCell::new(Vec::new());
+        let mut queue = RenderQueue::new();
+        let drawn_ref = &drawn;
+        let draw = |name: &'static str| move || drawn_ref.borrow_mut().push(name);
+        queue.push(Layer::Hud, draw("score"));
+        queue.push(Layer::Ball, draw("ball"));
+        queue.push(Layer::Background, draw("background"));
+        queue.push_keyed(Layer::Ball, -1, draw("paddle"));
+        queue.push(Layer::Ball, draw("ghost"));
+        queue.push(Layer::Foreground, draw("rain"));
+        queue.flush(SCENE);
+        assert_eq!(*drawn.borrow(), vec!["background", "paddle", "ball", "ghost"]);
+        assert_eq!(queue.len(), 2);
+        queue.flush(..);
+        assert_eq!(*drawn.borrow(), vec!["background", "paddle", "ball", "ghost", "rain", "score"]);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::difficulty::{BallSpeed, Difficulty};
+use crate::drops;
+use crate::drops::DropTable;
+use crate::brick_type;
+use crate::controller::{PaddleController, View};
+use crate::game::{BRICKS_FILE, DROPS_FILE, LEVEL_FILES, POWER_UP_TYPES};
+use crate::simulation::{Simulation, StepInput};
+
+// Replays are recorded input traces of a level, stepped through the headless Simulation.
+// Each trace carries the hash of the state it ended in when it was recorded; a replay
+// ending in another state means the rules of the game changed. The shipped traces in
+// resources/replays are checked by the tests below.
+
+// directory the shipped replays are stored in
+pub const REPLAYS_DIR: &str = "resources/replays";
+
+/// An input trace as stored in a replay file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Trace {
+    // 1-based number of the level played
+    pub level: usize,
+    // seed of the drop rolls
+    pub seed: u64,
+    // simulation steps per second
+    pub rate: u32,
+    // sets how quickly the ball speeds up
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    // runs of steps with the same input, e.g. "right+launch 20" ("-" holds nothing)
+    pub inputs: Vec<String>,
+    // hash of the state after the last step
+    #[serde(default)]
+    pub hash: String
+}
+
+fn format_input(input: StepInput) -> String {
+    let names: Vec<&str> = [(input.left, "left"), (input.right, "right"), (input.launch, "launch"), (input.dash, "dash"),
+        (input.tilt_left, "tilt-left"), (input.tilt_right, "tilt-right")]
+        .iter()
+        .filter(|it| it.0)
+        .map(|it| it.1)
+        .collect();
+    if names.is_empty() { "-".to_string() } else { names.join("+") }
+}
+
+fn parse_input(keys: &str) -> Result<StepInput, String> {
+    let mut input = StepInput::default();
+    if keys == "-" {
+        return Ok(input);
+    }
+    for key in keys.split('+') {
+        match key {
+            "left" => input.left = true,
+            "right" => input.right = true,
+            "launch" => input.launch = true,
+            "dash" => input.dash = true,
+            "tilt-left" => input.tilt_left = true,
+            "tilt-right" => input.tilt_right = true,
+            _ => return Err(format!("unknown input \"{}\"", key))
+        }
+    }
+    Ok(input)
+}
+
+impl Trace {
+    pub fn new(level: usize, seed: u64, rate: u32) -> Self {
+        Self {
+            level,
+            seed,
+            rate,
+            ..Self::default()
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let trace: Trace = toml::from_str(source).map_err(|e| e.to_string())?;
+        if trace.level == 0 || trace.level > LEVEL_FILES.len() {
+            return Err(format!("there is no level {}", trace.level));
+        }
+        if trace.rate == 0 {
+            return Err("the rate must be positive".to_string());
+        }
+        trace.steps()?;
+        Ok(trace)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+
+    // appends the input of the next step, extending the last run if it is the same
+    pub fn record(&mut self, input: StepInput) {
+        let keys = format_input(input);
+        if let Some(last) = self.inputs.last_mut() {
+            if let Some((last_keys, count)) = last.split_once(' ') {
+                if last_keys == keys {
+                    let count: u32 = count.parse().unwrap_or(0);
+                    *last = format!("{} {}", keys, count + 1);
+                    return;
+                }
+            }
+        }
+        self.inputs.push(format!("{} 1", keys));
+    }
+
+    // the input of each step
+    pub fn steps(&self) -> Result<Vec<StepInput>, String> {
+        let mut steps = Vec::new();
+        for (i, run) in self.inputs.iter().enumerate() {
+            let invalid = || format!("input {} (\"{}\") is not \"<keys> <steps>\"", i + 1, run);
+            let (keys, count) = run.split_once(' ').ok_or_else(invalid)?;
+            let count: usize = count.trim().parse().map_err(|_| invalid())?;
+            let input = parse_input(keys).map_err(|e| format!("input {}: {}", i + 1, e))?;
+            steps.extend(std::iter::repeat_n(input, count));
+        }
+        Ok(steps)
+    }
+}
+
+/// Feeds the inputs of a trace to the paddle of the game, a step at a time (nothing once they run out).
+pub struct ReplayController {
+    steps: Vec<StepInput>,
+    next: usize
+}
+
+impl ReplayController {
+    pub fn new(trace: &Trace) -> Result<Self, String> {
+        Ok(Self { steps: trace.steps()?, next: 0 })
+    }
+}
+
+impl PaddleController for ReplayController {
+    fn control(&mut self, _view: &View, _dt: f32) -> StepInput {
+        let input = self.steps.get(self.next).copied().unwrap_or_default();
+        self.next += 1;
+        input
+    }
+}
+
+// registers the brick types and loads the drop table the game plays with; resource paths are
+// relative to `root` (the working directory of the game)
+pub fn load_rules(root: &Path) -> Result<DropTable, String> {
+    let drops_source = fs::read_to_string(root.join(DROPS_FILE)).map_err(|e| format!("{}: {}", DROPS_FILE, e))?;
+    let power_up_names: Vec<&str> = POWER_UP_TYPES.iter().map(|it| it.0).collect();
+    // the same brick types as in the game
+    let bricks_source = fs::read_to_string(root.join(BRICKS_FILE)).map_err(|e| format!("{}: {}", BRICKS_FILE, e))?;
+    let brick_types = brick_type::parse_types(bricks_source.as_str(), &power_up_names).map_err(|e| format!("{}: {}", BRICKS_FILE, e))?;
+    for (code, brick_type) in brick_types {
+        brick_type::register(code, brick_type);
+    }
+    drops::parse_table(drops_source.as_str(), &power_up_names).map_err(|e| format!("{}: {}", DROPS_FILE, e))
+}
+
+// plays the trace back; resource paths are relative to `root`
+pub fn run(trace: &Trace, root: &Path) -> Result<Simulation, String> {
+    let level_file = root.join(LEVEL_FILES[trace.level - 1]);
+    let drops = load_rules(root)?;
+    let mut simulation = Simulation::new(level_file.to_string_lossy().as_ref(), trace.level, drops, trace.seed, 800, 600)
+        .map_err(|e| e.to_string())?;
+    simulation.ball_speed = BallSpeed::new(trace.difficulty.speed_rules());
+    simulation.catching = trace.difficulty.catches_ball();
+    let dt = 1.0 / trace.rate as f32;
+    for input in trace.steps()? {
+        simulation.step(dt, input);
+    }
+    Ok(simulation)
+}
+
+// plays the replay file back and compares the final state against its hash
+pub fn check(file: &Path, root: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(file).map_err(|e| e.to_string())?;
+    let trace = Trace::parse(source.as_str())?;
+    let hash = format!("{:016x}", run(&trace, root)?.state_hash());
+    if hash != trace.hash {
+        return Err(format!("ended in state {} instead of {}", hash, trace.hash));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+    }
+
+    #[test]
+    fn replay_test_record() {
+        let mut trace = Trace::new(1, 7, 120);
+        let launch = StepInput { launch: true, ..StepInput::default() };
+        let right = StepInput { right: true, launch: true, ..StepInput::default() };
+        for input in [launch, launch, StepInput::default(), right, right, right] {
+            trace.record(input);
+        }
+        assert_eq!(trace.inputs, vec!["launch 2", "- 1", "right+launch 3"]);
+        let parsed = Trace::parse(trace.to_toml().as_str()).expect("The recorded trace should be valid.");
+        assert_eq!(parsed.steps().unwrap(), vec![launch, launch, StepInput::default(), right, right, right]);
+    }
+
+    #[test]
+    fn replay_test_invalid_trace() {
+        assert!(Trace::parse("level = 9\nseed = 1\nrate = 120\ninputs = []").is_err());
+        assert!(Trace::parse("level = 1\nseed = 1\nrate = 120\ninputs = [\"jump 3\"]").is_err());
+        assert!(Trace::parse("level = 1\nseed = 1\nrate = 120\ninputs = [\"left\"]").is_err());
+    }
+
+    #[test]
+    fn replay_test_deterministic() {
+        let mut trace = Trace::new(2, 99, 120);
+        for i in 0..2400 {
+            trace.record(StepInput { left: i % 240 < 100, right: i % 240 >= 140, launch: true, dash: i % 240 == 150, tilt_left: i % 480 < 120, tilt_right: false });
+        }
+        let one = run(&trace, root()).unwrap();
+        let two = run(&trace, root()).unwrap();
+        assert_eq!(one.steps, 2400);
+        assert_eq!(one.state_hash(), two.state_hash());
+    }
+
+    #[test]
+    fn replay_test_golden() {
+        let mut files: Vec<_> = fs::read_dir(root().join(REPLAYS_DIR))
+            .expect("The replays directory should exist.")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|it| it == "toml"))
+            .collect();
+        files.sort();
+        assert!(!files.is_empty(), "There should be replays to check.");
+        let failures: Vec<String> = files.iter()
+            .filter_map(|file| check(file, root()).err().map(|e| format!("{}: {}", file.display(), e)))
+            .collect();
+        // if the change of the rules is intended, put the new hashes into the replay files
+        assert!(failures.is_empty(), "Replays diverged:\n{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+
+// Resources are looked up by name many times per frame. Names are interned once into a
+// ResName, a small id, so the lookups hash and compare integers instead of allocating and
+// comparing strings. The interned strings live as long as the program; there are only as many
+// as there are distinct resource names.
+
+struct Interner {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, u32>
+}
+
+lazy_static! {
+    static ref INTERNER: Mutex<Interner> = Mutex::new(Interner { names: Vec::new(), ids: HashMap::new() });
+}
+
+/// An interned resource name.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResName(u32);
+
+impl ResName {
+    // interns the given name, yielding the same ResName for equal names
+    pub fn new(name: &str) -> Self {
+        let mut interner = INTERNER.lock().unwrap();
+        if let Some(id) = interner.ids.get(name) {
+            return Self(*id);
+        }
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        let id = interner.names.len() as u32;
+        interner.names.push(name);
+        interner.ids.insert(name, id);
+        Self(id)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        INTERNER.lock().unwrap().names[self.0 as usize]
+    }
+}
+
+impl From<&str> for ResName {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<&String> for ResName {
+    fn from(name: &String) -> Self {
+        Self::new(name.as_str())
+    }
+}
+
+impl From<String> for ResName {
+    fn from(name: String) -> Self {
+        Self::new(name.as_str())
+    }
+}
+
+impl fmt::Display for ResName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for ResName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ResName({:?})", self.as_str())
+    }
+}
+
+// the ResName of a string literal, interned the first time the expression is evaluated
+macro_rules! res_name {
+    ($name:literal) => {{
+        static NAME: std::sync::OnceLock<$crate::res_name::ResName> = std::sync::OnceLock::new();
+        *NAME.get_or_init(|| $crate::res_name::ResName::new($name))
+    }};
+}
+pub(crate) use res_name;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn res_name_test_interning() {
+        let a = ResName::new("res_name_test_a");
+        let b = ResName::from("res_name_test_b".to_string());
+        assert_ne!(a, b);
+        assert_eq!(a, ResName::from("res_name_test_a"));
+        assert_eq!(b.as_str(), "res_name_test_b");
+        assert_eq!(format!("{}", a), "res_name_test_a");
+        // the macro interns once per call site and yields the same name
+        let names: Vec<ResName> = (0..3).map(|_| res_name!("res_name_test_a")).collect();
+        assert!(names.iter().all(|it| *it == a));
+    }
+}
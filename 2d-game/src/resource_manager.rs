@@ -16,22 +16,78 @@
 
 use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
 use lazy_static::lazy_static;
 use learnopengl_shared::util;
+use crate::builtin;
+use crate::fallback;
+use crate::frame_capture;
+use crate::material::Material;
+use crate::nine_slice;
+use crate::nine_slice::NineSlice;
+use crate::res_name::{res_name, ResName};
+use crate::shader;
 use crate::shader::Shader;
+use crate::shader_cache;
 use crate::texture::Texture2D;
 
 // A static singleton ResourceManager class that hosts several
 // functions to load Textures and Shaders. Each loaded texture
 // and/or shader is also stored for future reference by string
 // handles. All functions and resources are static and no
-// public constructor is defined.
+// public constructor is defined. The handles are interned
+// (see res_name), so prefer res_name! for names looked up every frame.
 
 // resource storage
 lazy_static! {
-    static ref TEXTURES: Mutex<HashMap<String, Texture2D>> = Mutex::new(HashMap::new());
-    static ref SHADERS: Mutex<HashMap<String, Shader>> = Mutex::new(HashMap::new());
+    static ref TEXTURES: Mutex<HashMap<ResName, Texture2D>> = Mutex::new(HashMap::new());
+    static ref SHADERS: Mutex<HashMap<ResName, Shader>> = Mutex::new(HashMap::new());
+    // the files (and defines) the stored shaders were built from, to recreate them
+    static ref SHADER_SOURCES: Mutex<HashMap<ResName, ShaderSource>> = Mutex::new(HashMap::new());
+    static ref MATERIALS: Mutex<HashMap<ResName, Material>> = Mutex::new(HashMap::new());
+    // GPU memory bookkeeping of the textures, by texture id
+    static ref RESIDENCY: Mutex<HashMap<u32, TextureRecord>> = Mutex::new(HashMap::new());
+    // GPU memory used outside of the stored textures (framebuffers, glyphs), by owner
+    static ref OTHER_MEMORY: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+    // textures loaded from file are evicted once all resident textures exceed this many bytes
+    static ref TEXTURE_BUDGET: Mutex<Option<usize>> = Mutex::new(None);
+    // nine-slice margins of textures, by texture name
+    static ref SLICES: Mutex<HashMap<ResName, NineSlice>> = Mutex::new(HashMap::new());
+    // the active texture set; its textures (named "<set>/<name>") replace the regular ones
+    static ref TEXTURE_SET: Mutex<Option<ResName>> = Mutex::new(None);
+    // the full names of the textures of all sets, by set and regular name
+    static ref VARIANTS: Mutex<HashMap<(ResName, ResName), ResName>> = Mutex::new(HashMap::new());
+}
+
+// number of the frame being rendered, used to find the least recently used textures
+static FRAME: AtomicU64 = AtomicU64::new(0);
+
+struct ShaderSource {
+    vertex: String,
+    fragment: String,
+    geometry: Option<String>,
+    defines: Vec<(String, String)>
+}
+
+struct TextureRecord {
+    texture: Texture2D,
+    // the file the texture can be reloaded from; textures without one are never evicted
+    file: Option<String>,
+    // the image of generated textures, to recreate them
+    pixels: Option<Vec<u8>>,
+    last_used: u64,
+    resident: bool
+}
+
+/// GPU memory in use, in bytes.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GpuMemory {
+    pub textures: usize,
+    pub other: usize,
+    // number of textures currently evicted
+    pub evicted: usize
 }
 
 // loads (and generates) a shader program from file loading vertex, fragment (and geometry) shader's source code. If gShaderFile is not nullptr, it also loads a geometry shader
@@ -39,34 +95,304 @@ pub fn load_shader(
     v_shader_file: &str,
     f_shader_file: &str,
     g_shader_file: Option<&str>,
-    name: String
+    name: impl Into<ResName>
 ) -> Shader {
+    load_shader_ex(v_shader_file, f_shader_file, g_shader_file, name, &[])
+}
+
+// like load_shader, additionally injecting the given #defines into each stage
+pub fn load_shader_ex(
+    v_shader_file: &str,
+    f_shader_file: &str,
+    g_shader_file: Option<&str>,
+    name: impl Into<ResName>,
+    defines: &[(&str, &str)]
+) -> Shader {
+    let name = name.into();
     let mut shaders = SHADERS.lock().unwrap();
-    shaders.entry(name.clone()).or_insert(load_shader_from_file(v_shader_file, f_shader_file, g_shader_file));
-    shaders[&name]
+    *shaders.entry(name).or_insert_with(|| {
+        let shader = load_shader_from_file(v_shader_file, f_shader_file, g_shader_file, defines);
+        frame_capture::label(gl::PROGRAM, shader.id, name.as_str());
+        SHADER_SOURCES.lock().unwrap().insert(name, ShaderSource {
+            vertex: v_shader_file.to_string(),
+            fragment: f_shader_file.to_string(),
+            geometry: g_shader_file.map(|it| it.to_string()),
+            defines: defines.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+        });
+        shader
+    })
 }
 
 // retrieves a stored sader
-pub fn get_shader(name: String) -> Shader {
+pub fn get_shader(name: impl Into<ResName>) -> Shader {
+    let name = name.into();
     let shaders = SHADERS.lock().unwrap();
-    shaders[&name]
+    match shaders.get(&name) {
+        Some(shader) => *shader,
+        None => unloaded("shader", name, shaders.keys())
+    }
 }
 
 // loads (and generates) a texture from file
 pub fn load_texture(
     file: &str,
     alpha: bool,
-    name: String
+    name: impl Into<ResName>
 ) -> Texture2D {
+    let name = name.into();
     let mut textures = TEXTURES.lock().unwrap();
-    textures.entry(name.clone()).or_insert(load_texture_from_file(file, alpha));
-    textures[&name]
+    textures.entry(name).or_insert(load_texture_from_file(file, alpha));
+    frame_capture::label(gl::TEXTURE, textures[&name].id, name.as_str());
+    textures[&name].clone()
 }
 
-// retrieves a stored texture
-pub fn get_texture(name: String) -> Texture2D {
+// stores an already generated texture
+pub fn add_texture(name: impl Into<ResName>, texture: Texture2D) -> Texture2D {
+    store_texture(name.into(), texture, None)
+}
+
+// stores a texture generated from the given image, which is kept to recreate it
+pub fn add_generated_texture(name: impl Into<ResName>, texture: Texture2D, pixels: Vec<u8>) -> Texture2D {
+    store_texture(name.into(), texture, Some(pixels))
+}
+
+fn store_texture(name: ResName, texture: Texture2D, pixels: Option<Vec<u8>>) -> Texture2D {
+    register_texture(texture.clone(), None, pixels);
+    frame_capture::label(gl::TEXTURE, texture.id, name.as_str());
+    if let Some((set, regular)) = name.as_str().split_once('/') {
+        VARIANTS.lock().unwrap().insert((ResName::new(set), ResName::new(regular)), name);
+    }
+    let mut textures = TEXTURES.lock().unwrap();
+    textures.insert(name, texture.clone());
+    texture
+}
+
+// creates a plain white texture, for drawing single-colored rectangles
+pub fn load_white_texture(name: impl Into<ResName>) -> Texture2D {
+    let mut texture = Texture2D::new();
+    texture.internal_format = gl::RGBA;
+    texture.image_format = gl::RGBA;
+    texture.generate(builtin::WHITE_TEXTURE_SIZE, builtin::WHITE_TEXTURE_SIZE, &builtin::WHITE_TEXTURE);
+    add_generated_texture(name, texture, builtin::WHITE_TEXTURE.to_vec())
+}
+
+// retrieves a stored texture, preferring the variant of the active texture set
+// (unknown textures are replaced by the placeholder)
+pub fn get_texture(name: impl Into<ResName>) -> Texture2D {
+    let name = name.into();
+    find_texture(name).unwrap_or_else(|| {
+        fallback::report("texture", name.as_str(), "it was never loaded");
+        placeholder_texture()
+    })
+}
+
+// the magenta checkerboard shown in place of missing textures, created when first needed
+pub fn placeholder_texture() -> Texture2D {
+    if let Some(texture) = find_texture(res_name!("placeholder")) {
+        return texture;
+    }
+    let size = fallback::CHECKERBOARD_SIZE;
+    let mut texture = Texture2D::new();
+    texture.internal_format = gl::RGBA;
+    texture.image_format = gl::RGBA;
+    let pixels = fallback::checkerboard(size, fallback::CHECKER_SIZE);
+    texture.generate(size, size, &pixels);
+    add_generated_texture(res_name!("placeholder"), texture, pixels)
+}
+
+// retrieves a stored texture if it exists, preferring the variant of the active texture set
+pub fn find_texture(name: impl Into<ResName>) -> Option<Texture2D> {
+    let name = name.into();
     let textures = TEXTURES.lock().unwrap();
-    textures[&name]
+    if let Some(set) = *TEXTURE_SET.lock().unwrap() {
+        if let Some(variant) = VARIANTS.lock().unwrap().get(&(set, name)) {
+            return textures.get(variant).cloned();
+        }
+    }
+    textures.get(&name).cloned()
+}
+
+// selects the texture set used by get_texture (None selects the regular textures)
+pub fn set_texture_set(set: Option<&str>) {
+    *TEXTURE_SET.lock().unwrap() = set.map(ResName::new);
+}
+
+// stores a material under the given name, replacing a previous one
+pub fn add_material(name: impl Into<ResName>, material: Material) {
+    MATERIALS.lock().unwrap().insert(name.into(), material);
+}
+
+// retrieves a stored material if it exists
+pub fn find_material(name: impl Into<ResName>) -> Option<Material> {
+    MATERIALS.lock().unwrap().get(&name.into()).cloned()
+}
+
+// loads the nine-slice margins from a descriptor file
+pub fn load_slices(file: &str) {
+    let slices = nine_slice::load_descriptors(file);
+    SLICES.lock().unwrap().extend(slices.into_iter().map(|(name, slice)| (ResName::from(name), slice)));
+}
+
+// retrieves the nine-slice margins of a texture if it has any
+pub fn find_slice(name: impl Into<ResName>) -> Option<NineSlice> {
+    SLICES.lock().unwrap().get(&name.into()).copied()
+}
+
+// fails the lookup of a resource that was never loaded; debug builds also list what was
+pub fn unloaded<'a>(kind: &str, name: ResName, loaded: impl Iterator<Item = &'a ResName>) -> ! {
+    if cfg!(debug_assertions) {
+        let mut loaded: Vec<&str> = loaded.map(|it| it.as_str()).collect();
+        loaded.sort();
+        panic!("ERROR::RESOURCE_MANAGER: Unknown {} {} (loaded: {})", kind, name, loaded.join(", "));
+    }
+    panic!("ERROR::RESOURCE_MANAGER: Unknown {} {}", kind, name)
+}
+
+// sets the budget of resident textures in bytes (None for no budget)
+pub fn set_texture_budget(bytes: Option<usize>) {
+    *TEXTURE_BUDGET.lock().unwrap() = bytes;
+}
+
+// records the GPU memory used by something other than a stored texture, replacing the previous amount
+pub fn track_gpu_memory(owner: &str, bytes: usize) {
+    OTHER_MEMORY.lock().unwrap().insert(owner.to_string(), bytes);
+}
+
+pub fn gpu_memory() -> GpuMemory {
+    let records = RESIDENCY.lock().unwrap();
+    GpuMemory {
+        textures: records.values().filter(|r| r.resident).map(|r| r.texture.memory_size()).sum(),
+        other: OTHER_MEMORY.lock().unwrap().values().sum(),
+        evicted: records.values().filter(|r| !r.resident).count()
+    }
+}
+
+// marks a texture as used by the current frame, reloading it first if it was evicted
+// (called whenever a texture is bound)
+pub fn touch_texture(id: u32) {
+    let mut records = RESIDENCY.lock().unwrap();
+    let Some(record) = records.get_mut(&id) else {
+        return;
+    };
+    record.last_used = FRAME.load(Ordering::Relaxed);
+    if !record.resident {
+        if let Some(file) = record.file.as_ref() {
+            log::debug!("RESOURCE_MANAGER: Reloading evicted texture {}", file);
+            let (width, height, pixels) = load_image(file);
+            record.texture.generate(width, height, pixels.as_slice());
+        }
+        record.resident = true;
+    }
+}
+
+// number of the frame being rendered
+pub fn frame() -> u64 {
+    FRAME.load(Ordering::Relaxed)
+}
+
+// finishes a frame, evicting the least recently used textures while over the budget
+pub fn end_frame() {
+    let frame = FRAME.fetch_add(1, Ordering::Relaxed);
+    let Some(budget) = *TEXTURE_BUDGET.lock().unwrap() else {
+        return;
+    };
+    let mut records = RESIDENCY.lock().unwrap();
+    let total = records.values().filter(|r| r.resident).map(|r| r.texture.memory_size()).sum();
+    let candidates: Vec<(u32, usize, u64)> = records.iter()
+        .filter(|(_, r)| r.resident && r.file.is_some())
+        .map(|(id, r)| (*id, r.texture.memory_size(), r.last_used))
+        .collect();
+    for id in select_evictions(&candidates, total, budget, frame) {
+        let record = records.get_mut(&id).unwrap();
+        unsafe {
+            // keep the texture name so copies of the texture stay valid, just drop the storage
+            gl::BindTexture(gl::TEXTURE_2D, record.texture.gl_id());
+            gl::TexImage2D(gl::TEXTURE_2D, 0, record.texture.internal_format as _, 0, 0, 0, record.texture.image_format, gl::UNSIGNED_BYTE, std::ptr::null());
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        record.resident = false;
+        log::debug!("RESOURCE_MANAGER: Evicted texture {}", record.file.as_ref().unwrap());
+    }
+}
+
+// picks the least recently used of the candidates (id, size, last used frame) to evict until
+// the total size of the resident textures fits the budget; textures used this frame are kept
+fn select_evictions(candidates: &[(u32, usize, u64)], total: usize, budget: usize, frame: u64) -> Vec<u32> {
+    let mut candidates: Vec<&(u32, usize, u64)> = candidates.iter()
+        .filter(|(_, _, last_used)| *last_used < frame)
+        .collect();
+    candidates.sort_by_key(|(id, _, last_used)| (*last_used, *id));
+    let mut total = total;
+    let mut evicted = Vec::new();
+    for (id, size, _) in candidates {
+        if total <= budget {
+            break;
+        }
+        total -= size;
+        evicted.push(*id);
+    }
+    evicted
+}
+
+// recreates the stored shaders and textures from their files (or the kept images of the
+// generated textures) with new OpenGL objects, e.g. after the context lost them; copies of them
+// keep working. Returns how many were recreated
+pub fn recreate_all() -> usize {
+    let shaders = SHADERS.lock().unwrap();
+    let sources = SHADER_SOURCES.lock().unwrap();
+    let mut records = RESIDENCY.lock().unwrap();
+    // all old objects go first, so the new ones may reuse their names
+    for shader in shaders.values() {
+        unsafe {
+            gl::DeleteProgram(shader.program());
+        }
+        shader::forget_uniforms(shader.program());
+    }
+    for record in records.values().filter(|r| r.texture.is_owned()) {
+        unsafe {
+            gl::DeleteTextures(1, &record.texture.gl_id());
+        }
+    }
+    let mut count = 0;
+    for (name, shader) in shaders.iter() {
+        let Some(source) = sources.get(name) else {
+            continue;
+        };
+        let defines: Vec<(&str, &str)> = source.defines.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        let recreated = load_shader_from_file(source.vertex.as_str(), source.fragment.as_str(), source.geometry.as_deref(), &defines);
+        shader::replace_program(shader, recreated.id);
+        frame_capture::label(gl::PROGRAM, recreated.id, name.as_str());
+        count += 1;
+    }
+    for record in records.values_mut() {
+        if !record.texture.replace_object() {
+            continue;
+        }
+        let (width, height) = (record.texture.width, record.texture.height);
+        if let Some(file) = record.file.as_ref() {
+            let (width, height, pixels) = load_image(file);
+            record.texture.generate(width, height, pixels.as_slice());
+        } else {
+            record.texture.generate(width, height, record.pixels.as_deref().unwrap_or(&[]));
+        }
+        record.resident = true;
+        count += 1;
+    }
+    log::info!("RESOURCE_MANAGER: Recreated {} shaders and textures", count);
+    count
+}
+
+// generated textures can't be loaded again, so they are registered with their image
+fn register_texture(texture: Texture2D, file: Option<String>, pixels: Option<Vec<u8>>) {
+    let id = texture.id;
+    let record = TextureRecord {
+        texture,
+        file,
+        pixels,
+        last_used: FRAME.load(Ordering::Relaxed),
+        resident: true
+    };
+    RESIDENCY.lock().unwrap().entry(id).or_insert(record);
 }
 
 // properly de-allocates all loaded resources
@@ -75,43 +401,71 @@ pub fn clear() {
     let shaders = SHADERS.lock().unwrap();
     for (_, shader) in shaders.iter() {
         unsafe {
-            gl::DeleteProgram(shader.id);
-        }
-    }
-    // (properly) delete all textures
-    let textures = TEXTURES.lock().unwrap();
-    for (_, texture) in textures.iter() {
-        unsafe {
-            gl::DeleteProgram(texture.id);
+            gl::DeleteProgram(shader.program());
         }
+        shader::forget_uniforms(shader.program());
     }
+    SHADER_SOURCES.lock().unwrap().clear();
+    MATERIALS.lock().unwrap().clear();
+    SLICES.lock().unwrap().clear();
+    RESIDENCY.lock().unwrap().clear();
+    OTHER_MEMORY.lock().unwrap().clear();
+    // the textures are deleted along with the last handle to them
+    TEXTURES.lock().unwrap().clear();
+    VARIANTS.lock().unwrap().clear();
 }
 
 // loads and generates a shader from file
 fn load_shader_from_file(
     v_shader_file: &str,
     f_shader_file: &str,
-    g_shader_file: Option<&str>
+    g_shader_file: Option<&str>,
+    defines: &[(&str, &str)]
 ) -> Shader {
     // 1. retrieve the vertex/fragment source code from filePath
-    let vertex_code = fs::read_to_string(v_shader_file)
-        .expect("ERROR::SHADER: Failed to read shader files");
-    let fragment_code = fs::read_to_string(f_shader_file)
-        .expect("ERROR::SHADER: Failed to read shader files");
+    let vertex_code = read_shader_source(v_shader_file, defines);
+    let fragment_code = read_shader_source(f_shader_file, defines);
     let geometry_code;
     match g_shader_file {
         Some(g_shader_file) => {
-            geometry_code = Some(fs::read_to_string(g_shader_file)
-                .expect("ERROR::SHADER: Failed to read shader files"));
+            geometry_code = Some(read_shader_source(g_shader_file, defines));
         }
         None => {
             geometry_code = None;
         }
     }
-    // 2. now create shader object from source code
-    let mut shader = Shader::new();
-    shader.compile(vertex_code, fragment_code, geometry_code);
-    shader
+    // 2. now create shader object from source code (or load the cached program)
+    shader_cache::load_or_compile(vertex_code, fragment_code, geometry_code)
+}
+
+// reads a shader's source and preprocesses it; includes are resolved relative to the shader's directory
+// (missing files are replaced by the built-in ones)
+fn read_shader_source(file: &str, defines: &[(&str, &str)]) -> String {
+    let read = |file: &str| fs::read_to_string(file).or_else(|e| match builtin::shader_source(file) {
+        Some(source) => {
+            fallback::report("shader", file, e.to_string().as_str());
+            Ok(source.to_string())
+        }
+        None => Err(e.to_string())
+    });
+    let source = read(file)
+        .unwrap_or_else(|e| panic!("ERROR::SHADER: Failed to read {}: {}", file, e));
+    let dir = Path::new(file).parent().unwrap_or(Path::new("."));
+    let resolve = |name: &str| read(dir.join(name).to_string_lossy().as_ref());
+    shader::preprocess(source.as_str(), defines, &resolve)
+        .unwrap_or_else(|e| panic!("ERROR::SHADER: Failed to preprocess {}: {}", file, e))
+}
+
+// the RGBA image of a texture file, or the placeholder if it can't be loaded (any more)
+fn load_image(file: &str) -> (u32, u32, Vec<u8>) {
+    match util::image::load_image_data_rgba_without_flip(file.to_string()) {
+        Ok(img) => (img.width(), img.height(), img.into_raw()),
+        Err(e) => {
+            fallback::report("texture", file, e.to_string().as_str());
+            let size = fallback::CHECKERBOARD_SIZE;
+            (size, size, fallback::checkerboard(size, fallback::CHECKER_SIZE))
+        }
+    }
 }
 
 // loads a single texture from file
@@ -123,12 +477,52 @@ fn load_texture_from_file(file: &str, alpha: bool) -> Texture2D {
         texture.image_format = gl::RGBA;
     }
     // load image
-    let img = util::image::load_image_data_rgba_without_flip(file.to_string())
-        .unwrap();
+    let Ok(img) = util::image::load_image_data_rgba_without_flip(file.to_string()).inspect_err(|e| {
+        fallback::report("texture", file, e.to_string().as_str());
+    }) else {
+        // the placeholder can't be reloaded from the file, so it is kept like a generated texture
+        texture.internal_format = gl::RGBA;
+        texture.image_format = gl::RGBA;
+        let pixels = fallback::checkerboard(fallback::CHECKERBOARD_SIZE, fallback::CHECKER_SIZE);
+        texture.generate(fallback::CHECKERBOARD_SIZE, fallback::CHECKERBOARD_SIZE, &pixels);
+        register_texture(texture.clone(), None, Some(pixels));
+        return texture;
+    };
     let width = img.width();
     let height = img.height();
     let data = img.as_raw();
     // now generate texture
     texture.generate(width, height, data.as_slice());
+    register_texture(texture.clone(), Some(file.to_string()), None);
     texture
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_evictions_test_case() {
+        let candidates = [(1, 100, 5), (2, 100, 3), (3, 100, 9), (4, 100, 8)];
+        // over budget by 150: the two least recently used go
+        assert_eq!(select_evictions(&candidates, 450, 300, 10), vec![2, 1]);
+        assert!(select_evictions(&candidates, 300, 300, 10).is_empty());
+        // textures used in the current frame are never evicted
+        assert_eq!(select_evictions(&candidates, 1000, 0, 8), vec![2, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown shader resource_manager_test_missing")]
+    fn resource_manager_test_unloaded_lookup() {
+        get_shader(ResName::new("resource_manager_test_missing"));
+    }
+
+    #[test]
+    fn resource_manager_test_texture_set() {
+        add_texture("resource_manager_test/block", Texture2D::empty());
+        add_material("resource_manager_test", Material::new(Shader::new()));
+        assert!(find_material(ResName::new("resource_manager_test")).is_some());
+        assert!(find_texture("resource_manager_test/block").is_some());
+        assert!(VARIANTS.lock().unwrap().contains_key(&(ResName::new("resource_manager_test"), ResName::new("block"))));
+    }
 }
\ No newline at end of file
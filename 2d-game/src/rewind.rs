@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+
+// Rewind keeps the most recent states of the simulation in a ring buffer. While scrubbing,
+// a cursor walks through the recorded states; resuming continues from the state under the
+// cursor and drops the ones after it, as that future is played anew.
+pub struct Rewind<T> {
+    frames: VecDeque<T>,
+    // number of states kept; the oldest one is dropped once it is exceeded
+    capacity: usize,
+    // index of the state shown while scrubbing
+    cursor: Option<usize>
+}
+
+impl<T> Rewind<T> {
+    // constructor
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            cursor: None
+        }
+    }
+
+    // records the state of a simulation step (ignored while scrubbing)
+    pub fn record(&mut self, frame: T) {
+        if self.cursor.is_some() {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    pub fn is_scrubbing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    // starts scrubbing at the most recent state, yielding it
+    pub fn start(&mut self) -> Option<&T> {
+        self.cursor = self.frames.len().checked_sub(1);
+        self.current()
+    }
+
+    // moves the cursor by the given number of states (negative is backwards in time), yielding the state under it
+    pub fn scrub(&mut self, steps: i32) -> Option<&T> {
+        let cursor = self.cursor?;
+        let last = self.frames.len() as i64 - 1;
+        self.cursor = Some((cursor as i64 + steps as i64).clamp(0, last) as usize);
+        self.current()
+    }
+
+    // stops scrubbing; the states after the cursor are dropped
+    pub fn resume(&mut self) {
+        if let Some(cursor) = self.cursor.take() {
+            self.frames.truncate(cursor + 1);
+        }
+    }
+
+    // the state under the cursor
+    pub fn current(&self) -> Option<&T> {
+        self.frames.get(self.cursor?)
+    }
+
+    // how many steps the state under the cursor lies behind the most recent one
+    pub fn offset(&self) -> usize {
+        self.cursor.map(|cursor| self.frames.len() - 1 - cursor).unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.cursor = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewind_test_capacity() {
+        let mut rewind = Rewind::new(3);
+        for i in 0..5 {
+            rewind.record(i);
+        }
+        assert_eq!(rewind.len(), 3);
+        assert_eq!(rewind.start(), Some(&4));
+        assert_eq!(rewind.scrub(-10), Some(&2));
+    }
+
+    #[test]
+    fn rewind_test_scrub_and_resume() {
+        let mut rewind = Rewind::new(10);
+        assert_eq!(rewind.start(), None);
+        assert!(!rewind.is_scrubbing());
+        for i in 0..6 {
+            rewind.record(i);
+        }
+        rewind.start();
+        assert_eq!(rewind.scrub(-3), Some(&2));
+        assert_eq!(rewind.offset(), 3);
+        assert_eq!(rewind.scrub(1), Some(&3));
+        assert_eq!(rewind.scrub(5), Some(&5));
+        assert_eq!(rewind.scrub(-2), Some(&3));
+        // nothing is recorded while scrubbing
+        rewind.record(100);
+        assert_eq!(rewind.len(), 6);
+        rewind.resume();
+        assert!(!rewind.is_scrubbing());
+        assert_eq!(rewind.len(), 4);
+        rewind.record(6);
+        assert_eq!(rewind.start(), Some(&6));
+        assert_eq!(rewind.scrub(-1), Some(&3));
+    }
+}
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// points awarded for each destroyed brick (unless its brick type is worth more or less)
+pub const BRICK_POINTS: u32 = 10;
+// combo at which fever starts
+pub const FEVER_COMBO: u32 = 8;
+// points multiplier while in fever
+const FEVER_MULTIPLIER: u32 = 2;
+
+/// Points and combo of the current run. The combo counts the bricks destroyed
+/// since the ball last touched the paddle; once it reaches FEVER_COMBO the game
+/// enters fever, which doubles all points until the ball hits the paddle again.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Score {
+    pub points: u32,
+    pub combo: u32,
+    pub fever: bool
+}
+
+impl Score {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // counts a destroyed brick worth the given points, returning the points awarded for it
+    pub fn brick_destroyed(&mut self, brick_points: u32) -> u32 {
+        self.combo += 1;
+        if self.combo >= FEVER_COMBO {
+            self.fever = true;
+        }
+        let points = if self.fever { brick_points * FEVER_MULTIPLIER } else { brick_points };
+        self.points += points;
+        points
+    }
+
+    // ends the combo (and fever), e.g. when the ball touches the paddle or is lost
+    pub fn end_combo(&mut self) {
+        self.combo = 0;
+        self.fever = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_test_fever_doubles_points() {
+        let mut score = Score::new();
+        for _ in 0..FEVER_COMBO - 1 {
+            assert_eq!(score.brick_destroyed(BRICK_POINTS), BRICK_POINTS);
+        }
+        assert!(!score.fever);
+        // the brick reaching the threshold already counts double
+        assert_eq!(score.brick_destroyed(BRICK_POINTS), BRICK_POINTS * FEVER_MULTIPLIER);
+        assert!(score.fever);
+        assert_eq!(score.points, (FEVER_COMBO - 1) * BRICK_POINTS + BRICK_POINTS * FEVER_MULTIPLIER);
+        score.end_combo();
+        assert!(!score.fever);
+        assert_eq!(score.combo, 0);
+        assert_eq!(score.brick_destroyed(BRICK_POINTS), BRICK_POINTS);
+    }
+}
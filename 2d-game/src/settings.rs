@@ -0,0 +1,398 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::difficulty::{Difficulty, DIFFICULTIES};
+use crate::display::{DisplayMode, DISPLAY_MODES};
+use crate::gl_debug::GlDebugSettings;
+use crate::post_processor::{ToneMapping, TONE_MAPPINGS};
+
+/// Player settings, stored in the settings file. Missing entries take their default value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // how quickly the ball speeds up
+    pub difficulty: Difficulty,
+    // windowed, fullscreen or borderless fullscreen
+    pub display_mode: DisplayMode,
+    // colorblind-safe brick palette with a distinct pattern per brick type
+    pub colorblind: bool,
+    // strength of the screen shake when hitting solid bricks (0.0 - 1.0)
+    pub screen_shake: f32,
+    // photosensitivity mode: no screen shake and toned down chaos/confuse effects
+    pub photosensitive: bool,
+    // scale of all HUD text and icons (1.0 - 2.0)
+    pub ui_scale: f32,
+    // the game world is rendered at this multiple of the window size and downsampled (1.0 - 2.0)
+    pub render_scale: f32,
+    // how the HDR scene is mapped to the screen, and its brightness before that
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32,
+    // CRT look (the crt theme of resources/levels/themes.toml) in place of the level themes
+    pub retro_filter: bool,
+    // HUD text in saturated colors on a solid backdrop
+    pub high_contrast: bool,
+    // assist mode: marks where the ball will reach paddle height
+    pub ghost_ball: bool,
+    // assist mode: the game runs at this fraction of its speed (0.5 - 1.0); menus and music don't slow down
+    pub game_speed: f32,
+    // on-screen speedrun timer with splits per level
+    pub speedrun_timer: bool,
+    // minimap of the remaining bricks in a corner of the HUD
+    pub minimap: bool,
+    // show the tutorial when playing the first level; cleared once it's been completed
+    // (only editable in the settings file)
+    pub tutorial: bool,
+    // budget of the textures in GPU memory in MiB, 0 for no budget (only editable in the settings file)
+    pub texture_budget_mb: u32,
+    // filters for the GL debug output (only editable in the settings file)
+    pub gl_debug: GlDebugSettings
+}
+
+// step the screen shake changes by in the options screen
+const SCREEN_SHAKE_STEP: f32 = 0.25;
+// range and step of the UI scale
+const UI_SCALE_MIN: f32 = 1.0;
+const UI_SCALE_MAX: f32 = 2.0;
+const UI_SCALE_STEP: f32 = 0.25;
+// range and step of the render scale (the slider shares the range of the UI scale)
+const RENDER_SCALE_MIN: f32 = 1.0;
+const RENDER_SCALE_MAX: f32 = 2.0;
+const RENDER_SCALE_STEP: f32 = 0.5;
+// range and step of the exposure
+const EXPOSURE_MIN: f32 = 0.5;
+const EXPOSURE_MAX: f32 = 2.0;
+const EXPOSURE_STEP: f32 = 0.25;
+// range and step of the game speed
+const GAME_SPEED_MIN: f32 = 0.5;
+const GAME_SPEED_MAX: f32 = 1.0;
+const GAME_SPEED_STEP: f32 = 0.1;
+
+/// The settings which can be changed in the options screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Setting {
+    Difficulty,
+    DisplayMode,
+    Colorblind,
+    ScreenShake,
+    Photosensitive,
+    UiScale,
+    RenderScale,
+    ToneMapping,
+    Exposure,
+    RetroFilter,
+    HighContrast,
+    GhostBall,
+    GameSpeed,
+    SpeedrunTimer,
+    Minimap
+}
+
+// all settings, in the order they are listed in the options screen
+pub const SETTINGS: [Setting; 15] = [
+    Setting::Difficulty,
+    Setting::DisplayMode,
+    Setting::Colorblind,
+    Setting::ScreenShake,
+    Setting::Photosensitive,
+    Setting::UiScale,
+    Setting::RenderScale,
+    Setting::ToneMapping,
+    Setting::Exposure,
+    Setting::RetroFilter,
+    Setting::HighContrast,
+    Setting::GhostBall,
+    Setting::GameSpeed,
+    Setting::SpeedrunTimer,
+    Setting::Minimap
+];
+
+/// Value of a setting as shown in the options screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SettingValue {
+    Toggle(bool),
+    Percent(f32),
+    // a value within a range (min, max)
+    Scale(f32, f32, f32),
+    // one of several named options (translated as options.choice_<id>), with its index and count
+    Choice(&'static str, usize, usize)
+}
+
+impl SettingValue {
+    // position of the value within its range, as shown by a slider
+    pub fn fraction(self) -> f32 {
+        match self {
+            SettingValue::Toggle(value) => if value { 1.0 } else { 0.0 },
+            SettingValue::Percent(value) => value,
+            SettingValue::Scale(value, min, max) => (value - min) / (max - min),
+            SettingValue::Choice(_, index, count) => index as f32 / (count - 1).max(1) as f32
+        }
+    }
+}
+
+impl Setting {
+    // identifier used for the translated setting names
+    pub fn id(self) -> &'static str {
+        match self {
+            Setting::Difficulty => "difficulty",
+            Setting::DisplayMode => "display_mode",
+            Setting::Colorblind => "colorblind",
+            Setting::ScreenShake => "screen_shake",
+            Setting::Photosensitive => "photosensitive",
+            Setting::UiScale => "ui_scale",
+            Setting::RenderScale => "render_scale",
+            Setting::ToneMapping => "tone_mapping",
+            Setting::Exposure => "exposure",
+            Setting::RetroFilter => "retro_filter",
+            Setting::HighContrast => "high_contrast",
+            Setting::GhostBall => "ghost_ball",
+            Setting::GameSpeed => "game_speed",
+            Setting::SpeedrunTimer => "speedrun_timer",
+            Setting::Minimap => "minimap"
+        }
+    }
+}
+
+impl Settings {
+    // changes a setting by one step in the given direction (-1 or 1); toggles flip either way
+    pub fn adjust(&mut self, setting: Setting, direction: i32) {
+        match setting {
+            Setting::Difficulty => {
+                let index = (self.difficulty.index() as i32 + direction).rem_euclid(DIFFICULTIES.len() as i32);
+                self.difficulty = DIFFICULTIES[index as usize];
+            }
+            Setting::DisplayMode => {
+                let index = (self.display_mode.index() as i32 + direction).rem_euclid(DISPLAY_MODES.len() as i32);
+                self.display_mode = DISPLAY_MODES[index as usize];
+            }
+            Setting::Colorblind => self.colorblind = !self.colorblind,
+            Setting::ScreenShake => {
+                // wraps around when stepping past either end
+                let steps = (1.0 / SCREEN_SHAKE_STEP) as i32 + 1;
+                let current = (self.screen_shake / SCREEN_SHAKE_STEP).round() as i32;
+                self.screen_shake = (current + direction).rem_euclid(steps) as f32 * SCREEN_SHAKE_STEP;
+            }
+            Setting::Photosensitive => self.photosensitive = !self.photosensitive,
+            Setting::UiScale => {
+                let steps = ((UI_SCALE_MAX - UI_SCALE_MIN) / UI_SCALE_STEP) as i32 + 1;
+                let current = ((self.ui_scale - UI_SCALE_MIN) / UI_SCALE_STEP).round() as i32;
+                self.ui_scale = UI_SCALE_MIN + (current + direction).rem_euclid(steps) as f32 * UI_SCALE_STEP;
+            }
+            Setting::RenderScale => {
+                let steps = ((RENDER_SCALE_MAX - RENDER_SCALE_MIN) / RENDER_SCALE_STEP) as i32 + 1;
+                let current = ((self.render_scale - RENDER_SCALE_MIN) / RENDER_SCALE_STEP).round() as i32;
+                self.render_scale = RENDER_SCALE_MIN + (current + direction).rem_euclid(steps) as f32 * RENDER_SCALE_STEP;
+            }
+            Setting::ToneMapping => {
+                let index = (self.tone_mapping.index() as i32 + direction).rem_euclid(TONE_MAPPINGS.len() as i32);
+                self.tone_mapping = TONE_MAPPINGS[index as usize];
+            }
+            Setting::Exposure => {
+                let steps = ((EXPOSURE_MAX - EXPOSURE_MIN) / EXPOSURE_STEP) as i32 + 1;
+                let current = ((self.exposure - EXPOSURE_MIN) / EXPOSURE_STEP).round() as i32;
+                self.exposure = EXPOSURE_MIN + (current + direction).rem_euclid(steps) as f32 * EXPOSURE_STEP;
+            }
+            Setting::RetroFilter => self.retro_filter = !self.retro_filter,
+            Setting::HighContrast => self.high_contrast = !self.high_contrast,
+            Setting::GhostBall => self.ghost_ball = !self.ghost_ball,
+            Setting::GameSpeed => {
+                let steps = ((GAME_SPEED_MAX - GAME_SPEED_MIN) / GAME_SPEED_STEP).round() as i32 + 1;
+                let current = ((self.game_speed - GAME_SPEED_MIN) / GAME_SPEED_STEP).round() as i32;
+                self.game_speed = GAME_SPEED_MIN + (current + direction).rem_euclid(steps) as f32 * GAME_SPEED_STEP;
+            }
+            Setting::SpeedrunTimer => self.speedrun_timer = !self.speedrun_timer,
+            Setting::Minimap => self.minimap = !self.minimap
+        }
+    }
+
+    // the effective shake strength; photosensitivity mode disables shaking entirely
+    pub fn shake_intensity(&self) -> f32 {
+        if self.photosensitive {
+            0.0
+        } else {
+            self.screen_shake.clamp(0.0, 1.0)
+        }
+    }
+
+    pub fn value(&self, setting: Setting) -> SettingValue {
+        match setting {
+            Setting::Difficulty => SettingValue::Choice(self.difficulty.id(), self.difficulty.index(), DIFFICULTIES.len()),
+            Setting::DisplayMode => SettingValue::Choice(self.display_mode.id(), self.display_mode.index(), DISPLAY_MODES.len()),
+            Setting::Colorblind => SettingValue::Toggle(self.colorblind),
+            Setting::ScreenShake => SettingValue::Percent(self.screen_shake),
+            Setting::Photosensitive => SettingValue::Toggle(self.photosensitive),
+            Setting::UiScale => SettingValue::Scale(self.ui_scale, UI_SCALE_MIN, UI_SCALE_MAX),
+            Setting::RenderScale => SettingValue::Scale(self.render_scale, RENDER_SCALE_MIN, RENDER_SCALE_MAX),
+            Setting::ToneMapping => SettingValue::Choice(self.tone_mapping.id(), self.tone_mapping.index(), TONE_MAPPINGS.len()),
+            Setting::Exposure => SettingValue::Scale(self.exposure, EXPOSURE_MIN, EXPOSURE_MAX),
+            Setting::RetroFilter => SettingValue::Toggle(self.retro_filter),
+            Setting::HighContrast => SettingValue::Toggle(self.high_contrast),
+            Setting::GhostBall => SettingValue::Toggle(self.ghost_ball),
+            Setting::GameSpeed => SettingValue::Scale(self.game_speed, GAME_SPEED_MIN, GAME_SPEED_MAX),
+            Setting::SpeedrunTimer => SettingValue::Toggle(self.speedrun_timer),
+            Setting::Minimap => SettingValue::Toggle(self.minimap)
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut settings: Self = toml::from_str(source).map_err(|e| e.to_string())?;
+        // hand-edited files may hold values outside of the supported ranges
+        settings.screen_shake = settings.screen_shake.clamp(0.0, 1.0);
+        settings.ui_scale = settings.ui_scale.clamp(UI_SCALE_MIN, UI_SCALE_MAX);
+        settings.render_scale = settings.render_scale.clamp(RENDER_SCALE_MIN, RENDER_SCALE_MAX);
+        settings.exposure = settings.exposure.clamp(EXPOSURE_MIN, EXPOSURE_MAX);
+        settings.game_speed = settings.game_speed.clamp(GAME_SPEED_MIN, GAME_SPEED_MAX);
+        Ok(settings)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+
+    // loads the settings from a file, falling back to the defaults if it doesn't exist or is invalid
+    pub fn load(path: &str) -> Self {
+        let Ok(source) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        Self::parse(source.as_str()).unwrap_or_else(|e| {
+            log::error!("SETTINGS: Failed to parse {}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::Normal,
+            display_mode: DisplayMode::Windowed,
+            colorblind: false,
+            screen_shake: 1.0,
+            photosensitive: false,
+            ui_scale: 1.0,
+            render_scale: 1.0,
+            tone_mapping: ToneMapping::None,
+            exposure: 1.0,
+            retro_filter: false,
+            high_contrast: false,
+            ghost_ball: false,
+            game_speed: 1.0,
+            speedrun_timer: false,
+            minimap: false,
+            tutorial: true,
+            texture_budget_mb: 0,
+            gl_debug: GlDebugSettings::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_test_round_trip() {
+        let mut settings = Settings::default();
+        settings.adjust(Setting::Colorblind, 1);
+        assert!(settings.colorblind);
+        assert_eq!(Settings::parse(settings.to_toml().as_str()).unwrap(), settings);
+        // missing entries take their defaults
+        assert_eq!(Settings::parse("").unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn settings_test_screen_shake() {
+        let mut settings = Settings::default();
+        settings.adjust(Setting::ScreenShake, 1);
+        assert_eq!(settings.screen_shake, 0.0, "Stepping past 100% wraps around to 0%.");
+        settings.adjust(Setting::ScreenShake, -1);
+        settings.adjust(Setting::ScreenShake, -1);
+        assert_eq!(settings.screen_shake, 0.75);
+        settings.adjust(Setting::Photosensitive, 1);
+        assert_eq!(settings.shake_intensity(), 0.0);
+    }
+
+    #[test]
+    fn settings_test_game_speed() {
+        let mut settings = Settings::default();
+        settings.adjust(Setting::GameSpeed, -1);
+        assert!((settings.game_speed - 0.9).abs() < 1e-5);
+        settings.adjust(Setting::GameSpeed, 1);
+        settings.adjust(Setting::GameSpeed, 1);
+        assert_eq!(settings.game_speed, 0.5, "Stepping past 1x wraps around to 0.5x.");
+        assert_eq!(Settings::parse("game_speed = 0.1").unwrap().game_speed, 0.5);
+    }
+
+    #[test]
+    fn settings_test_ui_scale() {
+        let mut settings = Settings::default();
+        settings.adjust(Setting::UiScale, -1);
+        assert_eq!(settings.ui_scale, 2.0);
+        settings.adjust(Setting::UiScale, -1);
+        assert_eq!(settings.ui_scale, 1.75);
+        let parsed = Settings::parse("ui_scale = 5.0").unwrap();
+        assert_eq!(parsed.ui_scale, 2.0);
+        settings.adjust(Setting::RenderScale, 1);
+        assert_eq!(settings.render_scale, 1.5);
+        settings.adjust(Setting::RenderScale, 1);
+        settings.adjust(Setting::RenderScale, 1);
+        assert_eq!(settings.render_scale, 1.0, "Stepping past 2x wraps around to 1x.");
+    }
+
+    #[test]
+    fn settings_test_tone_mapping() {
+        let mut settings = Settings::default();
+        settings.adjust(Setting::ToneMapping, -1);
+        assert_eq!(settings.tone_mapping, ToneMapping::Aces);
+        assert_eq!(settings.value(Setting::ToneMapping).fraction(), 1.0);
+        settings.adjust(Setting::Exposure, 1);
+        assert_eq!(settings.exposure, 1.25);
+        let parsed = Settings::parse("tone_mapping = \"reinhard\"\nexposure = 10.0").unwrap();
+        assert_eq!(parsed.tone_mapping, ToneMapping::Reinhard);
+        assert_eq!(parsed.exposure, EXPOSURE_MAX);
+    }
+
+    #[test]
+    fn settings_test_display_mode() {
+        let mut settings = Settings::default();
+        settings.adjust(Setting::DisplayMode, -1);
+        assert_eq!(settings.display_mode, DisplayMode::Borderless);
+        settings.adjust(Setting::DisplayMode, 1);
+        assert_eq!(settings.display_mode, DisplayMode::Windowed);
+        let parsed = Settings::parse("display_mode = \"fullscreen\"").unwrap();
+        assert_eq!(parsed.display_mode, DisplayMode::Fullscreen);
+    }
+
+    #[test]
+    fn settings_test_difficulty() {
+        let mut settings = Settings::default();
+        settings.adjust(Setting::Difficulty, 1);
+        assert_eq!(settings.difficulty, Difficulty::Hard);
+        settings.adjust(Setting::Difficulty, 1);
+        assert_eq!(settings.difficulty, Difficulty::Easy, "Stepping past hard wraps around to easy.");
+        let parsed = Settings::parse("difficulty = \"hard\"").unwrap();
+        assert_eq!(parsed.difficulty, Difficulty::Hard);
+    }
+}
@@ -16,8 +16,114 @@
 
 extern crate nalgebra_glm as glm;
 
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use gl::types::GLenum;
+use lazy_static::lazy_static;
+
+// location and type of an active uniform; the type is 0 for uniforms looked up lazily
+#[derive(Copy, Clone, Debug)]
+struct UniformInfo {
+    location: i32,
+    gltype: GLenum
+}
+
+// uniform locations of all linked programs, by program id and uniform name
+lazy_static! {
+    static ref UNIFORMS: Mutex<HashMap<u32, HashMap<String, UniformInfo>>> = Mutex::new(HashMap::new());
+    // programs which replaced those of recreated shaders, by shader key
+    static ref RECREATED: Mutex<HashMap<u32, u32>> = Mutex::new(HashMap::new());
+}
+
+// whether any shader was recreated, so lookups of the others can skip RECREATED
+static ANY_RECREATED: AtomicBool = AtomicBool::new(false);
+static NEXT_KEY: AtomicU32 = AtomicU32::new(1);
+
+// makes all copies of the shader use the given program from now on (for recreating it)
+pub fn replace_program(shader: &Shader, program: u32) {
+    RECREATED.lock().unwrap().insert(shader.key, program);
+    ANY_RECREATED.store(true, Ordering::Relaxed);
+}
+
+// GL types accepted by set_integer (which is also used for bools and samplers)
+const INTEGER_TYPES: [GLenum; 4] = [gl::INT, gl::BOOL, gl::UNSIGNED_INT, gl::SAMPLER_2D];
+
+// name of an array uniform without its index, as reported by glGetActiveUniform ("offsets[0]" -> "offsets")
+fn array_base_name(name: &str) -> Option<&str> {
+    name.strip_suffix("[0]")
+}
+
+// forgets the cached uniforms of a deleted program
+pub fn forget_uniforms(program: u32) {
+    UNIFORMS.lock().unwrap().remove(&program);
+}
+
+// nesting depth after which includes are assumed to be recursive
+const MAX_INCLUDE_DEPTH: u32 = 16;
+
+// GLSL preprocessing: replaces `#include "file"` lines with the file's source (each file is
+// included only once) and injects the given defines right after the #version directive.
+// `resolve` returns the source of an included file.
+pub fn preprocess(
+    source: &str,
+    defines: &[(&str, &str)],
+    resolve: &dyn Fn(&str) -> Result<String, String>
+) -> Result<String, String> {
+    let mut included = Vec::new();
+    let source = expand_includes(source, resolve, &mut included, 0)?;
+    if defines.is_empty() {
+        return Ok(source);
+    }
+    let define_lines: String = defines.iter().map(|(name, value)| format!("#define {} {}\n", name, value)).collect();
+    // the #version directive has to stay the first statement of the shader
+    let mut result = String::new();
+    let mut injected = false;
+    for line in source.lines() {
+        result.push_str(line);
+        result.push('\n');
+        if !injected && line.trim_start().starts_with("#version") {
+            result.push_str(define_lines.as_str());
+            injected = true;
+        }
+    }
+    if !injected {
+        result.insert_str(0, define_lines.as_str());
+    }
+    Ok(result)
+}
+
+fn expand_includes(
+    source: &str,
+    resolve: &dyn Fn(&str) -> Result<String, String>,
+    included: &mut Vec<String>,
+    depth: u32
+) -> Result<String, String> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err("includes are nested too deeply (recursive include?)".to_string());
+    }
+    let mut result = String::new();
+    for line in source.lines() {
+        let Some(rest) = line.trim_start().strip_prefix("#include") else {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        };
+        let name = rest.trim();
+        let Some(name) = name.strip_prefix('"').and_then(|it| it.strip_suffix('"')) else {
+            return Err(format!("malformed include: {}", line.trim()));
+        };
+        if included.iter().any(|it| it == name) {
+            continue;
+        }
+        included.push(name.to_string());
+        let included_source = resolve(name).map_err(|e| format!("failed to include \"{}\": {}", name, e))?;
+        result.push_str(expand_includes(included_source.as_str(), resolve, included, depth + 1)?.as_str());
+    }
+    Ok(result)
+}
 
 // General purpose shader object. Compiles from file, generates
 // compile/link-time error messages and hosts several utility
@@ -25,21 +131,32 @@ use std::ptr;
 #[derive(Copy, Clone)]
 pub struct Shader {
     // state
-    pub id: u32
+    pub id: u32,
+    // identifies the shader (and its copies) across recreations of its program
+    key: u32
 }
 
 impl Shader {
     // constructor
     pub fn new() -> Self {
         Self {
-            id: 0
+            id: 0,
+            key: NEXT_KEY.fetch_add(1, Ordering::Relaxed)
         }
     }
 
+    // the program currently behind the shader; differs from id once the shader was recreated
+    pub fn program(&self) -> u32 {
+        if !ANY_RECREATED.load(Ordering::Relaxed) {
+            return self.id;
+        }
+        RECREATED.lock().unwrap().get(&self.key).copied().unwrap_or(self.id)
+    }
+
     // sets the current shader as active
     pub fn use_shader(&self) -> &Self {
         unsafe {
-            gl::UseProgram(self.id);
+            gl::UseProgram(self.program());
         }
         self
     }
@@ -50,6 +167,17 @@ impl Shader {
         vertex_source: String,
         fragment_source: String,
         geometry_source: Option<String> // note: geometry source code is optional
+    ) {
+        self.compile_ex(vertex_source, fragment_source, geometry_source, false);
+    }
+
+    // like compile; `retrievable` asks the driver to keep the program binary around for the shader cache
+    pub fn compile_ex(
+        &mut self,
+        vertex_source: String,
+        fragment_source: String,
+        geometry_source: Option<String>,
+        retrievable: bool
     ) {
         unsafe {
             // vertex Shader
@@ -80,8 +208,12 @@ impl Shader {
             if let Some(g_shader) = g_shader {
                 gl::AttachShader(self.id, g_shader);
             }
+            if retrievable {
+                gl::ProgramParameteri(self.id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as i32);
+            }
             gl::LinkProgram(self.id);
             Self::check_compile_errors(self.id, "PROGRAM".to_string());
+            self.cache_uniforms();
             // delete the shaders as they're linked into our program now and no longer necessary
             gl::DeleteShader(s_vertex);
             gl::DeleteShader(s_fragment);
@@ -91,6 +223,54 @@ impl Shader {
         }
     }
 
+    // queries the locations of all active uniforms of the linked program
+    pub fn cache_uniforms(&self) {
+        let mut uniforms = HashMap::new();
+        let mut count = 0;
+        let id = self.program();
+        unsafe {
+            gl::GetProgramiv(id, gl::ACTIVE_UNIFORMS, &mut count);
+            for index in 0..count.max(0) as u32 {
+                let mut name = [0u8; 256];
+                let mut length = 0;
+                let mut size = 0;
+                let mut gltype = 0;
+                gl::GetActiveUniform(id, index, name.len() as i32, &mut length, &mut size, &mut gltype, name.as_mut_ptr() as *mut _);
+                let name = String::from_utf8_lossy(&name[..length.max(0) as usize]).into_owned();
+                let c_string = CString::new(name.as_str()).unwrap();
+                let info = UniformInfo { location: gl::GetUniformLocation(id, c_string.as_ptr()), gltype };
+                if let Some(base) = array_base_name(name.as_str()) {
+                    uniforms.insert(base.to_string(), info);
+                }
+                uniforms.insert(name, info);
+            }
+        }
+        UNIFORMS.lock().unwrap().insert(id, uniforms);
+    }
+
+    // location of a uniform; in debug builds its type is checked against the expected types
+    pub fn uniform_location(&self, name: &str, expected: &[GLenum]) -> i32 {
+        let id = self.program();
+        let mut uniforms = UNIFORMS.lock().unwrap();
+        let program = uniforms.entry(id).or_default();
+        // active uniforms were cached at link time, so only the others allocate their name
+        let info = match program.get(name) {
+            Some(info) => *info,
+            None => {
+                // not active (e.g. optimized out), so there is no type to check
+                let c_string = CString::new(name).unwrap();
+                let info = UniformInfo { location: unsafe { gl::GetUniformLocation(id, c_string.as_ptr()) }, gltype: 0 };
+                program.insert(name.to_string(), info);
+                info
+            }
+        };
+        debug_assert!(
+            info.gltype == 0 || expected.contains(&info.gltype),
+            "ERROR::SHADER: Uniform {} has type {:#x}, expected one of {:x?}", name, info.gltype, expected
+        );
+        info.location
+    }
+
     // checks if compilation or linking failed and if so, print the error logs
     fn check_compile_errors(object: u32, type_str: String) {
         let mut success = 0i32;
@@ -127,9 +307,8 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::Uniform1f(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), value);
+            gl::Uniform1f(self.uniform_location(name, &[gl::FLOAT]), value);
         }
     }
 
@@ -141,9 +320,8 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::Uniform1i(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), value);
+            gl::Uniform1i(self.uniform_location(name, &INTEGER_TYPES), value);
         }
     }
 
@@ -155,9 +333,8 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::Uniform2f(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), x, y);
+            gl::Uniform2f(self.uniform_location(name, &[gl::FLOAT_VEC2]), x, y);
         }
     }
 
@@ -169,9 +346,8 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::Uniform2f(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), value.x, value.y);
+            gl::Uniform2f(self.uniform_location(name, &[gl::FLOAT_VEC2]), value.x, value.y);
         }
     }
 
@@ -183,9 +359,8 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::Uniform3f(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), x, y, z);
+            gl::Uniform3f(self.uniform_location(name, &[gl::FLOAT_VEC3]), x, y, z);
         }
     }
 
@@ -197,9 +372,8 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::Uniform3f(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), value.x, value.y, value.z);
+            gl::Uniform3f(self.uniform_location(name, &[gl::FLOAT_VEC3]), value.x, value.y, value.z);
         }
     }
 
@@ -211,9 +385,8 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::Uniform4f(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), x, y, z, w);
+            gl::Uniform4f(self.uniform_location(name, &[gl::FLOAT_VEC4]), x, y, z, w);
         }
     }
 
@@ -225,9 +398,8 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::Uniform4f(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), value.x, value.y, value.z, value.w);
+            gl::Uniform4f(self.uniform_location(name, &[gl::FLOAT_VEC4]), value.x, value.y, value.z, value.w);
         }
     }
 
@@ -239,9 +411,57 @@ impl Shader {
         if use_shader {
             self.use_shader();
         }
-        let name_c_string = CString::new(name).unwrap();
         unsafe {
-            gl::UniformMatrix4fv(gl::GetUniformLocation(self.id, name_c_string.as_ptr()), 1, gl::FALSE, &glm::value_ptr(&matrix)[0]);
+            gl::UniformMatrix4fv(self.uniform_location(name, &[gl::FLOAT_MAT4]), 1, gl::FALSE, &glm::value_ptr(&matrix)[0]);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(name: &str) -> Result<String, String> {
+        match name {
+            "common.glsl" => Ok("uniform bool shake;".to_string()),
+            "nested.glsl" => Ok("#include \"common.glsl\"\nuniform float time;".to_string()),
+            _ => Err("not found".to_string())
+        }
+    }
+
+    #[test]
+    fn preprocess_test_include() {
+        let source = "#version 330 core\n#include \"nested.glsl\"\n#include \"common.glsl\"\nvoid main() {}";
+        let result = preprocess(source, &[], &resolve).unwrap();
+        assert_eq!(result, "#version 330 core\nuniform bool shake;\nuniform float time;\nvoid main() {}\n");
+        assert!(preprocess("#include \"missing.glsl\"", &[], &resolve).unwrap_err().contains("missing.glsl"));
+        assert!(preprocess("#include common.glsl", &[], &resolve).is_err());
+    }
+
+    #[test]
+    fn array_base_name_test_case() {
+        assert_eq!(array_base_name("offsets[0]"), Some("offsets"));
+        assert_eq!(array_base_name("offsets[1]"), None);
+        assert_eq!(array_base_name("time"), None);
+    }
+
+    #[test]
+    fn preprocess_test_defines() {
+        let source = "// license\n#version 330 core\nvoid main() {}";
+        let result = preprocess(source, &[("PARTICLE_SCALE", "10.0"), ("MAX_LIGHTS", "4")], &resolve).unwrap();
+        assert_eq!(result, "// license\n#version 330 core\n#define PARTICLE_SCALE 10.0\n#define MAX_LIGHTS 4\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn shader_test_replace_program() {
+        let mut shader = Shader::new();
+        shader.id = 5;
+        let copy = shader;
+        let mut other = Shader::new();
+        other.id = 5;
+        replace_program(&shader, 9);
+        // copies follow the recreated program, other shaders keep theirs
+        assert_eq!(copy.program(), 9);
+        assert_eq!(other.program(), 5);
+    }
+}
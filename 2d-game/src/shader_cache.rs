@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::CStr;
+use std::fs;
+use std::ptr;
+use learnopengl_shared::filesystem;
+use crate::shader::Shader;
+
+// Cache of linked shader program binaries. Entries are keyed by a hash over the shader
+// sources and the GL driver strings, so a driver update or an edited shader simply
+// misses the cache and the program is compiled again.
+
+// directory the program binaries are stored in
+const CACHE_DIR: &str = "userdata/shader_cache";
+// first bytes of each cache file
+const MAGIC: &[u8; 4] = b"SPC1";
+
+// 64-bit FNV-1a over the sources (and driver), each terminated by a zero byte
+pub fn cache_key(sources: &[&str], driver: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for part in sources.iter().chain([driver].iter()) {
+        for byte in part.bytes().chain([0u8]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+// cache file layout: magic, binary format, binary length, binary
+pub fn encode_entry(format: u32, binary: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(12 + binary.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&format.to_le_bytes());
+    data.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+    data.extend_from_slice(binary);
+    data
+}
+
+pub fn decode_entry(data: &[u8]) -> Option<(u32, &[u8])> {
+    if data.len() < 12 || &data[0..4] != MAGIC {
+        return None;
+    }
+    let format = u32::from_le_bytes(data[4..8].try_into().ok()?);
+    let length = u32::from_le_bytes(data[8..12].try_into().ok()?) as usize;
+    let binary = data.get(12..12 + length)?;
+    Some((format, binary))
+}
+
+fn gl_string(name: gl::types::GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(string as *const _).to_string_lossy().into_owned()
+    }
+}
+
+// program binaries need GL 4.1 or ARB_get_program_binary, which report at least one format
+fn supported() -> bool {
+    let mut formats = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+    }
+    formats > 0
+}
+
+fn load_binary(data: &[u8]) -> Option<Shader> {
+    let (format, binary) = decode_entry(data)?;
+    let mut shader = Shader::new();
+    let mut success = 0;
+    unsafe {
+        shader.id = gl::CreateProgram();
+        gl::ProgramBinary(shader.id, format, binary.as_ptr() as *const _, binary.len() as i32);
+        gl::GetProgramiv(shader.id, gl::LINK_STATUS, &mut success);
+        if success == 0 {
+            gl::DeleteProgram(shader.id);
+            return None;
+        }
+    }
+    shader.cache_uniforms();
+    Some(shader)
+}
+
+fn read_binary(shader: &Shader) -> Option<Vec<u8>> {
+    let mut length = 0;
+    unsafe {
+        gl::GetProgramiv(shader.id, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        if length <= 0 {
+            return None;
+        }
+        let mut binary = vec![0u8; length as usize];
+        let mut format = 0;
+        gl::GetProgramBinary(shader.id, length, ptr::null_mut(), &mut format, binary.as_mut_ptr() as *mut _);
+        Some(encode_entry(format, &binary))
+    }
+}
+
+// loads the program from the cache, compiling (and caching) it if there is no valid entry
+pub fn load_or_compile(vertex_source: String, fragment_source: String, geometry_source: Option<String>) -> Shader {
+    if !supported() {
+        let mut shader = Shader::new();
+        shader.compile(vertex_source, fragment_source, geometry_source);
+        return shader;
+    }
+    let driver = format!("{} / {} / {}", gl_string(gl::VENDOR), gl_string(gl::RENDERER), gl_string(gl::VERSION));
+    let sources = [vertex_source.as_str(), fragment_source.as_str(), geometry_source.as_deref().unwrap_or("")];
+    let dir = filesystem::get_path(CACHE_DIR.to_string());
+    let path = format!("{}/{:016x}.bin", dir, cache_key(&sources, driver.as_str()));
+    if let Some(shader) = fs::read(path.as_str()).ok().and_then(|data| load_binary(&data)) {
+        log::debug!("SHADER_CACHE: Loaded {}", path);
+        return shader;
+    }
+    let mut shader = Shader::new();
+    shader.compile_ex(vertex_source, fragment_source, geometry_source, true);
+    if let Some(data) = read_binary(&shader) {
+        if let Err(e) = fs::create_dir_all(dir.as_str()).and_then(|_| fs::write(path.as_str(), data)) {
+            log::warn!("SHADER_CACHE: Failed to write {}: {}", path, e);
+        }
+    }
+    shader
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_test_case() {
+        let key = cache_key(&["void main() {}", "void main() {}", ""], "driver 1");
+        assert_eq!(key, cache_key(&["void main() {}", "void main() {}", ""], "driver 1"));
+        assert_ne!(key, cache_key(&["void main() {}", "void main() {}", ""], "driver 2"));
+        // moving text between the stages changes the key
+        assert_ne!(cache_key(&["ab", "c"], ""), cache_key(&["a", "bc"], ""));
+    }
+
+    #[test]
+    fn entry_test_round_trip() {
+        let data = encode_entry(0x8741, &[1, 2, 3]);
+        assert_eq!(decode_entry(&data), Some((0x8741, &[1u8, 2, 3][..])));
+        assert_eq!(decode_entry(&data[..data.len() - 1]), None);
+        assert_eq!(decode_entry(b"nope"), None);
+    }
+}
@@ -0,0 +1,452 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use learnopengl_shared::util;
+use crate::ball_object::BallObject;
+use crate::collision::{check_collision, check_collision_1, check_collision_obb, rotate_vec2, Collision, Direction};
+use crate::dash::Dash;
+use crate::difficulty::{BallSpeed, Difficulty};
+use crate::drops::DropTable;
+use crate::game::{create_power_up, BALL_RADIUS, INITIAL_BALL_VELOCITY, PADDLE_TILT_MAX, PADDLE_TILT_SPEED, PLAYER_SIZE, PLAYER_VELOCITY, POWER_UP_TYPES};
+use crate::game_level::{GameLevel, LevelError, LevelObjectKind, LevelSnapshot};
+use crate::game_object::GameObject;
+use crate::power_up::PowerUp;
+use crate::power_up::effect::{find_effect, EffectTarget};
+use crate::resource_manager;
+use crate::score::Score;
+use crate::texture::Texture2D;
+use crate::walls::{Edge, WallEvent};
+
+// Simulation is the Breakout world without anything to show or hear: no renderer, sound,
+// particles or post effects. The rules it shares with the game (paddle movement, ball
+// collisions, drops and power-ups) are the functions below, so stepping it with recorded
+// input reproduces what the game would have done.
+
+/// What happened to a brick the ball collided with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BrickHit {
+    // a destructible brick lost hp but is still standing
+    Damaged,
+    Destroyed,
+    Solid
+}
+
+/// Input of a single simulation step.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct StepInput {
+    pub left: bool,
+    pub right: bool,
+    pub launch: bool,
+    // starts a dash towards the held direction
+    pub dash: bool,
+    pub tilt_left: bool,
+    pub tilt_right: bool
+}
+
+pub struct Simulation {
+    pub width: u32,
+    pub height: u32,
+    pub level: GameLevel,
+    // 1-based number of the level (as used by the drop table)
+    pub level_number: usize,
+    pub player: GameObject,
+    pub ball: BallObject,
+    pub power_ups: Vec<PowerUp>,
+    pub drops: DropTable,
+    pub score: Score,
+    pub lives: u32,
+    pub ball_speed: BallSpeed,
+    pub dash: Dash,
+    // whether the paddle catches the ball without the Sticky power-up
+    pub catching: bool,
+    // number of steps simulated so far
+    pub steps: u32,
+    // state restored when the ball is lost, saved by hitting a checkpoint brick
+    checkpoint: Option<(LevelSnapshot, Score)>,
+    rng: StdRng
+}
+
+// moves the paddle by the given distance (negative is to the left), carrying a stuck ball along
+pub fn move_paddle(player: &mut GameObject, ball: &mut BallObject, distance: f32, width: f32) {
+    let can_move = if distance < 0.0 {
+        player.position.x >= 0.0
+    } else {
+        player.position.x <= width - player.size.x
+    };
+    if can_move {
+        player.position.x += distance;
+        if ball.stuck {
+            ball.game_obj.position.x += distance;
+        }
+    }
+}
+
+// moves the paddle by a dash, stopping it at the edges of the playfield
+pub fn dash_paddle(player: &mut GameObject, ball: &mut BallObject, distance: f32, width: f32) {
+    let distance = distance.clamp(-player.position.x, (width - player.size.x - player.position.x).max(0.0));
+    player.position.x += distance;
+    if ball.stuck {
+        ball.game_obj.position.x += distance;
+    }
+}
+
+// turns the paddle towards the given tilt direction (-1 left, 1 right), or back level for 0
+pub fn tilt_paddle(player: &mut GameObject, direction: f32, dt: f32) {
+    let target = direction * PADDLE_TILT_MAX;
+    let step = PADDLE_TILT_SPEED * dt;
+    player.rotation = target.clamp(player.rotation - step, player.rotation + step);
+}
+
+// damages the brick the ball collided with and bounces the ball off it (unless it passes through)
+pub fn hit_brick(ball: &mut BallObject, brick: &mut GameObject, collision: &Collision) -> BrickHit {
+    let hit = if brick.is_solid {
+        BrickHit::Solid
+    } else {
+        // multi-hit bricks are only destroyed once they run out of hp
+        brick.hp = brick.hp.saturating_sub(1);
+        if brick.hp == 0 {
+            brick.destroyed = true;
+            BrickHit::Destroyed
+        } else {
+            BrickHit::Damaged
+        }
+    };
+    // don't do collision resolution on non-solid bricks if pass-through is activated
+    if ball.pass_through && !brick.is_solid {
+        return hit;
+    }
+    let (_, dir, diff_vector) = collision;
+    if *dir == Direction::Left || *dir == Direction::Right { // horizontal collision
+        ball.game_obj.velocity.x = -ball.game_obj.velocity.x;
+        // relocate
+        let penetration = ball.radius - diff_vector.x.abs();
+        if *dir == Direction::Left {
+            ball.game_obj.position.x += penetration; // move ball to right
+        } else {
+            ball.game_obj.position.x -= penetration; // move ball to left
+        }
+    } else { // vertical collision
+        ball.game_obj.velocity.y = -ball.game_obj.velocity.y; // reverse vertical velocity
+        // relocate
+        let penetration = ball.radius - diff_vector.y.abs();
+        if *dir == Direction::Up {
+            ball.game_obj.position.y -= penetration; // move ball back up
+        } else {
+            ball.game_obj.position.y += penetration; // move ball back down
+        }
+    }
+    hit
+}
+
+// seconds a caught ball is held on the paddle before it is thrown on its own
+pub const CATCH_TIME: f32 = 1.0;
+// how much the spot the ball hits the paddle at turns it sideways
+pub const BOUNCE_STRENGTH: f32 = 2.0;
+
+// sends the ball back up, angled by where it hit the paddle; returns whether it hit the paddle at all.
+// With `catching` (or the Sticky power-up) the ball is caught instead, to be thrown once released
+pub fn bounce_off_paddle(ball: &mut BallObject, player: &GameObject, catching: bool) -> bool {
+    // a tilted paddle is checked as an oriented box
+    let tilted = player.rotation != 0.0;
+    let collision = if tilted { check_collision_obb(ball, player) } else { check_collision_1(ball, player) };
+    if ball.stuck || !collision.0 {
+        return false;
+    }
+    ball.game_obj.velocity = paddle_velocity(ball, player);
+    // when catching, also stick ball to paddle once new velocity vectors were calculated
+    if catching || ball.sticky {
+        ball.stuck = true;
+        ball.catch_time = CATCH_TIME;
+    }
+    true
+}
+
+// the velocity a ball leaves the paddle with from where it is on the board and how the board is tilted
+pub fn paddle_velocity(ball: &BallObject, player: &GameObject) -> glm::TVec2<f32> {
+    let tilted = player.rotation != 0.0;
+    // check where it hit the board, and change velocity based on where it hit the board
+    let center_board = player.position.x + player.size.x / 2.0;
+    let distance = if tilted {
+        // measured along the tilted board
+        let offset = ball.game_obj.position + glm::vec2(ball.radius, ball.radius) - (player.position + player.size / 2.0);
+        rotate_vec2(offset, -player.rotation).x
+    } else {
+        ball.game_obj.position.x + ball.radius - center_board
+    };
+    let percentage = distance / (player.size.x / 2.0);
+    // then move accordingly
+    let old_velocity = ball.game_obj.velocity;
+    let mut velocity = old_velocity;
+    velocity.x = INITIAL_BALL_VELOCITY.x * percentage * BOUNCE_STRENGTH;
+    velocity = glm::normalize(&velocity) * glm::length(&old_velocity); // keep speed consistent over both axes (multiply by length of old velocity, so total strength is not changed)
+    // fix sticky paddle
+    velocity.y = -velocity.y.abs();
+    if tilted {
+        // the tilt turns the normal the ball leaves along
+        velocity = rotate_vec2(velocity, player.rotation);
+        velocity.y = -velocity.y.abs();
+    }
+    velocity
+}
+
+// counts down the time a caught ball is held for; it's thrown (aimed by the paddle as it is now)
+// when `launch` is pressed or the time is up. A ball waiting on the paddle for a new life only
+// leaves on `launch`
+pub fn update_catch(ball: &mut BallObject, player: &GameObject, dt: f32, launch: bool) {
+    if ball.catch_time > 0.0 {
+        ball.catch_time = (ball.catch_time - dt).max(0.0);
+        if launch || ball.catch_time == 0.0 {
+            ball.game_obj.velocity = paddle_velocity(ball, player);
+            ball.catch_time = 0.0;
+            ball.stuck = false;
+        }
+    } else if launch {
+        ball.stuck = false;
+    }
+}
+
+// power-ups which spawners placed in the level drop from the given brick
+pub fn spawner_drops(level: &GameLevel, brick_index: usize) -> Vec<String> {
+    let brick = &level.bricks[brick_index];
+    let center = brick.position + brick.size / 2.0;
+    level.objects.iter()
+        .filter(|object| object.contains(center))
+        .filter_map(|object| match &object.kind {
+            LevelObjectKind::PowerUpSpawner(type_str) => Some(type_str.clone()),
+            _ => None
+        })
+        .collect()
+}
+
+// the sprites are never drawn, but the level and power-ups look them up by name
+fn add_placeholder_textures() {
+    let power_up_textures = POWER_UP_TYPES.iter().map(|it| it.3);
+    for name in ["block", "block_solid", "paddle", "face"].into_iter().chain(power_up_textures) {
+        if resource_manager::find_texture(name).is_none() {
+            resource_manager::add_texture(name.to_string(), Texture2D::empty());
+        }
+    }
+}
+
+impl Simulation {
+    // constructor; the level occupies the upper half of the world like in the game
+    pub fn new(level_file: &str, level_number: usize, drops: DropTable, seed: u64, width: u32, height: u32) -> Result<Self, LevelError> {
+        add_placeholder_textures();
+        let mut level = GameLevel::new();
+        level.load(level_file, width, height / 2)?;
+        let mut result = Self {
+            width,
+            height,
+            level,
+            level_number,
+            player: GameObject::new_ex0(glm::vec2(0.0, 0.0), *PLAYER_SIZE, Texture2D::empty()),
+            ball: BallObject::new_ex(glm::vec2(0.0, 0.0), BALL_RADIUS, *INITIAL_BALL_VELOCITY, Texture2D::empty()),
+            power_ups: Vec::new(),
+            drops,
+            score: Score::new(),
+            lives: 3,
+            ball_speed: BallSpeed::new(Difficulty::default().speed_rules()),
+            dash: Dash::default(),
+            catching: false,
+            steps: 0,
+            checkpoint: None,
+            rng: StdRng::seed_from_u64(seed)
+        };
+        result.reset_player();
+        Ok(result)
+    }
+
+    // advances the world by a step of the given length (in the order of Game::process_input and Game::update)
+    pub fn step(&mut self, dt: f32, input: StepInput) {
+        self.steps += 1;
+        if self.lives == 0 {
+            return;
+        }
+        let pace = self.player.status.speed_factor();
+        let velocity = PLAYER_VELOCITY * dt * pace;
+        if input.left {
+            move_paddle(&mut self.player, &mut self.ball, -velocity, self.width as f32);
+        }
+        if input.right {
+            move_paddle(&mut self.player, &mut self.ball, velocity, self.width as f32);
+        }
+        if input.dash && input.left != input.right && pace != 0.0 {
+            self.dash.start(if input.left { -1.0 } else { 1.0 });
+        }
+        let distance = self.dash.update(dt) * pace;
+        if distance != 0.0 {
+            dash_paddle(&mut self.player, &mut self.ball, distance, self.width as f32);
+        }
+        tilt_paddle(&mut self.player, input.tilt_right as i32 as f32 - input.tilt_left as i32 as f32, dt * pace);
+        update_catch(&mut self.ball, &self.player, dt, input.launch);
+        self.player.update_status(dt);
+        self.ball.game_obj.update_status(dt);
+        self.level.update_status(dt);
+        let pace = self.ball.game_obj.status.speed_factor();
+        let before = glm::length(&self.ball.game_obj.velocity);
+        let wall_event = self.ball.move_ball(dt * pace, self.width, &self.level.bounds());
+        if wall_event == Some(WallEvent::Bounced(Edge::Top)) {
+            self.level.hit_ceiling();
+        }
+        if let Some(WallEvent::Bounced(_)) = wall_event {
+            self.ball_speed.limit(glm::length(&INITIAL_BALL_VELOCITY), before, &mut self.ball);
+        }
+        self.do_collisions();
+        self.update_power_ups(dt);
+        // did ball reach bottom edge or leave through an open side?
+        if wall_event == Some(WallEvent::Escaped) || self.ball.game_obj.position.y >= self.height as f32 {
+            self.lives -= 1;
+            if let Some((bricks, score)) = self.checkpoint.as_ref() {
+                self.level.restore(bricks);
+                self.score = *score;
+            }
+            self.reset_player();
+        }
+    }
+
+    fn do_collisions(&mut self) {
+        let mut hit = Vec::new();
+        let mut destroyed = Vec::new();
+        for (i, brick) in self.level.bricks.iter_mut().enumerate() {
+            if brick.destroyed {
+                continue;
+            }
+            let collision = check_collision_1(&self.ball, brick);
+            if collision.0 {
+                hit.push(i);
+                if hit_brick(&mut self.ball, brick, &collision) == BrickHit::Destroyed {
+                    destroyed.push(i);
+                }
+            }
+        }
+        for i in destroyed.iter() {
+            self.score.brick_destroyed(self.level.brick_points(*i));
+        }
+        self.ball_speed.rows_cleared(self.level.cleared_rows(&destroyed), &mut self.ball);
+        if hit.iter().any(|i| self.level.is_checkpoint(*i)) {
+            self.checkpoint = Some((self.level.snapshot(), self.score));
+        }
+        for i in destroyed {
+            self.spawn_power_ups(i);
+        }
+        let mut activated = Vec::new();
+        for power_up in self.power_ups.iter_mut().filter(|it| !it.game_obj.destroyed) {
+            if power_up.game_obj.position.y >= self.height as f32 {
+                power_up.game_obj.destroyed = true;
+            }
+            if check_collision(&self.player, &power_up.game_obj) {
+                power_up.game_obj.destroyed = true;
+                power_up.activated = true;
+                activated.push(power_up.type_str.clone());
+            }
+        }
+        let mut target = EffectTarget { ball: &mut self.ball, player: &mut self.player, screen: None };
+        for effect in activated.iter().filter_map(|type_str| find_effect(type_str)) {
+            effect.on_apply(&mut target);
+        }
+        if bounce_off_paddle(&mut self.ball, &self.player, self.catching) {
+            // touching the paddle ends the combo
+            self.score.end_combo();
+            self.ball_speed.paddle_hit(&mut self.ball);
+        }
+    }
+
+    fn spawn_power_ups(&mut self, brick_index: usize) {
+        let position = self.level.bricks[brick_index].position;
+        let mut spawned = spawner_drops(&self.level, brick_index);
+        if spawned.is_empty() {
+            let (x, y) = self.level.brick_cells[brick_index];
+            spawned.extend(self.drops.roll(&mut self.rng, self.level.tiles[y][x], self.level_number));
+        }
+        self.power_ups.extend(spawned.iter().filter_map(|type_str| create_power_up(type_str, position)));
+    }
+
+    fn update_power_ups(&mut self, dt: f32) {
+        let mut expired = Vec::new();
+        for power_up in self.power_ups.iter_mut() {
+            power_up.game_obj.position += power_up.game_obj.velocity * dt;
+            if power_up.activated {
+                power_up.duration -= dt;
+                if power_up.duration <= 0.0 {
+                    power_up.activated = false;
+                    expired.push(power_up.type_str.clone());
+                }
+            }
+        }
+        let mut target = EffectTarget { ball: &mut self.ball, player: &mut self.player, screen: None };
+        for effect in self.power_ups.iter().filter(|it| it.activated).filter_map(|it| find_effect(&it.type_str)) {
+            effect.on_update(&mut target, dt);
+        }
+        for type_str in expired {
+            // the effect stays while another power-up of its type is still active
+            let other_active = self.power_ups.iter().any(|it| it.activated && it.type_str == type_str);
+            if let Some(effect) = find_effect(&type_str).filter(|_| !other_active) {
+                effect.on_expire(&mut target);
+            }
+        }
+        self.power_ups.retain(|power_up| !power_up.game_obj.destroyed || power_up.activated);
+    }
+
+    fn reset_player(&mut self) {
+        self.player.size = *PLAYER_SIZE;
+        self.player.rotation = 0.0;
+        self.player.status.clear();
+        self.player.position = glm::vec2(self.width as f32 / 2.0 - PLAYER_SIZE.x / 2.0, self.height as f32 - PLAYER_SIZE.y);
+        self.ball.reset(self.player.position + glm::vec2(PLAYER_SIZE.x / 2.0 - BALL_RADIUS, -(BALL_RADIUS * 2.0)), *INITIAL_BALL_VELOCITY);
+        self.ball_speed = BallSpeed::new(self.ball_speed.rules);
+        self.dash = Dash::default();
+        self.score.end_combo();
+        self.player.color = util::glm::scale_vec3(1.0);
+        self.ball.game_obj.color = util::glm::scale_vec3(1.0);
+    }
+
+    // 64-bit FNV-1a over everything that affects the rest of the game; equal for equal worlds
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = 0xcbf29ce484222325u64;
+        let mut feed = |value: u32| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        for object in [&self.player, &self.ball.game_obj] {
+            for value in [object.position.x, object.position.y, object.size.x, object.size.y, object.velocity.x, object.velocity.y] {
+                feed(value.to_bits());
+            }
+        }
+        feed(self.ball.stuck as u32 | (self.ball.sticky as u32) << 1 | (self.ball.pass_through as u32) << 2);
+        feed(self.ball_speed.multiplier.to_bits());
+        for brick in self.level.bricks.iter() {
+            feed(brick.destroyed as u32);
+            feed(brick.hp);
+        }
+        for power_up in self.power_ups.iter() {
+            feed(POWER_UP_TYPES.iter().position(|it| it.0 == power_up.type_str).unwrap_or(usize::MAX) as u32);
+            feed(power_up.game_obj.position.x.to_bits());
+            feed(power_up.game_obj.position.y.to_bits());
+            feed(power_up.duration.to_bits());
+            feed(power_up.activated as u32 | (power_up.game_obj.destroyed as u32) << 1);
+        }
+        feed(self.score.points);
+        feed(self.score.combo);
+        feed(self.lives);
+        hash
+    }
+}
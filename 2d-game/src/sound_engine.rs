@@ -14,33 +14,206 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+use std::fs;
 use std::fs::File;
 use std::io::BufReader;
+use rand::Rng;
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use rodio::source::ChannelVolume;
+use serde::Deserialize;
+use learnopengl_shared::filesystem;
+use crate::fallback;
+
+// extra music volume and playback speed at full intensity
+const MUSIC_INTENSITY_BOOST: f32 = 0.3;
+const MUSIC_INTENSITY_SPEEDUP: f32 = 0.05;
+
+/// Describes how a single game event sounds, as declared in the audio event table.
+#[derive(Clone, Deserialize)]
+pub struct AudioEvent {
+    // sound file, relative to the project root
+    pub file: String,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    // maximum relative pitch deviation applied randomly on each playback (0.05 = ±5%)
+    #[serde(default)]
+    pub pitch_jitter: f32,
+    #[serde(default)]
+    pub repeat: bool
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
 
 pub struct SoundEngine {
+    // the audio output; without an audio device no sound is played
     #[allow(dead_code)]
-    stream: OutputStream,
-    handle: OutputStreamHandle
+    stream: Option<OutputStream>,
+    handle: Option<OutputStreamHandle>,
+    // event name -> sound description, loaded from the audio event table
+    events: HashMap<String, AudioEvent>,
+    // the currently playing background music, kept to allow adjusting it while playing
+    music: Option<Sink>,
+    // volume of the music as declared in the event table
+    music_volume: f32,
+    // temporary attenuation of all sounds, e.g. while the window is in the background
+    duck: f32,
+    // how driven the music is (0.0 normal, 1.0 full fever)
+    music_intensity: f32
 }
 
 impl SoundEngine {
     pub fn new() -> Self {
-        let (stream, handle) = OutputStream::try_default().unwrap();
+        let (stream, handle) = match OutputStream::try_default() {
+            Ok((stream, handle)) => (Some(stream), Some(handle)),
+            Err(e) => {
+                log::warn!("SOUND_ENGINE: No audio output, the game is silent: {}", e);
+                (None, None)
+            }
+        };
         Self {
             stream,
-            handle
+            handle,
+            events: HashMap::new(),
+            music: None,
+            music_volume: 1.0,
+            duck: 1.0,
+            music_intensity: 0.0
+        }
+    }
+
+    // loads (or reloads) the event -> sound mapping from a TOML file
+    pub fn load_events(&mut self, path: &str) {
+        // events missing from the table just stay silent
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                fallback::report("audio event table", path, e.to_string().as_str());
+                return;
+            }
+        };
+        match toml::from_str(source.as_str()) {
+            Ok(events) => self.events = events,
+            Err(e) => log::error!("ERROR::SOUND_ENGINE: Failed to parse audio event table: {}", e)
+        }
+    }
+
+    // plays the sound mapped to the given event, applying its volume and pitch variation
+    pub fn play_sound(&self, name: &str) {
+        self.play_sound_ex(name, 1.0, 1.0, 0.0);
+    }
+
+    // plays the sound mapped to the given event; volume and pitch are multiplied onto the
+    // values of the event table, pan ranges from -1.0 (left) to 1.0 (right)
+    pub fn play_sound_ex(&self, name: &str, volume: f32, pitch: f32, pan: f32) {
+        if let Some(sink) = self.event(name).and_then(|event| self.create_sink(event, volume, pitch, pan)) {
+            // keep playing after the sink handle goes out of scope
+            sink.detach();
+        }
+    }
+
+    // starts the sound mapped to the given event as background music, replacing the current one
+    pub fn play_music(&mut self, name: &str) {
+        if let Some(event) = self.event(name) {
+            let sink = self.create_sink(event, 1.0, 1.0, 0.0);
+            self.music_volume = event.volume;
+            // dropping the previous sink stops the previous music
+            self.music = sink;
+        }
+    }
+
+    // attenuates all sounds by the given factor (0.0 mutes, 1.0 restores the normal volume)
+    pub fn set_duck(&mut self, duck: f32) {
+        self.duck = duck.clamp(0.0, 1.0);
+        self.update_music();
+    }
+
+    // intensifies the background music: louder and slightly faster (0.0 restores it)
+    pub fn set_music_intensity(&mut self, intensity: f32) {
+        let intensity = intensity.clamp(0.0, 1.0);
+        if intensity != self.music_intensity {
+            self.music_intensity = intensity;
+            self.update_music();
+        }
+    }
+
+    fn update_music(&self) {
+        if let Some(music) = self.music.as_ref() {
+            music.set_volume(self.music_volume * self.duck * (1.0 + MUSIC_INTENSITY_BOOST * self.music_intensity));
+            music.set_speed(1.0 + MUSIC_INTENSITY_SPEEDUP * self.music_intensity);
         }
     }
 
     pub fn play(&self, path: &str, repeat: bool) {
-        let file = File::open(path).unwrap();
-        let source = Decoder::new(BufReader::new(file)).unwrap();
-        let sink = Sink::try_new(&self.handle).unwrap();
-        if repeat {
-            sink.append(source.repeat_infinite());
+        self.play_ex(path, repeat, 1.0, 1.0);
+    }
+
+    pub fn play_ex(&self, path: &str, repeat: bool, volume: f32, pitch: f32) {
+        if let Some(sink) = self.create_sink_from_file(path, repeat, volume, pitch, 0.0) {
+            sink.detach();
+        }
+    }
+
+    fn event(&self, name: &str) -> Option<&AudioEvent> {
+        let event = self.events.get(name);
+        if event.is_none() {
+            log::warn!("SOUND_ENGINE: No sound mapped to event \"{}\"", name);
+        }
+        event
+    }
+
+    fn create_sink(&self, event: &AudioEvent, volume: f32, pitch: f32, pan: f32) -> Option<Sink> {
+        let mut pitch = pitch;
+        if event.pitch_jitter > 0.0 {
+            pitch *= 1.0 + rand::rng().random_range(-event.pitch_jitter..=event.pitch_jitter);
+        }
+        let path = filesystem::get_path(event.file.clone());
+        self.create_sink_from_file(path.as_str(), event.repeat, event.volume * volume, pitch, pan)
+    }
+
+    // a sink playing the given file, or None if there is no audio output or the file can't be played
+    fn create_sink_from_file(&self, path: &str, repeat: bool, volume: f32, pitch: f32, pan: f32) -> Option<Sink> {
+        let handle = self.handle.as_ref()?;
+        let source = match File::open(path) {
+            Ok(file) => Decoder::new(BufReader::new(file)).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string())
+        };
+        let source = match source {
+            Ok(source) => source,
+            Err(e) => {
+                fallback::report("sound", path, e.as_str());
+                return None;
+            }
+        };
+        let sink = match Sink::try_new(handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                log::error!("ERROR::SOUND_ENGINE: Failed to create a sink: {}", e);
+                return None;
+            }
+        };
+        sink.set_volume(volume * self.duck);
+        // changing the playback speed of the source shifts its pitch accordingly
+        sink.set_speed(pitch);
+        if pan == 0.0 {
+            if repeat {
+                sink.append(source.repeat_infinite());
+            } else {
+                sink.append(source);
+            }
         } else {
-            sink.append(source);
+            // the source is down-mixed (summed) to mono and then played on each side with its own gain
+            let pan = pan.clamp(-1.0, 1.0);
+            let channels = source.channels() as f32;
+            let channel_volumes = vec![(1.0 - pan).min(1.0) / channels, (1.0 + pan).min(1.0) / channels];
+            if repeat {
+                sink.append(ChannelVolume::new(source.repeat_infinite(), channel_volumes));
+            } else {
+                sink.append(ChannelVolume::new(source, channel_volumes));
+            }
         }
+        Some(sink)
     }
-}
\ No newline at end of file
+}
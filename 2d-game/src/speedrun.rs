@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+// Speedrun timer. A run starts when the ball is first launched and goes through the
+// levels in order; each cleared level records a split which is compared against the
+// personal best stored in the stats file.
+
+/// Time spent on a single level of a run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Split {
+    pub level: u32,
+    pub time: f32
+}
+
+/// A finished (or abandoned) run, as exported to the runs directory.
+#[derive(Debug, Serialize)]
+pub struct RunRecord {
+    pub start_level: u32,
+    pub completed: bool,
+    pub total: f32,
+    pub splits: Vec<Split>
+}
+
+pub struct Speedrun {
+    start_level: u32,
+    time: f32,
+    // run time at which the current level was started
+    level_start: f32,
+    splits: Vec<Split>
+}
+
+impl Speedrun {
+    // constructor
+    pub fn new(start_level: u32) -> Self {
+        Self {
+            start_level,
+            time: 0.0,
+            level_start: 0.0,
+            splits: Vec::new()
+        }
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn splits(&self) -> &[Split] {
+        &self.splits
+    }
+
+    // records the split of the level which was just cleared
+    pub fn complete_level(&mut self, level: u32) -> &Split {
+        self.splits.push(Split { level, time: self.time - self.level_start });
+        self.level_start = self.time;
+        self.splits.last().unwrap()
+    }
+
+    pub fn record(&self, completed: bool) -> RunRecord {
+        RunRecord {
+            start_level: self.start_level,
+            completed,
+            total: self.time,
+            splits: self.splits.clone()
+        }
+    }
+
+    // writes the run as JSON into the given directory, named after the current time
+    pub fn export(&self, completed: bool, dir: &str) -> std::io::Result<String> {
+        fs::create_dir_all(dir)?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|it| it.as_secs())
+            .unwrap_or(0);
+        let path = format!("{}/run-{}.json", dir, timestamp);
+        fs::write(path.as_str(), serde_json::to_string_pretty(&self.record(completed)).unwrap())?;
+        Ok(path)
+    }
+}
+
+/// Personal bests, stored in the stats file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    // level index -> best split
+    pub best_splits: BTreeMap<String, f32>,
+    // best time of a completed run, per start level
+    pub best_runs: BTreeMap<String, f32>,
+    // best score of each daily challenge, by seed code
+    pub daily_scores: BTreeMap<String, u32>
+}
+
+impl Stats {
+    pub fn best_split(&self, level: u32) -> Option<f32> {
+        self.best_splits.get(&level.to_string()).copied()
+    }
+
+    pub fn best_run(&self, start_level: u32) -> Option<f32> {
+        self.best_runs.get(&start_level.to_string()).copied()
+    }
+
+    // takes over all splits (and the total of a completed run) which beat the personal bests
+    pub fn update(&mut self, run: &RunRecord) {
+        for split in run.splits.iter() {
+            if self.best_split(split.level).is_none_or(|best| split.time < best) {
+                self.best_splits.insert(split.level.to_string(), split.time);
+            }
+        }
+        if run.completed && self.best_run(run.start_level).is_none_or(|best| run.total < best) {
+            self.best_runs.insert(run.start_level.to_string(), run.total);
+        }
+    }
+
+    pub fn best_daily(&self, code: &str) -> Option<u32> {
+        self.daily_scores.get(code).copied()
+    }
+
+    // records the score of a daily challenge, returning whether it's a new best
+    pub fn update_daily(&mut self, code: &str, points: u32) -> bool {
+        if self.best_daily(code).is_some_and(|best| best >= points) {
+            return false;
+        }
+        self.daily_scores.insert(code.to_string(), points);
+        true
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        toml::from_str(source).map_err(|e| e.to_string())
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+
+    // loads the stats from a file, starting out empty if it doesn't exist or is invalid
+    pub fn load(path: &str) -> Self {
+        let Ok(source) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        Self::parse(source.as_str()).unwrap_or_else(|e| {
+            log::error!("STATS: Failed to parse {}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+}
+
+// formats a time as m:ss.cc
+pub fn format_time(seconds: f32) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u32;
+    format!("{}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
+
+// formats the difference to a personal best as +s.cc or -s.cc
+pub fn format_delta(delta: f32) -> String {
+    format!("{}{:.2}", if delta < 0.0 { "-" } else { "+" }, delta.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speedrun_test_splits() {
+        let mut run = Speedrun::new(1);
+        run.tick(2.5);
+        assert_eq!(run.complete_level(1).time, 2.5);
+        run.tick(1.5);
+        run.tick(0.5);
+        assert_eq!(run.complete_level(2), &Split { level: 2, time: 2.0 });
+        let record = run.record(true);
+        assert_eq!(record.total, 4.5);
+        assert_eq!(record.splits.len(), 2);
+    }
+
+    #[test]
+    fn stats_test_update() {
+        let mut stats = Stats::default();
+        let mut run = Speedrun::new(0);
+        run.tick(10.0);
+        run.complete_level(0);
+        // an abandoned run still counts for the splits, but not for the run time
+        stats.update(&run.record(false));
+        assert_eq!(stats.best_split(0), Some(10.0));
+        assert_eq!(stats.best_run(0), None);
+        let mut run = Speedrun::new(0);
+        run.tick(12.0);
+        run.complete_level(0);
+        stats.update(&run.record(true));
+        assert_eq!(stats.best_split(0), Some(10.0));
+        assert_eq!(stats.best_run(0), Some(12.0));
+        assert!(stats.update_daily("1A2B-3C4D", 300));
+        assert!(!stats.update_daily("1A2B-3C4D", 200));
+        assert_eq!(stats.best_daily("1A2B-3C4D"), Some(300));
+        assert_eq!(Stats::parse(stats.to_toml().as_str()).unwrap(), stats);
+    }
+
+    #[test]
+    fn format_time_test_case() {
+        assert_eq!(format_time(0.0), "0:00.00");
+        assert_eq!(format_time(75.456), "1:15.46");
+        assert_eq!(format_delta(-0.5), "-0.50");
+        assert_eq!(format_delta(1.25), "+1.25");
+    }
+}
@@ -0,0 +1,362 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::mem;
+use bumpalo::Bump;
+use bumpalo::collections::Vec as BumpVec;
+use crate::affine::Affine2;
+use crate::frame_arena;
+use crate::game_object::GameObject;
+use crate::gl_api::{GlApi, NativeGl};
+use crate::resource_manager;
+use crate::shader::Shader;
+use crate::sprite_renderer::Gradient;
+use crate::texture::Texture2D;
+
+/// Per-instance record of a batched sprite: 2D model transform, the colors of its corners
+/// (top left, top right, bottom left, bottom right) and the region of the texture shown on the quad.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteInstance {
+    pub model: Affine2,
+    pub colors: [[f32; 3]; 4],
+    pub tex_rect: [f32; 4]
+}
+
+impl SpriteInstance {
+    pub fn new(object: &GameObject, tex_rect: glm::TVec4<f32>) -> Self {
+        let color = object.shown_color();
+        Self {
+            model: Affine2::sprite(object.position, object.size, object.rotation),
+            colors: [[color.x, color.y, color.z]; 4],
+            tex_rect: [tex_rect.x, tex_rect.y, tex_rect.z, tex_rect.w]
+        }
+    }
+
+    // blends the given gradient over the color of the sprite
+    pub fn with_gradient(mut self, gradient: &Gradient) -> Self {
+        for (color, corner) in self.colors.iter_mut().zip(gradient.corners()) {
+            *color = [color[0] * corner.x, color[1] * corner.y, color[2] * corner.z];
+        }
+        self
+    }
+}
+
+// groups sprite indices by texture, keeping the order in which the textures first appear
+pub fn group_by_texture<'a>(arena: &'a Bump, texture_ids: &[u32]) -> BumpVec<'a, (u32, BumpVec<'a, usize>)> {
+    let mut groups: BumpVec<(u32, BumpVec<usize>)> = BumpVec::new_in(arena);
+    for (i, id) in texture_ids.iter().enumerate() {
+        match groups.iter_mut().find(|(group_id, _)| group_id == id) {
+            Some((_, indices)) => indices.push(i),
+            None => {
+                let mut indices = BumpVec::new_in(arena);
+                indices.push(i);
+                groups.push((*id, indices));
+            }
+        }
+    }
+    groups
+}
+
+// instances of the sprites sorted by texture, with the (texture, first, count) of each run,
+// staged in the given arena
+pub fn stage_instances<'a>(arena: &'a Bump, sprites: &[&GameObject]) -> (BumpVec<'a, SpriteInstance>, BumpVec<'a, (Texture2D, usize, usize)>) {
+    let ids = BumpVec::from_iter_in(sprites.iter().map(|sprite| sprite.sprite.id), arena);
+    let mut instances = BumpVec::with_capacity_in(sprites.len(), arena);
+    let mut runs = BumpVec::new_in(arena);
+    for (_, indices) in group_by_texture(arena, &ids) {
+        runs.push((sprites[indices[0]].sprite.clone(), instances.len(), indices.len()));
+        instances.extend(indices.iter().map(|i| SpriteInstance::new(sprites[*i], glm::vec4(0.0, 0.0, 1.0, 1.0))));
+    }
+    (instances, runs)
+}
+
+// like stage_instances, but the instances and runs are kept
+pub fn build_instances(sprites: &[&GameObject]) -> (Vec<SpriteInstance>, Vec<(Texture2D, usize, usize)>) {
+    frame_arena::with(|arena| {
+        let (instances, runs) = stage_instances(arena, sprites);
+        (instances.to_vec(), runs.to_vec())
+    })
+}
+
+// Renders many sprites with one instanced draw call per texture. The instance
+// data is kept on the GPU and only uploaded again when the sprites change, which
+// suits mostly static sets such as the bricks of a level. GL objects are created
+// on the first upload.
+pub struct SpriteBatch<G: GlApi = NativeGl> {
+    gl: G,
+    // instances sorted by texture, with the (texture, first, count) of each run
+    instances: Vec<SpriteInstance>,
+    runs: Vec<(Texture2D, usize, usize)>,
+    // render state
+    vao: u32,
+    quad_vbo: u32,
+    instance_vbo: u32,
+    capacity: usize
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpriteBatch {
+    // constructor
+    pub fn new() -> Self {
+        Self::with_gl(NativeGl)
+    }
+}
+
+impl<G: GlApi> SpriteBatch<G> {
+    // constructor issuing the GL calls through the given API
+    pub fn with_gl(gl: G) -> Self {
+        Self {
+            gl,
+            instances: Vec::new(),
+            runs: Vec::new(),
+            vao: 0,
+            quad_vbo: 0,
+            instance_vbo: 0,
+            capacity: 0
+        }
+    }
+
+    // replaces the batched sprites; the instance buffer is only uploaded again if they changed
+    // (they are staged in the frame arena and only copied over then)
+    pub fn set_sprites(&mut self, sprites: &[&GameObject]) {
+        frame_arena::with(|arena| {
+            let (instances, runs) = stage_instances(arena, sprites);
+            self.runs.clear();
+            self.runs.extend_from_slice(&runs);
+            if instances.as_slice() != self.instances.as_slice() {
+                self.instances.clear();
+                self.instances.extend_from_slice(&instances);
+                self.upload();
+            }
+        });
+    }
+
+    // number of draw calls issued by draw()
+    pub fn draw_calls(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn draw(&self, shader: &Shader) {
+        if self.instances.is_empty() {
+            return;
+        }
+        let gl = &self.gl;
+        gl.use_program(shader.program());
+        let stride = mem::size_of::<SpriteInstance>();
+        gl.active_texture(gl::TEXTURE0);
+        gl.bind_vertex_array(self.vao);
+        gl.bind_buffer(gl::ARRAY_BUFFER, self.instance_vbo);
+        for (texture, first, count) in self.runs.iter() {
+            // GL 3.3 has no base instance, so point the instance attributes at the run instead
+            let base = first * stride;
+            for row in 0..2 {
+                gl.vertex_attrib_pointer(1 + row, 3, stride, base + row as usize * 3 * mem::size_of::<f32>());
+            }
+            for corner in 0..4 {
+                gl.vertex_attrib_pointer(3 + corner, 3, stride, base + mem::offset_of!(SpriteInstance, colors) + corner as usize * 3 * mem::size_of::<f32>());
+            }
+            gl.vertex_attrib_pointer(7, 4, stride, base + mem::offset_of!(SpriteInstance, tex_rect));
+            resource_manager::touch_texture(texture.id);
+            gl.bind_texture(gl::TEXTURE_2D, texture.gl_id());
+            gl.draw_arrays_instanced(gl::TRIANGLES, 0, 6, *count);
+        }
+        gl.bind_buffer(gl::ARRAY_BUFFER, 0);
+        gl.bind_vertex_array(0);
+    }
+
+    fn upload(&mut self) {
+        if self.vao == 0 {
+            self.init_render_data();
+        }
+        let data = instance_bytes(&self.instances);
+        self.gl.bind_buffer(gl::ARRAY_BUFFER, self.instance_vbo);
+        if self.instances.len() > self.capacity {
+            self.gl.buffer_data(gl::ARRAY_BUFFER, data, gl::DYNAMIC_DRAW);
+            self.capacity = self.instances.len();
+        } else {
+            self.gl.buffer_sub_data(gl::ARRAY_BUFFER, 0, data);
+        }
+        self.gl.bind_buffer(gl::ARRAY_BUFFER, 0);
+    }
+
+    // initializes the quad and the instance attributes (pointers are set per draw)
+    fn init_render_data(&mut self) {
+        let vertices = [
+            // pos      // tex
+            0.0f32, 1.0, 0.0, 1.0,
+            1.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 0.0,
+
+            0.0, 1.0, 0.0, 1.0,
+            1.0, 1.0, 1.0, 1.0,
+            1.0, 0.0, 1.0, 0.0
+        ];
+        let gl = &self.gl;
+        self.vao = gl.gen_vertex_array();
+        self.quad_vbo = gl.gen_buffer();
+        self.instance_vbo = gl.gen_buffer();
+        gl.bind_vertex_array(self.vao);
+        gl.bind_buffer(gl::ARRAY_BUFFER, self.quad_vbo);
+        gl.buffer_data(gl::ARRAY_BUFFER, float_bytes(&vertices), gl::STATIC_DRAW);
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer(0, 4, 4 * mem::size_of::<f32>(), 0);
+        // model rows (locations 1-2), corner colors (3-6) and texture region (7), one value per instance
+        for location in 1..=7 {
+            gl.enable_vertex_attrib_array(location);
+            gl.vertex_attrib_divisor(location, 1);
+        }
+        gl.bind_buffer(gl::ARRAY_BUFFER, 0);
+        gl.bind_vertex_array(0);
+    }
+}
+
+// the raw bytes of the instances, as uploaded to the instance buffer
+fn instance_bytes(instances: &[SpriteInstance]) -> &[u8] {
+    // SpriteInstance is repr(C) and made of f32s only
+    unsafe { std::slice::from_raw_parts(instances.as_ptr() as *const u8, mem::size_of_val(instances)) }
+}
+
+fn float_bytes(values: &[f32]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(values.as_ptr() as *const u8, mem::size_of_val(values)) }
+}
+
+impl<G: GlApi> Drop for SpriteBatch<G> {
+    // Destructor
+    fn drop(&mut self) {
+        if self.vao == 0 {
+            return;
+        }
+        self.gl.delete_vertex_array(self.vao);
+        self.gl.delete_buffer(self.quad_vbo);
+        self.gl.delete_buffer(self.instance_vbo);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_api::{redundant_calls, GlCall, RecordingGl};
+
+    fn sprites(textures: &[u32]) -> Vec<GameObject> {
+        textures.iter().enumerate().map(|(i, id)| {
+            let mut texture = Texture2D::empty();
+            texture.id = *id;
+            GameObject::new_ex0(glm::vec2(i as f32 * 10.0, 0.0), glm::vec2(10.0, 5.0), texture)
+        }).collect()
+    }
+
+    #[test]
+    fn sprite_batch_test_group_by_texture() {
+        let arena = Bump::new();
+        let groups: Vec<(u32, Vec<usize>)> = group_by_texture(&arena, &[3, 1, 3, 2, 1]).iter()
+            .map(|(id, indices)| (*id, indices.to_vec()))
+            .collect();
+        assert_eq!(groups, vec![(3, vec![0, 2]), (1, vec![1, 4]), (2, vec![3])]);
+        assert!(group_by_texture(&arena, &[]).is_empty());
+    }
+
+    #[test]
+    fn sprite_batch_test_build_instances() {
+        let mut textures = [Texture2D::empty(), Texture2D::empty()];
+        textures[0].id = 7;
+        textures[1].id = 3;
+        let sprites: Vec<GameObject> = (0..5)
+            .map(|i| GameObject::new_ex0(glm::vec2(i as f32, 0.0), glm::vec2(1.0, 1.0), textures[i % 2].clone()))
+            .collect();
+        let refs: Vec<&GameObject> = sprites.iter().collect();
+        let (instances, runs) = build_instances(&refs);
+        assert_eq!(instances.len(), 5);
+        let runs: Vec<(u32, usize, usize)> = runs.iter().map(|(texture, first, count)| (texture.id, *first, *count)).collect();
+        assert_eq!(runs, vec![(7, 0, 3), (3, 3, 2)]);
+    }
+
+    #[test]
+    fn sprite_batch_test_gradient() {
+        let objects = sprites(&[1]);
+        let instance = SpriteInstance::new(&objects[0], glm::vec4(0.0, 0.0, 1.0, 1.0));
+        assert_eq!(instance.colors, [[1.0; 3]; 4]);
+        let faded = instance.with_gradient(&Gradient::vertical(glm::vec3(1.0, 1.0, 1.0), glm::vec3(0.5, 0.0, 0.0)));
+        assert_eq!(faded.colors, [[1.0; 3], [1.0; 3], [0.5, 0.0, 0.0], [0.5, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn sprite_batch_test_uploads() {
+        let mut batch = SpriteBatch::with_gl(RecordingGl::default());
+        let objects = sprites(&[1, 1, 2]);
+        let refs: Vec<&GameObject> = objects.iter().collect();
+        batch.set_sprites(&refs);
+        let calls = batch.gl.take();
+        // the GL objects are created with the first upload, which allocates the buffer
+        assert!(calls.contains(&GlCall::GenVertexArray(1)));
+        assert!(calls.contains(&GlCall::BufferData(gl::ARRAY_BUFFER, 3 * mem::size_of::<SpriteInstance>())));
+        // unchanged sprites aren't uploaded again
+        batch.set_sprites(&refs);
+        assert!(batch.gl.take().is_empty());
+        // fewer sprites fit into the buffer
+        batch.set_sprites(&refs[..2]);
+        assert_eq!(batch.gl.take(), vec![
+            GlCall::BindBuffer(gl::ARRAY_BUFFER, 3),
+            GlCall::BufferSubData(gl::ARRAY_BUFFER, 0, 2 * mem::size_of::<SpriteInstance>()),
+            GlCall::BindBuffer(gl::ARRAY_BUFFER, 0)
+        ]);
+    }
+
+    #[test]
+    fn sprite_batch_test_draw_calls() {
+        let mut batch = SpriteBatch::with_gl(RecordingGl::default());
+        let mut shader = Shader::new();
+        shader.id = 11;
+        // nothing is drawn (or bound) without sprites
+        batch.draw(&shader);
+        assert!(batch.gl.take().is_empty());
+        let objects = sprites(&[5, 6, 5, 6, 7]);
+        let refs: Vec<&GameObject> = objects.iter().collect();
+        batch.set_sprites(&refs);
+        batch.gl.take();
+        batch.draw(&shader);
+        let calls = batch.gl.take();
+        assert_eq!(calls[..4], [
+            GlCall::UseProgram(11),
+            GlCall::ActiveTexture(gl::TEXTURE0),
+            GlCall::BindVertexArray(1),
+            GlCall::BindBuffer(gl::ARRAY_BUFFER, 3)
+        ]);
+        // one instanced draw per texture, each right after binding its texture
+        let draws: Vec<(GlCall, GlCall)> = calls.windows(2)
+            .filter(|pair| matches!(pair[1], GlCall::DrawArraysInstanced(..)))
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+        assert_eq!(draws, vec![
+            (GlCall::BindTexture(gl::TEXTURE_2D, 5), GlCall::DrawArraysInstanced(gl::TRIANGLES, 0, 6, 2)),
+            (GlCall::BindTexture(gl::TEXTURE_2D, 6), GlCall::DrawArraysInstanced(gl::TRIANGLES, 0, 6, 2)),
+            (GlCall::BindTexture(gl::TEXTURE_2D, 7), GlCall::DrawArraysInstanced(gl::TRIANGLES, 0, 6, 1))
+        ]);
+        assert_eq!(batch.draw_calls(), 3);
+        // the instance attributes of the second run start at its first instance
+        let stride = mem::size_of::<SpriteInstance>();
+        assert!(calls.contains(&GlCall::VertexAttribPointer(1, 3, stride, 2 * stride)));
+        assert!(redundant_calls(&calls).is_empty(), "redundant state changes: {:?}", redundant_calls(&calls));
+        assert_eq!(calls[calls.len() - 2..], [GlCall::BindBuffer(gl::ARRAY_BUFFER, 0), GlCall::BindVertexArray(0)]);
+    }
+}
@@ -18,10 +18,118 @@ extern crate nalgebra_glm as glm;
 
 use std::{mem, ptr};
 use learnopengl_shared::util;
+use crate::material::Material;
+use crate::nine_slice::NineSlice;
 use crate::shader::Shader;
 use crate::texture::Texture2D;
 
+// uniforms holding the colors of the corners of the quad
+const CORNER_COLORS: [&str; 4] = ["cornerColors[0]", "cornerColors[1]", "cornerColors[2]", "cornerColors[3]"];
+
+/// How a sprite is placed beyond its position, size and rotation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteTransform {
+    // mirrors the texture horizontally/vertically
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // point the sprite rotates around, relative to its size (0, 0 is the top left corner)
+    pub pivot: glm::TVec2<f32>,
+    // skews the sprite: x moves by shear.x per unit of y, y by shear.y per unit of x
+    pub shear: glm::TVec2<f32>
+}
+
+impl Default for SpriteTransform {
+    fn default() -> Self {
+        Self {
+            flip_x: false,
+            flip_y: false,
+            pivot: glm::vec2(0.5, 0.5),
+            shear: glm::vec2(0.0, 0.0)
+        }
+    }
+}
+
+impl SpriteTransform {
+    // the texture region drawn, mirrored as the flags say
+    pub fn tex_rect(&self, tex_rect: glm::TVec4<f32>) -> glm::TVec4<f32> {
+        let mut result = tex_rect;
+        if self.flip_x {
+            result.x += tex_rect.z;
+            result.z = -tex_rect.z;
+        }
+        if self.flip_y {
+            result.y += tex_rect.w;
+            result.w = -tex_rect.w;
+        }
+        result
+    }
+}
+
+/// Colors of the four corners of a sprite, blended over it (and multiplied with its color).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub top_left: glm::TVec3<f32>,
+    pub top_right: glm::TVec3<f32>,
+    pub bottom_left: glm::TVec3<f32>,
+    pub bottom_right: glm::TVec3<f32>
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self::solid(util::glm::scale_vec3(1.0))
+    }
+}
+
+impl Gradient {
+    pub fn solid(color: glm::TVec3<f32>) -> Self {
+        Self { top_left: color, top_right: color, bottom_left: color, bottom_right: color }
+    }
+
+    pub fn vertical(top: glm::TVec3<f32>, bottom: glm::TVec3<f32>) -> Self {
+        Self { top_left: top, top_right: top, bottom_left: bottom, bottom_right: bottom }
+    }
+
+    pub fn horizontal(left: glm::TVec3<f32>, right: glm::TVec3<f32>) -> Self {
+        Self { top_left: left, top_right: right, bottom_left: left, bottom_right: right }
+    }
+
+    // the color at a point relative to the size of the sprite (0, 0 is the top left corner)
+    pub fn at(&self, point: glm::TVec2<f32>) -> glm::TVec3<f32> {
+        let top = glm::lerp(&self.top_left, &self.top_right, point.x);
+        let bottom = glm::lerp(&self.bottom_left, &self.bottom_right, point.x);
+        glm::lerp(&top, &bottom, point.y)
+    }
+
+    // the part of the gradient over a region of the sprite, given by its offset and size relative to the sprite
+    pub fn region(&self, offset: glm::TVec2<f32>, size: glm::TVec2<f32>) -> Self {
+        Self {
+            top_left: self.at(offset),
+            top_right: self.at(offset + glm::vec2(size.x, 0.0)),
+            bottom_left: self.at(offset + glm::vec2(0.0, size.y)),
+            bottom_right: self.at(offset + size)
+        }
+    }
+
+    // the colors in the order of the cornerColors of the sprite shader
+    pub fn corners(&self) -> [glm::TVec3<f32>; 4] {
+        [self.top_left, self.top_right, self.bottom_left, self.bottom_right]
+    }
+
+    // every corner multiplied with the given color
+    pub fn tinted(&self, color: glm::TVec3<f32>) -> Self {
+        Self {
+            top_left: self.top_left.component_mul(&color),
+            top_right: self.top_right.component_mul(&color),
+            bottom_left: self.bottom_left.component_mul(&color),
+            bottom_right: self.bottom_right.component_mul(&color)
+        }
+    }
+}
+
 pub struct SpriteRenderer {
+    // scale applied to HUD sprites (UI scale option)
+    pub ui_scale: f32,
+
     // Render state
     shader: Shader,
     quad_vao: u32
@@ -31,6 +139,7 @@ impl SpriteRenderer {
     // Constructor (inits shaders/shapes)
     pub fn new(shader: Shader) -> Self {
         let mut result = Self {
+            ui_scale: 1.0,
             shader,
             quad_vao: 0
         };
@@ -81,6 +190,60 @@ impl SpriteRenderer {
         );
     }
 
+    // Renders a HUD element; its size is multiplied with the UI scale
+    pub fn draw_hud_sprite(
+        &self,
+        texture: &Texture2D,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        color: glm::TVec3<f32>
+    ) {
+        self.draw_sprite_ex2(
+            texture,
+            position,
+            size * self.ui_scale,
+            0.0,
+            color
+        );
+    }
+
+    // Renders a texture sliced into nine parts so its borders don't stretch
+    pub fn draw_nine_slice(
+        &self,
+        texture: &Texture2D,
+        slice: &NineSlice,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        color: glm::TVec3<f32>
+    ) {
+        self.draw_nine_slice_gradient(texture, slice, position, size, &Gradient::solid(color));
+    }
+
+    // Renders a nine-sliced texture with a gradient over the whole of it
+    pub fn draw_nine_slice_gradient(
+        &self,
+        texture: &Texture2D,
+        slice: &NineSlice,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        gradient: &Gradient
+    ) {
+        let texture_size = glm::vec2(texture.width as f32, texture.height as f32);
+        for piece in slice.pieces(position, size, texture_size).iter() {
+            if piece.size.x <= 0.0 || piece.size.y <= 0.0 {
+                continue;
+            }
+            self.draw_quad(
+                self.shader.use_shader(),
+                texture,
+                &Self::model_matrix(piece.position, piece.size, 0.0),
+                util::glm::scale_vec3(1.0),
+                &gradient.region((piece.position - position).component_div(&size), piece.size.component_div(&size)),
+                piece.tex_rect
+            );
+        }
+    }
+
     pub fn draw_sprite_ex0(
         &self,
         texture: &Texture2D,
@@ -119,21 +282,145 @@ impl SpriteRenderer {
         rotate: f32,
         color: glm::TVec3<f32>
     ) {
-        // prepare transformations
-        self.shader.use_shader();
+        self.draw_sprite_ex3(
+            texture,
+            position,
+            size,
+            rotate,
+            color,
+            None
+        );
+    }
+
+    // Renders the sprite with the given material instead of the sprite shader
+    pub fn draw_sprite_ex3(
+        &self,
+        texture: &Texture2D,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        rotate: f32,
+        color: glm::TVec3<f32>,
+        material: Option<&Material>
+    ) {
+        let shader = match material {
+            Some(material) => material.apply(),
+            None => self.shader.use_shader()
+        };
+        self.draw_quad(
+            shader,
+            texture,
+            &Self::model_matrix(position, size, rotate),
+            color,
+            &Gradient::default(),
+            glm::vec4(0.0, 0.0, 1.0, 1.0)
+        );
+    }
+
+    // Renders the sprite with a color for each of its corners
+    pub fn draw_sprite_gradient(
+        &self,
+        texture: &Texture2D,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        rotate: f32,
+        gradient: &Gradient
+    ) {
+        self.draw_quad(
+            self.shader.use_shader(),
+            texture,
+            &Self::model_matrix(position, size, rotate),
+            util::glm::scale_vec3(1.0),
+            gradient,
+            glm::vec4(0.0, 0.0, 1.0, 1.0)
+        );
+    }
+
+    // Renders the sprite flipped, rotated around a pivot or sheared
+    pub fn draw_sprite_ex4(
+        &self,
+        texture: &Texture2D,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        rotate: f32,
+        color: glm::TVec3<f32>,
+        transform: &SpriteTransform
+    ) {
+        self.draw_quad(
+            self.shader.use_shader(),
+            texture,
+            &Self::model_matrix_ex(position, size, rotate, transform),
+            color,
+            &Gradient::default(),
+            transform.tex_rect(glm::vec4(0.0, 0.0, 1.0, 1.0))
+        );
+    }
+
+    // Renders the given region of the texture (<offset, size> in texture coordinates)
+    pub fn draw_sprite_region(
+        &self,
+        texture: &Texture2D,
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        color: glm::TVec3<f32>,
+        tex_rect: glm::TVec4<f32>
+    ) {
+        self.draw_quad(
+            self.shader.use_shader(),
+            texture,
+            &Self::model_matrix(position, size, 0.0),
+            color,
+            &Gradient::default(),
+            tex_rect
+        );
+    }
+
+    // prepare transformations
+    pub fn model_matrix(
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        rotate: f32
+    ) -> glm::TMat4<f32> {
+        Self::model_matrix_ex(position, size, rotate, &SpriteTransform::default())
+    }
+
+    pub fn model_matrix_ex(
+        position: glm::TVec2<f32>,
+        size: glm::TVec2<f32>,
+        rotate: f32,
+        transform: &SpriteTransform
+    ) -> glm::TMat4<f32> {
+        let pivot = transform.pivot.component_mul(&size);
         let mut model = util::glm::diag_mat4(1.0);
-        model = glm::translate(&model, &util::glm::vec3_wrap_vec2(&position, 0.0)); // first translate (transformations are: scale happens first, then rotation, and then final translation happens; reversed order)
+        model = glm::translate(&model, &util::glm::vec3_wrap_vec2(&position, 0.0)); // first translate (transformations are: scale happens first, then shear, then rotation, and then final translation happens; reversed order)
 
-        model = glm::translate(&model, &glm::vec3(0.5 * size.x, 0.5 * size.y, 0.0)); // move origin of rotation to center of quad
+        model = glm::translate(&model, &glm::vec3(pivot.x, pivot.y, 0.0)); // move origin of rotation to the pivot
         model = glm::rotate(&model, rotate.to_radians(), &glm::vec3(0.0, 0.0, 1.0)); // then rotate
-        model = glm::translate(&model, &glm::vec3(-0.5 * size.x, -0.5 * size.y, 0.0)); // move origin back
+        let mut shear = util::glm::diag_mat4(1.0);
+        shear[(0, 1)] = transform.shear.x;
+        shear[(1, 0)] = transform.shear.y;
+        model *= shear; // skew around the pivot as well
+        model = glm::translate(&model, &glm::vec3(-pivot.x, -pivot.y, 0.0)); // move origin back
 
-        model = glm::scale(&model, &util::glm::vec3_wrap_vec2(&size, 1.0));
+        glm::scale(&model, &util::glm::vec3_wrap_vec2(&size, 1.0))
+    }
 
-        self.shader.set_matrix4("model", &model);
+    fn draw_quad(
+        &self,
+        shader: &Shader,
+        texture: &Texture2D,
+        model: &glm::TMat4<f32>,
+        color: glm::TVec3<f32>,
+        gradient: &Gradient,
+        tex_rect: glm::TVec4<f32>
+    ) {
+        shader.set_matrix4("model", model);
 
         // render textured quad
-        self.shader.set_vector3f("spriteColor", &color);
+        shader.set_vector3f("spriteColor", &color);
+        for (i, corner) in gradient.corners().iter().enumerate() {
+            shader.set_vector3f(CORNER_COLORS[i], corner);
+        }
+        shader.set_vector4f("texRect", &tex_rect);
 
         unsafe {
             gl::ActiveTexture(gl::TEXTURE0);
@@ -153,4 +440,53 @@ impl Drop for SpriteRenderer {
             gl::DeleteVertexArrays(1, &self.quad_vao);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // where the model matrix puts a corner of the unit quad
+    fn corner(model: &glm::TMat4<f32>, x: f32, y: f32) -> glm::TVec2<f32> {
+        let point = model * glm::vec4(x, y, 0.0, 1.0);
+        glm::vec2(point.x, point.y)
+    }
+
+    #[test]
+    fn sprite_renderer_test_pivot_and_shear() {
+        let (position, size) = (glm::vec2(10.0, 20.0), glm::vec2(40.0, 20.0));
+        // the default transform rotates around the center, like model_matrix always did
+        let center = SpriteRenderer::model_matrix(position, size, 90.0);
+        assert!(glm::distance(&corner(&center, 0.5, 0.5), &glm::vec2(30.0, 30.0)) < 1e-4);
+        // rotating around the top left corner keeps that corner in place
+        let transform = SpriteTransform { pivot: glm::vec2(0.0, 0.0), ..SpriteTransform::default() };
+        let model = SpriteRenderer::model_matrix_ex(position, size, 90.0, &transform);
+        assert!(glm::distance(&corner(&model, 0.0, 0.0), &position) < 1e-4);
+        assert!(glm::distance(&corner(&model, 1.0, 0.0), &glm::vec2(10.0, 60.0)) < 1e-4);
+        // shearing around the bottom edge moves the top edge sideways
+        let transform = SpriteTransform { pivot: glm::vec2(0.0, 1.0), shear: glm::vec2(0.5, 0.0), ..SpriteTransform::default() };
+        let model = SpriteRenderer::model_matrix_ex(position, size, 0.0, &transform);
+        assert!(glm::distance(&corner(&model, 0.0, 1.0), &glm::vec2(10.0, 40.0)) < 1e-4);
+        assert!(glm::distance(&corner(&model, 0.0, 0.0), &glm::vec2(0.0, 20.0)) < 1e-4);
+    }
+
+    #[test]
+    fn sprite_renderer_test_gradient() {
+        let (top, bottom) = (glm::vec3(1.0, 1.0, 1.0), glm::vec3(0.0, 0.5, 0.0));
+        let gradient = Gradient::vertical(top, bottom);
+        assert_eq!(gradient.at(glm::vec2(0.3, 0.0)), top);
+        assert_eq!(gradient.at(glm::vec2(0.7, 0.5)), glm::vec3(0.5, 0.75, 0.5));
+        // the lower half of the sprite starts where the upper one ends
+        let lower = gradient.region(glm::vec2(0.0, 0.5), glm::vec2(1.0, 0.5));
+        assert_eq!(lower, Gradient::vertical(glm::vec3(0.5, 0.75, 0.5), bottom));
+        assert_eq!(Gradient::horizontal(top, bottom).tinted(glm::vec3(0.5, 1.0, 1.0)).corners()[1], glm::vec3(0.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn sprite_renderer_test_flip() {
+        let region = glm::vec4(0.25, 0.0, 0.5, 1.0);
+        assert_eq!(SpriteTransform::default().tex_rect(region), region);
+        let flipped = SpriteTransform { flip_x: true, flip_y: true, ..SpriteTransform::default() };
+        assert_eq!(flipped.tex_rect(region), glm::vec4(0.75, 1.0, -0.5, -1.0));
+    }
+}
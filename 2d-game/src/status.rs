@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+// seconds between two burn ticks, each taking one hit point per stack
+pub const BURN_TICK: f32 = 0.5;
+// most burns a single object can carry at once
+pub const BURN_MAX_STACKS: u32 = 3;
+// speed of a slowed object, as a fraction of its usual speed
+pub const SLOW_FACTOR: f32 = 0.5;
+// how strongly the tint of an effect shows over the color of the object
+const TINT_AMOUNT: f32 = 0.5;
+
+/// Kind of a status effect an object can carry.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StatusKind {
+    // moves at SLOW_FACTOR of its speed
+    Slow,
+    // loses hit points over time, but never its last one
+    Burn,
+    // doesn't move at all
+    Frozen
+}
+
+impl StatusKind {
+    // how often the effect can be stacked; applying it again beyond that only refreshes its duration
+    pub fn max_stacks(self) -> u32 {
+        match self {
+            StatusKind::Burn => BURN_MAX_STACKS,
+            StatusKind::Slow | StatusKind::Frozen => 1
+        }
+    }
+
+    // color the object is tinted towards while carrying the effect
+    pub fn tint(self) -> glm::TVec3<f32> {
+        match self {
+            StatusKind::Slow => glm::vec3(0.5, 0.6, 1.0),
+            StatusKind::Burn => glm::vec3(1.0, 0.45, 0.1),
+            StatusKind::Frozen => glm::vec3(0.75, 0.95, 1.0)
+        }
+    }
+}
+
+/// A status effect carried by an object.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    // seconds until the effect wears off
+    pub time_left: f32,
+    pub stacks: u32,
+    // seconds since the last tick
+    since_tick: f32
+}
+
+/// The status effects of an object. Each kind is carried at most once, with its stacks.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>
+}
+
+impl StatusEffects {
+    // applies an effect for the given number of seconds; an effect which is already carried gains a stack
+    // (up to its maximum) and keeps the longer of both durations
+    pub fn apply(&mut self, kind: StatusKind, duration: f32) {
+        match self.effects.iter_mut().find(|it| it.kind == kind) {
+            Some(effect) => {
+                effect.stacks = (effect.stacks + 1).min(kind.max_stacks());
+                effect.time_left = effect.time_left.max(duration);
+            }
+            None => self.effects.push(StatusEffect { kind, time_left: duration, stacks: 1, since_tick: 0.0 })
+        }
+    }
+
+    // advances the effects, dropping the ones which wore off; returns the hit points burns take this tick
+    pub fn update(&mut self, dt: f32) -> u32 {
+        let mut damage = 0;
+        for effect in self.effects.iter_mut() {
+            effect.time_left -= dt;
+            effect.since_tick += dt;
+            if effect.kind == StatusKind::Burn && effect.time_left > 0.0 && effect.since_tick >= BURN_TICK {
+                effect.since_tick -= BURN_TICK;
+                damage += effect.stacks;
+            }
+        }
+        self.effects.retain(|it| it.time_left > 0.0);
+        damage
+    }
+
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|it| it.kind == kind)
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    // multiplier of the speed the object moves at
+    pub fn speed_factor(&self) -> f32 {
+        if self.has(StatusKind::Frozen) {
+            0.0
+        } else if self.has(StatusKind::Slow) {
+            SLOW_FACTOR
+        } else {
+            1.0
+        }
+    }
+
+    // the given color, tinted by the effect applied most recently
+    pub fn tinted(&self, color: glm::TVec3<f32>) -> glm::TVec3<f32> {
+        match self.effects.last() {
+            Some(effect) => glm::mix(&color, &effect.kind.tint(), TINT_AMOUNT),
+            None => color
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_test_stacking() {
+        let mut status = StatusEffects::default();
+        status.apply(StatusKind::Slow, 2.0);
+        status.apply(StatusKind::Slow, 1.0);
+        assert_eq!(status.get(StatusKind::Slow).unwrap().stacks, 1);
+        assert_eq!(status.get(StatusKind::Slow).unwrap().time_left, 2.0);
+        for _ in 0..5 {
+            status.apply(StatusKind::Burn, 1.0);
+        }
+        assert_eq!(status.get(StatusKind::Burn).unwrap().stacks, BURN_MAX_STACKS);
+    }
+
+    #[test]
+    fn status_test_burn_ticks() {
+        let mut status = StatusEffects::default();
+        status.apply(StatusKind::Burn, 1.2);
+        status.apply(StatusKind::Burn, 1.2);
+        assert_eq!(status.update(0.4), 0);
+        assert_eq!(status.update(0.2), 2);
+        assert_eq!(status.update(0.5), 2);
+        // wears off before the third tick
+        assert_eq!(status.update(0.5), 0);
+        assert!(status.is_empty());
+    }
+
+    #[test]
+    fn status_test_speed_and_tint() {
+        let mut status = StatusEffects::default();
+        let color = glm::vec3(0.2, 0.2, 0.2);
+        assert_eq!(status.speed_factor(), 1.0);
+        assert_eq!(status.tinted(color), color);
+        status.apply(StatusKind::Slow, 1.0);
+        assert_eq!(status.speed_factor(), SLOW_FACTOR);
+        status.apply(StatusKind::Frozen, 0.5);
+        assert_eq!(status.speed_factor(), 0.0);
+        assert_eq!(status.tinted(color), glm::mix(&color, &StatusKind::Frozen.tint(), TINT_AMOUNT));
+        status.update(0.6);
+        assert_eq!(status.speed_factor(), SLOW_FACTOR);
+    }
+}
@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{mem, ptr};
+use gl::types::GLsync;
+
+// Size of the ring given to each streaming user (text, particles)
+pub const DEFAULT_STREAM_SIZE: usize = 256 * 1024;
+// Number of segments the ring is split into; each one is fenced separately
+const SEGMENTS: usize = 3;
+// Segments start on this boundary so offsets stay aligned for any vertex layout
+const SEGMENT_ALIGNMENT: usize = 256;
+
+/// A range handed out by the ring. When the write moved on to the next
+/// segment, `advanced` holds the (left, entered) segment indices.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Allocation {
+    pub offset: usize,
+    pub advanced: Option<(usize, usize)>
+}
+
+// Bookkeeping half of the stream buffer: hands out ranges one after another and
+// moves on to the next segment whenever the current one is full. Allocations never
+// straddle two segments, so every segment can be guarded by a single fence.
+#[derive(Copy, Clone, Debug)]
+pub struct Ring {
+    segment_size: usize,
+    current: usize,
+    offset: usize
+}
+
+impl Ring {
+    pub fn new(size: usize) -> Self {
+        let segment_size = size / SEGMENTS / SEGMENT_ALIGNMENT * SEGMENT_ALIGNMENT;
+        assert!(segment_size > 0, "ERROR::STREAM_BUFFER: ring of {} bytes is too small", size);
+        Self {
+            segment_size,
+            current: 0,
+            offset: 0
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.segment_size * SEGMENTS
+    }
+
+    pub fn segment_size(&self) -> usize {
+        self.segment_size
+    }
+
+    // reserves len bytes aligned to align inside the current segment, moving on to the next one if needed
+    pub fn allocate(&mut self, len: usize, align: usize) -> Allocation {
+        assert!(len <= self.segment_size, "ERROR::STREAM_BUFFER: {} bytes do not fit into a segment of {} bytes", len, self.segment_size);
+        let start = self.offset.div_ceil(align.max(1)) * align.max(1);
+        if start + len <= (self.current + 1) * self.segment_size {
+            self.offset = start + len;
+            return Allocation {
+                offset: start,
+                advanced: None
+            };
+        }
+        let left = self.current;
+        self.current = (self.current + 1) % SEGMENTS;
+        let start = self.current * self.segment_size;
+        self.offset = start + len;
+        Allocation {
+            offset: start,
+            advanced: Some((left, self.current))
+        }
+    }
+}
+
+// Ring buffer used to stream per-frame vertex data without stalling on the GPU.
+// Uses a persistently mapped buffer guarded by one fence per segment when
+// glBufferStorage is available, otherwise writes through unsynchronized maps and
+// orphans the whole buffer each time the ring wraps around.
+pub struct StreamBuffer {
+    id: u32,
+    ring: Ring,
+    // persistent mapping (null when orphaning)
+    mapped: *mut u8,
+    fences: [GLsync; SEGMENTS]
+}
+
+impl StreamBuffer {
+    // constructor
+    pub fn new(size: usize) -> Self {
+        let ring = Ring::new(size);
+        let mut result = Self {
+            id: 0,
+            ring,
+            mapped: ptr::null_mut(),
+            fences: [ptr::null(); SEGMENTS]
+        };
+        unsafe {
+            gl::GenBuffers(1, &mut result.id);
+            gl::BindBuffer(gl::ARRAY_BUFFER, result.id);
+            if gl::BufferStorage::is_loaded() && gl::FenceSync::is_loaded() {
+                let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+                gl::BufferStorage(gl::ARRAY_BUFFER, ring.size() as _, ptr::null(), flags);
+                result.mapped = gl::MapBufferRange(gl::ARRAY_BUFFER, 0, ring.size() as _, flags) as *mut u8;
+                if result.mapped.is_null() {
+                    log::warn!("STREAM_BUFFER: persistent mapping failed, falling back to orphaning");
+                }
+            }
+            if result.mapped.is_null() {
+                // buffer storage is immutable, so start over with a fresh buffer
+                gl::DeleteBuffers(1, &result.id);
+                gl::GenBuffers(1, &mut result.id);
+                gl::BindBuffer(gl::ARRAY_BUFFER, result.id);
+                gl::BufferData(gl::ARRAY_BUFFER, ring.size() as _, ptr::null(), gl::STREAM_DRAW);
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        result
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn size(&self) -> usize {
+        self.ring.size()
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        !self.mapped.is_null()
+    }
+
+    // copies data into the ring and returns the byte offset it was written to
+    // (aligned to the element size; leaves the buffer bound to GL_ARRAY_BUFFER)
+    pub fn push<T: Copy>(&mut self, data: &[T]) -> usize {
+        let len = mem::size_of_val(data);
+        let allocation = self.ring.allocate(len, mem::size_of::<T>());
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
+            if let Some((left, entered)) = allocation.advanced {
+                self.advance(left, entered);
+            }
+            if self.is_persistent() {
+                ptr::copy_nonoverlapping(data.as_ptr() as *const u8, self.mapped.add(allocation.offset), len);
+            } else {
+                // the range was never written since the last orphaning, so no need to synchronize
+                let flags = gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_RANGE_BIT | gl::MAP_UNSYNCHRONIZED_BIT;
+                let target = gl::MapBufferRange(gl::ARRAY_BUFFER, allocation.offset as _, len as _, flags) as *mut u8;
+                if target.is_null() {
+                    gl::BufferSubData(gl::ARRAY_BUFFER, allocation.offset as _, len as _, data.as_ptr() as _);
+                } else {
+                    ptr::copy_nonoverlapping(data.as_ptr() as *const u8, target, len);
+                    gl::UnmapBuffer(gl::ARRAY_BUFFER);
+                }
+            }
+        }
+        allocation.offset
+    }
+
+    unsafe fn advance(&mut self, left: usize, entered: usize) {
+        if self.is_persistent() {
+            // everything drawn from the segment we leave is guarded by a fence
+            self.fences[left] = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            // and the segment we are about to overwrite must no longer be in use
+            let fence = mem::replace(&mut self.fences[entered], ptr::null());
+            if !fence.is_null() {
+                while gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, 1_000_000) == gl::TIMEOUT_EXPIRED {}
+                gl::DeleteSync(fence);
+            }
+        } else if entered == 0 {
+            // wrapped around: orphan the storage so the driver hands out a fresh one
+            gl::BufferData(gl::ARRAY_BUFFER, self.ring.size() as _, ptr::null(), gl::STREAM_DRAW);
+        }
+    }
+}
+
+impl Drop for StreamBuffer {
+    // Destructor
+    fn drop(&mut self) {
+        unsafe {
+            for fence in self.fences {
+                if !fence.is_null() {
+                    gl::DeleteSync(fence);
+                }
+            }
+            if self.is_persistent() {
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
+                gl::UnmapBuffer(gl::ARRAY_BUFFER);
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            }
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_buffer_test_allocations_are_aligned_and_sequential() {
+        let mut ring = Ring::new(3 * 1024);
+        assert_eq!(ring.segment_size(), 1024);
+        assert_eq!(ring.allocate(10, 4), Allocation { offset: 0, advanced: None });
+        assert_eq!(ring.allocate(16, 16), Allocation { offset: 16, advanced: None });
+        assert_eq!(ring.allocate(8, 8), Allocation { offset: 32, advanced: None });
+    }
+
+    #[test]
+    fn stream_buffer_test_full_segment_moves_on_and_wraps() {
+        let mut ring = Ring::new(3 * 1024);
+        ring.allocate(1000, 4);
+        // does not fit behind the first allocation, so it starts the next segment
+        assert_eq!(ring.allocate(100, 4), Allocation { offset: 1024, advanced: Some((0, 1)) });
+        assert_eq!(ring.allocate(1024, 4), Allocation { offset: 2048, advanced: Some((1, 2)) });
+        assert_eq!(ring.allocate(4, 4), Allocation { offset: 0, advanced: Some((2, 0)) });
+    }
+}
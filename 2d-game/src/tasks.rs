@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+// something a task does to the game once it yields
+type Command<C> = Box<dyn FnOnce(&mut C)>;
+// something a task waits for to become true
+type Condition<C> = Box<dyn Fn(&C) -> bool>;
+
+// what a task shares with the script handle its sequence holds
+struct Shared<C> {
+    // seconds the task has been running for
+    time: Cell<f32>,
+    commands: RefCell<Vec<Command<C>>>,
+    // the condition the task is waiting for, checked before it is resumed
+    condition: RefCell<Option<Condition<C>>>,
+    met: Cell<bool>
+}
+
+/// The handle a scripted sequence uses to wait and to act on the game. Sequences are
+/// written as async blocks which are resumed once a frame by [Tasks::pump], e.g.
+/// `script.run(|game| ...); script.wait(1.0).await; script.run(|game| ...);`
+pub struct Script<C> {
+    shared: Rc<Shared<C>>
+}
+
+struct Task<C> {
+    shared: Rc<Shared<C>>,
+    sequence: Pin<Box<dyn Future<Output = ()>>>
+}
+
+/// Cooperative tasks running on the game loop: each one runs until its next wait and is
+/// resumed by the pump once that's over, so it never blocks the frame.
+pub struct Tasks<C> {
+    tasks: Vec<Task<C>>
+}
+
+// waits for the time of the task to reach a deadline
+pub struct Wait<C> {
+    shared: Rc<Shared<C>>,
+    deadline: f32
+}
+
+// waits for a condition on the game to become true
+pub struct Until<C> {
+    shared: Rc<Shared<C>>,
+    condition: Option<Condition<C>>
+}
+
+// waits for the next frame
+pub struct NextFrame {
+    yielded: bool
+}
+
+impl<C> Script<C> {
+    // runs a command on the game once the task yields
+    pub fn run(&self, command: impl FnOnce(&mut C) + 'static) {
+        self.shared.commands.borrow_mut().push(Box::new(command));
+    }
+
+    pub fn wait(&self, seconds: f32) -> Wait<C> {
+        Wait { shared: self.shared.clone(), deadline: self.shared.time.get() + seconds }
+    }
+
+    // checked once a frame, starting with the next one
+    pub fn until(&self, condition: impl Fn(&C) -> bool + 'static) -> Until<C> {
+        Until { shared: self.shared.clone(), condition: Some(Box::new(condition)) }
+    }
+
+    pub fn next_frame(&self) -> NextFrame {
+        NextFrame { yielded: false }
+    }
+
+    // seconds since the task was spawned
+    pub fn time(&self) -> f32 {
+        self.shared.time.get()
+    }
+}
+
+impl<C> Future for Wait<C> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        if self.shared.time.get() >= self.deadline {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<C> Future for Until<C> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        if self.shared.met.replace(false) {
+            return Poll::Ready(());
+        }
+        if let Some(condition) = self.condition.take() {
+            *self.shared.condition.borrow_mut() = Some(condition);
+        }
+        Poll::Pending
+    }
+}
+
+impl Future for NextFrame {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            Poll::Pending
+        }
+    }
+}
+
+impl<C> Default for Tasks<C> {
+    fn default() -> Self {
+        Self { tasks: Vec::new() }
+    }
+}
+
+impl<C> Tasks<C> {
+    // starts a sequence; it runs up to its first wait on the next pump
+    pub fn spawn<F, S>(&mut self, sequence: F)
+    where
+        F: FnOnce(Script<C>) -> S,
+        S: Future<Output = ()> + 'static
+    {
+        let shared = Rc::new(Shared {
+            time: Cell::new(0.0),
+            commands: RefCell::new(Vec::new()),
+            condition: RefCell::new(None),
+            met: Cell::new(false)
+        });
+        let sequence = sequence(Script { shared: shared.clone() });
+        self.tasks.push(Task { shared, sequence: Box::pin(sequence) });
+    }
+
+    // advances the tasks by dt seconds, resuming the ones whose wait is over
+    pub fn pump(&mut self, ctx: &mut C, dt: f32) {
+        let mut cx = Context::from_waker(Waker::noop());
+        self.tasks.retain_mut(|task| {
+            let shared = &task.shared;
+            shared.time.set(shared.time.get() + dt);
+            let waiting = shared.condition.borrow().as_ref().is_some_and(|condition| !condition(ctx));
+            if waiting {
+                return true;
+            }
+            if shared.condition.borrow_mut().take().is_some() {
+                shared.met.set(true);
+            }
+            let done = task.sequence.as_mut().poll(&mut cx).is_ready();
+            let commands: Vec<Command<C>> = shared.commands.borrow_mut().drain(..).collect();
+            for command in commands {
+                command(ctx);
+            }
+            !done
+        });
+    }
+
+    // moves the tasks spawned into another list (e.g. by a command while pumping) over to this one
+    pub fn append(&mut self, other: &mut Tasks<C>) {
+        self.tasks.append(&mut other.tasks);
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    // drops all tasks, wherever they are in their sequence
+    pub fn clear(&mut self) {
+        self.tasks.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log {
+        lines: Vec<&'static str>,
+        launched: bool
+    }
+
+    #[test]
+    fn tasks_test_sequence() {
+        let mut tasks = Tasks::default();
+        tasks.spawn(|script| async move {
+            script.run(|log: &mut Log| log.lines.push("flash"));
+            script.wait(1.0).await;
+            script.run(|log| log.lines.push("spawn"));
+            script.wait(0.5).await;
+            script.run(|log| log.lines.push("input"));
+        });
+        let mut log = Log::default();
+        tasks.pump(&mut log, 0.0);
+        assert_eq!(log.lines, ["flash"]);
+        for _ in 0..9 {
+            tasks.pump(&mut log, 0.1);
+        }
+        assert_eq!(log.lines, ["flash"]);
+        tasks.pump(&mut log, 0.1);
+        assert_eq!(log.lines, ["flash", "spawn"]);
+        tasks.pump(&mut log, 0.5);
+        assert_eq!(log.lines, ["flash", "spawn", "input"]);
+        assert!(tasks.is_empty());
+    }
+
+    #[test]
+    fn tasks_test_until() {
+        let mut tasks = Tasks::default();
+        tasks.spawn(|script| async move {
+            script.until(|log: &Log| log.launched).await;
+            script.run(|log| log.lines.push("launched"));
+            script.next_frame().await;
+            script.run(|log| log.lines.push("next"));
+        });
+        let mut log = Log::default();
+        for _ in 0..3 {
+            tasks.pump(&mut log, 0.1);
+        }
+        assert!(log.lines.is_empty());
+        log.launched = true;
+        tasks.pump(&mut log, 0.1);
+        assert_eq!(log.lines, ["launched"]);
+        tasks.pump(&mut log, 0.1);
+        assert_eq!(log.lines, ["launched", "next"]);
+        assert_eq!(tasks.len(), 0);
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::f32::consts::TAU;
+use crate::text_renderer::GlyphQuad;
+
+// Text effects move the glyphs of a string after it has been laid out, each glyph on its own: a
+// typewriter reveals the string a glyph at a time, a wave bobs the glyphs one after another and a
+// shake jitters them around. Animated text is laid out anew each frame (it isn't cached).
+
+/// How the glyphs of a string are animated.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextEffect {
+    // reveals the string a glyph at a time
+    Typewriter { chars_per_second: f32 },
+    // moves the glyphs up and down, `amplitude` pixels (at a scale of 1.0) `speed` times a second,
+    // each glyph `spread` of a period behind the one before it
+    Wave { amplitude: f32, speed: f32, spread: f32 },
+    // moves each glyph to a random place up to `amplitude` pixels away, `rate` times a second
+    Shake { amplitude: f32, rate: f32 }
+}
+
+/// An effect and how far into it (in seconds) a string is drawn.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextAnimation {
+    pub effect: TextEffect,
+    pub time: f32
+}
+
+impl TextAnimation {
+    pub fn new(effect: TextEffect, time: f32) -> Self {
+        Self { effect, time }
+    }
+
+    // moves laid out glyphs, returning how many of them are shown (those at the start)
+    pub fn apply(&self, glyphs: &mut [(u32, GlyphQuad)], scale: f32) -> usize {
+        match self.effect {
+            TextEffect::Typewriter { chars_per_second } => ((self.time.max(0.0) * chars_per_second) as usize).min(glyphs.len()),
+            TextEffect::Wave { amplitude, speed, spread } => {
+                for (i, (_, quad)) in glyphs.iter_mut().enumerate() {
+                    let phase = (self.time * speed - i as f32 * spread) * TAU;
+                    translate(quad, 0.0, -phase.sin() * amplitude * scale);
+                }
+                glyphs.len()
+            }
+            TextEffect::Shake { amplitude, rate } => {
+                let step = (self.time.max(0.0) * rate) as u32;
+                for (i, (_, quad)) in glyphs.iter_mut().enumerate() {
+                    translate(quad, noise(i as u32, step, 0) * amplitude * scale, noise(i as u32, step, 1) * amplitude * scale);
+                }
+                glyphs.len()
+            }
+        }
+    }
+
+    // whether the string has been revealed completely (effects other than the typewriter are never done)
+    pub fn is_finished(&self, glyphs: usize) -> bool {
+        match self.effect {
+            TextEffect::Typewriter { chars_per_second } => self.time * chars_per_second >= glyphs as f32,
+            _ => false
+        }
+    }
+}
+
+fn translate(quad: &mut GlyphQuad, x: f32, y: f32) {
+    for vertex in quad.iter_mut() {
+        vertex[0] += x;
+        vertex[1] += y;
+    }
+}
+
+// a number between -1.0 and 1.0 which looks random, the same for the same arguments
+fn noise(index: u32, step: u32, axis: u32) -> f32 {
+    let mut hash = index.wrapping_mul(0x9E37_79B9) ^ step.wrapping_mul(0x85EB_CA6B) ^ axis.wrapping_mul(0xC2B2_AE35);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    (hash as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_renderer::glyph_quad;
+
+    fn glyphs(count: usize) -> Vec<(u32, GlyphQuad)> {
+        (0..count).map(|i| (1, glyph_quad(i as f32 * 10.0, 0.0, glm::vec2(8, 10), glm::vec2(0, 10), 10, 1.0))).collect()
+    }
+
+    #[test]
+    fn animation_test_typewriter() {
+        let typewriter = TextEffect::Typewriter { chars_per_second: 20.0 };
+        let mut laid_out = glyphs(5);
+        assert_eq!(TextAnimation::new(typewriter, 0.0).apply(&mut laid_out, 1.0), 0);
+        assert_eq!(TextAnimation::new(typewriter, 0.1).apply(&mut laid_out, 1.0), 2);
+        assert_eq!(TextAnimation::new(typewriter, 10.0).apply(&mut laid_out, 1.0), 5);
+        // the glyphs shown stay where they are
+        assert_eq!(laid_out, glyphs(5));
+        assert!(!TextAnimation::new(typewriter, 0.2).is_finished(5));
+        assert!(TextAnimation::new(typewriter, 0.25).is_finished(5));
+    }
+
+    #[test]
+    fn animation_test_wave() {
+        let wave = TextAnimation::new(TextEffect::Wave { amplitude: 4.0, speed: 1.0, spread: 0.25 }, 0.25);
+        let mut laid_out = glyphs(3);
+        assert_eq!(wave.apply(&mut laid_out, 2.0), 3);
+        // a quarter of a period in, the first glyph is at the top, the second one back in place
+        assert!((laid_out[0].1[0][1] - (10.0 - 8.0)).abs() < 1e-4);
+        assert!((laid_out[1].1[0][1] - 10.0).abs() < 1e-4);
+        assert_eq!(laid_out[2].1[0][0], 20.0);
+    }
+
+    #[test]
+    fn animation_test_shake() {
+        let shake = |time| {
+            let mut laid_out = glyphs(4);
+            TextAnimation::new(TextEffect::Shake { amplitude: 3.0, rate: 10.0 }, time).apply(&mut laid_out, 1.0);
+            laid_out
+        };
+        let original = glyphs(4);
+        let shaken = shake(0.05);
+        for (glyph, original) in shaken.iter().zip(original.iter()) {
+            let offset = glm::vec2(glyph.1[0][0] - original.1[0][0], glyph.1[0][1] - original.1[0][1]);
+            assert!(offset.x.abs() <= 3.0 && offset.y.abs() <= 3.0);
+            // the whole glyph moves
+            assert!((glyph.1[4][0] - original.1[4][0] - offset.x).abs() < 1e-4);
+        }
+        // the same within a step, elsewhere in the next one
+        assert_eq!(shake(0.01), shaken);
+        assert_ne!(shake(0.15), shaken);
+    }
+}
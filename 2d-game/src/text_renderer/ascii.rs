@@ -18,16 +18,23 @@ extern crate nalgebra_glm as glm;
 
 use std::collections::HashMap;
 use std::{mem, ptr};
-use std::ffi::CString;
+use std::cell::{Cell, RefCell};
+use bumpalo::Bump;
+use bumpalo::collections::Vec as BumpVec;
 use freetype::freetype::{
-    FT_Done_Face, FT_Done_FreeType, FT_Face, FT_Init_FreeType,
-    FT_Library, FT_Load_Char, FT_LOAD_RENDER, FT_New_Face,
-    FT_Set_Pixel_Sizes
+    FT_Done_Face, FT_Done_FreeType, FT_Init_FreeType,
+    FT_Library, FT_Load_Char, FT_LOAD_RENDER
 };
 use learnopengl_shared::{filesystem, util};
+use crate::frame_capture;
 use crate::resource_manager;
 use crate::shader::Shader;
-use crate::text_renderer::ITextRenderer;
+use crate::stream_buffer::{StreamBuffer, DEFAULT_STREAM_SIZE};
+use crate::gl_api::NativeGl;
+use crate::frame_arena;
+use crate::text_renderer::{draw_glyphs, face_for, glyph_quad, open_faces, GlyphQuad, ITextRenderer, TextSpace};
+use crate::text_renderer::animation::TextAnimation;
+use crate::text_renderer::cache::{self, Lookup, TextCache};
 
 /// Holds all state information relevant to a character as loaded using FreeType
 #[derive(Copy, Clone, Default)]
@@ -46,20 +53,37 @@ pub struct TextRenderer {
     pub characters: HashMap<u8, Character>,
     // shader used for text rendering
     pub text_shader: Shader,
+    // scale applied to the text rendered on the screen (UI scale option)
+    pub ui_scale: f32,
 
     // render state
     vao: u32,
-    vbo: u32
+    stream: RefCell<StreamBuffer>,
+    // projection of the screen
+    projection: glm::TMat4<f32>,
+    // where text is placed
+    space: Cell<TextSpace>,
+    // textures of the strings drawn unchanged for a while
+    cache: RefCell<TextCache>
 }
 
 impl TextRenderer {
     // constructor
     pub fn new(width: u32, height: u32) -> Self {
+        Self::new_ex(width, height, "text")
+    }
+
+    // constructor (with the name of its shader program, for a renderer with a projection of its own)
+    pub fn new_ex(width: u32, height: u32, shader_name: &str) -> Self {
         let mut result = Self {
             characters: HashMap::new(),
             text_shader: Shader::new(),
+            ui_scale: 1.0,
             vao: u32::default(),
-            vbo: u32::default()
+            stream: RefCell::new(StreamBuffer::new(DEFAULT_STREAM_SIZE)),
+            projection: util::glm::ortho(0.0, width as _, height as _, 0.0),
+            space: Cell::new(TextSpace::Screen),
+            cache: RefCell::new(TextCache::default())
         };
 
         // load and configure shader
@@ -67,48 +91,58 @@ impl TextRenderer {
             filesystem::get_path("resources/shaders/text_2d.vs".to_string()).as_str(),
             filesystem::get_path("resources/shaders/text_2d.fs".to_string()).as_str(),
             None,
-            "text".to_string()
+            shader_name.to_string()
         );
-        result.text_shader.set_matrix4_ex("projection", &util::glm::ortho(0.0, width as _, height as _, 0.0), true);
+        result.text_shader.set_matrix4_ex("projection", &result.projection, true);
         result.text_shader.set_integer("text", 0);
         unsafe {
             // configure VAO/VBO for texture quads
             gl::GenVertexArrays(1, &mut result.vao);
-            gl::GenBuffers(1, &mut result.vbo);
             gl::BindVertexArray(result.vao);
-            gl::BindBuffer(gl::ARRAY_BUFFER, result.vbo);
-            gl::BufferData(gl::ARRAY_BUFFER, (mem::size_of::<f32>() * 6 * 4) as _, ptr::null(), gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::ARRAY_BUFFER, result.stream.borrow().id());
             gl::EnableVertexAttribArray(0);
             gl::VertexAttribPointer(0, 4, gl::FLOAT, gl::FALSE, (4 * mem::size_of::<f32>()) as _, ptr::null());
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::BindVertexArray(0);
         }
+        resource_manager::track_gpu_memory("text_stream_ascii", result.stream.borrow().size());
 
         result
     }
 
     // pre-compiles a list of characters from the given font
     pub fn load(&mut self, font: String, font_size: u32) {
-        // first clear the previously loaded Characters
-        self.characters.clear();
+        self.load_fonts(&[font], font_size);
+    }
+
+    // pre-compiles a list of characters, each from the first of the fonts having it
+    // (replacing those compiled before)
+    pub fn load_fonts(&mut self, fonts: &[String], font_size: u32) {
+        // first clear the previously loaded Characters (and the text drawn with them)
+        let cache = self.cache.get_mut();
+        cache.clear();
+        cache.delete_evicted();
+        resource_manager::track_gpu_memory("text_cache_ascii", 0);
+        for character in self.characters.drain().map(|(_, it)| it) {
+            unsafe { gl::DeleteTextures(1, &character.texture_id); }
+        }
         unsafe {
             // then initialize and load the FreeType library
             let mut ft: FT_Library = ptr::null_mut();
             if FT_Init_FreeType(&mut ft) != 0 { // all functions return a value different than 0 whenever an error occurred
                 log::error!("ERROR::FREETYPE: Could not init FreeType Library");
             }
-            // load font as face
-            let mut face: FT_Face = ptr::null_mut();
-            let font = CString::new(font).unwrap();
-            if FT_New_Face(ft, font.as_ptr(), 0, &mut face) != 0 {
-                log::error!("ERROR::FREETYPE: Failed to load font");
+            // load fonts as faces (the built-in one for those which can't be)
+            let faces = open_faces(ft, fonts, font_size);
+            if faces.is_empty() {
+                FT_Done_FreeType(ft);
+                return;
             }
-            // set size to load glyphs as
-            FT_Set_Pixel_Sizes(face, 0, font_size);
             // disable byte-alignment restriction
             gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
             // then for the first 128 ASCII characters, pre-load/compile their characters and store them
             for c in 0u8..128 { // lol see what I did there
+                let face = face_for(&faces, c as char).unwrap();
                 // load character glyph
                 if FT_Load_Char(face, c as _, FT_LOAD_RENDER as _) != 0 {
                     log::error!("ERROR::FREETYTPE: Failed to load Glyph");
@@ -145,11 +179,29 @@ impl TextRenderer {
                 self.characters.insert(c, character);
             }
             gl::BindTexture(gl::TEXTURE_2D, 0);
+            let glyph_memory = self.characters.values().map(|it| (it.size.x * it.size.y) as usize).sum();
+            resource_manager::track_gpu_memory("glyphs_ascii", glyph_memory);
             // destroy FreeType once we're finished
-            FT_Done_Face(face);
+            for face in faces {
+                FT_Done_Face(face);
+            }
             FT_Done_FreeType(ft);
         }
     }
+
+    // the texture and quad of each glyph of a line of text
+    fn layout<'a>(&self, arena: &'a Bump, text: &str, mut x: f32, y: f32, scale: f32) -> BumpVec<'a, (u32, GlyphQuad)> {
+        // iterate through all characters
+        let mut glyphs = BumpVec::with_capacity_in(text.len(), arena);
+        let top = self.characters.get(&b'H').map(|ch| ch.bearing.y).unwrap_or(0);
+        // characters without a glyph (not ASCII, or no font at all) are left out
+        for ch in text.bytes().filter_map(|c| self.characters.get(&c)) {
+            glyphs.push((ch.texture_id, glyph_quad(x, y, ch.size, ch.bearing, top, scale)));
+            // now advance cursors for next glyph
+            x += (ch.advance >> 6) as f32 * scale; // bitshift by 6 to get value in pixels (1/64th times 2^6 = 64)
+        }
+        glyphs
+    }
 }
 
 impl ITextRenderer for TextRenderer {
@@ -173,50 +225,106 @@ impl ITextRenderer for TextRenderer {
     fn render_text_ex(
         &self,
         text: String,
-        mut x: f32,
+        x: f32,
         y: f32,
         scale: f32,
         color: glm::TVec3<f32>
     ) {
+        let _group = frame_capture::group("text");
+        let scale = self.space.get().scale(scale, self.ui_scale);
         // activate corresponding render state
         self.text_shader.use_shader();
-        self.text_shader.set_vector3f("textColor", &color);
-        unsafe {
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindVertexArray(self.vao);
-
-            // iterate through all characters
-            for c in text.bytes() {
-                let ch = &self.characters[&c];
-
-                let xpos = x + ch.bearing.x as f32 * scale;
-                let ypos = y + (self.characters[&b'H'].bearing.y - ch.bearing.y) as f32 * scale;
-
-                let w = ch.size.x as f32 * scale;
-                let h = ch.size.y as f32 * scale;
-                // update VBO for each character
-                let vertices = [
-                    [xpos    , ypos + h, 0.0, 1.0],
-                    [xpos + w, ypos    , 1.0, 0.0],
-                    [xpos    , ypos    , 0.0, 0.0],
-
-                    [xpos    , ypos + h, 0.0, 1.0],
-                    [xpos + w, ypos + h, 1.0, 1.0],
-                    [xpos + w, ypos    , 1.0, 0.0],
-                ];
-                // render glyph texture over quad
-                gl::BindTexture(gl::TEXTURE_2D, ch.texture_id);
-                // update content of VBO memory
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-                gl::BufferSubData(gl::ARRAY_BUFFER, 0, mem::size_of_val(&vertices) as _, ptr::addr_of!(vertices) as _); // be sure to use glBufferSubData and not glBufferData
-                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-                // render quad
-                gl::DrawArrays(gl::TRIANGLES, 0, 6);
-                // now advance cursors for next glyph
-                x += (ch.advance >> 6) as f32 * scale; // bitshift by 6 to get value in pixels (1/64th times 2^6 = 64)
+        let push = |vertices: &GlyphQuad| self.stream.borrow_mut().push(vertices);
+        frame_arena::with(|arena| {
+            let mut cache = self.cache.borrow_mut();
+            let cached = match cache.lookup(text.as_str(), scale, resource_manager::frame()) {
+                Lookup::Cached(cached) => Some(cached),
+                Lookup::Bake => {
+                    let glyphs = self.layout(arena, text.as_str(), 0.0, 0.0, scale);
+                    let cached = cache::bake(&self.text_shader, self.vao, &glyphs, &self.space.get().projection(&self.projection), push);
+                    cache.insert(text.as_str(), scale, cached);
+                    cached
+                }
+                Lookup::Draw => None
+            };
+            cache.delete_evicted();
+            resource_manager::track_gpu_memory("text_cache_ascii", cache.memory());
+            self.text_shader.set_vector3f("textColor", &color);
+            match cached {
+                // the whole string at once
+                Some(cached) => draw_glyphs(&NativeGl, self.vao, &[(cached.texture_id, cached.quad(x, y))], push),
+                // stream the quad of each character
+                None => draw_glyphs(&NativeGl, self.vao, &self.layout(arena, text.as_str(), x, y, scale), push)
             }
-            gl::BindVertexArray(0);
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-        }
+        });
+    }
+
+    fn render_text_faded(
+        &self,
+        text: &str,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        fade: f32
+    ) {
+        let _group = frame_capture::group("text");
+        let scale = self.space.get().scale(scale, self.ui_scale);
+        // activate corresponding render state
+        self.text_shader.use_shader();
+        self.text_shader.set_vector3f("textColor", &color);
+        self.text_shader.set_float("fade", fade);
+        frame_arena::with(|arena| {
+            let glyphs = self.layout(arena, text, x, y, scale);
+            draw_glyphs(&NativeGl, self.vao, &glyphs, |vertices| self.stream.borrow_mut().push(vertices));
+        });
+        self.text_shader.set_float("fade", 0.0);
+    }
+
+    fn set_space(
+        &self,
+        space: TextSpace
+    ) {
+        self.space.set(space);
+        self.text_shader.set_matrix4_ex("projection", &space.projection(&self.projection), true);
+    }
+
+    fn render_text_animated(
+        &self,
+        text: String,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        animation: TextAnimation
+    ) {
+        let _group = frame_capture::group("text");
+        let scale = self.space.get().scale(scale, self.ui_scale);
+        // activate corresponding render state
+        self.text_shader.use_shader();
+        self.text_shader.set_vector3f("textColor", &color);
+        frame_arena::with(|arena| {
+            let mut glyphs = self.layout(arena, text.as_str(), x, y, scale);
+            let shown = animation.apply(&mut glyphs, scale);
+            draw_glyphs(&NativeGl, self.vao, &glyphs[..shown], |vertices| self.stream.borrow_mut().push(vertices));
+        });
+    }
+
+    fn text_width(
+        &self,
+        text: &str,
+        scale: f32
+    ) -> f32 {
+        text.bytes()
+            .filter_map(|c| self.characters.get(&c))
+            .map(|ch| (ch.advance >> 6) as f32)
+            .sum::<f32>() * self.space.get().scale(scale, self.ui_scale)
+    }
+
+    fn text_height(
+        &self,
+        scale: f32
+    ) -> f32 {
+        self.characters.get(&b'H').map(|ch| ch.size.y as f32).unwrap_or(0.0) * self.space.get().scale(scale, self.ui_scale)
     }
 }
\ No newline at end of file
@@ -0,0 +1,300 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::collections::HashMap;
+use std::ptr;
+use learnopengl_shared::util;
+use crate::gl_api::NativeGl;
+use crate::shader::Shader;
+use crate::text_renderer::{draw_glyphs, glyph_quad, GlyphQuad};
+
+// Strings drawn unchanged for a few frames in a row (labels, menu items, the lives) are rendered
+// into a texture of their own once, which is then drawn as a single quad tinted with the text
+// color, until the string hasn't been drawn for a while. Strings changing every frame never get
+// that far, so they cost no more than before. Text is cached by its string and scale; the position
+// and the color are applied when drawing. Only frames drawing any text are counted, so the menus,
+// which are redrawn only now and then, are cached as well.
+
+// frames a string has to be drawn in a row before it is cached
+pub const CACHE_AFTER_FRAMES: u32 = 3;
+// frames a string is remembered for after it was last drawn
+pub const EVICT_AFTER_FRAMES: u64 = 120;
+
+/// A string rendered into a texture of its own, holding the coverage of its glyphs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CachedText {
+    pub texture_id: u32,
+    // offset of the texture's top left corner from the position the text is drawn at
+    pub offset: glm::Vec2,
+    pub size: glm::IVec2
+}
+
+impl CachedText {
+    // the quad drawing the text at the given position
+    pub fn quad(&self, x: f32, y: f32) -> GlyphQuad {
+        glyph_quad(x + self.offset.x, y + self.offset.y, self.size, glm::vec2(0, 0), 0, 1.0)
+    }
+}
+
+/// What a text renderer has to do to draw a string.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Lookup {
+    // draw the cached texture
+    Cached(CachedText),
+    // render the string into a texture (see bake) and insert it
+    Bake,
+    // draw the glyphs as usual
+    Draw
+}
+
+struct Entry {
+    // frames in a row the string was drawn in, and the last of them (see TextCache::tick)
+    streak: u32,
+    last_frame: u64,
+    // whether baking was tried, and the result
+    baked: bool,
+    text: Option<CachedText>
+}
+
+#[derive(Default)]
+pub struct TextCache {
+    // keyed by the string and the bits of its scale
+    entries: HashMap<(String, u32), Entry>,
+    // the frame text was last drawn in, and the number of frames text was drawn in
+    frame: u64,
+    tick: u64,
+    // textures of the evicted strings, to be deleted by the renderer
+    evicted: Vec<u32>
+}
+
+impl TextCache {
+    // what to do about drawing a string in the given frame
+    pub fn lookup(&mut self, text: &str, scale: f32, frame: u64) -> Lookup {
+        if frame != self.frame {
+            self.frame = frame;
+            self.tick += 1;
+            self.sweep();
+        }
+        let tick = self.tick;
+        let entry = self.entries.entry((text.to_string(), scale.to_bits())).or_insert(Entry { streak: 0, last_frame: tick, baked: false, text: None });
+        if entry.streak == 0 || entry.last_frame + 1 < tick {
+            entry.streak = 1;
+        } else if entry.last_frame + 1 == tick {
+            entry.streak += 1;
+        }
+        entry.last_frame = tick;
+        match entry.text {
+            Some(text) => Lookup::Cached(text),
+            None if !entry.baked && entry.streak >= CACHE_AFTER_FRAMES => Lookup::Bake,
+            None => Lookup::Draw
+        }
+    }
+
+    // stores a baked string (None if it couldn't be baked, so it is drawn as usual)
+    pub fn insert(&mut self, text: &str, scale: f32, cached: Option<CachedText>) {
+        if let Some(entry) = self.entries.get_mut(&(text.to_string(), scale.to_bits())) {
+            // not tried again until the string drops out of the cache
+            entry.baked = true;
+            entry.text = cached;
+        }
+    }
+
+    // forgets the strings which haven't been drawn for a while
+    fn sweep(&mut self) {
+        let tick = self.tick;
+        let evicted = &mut self.evicted;
+        self.entries.retain(|_, entry| {
+            let keep = entry.last_frame + EVICT_AFTER_FRAMES > tick;
+            if !keep {
+                evicted.extend(entry.text.map(|it| it.texture_id));
+            }
+            keep
+        });
+    }
+
+    // forgets all strings, e.g. because the font changed
+    pub fn clear(&mut self) {
+        let evicted = &mut self.evicted;
+        evicted.extend(self.entries.drain().filter_map(|(_, entry)| entry.text.map(|it| it.texture_id)));
+    }
+
+    // the textures of the strings forgotten since the last call
+    pub fn take_evicted(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.evicted)
+    }
+
+    // deletes the textures of the strings forgotten since the last call
+    pub fn delete_evicted(&mut self) {
+        for texture in self.take_evicted() {
+            unsafe { gl::DeleteTextures(1, &texture); }
+        }
+    }
+
+    // video memory taken by the cached strings in bytes
+    pub fn memory(&self) -> usize {
+        self.entries.values()
+            .filter_map(|entry| entry.text)
+            .map(|text| (text.size.x * text.size.y) as usize)
+            .sum()
+    }
+}
+
+// the smallest and largest corner of laid out glyphs
+pub fn bounds(glyphs: &[(u32, GlyphQuad)]) -> Option<(glm::Vec2, glm::Vec2)> {
+    let mut vertices = glyphs.iter().flat_map(|(_, quad)| quad.iter()).map(|v| glm::vec2(v[0], v[1]));
+    let first = vertices.next()?;
+    Some(vertices.fold((first, first), |(min, max), v| (glm::min2(&min, &v), glm::max2(&max, &v))))
+}
+
+// renders glyphs laid out at the origin into a texture of their own, with the text shader in use;
+// the shader gets back `projection`, the text color has to be set again
+pub fn bake(
+    shader: &Shader,
+    vao: u32,
+    glyphs: &[(u32, GlyphQuad)],
+    projection: &glm::TMat4<f32>,
+    push: impl FnMut(&GlyphQuad) -> usize
+) -> Option<CachedText> {
+    let (min, max) = bounds(glyphs)?;
+    let size = glm::vec2((max.x - min.x).ceil() as i32, (max.y - min.y).ceil() as i32);
+    if size.x <= 0 || size.y <= 0 {
+        return None;
+    }
+    let shifted: Vec<(u32, GlyphQuad)> = glyphs.iter()
+        .map(|(id, quad)| (*id, quad.map(|v| [v[0] - min.x, v[1] - min.y, v[2], v[3]])))
+        .collect();
+    let mut texture = 0u32;
+    unsafe {
+        // keep what is being rendered to
+        let mut framebuffer = 0i32;
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut framebuffer);
+        let mut viewport = [0i32; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        let mut clear_color = [0f32; 4];
+        gl::GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+        // text clipped by a scroll view is only clipped when drawn
+        let scissor = gl::IsEnabled(gl::SCISSOR_TEST) == gl::TRUE;
+        gl::Disable(gl::SCISSOR_TEST);
+
+        gl::GenTextures(1, &mut texture);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+        gl::TexImage2D(gl::TEXTURE_2D, 0, gl::R8 as _, size.x, size.y, 0, gl::RED, gl::UNSIGNED_BYTE, ptr::null());
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+        let mut target = 0u32;
+        gl::GenFramebuffers(1, &mut target);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, target);
+        gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, texture, 0);
+        let complete = gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE;
+        if complete {
+            gl::Viewport(0, 0, size.x, size.y);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            // the top of the text goes into the first row, like the rows of a glyph
+            shader.set_matrix4("projection", &util::glm::ortho(0.0, size.x as f32, 0.0, size.y as f32));
+            // white text blended over black leaves the coverage in the red channel
+            shader.set_vector3f("textColor", &util::glm::scale_vec3(1.0));
+            draw_glyphs(&NativeGl, vao, &shifted, push);
+            shader.set_matrix4("projection", projection);
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer as u32);
+        gl::DeleteFramebuffers(1, &target);
+        gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+        if scissor {
+            gl::Enable(gl::SCISSOR_TEST);
+        }
+        if !complete {
+            log::error!("ERROR::TEXT_CACHE: Framebuffer for cached text is not complete");
+            gl::DeleteTextures(1, &texture);
+            return None;
+        }
+    }
+    Some(CachedText { texture_id: texture, offset: min, size })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_test_lookup() {
+        let mut cache = TextCache::default();
+        // drawn in consecutive frames, the string is baked on the third
+        assert_eq!(cache.lookup("Lives:3", 1.0, 10), Lookup::Draw);
+        // a second draw within the frame doesn't count
+        assert_eq!(cache.lookup("Lives:3", 1.0, 10), Lookup::Draw);
+        assert_eq!(cache.lookup("Lives:3", 1.0, 11), Lookup::Draw);
+        assert_eq!(cache.lookup("Lives:3", 1.0, 12), Lookup::Bake);
+        let cached = CachedText { texture_id: 7, offset: glm::vec2(0.0, -2.0), size: glm::vec2(40, 20) };
+        cache.insert("Lives:3", 1.0, Some(cached));
+        assert_eq!(cache.lookup("Lives:3", 1.0, 13), Lookup::Cached(cached));
+        assert_eq!(cache.memory(), 800);
+        // another scale is another entry, and a frame without it starts the streak over
+        assert_eq!(cache.lookup("Lives:3", 2.0, 13), Lookup::Draw);
+        assert_eq!(cache.lookup("Lives:3", 2.0, 14), Lookup::Draw);
+        cache.lookup("Lives:2", 1.0, 15);
+        assert_eq!(cache.lookup("Lives:3", 2.0, 16), Lookup::Draw);
+        // frames without any text don't count
+        assert_eq!(cache.lookup("Lives:3", 2.0, 20), Lookup::Draw);
+        assert_eq!(cache.lookup("Lives:3", 2.0, 40), Lookup::Bake);
+    }
+
+    #[test]
+    fn cache_test_eviction() {
+        let mut cache = TextCache::default();
+        let cached = CachedText { texture_id: 7, offset: glm::vec2(0.0, 0.0), size: glm::vec2(4, 4) };
+        for frame in 0..CACHE_AFTER_FRAMES as u64 {
+            cache.lookup("Menu", 1.0, frame);
+        }
+        cache.insert("Menu", 1.0, Some(cached));
+        for frame in CACHE_AFTER_FRAMES as u64..EVICT_AFTER_FRAMES + 2 {
+            cache.lookup(format!("Score:{}", frame).as_str(), 1.0, frame);
+        }
+        assert!(cache.take_evicted().is_empty());
+        cache.lookup("Score", 1.0, 1000);
+        assert_eq!(cache.take_evicted(), vec![7]);
+        assert_eq!(cache.memory(), 0);
+        // a string which couldn't be baked is drawn as usual
+        for frame in 200..203 {
+            cache.lookup("Menu", 1.0, frame);
+        }
+        cache.insert("Menu", 1.0, None);
+        assert!((203..210).all(|frame| cache.lookup("Menu", 1.0, frame) == Lookup::Draw));
+        cache.clear();
+        assert!(cache.take_evicted().is_empty());
+    }
+
+    #[test]
+    fn cache_test_quad() {
+        let glyphs = [
+            (1, glyph_quad(0.0, 0.0, glm::vec2(10, 12), glm::vec2(1, 12), 12, 1.0)),
+            (2, glyph_quad(12.0, 0.0, glm::vec2(10, 14), glm::vec2(1, 10), 12, 1.0))
+        ];
+        let (min, max) = bounds(&glyphs).unwrap();
+        assert_eq!((min, max), (glm::vec2(1.0, 0.0), glm::vec2(23.0, 16.0)));
+        assert!(bounds(&[]).is_none());
+        let cached = CachedText { texture_id: 3, offset: min, size: glm::vec2(22, 16) };
+        // drawn where the glyphs would have been
+        assert_eq!(cached.quad(5.0, 5.0)[2], [6.0, 5.0, 0.0, 0.0]);
+        assert_eq!(cached.quad(5.0, 5.0)[4], [28.0, 21.0, 1.0, 1.0]);
+    }
+}
@@ -1,6 +1,21 @@
 extern crate nalgebra_glm as glm;
 
+use std::ffi::CString;
+use std::mem;
+use freetype::freetype::{
+    FT_Face, FT_Get_Char_Index, FT_Library, FT_Long, FT_New_Face, FT_New_Memory_Face,
+    FT_Select_Size, FT_Set_Pixel_Sizes
+};
+use rust_i18n::t;
+use learnopengl_shared::filesystem;
+use crate::builtin;
+use crate::fallback;
+use crate::gl_api::GlApi;
+use crate::text_renderer::animation::TextAnimation;
+
+pub mod animation;
 pub mod ascii;
+pub mod cache;
 pub mod utf8;
 
 pub trait ITextRenderer {
@@ -20,4 +35,304 @@ pub trait ITextRenderer {
         scale: f32,
         color: glm::TVec3<f32>
     );
-}
\ No newline at end of file
+
+    // places the text rendered from now on on the screen (the default) or in the game world
+    fn set_space(
+        &self,
+        space: TextSpace
+    );
+
+    // renders text faded out by the given fraction (0 is opaque), e.g. in the game world;
+    // never cached, such text moves and fades from frame to frame
+    fn render_text_faded(
+        &self,
+        text: &str,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        fade: f32
+    );
+
+    // renders text with its glyphs moved by an animation
+    fn render_text_animated(
+        &self,
+        text: String,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        animation: TextAnimation
+    );
+
+    // width of the rendered text in pixels
+    fn text_width(
+        &self,
+        text: &str,
+        scale: f32
+    ) -> f32;
+
+    // height of a line of capital letters in pixels
+    fn text_height(
+        &self,
+        scale: f32
+    ) -> f32;
+}
+
+// loads a font file as a FreeType face, falling back to the built-in font if it can't be
+// (returns the face, null if not even that could be loaded)
+fn open_face(ft: FT_Library, font: &str) -> FT_Face {
+    let mut face: FT_Face = std::ptr::null_mut();
+    let path = CString::new(font).unwrap_or_default();
+    unsafe {
+        if FT_New_Face(ft, path.as_ptr(), 0, &mut face) == 0 {
+            return face;
+        }
+        fallback::report("font", font, "FreeType can't open it");
+        if FT_New_Memory_Face(ft, builtin::FONT.as_ptr(), builtin::FONT.len() as FT_Long, 0, &mut face) != 0 {
+            log::error!("ERROR::FREETYPE: Failed to load the built-in font");
+            return std::ptr::null_mut();
+        }
+    }
+    face
+}
+
+// size text is rendered at if a language doesn't declare one
+pub const DEFAULT_FONT_SIZE: u32 = 24;
+
+/// Fonts a language is shown in, as declared in the [font] section of its language file: the
+/// faces are tried in order for each glyph (e.g. a CJK face first, a Latin one for the rest).
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontChain {
+    // font files, relative to the project root
+    pub faces: Vec<String>,
+    // pixel size glyphs are loaded at
+    pub size: u32
+}
+
+impl FontChain {
+    pub fn new(faces: Vec<String>, size: u32) -> Self {
+        Self { faces, size }
+    }
+
+    // parses the declaration of a language file: a comma separated list of font files and a size
+    pub fn parse(faces: &str, size: &str) -> Self {
+        let faces = faces.split(',').map(str::trim).filter(|it| !it.is_empty()).map(str::to_string).collect();
+        Self::new(faces, size.trim().parse().unwrap_or(DEFAULT_FONT_SIZE))
+    }
+
+    // the fonts declared by the given language
+    pub fn of_locale(locale: &str) -> Self {
+        Self::parse(t!("font.faces", locale = locale).as_ref(), t!("font.size", locale = locale).as_ref())
+    }
+
+    // the font files as paths
+    pub fn paths(&self) -> Vec<String> {
+        self.faces.iter().map(|it| filesystem::get_path(it.clone())).collect()
+    }
+}
+
+// opens the fonts of a chain at the given size (the built-in font stands in for those that can't be
+// opened, so the chain is only empty if not even that one could)
+fn open_faces(ft: FT_Library, fonts: &[String], size: u32) -> Vec<FT_Face> {
+    let faces: Vec<FT_Face> = fonts.iter()
+        .map(|font| open_face(ft, font.as_str()))
+        .filter(|face| !face.is_null())
+        .collect();
+    for face in faces.iter() {
+        unsafe {
+            // set size to load glyphs as
+            if FT_Set_Pixel_Sizes(*face, 0, size) == 0 || (**face).num_fixed_sizes <= 0 {
+                continue;
+            }
+            // bitmap fonts (like most color emoji fonts) only come in some sizes, their glyphs are scaled
+            let strikes = std::slice::from_raw_parts((**face).available_sizes, (**face).num_fixed_sizes as usize);
+            let heights: Vec<u32> = strikes.iter().map(|it| (it.y_ppem >> 6) as u32).collect();
+            FT_Select_Size(*face, closest_strike(&heights, size) as _);
+        }
+    }
+    faces
+}
+
+// the strike of a bitmap font glyphs are loaded from: the smallest one at least as large as wanted, or the largest
+pub fn closest_strike(heights: &[u32], wanted: u32) -> usize {
+    let larger = heights.iter().enumerate().filter(|(_, it)| **it >= wanted).min_by_key(|(_, it)| **it);
+    let largest = heights.iter().enumerate().max_by_key(|(_, it)| **it);
+    larger.or(largest).map(|(i, _)| i).unwrap_or(0)
+}
+
+// how much the glyphs of a face have to be scaled to come out at the given size (not at all unless
+// a strike of a bitmap font was selected)
+fn glyph_scale(face: FT_Face, size: u32) -> f32 {
+    let loaded = unsafe { (*(*face).size).metrics.y_ppem };
+    if loaded == 0 {
+        1.0
+    } else {
+        size as f32 / loaded as f32
+    }
+}
+
+// RGBA pixels of a color glyph's bitmap, which FreeType renders as premultiplied BGRA rows of `pitch` bytes
+pub fn bgra_to_rgba(data: &[u8], width: usize, rows: usize, pitch: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(width * rows * 4);
+    for row in data.chunks(pitch).take(rows) {
+        for bgra in row[..width * 4].chunks_exact(4) {
+            let alpha = bgra[3] as u32;
+            let straight = |c: u8| (c as u32 * 255).checked_div(alpha).map_or(0, |it| it.min(255) as u8);
+            pixels.extend_from_slice(&[straight(bgra[2]), straight(bgra[1]), straight(bgra[0]), bgra[3]]);
+        }
+    }
+    pixels
+}
+
+// the first face of a chain which has a glyph for the character (the first face if none has)
+fn face_for(faces: &[FT_Face], c: char) -> Option<FT_Face> {
+    faces.iter()
+        .find(|face| unsafe { FT_Get_Char_Index(**face, c as _) } != 0)
+        .or(faces.first())
+        .copied()
+}
+
+/// Where text is placed: on the screen like the HUD, scaled by the UI scale, or at world
+/// coordinates as seen through a view-projection (e.g. the photo camera's), at the size asked for.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextSpace {
+    Screen,
+    World(glm::TMat4<f32>)
+}
+
+impl TextSpace {
+    // the projection text in this space is drawn with, given the one of the screen
+    pub fn projection(&self, screen: &glm::TMat4<f32>) -> glm::TMat4<f32> {
+        match self {
+            TextSpace::Screen => *screen,
+            TextSpace::World(view_projection) => *view_projection
+        }
+    }
+
+    // the scale text in this space is drawn at
+    pub fn scale(&self, scale: f32, ui_scale: f32) -> f32 {
+        match self {
+            TextSpace::Screen => scale * ui_scale,
+            TextSpace::World(_) => scale
+        }
+    }
+}
+
+/// Quad of a glyph as streamed to the text shader: two triangles of (x, y, u, v) vertices.
+pub type GlyphQuad = [[f32; 4]; 6];
+
+// places the quad of a glyph at the pen position; glyphs hang from the line of the capitals
+// (`top` is the bearing of 'H'), so text is positioned by its top left corner
+pub fn glyph_quad(x: f32, y: f32, size: glm::IVec2, bearing: glm::IVec2, top: i32, scale: f32) -> GlyphQuad {
+    let xpos = x + bearing.x as f32 * scale;
+    let ypos = y + (top - bearing.y) as f32 * scale;
+
+    let w = size.x as f32 * scale;
+    let h = size.y as f32 * scale;
+    [
+        [xpos    , ypos + h, 0.0, 1.0],
+        [xpos + w, ypos    , 1.0, 0.0],
+        [xpos    , ypos    , 0.0, 0.0],
+
+        [xpos    , ypos + h, 0.0, 1.0],
+        [xpos + w, ypos + h, 1.0, 1.0],
+        [xpos + w, ypos    , 1.0, 0.0],
+    ]
+}
+
+// draws laid out glyphs with the text shader in use: each quad is streamed with `push`
+// (which yields its byte offset in the vertex buffer of the VAO) and drawn on its own,
+// and a glyph texture is only bound again when it changes
+pub fn draw_glyphs<G: GlApi>(gl: &G, vao: u32, glyphs: &[(u32, GlyphQuad)], mut push: impl FnMut(&GlyphQuad) -> usize) {
+    gl.active_texture(gl::TEXTURE0);
+    gl.bind_vertex_array(vao);
+    let mut bound = None;
+    for (texture_id, quad) in glyphs {
+        // render glyph texture over quad
+        if bound != Some(*texture_id) {
+            gl.bind_texture(gl::TEXTURE_2D, *texture_id);
+            bound = Some(*texture_id);
+        }
+        let offset = push(quad);
+        // render quad
+        gl.draw_arrays(gl::TRIANGLES, offset / mem::size_of::<[f32; 4]>(), 6);
+    }
+    gl.bind_buffer(gl::ARRAY_BUFFER, 0);
+    gl.bind_vertex_array(0);
+    gl.bind_texture(gl::TEXTURE_2D, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_api::{redundant_calls, GlCall, RecordingGl};
+
+    #[test]
+    fn text_renderer_test_color_glyphs() {
+        // a color emoji font with strikes of 20, 64 and 136 pixels
+        assert_eq!(closest_strike(&[20, 64, 136], 24), 1);
+        assert_eq!(closest_strike(&[20, 64, 136], 200), 2);
+        assert_eq!(closest_strike(&[], 24), 0);
+        // two pixels per row and a padded pitch: premultiplied half transparent red, opaque blue
+        let data = [0, 0, 128, 128, 255, 0, 0, 255, 9, 9, 0, 0, 0, 0, 0, 0, 0, 0, 9, 9];
+        let pixels = bgra_to_rgba(&data, 2, 2, 10);
+        assert_eq!(pixels, vec![255, 0, 0, 128, 0, 0, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn text_renderer_test_font_chain() {
+        let chain = FontChain::parse(" a.ttf, b.ttf ,,", "32");
+        assert_eq!(chain, FontChain::new(vec!["a.ttf".to_string(), "b.ttf".to_string()], 32));
+        assert_eq!(FontChain::parse("a.ttf", "large").size, DEFAULT_FONT_SIZE);
+        // the shipped languages declare fonts which exist
+        for locale in ["en", "zh-CN"] {
+            let chain = FontChain::of_locale(locale);
+            assert!(!chain.faces.is_empty(), "{} should declare its fonts", locale);
+            // the lives and score popups show emoji
+            assert!(chain.faces.iter().any(|it| it.ends_with("BreakoutEmoji.ttf")), "{} should have the emoji font", locale);
+            let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+            for face in chain.faces.iter() {
+                assert!(root.join(face).exists(), "{} of {} should exist", face, locale);
+            }
+        }
+    }
+
+    #[test]
+    fn text_renderer_test_space() {
+        let screen = glm::ortho(0.0, 800.0, 600.0, 0.0, -1.0, 1.0);
+        let camera = glm::ortho(200.0, 600.0, 450.0, 150.0, -1.0, 1.0);
+        assert_eq!(TextSpace::Screen.projection(&screen), screen);
+        assert_eq!(TextSpace::World(camera).projection(&screen), camera);
+        // the UI scale only grows the HUD
+        assert_eq!(TextSpace::Screen.scale(0.5, 2.0), 1.0);
+        assert_eq!(TextSpace::World(camera).scale(0.5, 2.0), 0.5);
+    }
+
+    #[test]
+    fn text_renderer_test_draw_glyphs() {
+        let quad = glyph_quad(0.0, 0.0, glm::vec2(1, 1), glm::vec2(0, 1), 1, 1.0);
+        // "aab": the texture of 'a' is bound once for both glyphs
+        let glyphs = [(4, quad), (4, quad), (9, quad)];
+        let gl = RecordingGl::default();
+        let mut offset = 0;
+        draw_glyphs(&gl, 2, &glyphs, |quad| {
+            offset += mem::size_of_val(quad);
+            offset - mem::size_of_val(quad)
+        });
+        let calls = gl.take();
+        assert_eq!(calls, vec![
+            GlCall::ActiveTexture(gl::TEXTURE0),
+            GlCall::BindVertexArray(2),
+            GlCall::BindTexture(gl::TEXTURE_2D, 4),
+            GlCall::DrawArrays(gl::TRIANGLES, 0, 6),
+            GlCall::DrawArrays(gl::TRIANGLES, 6, 6),
+            GlCall::BindTexture(gl::TEXTURE_2D, 9),
+            GlCall::DrawArrays(gl::TRIANGLES, 12, 6),
+            GlCall::BindBuffer(gl::ARRAY_BUFFER, 0),
+            GlCall::BindVertexArray(0),
+            GlCall::BindTexture(gl::TEXTURE_2D, 0)
+        ]);
+        assert!(redundant_calls(&calls).is_empty());
+    }
+}
@@ -16,19 +16,25 @@
 
 extern crate nalgebra_glm as glm;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::{mem, ptr};
-use std::cell::RefCell;
-use std::ffi::CString;
+use std::cell::{Cell, RefCell};
+use bumpalo::Bump;
+use bumpalo::collections::Vec as BumpVec;
 use learnopengl_shared::{filesystem, util};
+use crate::frame_arena;
+use crate::frame_capture;
 use crate::resource_manager;
 use crate::shader::Shader;
+use crate::stream_buffer::{StreamBuffer, DEFAULT_STREAM_SIZE};
 use freetype::freetype::{
     FT_Done_Face, FT_Done_FreeType, FT_Face, FT_Init_FreeType,
-    FT_Library, FT_Load_Char, FT_LOAD_RENDER, FT_New_Face,
-    FT_Set_Pixel_Sizes
+    FT_Library, FT_Load_Char, FT_LOAD_COLOR, FT_LOAD_RENDER, FT_Pixel_Mode
 };
-use crate::text_renderer::ITextRenderer;
+use crate::gl_api::NativeGl;
+use crate::text_renderer::{bgra_to_rgba, draw_glyphs, face_for, glyph_quad, glyph_scale, open_faces, GlyphQuad, ITextRenderer, TextSpace};
+use crate::text_renderer::animation::TextAnimation;
+use crate::text_renderer::cache::{self, Lookup, TextCache};
 
 /// Holds all state information relevant to a character as loaded using FreeType
 #[derive(Copy, Clone, Default)]
@@ -39,16 +45,22 @@ pub struct Character {
     advance: u32 // horizontal offset to advance to next glyph
 }
 
+// glyphs as laid out: the texture and quad of each
+pub type Glyphs<'a> = BumpVec<'a, (u32, GlyphQuad)>;
+
 /// A utility struct used to connect FreeType library with OpenGL.
-struct FTHelper {
+pub struct FTHelper {
     initialized: bool,
     font_size: u32,
 
     ft: FT_Library,
-    face: FT_Face,
+    // the fonts, in the order they are tried for each glyph
+    faces: Vec<FT_Face>,
 
     // holds a list of pre-compiled Characters
     characters: HashMap<char, Character>,
+    // textures of the glyphs in color (emoji), which aren't tinted with the text color
+    color_glyphs: HashSet<u32>
 }
 
 // A renderer class for rendering text displayed by a font loaded using the
@@ -57,36 +69,63 @@ struct FTHelper {
 pub struct TextRenderer {
     // shader used for text rendering
     pub text_shader: Shader,
+    // scale applied to the text rendered on the screen (UI scale option)
+    pub ui_scale: f32,
 
     // render state
     vao: u32,
-    vbo: u32,
+    stream: RefCell<StreamBuffer>,
+    // projection of the screen
+    projection: glm::TMat4<f32>,
+    // where text is placed
+    space: Cell<TextSpace>,
     // the FreeType library
-    ft_helper: RefCell<FTHelper>
+    ft_helper: RefCell<FTHelper>,
+    // textures of the strings drawn unchanged for a while
+    cache: RefCell<TextCache>
 }
 
 impl FTHelper {
-    fn init(&mut self, font: String, font_size: u32) {
+    fn init(&mut self, fonts: &[String], font_size: u32) {
+        // the glyphs of the fonts loaded before are rebuilt as needed
+        self.release();
         unsafe {
             if FT_Init_FreeType(&mut self.ft) != 0 { // all functions return a value different than 0 whenever an error occurred
                 log::error!("ERROR::FREETYPE: Could not init FreeType Library");
             }
-            // load font as face
-            let font = CString::new(font).unwrap();
-            if FT_New_Face(self.ft, font.as_ptr(), 0, &mut self.face) != 0 {
-                log::error!("ERROR::FREETYPE: Failed to load font");
+            // load fonts as faces (the built-in one for those which can't be)
+            self.faces = open_faces(self.ft, fonts, font_size);
+            if self.faces.is_empty() {
+                FT_Done_FreeType(self.ft);
+                return;
             }
-            // set size to load glyphs as
-            FT_Set_Pixel_Sizes(self.face, 0, font_size);
         }
 
         self.font_size = font_size;
         self.initialized = true;
     }
 
+    // destroys the fonts and the glyphs loaded from them
+    fn release(&mut self) {
+        if self.initialized {
+            unsafe {
+                for character in self.characters.drain().map(|(_, it)| it) {
+                    gl::DeleteTextures(1, &character.texture_id);
+                }
+                self.color_glyphs.clear();
+                // destroy FreeType once we're finished
+                for face in self.faces.drain(..) {
+                    FT_Done_Face(face);
+                }
+                FT_Done_FreeType(self.ft);
+            }
+            self.initialized = false;
+        }
+    }
+
     fn load(&mut self, c: char) -> bool {
         // Ensure the character has not been loaded yet at first.
-        if self.characters.iter().any(|it| c == *it.0) {
+        if self.characters.contains_key(&c) {
             return false;
         }
 
@@ -94,27 +133,42 @@ impl FTHelper {
             // disable byte-alignment restriction
             gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
 
-            // load character glyph
-            let face = self.face;
-            if FT_Load_Char(face, c as _, FT_LOAD_RENDER as _) != 0 {
+            // load character glyph (from the first font having it)
+            let Some(face) = face_for(&self.faces, c) else {
+                self.characters.insert(c, Character::default());
+                return false;
+            };
+            // color fonts are rendered in color
+            if FT_Load_Char(face, c as _, (FT_LOAD_RENDER | FT_LOAD_COLOR) as _) != 0 {
                 log::error!("ERROR::FREETYPE: Failed to load Glyph");
+                // kept empty, so it isn't tried again
+                self.characters.insert(c, Character::default());
                 return false;
             }
             // generate texture
+            let bitmap = (*(*face).glyph).bitmap;
+            let color = bitmap.pixel_mode == FT_Pixel_Mode::FT_PIXEL_MODE_BGRA as u8;
             let mut texture = 0u32;
             gl::GenTextures(1, &mut texture);
             gl::BindTexture(gl::TEXTURE_2D, texture);
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RED as _,
-                (*(*face).glyph).bitmap.width as _,
-                (*(*face).glyph).bitmap.rows as _,
-                0,
-                gl::RED,
-                gl::UNSIGNED_BYTE,
-                (*(*face).glyph).bitmap.buffer as _
-            );
+            if color {
+                let (width, rows) = (bitmap.width as usize, bitmap.rows as usize);
+                let data = if bitmap.buffer.is_null() { &[][..] } else { std::slice::from_raw_parts(bitmap.buffer, rows * bitmap.pitch.unsigned_abs() as usize) };
+                let pixels = bgra_to_rgba(data, width, rows, bitmap.pitch.unsigned_abs() as usize);
+                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as _, width as _, rows as _, 0, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as _);
+            } else {
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RED as _,
+                    bitmap.width as _,
+                    bitmap.rows as _,
+                    0,
+                    gl::RED,
+                    gl::UNSIGNED_BYTE,
+                    bitmap.buffer as _
+                );
+            }
             // set texture options
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
@@ -122,25 +176,76 @@ impl FTHelper {
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
 
             // now store character for later use
+            // (the glyphs of bitmap fonts scaled to the size of the others)
+            let scale = glyph_scale(face, self.font_size);
+            let scaled = |value: i32| (value as f32 * scale).round() as i32;
             let character = Character {
                 texture_id: texture,
-                size: glm::vec2((*(*face).glyph).bitmap.width as _, (*(*face).glyph).bitmap.rows as _),
-                bearing: glm::vec2((*(*face).glyph).bitmap_left, (*(*face).glyph).bitmap_top),
-                advance: (*(*face).glyph).advance.x as _
+                size: glm::vec2(scaled(bitmap.width as _), scaled(bitmap.rows as _)),
+                bearing: glm::vec2(scaled((*(*face).glyph).bitmap_left), scaled((*(*face).glyph).bitmap_top)),
+                advance: scaled((*(*face).glyph).advance.x as _) as _
             };
             self.characters.insert(c, character);
+            if color {
+                self.color_glyphs.insert(texture);
+            }
 
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
+        let glyph_memory = self.characters.values().map(|it| (it.size.x * it.size.y) as usize).sum();
+        resource_manager::track_gpu_memory("glyphs_utf8", glyph_memory);
 
         true
     }
 
     fn get_or_load(&mut self, c: char) -> Character {
-        if !self.characters.iter().any(|it| c == *it.0) {
+        if !self.characters.contains_key(&c) {
             self.load(c);
         }
-        self.characters[&c]
+        // an empty glyph if it couldn't be loaded
+        self.characters.get(&c).copied().unwrap_or_default()
+    }
+
+    // a helper with made-up metrics for the given characters (and 'H'), so no font (or GL) is
+    // needed to lay out text of them, e.g. in tests and benchmarks
+    pub fn with_metrics(text: &str) -> Self {
+        let mut helper = FTHelper::default();
+        for (i, c) in text.chars().chain(['H']).enumerate() {
+            helper.characters.insert(c, Character {
+                texture_id: i as u32 + 1,
+                size: glm::vec2(10, 12),
+                bearing: glm::vec2(1, if c == 'H' { 12 } else { 10 }),
+                advance: 12 << 6
+            });
+        }
+        helper
+    }
+
+    // the texture and quad of each glyph of a line of text (glyphs are loaded as needed)
+    pub fn layout<'a>(&mut self, arena: &'a Bump, text: &str, mut x: f32, y: f32, scale: f32) -> Glyphs<'a> {
+        let top = self.get_or_load('H').bearing.y;
+        let mut glyphs = BumpVec::with_capacity_in(text.len(), arena);
+        for c in text.chars() {
+            let ch = self.get_or_load(c);
+            glyphs.push((ch.texture_id, glyph_quad(x, y, ch.size, ch.bearing, top, scale)));
+            // now advance cursors for next glyph
+            x += (ch.advance >> 6) as f32 * scale; // bitshift by 6 to get value in pixels (1/64th times 2^6 = 64)
+        }
+        glyphs
+    }
+
+    // splits laid out glyphs into those drawn in the text color and those in color of their own
+    fn split_color<'a>(&self, arena: &'a Bump, glyphs: &[(u32, GlyphQuad)]) -> (Glyphs<'a>, Glyphs<'a>) {
+        let mut tinted = BumpVec::with_capacity_in(glyphs.len(), arena);
+        let mut color = BumpVec::new_in(arena);
+        for glyph in glyphs {
+            if self.color_glyphs.contains(&glyph.0) {
+                color.push(*glyph);
+            } else {
+                tinted.push(*glyph);
+            }
+        }
+        (tinted, color)
     }
 }
 
@@ -150,21 +255,16 @@ impl Default for FTHelper {
             initialized: false,
             font_size: 0,
             ft: ptr::null_mut(),
-            face: ptr::null_mut(),
-            characters: HashMap::new()
+            faces: Vec::new(),
+            characters: HashMap::new(),
+            color_glyphs: HashSet::new()
         }
     }
 }
 
 impl Drop for FTHelper {
     fn drop(&mut self) {
-        if self.initialized {
-            unsafe {
-                // destroy FreeType once we're finished
-                FT_Done_Face(self.face);
-                FT_Done_FreeType(self.ft);
-            }
-        }
+        self.release();
     }
 }
 
@@ -173,9 +273,13 @@ impl TextRenderer {
     pub fn new(width: u32, height: u32) -> Self {
         let mut result = Self {
             text_shader: Shader::new(),
+            ui_scale: 1.0,
             vao: u32::default(),
-            vbo: u32::default(),
-            ft_helper: RefCell::new(FTHelper::default())
+            stream: RefCell::new(StreamBuffer::new(DEFAULT_STREAM_SIZE)),
+            projection: util::glm::ortho(0.0, width as _, height as _, 0.0),
+            space: Cell::new(TextSpace::Screen),
+            ft_helper: RefCell::new(FTHelper::default()),
+            cache: RefCell::new(TextCache::default())
         };
 
         // load and configure shader
@@ -185,27 +289,45 @@ impl TextRenderer {
             None,
             "text".to_string()
         );
-        result.text_shader.set_matrix4_ex("projection", &util::glm::ortho(0.0, width as _, height as _, 0.0), true);
+        result.text_shader.set_matrix4_ex("projection", &result.projection, true);
         result.text_shader.set_integer("text", 0);
         unsafe {
             // configure VAO/VBO for texture quads
             gl::GenVertexArrays(1, &mut result.vao);
-            gl::GenBuffers(1, &mut result.vbo);
             gl::BindVertexArray(result.vao);
-            gl::BindBuffer(gl::ARRAY_BUFFER, result.vbo);
-            gl::BufferData(gl::ARRAY_BUFFER, (mem::size_of::<f32>() * 6 * 4) as _, ptr::null(), gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::ARRAY_BUFFER, result.stream.borrow().id());
             gl::EnableVertexAttribArray(0);
             gl::VertexAttribPointer(0, 4, gl::FLOAT, gl::FALSE, (4 * mem::size_of::<f32>()) as _, ptr::null());
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
             gl::BindVertexArray(0);
         }
+        resource_manager::track_gpu_memory("text_stream_utf8", result.stream.borrow().size());
 
         result
     }
 
-    // pre-compiles a list of characters from the given font
-    pub fn load(&self, font: String, font_size: u32) {
-        self.ft_helper.borrow_mut().init(font, font_size);
+    // loads the fonts glyphs are taken from, each from the first of them having it
+    // (the glyphs of the fonts loaded before are dropped)
+    pub fn load_fonts(&self, fonts: &[String], font_size: u32) {
+        let mut cache = self.cache.borrow_mut();
+        cache.clear();
+        cache.delete_evicted();
+        resource_manager::track_gpu_memory("text_cache_utf8", 0);
+        self.ft_helper.borrow_mut().init(fonts, font_size);
+    }
+}
+
+impl TextRenderer {
+    // streams laid out glyphs with the text shader in use, those in color after the others
+    fn draw(&self, arena: &Bump, glyphs: &[(u32, GlyphQuad)]) {
+        let push = |vertices: &GlyphQuad| self.stream.borrow_mut().push(vertices);
+        let (tinted, color) = self.ft_helper.borrow().split_color(arena, glyphs);
+        draw_glyphs(&NativeGl, self.vao, &tinted, push);
+        if !color.is_empty() {
+            self.text_shader.set_integer("colorGlyphs", 1);
+            draw_glyphs(&NativeGl, self.vao, &color, push);
+            self.text_shader.set_integer("colorGlyphs", 0);
+        }
     }
 }
 
@@ -230,50 +352,147 @@ impl ITextRenderer for TextRenderer {
     fn render_text_ex(
         &self,
         text: String,
-        mut x: f32,
+        x: f32,
         y: f32,
         scale: f32,
         color: glm::TVec3<f32>
     ) {
+        let _group = frame_capture::group("text");
+        let scale = self.space.get().scale(scale, self.ui_scale);
         // activate corresponding render state
         self.text_shader.use_shader();
-        self.text_shader.set_vector3f("textColor", &color);
-        unsafe {
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindVertexArray(self.vao);
-
-            // iterate through all characters
-            for c in text.chars() {
-                let ch = self.ft_helper.borrow_mut().get_or_load(c);
-
-                let xpos = x + ch.bearing.x as f32 * scale;
-                let ypos = y + (self.ft_helper.borrow_mut().get_or_load('H').bearing.y - ch.bearing.y) as f32 * scale;
-
-                let w = ch.size.x as f32 * scale;
-                let h = ch.size.y as f32 * scale;
-                // update VBO for each character
-                let vertices = [
-                    [xpos    , ypos + h, 0.0, 1.0],
-                    [xpos + w, ypos    , 1.0, 0.0],
-                    [xpos    , ypos    , 0.0, 0.0],
-
-                    [xpos    , ypos + h, 0.0, 1.0],
-                    [xpos + w, ypos + h, 1.0, 1.0],
-                    [xpos + w, ypos    , 1.0, 0.0],
-                ];
-                // render glyph texture over quad
-                gl::BindTexture(gl::TEXTURE_2D, ch.texture_id);
-                // update content of VBO memory
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-                gl::BufferSubData(gl::ARRAY_BUFFER, 0, mem::size_of_val(&vertices) as _, ptr::addr_of!(vertices) as _); // be sure to use glBufferSubData and not glBufferData
-                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-                // render quad
-                gl::DrawArrays(gl::TRIANGLES, 0, 6);
-                // now advance cursors for next glyph
-                x += (ch.advance >> 6) as f32 * scale; // bitshift by 6 to get value in pixels (1/64th times 2^6 = 64)
+        let push = |vertices: &GlyphQuad| self.stream.borrow_mut().push(vertices);
+        frame_arena::with(|arena| {
+            let mut cache = self.cache.borrow_mut();
+            let cached = match cache.lookup(text.as_str(), scale, resource_manager::frame()) {
+                Lookup::Cached(cached) => Some(cached),
+                Lookup::Bake => {
+                    let mut ft_helper = self.ft_helper.borrow_mut();
+                    let glyphs = ft_helper.layout(arena, text.as_str(), 0.0, 0.0, scale);
+                    // the cache only holds the coverage of glyphs, so text with color glyphs isn't cached
+                    let cached = if glyphs.iter().any(|glyph| ft_helper.color_glyphs.contains(&glyph.0)) {
+                        None
+                    } else {
+                        cache::bake(&self.text_shader, self.vao, &glyphs, &self.space.get().projection(&self.projection), push)
+                    };
+                    cache.insert(text.as_str(), scale, cached);
+                    cached
+                }
+                Lookup::Draw => None
+            };
+            cache.delete_evicted();
+            resource_manager::track_gpu_memory("text_cache_utf8", cache.memory());
+            self.text_shader.set_vector3f("textColor", &color);
+            match cached {
+                // the whole string at once
+                Some(cached) => draw_glyphs(&NativeGl, self.vao, &[(cached.texture_id, cached.quad(x, y))], push),
+                // stream the quad of each character
+                None => {
+                    let glyphs = self.ft_helper.borrow_mut().layout(arena, text.as_str(), x, y, scale);
+                    self.draw(arena, &glyphs);
+                }
             }
-            gl::BindVertexArray(0);
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-        }
+        });
     }
-}
\ No newline at end of file
+
+    fn render_text_faded(
+        &self,
+        text: &str,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        fade: f32
+    ) {
+        let _group = frame_capture::group("text");
+        let scale = self.space.get().scale(scale, self.ui_scale);
+        // activate corresponding render state
+        self.text_shader.use_shader();
+        self.text_shader.set_vector3f("textColor", &color);
+        self.text_shader.set_float("fade", fade);
+        frame_arena::with(|arena| {
+            let glyphs = self.ft_helper.borrow_mut().layout(arena, text, x, y, scale);
+            self.draw(arena, &glyphs);
+        });
+        self.text_shader.set_float("fade", 0.0);
+    }
+
+    fn set_space(
+        &self,
+        space: TextSpace
+    ) {
+        self.space.set(space);
+        self.text_shader.set_matrix4_ex("projection", &space.projection(&self.projection), true);
+    }
+
+    fn render_text_animated(
+        &self,
+        text: String,
+        x: f32,
+        y: f32,
+        scale: f32,
+        color: glm::TVec3<f32>,
+        animation: TextAnimation
+    ) {
+        let _group = frame_capture::group("text");
+        let scale = self.space.get().scale(scale, self.ui_scale);
+        // activate corresponding render state
+        self.text_shader.use_shader();
+        self.text_shader.set_vector3f("textColor", &color);
+        frame_arena::with(|arena| {
+            let mut glyphs = self.ft_helper.borrow_mut().layout(arena, text.as_str(), x, y, scale);
+            let shown = animation.apply(&mut glyphs, scale);
+            self.draw(arena, &glyphs[..shown]);
+        });
+    }
+
+    fn text_width(
+        &self,
+        text: &str,
+        scale: f32
+    ) -> f32 {
+        let mut ft_helper = self.ft_helper.borrow_mut();
+        text.chars()
+            .map(|c| (ft_helper.get_or_load(c).advance >> 6) as f32)
+            .sum::<f32>() * self.space.get().scale(scale, self.ui_scale)
+    }
+
+    fn text_height(
+        &self,
+        scale: f32
+    ) -> f32 {
+        self.ft_helper.borrow_mut().get_or_load('H').size.y as f32 * self.space.get().scale(scale, self.ui_scale)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn helper(text: &str) -> FTHelper {
+        FTHelper::with_metrics(text)
+    }
+
+    #[test]
+    fn utf8_test_layout() {
+        let mut helper = helper("a中");
+        let arena = Bump::new();
+        let glyphs = helper.layout(&arena, "a中a", 5.0, 20.0, 2.0);
+        assert_eq!(glyphs.len(), 3);
+        assert_eq!(glyphs[0].0, glyphs[2].0);
+        // the pen advances by 12 pixels per glyph, glyphs hang 2 pixels below the capitals
+        assert_eq!(glyphs[1].1[2], [5.0 + 24.0 + 2.0, 20.0 + 4.0, 0.0, 0.0]);
+        assert_eq!(glyphs[2].1[4], [5.0 + 48.0 + 2.0 + 20.0, 20.0 + 4.0 + 24.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn utf8_test_split_color() {
+        let mut helper = helper("a❤");
+        let heart = helper.characters[&'❤'].texture_id;
+        helper.color_glyphs.insert(heart);
+        let arena = Bump::new();
+        let glyphs = helper.layout(&arena, "a❤a", 0.0, 0.0, 1.0);
+        let (tinted, color) = helper.split_color(&arena, &glyphs);
+        assert_eq!(tinted.len(), 2);
+        assert_eq!(color.iter().map(|it| it.0).collect::<Vec<_>>(), vec![heart]);
+    }
+}
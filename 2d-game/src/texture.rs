@@ -14,14 +14,91 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU32, Ordering};
+use lazy_static::lazy_static;
+use crate::resource_manager;
+
+// Texture objects created through Texture2D are owned by the handles referring to them: the
+// object is deleted once the last clone of its Texture2D is dropped. OpenGL calls only work on
+// the thread owning the context, so textures dropped on any other thread (e.g. by a background
+// task) are queued and deleted by that thread at the end of the frame. The textures still alive
+// at shutdown are reported as leaks.
+
+lazy_static! {
+    // texture objects owned by a Texture2D which haven't been deleted yet
+    static ref LIVE: Mutex<HashSet<u32>> = Mutex::new(HashSet::new());
+    // texture objects dropped off the GL thread, waiting to be deleted
+    static ref PENDING: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+}
+
+thread_local! {
+    static GL_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+// marks the calling thread as the one owning the OpenGL context
+pub fn mark_gl_thread() {
+    GL_THREAD.with(|it| it.set(true));
+}
+
+fn is_gl_thread() -> bool {
+    GL_THREAD.with(|it| it.get())
+}
+
+// deletes the textures dropped off the GL thread since the last call (on the GL thread)
+pub fn delete_pending() {
+    if !is_gl_thread() {
+        return;
+    }
+    let pending: Vec<u32> = PENDING.lock().unwrap().drain(..).collect();
+    for id in pending {
+        delete(id);
+    }
+}
+
+// logs the owned textures which were never deleted, returning how many there are
+pub fn report_leaks() -> usize {
+    let mut live: Vec<u32> = LIVE.lock().unwrap().iter().copied().collect();
+    if !live.is_empty() {
+        live.sort();
+        log::warn!("TEXTURE: {} texture object(s) were never deleted: {:?}", live.len(), live);
+    }
+    live.len()
+}
+
+fn delete(id: u32) {
+    unsafe {
+        gl::DeleteTextures(1, &id);
+    }
+    LIVE.lock().unwrap().remove(&id);
+}
+
+// the ownership of a texture object shared by the clones of a Texture2D; the object is
+// replaced when the texture is recreated
+struct TextureHandle(AtomicU32);
+
+impl Drop for TextureHandle {
+    fn drop(&mut self) {
+        let id = self.0.load(Ordering::Relaxed);
+        if is_gl_thread() {
+            delete(id);
+        } else {
+            PENDING.lock().unwrap().push(id);
+        }
+    }
+}
 
 // Texture2D is able to store and configure a texture in OpenGL.
 // It also hosts utility functions for easy management.
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct Texture2D {
     // holds the ID of the texture object, used for all texture operations to reference to this particular texture
     pub id: u32,
+    // keeps the texture object alive; None for textures owned elsewhere (e.g. render targets)
+    handle: Option<Arc<TextureHandle>>,
     // texture image dimensions
     // width and height of loaded image in pixels
     pub width: u32,
@@ -39,8 +116,29 @@ pub struct Texture2D {
 impl Texture2D {
     // constructor (sets default texture modes)
     pub fn new() -> Self {
-        let mut result = Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+        }
+        Self::adopt(id)
+    }
+
+    // takes ownership of an existing texture object, which is deleted along with the last clone
+    pub fn adopt(id: u32) -> Self {
+        LIVE.lock().unwrap().insert(id);
+        Self { id, handle: Some(Arc::new(TextureHandle(AtomicU32::new(id)))), ..Self::empty() }
+    }
+
+    // a texture referring to a texture object owned elsewhere, which is never deleted by it
+    pub fn borrowed(id: u32, width: u32, height: u32) -> Self {
+        Self { id, width, height, ..Self::empty() }
+    }
+
+    // a texture without an OpenGL texture object behind it (id 0), e.g. for headless simulations
+    pub fn empty() -> Self {
+        Self {
             id: 0,
+            handle: None,
             width: 0,
             height: 0,
             internal_format: gl::RGB,
@@ -49,12 +147,37 @@ impl Texture2D {
             wrap_t: gl::REPEAT,
             filter_min: gl::LINEAR,
             filter_max: gl::LINEAR
+        }
+    }
 
+    // whether the texture object is owned (and deleted) by the clones of this texture
+    pub fn is_owned(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    // the texture object currently behind the texture; id stays the same when it is recreated
+    pub fn gl_id(&self) -> u32 {
+        match self.handle.as_ref() {
+            Some(handle) => handle.0.load(Ordering::Relaxed),
+            None => self.id
+        }
+    }
+
+    // gives the texture (and all its clones) a new, empty texture object in place of the
+    // current one, which is expected to be deleted already; false if the texture is not owned
+    pub fn replace_object(&self) -> bool {
+        let Some(handle) = self.handle.as_ref() else {
+            return false;
         };
+        let mut id = 0;
         unsafe {
-            gl::GenTextures(1, &mut result.id);
+            gl::GenTextures(1, &mut id);
         }
-        result
+        let mut live = LIVE.lock().unwrap();
+        live.remove(&handle.0.load(Ordering::Relaxed));
+        live.insert(id);
+        handle.0.store(id, Ordering::Relaxed);
+        true
     }
 
     // generates texture from image data
@@ -64,7 +187,7 @@ impl Texture2D {
         let data_vec = Vec::from(data);
         unsafe {
             // create Texture
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::BindTexture(gl::TEXTURE_2D, self.gl_id());
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
@@ -86,10 +209,44 @@ impl Texture2D {
         }
     }
 
+    // size of the texture's image in GPU memory, in bytes
+    pub fn memory_size(&self) -> usize {
+        let channels = match self.internal_format {
+            gl::RGBA16F => 8,
+            gl::RGBA => 4,
+            gl::RGB => 3,
+            _ => 1
+        };
+        self.width as usize * self.height as usize * channels
+    }
+
     // binds the texture as the current active GL_TEXTURE_2D texture object
     pub fn bind(&self) {
+        resource_manager::touch_texture(self.id);
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::BindTexture(gl::TEXTURE_2D, self.gl_id());
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_test_deleted_with_last_clone() {
+        // made-up ids; tests don't run on the GL thread, so deletions are only queued
+        let texture = Texture2D::adopt(900_001);
+        let copy = texture.clone();
+        drop(texture);
+        assert!(!PENDING.lock().unwrap().contains(&900_001));
+        drop(copy);
+        assert!(PENDING.lock().unwrap().contains(&900_001));
+        assert!(LIVE.lock().unwrap().contains(&900_001));
+        // borrowed textures are never deleted
+        drop(Texture2D::borrowed(900_002, 4, 4));
+        assert!(!PENDING.lock().unwrap().contains(&900_002));
+        assert!(!LIVE.lock().unwrap().contains(&900_002));
+        assert_eq!(Texture2D::borrowed(900_002, 4, 4).gl_id(), 900_002);
+        assert!(!Texture2D::borrowed(900_002, 4, 4).replace_object());
+    }
+}
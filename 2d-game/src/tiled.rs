@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use std::collections::HashMap;
+use std::fs;
+use serde::Deserialize;
+use serde_json::Value;
+use xml::reader::{EventReader, XmlEvent};
+use crate::game_level::{LevelError, LevelObject, LevelObjectKind};
+
+// Importer for maps authored with the Tiled map editor (https://www.mapeditor.org),
+// supporting both the TMX (XML) and the JSON map format. Tile layers are converted
+// into the tile codes understood by GameLevel through a configurable GID table, and
+// object layers are read as power-up spawners and checkpoints (hazards are skipped, as the game has none).
+
+// the upper bits of a GID store the flip/rotation flags of a tile, we don't use them
+const GID_FLAGS_MASK: u32 = 0xF0000000;
+
+/// A map imported from Tiled, already converted into level tile codes.
+pub struct ImportedLevel {
+    pub tiles: Vec<Vec<u32>>,
+    // objects in map pixel coordinates
+    pub objects: Vec<LevelObject>,
+    // size of the whole map in pixels
+    pub pixel_size: glm::TVec2<f32>
+}
+
+/// Maps Tiled global tile IDs (GIDs) to level tile codes.
+#[derive(Deserialize)]
+pub struct GidTable {
+    gids: HashMap<String, u32>
+}
+
+impl GidTable {
+    // loads the table from a TOML file holding a [gids] section of `gid = tile code` pairs
+    pub fn load(path: &str) -> Result<Self, LevelError> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| LevelError::Io(format!("{}: {}", path, e)))?;
+        Self::parse(source.as_str())
+    }
+
+    pub fn parse(source: &str) -> Result<Self, LevelError> {
+        let table: Self = toml::from_str(source)
+            .map_err(|e| LevelError::Import(format!("invalid GID table: {}", e)))?;
+        if let Some(key) = table.gids.keys().find(|key| key.parse::<u32>().is_err()) {
+            return Err(LevelError::Import(format!("invalid GID table: \"{}\" is not a GID", key)));
+        }
+        Ok(table)
+    }
+
+    // returns the tile code of the given GID, GID 0 always is an empty tile
+    fn tile_code(&self, gid: u32) -> Option<u32> {
+        let gid = gid & !GID_FLAGS_MASK;
+        if gid == 0 {
+            return Some(0);
+        }
+        self.gids.get(&gid.to_string()).copied()
+    }
+}
+
+// imports a Tiled map file, the format is chosen by the file extension (.tmx or .json)
+pub fn import(file: &str, gid_table: &GidTable) -> Result<ImportedLevel, LevelError> {
+    let source = fs::read_to_string(file)
+        .map_err(|e| LevelError::Io(format!("{}: {}", file, e)))?;
+    if file.ends_with(".tmx") {
+        import_tmx(source.as_str(), gid_table)
+    } else {
+        import_json(source.as_str(), gid_table)
+    }
+}
+
+// returns whether the file should be loaded by the Tiled importer
+pub fn is_tiled_file(file: &str) -> bool {
+    file.ends_with(".tmx") || file.ends_with(".json")
+}
+
+// intermediate, format-independent representation of a map
+#[derive(Default)]
+struct RawMap {
+    width: usize,
+    height: usize,
+    tile_width: f32,
+    tile_height: f32,
+    // GIDs of all tile layers, top layer last
+    layers: Vec<Vec<u32>>,
+    objects: Vec<RawObject>
+}
+
+#[derive(Default)]
+struct RawObject {
+    class: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    properties: HashMap<String, String>
+}
+
+pub fn import_json(source: &str, gid_table: &GidTable) -> Result<ImportedLevel, LevelError> {
+    let map: Value = serde_json::from_str(source)
+        .map_err(|e| LevelError::Import(format!("invalid Tiled JSON map: {}", e)))?;
+    let mut raw = RawMap {
+        width: json_u64(&map, "width")? as usize,
+        height: json_u64(&map, "height")? as usize,
+        tile_width: json_u64(&map, "tilewidth")? as f32,
+        tile_height: json_u64(&map, "tileheight")? as f32,
+        ..RawMap::default()
+    };
+    let layers = map["layers"].as_array()
+        .ok_or_else(|| LevelError::Import("map has no layers".to_string()))?;
+    for layer in layers {
+        match layer["type"].as_str() {
+            Some("tilelayer") => {
+                let data = layer["data"].as_array()
+                    .ok_or_else(|| LevelError::Import("only uncompressed tile layer data is supported".to_string()))?;
+                raw.layers.push(data.iter().map(|gid| gid.as_u64().unwrap_or(0) as u32).collect());
+            }
+            Some("objectgroup") => {
+                for object in layer["objects"].as_array().into_iter().flatten() {
+                    // Tiled 1.9 renamed the "type" of objects to "class"
+                    let class = object["class"].as_str().or(object["type"].as_str()).unwrap_or("");
+                    let mut properties = HashMap::new();
+                    for property in object["properties"].as_array().into_iter().flatten() {
+                        if let Some(name) = property["name"].as_str() {
+                            let value = match &property["value"] {
+                                Value::String(value) => value.clone(),
+                                value => value.to_string()
+                            };
+                            properties.insert(name.to_string(), value);
+                        }
+                    }
+                    raw.objects.push(RawObject {
+                        class: class.to_string(),
+                        x: object["x"].as_f64().unwrap_or(0.0) as f32,
+                        y: object["y"].as_f64().unwrap_or(0.0) as f32,
+                        width: object["width"].as_f64().unwrap_or(0.0) as f32,
+                        height: object["height"].as_f64().unwrap_or(0.0) as f32,
+                        properties
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    convert(raw, gid_table)
+}
+
+fn json_u64(value: &Value, key: &str) -> Result<u64, LevelError> {
+    value[key].as_u64().ok_or_else(|| LevelError::Import(format!("map is missing \"{}\"", key)))
+}
+
+pub fn import_tmx(source: &str, gid_table: &GidTable) -> Result<ImportedLevel, LevelError> {
+    let mut raw = RawMap::default();
+    let mut in_data = false;
+    let mut current_object: Option<RawObject> = None;
+    for event in EventReader::from_str(source) {
+        let event = event.map_err(|e| LevelError::Import(format!("invalid TMX map: {}", e)))?;
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let attribute = |key: &str| attributes.iter()
+                    .find(|it| it.name.local_name == key)
+                    .map(|it| it.value.clone());
+                let number = |key: &str| attribute(key).and_then(|it| it.parse::<f32>().ok()).unwrap_or(0.0);
+                match name.local_name.as_str() {
+                    "map" => {
+                        raw.width = number("width") as usize;
+                        raw.height = number("height") as usize;
+                        raw.tile_width = number("tilewidth");
+                        raw.tile_height = number("tileheight");
+                    }
+                    "data" => {
+                        let encoding = attribute("encoding").unwrap_or_default();
+                        if encoding != "csv" {
+                            return Err(LevelError::Import(format!("unsupported tile layer encoding \"{}\", save the map with CSV encoding", encoding)));
+                        }
+                        in_data = true;
+                    }
+                    "object" => {
+                        current_object = Some(RawObject {
+                            class: attribute("class").or(attribute("type")).unwrap_or_default(),
+                            x: number("x"),
+                            y: number("y"),
+                            width: number("width"),
+                            height: number("height"),
+                            properties: HashMap::new()
+                        });
+                    }
+                    "property" => {
+                        if let (Some(object), Some(key), Some(value)) = (current_object.as_mut(), attribute("name"), attribute("value")) {
+                            object.properties.insert(key, value);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::Characters(text) if in_data => {
+                let mut layer = Vec::new();
+                for gid in text.split(',') {
+                    let gid = gid.trim();
+                    if gid.is_empty() {
+                        continue;
+                    }
+                    layer.push(gid.parse::<u32>()
+                        .map_err(|_| LevelError::Import(format!("invalid GID \"{}\"", gid)))?);
+                }
+                raw.layers.push(layer);
+            }
+            XmlEvent::EndElement { name } => {
+                match name.local_name.as_str() {
+                    "data" => in_data = false,
+                    "object" => {
+                        if let Some(object) = current_object.take() {
+                            raw.objects.push(object);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    convert(raw, gid_table)
+}
+
+// flattens the tile layers into level tile codes and interprets the objects
+fn convert(raw: RawMap, gid_table: &GidTable) -> Result<ImportedLevel, LevelError> {
+    if raw.width == 0 || raw.height == 0 || raw.layers.is_empty() {
+        return Err(LevelError::Empty);
+    }
+    let mut tiles = vec![vec![0u32; raw.width]; raw.height];
+    // upper layers overwrite lower ones wherever they contain a tile
+    for layer in raw.layers.iter() {
+        if layer.len() != raw.width * raw.height {
+            return Err(LevelError::Import(format!("tile layer has {} tiles but the map is {}x{}", layer.len(), raw.width, raw.height)));
+        }
+        for (i, gid) in layer.iter().enumerate() {
+            let (x, y) = (i % raw.width, i / raw.width);
+            let code = gid_table.tile_code(*gid)
+                .ok_or_else(|| LevelError::Import(format!("row {}, column {}: GID {} is not mapped to a tile code", y + 1, x + 1, gid & !GID_FLAGS_MASK)))?;
+            if code != 0 {
+                tiles[y][x] = code;
+            }
+        }
+    }
+
+    let mut objects = Vec::new();
+    for object in raw.objects {
+        let kind = match object.class.as_str() {
+            "power_up_spawner" => match object.properties.get("power_up") {
+                Some(power_up) => LevelObjectKind::PowerUpSpawner(power_up.clone()),
+                None => return Err(LevelError::Import("power_up_spawner object without a \"power_up\" property".to_string()))
+            },
+            "checkpoint" => LevelObjectKind::Checkpoint,
+            // nothing in the game reacts to hazards yet
+            "hazard" => {
+                log::warn!("TILED: Ignoring hazard object at {}, {}, hazards aren't supported", object.x, object.y);
+                continue;
+            }
+            class => {
+                log::warn!("TILED: Ignoring object of unknown class \"{}\"", class);
+                continue;
+            }
+        };
+        objects.push(LevelObject {
+            kind,
+            position: glm::vec2(object.x, object.y),
+            size: glm::vec2(object.width, object.height)
+        });
+    }
+
+    Ok(ImportedLevel {
+        tiles,
+        objects,
+        pixel_size: glm::vec2(raw.width as f32 * raw.tile_width, raw.height as f32 * raw.tile_height)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIDS: &str = "[gids]\n1 = 1\n2 = 2\n3 = 5\n";
+
+    const JSON_MAP: &str = r#"{
+        "width": 3, "height": 2, "tilewidth": 32, "tileheight": 16,
+        "layers": [
+            { "type": "tilelayer", "data": [1, 2, 0, 3, 3, 2147483650] },
+            { "type": "objectgroup", "objects": [
+                { "class": "power_up_spawner", "x": 32, "y": 0, "width": 32, "height": 16,
+                  "properties": [{ "name": "power_up", "type": "string", "value": "sticky" }] },
+                { "type": "hazard", "x": 0, "y": 16, "width": 96, "height": 16 },
+                { "class": "checkpoint", "x": 64, "y": 0, "width": 32, "height": 16 }
+            ] }
+        ]
+    }"#;
+
+    const TMX_MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="32" tileheight="16">
+            <layer id="1" name="bricks" width="3" height="2">
+                <data encoding="csv">
+1,2,0,
+3,3,2
+                </data>
+            </layer>
+            <objectgroup id="2" name="objects">
+                <object id="1" type="power_up_spawner" x="32" y="0" width="32" height="16">
+                    <properties><property name="power_up" value="sticky"/></properties>
+                </object>
+            </objectgroup>
+        </map>"#;
+
+    #[test]
+    fn import_json_test() {
+        let gids = GidTable::parse(GIDS).expect("The GID table should be valid.");
+        let level = import_json(JSON_MAP, &gids).expect("The map should be importable.");
+        assert_eq!(level.tiles, vec![vec![1, 2, 0], vec![5, 5, 2]]);
+        assert_eq!(level.pixel_size, glm::vec2(96.0, 32.0));
+        // the hazard is left out
+        assert_eq!(level.objects.len(), 2);
+        assert_eq!(level.objects[0].kind, LevelObjectKind::PowerUpSpawner("sticky".to_string()));
+        assert_eq!(level.objects[0].position, glm::vec2(32.0, 0.0));
+        assert_eq!(level.objects[1].kind, LevelObjectKind::Checkpoint);
+    }
+
+    #[test]
+    fn import_tmx_test() {
+        let gids = GidTable::parse(GIDS).expect("The GID table should be valid.");
+        let level = import_tmx(TMX_MAP, &gids).expect("The map should be importable.");
+        assert_eq!(level.tiles, vec![vec![1, 2, 0], vec![5, 5, 2]]);
+        assert_eq!(level.objects.len(), 1);
+        assert_eq!(level.objects[0].kind, LevelObjectKind::PowerUpSpawner("sticky".to_string()));
+    }
+
+    #[test]
+    fn import_test_unmapped_gid() {
+        let gids = GidTable::parse("[gids]\n1 = 1\n").expect("The GID table should be valid.");
+        let result = import_tmx(TMX_MAP, &gids);
+        assert!(matches!(result, Err(LevelError::Import(_))), "Unmapped GIDs must be rejected.");
+    }
+}
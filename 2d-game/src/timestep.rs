@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// FixedTimestep splits the measured frame times into simulation steps of a fixed length.
+// Long frames (debugger pauses, window drags, slow frames) are clamped and the number of
+// steps per frame is capped, so the simulation never jumps and can't fall into a spiral
+// of death where each frame has to simulate more time than the one before. The steps take
+// as long in real time either way, but the game speed decides how much simulated time passes
+// in each of them.
+pub struct FixedTimestep {
+    // length of a single simulation step in seconds
+    pub step: f32,
+    // longest frame time which gets simulated; the rest of a longer frame is dropped
+    pub max_frame_time: f32,
+    // maximum number of simulation steps per frame
+    pub max_updates: u32,
+    // simulated seconds per real second (the game speed setting)
+    pub speed: f32,
+    accumulator: f32
+}
+
+impl FixedTimestep {
+    // constructor
+    pub fn new(step: f32, max_frame_time: f32, max_updates: u32) -> Self {
+        Self {
+            step,
+            max_frame_time,
+            max_updates,
+            speed: 1.0,
+            accumulator: 0.0
+        }
+    }
+
+    // adds the time of the last frame and returns how many steps have to be simulated
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.clamp(0.0, self.max_frame_time);
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_updates {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if steps == self.max_updates {
+            // we can't keep up; drop the backlog instead of carrying it into the next frame
+            self.accumulator = self.accumulator.min(self.step);
+        }
+        steps
+    }
+
+    // simulated time of a single step
+    pub fn world_step(&self) -> f32 {
+        self.step * self.speed
+    }
+
+    // fraction of a step which is accumulated but not simulated yet
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_timestep_test_steps() {
+        let mut timestep = FixedTimestep::new(0.125, 1.0, 8);
+        assert_eq!(timestep.advance(0.3125), 2);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(timestep.advance(0.0625), 1);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+
+    #[test]
+    fn fixed_timestep_test_speed() {
+        let mut timestep = FixedTimestep::new(0.125, 1.0, 8);
+        timestep.speed = 0.5;
+        // just as many steps, each of which simulates less time
+        assert_eq!(timestep.advance(0.25), 2);
+        assert_eq!(timestep.world_step(), 0.0625);
+    }
+
+    #[test]
+    fn fixed_timestep_test_clamp() {
+        let mut timestep = FixedTimestep::new(0.125, 0.5, 100);
+        // a ten second stall is simulated as a single long frame
+        assert_eq!(timestep.advance(10.0), 4);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+
+    #[test]
+    fn fixed_timestep_test_max_updates() {
+        let mut timestep = FixedTimestep::new(0.125, 2.0, 4);
+        assert_eq!(timestep.advance(1.0), 4);
+        // the backlog of the slow frame isn't carried over
+        assert_eq!(timestep.alpha(), 1.0);
+        assert_eq!(timestep.advance(0.0), 1);
+    }
+}
@@ -0,0 +1,115 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// how far the paddle has to be moved to finish the first step
+pub const MOVE_DISTANCE: f32 = 100.0;
+
+/// What a tutorial step points the player at; the rest of the screen is dimmed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Highlight {
+    Paddle,
+    Ball,
+    Bricks
+}
+
+/// The action finishing a tutorial step.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Trigger {
+    // the paddle is moved by MOVE_DISTANCE
+    Move,
+    // the ball leaves the paddle
+    Launch,
+    // a brick is destroyed
+    BreakBrick
+}
+
+// the parts of the game the triggers look at
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    pub paddle_x: f32,
+    pub launched: bool,
+    pub bricks_left: usize
+}
+
+/// A prompt of the tutorial; its text is translated as tutorial.<id>.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Step {
+    pub id: &'static str,
+    pub highlight: Highlight,
+    pub trigger: Trigger
+}
+
+// the steps of the tutorial of the first level, in order
+pub const STEPS: [Step; 3] = [
+    Step { id: "move", highlight: Highlight::Paddle, trigger: Trigger::Move },
+    Step { id: "launch", highlight: Highlight::Ball, trigger: Trigger::Launch },
+    Step { id: "break", highlight: Highlight::Bricks, trigger: Trigger::BreakBrick }
+];
+
+impl Trigger {
+    // whether the action has been performed since the step started
+    pub fn is_met(self, start: &Progress, now: &Progress) -> bool {
+        match self {
+            Trigger::Move => (now.paddle_x - start.paddle_x).abs() >= MOVE_DISTANCE,
+            Trigger::Launch => now.launched,
+            Trigger::BreakBrick => now.bricks_left < start.bricks_left
+        }
+    }
+}
+
+/// The step of the tutorial being shown.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tutorial {
+    pub step: Step,
+    // the progress when the step was shown, and the time it was shown at (its prompt is typed out)
+    pub start: Progress,
+    pub shown: f64
+}
+
+impl Tutorial {
+    pub fn new(step: Step, start: Progress, shown: f64) -> Self {
+        Self { step, start, shown }
+    }
+
+    pub fn is_done(&self, now: &Progress) -> bool {
+        self.step.trigger.is_met(&self.start, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tutorial_test_triggers() {
+        let start = Progress { paddle_x: 300.0, launched: false, bricks_left: 20 };
+        let moved = Progress { paddle_x: 190.0, ..start };
+        assert!(Trigger::Move.is_met(&start, &moved));
+        assert!(!Trigger::Move.is_met(&start, &Progress { paddle_x: 350.0, ..start }));
+        assert!(!Trigger::Launch.is_met(&start, &moved));
+        assert!(Trigger::Launch.is_met(&start, &Progress { launched: true, ..start }));
+        assert!(!Trigger::BreakBrick.is_met(&start, &start));
+        assert!(Trigger::BreakBrick.is_met(&start, &Progress { bricks_left: 19, ..start }));
+    }
+
+    #[test]
+    fn tutorial_test_steps() {
+        let tutorial = Tutorial::new(STEPS[0], Progress::default(), 0.0);
+        assert_eq!(tutorial.step.highlight, Highlight::Paddle);
+        assert!(tutorial.is_done(&Progress { paddle_x: -MOVE_DISTANCE, ..Progress::default() }));
+        assert_eq!(STEPS.last().unwrap().trigger, Trigger::BreakBrick);
+    }
+}
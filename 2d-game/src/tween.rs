@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+/// Easing curves mapping linear progress (0 to 1) onto the progress of an animation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Ease {
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    // overshoots the target slightly before settling
+    OutBack
+}
+
+impl Ease {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::InQuad => t * t,
+            Ease::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Ease::InOutQuad => if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 },
+            Ease::OutBack => {
+                let c = 1.70158;
+                1.0 + (c + 1.0) * (t - 1.0).powi(3) + c * (t - 1.0).powi(2)
+            }
+        }
+    }
+}
+
+// values a tween can animate
+pub trait Lerp: Copy {
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for glm::TVec2<f32> {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for glm::TVec3<f32> {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+/// Animates a value from `from` to `to` over `duration` seconds.
+#[derive(Copy, Clone, Debug)]
+pub struct Tween<T: Lerp> {
+    pub from: T,
+    pub to: T,
+    pub duration: f32,
+    pub ease: Ease,
+    elapsed: f32
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32, ease: Ease) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            ease,
+            elapsed: 0.0
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+    }
+
+    // linear progress from 0 to 1
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 { 1.0 } else { self.elapsed / self.duration }
+    }
+
+    pub fn value(&self) -> T {
+        self.from.lerp(&self.to, self.ease.apply(self.progress()))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tween_test_ease_endpoints() {
+        for ease in [Ease::Linear, Ease::InQuad, Ease::OutQuad, Ease::InOutQuad, Ease::OutBack] {
+            assert!(ease.apply(0.0).abs() < 1e-5, "{:?}", ease);
+            assert!((ease.apply(1.0) - 1.0).abs() < 1e-5, "{:?}", ease);
+        }
+        assert!(Ease::OutBack.apply(0.8) > 1.0);
+    }
+
+    #[test]
+    fn tween_test_update() {
+        let mut tween = Tween::new(glm::vec2(0.0, 10.0), glm::vec2(10.0, 0.0), 0.5, Ease::Linear);
+        tween.update(0.25);
+        assert_eq!(tween.value(), glm::vec2(5.0, 5.0));
+        assert!(!tween.is_finished());
+        tween.update(1.0);
+        assert_eq!(tween.value(), glm::vec2(10.0, 0.0));
+        assert!(tween.is_finished());
+    }
+}
@@ -0,0 +1,351 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use learnopengl_shared::util;
+use crate::resource_manager;
+use crate::res_name::res_name;
+use crate::sprite_renderer::SpriteRenderer;
+
+// height of widget text at a scale of 1.0 (the font is loaded at 24 pixels)
+const FONT_HEIGHT: f32 = 24.0;
+// scale of the widget text
+const TEXT_SCALE: f32 = 0.75;
+// width of the track of a slider
+const SLIDER_WIDTH: f32 = 100.0;
+// size of the box of a toggle
+const TOGGLE_SIZE: f32 = 14.0;
+// distance of the label and the controls to the border of a widget
+const PADDING: f32 = 8.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn contains(&self, point: glm::TVec2<f32>) -> bool {
+        point.x >= self.x && point.x < self.x + self.width && point.y >= self.y && point.y < self.y + self.height
+    }
+
+    // the rect grown by the given amount on every side
+    pub fn expand(&self, amount: f32) -> Self {
+        Self::new(self.x - amount, self.y - amount, self.width + amount * 2.0, self.height + amount * 2.0)
+    }
+
+    // the area covered by both rects (empty if they don't overlap)
+    pub fn intersect(&self, other: &Rect) -> Self {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Self::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WidgetKind {
+    Button,
+    Toggle(bool),
+    // position of the value between the minimum (0.0) and the maximum (1.0)
+    Slider(f32)
+}
+
+// A single control of a screen. The id tells the screen which of its items the widget is.
+pub struct Widget<T> {
+    pub id: T,
+    pub label: String,
+    pub kind: WidgetKind,
+    pub rect: Rect,
+    // scale of the layout the widget belongs to
+    pub scale: f32
+}
+
+impl<T> Widget<T> {
+    // the track of a slider, at the right end of the widget
+    pub fn slider_track(&self) -> Rect {
+        let width = SLIDER_WIDTH * self.scale;
+        let height = 6.0 * self.scale;
+        Rect::new(
+            self.rect.x + self.rect.width - width - PADDING * self.scale,
+            self.rect.y + (self.rect.height - height) / 2.0,
+            width,
+            height
+        )
+    }
+
+    // the box of a toggle, at the right end of the widget
+    pub fn toggle_box(&self) -> Rect {
+        let size = TOGGLE_SIZE * self.scale;
+        Rect::new(
+            self.rect.x + self.rect.width - size - PADDING * self.scale,
+            self.rect.y + (self.rect.height - size) / 2.0,
+            size,
+            size
+        )
+    }
+}
+
+// Column lays out widgets from top to bottom. Screens build their column whenever they
+// handle input or render, so the widgets always reflect the current state.
+pub struct Column<T> {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub row_height: f32,
+    pub scale: f32,
+    widgets: Vec<Widget<T>>
+}
+
+impl<T: Copy + PartialEq> Column<T> {
+    // constructor; width and row height are multiplied with the scale
+    pub fn new(x: f32, y: f32, width: f32, row_height: f32, scale: f32) -> Self {
+        Self {
+            x,
+            y,
+            width: width * scale,
+            row_height: row_height * scale,
+            scale,
+            widgets: Vec::new()
+        }
+    }
+
+    fn push(&mut self, id: T, label: String, kind: WidgetKind) {
+        let rect = Rect::new(self.x, self.y + self.widgets.len() as f32 * self.row_height, self.width, self.row_height);
+        self.widgets.push(Widget { id, label, kind, rect, scale: self.scale });
+    }
+
+    pub fn button(&mut self, id: T, label: String) {
+        self.push(id, label, WidgetKind::Button);
+    }
+
+    pub fn toggle(&mut self, id: T, label: String, value: bool) {
+        self.push(id, label, WidgetKind::Toggle(value));
+    }
+
+    pub fn slider(&mut self, id: T, label: String, value: f32) {
+        self.push(id, label, WidgetKind::Slider(value.clamp(0.0, 1.0)));
+    }
+
+    pub fn widgets(&self) -> &[Widget<T>] {
+        &self.widgets
+    }
+
+    // the area covered by all widgets
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.widgets.len() as f32 * self.row_height)
+    }
+}
+
+// input of a single step, gathered from keyboard, mouse and gamepad
+#[derive(Copy, Clone, Debug, Default)]
+pub struct UiInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub activate: bool,
+    pub back: bool,
+    pub cursor: glm::TVec2<f32>,
+    // the cursor moved during the step; only then does it take the focus
+    pub cursor_moved: bool,
+    pub click: bool
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UiEvent<T> {
+    // the widget was pressed
+    Activate(T),
+    // the value of the widget should be lowered (-1) or raised (1)
+    Adjust(T, i32),
+    Back
+}
+
+// UiState holds what a screen needs to remember between steps: the focused widget.
+#[derive(Default)]
+pub struct UiState {
+    pub focus: usize
+}
+
+impl UiState {
+    pub fn new() -> Self {
+        Self { focus: 0 }
+    }
+
+    // moves the focus back to the first widget, e.g. when another screen opens
+    pub fn reset(&mut self) {
+        self.focus = 0;
+    }
+
+    // applies the input to the widgets, returning what the screen has to do
+    pub fn update<T: Copy>(&mut self, widgets: &[Widget<T>], input: &UiInput) -> Option<UiEvent<T>> {
+        if input.back {
+            return Some(UiEvent::Back);
+        }
+        if widgets.is_empty() {
+            return None;
+        }
+        let count = widgets.len();
+        self.focus = self.focus.min(count - 1);
+        let hovered = widgets.iter().position(|w| w.rect.contains(input.cursor));
+        if input.cursor_moved {
+            if let Some(i) = hovered {
+                self.focus = i;
+            }
+        }
+        if input.up {
+            self.focus = (self.focus + count - 1) % count;
+        }
+        if input.down {
+            self.focus = (self.focus + 1) % count;
+        }
+        if input.click {
+            let i = hovered?;
+            self.focus = i;
+            let widget = &widgets[i];
+            return Some(match widget.kind {
+                WidgetKind::Slider(value) => {
+                    // clicks on either side of the knob step the value towards them
+                    let track = widget.slider_track();
+                    let knob = track.x + track.width * value;
+                    UiEvent::Adjust(widget.id, if input.cursor.x < knob { -1 } else { 1 })
+                }
+                _ => UiEvent::Activate(widget.id)
+            });
+        }
+        let id = widgets[self.focus].id;
+        if input.activate {
+            return Some(UiEvent::Activate(id));
+        }
+        if input.left {
+            return Some(UiEvent::Adjust(id, -1));
+        }
+        if input.right {
+            return Some(UiEvent::Adjust(id, 1));
+        }
+        None
+    }
+}
+
+// renders the widgets; text is drawn by the given function (text, x, y, scale, color)
+pub fn draw<T>(
+    widgets: &[Widget<T>],
+    state: &UiState,
+    renderer: &SpriteRenderer,
+    text: &dyn Fn(String, f32, f32, f32, glm::TVec3<f32>)
+) {
+    let white = resource_manager::get_texture(res_name!("white"));
+    let fill = |rect: Rect, color: glm::TVec3<f32>| {
+        renderer.draw_sprite_ex2(&white, glm::vec2(rect.x, rect.y), glm::vec2(rect.width, rect.height), 0.0, color);
+    };
+    for (i, widget) in widgets.iter().enumerate() {
+        let focused = i == state.focus;
+        if focused {
+            fill(widget.rect, glm::vec3(0.25, 0.25, 0.1));
+        }
+        match widget.kind {
+            WidgetKind::Button => {}
+            WidgetKind::Toggle(value) => {
+                let rect = widget.toggle_box();
+                fill(rect, glm::vec3(0.8, 0.8, 0.8));
+                let color = if value { glm::vec3(0.2, 0.8, 0.2) } else { glm::vec3(0.1, 0.1, 0.1) };
+                fill(rect.expand(-2.0 * widget.scale), color);
+            }
+            WidgetKind::Slider(value) => {
+                let track = widget.slider_track();
+                fill(track, glm::vec3(0.3, 0.3, 0.3));
+                fill(Rect::new(track.x, track.y, track.width * value, track.height), glm::vec3(0.8, 0.8, 0.2));
+                let knob = Rect::new(track.x + track.width * value - 3.0 * widget.scale, track.y - 5.0 * widget.scale, 6.0 * widget.scale, track.height + 10.0 * widget.scale);
+                fill(knob, util::glm::scale_vec3(1.0));
+            }
+        }
+        let color = if focused { glm::vec3(1.0, 1.0, 0.0) } else { util::glm::scale_vec3(1.0) };
+        let text_height = FONT_HEIGHT * TEXT_SCALE * widget.scale;
+        text(
+            widget.label.clone(),
+            widget.rect.x + PADDING * widget.scale,
+            widget.rect.y + (widget.rect.height - text_height) / 2.0,
+            TEXT_SCALE,
+            color
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column() -> Column<u32> {
+        let mut column = Column::new(100.0, 50.0, 200.0, 20.0, 1.0);
+        column.button(0, "Start".to_string());
+        column.toggle(1, "Toggle".to_string(), true);
+        column.slider(2, "Slider".to_string(), 0.5);
+        column
+    }
+
+    #[test]
+    fn ui_test_layout() {
+        let column = column();
+        assert_eq!(column.widgets()[2].rect, Rect::new(100.0, 90.0, 200.0, 20.0));
+        assert_eq!(column.bounds(), Rect::new(100.0, 50.0, 200.0, 60.0));
+        let scaled = Column::<u32>::new(0.0, 0.0, 200.0, 20.0, 1.5);
+        assert_eq!(scaled.row_height, 30.0);
+    }
+
+    #[test]
+    fn ui_test_focus_navigation() {
+        let column = column();
+        let mut state = UiState::new();
+        let up = UiInput { up: true, ..Default::default() };
+        assert_eq!(state.update(column.widgets(), &up), None);
+        assert_eq!(state.focus, 2);
+        let down = UiInput { down: true, activate: true, ..Default::default() };
+        assert_eq!(state.update(column.widgets(), &down), Some(UiEvent::Activate(0)));
+        let right = UiInput { right: true, ..Default::default() };
+        assert_eq!(state.update(column.widgets(), &right), Some(UiEvent::Adjust(0, 1)));
+        let back = UiInput { back: true, ..Default::default() };
+        assert_eq!(state.update(column.widgets(), &back), Some(UiEvent::Back));
+    }
+
+    #[test]
+    fn ui_test_mouse() {
+        let column = column();
+        let mut state = UiState::new();
+        // hovering only takes the focus while the cursor moves
+        let hover = UiInput { cursor: glm::vec2(150.0, 75.0), ..Default::default() };
+        state.update(column.widgets(), &hover);
+        assert_eq!(state.focus, 0);
+        let hover = UiInput { cursor_moved: true, ..hover };
+        state.update(column.widgets(), &hover);
+        assert_eq!(state.focus, 1);
+        let click = UiInput { click: true, ..hover };
+        assert_eq!(state.update(column.widgets(), &click), Some(UiEvent::Activate(1)));
+        // the knob of the slider is at the middle of its track (192..292)
+        let click = UiInput { cursor: glm::vec2(200.0, 100.0), click: true, ..Default::default() };
+        assert_eq!(state.update(column.widgets(), &click), Some(UiEvent::Adjust(2, -1)));
+        let click = UiInput { cursor: glm::vec2(280.0, 100.0), click: true, ..Default::default() };
+        assert_eq!(state.update(column.widgets(), &click), Some(UiEvent::Adjust(2, 1)));
+        let miss = UiInput { cursor: glm::vec2(0.0, 0.0), click: true, ..Default::default() };
+        assert_eq!(state.update(column.widgets(), &miss), None);
+    }
+}
@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::ui::Rect;
+
+/// The part of the window the game is shown in: the largest area with the aspect ratio of
+/// the game, centered in the framebuffer. In framebuffer pixels, with y from the bottom as
+/// for glViewport.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32
+}
+
+impl Viewport {
+    // letterboxes (or pillarboxes) a game of the given size into a framebuffer
+    pub fn fit(game_width: u32, game_height: u32, width: i32, height: i32) -> Self {
+        let scale = (width as f32 / game_width as f32).min(height as f32 / game_height as f32);
+        let (fit_width, fit_height) = ((game_width as f32 * scale).round() as i32, (game_height as f32 * scale).round() as i32);
+        Self {
+            x: (width - fit_width) / 2,
+            y: (height - fit_height) / 2,
+            width: fit_width,
+            height: fit_height
+        }
+    }
+
+    // makes this the GL viewport
+    pub fn apply(&self) {
+        unsafe {
+            gl::Viewport(self.x, self.y, self.width, self.height);
+        }
+    }
+
+    // converts a point in framebuffer pixels (y from the top) to game coordinates; as the
+    // viewport is centered, its top border is as wide as the bottom one
+    pub fn to_game(&self, game_width: u32, game_height: u32, x: f32, y: f32) -> (f32, f32) {
+        (
+            (x - self.x as f32) / self.width.max(1) as f32 * game_width as f32,
+            (y - self.y as f32) / self.height.max(1) as f32 * game_height as f32
+        )
+    }
+
+    // converts a rect in game coordinates (y from the top) to framebuffer pixels <x, y, width, height>
+    // with y from the bottom, as for glScissor
+    pub fn to_framebuffer(&self, game_width: u32, game_height: u32, rect: &Rect) -> [i32; 4] {
+        let (sx, sy) = (self.width as f32 / game_width.max(1) as f32, self.height as f32 / game_height.max(1) as f32);
+        let x = (self.x as f32 + rect.x * sx).round() as i32;
+        let y = (self.y as f32 + (game_height as f32 - rect.y - rect.height) * sy).round() as i32;
+        let right = (self.x as f32 + (rect.x + rect.width) * sx).round() as i32;
+        let top = (self.y as f32 + (game_height as f32 - rect.y) * sy).round() as i32;
+        [x, y, right - x, top - y]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewport_test_fit() {
+        assert_eq!(Viewport::fit(800, 600, 800, 600), Viewport { x: 0, y: 0, width: 800, height: 600 });
+        // wider windows get bars on the sides, taller ones on the top and bottom
+        assert_eq!(Viewport::fit(800, 600, 1920, 1080), Viewport { x: 240, y: 0, width: 1440, height: 1080 });
+        assert_eq!(Viewport::fit(800, 600, 800, 800), Viewport { x: 0, y: 100, width: 800, height: 600 });
+    }
+
+    #[test]
+    fn viewport_test_to_game() {
+        let viewport = Viewport::fit(800, 600, 1920, 1080);
+        assert_eq!(viewport.to_game(800, 600, 240.0, 0.0), (0.0, 0.0));
+        assert_eq!(viewport.to_game(800, 600, 960.0, 540.0), (400.0, 300.0));
+    }
+
+    #[test]
+    fn viewport_test_to_framebuffer() {
+        let viewport = Viewport::fit(800, 600, 1920, 1080);
+        // the top left quarter of the game, scaled by 1.8 and moved past the bar on the left
+        assert_eq!(viewport.to_framebuffer(800, 600, &Rect::new(0.0, 0.0, 400.0, 300.0)), [240, 540, 720, 540]);
+        assert_eq!(viewport.to_framebuffer(800, 600, &Rect::new(0.0, 0.0, 800.0, 600.0)), [240, 0, 1440, 1080]);
+    }
+}
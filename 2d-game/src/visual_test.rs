@@ -0,0 +1,260 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use image::RgbaImage;
+use serde::Deserialize;
+use crate::photo;
+use crate::replay::Trace;
+
+// Visual tests play a replay in the window of the game (run with --visual-test <file>) and take
+// screenshots after some of its steps, which are compared against reference images stored
+// next to the test as <test>-<step>.png, so rendering regressions show up. A test without all of
+// its references fails; --update records them (again). The inputs and the clock are the replay's,
+// but particles still move at random, hence the tolerance. The shipped tests are in
+// resources/visual_tests; their references depend on the GPU and driver they're recorded with and
+// aren't part of the repository, so they check nothing until recorded on the machine they run on.
+
+fn default_tolerance() -> u8 {
+    8
+}
+
+fn default_max_different() -> f32 {
+    0.002
+}
+
+/// A visual test as stored in its file.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct VisualTest {
+    // replay played, relative to the working directory of the game
+    pub replay: String,
+    // steps after which a screenshot is taken
+    pub captures: Vec<u32>,
+    // largest difference of a color channel that still counts as the same pixel
+    #[serde(default = "default_tolerance")]
+    pub tolerance: u8,
+    // fraction of the pixels which may differ
+    #[serde(default = "default_max_different")]
+    pub max_different: f32
+}
+
+impl VisualTest {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let test: VisualTest = toml::from_str(source).map_err(|e| e.to_string())?;
+        if test.captures.is_empty() {
+            return Err("there are no captures".to_string());
+        }
+        if test.captures.windows(2).any(|it| it[0] >= it[1]) {
+            return Err("captures have to be in increasing order".to_string());
+        }
+        Ok(test)
+    }
+}
+
+/// How a screenshot differs from its reference.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Difference {
+    // pixels with a channel differing by more than the tolerance
+    pub different: usize,
+    pub total: usize,
+    // largest difference of any channel
+    pub max_delta: u8
+}
+
+impl Difference {
+    pub fn fraction(&self) -> f32 {
+        self.different as f32 / self.total.max(1) as f32
+    }
+}
+
+// compares two images pixel by pixel
+pub fn compare(actual: &RgbaImage, reference: &RgbaImage, tolerance: u8) -> Result<Difference, String> {
+    if actual.dimensions() != reference.dimensions() {
+        return Err(format!("the screenshot is {:?} instead of {:?}", actual.dimensions(), reference.dimensions()));
+    }
+    let mut difference = Difference { different: 0, total: (actual.width() * actual.height()) as usize, max_delta: 0 };
+    for (a, b) in actual.pixels().zip(reference.pixels()) {
+        let delta = a.0.iter().zip(b.0.iter()).map(|(a, b)| a.abs_diff(*b)).max().unwrap_or(0);
+        difference.max_delta = difference.max_delta.max(delta);
+        if delta > tolerance {
+            difference.different += 1;
+        }
+    }
+    Ok(difference)
+}
+
+// the reference image of the screenshot after the given step
+pub fn reference_path(file: &Path, step: u32) -> PathBuf {
+    let stem = file.file_stem().map(|it| it.to_string_lossy().into_owned()).unwrap_or_default();
+    file.with_file_name(format!("{}-{}.png", stem, step))
+}
+
+/// A visual test being run by the game.
+pub struct Run {
+    pub test: VisualTest,
+    pub trace: Trace,
+    file: PathBuf,
+    // records the references instead of comparing against them
+    update: bool,
+    // steps played so far
+    pub step: u32,
+    next: usize,
+    pub failures: Vec<String>
+}
+
+impl Run {
+    pub fn load(file: &Path, root: &Path, update: bool) -> Result<Self, String> {
+        let source = fs::read_to_string(file).map_err(|e| e.to_string())?;
+        let test = VisualTest::parse(source.as_str())?;
+        let replay = root.join(&test.replay);
+        let source = fs::read_to_string(&replay).map_err(|e| format!("{}: {}", replay.display(), e))?;
+        let trace = Trace::parse(source.as_str()).map_err(|e| format!("{}: {}", replay.display(), e))?;
+        if !update {
+            if let Some(path) = test.captures.iter().map(|step| reference_path(file, *step)).find(|path| !path.exists()) {
+                return Err(format!("there's no reference {} (record the references with --update)", path.display()));
+            }
+        }
+        Ok(Self { test, trace, file: file.to_path_buf(), update, step: 0, next: 0, failures: Vec::new() })
+    }
+
+    // length of a step of the replay
+    pub fn dt(&self) -> f32 {
+        1.0 / self.trace.rate as f32
+    }
+
+    // time the game is at, as far as the replay goes
+    pub fn time(&self) -> f64 {
+        self.step as f64 / self.trace.rate as f64
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.test.captures.len()
+    }
+
+    // whether a screenshot is due after the steps played so far
+    pub fn is_capture_due(&self) -> bool {
+        self.test.captures.get(self.next) == Some(&self.step)
+    }
+
+    // checks the screenshot due after the current step (pixels as read back from OpenGL)
+    pub fn capture(&mut self, width: u32, height: u32, pixels: Vec<u8>) {
+        self.next += 1;
+        let path = reference_path(&self.file, self.step);
+        let Some(image) = photo::image_from_gl(width, height, pixels) else {
+            self.failures.push(format!("step {}: failed to read back the screenshot", self.step));
+            return;
+        };
+        if self.update {
+            match image.save(&path) {
+                Ok(()) => log::info!("VISUAL_TEST: Recorded {}", path.display()),
+                Err(e) => self.failures.push(format!("{}: {}", path.display(), e))
+            }
+            return;
+        }
+        let result = image::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|reference| compare(&image, &reference.to_rgba8(), self.test.tolerance));
+        match result {
+            Ok(difference) if difference.fraction() <= self.test.max_different => {}
+            Ok(difference) => {
+                // kept next to the reference to look at
+                let actual = path.with_extension("actual.png");
+                if let Err(e) = image.save(&actual) {
+                    log::error!("VISUAL_TEST: Failed to save {}: {}", actual.display(), e);
+                }
+                self.failures.push(format!("step {}: {:.2}% of the pixels differ (by up to {}), see {}",
+                    self.step, difference.fraction() * 100.0, difference.max_delta, actual.display()));
+            }
+            Err(e) => self.failures.push(format!("step {}: {}", self.step, e))
+        }
+    }
+}
+
+// reads back the pixels of the frame just rendered (bottom row first)
+pub fn read_frame(width: u32, height: u32) -> Vec<u8> {
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadBuffer(gl::BACK);
+        gl::ReadPixels(0, 0, width as _, height as _, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as _);
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn visual_test_test_parse() {
+        let test = VisualTest::parse("replay = \"resources/replays/one.toml\"\ncaptures = [1, 120]").unwrap();
+        assert_eq!(test.captures, vec![1, 120]);
+        assert_eq!(test.tolerance, default_tolerance());
+        assert!(VisualTest::parse("replay = \"x.toml\"\ncaptures = []").is_err());
+        assert!(VisualTest::parse("replay = \"x.toml\"\ncaptures = [120, 1]").is_err());
+        assert_eq!(reference_path(Path::new("tests/one.toml"), 120), PathBuf::from("tests/one-120.png"));
+    }
+
+    #[test]
+    fn visual_test_test_compare() {
+        let reference = RgbaImage::from_pixel(10, 10, Rgba([100, 100, 100, 255]));
+        let mut actual = reference.clone();
+        actual.put_pixel(0, 0, Rgba([104, 100, 100, 255]));
+        actual.put_pixel(1, 0, Rgba([100, 150, 100, 255]));
+        let difference = compare(&actual, &reference, 8).unwrap();
+        assert_eq!(difference, Difference { different: 1, total: 100, max_delta: 50 });
+        assert_eq!(difference.fraction(), 0.01);
+        assert!(compare(&RgbaImage::new(5, 5), &reference, 8).is_err());
+    }
+
+    #[test]
+    fn visual_test_test_missing_references() {
+        let root = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."));
+        let dir = std::env::temp_dir().join(format!("visual_test_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("one.toml");
+        fs::write(&file, "replay = \"resources/replays/one.toml\"\ncaptures = [1, 2]").unwrap();
+        let error = Run::load(&file, root, false).err().unwrap();
+        assert!(error.contains("one-1.png"), "{}", error);
+        assert!(Run::load(&file, root, true).is_ok());
+        RgbaImage::new(2, 2).save(dir.join("one-1.png")).unwrap();
+        let error = Run::load(&file, root, false).err().unwrap();
+        assert!(error.contains("one-2.png"), "{}", error);
+        RgbaImage::new(2, 2).save(dir.join("one-2.png")).unwrap();
+        assert!(Run::load(&file, root, false).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn visual_test_test_shipped() {
+        let root = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."));
+        let files: Vec<_> = fs::read_dir(root.join("resources/visual_tests"))
+            .expect("The visual tests directory should exist.")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|it| it == "toml"))
+            .collect();
+        assert!(!files.is_empty(), "There should be visual tests.");
+        for file in files {
+            // their references aren't in the repository (see above), so they're only checked for
+            // being loadable
+            if let Err(e) = Run::load(&file, root, true) {
+                panic!("{} must be a valid visual test: {}", file.display(), e);
+            }
+        }
+    }
+}
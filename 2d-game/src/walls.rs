@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use serde::{Deserialize, Serialize};
+use crate::game_object::GameObject;
+
+/// What lies beyond the left, right or top edge of the playfield.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Side {
+    // the ball bounces off the edge
+    #[default]
+    Wall,
+    // the ball leaves through the edge and comes back in on the opposite side
+    Wrap,
+    // the ball is lost once it has fully left through the edge
+    Open
+}
+
+/// An edge of the playfield the ball can bounce off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Left,
+    Right,
+    Top
+}
+
+/// What happened to the ball at the edges of the playfield during a move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WallEvent {
+    Bounced(Edge),
+    Wrapped,
+    // the ball left through an open side and is lost
+    Escaped
+}
+
+/// The boundary of a level's playfield (the `[walls]` table of a structured level).
+/// The bottom edge is always open; the default is a closed box of perfectly elastic walls.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Walls {
+    pub left: Side,
+    pub right: Side,
+    // the top can be a wall or open, but doesn't wrap around to the bottom
+    pub top: Side,
+    // hits the ceiling takes before it breaks open (0 = it never breaks)
+    pub ceiling_hp: u32,
+    // factor the ball's velocity across a wall is scaled by when bouncing off it
+    pub restitution: f32
+}
+
+// restitutions outside of this range make the ball stall or race off too quickly
+pub const RESTITUTION_RANGE: std::ops::RangeInclusive<f32> = 0.5..=1.5;
+
+impl Default for Walls {
+    fn default() -> Self {
+        Self {
+            left: Side::Wall,
+            right: Side::Wall,
+            top: Side::Wall,
+            ceiling_hp: 0,
+            restitution: 1.0
+        }
+    }
+}
+
+impl Walls {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // whether the ball can only leave through the bottom edge
+    pub fn is_closed(&self) -> bool {
+        self.left == Side::Wall && self.right == Side::Wall && self.top == Side::Wall
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.top == Side::Wrap {
+            return Err("the top can't wrap around".to_string());
+        }
+        if (self.left == Side::Wrap) != (self.right == Side::Wrap) {
+            return Err("only one of the sides wraps around".to_string());
+        }
+        if self.ceiling_hp > 0 && self.top != Side::Wall {
+            return Err("ceiling-hp needs a top wall".to_string());
+        }
+        if !RESTITUTION_RANGE.contains(&self.restitution) {
+            return Err(format!("restitution {} is not between {} and {}", self.restitution, RESTITUTION_RANGE.start(), RESTITUTION_RANGE.end()));
+        }
+        Ok(())
+    }
+
+    // keeps a moved object within the playfield of the given width: bounces it off walls,
+    // wraps it around wrapping sides and reports it once it has left through an open side
+    pub fn constrain(&self, object: &mut GameObject, width: f32) -> Option<WallEvent> {
+        let mut event = None;
+        if object.position.x <= 0.0 {
+            event = self.cross_side(object, self.left, Edge::Left, width);
+        } else if object.position.x + object.size.x >= width {
+            event = self.cross_side(object, self.right, Edge::Right, width);
+        }
+        if object.position.y <= 0.0 {
+            match self.top {
+                Side::Open => if object.position.y + object.size.y < 0.0 {
+                    event = Some(WallEvent::Escaped);
+                },
+                _ => {
+                    object.velocity.y = -object.velocity.y * self.restitution;
+                    object.position.y = 0.0;
+                    event = event.or(Some(WallEvent::Bounced(Edge::Top)));
+                }
+            }
+        }
+        event
+    }
+
+    fn cross_side(&self, object: &mut GameObject, side: Side, edge: Edge, width: f32) -> Option<WallEvent> {
+        let center = object.position.x + object.size.x / 2.0;
+        match side {
+            Side::Wall => {
+                object.velocity.x = -object.velocity.x * self.restitution;
+                object.position.x = if edge == Edge::Left { 0.0 } else { width - object.size.x };
+                Some(WallEvent::Bounced(edge))
+            }
+            // the ball comes back in once its center has crossed the edge
+            Side::Wrap if center < 0.0 => {
+                object.position.x += width;
+                Some(WallEvent::Wrapped)
+            }
+            Side::Wrap if center > width => {
+                object.position.x -= width;
+                Some(WallEvent::Wrapped)
+            }
+            Side::Open if object.position.x + object.size.x < 0.0 || object.position.x > width => Some(WallEvent::Escaped),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::Texture2D;
+
+    fn ball(x: f32, y: f32, vx: f32, vy: f32) -> GameObject {
+        GameObject::new_ex1(glm::vec2(x, y), glm::vec2(20.0, 20.0), Texture2D::empty(), glm::vec3(1.0, 1.0, 1.0), glm::vec2(vx, vy))
+    }
+
+    #[test]
+    fn walls_test_default_bounces() {
+        let walls = Walls::default();
+        let mut object = ball(-3.0, 50.0, -100.0, 80.0);
+        assert_eq!(walls.constrain(&mut object, 400.0), Some(WallEvent::Bounced(Edge::Left)));
+        assert_eq!((object.position.x, object.velocity.x), (0.0, 100.0));
+        let mut object = ball(385.0, -2.0, 100.0, -80.0);
+        assert_eq!(walls.constrain(&mut object, 400.0), Some(WallEvent::Bounced(Edge::Right)));
+        assert_eq!(object.position, glm::vec2(380.0, 0.0));
+        assert_eq!(object.velocity, glm::vec2(-100.0, 80.0));
+        let mut object = ball(100.0, 100.0, 100.0, -80.0);
+        assert_eq!(walls.constrain(&mut object, 400.0), None);
+    }
+
+    #[test]
+    fn walls_test_restitution() {
+        let walls = Walls { restitution: 0.5, ..Walls::default() };
+        let mut object = ball(100.0, -1.0, 10.0, -80.0);
+        assert_eq!(walls.constrain(&mut object, 400.0), Some(WallEvent::Bounced(Edge::Top)));
+        assert_eq!(object.velocity, glm::vec2(10.0, 40.0));
+    }
+
+    #[test]
+    fn walls_test_wrap_and_open() {
+        let walls = Walls { left: Side::Wrap, right: Side::Wrap, top: Side::Open, ..Walls::default() };
+        // half way out on the left the ball is only leaving, further out it reappears on the right
+        let mut object = ball(-5.0, 50.0, -100.0, 0.0);
+        assert_eq!(walls.constrain(&mut object, 400.0), None);
+        object.position.x = -12.0;
+        assert_eq!(walls.constrain(&mut object, 400.0), Some(WallEvent::Wrapped));
+        assert_eq!((object.position.x, object.velocity.x), (388.0, -100.0));
+        let mut object = ball(100.0, -10.0, 0.0, -80.0);
+        assert_eq!(walls.constrain(&mut object, 400.0), None);
+        object.position.y = -21.0;
+        assert_eq!(walls.constrain(&mut object, 400.0), Some(WallEvent::Escaped));
+    }
+
+    #[test]
+    fn walls_test_validate() {
+        assert!(Walls::default().validate().is_ok());
+        assert!(Walls { left: Side::Open, ceiling_hp: 3, ..Walls::default() }.validate().is_ok());
+        assert!(Walls { left: Side::Wrap, ..Walls::default() }.validate().is_err());
+        assert!(Walls { top: Side::Wrap, ..Walls::default() }.validate().is_err());
+        assert!(Walls { top: Side::Open, ceiling_hp: 2, ..Walls::default() }.validate().is_err());
+        assert!(Walls { restitution: 3.0, ..Walls::default() }.validate().is_err());
+    }
+}
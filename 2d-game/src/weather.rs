@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate nalgebra_glm as glm;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::resource_manager;
+use crate::sprite_renderer::SpriteRenderer;
+use crate::texture::Texture2D;
+use crate::tween::{Ease, Tween};
+
+// Weather is drawn over the post-processed game but below the HUD: rain streaks falling
+// across the screen and a fog of two layers of noise scrolling past each other. Both have a
+// density from 0 (off) to 1, which levels set and scripts can fade with WeatherOverlay::fade_to.
+
+// rain drops on screen at full density
+pub const MAX_DROPS: usize = 300;
+// range of the speed of a drop, in pixels per second
+const DROP_SPEED: (f32, f32) = (700.0, 1000.0);
+// range of the length of a streak, in pixels
+const DROP_LENGTH: (f32, f32) = (10.0, 26.0);
+const DROP_WIDTH: f32 = 1.5;
+// pixels a drop moves sideways per pixel it falls
+const WIND: f32 = 0.15;
+const RAIN_COLOR: [f32; 3] = [0.55, 0.6, 0.7];
+// side of the fog texture and the number of noise cells along it
+const NOISE_SIZE: u32 = 128;
+const NOISE_CELLS: u32 = 8;
+// scale of the fog texture on screen and its scroll speed (pixels per second) per layer
+const FOG_LAYERS: [(f32, [f32; 2]); 2] = [(3.0, [14.0, 3.0]), (5.0, [-9.0, 1.5])];
+const FOG_COLOR: [f32; 3] = [0.35, 0.37, 0.4];
+// seconds the overlays take to change over to the weather of another level
+pub const LEVEL_FADE: f32 = 2.0;
+
+/// The weather of a level (the `weather` table of a structured level).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Weather {
+    // density of the rain
+    #[serde(default)]
+    pub rain: f32,
+    // density of the fog
+    #[serde(default)]
+    pub fog: f32
+}
+
+impl Weather {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.rain) {
+            return Err(format!("rain {} is not between 0 and 1", self.rain));
+        }
+        if !(0.0..=1.0).contains(&self.fog) {
+            return Err(format!("fog {} is not between 0 and 1", self.fog));
+        }
+        Ok(())
+    }
+
+    pub fn is_clear(&self) -> bool {
+        self.rain <= 0.0 && self.fog <= 0.0
+    }
+}
+
+// tileable value noise, one byte per pixel of a size x size image made of cells x cells cells
+pub fn noise(size: u32, cells: u32, seed: u64) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let lattice: Vec<f32> = (0..cells * cells).map(|_| rng.random_range(0.0..1.0)).collect();
+    // the lattice wraps around, so the image does as well
+    let at = |x: u32, y: u32| lattice[((y % cells) * cells + x % cells) as usize];
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let cell = size as f32 / cells as f32;
+    let mut result = Vec::with_capacity((size * size) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let (fx, fy) = (x as f32 / cell, y as f32 / cell);
+            let (cx, cy) = (fx.floor() as u32, fy.floor() as u32);
+            let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+            let top = at(cx, cy) + (at(cx + 1, cy) - at(cx, cy)) * tx;
+            let bottom = at(cx, cy + 1) + (at(cx + 1, cy + 1) - at(cx, cy + 1)) * tx;
+            result.push(((top + (bottom - top) * ty) * 255.0).round() as u8);
+        }
+    }
+    result
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Raindrop {
+    position: glm::TVec2<f32>,
+    speed: f32,
+    length: f32
+}
+
+/// Rain drops falling across the screen; the density decides how many of them are shown.
+pub struct Rain {
+    drops: Vec<Raindrop>,
+    width: f32,
+    height: f32,
+    rng: StdRng
+}
+
+impl Rain {
+    pub fn new(width: u32, height: u32, seed: u64) -> Self {
+        let mut result = Self { drops: Vec::with_capacity(MAX_DROPS), width: width as f32, height: height as f32, rng: StdRng::seed_from_u64(seed) };
+        // the screen starts out full of drops
+        for _ in 0..MAX_DROPS {
+            let y = result.rng.random_range(0.0..result.height);
+            let drop = result.spawn(y);
+            result.drops.push(drop);
+        }
+        result
+    }
+
+    // a new drop with its top at the given height
+    fn spawn(&mut self, y: f32) -> Raindrop {
+        // drops drift sideways, so they may come in from beyond the left edge
+        let x = self.rng.random_range(-self.height * WIND..self.width);
+        Raindrop {
+            position: glm::vec2(x, y),
+            speed: self.rng.random_range(DROP_SPEED.0..DROP_SPEED.1),
+            length: self.rng.random_range(DROP_LENGTH.0..DROP_LENGTH.1)
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for i in 0..self.drops.len() {
+            let drop = &mut self.drops[i];
+            drop.position += glm::vec2(WIND, 1.0) * drop.speed * dt;
+            if drop.position.y > self.height {
+                let length = drop.length;
+                self.drops[i] = self.spawn(-length);
+            }
+        }
+    }
+
+    // the drops shown at the given density
+    pub fn visible(&self, density: f32) -> usize {
+        (density.clamp(0.0, 1.0) * self.drops.len() as f32).round() as usize
+    }
+}
+
+/// The weather overlays of the game, fading between densities.
+pub struct WeatherOverlay {
+    // the current densities
+    pub weather: Weather,
+    // the weather of the level it last changed over to
+    pub level: Option<Weather>,
+    rain_fade: Option<Tween<f32>>,
+    fog_fade: Option<Tween<f32>>,
+    rain: Rain,
+    // seconds the fog has been scrolling for
+    time: f32,
+    streak: Texture2D,
+    fog: Texture2D
+}
+
+impl WeatherOverlay {
+    pub fn new(level: Option<Weather>, width: u32, height: u32) -> Self {
+        let seed = rand::random();
+        // the streaks fade in towards the bottom, where the drop is
+        let streak = (0..16u8).flat_map(|i| [255, 255, 255, i * 17]).collect::<Vec<u8>>();
+        let fog = noise(NOISE_SIZE, NOISE_CELLS, seed).into_iter().flat_map(|it| [255, 255, 255, it]).collect::<Vec<u8>>();
+        resource_manager::track_gpu_memory("weather", streak.len() + fog.len());
+        Self {
+            weather: level.unwrap_or_default(),
+            level,
+            rain_fade: None,
+            fog_fade: None,
+            rain: Rain::new(width, height, seed),
+            time: 0.0,
+            streak: rgba_texture(1, 16, streak.as_slice(), gl::CLAMP_TO_EDGE),
+            fog: rgba_texture(NOISE_SIZE, NOISE_SIZE, fog.as_slice(), gl::REPEAT)
+        }
+    }
+
+    // changes the densities over the given seconds
+    pub fn fade_to(&mut self, weather: Weather, seconds: f32) {
+        self.rain_fade = Some(Tween::new(self.weather.rain, weather.rain, seconds, Ease::InOutQuad));
+        self.fog_fade = Some(Tween::new(self.weather.fog, weather.fog, seconds, Ease::InOutQuad));
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for (fade, density) in [(&mut self.rain_fade, &mut self.weather.rain), (&mut self.fog_fade, &mut self.weather.fog)] {
+            if let Some(tween) = fade.as_mut() {
+                tween.update(dt);
+                *density = tween.value();
+                if tween.is_finished() {
+                    *fade = None;
+                }
+            }
+        }
+        if self.weather.rain > 0.0 {
+            self.rain.update(dt);
+        }
+        self.time += dt;
+    }
+
+    pub fn draw(&self, renderer: &SpriteRenderer, width: u32, height: u32) {
+        if self.weather.is_clear() {
+            return;
+        }
+        unsafe {
+            // the overlays only ever lighten what is below them
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+        }
+        if self.weather.fog > 0.0 {
+            let size = glm::vec2(width as f32, height as f32);
+            for (scale, scroll) in FOG_LAYERS.iter() {
+                let tile = NOISE_SIZE as f32 * scale;
+                let offset = glm::vec2(scroll[0], scroll[1]) * self.time / tile;
+                let color = glm::vec3(FOG_COLOR[0], FOG_COLOR[1], FOG_COLOR[2]) * self.weather.fog;
+                renderer.draw_sprite_region(&self.fog, glm::vec2(0.0, 0.0), size, color, glm::vec4(offset.x, offset.y, size.x / tile, size.y / tile));
+            }
+        }
+        let color = glm::vec3(RAIN_COLOR[0], RAIN_COLOR[1], RAIN_COLOR[2]);
+        // the streaks lean with the wind
+        let angle = -WIND.atan().to_degrees();
+        for drop in self.rain.drops.iter().take(self.rain.visible(self.weather.rain)) {
+            renderer.draw_sprite_ex2(&self.streak, drop.position, glm::vec2(DROP_WIDTH, drop.length), angle, color);
+        }
+        unsafe {
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+}
+
+impl Drop for WeatherOverlay {
+    fn drop(&mut self) {
+        // the textures are deleted along with the overlay
+        resource_manager::track_gpu_memory("weather", 0);
+    }
+}
+
+fn rgba_texture(width: u32, height: u32, data: &[u8], wrap: u32) -> Texture2D {
+    let mut texture = Texture2D::new();
+    texture.internal_format = gl::RGBA;
+    texture.image_format = gl::RGBA;
+    texture.wrap_s = wrap;
+    texture.wrap_t = wrap;
+    texture.generate(width, height, data);
+    texture
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weather_test_validate() {
+        assert!(Weather { rain: 0.5, fog: 1.0 }.validate().is_ok());
+        assert!(Weather { rain: 1.5, fog: 0.0 }.validate().is_err());
+        assert!(Weather { rain: 0.0, fog: -0.1 }.validate().is_err());
+        let parsed: Weather = toml::from_str("fog = 0.4").unwrap();
+        assert_eq!(parsed, Weather { rain: 0.0, fog: 0.4 });
+        assert!(Weather::default().is_clear());
+    }
+
+    #[test]
+    fn weather_test_noise_tiles() {
+        let size = 32;
+        let noise = noise(size, 4, 7);
+        assert_eq!(noise.len(), (size * size) as usize);
+        // the right column runs into the left one and the bottom row into the top one
+        for i in 0..size {
+            let (left, right) = (noise[(i * size) as usize], noise[(i * size + size - 1) as usize]);
+            let (top, bottom) = (noise[i as usize], noise[((size - 1) * size + i) as usize]);
+            assert!(left.abs_diff(right) < 64 && top.abs_diff(bottom) < 64);
+        }
+        assert!(noise.iter().any(|it| *it != noise[0]));
+    }
+
+    #[test]
+    fn weather_test_rain() {
+        let mut rain = Rain::new(800, 600, 3);
+        assert_eq!(rain.visible(0.0), 0);
+        assert_eq!(rain.visible(0.5), MAX_DROPS / 2);
+        assert_eq!(rain.visible(2.0), MAX_DROPS);
+        for _ in 0..600 {
+            rain.update(1.0 / 60.0);
+            assert!(rain.drops.iter().all(|drop| drop.position.y <= 600.0));
+        }
+    }
+}
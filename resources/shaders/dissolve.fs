@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#version 330 core
+in vec2 TexCoords;
+out vec4 color;
+
+uniform sampler2D sprite;
+uniform vec3 spriteColor;
+uniform vec3 edge_color;
+// seconds since the brick started dissolving and how long that takes
+uniform float time;
+uniform float duration;
+// varies the noise pattern per brick
+uniform float seed;
+
+float hash(vec2 p)
+{
+    return fract(sin(dot(p, vec2(127.1, 311.7))) * 43758.5453);
+}
+
+// value noise, smooth enough for a ragged but connected edge
+float noise(vec2 p)
+{
+    vec2 i = floor(p);
+    vec2 f = fract(p);
+    vec2 u = f * f * (3.0 - 2.0 * f);
+    return mix(mix(hash(i), hash(i + vec2(1.0, 0.0)), u.x),
+               mix(hash(i + vec2(0.0, 1.0)), hash(i + vec2(1.0, 1.0)), u.x), u.y);
+}
+
+void main()
+{
+    float threshold = clamp(time / duration, 0.0, 1.0);
+    float n = noise(TexCoords * vec2(8.0, 4.0) + vec2(seed * 17.0));
+    // everything below the threshold is clipped away
+    if(n < threshold)
+        discard;
+    vec4 base = vec4(spriteColor, 1.0) * texture(sprite, TexCoords);
+    // the pixels about to be clipped glow
+    float edge = 1.0 - smoothstep(0.0, 0.08, n - threshold);
+    color = vec4(mix(base.rgb, edge_color, edge * step(0.001, threshold)), base.a);
+}
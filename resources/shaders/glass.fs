@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#version 330 core
+in vec2 TexCoords;
+out vec4 color;
+
+uniform sampler2D sprite;
+uniform vec3 spriteColor;
+uniform float opacity;
+
+void main()
+{
+    // translucent, with a brighter rim
+    vec2 edge = min(TexCoords, 1.0 - TexCoords);
+    float rim = 1.0 - smoothstep(0.0, 0.12, min(edge.x, edge.y));
+    vec4 base = vec4(spriteColor, 1.0) * texture(sprite, TexCoords);
+    color = vec4(mix(base.rgb, vec3(1.0), rim * 0.6), base.a * mix(opacity, 1.0, rim));
+}
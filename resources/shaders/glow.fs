@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// Copyright 2024 src_resources
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#version 330 core
+in vec2 TexCoords;
+out vec4 color;
+
+uniform sampler2D sprite;
+uniform vec3 spriteColor;
+uniform vec3 glow_color;
+uniform float glow_strength;
+
+void main()
+{
+    // brightens towards the center of the brick
+    float glow = 1.0 - length(TexCoords - vec2(0.5)) * 2.0;
+    vec4 base = vec4(spriteColor, 1.0) * texture(sprite, TexCoords);
+    color = vec4(base.rgb + glow_color * glow_strength * max(glow, 0.0), base.a);
+}
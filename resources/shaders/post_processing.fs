@@ -22,13 +22,87 @@ uniform sampler2D scene;
 uniform vec2  offsets[9];
 uniform int     edge_kernel[9];
 uniform float  blur_kernel[9];
+uniform float  saturation;
+// 0 clips colors above 1.0, 1 Reinhard, 2 ACES (Narkowicz)
+uniform int    tone_mapping;
+uniform float  exposure;
+// photo mode filter: 0 none, 1 grayscale, 2 sepia, 3 vignette
+uniform int    photo_filter;
+// the scene outside of the spotlight (min, max corner) is darkened by spotlight_dim
+uniform vec4   spotlight;
+uniform float  spotlight_dim;
 
-uniform bool chaos;
-uniform bool confuse;
-uniform bool shake;
+#include "effects.glsl"
+
+// color grading LUT of the level theme (see color_grading.rs)
+uniform sampler3D lut;
+uniform bool   grading;
+
+// the previous frame, blended in by ghosting (0 while no effect ghosts)
+uniform sampler2D history;
+uniform float  ghosting;
+
+// retro effects (see post_effects.rs): kind 0 unused, 1 scanlines, 2 barrel distortion,
+// 3 chromatic aberration, 4 dithering; params as listed there
+uniform int    effect_kinds[4];
+uniform vec4   effect_params[4];
+
+// while mask_pass is set, only the shape of the mask is drawn (into the stencil buffer):
+// kind 1 is a circle <center, radius relative to the height>, 2 a rect <min, max>
+uniform bool   mask_pass;
+uniform int    mask_kind;
+uniform vec4   mask_params;
+
+// ordered dithering thresholds
+const float bayer[16] = float[16](0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0);
+
+float aberration = 0.0;
+
+// samples the scene, fanning the color channels out towards the edges when there's aberration
+vec4 sample_scene(vec2 uv)
+{
+    if(uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0)
+        return vec4(0.0, 0.0, 0.0, 1.0);
+    vec4 color = texture(scene, uv);
+    if(aberration > 0.0)
+    {
+        vec2 offset = (uv - vec2(0.5)) * aberration * 2.0;
+        color.r = texture(scene, uv + offset).r;
+        color.b = texture(scene, uv - offset).b;
+    }
+    return color;
+}
+
+bool in_mask(vec2 point, float aspect)
+{
+    if(mask_kind == 1)
+        return length((point - mask_params.xy) * vec2(aspect, 1.0)) <= mask_params.z;
+    return all(greaterThanEqual(point, mask_params.xy)) && all(lessThanEqual(point, mask_params.zw));
+}
 
 void main()
 {
+    if(mask_pass)
+    {
+        // masks are drawn into the ghost target, which has the size of its history
+        vec2 size = vec2(textureSize(history, 0));
+        if(!in_mask(gl_FragCoord.xy / size, size.x / size.y))
+            discard;
+        color = vec4(0.0);
+        return;
+    }
+    // distortions of the retro effects, in order
+    vec2 uv = TexCoords;
+    for(int i = 0; i < 4; i++)
+    {
+        if(effect_kinds[i] == 2)
+        {
+            vec2 centered = uv - vec2(0.5);
+            uv = vec2(0.5) + centered * (1.0 + effect_params[i].x * dot(centered, centered) * 2.0);
+        }
+        else if(effect_kinds[i] == 3)
+            aberration += effect_params[i].x;
+    }
     // zero out memory since an out variable is initialized with undefined values by default 
     color = vec4(0.0f);
 
@@ -36,18 +110,25 @@ void main()
     // sample from texture offsets if using convolution matrix
     if(chaos || shake)
         for(int i = 0; i < 9; i++)
-            sample[i] = vec3(texture(scene, TexCoords.st + offsets[i]));
+            sample[i] = vec3(sample_scene(uv + offsets[i]));
 
     // process effects
     if(chaos)
     {           
         for(int i = 0; i < 9; i++)
             color += vec4(sample[i] * edge_kernel[i], 0.0f);
+        // keep most of the scene instead of flashing edges only
+        if(reduce_flashing)
+            color = mix(sample_scene(uv), color, 0.35);
         color.a = 1.0f;
     }
     else if(confuse)
     {
-        color = vec4(1.0 - texture(scene, TexCoords).rgb, 1.0);
+        // the flipped scene is kept, but its colors aren't inverted
+        if(reduce_flashing)
+            color = sample_scene(uv);
+        else
+            color = vec4(1.0 - sample_scene(uv).rgb, 1.0);
     }
     else if(shake)
     {
@@ -57,6 +138,52 @@ void main()
     }
     else
     {
-        color =  texture(scene, TexCoords);
+        color =  sample_scene(uv);
+    }
+    // map the HDR scene into the range of the screen
+    color.rgb *= exposure;
+    if(tone_mapping == 1)
+        color.rgb = color.rgb / (vec3(1.0) + color.rgb);
+    else if(tone_mapping == 2)
+        color.rgb = (color.rgb * (2.51 * color.rgb + 0.03)) / (color.rgb * (2.43 * color.rgb + 0.59) + 0.14);
+    color.rgb = clamp(color.rgb, 0.0, 1.0);
+    // the alpha of the framebuffer is left over from blending
+    color.a = 1.0;
+    // push the colors away from (or towards) their luminance
+    float luminance = dot(color.rgb, vec3(0.299, 0.587, 0.114));
+    color.rgb = clamp(mix(vec3(luminance), color.rgb, saturation), 0.0, 1.0);
+    // dim everything but what the tutorial points at
+    if(spotlight_dim > 0.0 && (any(lessThan(TexCoords, spotlight.xy)) || any(greaterThan(TexCoords, spotlight.zw))))
+        color.rgb *= 1.0 - spotlight_dim;
+    // photo mode filters
+    luminance = dot(color.rgb, vec3(0.299, 0.587, 0.114));
+    if(photo_filter == 1)
+        color.rgb = vec3(luminance);
+    else if(photo_filter == 2)
+        color.rgb = clamp(vec3(luminance) * vec3(1.07, 0.74, 0.43) + vec3(0.1, 0.05, 0.0), 0.0, 1.0);
+    else if(photo_filter == 3)
+        color.rgb *= smoothstep(0.85, 0.35, distance(TexCoords, vec2(0.5)));
+    // color effects of the retro effects, in order
+    for(int i = 0; i < 4; i++)
+    {
+        if(effect_kinds[i] == 1)
+            color.rgb *= 1.0 - effect_params[i].x * (0.5 + 0.5 * sin(uv.y * effect_params[i].y * 6.2831853));
+        else if(effect_kinds[i] == 4)
+        {
+            float levels = max(effect_params[i].x - 1.0, 1.0);
+            ivec2 cell = ivec2(gl_FragCoord.xy) % 4;
+            float threshold = (bayer[cell.y * 4 + cell.x] + 0.5) / 16.0 - 0.5;
+            color.rgb = floor(color.rgb * levels + threshold + 0.5) / levels;
+        }
+    }
+    color.rgb = clamp(color.rgb, 0.0, 1.0);
+    // color grading, sampling the LUT at the centers of its first and last entries
+    if(grading)
+    {
+        float size = float(textureSize(lut, 0).x);
+        color.rgb = texture(lut, color.rgb * ((size - 1.0) / size) + 0.5 / size).rgb;
     }
+    // ghost trails of the previous frames (the history has the size of this target)
+    if(ghosting > 0.0)
+        color.rgb = mix(color.rgb, texelFetch(history, ivec2(gl_FragCoord.xy), 0).rgb, ghosting);
 }
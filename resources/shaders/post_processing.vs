@@ -15,14 +15,13 @@
 // limitations under the License.
 
 #version 330 core
-layout (location = 0) in vec4 vertex; // <vec2 position, vec2 texCoords>
+#include "common.glsl"
+#include "effects.glsl"
 
 out vec2 TexCoords;
 
-uniform bool chaos;
-uniform bool confuse;
-uniform bool shake;
 uniform float time;
+uniform float shake_strength; // 0.0 disables the shake, 1.0 is full strength
 
 void main()
 {
@@ -30,7 +29,7 @@ void main()
     vec2 texture = vertex.zw;
     if(chaos)
     {
-        float strength = 0.3;
+        float strength = reduce_flashing ? 0.05 : 0.3;
         vec2 pos = vec2(texture.x + sin(time) * strength, texture.y + cos(time) * strength);        
         TexCoords = pos;
     }
@@ -44,7 +43,7 @@ void main()
     }
     if (shake)
     {
-        float strength = 0.01;
+        float strength = 0.01 * shake_strength;
         gl_Position.x += cos(time * 10) * strength;        
         gl_Position.y += cos(time * 15) * strength;        
     }
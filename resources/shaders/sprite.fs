@@ -16,13 +16,18 @@
 
 #version 330 core
 in vec2 TexCoords;
+in vec3 Tint;
 out vec4 color;
 
 uniform sampler2D sprite;
+#ifdef INSTANCED
+// instances only have the colors of their corners
+#define spriteColor vec3(1.0)
+#else
 uniform vec3 spriteColor;
+#endif
 
 void main()
 {
-    
-    color = vec4(spriteColor, 1.0) * texture(sprite, TexCoords);
+    color = vec4(spriteColor * Tint, 1.0) * texture(sprite, TexCoords);
 }
\ No newline at end of file
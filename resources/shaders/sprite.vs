@@ -15,16 +15,38 @@
 // limitations under the License.
 
 #version 330 core
-layout (location = 0) in vec4 vertex; // <vec2 position, vec2 texCoords>
+#include "common.glsl"
 
 out vec2 TexCoords;
+// the colors of the corners, blended over the quad
+out vec3 Tint;
 
+#ifdef INSTANCED
+// per-instance attributes of the sprite batch
+// the model transform as the two rows of a 2D affine matrix
+layout (location = 1) in vec3 modelRows[2];
+layout (location = 3) in vec3 cornerColors[4];
+layout (location = 7) in vec4 texRect;
+#else
 uniform mat4 model;
+// top left, top right, bottom left, bottom right
+uniform vec3 cornerColors[4];
+#endif
 // note that we're omitting the view matrix; the view never changes so we basically have an identity view matrix and can therefore omit it.
 uniform mat4 projection;
+#ifndef INSTANCED
+// region of the texture shown on the quad: <vec2 offset, vec2 size>
+uniform vec4 texRect;
+#endif
 
 void main()
 {
-    TexCoords = vertex.zw;
+    TexCoords = texRect.xy + vertex.zw * texRect.zw;
+    Tint = mix(mix(cornerColors[0], cornerColors[1], vertex.z), mix(cornerColors[2], cornerColors[3], vertex.z), vertex.w);
+#ifdef INSTANCED
+    vec2 position = vec2(dot(modelRows[0], vec3(vertex.xy, 1.0)), dot(modelRows[1], vec3(vertex.xy, 1.0)));
+    gl_Position = projection * vec4(position, 0.0, 1.0);
+#else
     gl_Position = projection * model * vec4(vertex.xy, 0.0, 1.0);
+#endif
 }
\ No newline at end of file
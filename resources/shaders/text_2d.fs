@@ -20,9 +20,18 @@ out vec4 color;
 
 uniform sampler2D text;
 uniform vec3 textColor;
+// color glyphs (emoji) are RGBA and drawn as they are, not tinted with the text color
+uniform bool colorGlyphs;
+// fraction the text is faded out by (0 draws it opaque)
+uniform float fade;
 
 void main()
 {    
+    if (colorGlyphs) {
+        color = texture(text, TexCoords);
+        color.a *= 1.0 - fade;
+        return;
+    }
     vec4 sampled = vec4(1.0, 1.0, 1.0, texture(text, TexCoords).r);
-    color = vec4(textColor, 1.0) * sampled;
+    color = vec4(textColor, 1.0 - fade) * sampled;
 }  
\ No newline at end of file